@@ -1 +1,152 @@
-pub fn hello() {}
\ No newline at end of file
+/// 附件文本提取
+///
+/// 提取结果写入 `parsed/{attachment_id}.txt`（相对应用数据目录），路径保存在
+/// `attachments.parsed_content_path`；`index_status` / `index_reason` 记录提取结果。
+use crate::artifacts::parser::{extract_office_text, OfficeFormat};
+use crate::error::AppError;
+use crate::storage::file_manager;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+
+/// 单次批量提取的默认数量
+pub const DEFAULT_EXTRACT_BATCH: i64 = 50;
+
+/// 提取结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionResult {
+    pub attachment_id: i64,
+    /// `indexed` / `failed` / `skipped`
+    pub status: String,
+    pub reason: Option<String>,
+    pub characters: usize,
+}
+
+/// 附件文本提取器
+pub struct AttachmentExtractor {
+    pool: SqlitePool,
+}
+
+impl AttachmentExtractor {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 提取单个附件的文本
+    pub async fn extract(&self, attachment_id: i64) -> Result<ExtractionResult, AppError> {
+        let row: Option<(String, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT filename, file_path, detected_mime FROM attachments WHERE id = ?"
+        )
+        .bind(attachment_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let (filename, file_path, detected_mime) = row.ok_or(AppError::AttachmentNotFound { id: attachment_id })?;
+
+        // 按文件头识别出的类型优先于扩展名（扩展名可能是错的）
+        let extension = std::path::Path::new(&filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        let format = detected_mime
+            .as_deref()
+            .and_then(OfficeFormat::from_mime)
+            .or_else(|| OfficeFormat::from_extension(extension));
+        let Some(format) = format else {
+            return Ok(ExtractionResult {
+                attachment_id,
+                status: "skipped".to_string(),
+                reason: None,
+                characters: 0,
+            });
+        };
+
+        let file_path = file_path.ok_or_else(|| AppError::FileSystem(format!("Attachment {} has no file", attachment_id)))?;
+        let source = file_manager::resolve_attachment_path(&file_path)?;
+
+        let extracted = tokio::task::spawn_blocking(move || extract_office_text(&source, format)).await?;
+
+        match extracted {
+            Ok(text) => {
+                let relative = format!("{}.txt", attachment_id);
+                let target = parsed_root()?.join(&relative);
+                if let Some(parent) = target.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                tokio::fs::write(&target, text.as_bytes()).await?;
+
+                sqlx::query(
+                    r#"
+                    UPDATE attachments
+                    SET parsed_content_path = ?, index_status = 'indexed', index_reason = NULL,
+                        indexed_at = CURRENT_TIMESTAMP
+                    WHERE id = ?
+                    "#
+                )
+                .bind(&relative)
+                .bind(attachment_id)
+                .execute(&self.pool)
+                .await?;
+
+                log::info!("Extracted {} characters from attachment {} ({})", text.len(), attachment_id, filename);
+                Ok(ExtractionResult {
+                    attachment_id,
+                    status: "indexed".to_string(),
+                    reason: None,
+                    characters: text.chars().count(),
+                })
+            }
+            Err(failure) => {
+                sqlx::query(
+                    "UPDATE attachments SET index_status = 'failed', index_reason = ? WHERE id = ?"
+                )
+                .bind(failure.reason())
+                .bind(attachment_id)
+                .execute(&self.pool)
+                .await?;
+
+                log::warn!("Failed to extract text from attachment {} ({}): {}", attachment_id, filename, failure);
+                Ok(ExtractionResult {
+                    attachment_id,
+                    status: "failed".to_string(),
+                    reason: Some(failure.reason().to_string()),
+                    characters: 0,
+                })
+            }
+        }
+    }
+
+    /// 提取待处理的 Office 附件
+    pub async fn extract_pending(&self, limit: i64) -> Result<Vec<ExtractionResult>, AppError> {
+        let ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM attachments
+            WHERE COALESCE(index_status, 'pending') = 'pending'
+              AND LOWER(file_type) IN ('docx', 'docm', 'xlsx', 'xlsm', 'pptx', 'pptm', 'doc', 'xls', 'ppt')
+            ORDER BY id DESC
+            LIMIT ?
+            "#
+        )
+        .bind(limit.max(1))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut results = Vec::with_capacity(ids.len());
+        for id in ids {
+            match self.extract(id).await {
+                Ok(result) => results.push(result),
+                Err(e) => log::warn!("Failed to extract attachment {}: {}", id, e),
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// 提取文本的存储目录
+pub fn parsed_root() -> Result<PathBuf, AppError> {
+    Ok(file_manager::app_data_dir()?.join("parsed"))
+}
+
+/// 读取已提取的文本
+pub async fn read_parsed_content(relative: &str) -> Result<String, AppError> {
+    Ok(tokio::fs::read_to_string(parsed_root()?.join(relative)).await?)
+}
@@ -0,0 +1,703 @@
+/// 附件文件完整性检查与修复
+///
+/// 检查附件文件是否存在、哈希是否一致；损坏的附件通过 IMAP 重新下载后重写文件。同步时记录了
+/// MIME 部分路径的附件先只下载这个部分，内容哈希与原来一致才使用；否则下载整封原始邮件，
+/// 按文件名和大小找到对应的 MIME 部分。服务器上已不存在的邮件标记为永久缺失。
+///
+/// 崩溃后可能留下指向空文件的附件记录。启动时对最近创建的附件做快速检查（只看文件是否存在、
+/// 是否为空，不计算哈希），有时间预算，超出预算的部分交给后台任务；有问题的标记为 `corrupt` 并排队修复。
+use crate::artifacts::sniff;
+use crate::commands::sync::resolve_account_auth;
+use crate::error::AppError;
+use crate::events::notifications::SOURCE_ATTACHMENT_REPAIR;
+use crate::events::{EventEmitter, IndexProgressEvent, IndexStatus, NotificationLevel};
+use crate::mail::connection_pool::ImapConnectionPool;
+use crate::mail::imap_client::ImapConnection;
+use crate::mail::parser::{parse_email, ParsedAttachment};
+use crate::mail::sync::{calculate_sha256, extract_file_extension, sanitize_filename};
+use crate::storage::file_manager;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// 附件完整性状态
+pub const STATUS_OK: &str = "ok";
+pub const STATUS_BROKEN: &str = "broken";
+pub const STATUS_MISSING_REMOTE: &str = "missing_remote";
+/// 快速检查发现文件缺失或为空
+pub const STATUS_CORRUPT: &str = "corrupt";
+
+/// 启动检查覆盖最近多少天创建的附件
+pub const STARTUP_CHECK_DAYS: i64 = 7;
+
+/// 启动检查的时间预算
+pub const STARTUP_CHECK_BUDGET: Duration = Duration::from_secs(2);
+
+/// 损坏的附件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokenAttachment {
+    pub id: i64,
+    pub filename: String,
+    pub reason: String,
+}
+
+/// 检查结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerifySummary {
+    pub checked: usize,
+    pub ok: usize,
+    pub broken: Vec<BrokenAttachment>,
+}
+
+/// 快速检查结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuickCheckSummary {
+    pub checked: usize,
+    pub corrupt: Vec<BrokenAttachment>,
+    /// 可以从邮件重新下载的损坏附件（手动添加的文件无法修复）
+    pub repairable: Vec<i64>,
+    /// 超出时间预算、尚未检查的附件
+    pub deferred: Vec<i64>,
+}
+
+/// 修复结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepairSummary {
+    pub repaired: Vec<i64>,
+    /// 服务器上已不存在，标记为永久缺失
+    pub missing_remote: Vec<BrokenAttachment>,
+    pub failed: Vec<BrokenAttachment>,
+}
+
+/// 可能被覆盖的邮件上的附件（供人工核对）
+///
+/// 早期版本用 `INSERT OR REPLACE` 保存邮件，Message-ID 冲突（例如同一秒内生成的 ID）时旧邮件行被删除后重建，
+/// 附件仍指向原来的邮件 ID：邮件行不存在，或者 ID 被新邮件复用而内容对不上。
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ClobberedAttachment {
+    pub id: i64,
+    pub email_id: i64,
+    pub filename: String,
+    /// 附件现在指向的邮件主题（邮件行不存在时为空）
+    pub email_subject: Option<String>,
+    pub email_message_id: Option<String>,
+    /// email_missing / email_has_no_attachments / email_newer_than_attachment
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct AttachmentRow {
+    id: i64,
+    email_id: Option<i64>,
+    filename: String,
+    file_size: Option<i64>,
+    file_path: Option<String>,
+    content_hash: Option<String>,
+    /// 只在修复时读取
+    #[sqlx(default)]
+    part_path: Option<String>,
+    #[sqlx(default)]
+    transfer_encoding: Option<String>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct SourceEmail {
+    account_id: i64,
+    message_id: String,
+    raw_path: Option<String>,
+}
+
+/// 附件完整性检查器
+pub struct AttachmentIntegrity {
+    pool: SqlitePool,
+    event_emitter: Option<EventEmitter>,
+    /// 修复时下载原始邮件使用的连接池（未设置时每次修复单独连接）
+    connections: ImapConnectionPool,
+}
+
+impl AttachmentIntegrity {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            event_emitter: None,
+            connections: ImapConnectionPool::default(),
+        }
+    }
+
+    pub fn with_event_emitter(pool: SqlitePool, emitter: EventEmitter) -> Self {
+        Self {
+            pool,
+            event_emitter: Some(emitter),
+            connections: ImapConnectionPool::default(),
+        }
+    }
+
+    /// 交互修复使用共享的连接池（复用已认证的会话）
+    pub fn with_connection_pool(mut self, connections: ImapConnectionPool) -> Self {
+        self.connections = connections;
+        self
+    }
+
+    fn emit_progress(&self, index_type: &str, current: usize, total: usize, status: IndexStatus) {
+        if let Some(emitter) = &self.event_emitter {
+            emitter.emit_index_progress(IndexProgressEvent {
+                current,
+                total,
+                status,
+                index_type: index_type.to_string(),
+            });
+        }
+    }
+
+    fn notify(&self, title: &str, message: &str, level: NotificationLevel) {
+        if let Some(emitter) = &self.event_emitter {
+            emitter.emit_notification_from(title, message, level, Some(SOURCE_ATTACHMENT_REPAIR), None);
+        }
+    }
+
+    /// 检查附件文件（可限定项目），损坏的附件标记为 `broken`
+    pub async fn verify(&self, project_id: Option<i64>) -> Result<VerifySummary, AppError> {
+        let rows = sqlx::query_as::<_, AttachmentRow>(
+            r#"
+            SELECT a.id, a.email_id, a.filename, a.file_size, a.file_path, a.content_hash
+            FROM attachments a
+            LEFT JOIN emails e ON e.id = a.email_id
+            WHERE ? IS NULL OR COALESCE(a.project_id, e.project_id) = ?
+            ORDER BY a.id
+            "#
+        )
+        .bind(project_id)
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let total = rows.len();
+        let mut summary = VerifySummary::default();
+        self.emit_progress("attachment_verify", 0, total, IndexStatus::Starting);
+
+        let cold_mounted = file_manager::mounted_cold_storage_root().is_ok();
+        for row in rows {
+            // 外置磁盘未挂载时无法检查冷存储中的附件，保留原状态
+            if !cold_mounted && row.file_path.as_deref().is_some_and(file_manager::is_cold_path) {
+                continue;
+            }
+            summary.checked += 1;
+            match check_file(&row).await {
+                Ok(()) => {
+                    self.set_status(row.id, STATUS_OK, None).await?;
+                    summary.ok += 1;
+                }
+                Err(reason) => {
+                    self.set_status(row.id, STATUS_BROKEN, Some(&reason)).await?;
+                    summary.broken.push(BrokenAttachment {
+                        id: row.id,
+                        filename: row.filename,
+                        reason,
+                    });
+                }
+            }
+            self.emit_progress("attachment_verify", summary.checked, total, IndexStatus::Building);
+        }
+
+        self.emit_progress("attachment_verify", total, total, IndexStatus::Completed);
+        log::info!("Verified {} attachments: {} broken", summary.checked, summary.broken.len());
+        Ok(summary)
+    }
+
+    /// 检查所有附件（含哈希），完成后发送汇总通知
+    pub async fn verify_all(&self) -> Result<VerifySummary, AppError> {
+        let summary = self.verify(None).await?;
+        self.notify(
+            "Attachment check finished",
+            &format!("{} checked, {} broken", summary.checked, summary.broken.len()),
+            if summary.broken.is_empty() { NotificationLevel::Success } else { NotificationLevel::Warning },
+        );
+        Ok(summary)
+    }
+
+    /// 查找可能被覆盖的邮件上的附件，只报告不修改
+    ///
+    /// 邮件附件指向的邮件行不存在、邮件本身标记为没有附件，或者邮件行比附件晚创建（行被重建）时列出。
+    pub async fn find_clobbered(&self) -> Result<Vec<ClobberedAttachment>, AppError> {
+        let rows = sqlx::query_as::<_, ClobberedAttachment>(
+            r#"
+            SELECT * FROM (
+                SELECT a.id, a.email_id, a.filename,
+                       e.subject AS email_subject, e.message_id AS email_message_id,
+                       CASE
+                           WHEN e.id IS NULL THEN 'email_missing'
+                           WHEN COALESCE(e.has_attachments, 0) = 0 THEN 'email_has_no_attachments'
+                           WHEN datetime(e.created_at) > datetime(a.created_at) THEN 'email_newer_than_attachment'
+                       END AS reason
+                FROM attachments a
+                LEFT JOIN emails e ON e.id = a.email_id
+                WHERE a.email_id IS NOT NULL AND COALESCE(a.origin, 'email') = 'email'
+            )
+            WHERE reason IS NOT NULL
+            ORDER BY email_id, id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// 最近 `days` 天创建、尚未标记为损坏的附件
+    pub async fn recent_ids(&self, days: i64) -> Result<Vec<i64>, AppError> {
+        let ids = sqlx::query_scalar(
+            r#"
+            SELECT id FROM attachments
+            WHERE datetime(created_at) > datetime('now', '-' || ? || ' days')
+              AND COALESCE(integrity_status, '') NOT IN (?, ?, ?)
+            ORDER BY id DESC
+            "#
+        )
+        .bind(days)
+        .bind(STATUS_BROKEN)
+        .bind(STATUS_CORRUPT)
+        .bind(STATUS_MISSING_REMOTE)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// 快速检查：文件存在且非空（不计算哈希），有问题的标记为 `corrupt`
+    ///
+    /// 给定 `budget` 时超出预算即停止，剩余的附件放入 `deferred`。
+    pub async fn quick_check(&self, ids: &[i64], budget: Option<Duration>) -> Result<QuickCheckSummary, AppError> {
+        let started = Instant::now();
+        let cold_mounted = file_manager::mounted_cold_storage_root().is_ok();
+        let mut summary = QuickCheckSummary::default();
+
+        for (index, id) in ids.iter().enumerate() {
+            if budget.is_some_and(|budget| started.elapsed() >= budget) {
+                summary.deferred = ids[index..].to_vec();
+                break;
+            }
+
+            let row = sqlx::query_as::<_, AttachmentRow>(
+                r#"
+                SELECT id, email_id, filename, file_size, file_path, content_hash
+                FROM attachments WHERE id = ?
+                "#
+            )
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+            let Some(row) = row else {
+                continue;
+            };
+            if !cold_mounted && row.file_path.as_deref().is_some_and(file_manager::is_cold_path) {
+                continue;
+            }
+
+            summary.checked += 1;
+            if let Err(reason) = check_file_size(&row).await {
+                self.set_status(row.id, STATUS_CORRUPT, Some(&reason)).await?;
+                if row.email_id.is_some() {
+                    summary.repairable.push(row.id);
+                }
+                summary.corrupt.push(BrokenAttachment {
+                    id: row.id,
+                    filename: row.filename,
+                    reason,
+                });
+            }
+        }
+
+        log::info!(
+            "Quick attachment check: {} checked, {} corrupt, {} deferred ({} ms)",
+            summary.checked, summary.corrupt.len(), summary.deferred.len(), started.elapsed().as_millis()
+        );
+        Ok(summary)
+    }
+
+    /// 修复指定附件
+    pub async fn repair_many(&self, ids: &[i64]) -> Result<RepairSummary, AppError> {
+        self.repair_ids(ids).await
+    }
+
+    /// 修复单个附件
+    pub async fn repair(&self, id: i64) -> Result<RepairSummary, AppError> {
+        self.repair_ids(&[id]).await
+    }
+
+    /// 修复所有标记为 `broken` 或 `corrupt` 的附件
+    pub async fn repair_all_broken(&self) -> Result<RepairSummary, AppError> {
+        let ids: Vec<i64> = sqlx::query_scalar(
+            "SELECT id FROM attachments WHERE integrity_status IN (?, ?) ORDER BY email_id, id"
+        )
+        .bind(STATUS_BROKEN)
+        .bind(STATUS_CORRUPT)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.repair_ids(&ids).await
+    }
+
+    async fn repair_ids(&self, ids: &[i64]) -> Result<RepairSummary, AppError> {
+        let mut summary = RepairSummary::default();
+        let total = ids.len();
+        self.emit_progress("attachment_repair", 0, total, IndexStatus::Starting);
+
+        // 按账户分组，每个账户只建立一次连接
+        let mut by_account: HashMap<i64, Vec<(AttachmentRow, SourceEmail)>> = HashMap::new();
+        for id in ids {
+            let row = sqlx::query_as::<_, AttachmentRow>(
+                r#"
+                SELECT id, email_id, filename, file_size, file_path, content_hash, part_path, transfer_encoding
+                FROM attachments WHERE id = ?
+                "#
+            )
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(AppError::AttachmentNotFound { id: *id })?;
+
+            let source = match row.email_id {
+                Some(email_id) => sqlx::query_as::<_, SourceEmail>(
+                    "SELECT account_id, message_id, raw_path FROM emails WHERE id = ? AND account_id IS NOT NULL"
+                )
+                .bind(email_id)
+                .fetch_optional(&self.pool)
+                .await?,
+                None => None,
+            };
+
+            match source {
+                Some(source) => by_account.entry(source.account_id).or_default().push((row, source)),
+                None => {
+                    let reason = "Source email no longer exists locally".to_string();
+                    self.set_status(row.id, STATUS_MISSING_REMOTE, Some(&reason)).await?;
+                    summary.missing_remote.push(BrokenAttachment { id: row.id, filename: row.filename, reason });
+                }
+            }
+        }
+
+        let mut current = summary.missing_remote.len();
+        for (account_id, items) in by_account {
+            let count = items.len();
+            if let Err(e) = self.repair_account(account_id, items, &mut summary).await {
+                log::error!("Failed to repair attachments for account {}: {}", account_id, e);
+            }
+            current += count;
+            self.emit_progress("attachment_repair", current, total, IndexStatus::Building);
+        }
+
+        self.emit_progress("attachment_repair", total, total, IndexStatus::Completed);
+        self.notify(
+            "Attachment repair finished",
+            &format!(
+                "{} repaired, {} missing on server, {} failed",
+                summary.repaired.len(),
+                summary.missing_remote.len(),
+                summary.failed.len()
+            ),
+            if summary.failed.is_empty() { NotificationLevel::Success } else { NotificationLevel::Warning },
+        );
+        log::info!(
+            "Attachment repair: {} repaired, {} missing remote, {} failed",
+            summary.repaired.len(), summary.missing_remote.len(), summary.failed.len()
+        );
+        Ok(summary)
+    }
+
+    async fn repair_account(
+        &self,
+        account_id: i64,
+        items: Vec<(AttachmentRow, SourceEmail)>,
+        summary: &mut RepairSummary,
+    ) -> Result<(), AppError> {
+        let email: String = sqlx::query_scalar("SELECT email FROM accounts WHERE id = ?")
+            .bind(account_id)
+            .fetch_one(&self.pool)
+            .await?;
+        let (_, auth, provider) = resolve_account_auth(&self.pool, &email, None)
+            .await
+            .map_err(|e| AppError::Auth(e.message))?;
+
+        let connection = async {
+            let mut conn = self.connections.acquire(account_id, auth, &provider).await?;
+            conn.select_folder("INBOX").await?;
+            Ok::<_, AppError>(conn)
+        }
+        .await;
+
+        let mut conn = match connection {
+            Ok(conn) => conn,
+            Err(e) => {
+                for (row, _) in items {
+                    summary.failed.push(BrokenAttachment { id: row.id, filename: row.filename, reason: e.to_string() });
+                }
+                return Err(e);
+            }
+        };
+
+        // 同一封邮件的多个附件只下载一次
+        let mut messages: HashMap<String, Option<Vec<ParsedAttachment>>> = HashMap::new();
+        for (row, source) in items {
+            if let Some(data) = fetch_stored_part(&mut conn, &row, &source).await {
+                match self.write_repaired(&row, account_id, &data).await {
+                    Ok(()) => summary.repaired.push(row.id),
+                    Err(e) => {
+                        let reason = e.to_string();
+                        self.set_status(row.id, STATUS_BROKEN, Some(&reason)).await?;
+                        summary.failed.push(BrokenAttachment { id: row.id, filename: row.filename, reason });
+                    }
+                }
+                continue;
+            }
+
+            if !messages.contains_key(&source.message_id) {
+                let attachments = fetch_source_attachments(&mut conn, &source).await;
+                messages.insert(source.message_id.clone(), attachments);
+            }
+
+            let result = match messages.get(&source.message_id) {
+                Some(Some(attachments)) => self.rewrite(&row, account_id, attachments).await,
+                _ => {
+                    let reason = "Message no longer exists on server".to_string();
+                    self.set_status(row.id, STATUS_MISSING_REMOTE, Some(&reason)).await?;
+                    summary.missing_remote.push(BrokenAttachment { id: row.id, filename: row.filename, reason });
+                    continue;
+                }
+            };
+
+            match result {
+                Ok(()) => summary.repaired.push(row.id),
+                Err(e) => {
+                    let reason = e.to_string();
+                    self.set_status(row.id, STATUS_BROKEN, Some(&reason)).await?;
+                    summary.failed.push(BrokenAttachment { id: row.id, filename: row.filename, reason });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 在原始邮件中找到对应的附件并重写文件
+    async fn rewrite(&self, row: &AttachmentRow, account_id: i64, attachments: &[ParsedAttachment]) -> Result<(), AppError> {
+        let attachment = attachments
+            .iter()
+            .find(|a| a.filename == row.filename && row.file_size.map_or(true, |size| a.size as i64 == size))
+            .or_else(|| attachments.iter().find(|a| a.filename == row.filename))
+            .ok_or_else(|| AppError::Generic(format!("Attachment {} not found in source message", row.filename)))?;
+
+        self.write_repaired(row, account_id, &attachment.data).await
+    }
+
+    /// 用重新下载的内容重写附件文件并更新记录
+    async fn write_repaired(&self, row: &AttachmentRow, account_id: i64, data: &[u8]) -> Result<(), AppError> {
+        let relative = match &row.file_path {
+            Some(path) => path.clone(),
+            None => format!(
+                "{}/{}/{}/{}",
+                extract_file_extension(&row.filename),
+                account_id,
+                row.email_id.unwrap_or_default(),
+                sanitize_filename(&row.filename)
+            ),
+        };
+        let path = file_manager::resolve_attachment_path(&relative)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, data).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE attachments
+            SET file_path = ?, content_hash = ?, file_size = ?, detected_mime = ?,
+                integrity_status = ?, integrity_reason = NULL
+            WHERE id = ?
+            "#
+        )
+        .bind(&relative)
+        .bind(calculate_sha256(data))
+        .bind(data.len() as i64)
+        .bind(sniff::sniff(data).unwrap_or_default())
+        .bind(STATUS_OK)
+        .bind(row.id)
+        .execute(&self.pool)
+        .await?;
+
+        log::info!("Repaired attachment {} ({})", row.id, row.filename);
+        Ok(())
+    }
+
+    async fn set_status(&self, id: i64, status: &str, reason: Option<&str>) -> Result<(), AppError> {
+        sqlx::query("UPDATE attachments SET integrity_status = ?, integrity_reason = ? WHERE id = ?")
+            .bind(status)
+            .bind(reason)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// 检查文件是否存在且哈希一致
+async fn check_file(row: &AttachmentRow) -> Result<(), String> {
+    let relative = row.file_path.as_deref().ok_or("No stored file path")?;
+    let path = file_manager::resolve_attachment_path(relative).map_err(|e| e.to_string())?;
+    let data = match tokio::fs::read(&path).await {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err("File missing".to_string()),
+        Err(e) => return Err(format!("Failed to read file: {}", e)),
+    };
+
+    if data.is_empty() && row.file_size.unwrap_or(0) > 0 {
+        return Err("File is empty".to_string());
+    }
+    match &row.content_hash {
+        Some(hash) if *hash != calculate_sha256(&data) => Err("Content hash mismatch".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// 检查文件是否存在且非空（不读取内容）
+async fn check_file_size(row: &AttachmentRow) -> Result<(), String> {
+    let relative = row.file_path.as_deref().ok_or("No stored file path")?;
+    let path = file_manager::resolve_attachment_path(relative).map_err(|e| e.to_string())?;
+    let metadata = match tokio::fs::metadata(&path).await {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err("File missing".to_string()),
+        Err(e) => return Err(format!("Failed to read file: {}", e)),
+    };
+
+    if metadata.len() == 0 && row.file_size.unwrap_or(0) > 0 {
+        return Err("File is empty".to_string());
+    }
+    Ok(())
+}
+
+/// 按同步时记录的 MIME 部分只下载这个附件，内容哈希与记录一致时返回
+///
+/// UID 已经变化（指向另一封邮件）或保存的是按字符集转换过的文本附件时哈希不一致，由调用方改为下载整封邮件。
+async fn fetch_stored_part(conn: &mut ImapConnection, row: &AttachmentRow, source: &SourceEmail) -> Option<Vec<u8>> {
+    let uid = source.raw_path.as_deref()?.parse::<u32>().ok()?;
+    let part = row.part_path.as_deref()?;
+    let expected = row.content_hash.as_deref()?;
+    let encoding = row.transfer_encoding.as_deref().unwrap_or("7bit");
+
+    match conn.fetch_body_part(uid, part, encoding).await {
+        Ok(data) if calculate_sha256(&data) == expected => Some(data),
+        Ok(_) => {
+            log::info!(
+                "Part {} of UID {} does not match attachment {}, downloading the whole message",
+                part, uid, row.id
+            );
+            None
+        }
+        Err(e) => {
+            log::warn!("Failed to fetch part {} of UID {} for attachment {}: {}", part, uid, row.id, e);
+            None
+        }
+    }
+}
+
+/// 下载原始邮件并解析附件：先按 UID，UID 不匹配时按 Message-ID 搜索
+async fn fetch_source_attachments(conn: &mut ImapConnection, source: &SourceEmail) -> Option<Vec<ParsedAttachment>> {
+    if let Some(uid) = source.raw_path.as_deref().and_then(|p| p.parse::<u32>().ok()) {
+        if let Ok(raw) = conn.fetch_email(uid).await {
+            if let Ok(parsed) = parse_email(&raw) {
+                if parsed.message_id == source.message_id {
+                    return Some(parsed.attachments);
+                }
+            }
+        }
+    }
+
+    let criteria = format!("HEADER Message-ID \"{}\"", source.message_id.replace('"', ""));
+    let uids = conn.uid_search(&criteria, None).await.ok()?;
+    for uid in uids {
+        let Ok(raw) = conn.fetch_email(uid).await else { continue };
+        if let Ok(parsed) = parse_email(&raw) {
+            if parsed.message_id == source.message_id {
+                return Some(parsed.attachments);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::fixtures::{insert_account, insert_email, NewEmail};
+    use crate::storage::database::test_pool;
+
+    async fn email_saved_at(
+        pool: &SqlitePool,
+        account_id: i64,
+        message_id: &str,
+        has_attachments: bool,
+        created_at: &str,
+    ) -> i64 {
+        let email = NewEmail {
+            account_id: Some(account_id),
+            message_id,
+            has_attachments: Some(has_attachments),
+            created_at: Some(created_at),
+            ..Default::default()
+        };
+        insert_email(pool, email).await
+    }
+
+    async fn insert_attachment(pool: &SqlitePool, email_id: Option<i64>, filename: &str, origin: &str) -> i64 {
+        sqlx::query(
+            "INSERT INTO attachments (email_id, filename, origin, created_at) VALUES (?, ?, ?, '2026-10-01 12:00:00')"
+        )
+        .bind(email_id)
+        .bind(filename)
+        .bind(origin)
+        .execute(pool)
+        .await
+        .unwrap()
+        .last_insert_rowid()
+    }
+
+    #[tokio::test]
+    async fn find_clobbered_reports_attachments_that_lost_their_email() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+
+        let saved_at = "2026-10-01 11:00:00";
+        let intact = email_saved_at(&pool, account_id, "intact", true, saved_at).await;
+        let no_attachments = email_saved_at(&pool, account_id, "no-attachments", false, saved_at).await;
+        let rebuilt = email_saved_at(&pool, account_id, "rebuilt", true, "2026-10-02 08:00:00").await;
+        insert_attachment(&pool, Some(intact), "ok.pdf", "email").await;
+        insert_attachment(&pool, None, "manual.pdf", "manual").await;
+        // 早期版本重建邮件行时留下的孤立附件（当时未启用外键约束）
+        let deleted = email_saved_at(&pool, account_id, "deleted", true, saved_at).await;
+        let orphan = insert_attachment(&pool, Some(deleted), "orphan.pdf", "email").await;
+        let mut conn = pool.acquire().await.unwrap();
+        for statement in ["PRAGMA foreign_keys = OFF", "DELETE FROM emails WHERE message_id = 'deleted'", "PRAGMA foreign_keys = ON"] {
+            sqlx::query(statement).execute(&mut *conn).await.unwrap();
+        }
+        drop(conn);
+        let mismatched = insert_attachment(&pool, Some(no_attachments), "mismatch.pdf", "email").await;
+        let older = insert_attachment(&pool, Some(rebuilt), "older.pdf", "email").await;
+
+        let found: Vec<(i64, String, Option<String>)> = AttachmentIntegrity::new(pool.clone())
+            .find_clobbered()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| (row.id, row.reason, row.email_message_id))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (mismatched, "email_has_no_attachments".to_string(), Some("no-attachments".to_string())),
+                (older, "email_newer_than_attachment".to_string(), Some("rebuilt".to_string())),
+                (orphan, "email_missing".to_string(), None),
+            ]
+        );
+    }
+}
@@ -4,14 +4,43 @@ pub mod extractor;
 pub mod parser;
 pub mod ocr;
 pub mod archive;
+pub mod safety;
+pub mod sniff;
+pub mod integrity;
+pub mod upload;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Artifact {
     pub id: i64,
     pub filename: String,
     pub file_type: String, // e.g., 'pdf', 'docx'
     pub file_size: i64,
     pub mime_type: Option<String>,
+    /// 按文件头识别的类型（与声明类型不同时界面按它显示图标）
+    #[serde(default)]
+    pub detected_mime: Option<String>,
     pub source_email_id: Option<i64>, 
     pub created_at: String,
+    /// 是否星标（星标附件排在前面，且不会被清理任务删除）
+    #[serde(default)]
+    pub is_starred: bool,
+    /// 危险等级：safe / suspicious / dangerous
+    #[serde(default)]
+    pub danger_level: Option<String>,
+    /// 所属项目
+    #[serde(default)]
+    pub project_id: Option<i64>,
+    #[serde(default)]
+    pub project_name: Option<String>,
+    /// 来源邮件上下文
+    #[serde(default)]
+    pub email_subject: Option<String>,
+    #[serde(default)]
+    pub email_date: Option<String>,
+    /// 来源：email（邮件附件）/ manual（手动添加）
+    #[serde(default)]
+    pub origin: String,
+    /// 手动添加时的备注
+    #[serde(default)]
+    pub note: Option<String>,
 }
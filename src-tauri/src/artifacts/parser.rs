@@ -1 +1,461 @@
-pub fn hello() {}
+/// Office OpenXML 文本提取（docx / xlsx / pptx）
+///
+/// 解压容器后用 quick-xml 流式读取 XML，不构建 DOM，
+/// 几十万个单元格的表格也只占用与输出文本相当的内存；输出超过上限时截断。
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// 提取文本的最大字节数
+pub const MAX_EXTRACTED_BYTES: usize = 8 * 1024 * 1024;
+
+/// OLE2 复合文档文件头（旧版 Office 格式，以及加密后的 OpenXML 文件）
+const OLE2_MAGIC: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+/// Office 文档类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfficeFormat {
+    Docx,
+    Xlsx,
+    Pptx,
+    /// 旧版二进制格式（doc / xls / ppt）
+    Legacy,
+}
+
+impl OfficeFormat {
+    /// 根据扩展名判断，不是 Office 文档时返回 None
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "docx" | "docm" => Some(OfficeFormat::Docx),
+            "xlsx" | "xlsm" => Some(OfficeFormat::Xlsx),
+            "pptx" | "pptm" => Some(OfficeFormat::Pptx),
+            "doc" | "xls" | "ppt" => Some(OfficeFormat::Legacy),
+            _ => None,
+        }
+    }
+
+    /// 根据按文件头识别的 MIME 类型判断，不是 Office 文档时返回 None
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        match mime {
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => Some(OfficeFormat::Docx),
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => Some(OfficeFormat::Xlsx),
+            "application/vnd.openxmlformats-officedocument.presentationml.presentation" => Some(OfficeFormat::Pptx),
+            "application/msword" | "application/vnd.ms-excel" | "application/vnd.ms-powerpoint" => {
+                Some(OfficeFormat::Legacy)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 提取失败原因（写入 `attachments.index_reason`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractFailure {
+    /// 旧版二进制格式，不支持
+    UnsupportedFormat,
+    /// 文档有密码保护
+    PasswordProtected,
+    /// 文件损坏或不是有效的 Office 文档
+    Corrupt(String),
+}
+
+impl ExtractFailure {
+    pub fn reason(&self) -> &str {
+        match self {
+            ExtractFailure::UnsupportedFormat => "unsupported_format",
+            ExtractFailure::PasswordProtected => "password_protected",
+            ExtractFailure::Corrupt(_) => "corrupt",
+        }
+    }
+}
+
+impl std::fmt::Display for ExtractFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractFailure::UnsupportedFormat => write!(f, "Legacy Office format is not supported"),
+            ExtractFailure::PasswordProtected => write!(f, "Document is password protected"),
+            ExtractFailure::Corrupt(msg) => write!(f, "Invalid Office document: {}", msg),
+        }
+    }
+}
+
+impl From<ZipError> for ExtractFailure {
+    fn from(err: ZipError) -> Self {
+        match err {
+            ZipError::UnsupportedArchive(msg) if msg.contains("Password") => ExtractFailure::PasswordProtected,
+            other => ExtractFailure::Corrupt(other.to_string()),
+        }
+    }
+}
+
+impl From<quick_xml::Error> for ExtractFailure {
+    fn from(err: quick_xml::Error) -> Self {
+        ExtractFailure::Corrupt(err.to_string())
+    }
+}
+
+impl From<std::io::Error> for ExtractFailure {
+    fn from(err: std::io::Error) -> Self {
+        ExtractFailure::Corrupt(err.to_string())
+    }
+}
+
+/// 提取 Office 文档文本（同步执行，调用方应放在阻塞线程中）
+pub fn extract_office_text(path: &Path, format: OfficeFormat) -> Result<String, ExtractFailure> {
+    let mut file = File::open(path)?;
+    let mut magic = [0u8; 8];
+    let is_ole2 = file.read_exact(&mut magic).is_ok() && magic == OLE2_MAGIC;
+
+    if format == OfficeFormat::Legacy {
+        return Err(ExtractFailure::UnsupportedFormat);
+    }
+    // 加密的 OpenXML 文档被包装在 OLE2 容器中
+    if is_ole2 {
+        return Err(ExtractFailure::PasswordProtected);
+    }
+
+    file.rewind()?;
+    let mut archive = ZipArchive::new(BufReader::new(file))?;
+    let mut out = TextSink::new(MAX_EXTRACTED_BYTES);
+
+    match format {
+        OfficeFormat::Docx => extract_docx(&mut archive, &mut out)?,
+        OfficeFormat::Xlsx => extract_xlsx(&mut archive, &mut out)?,
+        OfficeFormat::Pptx => extract_pptx(&mut archive, &mut out)?,
+        OfficeFormat::Legacy => unreachable!(),
+    }
+
+    Ok(normalize_whitespace(&out.finish()))
+}
+
+/// 带上限的文本输出
+struct TextSink {
+    text: String,
+    limit: usize,
+}
+
+impl TextSink {
+    fn new(limit: usize) -> Self {
+        Self {
+            text: String::new(),
+            limit,
+        }
+    }
+
+    fn full(&self) -> bool {
+        self.text.len() >= self.limit
+    }
+
+    fn push(&mut self, s: &str) {
+        if !self.full() {
+            self.text.push_str(s);
+        }
+    }
+
+    fn finish(mut self) -> String {
+        if self.text.len() > self.limit {
+            let mut end = self.limit;
+            while !self.text.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.text.truncate(end);
+        }
+        self.text
+    }
+}
+
+type Archive = ZipArchive<BufReader<File>>;
+
+fn xml_reader<R: Read>(reader: R) -> Reader<BufReader<R>> {
+    let mut reader = Reader::from_reader(BufReader::new(reader));
+    reader.trim_text(false);
+    reader
+}
+
+/// docx：`w:t` 为文本，`w:p` 结束换行，`w:tab` / `w:br` 为制表符和换行
+fn extract_docx(archive: &mut Archive, out: &mut TextSink) -> Result<(), ExtractFailure> {
+    let entry = archive.by_name("word/document.xml")?;
+    let mut reader = xml_reader(entry);
+    let mut buf = Vec::new();
+    let mut in_text = false;
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) if e.local_name().as_ref() == b"t" => in_text = true,
+            Event::End(e) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => out.push("\n"),
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"tab" => out.push("\t"),
+                b"br" | b"cr" => out.push("\n"),
+                _ => {}
+            },
+            Event::Text(e) if in_text => out.push(&e.unescape()?),
+            Event::Eof => break,
+            _ => {}
+        }
+        if out.full() {
+            break;
+        }
+        buf.clear();
+    }
+
+    Ok(())
+}
+
+/// xlsx：按工作簿顺序输出各工作表，每行单元格用制表符分隔
+fn extract_xlsx(archive: &mut Archive, out: &mut TextSink) -> Result<(), ExtractFailure> {
+    let shared_strings = read_shared_strings(archive, MAX_EXTRACTED_BYTES)?;
+    let sheets = read_sheet_list(archive)?;
+
+    for (name, part) in sheets {
+        if out.full() {
+            break;
+        }
+        let entry = match archive.by_name(&part) {
+            Ok(entry) => entry,
+            Err(ZipError::FileNotFound) => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        out.push(&format!("## {}\n", name));
+        let mut reader = xml_reader(entry);
+        let mut buf = Vec::new();
+        let mut cell_type: Option<String> = None;
+        let mut in_value = false;
+        let mut first_in_row = true;
+
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) => match e.local_name().as_ref() {
+                    b"row" => first_in_row = true,
+                    b"c" => {
+                        cell_type = e
+                            .try_get_attribute("t")
+                            .ok()
+                            .flatten()
+                            .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()));
+                    }
+                    // `v` 为值，内联字符串在 `is/t` 中
+                    b"v" | b"t" => in_value = true,
+                    _ => {}
+                },
+                Event::End(e) => match e.local_name().as_ref() {
+                    b"row" => out.push("\n"),
+                    b"v" | b"t" => in_value = false,
+                    _ => {}
+                },
+                Event::Text(e) if in_value => {
+                    let raw = e.unescape()?;
+                    let value = match cell_type.as_deref() {
+                        Some("s") => raw
+                            .trim()
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|i| shared_strings.get(i))
+                            .map(String::as_str)
+                            .unwrap_or_default(),
+                        _ => raw.as_ref(),
+                    };
+                    if !value.is_empty() {
+                        if !first_in_row {
+                            out.push("\t");
+                        }
+                        out.push(value);
+                        first_in_row = false;
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            if out.full() {
+                break;
+            }
+            buf.clear();
+        }
+        out.push("\n");
+    }
+
+    Ok(())
+}
+
+/// 读取共享字符串表（总大小超过上限后的字符串按空处理）
+fn read_shared_strings(archive: &mut Archive, limit: usize) -> Result<Vec<String>, ExtractFailure> {
+    let entry = match archive.by_name("xl/sharedStrings.xml") {
+        Ok(entry) => entry,
+        Err(ZipError::FileNotFound) => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut reader = xml_reader(entry);
+    let mut buf = Vec::new();
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    let mut in_text = false;
+    let mut total = 0;
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"si" => current.clear(),
+                b"t" => in_text = true,
+                _ => {}
+            },
+            Event::End(e) => match e.local_name().as_ref() {
+                b"si" => {
+                    total += current.len();
+                    strings.push(if total <= limit { current.clone() } else { String::new() });
+                }
+                b"t" => in_text = false,
+                _ => {}
+            },
+            Event::Text(e) if in_text => current.push_str(&e.unescape()?),
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(strings)
+}
+
+/// 读取工作表名称及其 XML 路径（按工作簿中的顺序）
+fn read_sheet_list(archive: &mut Archive) -> Result<Vec<(String, String)>, ExtractFailure> {
+    // r:id -> 目标路径
+    let mut targets = HashMap::new();
+    if let Ok(entry) = archive.by_name("xl/_rels/workbook.xml.rels") {
+        let mut reader = xml_reader(entry);
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                    let id = attribute(&e, "Id");
+                    let target = attribute(&e, "Target");
+                    if let (Some(id), Some(target)) = (id, target) {
+                        let target = target.trim_start_matches('/');
+                        let path = if target.starts_with("xl/") {
+                            target.to_string()
+                        } else {
+                            format!("xl/{}", target)
+                        };
+                        targets.insert(id, path);
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+    }
+
+    let entry = archive.by_name("xl/workbook.xml")?;
+    let mut reader = xml_reader(entry);
+    let mut buf = Vec::new();
+    let mut sheets = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"sheet" => {
+                let name = attribute(&e, "name").unwrap_or_default();
+                let part = attribute(&e, "r:id")
+                    .and_then(|id| targets.get(&id).cloned())
+                    .unwrap_or_else(|| format!("xl/worksheets/sheet{}.xml", sheets.len() + 1));
+                sheets.push((name, part));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(sheets)
+}
+
+/// pptx：按编号顺序输出各幻灯片，`a:t` 为文本，`a:p` 结束换行
+fn extract_pptx(archive: &mut Archive, out: &mut TextSink) -> Result<(), ExtractFailure> {
+    let mut slides: Vec<(u32, String)> = archive
+        .file_names()
+        .filter_map(|name| {
+            let number = name
+                .strip_prefix("ppt/slides/slide")?
+                .strip_suffix(".xml")?
+                .parse::<u32>()
+                .ok()?;
+            Some((number, name.to_string()))
+        })
+        .collect();
+    slides.sort();
+
+    for (number, name) in slides {
+        if out.full() {
+            break;
+        }
+        out.push(&format!("## Slide {}\n", number));
+
+        let entry = archive.by_name(&name)?;
+        let mut reader = xml_reader(entry);
+        let mut buf = Vec::new();
+        let mut in_text = false;
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) if e.local_name().as_ref() == b"t" => in_text = true,
+                Event::End(e) => match e.local_name().as_ref() {
+                    b"t" => in_text = false,
+                    b"p" => out.push("\n"),
+                    _ => {}
+                },
+                Event::Text(e) if in_text => out.push(&e.unescape()?),
+                Event::Eof => break,
+                _ => {}
+            }
+            if out.full() {
+                break;
+            }
+            buf.clear();
+        }
+        out.push("\n");
+    }
+
+    Ok(())
+}
+
+fn attribute(e: &quick_xml::events::BytesStart, name: &str) -> Option<String> {
+    e.try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+/// 合并行内连续空白，去除行首尾空白和多余空行
+fn normalize_whitespace(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut blank_lines = 0;
+
+    for line in text.lines() {
+        let line = line
+            .split(|c: char| c.is_whitespace() && c != '\t')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let line = line.trim_matches(|c: char| c.is_whitespace());
+
+        if line.is_empty() {
+            blank_lines += 1;
+            if blank_lines > 1 || result.is_empty() {
+                continue;
+            }
+        } else {
+            blank_lines = 0;
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+
+    result.trim_end().to_string()
+}
@@ -0,0 +1,203 @@
+/// 危险附件检测
+///
+/// 根据扩展名和 MIME 类型判断附件的危险等级。危险附件在打开前需要用户确认。
+/// 内置黑名单之外，用户可以在同步设置中追加扩展名和 MIME 类型（逗号分隔）。
+/// 按文件头识别出的类型与声明不符的附件至少为可疑。
+use crate::artifacts::sniff;
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 可执行文件和脚本
+const DANGEROUS_EXTENSIONS: &[&str] = &[
+    "exe", "scr", "com", "bat", "cmd", "pif", "cpl", "msi", "msp", "msc", "dll", "jar",
+    "js", "jse", "vbs", "vbe", "wsf", "wsh", "ps1", "psm1", "hta", "lnk", "reg", "inf",
+    "sh", "app", "command", "iso", "img", "vhd", "vhdx", "appx", "msix", "application",
+];
+
+/// 启用宏的 Office 文档
+const MACRO_EXTENSIONS: &[&str] = &[
+    "docm", "dotm", "xlsm", "xltm", "xlam", "pptm", "potm", "ppsm", "ppam", "sldm",
+];
+
+/// 可能用于钓鱼或隐藏内容的类型
+const SUSPICIOUS_EXTENSIONS: &[&str] = &["html", "htm", "svg", "zip", "rar", "7z"];
+
+const DANGEROUS_MIME_TYPES: &[&str] = &[
+    "application/x-msdownload",
+    "application/x-msdos-program",
+    "application/x-dosexec",
+    "application/x-executable",
+    "application/vnd.microsoft.portable-executable",
+    "application/x-mach-binary",
+    "application/x-sh",
+    "application/x-bat",
+    "application/hta",
+    "application/javascript",
+    "text/javascript",
+    "application/java-archive",
+    "application/x-ms-shortcut",
+    "application/vnd.ms-word.document.macroenabled.12",
+    "application/vnd.ms-excel.sheet.macroenabled.12",
+    "application/vnd.ms-powerpoint.presentation.macroenabled.12",
+];
+
+/// 危险等级
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum DangerLevel {
+    Safe,
+    Suspicious,
+    Dangerous,
+}
+
+impl DangerLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DangerLevel::Safe => "safe",
+            DangerLevel::Suspicious => "suspicious",
+            DangerLevel::Dangerous => "dangerous",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "dangerous" => DangerLevel::Dangerous,
+            "suspicious" => DangerLevel::Suspicious,
+            _ => DangerLevel::Safe,
+        }
+    }
+}
+
+/// 附件安全策略（内置黑名单 + 用户追加项）
+#[derive(Debug, Clone, Default)]
+pub struct SafetyPolicy {
+    extra_extensions: Vec<String>,
+    extra_mime_types: Vec<String>,
+}
+
+impl SafetyPolicy {
+    /// 从同步设置加载用户追加的黑名单
+    pub async fn load(pool: &SqlitePool) -> Result<Self, AppError> {
+        let row: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT blocked_extensions, blocked_mime_types FROM sync_settings WHERE id = 1"
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let (extensions, mime_types) = row.unwrap_or_default();
+        Ok(Self {
+            extra_extensions: split_list(extensions.as_deref())
+                .into_iter()
+                .map(|ext| ext.trim_start_matches('.').to_string())
+                .collect(),
+            extra_mime_types: split_list(mime_types.as_deref()),
+        })
+    }
+
+    /// 计算附件危险等级
+    pub fn classify(&self, filename: &str, mime_type: Option<&str>) -> DangerLevel {
+        // 右到左覆盖字符可以把 "fdp.exe" 显示成 "exe.pdf"
+        if filename.chars().any(|c| matches!(c, '\u{202E}' | '\u{202D}' | '\u{200F}')) {
+            return DangerLevel::Dangerous;
+        }
+
+        // Windows 会忽略末尾的点和空格（"invoice.pdf.exe. " 实际是 .exe）
+        let name = filename.trim_end_matches(|c: char| c == '.' || c.is_whitespace()).to_lowercase();
+        let mut parts = name.split('.').skip(1).collect::<Vec<_>>();
+        let extension = parts.pop().unwrap_or_default();
+
+        if self.is_dangerous_extension(extension) || MACRO_EXTENSIONS.contains(&extension) {
+            return DangerLevel::Dangerous;
+        }
+
+        if let Some(mime) = mime_type.map(|m| m.trim().to_lowercase()) {
+            if DANGEROUS_MIME_TYPES.contains(&mime.as_str())
+                || self.extra_mime_types.iter().any(|m| *m == mime)
+            {
+                return DangerLevel::Dangerous;
+            }
+        }
+
+        // 双扩展名中间夹带可执行扩展名（例如 "setup.exe.txt"）
+        if parts.iter().any(|part| self.is_dangerous_extension(part)) {
+            return DangerLevel::Suspicious;
+        }
+
+        if SUSPICIOUS_EXTENSIONS.contains(&extension) {
+            return DangerLevel::Suspicious;
+        }
+
+        DangerLevel::Safe
+    }
+
+    /// 计算附件危险等级，同时考虑按文件头识别出的类型
+    ///
+    /// 识别出的类型本身危险时为危险；与声明类型或扩展名不符时至少为可疑。
+    pub fn classify_detected(&self, filename: &str, declared: Option<&str>, detected: Option<&str>) -> DangerLevel {
+        let level = self.classify(filename, declared);
+        let Some(detected) = detected.filter(|mime| !mime.is_empty()) else {
+            return level;
+        };
+
+        let mut level = level.max(self.classify("", Some(detected)));
+        if sniff::is_mismatch(filename, declared, detected) {
+            log::warn!("Attachment {:?} declared as {:?} but looks like {}", filename, declared, detected);
+            level = level.max(DangerLevel::Suspicious);
+        }
+        level
+    }
+
+    fn is_dangerous_extension(&self, extension: &str) -> bool {
+        DANGEROUS_EXTENSIONS.contains(&extension)
+            || self.extra_extensions.iter().any(|ext| ext == extension)
+    }
+}
+
+/// 解析逗号分隔的列表
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(|item| item.trim().to_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// 交给系统安全机制检查（需启用 `os-scanner` feature）
+///
+/// 为文件添加"来自互联网"标记，打开时由系统安全组件（Windows SmartScreen/Defender、
+/// macOS Gatekeeper）检查。Linux 写入 xdg 来源属性，供桌面环境识别。
+#[cfg(feature = "os-scanner")]
+pub fn mark_as_downloaded(path: &std::path::Path) -> Result<(), AppError> {
+    #[cfg(windows)]
+    {
+        let stream = format!("{}:Zone.Identifier", path.display());
+        std::fs::write(&stream, "[ZoneTransfer]\r\nZoneId=3\r\n")
+            .map_err(|e| AppError::FileSystem(format!("Failed to write Zone.Identifier: {}", e)))?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let status = std::process::Command::new("xattr")
+            .args(["-w", "com.apple.quarantine", "0081;00000000;ThreadLine;"])
+            .arg(path)
+            .status()?;
+        if !status.success() {
+            return Err(AppError::FileSystem(format!("Failed to set quarantine attribute on {:?}", path)));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::process::Command::new("setfattr")
+            .args(["-n", "user.xdg.origin.url", "-v", "threadline:email-attachment"])
+            .arg(path)
+            .status()?;
+        if !status.success() {
+            return Err(AppError::FileSystem(format!("Failed to set xdg origin attribute on {:?}", path)));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+/// 附件内容类型识别
+///
+/// 很多附件的声明类型是 `application/octet-stream`，或者扩展名与内容不符。保存时按文件头（magic bytes）
+/// 识别真实类型写入 `attachments.detected_mime`，文本提取优先按识别出的类型选择处理方式；
+/// 声明类型或扩展名与识别结果属于不同大类时（声明为 PDF，实际是可执行文件）安全检查将其视为可疑。
+use crate::artifacts::safety::{DangerLevel, SafetyPolicy};
+use crate::error::AppError;
+use crate::storage::file_manager;
+use sqlx::SqlitePool;
+
+/// 识别文件类型需要读取的字节数
+const SNIFF_BYTES: usize = 8192;
+
+/// 补充识别时每批处理的附件数
+const BACKFILL_BATCH_SIZE: i64 = 200;
+
+/// 按文件头识别 MIME 类型，无法识别时返回 None（纯文本、CSV 等没有文件头）
+pub fn sniff(data: &[u8]) -> Option<String> {
+    infer::get(&data[..data.len().min(SNIFF_BYTES)]).map(|kind| kind.mime_type().to_string())
+}
+
+/// 读取文件开头识别类型
+pub fn sniff_file(path: &std::path::Path) -> std::io::Result<Option<String>> {
+    use std::io::Read;
+    let mut buffer = Vec::with_capacity(SNIFF_BYTES);
+    std::fs::File::open(path)?.take(SNIFF_BYTES as u64).read_to_end(&mut buffer)?;
+    Ok(sniff(&buffer))
+}
+
+/// 声明类型或扩展名与识别结果是否属于不同大类
+///
+/// 只比较能归类的类型；`application/octet-stream` 和未知扩展名不算不符。
+pub fn is_mismatch(filename: &str, declared: Option<&str>, detected: &str) -> bool {
+    let Some(actual) = mime_family(detected) else {
+        return false;
+    };
+    let declared_family = declared.and_then(mime_family);
+    let extension = filename.rsplit_once('.').map(|(_, ext)| ext).unwrap_or_default();
+    let extension_family = extension_family(extension);
+
+    declared_family.is_some_and(|family| family != actual) || extension_family.is_some_and(|family| family != actual)
+}
+
+/// MIME 类型的大类
+fn mime_family(mime: &str) -> Option<&'static str> {
+    let mime = mime.split(';').next().unwrap_or_default().trim().to_lowercase();
+    let family = match mime.as_str() {
+        "application/pdf" => "pdf",
+        "application/zip"
+        | "application/epub+zip"
+        | "application/java-archive"
+        | "application/vnd.android.package-archive" => "zip",
+        m if m.starts_with("application/vnd.openxmlformats-officedocument.")
+            || m.starts_with("application/vnd.oasis.opendocument.")
+            || m.contains("macroenabled") => "zip",
+        "application/msword"
+        | "application/vnd.ms-excel"
+        | "application/vnd.ms-powerpoint"
+        | "application/vnd.ms-outlook"
+        | "application/x-ole-storage"
+        | "application/x-msi" => "ole",
+        "application/vnd.microsoft.portable-executable"
+        | "application/x-msdownload"
+        | "application/x-dosexec"
+        | "application/x-executable"
+        | "application/x-mach-binary"
+        | "application/vnd.android.dex" => "executable",
+        "application/vnd.rar"
+        | "application/x-rar-compressed"
+        | "application/x-7z-compressed"
+        | "application/gzip"
+        | "application/x-tar"
+        | "application/x-bzip2"
+        | "application/x-xz" => "archive",
+        m if m.starts_with("image/") => "image",
+        m if m.starts_with("video/") => "video",
+        m if m.starts_with("audio/") => "audio",
+        _ => return None,
+    };
+    Some(family)
+}
+
+/// 扩展名的大类
+fn extension_family(extension: &str) -> Option<&'static str> {
+    let family = match extension.to_lowercase().as_str() {
+        "pdf" => "pdf",
+        "zip" | "docx" | "docm" | "xlsx" | "xlsm" | "pptx" | "pptm" | "odt" | "ods" | "odp" | "epub" | "jar"
+        | "apk" => "zip",
+        "doc" | "xls" | "ppt" | "msg" | "msi" => "ole",
+        "exe" | "dll" | "scr" | "com" | "sys" => "executable",
+        "rar" | "7z" | "gz" | "tgz" | "tar" | "bz2" | "xz" => "archive",
+        "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" | "tif" | "tiff" | "heic" | "ico" => "image",
+        "mp4" | "mov" | "avi" | "mkv" | "webm" | "m4v" => "video",
+        "mp3" | "wav" | "ogg" | "m4a" | "flac" | "aac" => "audio",
+        _ => return None,
+    };
+    Some(family)
+}
+
+/// 为升级前保存的附件识别类型并重新计算危险等级，返回处理的附件数
+///
+/// 文件缺失或无法识别的附件记为空字符串，避免重复处理。
+pub async fn backfill_detected_mime(pool: &SqlitePool) -> Result<u64, AppError> {
+    let policy = SafetyPolicy::load(pool).await?;
+    let mut processed = 0u64;
+    loop {
+        let batch: Vec<(i64, String, Option<String>, Option<String>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, filename, mime_type, file_path, danger_level
+            FROM attachments
+            WHERE detected_mime IS NULL
+            ORDER BY id
+            LIMIT ?
+            "#
+        )
+        .bind(BACKFILL_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+        if batch.is_empty() {
+            break;
+        }
+
+        let mut updates = Vec::with_capacity(batch.len());
+        for (id, filename, mime_type, file_path, danger_level) in batch {
+            // 冷存储未挂载时无法读取，按无法识别处理
+            let path = file_path.as_deref().and_then(|path| file_manager::resolve_attachment_path(path).ok());
+            let detected = match path {
+                Some(path) => tokio::task::spawn_blocking(move || sniff_file(&path)).await?.unwrap_or_else(|e| {
+                    log::debug!("Failed to sniff attachment {}: {}", id, e);
+                    None
+                }),
+                None => None,
+            };
+            let stored = danger_level.as_deref().map(DangerLevel::parse).unwrap_or(DangerLevel::Safe);
+            let level = stored.max(policy.classify_detected(&filename, mime_type.as_deref(), detected.as_deref()));
+            updates.push((id, detected.unwrap_or_default(), level));
+        }
+
+        let mut tx = pool.begin().await?;
+        for (id, detected, level) in &updates {
+            sqlx::query("UPDATE attachments SET detected_mime = ?, danger_level = ? WHERE id = ?")
+                .bind(detected)
+                .bind(level.as_str())
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        processed += updates.len() as u64;
+    }
+
+    if processed > 0 {
+        log::info!("Detected content type for {} existing attachments", processed);
+    }
+    Ok(processed)
+}
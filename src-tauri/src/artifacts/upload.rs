@@ -0,0 +1,184 @@
+/// 手动添加到项目的文件
+///
+/// 不是通过邮件收到的文件（会议记录、白板照片等）复制到附件存储，
+/// 以 `email_id = NULL`、`origin = 'manual'` 保存，并走与邮件附件相同的提取和索引流程。
+use crate::artifacts::extractor::AttachmentExtractor;
+use crate::artifacts::safety::SafetyPolicy;
+use crate::artifacts::sniff;
+use crate::artifacts::Artifact;
+use crate::error::AppError;
+use crate::mail::sync::{calculate_sha256, extract_file_extension, sanitize_filename};
+use crate::repository::{ArtifactRepository, ProjectRepository};
+use crate::storage::quarantine::{AttachmentQuarantine, RemovedAttachment, REMOVED_ATTACHMENT_COLUMNS};
+use crate::storage::{disk_space, file_manager};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+
+/// 附件来源
+pub const ORIGIN_EMAIL: &str = "email";
+pub const ORIGIN_MANUAL: &str = "manual";
+
+/// 批量添加中单个文件的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectFileResult {
+    pub source_path: String,
+    pub artifact: Option<Artifact>,
+    pub error: Option<String>,
+}
+
+/// 项目文件存储
+pub struct ProjectFileStore {
+    pool: SqlitePool,
+}
+
+impl ProjectFileStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 把本地文件复制到项目中
+    pub async fn add(&self, project_id: i64, source_path: &str, note: Option<&str>) -> Result<Artifact, AppError> {
+        let status: Option<String> = sqlx::query_scalar("SELECT status FROM projects WHERE id = ?")
+            .bind(project_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if matches!(status.as_deref(), None | Some("deleted")) {
+            return Err(AppError::ProjectNotFound { id: project_id });
+        }
+
+        let source = PathBuf::from(source_path);
+        let metadata = tokio::fs::metadata(&source).await?;
+        if !metadata.is_file() {
+            return Err(AppError::Validation(format!("Not a file: {}", source_path)));
+        }
+        let filename = source
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(str::to_string)
+            .ok_or_else(|| AppError::Validation(format!("Invalid file name: {}", source_path)))?;
+
+        let data = tokio::fs::read(&source).await?;
+        let content_hash = calculate_sha256(&data);
+        let file_type = extract_file_extension(&filename);
+        let relative = self.store_file(project_id, &file_type, &filename, &data).await?;
+
+        let detected_mime = sniff::sniff(&data).unwrap_or_default();
+        let danger_level = SafetyPolicy::load(&self.pool)
+            .await?
+            .classify_detected(&filename, None, Some(&detected_mime));
+
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO attachments (
+                email_id, project_id, filename, file_type, file_size, detected_mime, file_path,
+                content_hash, danger_level, origin, note
+            ) VALUES (NULL, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(project_id)
+        .bind(&filename)
+        .bind(&file_type)
+        .bind(data.len() as i64)
+        .bind(&detected_mime)
+        .bind(&relative)
+        .bind(&content_hash)
+        .bind(danger_level.as_str())
+        .bind(ORIGIN_MANUAL)
+        .bind(note)
+        .execute(&self.pool)
+        .await;
+
+        let id = match inserted {
+            Ok(result) => result.last_insert_rowid(),
+            Err(e) => {
+                // 数据库写入失败时不留下孤立文件
+                let _ = file_manager::remove_attachment_file(&relative).await;
+                return Err(e.into());
+            }
+        };
+
+        if let Err(e) = AttachmentExtractor::new(self.pool.clone()).extract(id).await {
+            log::warn!("Failed to extract text from project file {}: {}", id, e);
+        }
+        ProjectRepository::new(self.pool.clone()).recompute_stats(Some(project_id)).await?;
+
+        log::info!("Added file {} to project {} as attachment {}", filename, project_id, id);
+        ArtifactRepository::new(self.pool.clone()).get_by_id(id).await
+    }
+
+    /// 批量添加（拖放多个文件），每个文件单独返回结果
+    pub async fn add_many(&self, project_id: i64, source_paths: &[String]) -> Vec<ProjectFileResult> {
+        let mut results = Vec::with_capacity(source_paths.len());
+        for source_path in source_paths {
+            let result = self.add(project_id, source_path, None).await;
+            if let Err(e) = &result {
+                log::warn!("Failed to add {} to project {}: {}", source_path, project_id, e);
+            }
+            results.push(ProjectFileResult {
+                source_path: source_path.clone(),
+                error: result.as_ref().err().map(|e| e.to_string()),
+                artifact: result.ok(),
+            });
+        }
+        results
+    }
+
+    /// 删除手动添加的文件（复制的文件移入隔离区）
+    pub async fn delete(&self, id: i64) -> Result<(), AppError> {
+        let removed = sqlx::query_as::<_, RemovedAttachment>(&format!(
+            "SELECT {} FROM attachments WHERE id = ?",
+            REMOVED_ATTACHMENT_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(AppError::AttachmentNotFound { id })?;
+        if removed.origin.as_deref() != Some(ORIGIN_MANUAL) {
+            return Err(AppError::Validation(format!("Attachment {} came from an email and cannot be deleted", id)));
+        }
+
+        sqlx::query("DELETE FROM attachments WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        AttachmentQuarantine::new(self.pool.clone()).quarantine(&removed).await?;
+        if let Some(project_id) = removed.project_id {
+            ProjectRepository::new(self.pool.clone()).recompute_stats(Some(project_id)).await?;
+        }
+
+        log::info!("Deleted project file {}", id);
+        Ok(())
+    }
+
+    /// 写入附件存储：`{file_type}/manual/{project_id}/{filename}`，重名时追加序号
+    async fn store_file(&self, project_id: i64, file_type: &str, filename: &str, data: &[u8]) -> Result<String, AppError> {
+        let dir = format!("{}/manual/{}", file_type, project_id);
+        let absolute_dir = file_manager::resolve_attachment_path(&dir)?;
+        if data.len() as u64 >= disk_space::LARGE_WRITE_BYTES {
+            disk_space::ensure_available(&absolute_dir, data.len() as u64)?;
+        }
+        tokio::fs::create_dir_all(&absolute_dir).await?;
+
+        let safe_name = sanitize_filename(filename);
+        let (stem, ext) = match safe_name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), format!(".{}", ext)),
+            _ => (safe_name.clone(), String::new()),
+        };
+
+        let mut candidate = safe_name.clone();
+        let mut counter = 1;
+        loop {
+            let relative = format!("{}/{}", dir, candidate);
+            let absolute = file_manager::resolve_attachment_path(&relative)?;
+            if !tokio::fs::try_exists(&absolute).await? {
+                tokio::fs::write(&absolute, data)
+                    .await
+                    .map_err(|e| disk_space::write_error(e, &absolute))?;
+                return Ok(relative);
+            }
+            counter += 1;
+            candidate = format!("{} ({}){}", stem, counter, ext);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::error::ErrorResponse;
+use crate::storage::archive::{ArchiveInfo, ArchiveState};
+use tauri::State;
+
+/// 以只读方式打开归档数据库（`attachment_root` 为归档附件目录，默认为数据库旁的 attachments）
+#[tauri::command]
+pub async fn open_archive_database(
+    archive: State<'_, ArchiveState>,
+    path: String,
+    attachment_root: Option<String>,
+) -> Result<ArchiveInfo, ErrorResponse> {
+    archive
+        .open(&path, attachment_root.as_deref())
+        .await
+        .map_err(Into::into)
+}
+
+/// 关闭归档数据库，没有打开的归档时返回 false
+#[tauri::command]
+pub async fn close_archive_database(
+    archive: State<'_, ArchiveState>,
+) -> Result<bool, ErrorResponse> {
+    Ok(archive.close().await)
+}
+
+/// 获取当前打开的归档
+#[tauri::command]
+pub async fn get_archive_database(
+    archive: State<'_, ArchiveState>,
+) -> Result<Option<ArchiveInfo>, ErrorResponse> {
+    archive.info().await.map_err(Into::into)
+}
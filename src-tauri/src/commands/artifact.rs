@@ -1,13 +1,346 @@
+use crate::artifacts::extractor::{AttachmentExtractor, ExtractionResult, DEFAULT_EXTRACT_BATCH};
+use crate::artifacts::integrity::{AttachmentIntegrity, ClobberedAttachment, RepairSummary, VerifySummary};
+use crate::artifacts::safety::{DangerLevel, SafetyPolicy};
+use crate::artifacts::upload::{ProjectFileResult, ProjectFileStore};
 use crate::artifacts::Artifact;
+use crate::error::{AppError, ErrorResponse};
+use crate::events::EventEmitter;
+use crate::mail::connection_pool::ImapConnectionPool;
+use crate::repository::ArtifactRepository;
+use crate::storage::app_state::Db;
+use crate::storage::archive::{ArchiveState, DataSource};
+use crate::storage::cold_storage::{ColdMigrationSummary, ColdStorage, StorageStats};
+use crate::storage::file_manager;
+use crate::storage::quarantine::{AttachmentQuarantine, QuarantinedAttachment};
+use crate::utils::payload::{envelope, Payload};
+use serde::{Deserialize, Serialize};
+use tauri::State;
 
+/// 最近附件列表的默认数量
+const DEFAULT_RECENT_LIMIT: i64 = 50;
+
+/// 根据 ID 获取附件
+#[tauri::command]
+pub async fn get_artifact(
+    repo: ArtifactRepository,
+    id: i64,
+) -> Result<Artifact, ErrorResponse> {
+    repo.get_by_id(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取项目附件（星标优先，`source` 可指定归档数据库）
+#[tauri::command]
+pub async fn get_project_artifacts(
+    pool: Db,
+    archive: State<'_, ArchiveState>,
+    project_id: i64,
+    source: Option<DataSource>,
+) -> Result<Vec<Artifact>, ErrorResponse> {
+    let pool = archive.pool(source.unwrap_or_default(), pool.inner()).await?;
+    ArtifactRepository::new(pool)
+        .list_by_project(project_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 打开附件的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenArtifactResponse {
+    pub opened: bool,
+    /// 危险附件需要用户确认后再次调用（`confirmed: true`）
+    pub requires_confirmation: bool,
+    pub danger_level: DangerLevel,
+    pub filename: String,
+}
+
+/// 用系统默认程序打开附件
+///
+/// 危险或可疑的附件不会直接打开，而是返回 `requires_confirmation: true`
+#[tauri::command]
+pub async fn open_artifact(
+    pool: Db,
+    archive: State<'_, ArchiveState>,
+    id: i64,
+    confirmed: Option<bool>,
+    source: Option<DataSource>,
+) -> Result<OpenArtifactResponse, ErrorResponse> {
+    let source = source.unwrap_or_default();
+    let repo = ArtifactRepository::new(archive.pool(source, pool.inner()).await?);
+    let artifact = repo.get_by_id(id).await?;
+    let file_path = repo
+        .get_file_path(id)
+        .await?
+        .ok_or_else(|| AppError::FileSystem(format!("Attachment {} has no stored file", id)))?;
+
+    // 黑名单可能在保存附件后更新过，取保存时和当前计算结果中更严重的等级
+    let stored = artifact.danger_level.as_deref().map(DangerLevel::parse).unwrap_or(DangerLevel::Safe);
+    let current = SafetyPolicy::load(pool.inner())
+        .await?
+        .classify_detected(&artifact.filename, artifact.mime_type.as_deref(), artifact.detected_mime.as_deref());
+    let danger_level = stored.max(current);
+    if danger_level != stored && source == DataSource::Live {
+        repo.set_danger_level(id, danger_level).await?;
+    }
+
+    if danger_level != DangerLevel::Safe && !confirmed.unwrap_or(false) {
+        log::warn!("Attachment {} ({}) is {}, confirmation required", id, artifact.filename, danger_level.as_str());
+        return Ok(OpenArtifactResponse {
+            opened: false,
+            requires_confirmation: true,
+            danger_level,
+            filename: artifact.filename,
+        });
+    }
+
+    // 归档中的附件解析到归档的附件目录
+    let path = match source {
+        DataSource::Live => file_manager::resolve_attachment_path(&file_path)?,
+        DataSource::Archive => archive.current().await?.resolve_attachment_path(&file_path),
+    };
+    if !path.exists() {
+        return Err(AppError::FileSystem(format!("Attachment file missing: {:?}", path)).into());
+    }
+
+    #[cfg(feature = "os-scanner")]
+    if danger_level != DangerLevel::Safe {
+        crate::artifacts::safety::mark_as_downloaded(&path)?;
+    }
+
+    open::that(&path)
+        .map_err(|e| AppError::FileSystem(format!("Failed to open {:?}: {}", path, e)))?;
+
+    log::info!("Opened attachment {} ({})", id, artifact.filename);
+    Ok(OpenArtifactResponse {
+        opened: true,
+        requires_confirmation: false,
+        danger_level,
+        filename: artifact.filename,
+    })
+}
+
+/// 星标/取消星标附件
+#[tauri::command]
+pub async fn star_artifact(
+    repo: ArtifactRepository,
+    id: i64,
+    starred: bool,
+) -> Result<(), ErrorResponse> {
+    repo.set_starred(id, starred)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取所有星标附件
+#[tauri::command]
+pub async fn list_starred_artifacts(
+    repo: ArtifactRepository,
+) -> Result<Vec<Artifact>, ErrorResponse> {
+    repo.list_starred()
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取所有附件（`compress` 开启大响应压缩）
+#[tauri::command]
+pub async fn list_all_artifacts(
+    repo: ArtifactRepository,
+    compress: Option<bool>,
+) -> Result<Payload<Vec<Artifact>>, ErrorResponse> {
+    let artifacts = repo.list_all().await?;
+    envelope("list_all_artifacts", artifacts, compress.unwrap_or(false)).map_err(Into::into)
+}
+
+/// 获取最近的附件（按来源邮件日期）
+#[tauri::command]
+pub async fn list_recent_artifacts(
+    repo: ArtifactRepository,
+    limit: Option<i64>,
+) -> Result<Vec<Artifact>, ErrorResponse> {
+    repo.list_recent(limit.unwrap_or(DEFAULT_RECENT_LIMIT))
+        .await
+        .map_err(Into::into)
+}
+
+/// 按所属邮件修复附件的项目归属（一次性修复旧数据），返回修改的附件数
+#[tauri::command]
+pub async fn backfill_attachment_projects(
+    repo: ArtifactRepository,
+) -> Result<u64, ErrorResponse> {
+    repo.sync_project_ids()
+        .await
+        .map_err(Into::into)
+}
+
+/// 检查附件文件是否存在且哈希一致（可限定项目）
+#[tauri::command]
+pub async fn verify_attachments(
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
+    project_id: Option<i64>,
+) -> Result<VerifySummary, ErrorResponse> {
+    AttachmentIntegrity::with_event_emitter(pool.inner().clone(), emitter.inner().clone())
+        .verify(project_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 检查所有附件（含哈希校验），返回汇总
+#[tauri::command]
+pub async fn verify_all_attachments(
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
+) -> Result<VerifySummary, ErrorResponse> {
+    AttachmentIntegrity::with_event_emitter(pool.inner().clone(), emitter.inner().clone())
+        .verify_all()
+        .await
+        .map_err(Into::into)
+}
+
+/// 从源账户重新下载并修复单个附件
+#[tauri::command]
+pub async fn repair_attachment(
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
+    connections: State<'_, ImapConnectionPool>,
+    id: i64,
+) -> Result<RepairSummary, ErrorResponse> {
+    AttachmentIntegrity::with_event_emitter(pool.inner().clone(), emitter.inner().clone())
+        .with_connection_pool(connections.inner().clone())
+        .repair(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 修复所有已标记为损坏的附件
+#[tauri::command]
+pub async fn repair_all_broken(
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
+    connections: State<'_, ImapConnectionPool>,
+) -> Result<RepairSummary, ErrorResponse> {
+    AttachmentIntegrity::with_event_emitter(pool.inner().clone(), emitter.inner().clone())
+        .with_connection_pool(connections.inner().clone())
+        .repair_all_broken()
+        .await
+        .map_err(Into::into)
+}
+
+/// 列出可能被覆盖的邮件上的附件（早期 Message-ID 冲突遗留），供人工核对
+#[tauri::command]
+pub async fn find_clobbered_attachments(pool: Db) -> Result<Vec<ClobberedAttachment>, ErrorResponse> {
+    AttachmentIntegrity::new(pool.inner().clone())
+        .find_clobbered()
+        .await
+        .map_err(Into::into)
+}
+
+/// 提取单个 Office 附件的文本
+#[tauri::command]
+pub async fn extract_attachment_text(
+    pool: Db,
+    id: i64,
+) -> Result<ExtractionResult, ErrorResponse> {
+    AttachmentExtractor::new(pool.inner().clone())
+        .extract(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 批量提取待处理的 Office 附件文本
+#[tauri::command]
+pub async fn extract_pending_attachments(
+    pool: Db,
+    limit: Option<i64>,
+) -> Result<Vec<ExtractionResult>, ErrorResponse> {
+    AttachmentExtractor::new(pool.inner().clone())
+        .extract_pending(limit.unwrap_or(DEFAULT_EXTRACT_BATCH))
+        .await
+        .map_err(Into::into)
+}
+
+/// 把本地文件添加到项目（会议记录、白板照片等非邮件文件）
+#[tauri::command]
+pub async fn add_project_file(
+    pool: Db,
+    project_id: i64,
+    source_path: String,
+    note: Option<String>,
+) -> Result<Artifact, ErrorResponse> {
+    ProjectFileStore::new(pool.inner().clone())
+        .add(project_id, &source_path, note.as_deref())
+        .await
+        .map_err(Into::into)
+}
+
+/// 批量添加本地文件（拖放），每个文件单独返回结果
+#[tauri::command]
+pub async fn add_project_files(
+    pool: Db,
+    project_id: i64,
+    source_paths: Vec<String>,
+) -> Result<Vec<ProjectFileResult>, ErrorResponse> {
+    Ok(ProjectFileStore::new(pool.inner().clone())
+        .add_many(project_id, &source_paths)
+        .await)
+}
+
+/// 把早于指定天数的附件移动到冷存储（星标附件和置顶项目除外）
+#[tauri::command]
+pub async fn migrate_cold_attachments(
+    pool: Db,
+    older_than_days: i64,
+) -> Result<ColdMigrationSummary, ErrorResponse> {
+    ColdStorage::new(pool.inner().clone())
+        .migrate(older_than_days)
+        .await
+        .map_err(Into::into)
+}
+
+/// 附件存储用量（按热/冷存储层）、磁盘剩余空间和邮箱配额
+#[tauri::command]
+pub async fn get_storage_stats(
+    pool: Db,
+) -> Result<StorageStats, ErrorResponse> {
+    ColdStorage::new(pool.inner().clone())
+        .stats()
+        .await
+        .map_err(Into::into)
+}
+
+/// 删除手动添加的项目文件
+#[tauri::command]
+pub async fn delete_project_file(
+    pool: Db,
+    id: i64,
+) -> Result<(), ErrorResponse> {
+    ProjectFileStore::new(pool.inner().clone())
+        .delete(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 隔离区中的已删除附件（30 天内可以恢复）
 #[tauri::command]
-pub fn get_artifact(id: String) {
-    log::info!("Getting artifact: {}", id);
+pub async fn list_deleted_attachments(
+    pool: Db,
+) -> Result<Vec<QuarantinedAttachment>, ErrorResponse> {
+    AttachmentQuarantine::new(pool.inner().clone())
+        .list()
+        .await
+        .map_err(Into::into)
 }
 
+/// 恢复已删除的附件，返回新的附件 ID（原邮件已不存在时恢复到 `project_id` 指定的项目）
 #[tauri::command]
-pub fn get_project_artifacts(_project_id: i64) -> Result<Vec<Artifact>, String> {
-    // TODO: 迁移到 repository 模式
-    log::warn!("get_project_artifacts not yet implemented with SQLx");
-    Ok(vec![])
+pub async fn restore_deleted_attachment(
+    pool: Db,
+    manifest_id: i64,
+    project_id: Option<i64>,
+) -> Result<i64, ErrorResponse> {
+    AttachmentQuarantine::new(pool.inner().clone())
+        .restore(manifest_id, project_id)
+        .await
+        .map_err(Into::into)
 }
@@ -1,4 +1,30 @@
-use sqlx::SqlitePool;
+use crate::commands::sync::resolve_account_auth;
+use crate::error::{AppError, ErrorResponse};
+use crate::events::EventEmitter;
+use crate::export::email_pdf::{EmailPdfExporter, EmailPdfSummary};
+use crate::export::thread::{ThreadExportFormat, ThreadExportSummary, ThreadExporter};
+use crate::mail::automated::{AutomatedDetector, SenderRule};
+use crate::mail::calendar::{CalendarStore, InviteReply, InviteResponse};
+use crate::mail::connection_pool::ImapConnectionPool;
+use crate::mail::contacts::{ContactBook, ContactSummary, MergeProposal, RecipientSuggestion};
+use crate::mail::deletions::{ServerDeletedEmail, ServerDeletions};
+use crate::mail::folders::{EmailFolder, FolderStore};
+use crate::mail::identities::{Identity, IdentityRequest, IdentityStore, OutgoingSender};
+use crate::mail::language;
+use crate::mail::namespaces::{self, Namespaces};
+use crate::mail::outgoing::{MailSender, OutgoingEmail, SentEmail};
+use crate::mail::recipients::{self, ReplyMode, ReplyRecipients};
+use crate::mail::remote_search::{self, RemoteEmailPreview, RemoteSearchQuery};
+use crate::mail::smart_views::{SmartViewPage, SmartViews};
+use crate::mail::sync::EmailSyncer;
+use crate::mail::templates::{EmailTemplate, RenderedTemplate, TemplateRequest, TemplateStore};
+use crate::mail::vip::{VipContact, VipList, VIP_ADDRESSES_SQL};
+use crate::mail::watch_folder::{self, WatchFolder, WatchFolderConfig, WatchFolderStatus};
+use crate::storage::app_state::Db;
+use crate::storage::body_store::{self, BodyCompactionSummary};
+use crate::storage::remote_content::{self, CacheClearSummary, RemoteContentCache};
+use crate::utils::translate::{EmailTranslation, Translator};
+use crate::utils::validation;
 use tauri::State;
 use serde::{Deserialize, Serialize};
 
@@ -10,8 +36,16 @@ pub struct EmailPreview {
     pub sender: Option<String>,
     pub date: Option<String>,
     pub body_text: Option<String>,
+    /// 保存时计算的一行预览
+    pub preview: Option<String>,
     pub is_read: bool,
     pub has_attachments: bool,
+    /// 被折叠的重复邮件数量
+    pub duplicate_count: i64,
+    /// 自己只在抄送中（分拣时可降低优先级）
+    pub is_cc_only: bool,
+    /// 发件人是 VIP（含合并联系人的别名）
+    pub is_vip: bool,
 }
 
 #[tauri::command]
@@ -19,20 +53,38 @@ pub fn fetch_emails() {
     log::info!("Fetching emails...");
 }
 
+/// 收件箱最近的 100 封邮件（`vip_only` 时只返回 VIP 发件人的邮件）
 #[tauri::command]
-pub async fn get_inbox_emails(pool: State<'_, SqlitePool>) -> Result<Vec<EmailPreview>, String> {
+pub async fn get_inbox_emails(pool: Db, vip_only: Option<bool>) -> Result<Vec<EmailPreview>, String> {
     log::info!("Fetching inbox emails from database");
 
-    let emails = sqlx::query_as::<_, EmailPreview>(
+    let show_duplicates: bool = sqlx::query_scalar(
+        "SELECT show_duplicates FROM sync_settings WHERE id = 1"
+    )
+    .fetch_one(pool.inner())
+    .await
+    .unwrap_or(false);
+
+    let emails = sqlx::query_as::<_, EmailPreview>(&format!(
         r#"
-        SELECT
-            id, account_id, subject, sender, date,
-            body_text, is_read, has_attachments
-        FROM emails
-        ORDER BY date DESC
+        SELECT * FROM (
+            SELECT
+                id, account_id, subject, sender, date,
+                body_text, preview, is_read, has_attachments,
+                (SELECT COUNT(*) FROM emails d WHERE d.duplicate_of = emails.id) AS duplicate_count,
+                COALESCE(is_cc_only, 0) AS is_cc_only,
+                COALESCE(sender_address IN ({}), 0) AS is_vip
+            FROM emails
+            WHERE (? OR duplicate_of IS NULL) AND server_deleted_at IS NULL
+        )
+        WHERE NOT ? OR is_vip
+        ORDER BY julianday(date) DESC
         LIMIT 100
-        "#
-    )
+        "#,
+        VIP_ADDRESSES_SQL
+    ))
+    .bind(show_duplicates)
+    .bind(vip_only.unwrap_or(false))
     .fetch_all(pool.inner())
     .await
     .map_err(|e| {
@@ -43,3 +95,632 @@ pub async fn get_inbox_emails(pool: State<'_, SqlitePool>) -> Result<Vec<EmailPr
     log::info!("Fetched {} emails from database", emails.len());
     Ok(emails)
 }
+
+/// 跨项目的智能视图（starred / with_attachments / unread / awaiting_reply / recent），按游标分页
+///
+/// `vip_only` 时只包含 VIP 发件人的邮件。
+#[tauri::command]
+pub async fn get_smart_view(
+    pool: Db,
+    kind: String,
+    account_id: Option<i64>,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    vip_only: Option<bool>,
+) -> Result<SmartViewPage, ErrorResponse> {
+    SmartViews::new(pool.inner().clone())
+        .page(&kind, account_id, limit, cursor.as_deref(), vip_only.unwrap_or(false))
+        .await
+        .map_err(Into::into)
+}
+
+/// 账户中服务器上已删除的邮件（墓碑，开启镜像删除后产生）
+#[tauri::command]
+pub async fn list_server_deleted(pool: Db, account_id: i64) -> Result<Vec<ServerDeletedEmail>, ErrorResponse> {
+    ServerDeletions::new(pool.inner().clone())
+        .list(account_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 恢复服务器上已删除的邮件（重新显示并计入项目统计），返回恢复的邮件数
+#[tauri::command]
+pub async fn restore_server_deleted(pool: Db, email_ids: Vec<i64>) -> Result<u64, ErrorResponse> {
+    ServerDeletions::new(pool.inner().clone())
+        .restore(&email_ids)
+        .await
+        .map_err(Into::into)
+}
+
+/// 账户上次同步时服务器返回的 IMAP 命名空间（不支持 NAMESPACE 或尚未同步时为空）
+#[tauri::command]
+pub async fn get_account_namespaces(pool: Db, account_id: i64) -> Result<Option<Namespaces>, ErrorResponse> {
+    namespaces::load(pool.inner(), account_id).await.map_err(Into::into)
+}
+
+/// 服务器端搜索尚未同步的邮件
+#[tauri::command]
+pub async fn search_remote(
+    pool: Db,
+    connections: State<'_, ImapConnectionPool>,
+    account_email: String,
+    query: String,
+    since: Option<String>,
+    before: Option<String>,
+) -> Result<Vec<RemoteEmailPreview>, ErrorResponse> {
+    validation::max_length("query", &query, validation::MAX_QUERY_CHARS)?;
+    let query = RemoteSearchQuery::parse(&query, since.as_deref(), before.as_deref())?;
+    let (account_id, auth, provider) = resolve_account_auth(pool.inner(), &account_email, None).await?;
+
+    log::info!("Remote search for {}: {}", account_email, query.to_imap_criteria());
+    let mut conn = connections.acquire(account_id, auth, &provider).await?;
+    let result = remote_search::search_remote(pool.inner(), account_id, &mut conn, &query).await;
+    if result.is_err() {
+        conn.discard();
+    }
+    result.map_err(Into::into)
+}
+
+/// 导入服务器端搜索命中的邮件（下载、解析、保存并分类）
+#[tauri::command]
+pub async fn import_remote_email(
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
+    connections: State<'_, ImapConnectionPool>,
+    account_email: String,
+    uid: u32,
+) -> Result<i64, ErrorResponse> {
+    let (account_id, auth, provider) = resolve_account_auth(pool.inner(), &account_email, None).await?;
+
+    let mut conn = connections.acquire(account_id, auth, &provider).await?;
+    let result = EmailSyncer::new(pool.inner().clone(), emitter.inner().clone())
+        .import_remote_email(account_id, &mut conn, uid)
+        .await;
+    if result.is_err() {
+        conn.discard();
+    }
+    result.map_err(Into::into)
+}
+
+/// 收件人自动补全
+#[tauri::command]
+pub async fn suggest_recipients(
+    pool: Db,
+    prefix: String,
+    limit: Option<i64>,
+) -> Result<Vec<RecipientSuggestion>, ErrorResponse> {
+    ContactBook::new(pool.inner().clone())
+        .suggest(&prefix, limit.unwrap_or(10))
+        .await
+        .map_err(Into::into)
+}
+
+/// 屏蔽/取消屏蔽联系人
+#[tauri::command]
+pub async fn set_contact_muted(
+    pool: Db,
+    address: String,
+    muted: bool,
+) -> Result<(), ErrorResponse> {
+    ContactBook::new(pool.inner().clone())
+        .set_muted(&address, muted)
+        .await
+        .map_err(Into::into)
+}
+
+/// VIP 发件人列表
+#[tauri::command]
+pub async fn list_vip_contacts(pool: Db) -> Result<Vec<VipContact>, ErrorResponse> {
+    VipList::new(pool.inner().clone()).list().await.map_err(Into::into)
+}
+
+/// 把地址标记为 VIP（`name` 为空时使用联系人簿中的名字）
+#[tauri::command]
+pub async fn add_vip_contact(
+    pool: Db,
+    address: String,
+    name: Option<String>,
+) -> Result<VipContact, ErrorResponse> {
+    VipList::new(pool.inner().clone())
+        .add(&address, name.as_deref())
+        .await
+        .map_err(Into::into)
+}
+
+/// 取消 VIP，返回地址是否在列表中
+#[tauri::command]
+pub async fn remove_vip_contact(pool: Db, address: String) -> Result<bool, ErrorResponse> {
+    VipList::new(pool.inner().clone())
+        .remove(&address)
+        .await
+        .map_err(Into::into)
+}
+
+/// 把多个联系人合并为一个（别名指向主联系人），返回主联系人
+#[tauri::command]
+pub async fn merge_contacts(
+    pool: Db,
+    primary_id: i64,
+    alias_ids: Vec<i64>,
+) -> Result<ContactSummary, ErrorResponse> {
+    ContactBook::new(pool.inner().clone())
+        .merge(primary_id, &alias_ids)
+        .await
+        .map_err(Into::into)
+}
+
+/// 取消联系人合并
+#[tauri::command]
+pub async fn unmerge_contact(
+    pool: Db,
+    contact_id: i64,
+) -> Result<(), ErrorResponse> {
+    ContactBook::new(pool.inner().clone())
+        .unmerge(contact_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取主联系人的别名
+#[tauri::command]
+pub async fn get_contact_aliases(
+    pool: Db,
+    contact_id: i64,
+) -> Result<Vec<ContactSummary>, ErrorResponse> {
+    ContactBook::new(pool.inner().clone())
+        .aliases(contact_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 联系人合并建议（需用户确认）
+#[tauri::command]
+pub async fn get_contact_merge_proposals(
+    pool: Db,
+) -> Result<Vec<MergeProposal>, ErrorResponse> {
+    ContactBook::new(pool.inner().clone())
+        .propose_merges()
+        .await
+        .map_err(Into::into)
+}
+
+/// 按发件人（地址或 @域名）标记是否为自动通知，返回更新的已有邮件数
+#[tauri::command]
+pub async fn set_sender_rule(
+    pool: Db,
+    pattern: String,
+    is_automated: bool,
+) -> Result<u64, ErrorResponse> {
+    AutomatedDetector::new(pool.inner().clone())
+        .set_rule(&pattern, is_automated)
+        .await
+        .map_err(Into::into)
+}
+
+/// 删除发件人规则
+#[tauri::command]
+pub async fn delete_sender_rule(
+    pool: Db,
+    pattern: String,
+) -> Result<(), ErrorResponse> {
+    AutomatedDetector::new(pool.inner().clone())
+        .delete_rule(&pattern)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取所有发件人规则
+#[tauri::command]
+pub async fn list_sender_rules(
+    pool: Db,
+) -> Result<Vec<SenderRule>, ErrorResponse> {
+    AutomatedDetector::new(pool.inner().clone())
+        .list_rules()
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取所有回复模板
+#[tauri::command]
+pub async fn list_templates(
+    pool: Db,
+) -> Result<Vec<EmailTemplate>, ErrorResponse> {
+    TemplateStore::new(pool.inner().clone())
+        .list()
+        .await
+        .map_err(Into::into)
+}
+
+/// 创建回复模板，返回 ID
+#[tauri::command]
+pub async fn create_template(
+    pool: Db,
+    request: TemplateRequest,
+) -> Result<i64, ErrorResponse> {
+    TemplateStore::new(pool.inner().clone())
+        .create(&request)
+        .await
+        .map_err(Into::into)
+}
+
+/// 更新回复模板
+#[tauri::command]
+pub async fn update_template(
+    pool: Db,
+    id: i64,
+    request: TemplateRequest,
+) -> Result<EmailTemplate, ErrorResponse> {
+    TemplateStore::new(pool.inner().clone())
+        .update(id, &request)
+        .await
+        .map_err(Into::into)
+}
+
+/// 删除回复模板
+#[tauri::command]
+pub async fn delete_template(
+    pool: Db,
+    id: i64,
+) -> Result<(), ErrorResponse> {
+    TemplateStore::new(pool.inner().clone())
+        .delete(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 用目标邮件填充模板，返回可直接用于撰写的主题和正文
+#[tauri::command]
+pub async fn render_template(
+    pool: Db,
+    template_id: i64,
+    email_id: i64,
+) -> Result<RenderedTemplate, ErrorResponse> {
+    TemplateStore::new(pool.inner().clone())
+        .render(template_id, email_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取邮件所在的文件夹（Gmail 邮件可能同时在多个标签文件夹中）
+#[tauri::command]
+pub async fn get_email_folders(
+    pool: Db,
+    email_id: i64,
+) -> Result<Vec<EmailFolder>, ErrorResponse> {
+    FolderStore::new(pool.inner().clone())
+        .folders_for(email_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取账户的身份（别名 / send-as 地址）
+#[tauri::command]
+pub async fn list_identities(
+    pool: Db,
+    account_id: i64,
+) -> Result<Vec<Identity>, ErrorResponse> {
+    IdentityStore::new(pool.inner().clone())
+        .list(account_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 为账户添加身份（地址已属于其他账户时拒绝）
+#[tauri::command]
+pub async fn add_identity(
+    pool: Db,
+    account_id: i64,
+    request: IdentityRequest,
+) -> Result<Identity, ErrorResponse> {
+    IdentityStore::new(pool.inner().clone())
+        .add(account_id, &request)
+        .await
+        .map_err(Into::into)
+}
+
+/// 更新身份
+#[tauri::command]
+pub async fn update_identity(
+    pool: Db,
+    id: i64,
+    request: IdentityRequest,
+) -> Result<Identity, ErrorResponse> {
+    IdentityStore::new(pool.inner().clone())
+        .update(id, &request)
+        .await
+        .map_err(Into::into)
+}
+
+/// 删除身份
+#[tauri::command]
+pub async fn delete_identity(
+    pool: Db,
+    id: i64,
+) -> Result<(), ErrorResponse> {
+    IdentityStore::new(pool.inner().clone())
+        .delete(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 从 Gmail 的 send-as 设置导入身份，返回导入数量
+#[tauri::command]
+pub async fn discover_gmail_identities(
+    pool: Db,
+    account_id: i64,
+) -> Result<usize, ErrorResponse> {
+    IdentityStore::new(pool.inner().clone())
+        .discover_gmail(account_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 撰写时选择发件身份：返回 From 头和发信用的 SMTP 配置（未指定身份时使用默认身份或账户主地址）
+#[tauri::command]
+pub async fn resolve_sender(
+    pool: Db,
+    account_id: i64,
+    identity_id: Option<i64>,
+) -> Result<OutgoingSender, ErrorResponse> {
+    IdentityStore::new(pool.inner().clone())
+        .sender_for(account_id, identity_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 发送邮件（可附带本地文件或转发已有附件，超过服务商大小上限时返回 ATTACHMENTS_TOO_LARGE）
+#[tauri::command]
+pub async fn send_email(
+    pool: Db,
+    email: OutgoingEmail,
+) -> Result<SentEmail, ErrorResponse> {
+    MailSender::new(pool.inner().clone())
+        .send(&email)
+        .await
+        .map_err(Into::into)
+}
+
+/// 回复会议邀请（向组织者发送 METHOD:REPLY 并记录自己的回复）
+#[tauri::command]
+pub async fn respond_to_invite(
+    pool: Db,
+    calendar_event_id: i64,
+    response: InviteResponse,
+) -> Result<InviteReply, ErrorResponse> {
+    CalendarStore::new(pool.inner().clone())
+        .respond(calendar_event_id, response)
+        .await
+        .map_err(Into::into)
+}
+
+/// 开始监视文件夹，自动导入放入的 .eml 文件（设置会保存，下次启动时恢复）
+#[tauri::command]
+pub async fn start_watch_folder(
+    pool: Db,
+    watch: State<'_, WatchFolder>,
+    emitter: State<'_, EventEmitter>,
+    config: WatchFolderConfig,
+) -> Result<WatchFolderStatus, ErrorResponse> {
+    watch_folder::validate_config(&pool, &config).await?;
+    watch_folder::save_config(&pool, Some(&config)).await?;
+    watch.start(pool.inner().clone(), emitter.inner().clone(), config);
+    Ok(watch.status())
+}
+
+/// 停止监视文件夹
+#[tauri::command]
+pub async fn stop_watch_folder(
+    pool: Db,
+    watch: State<'_, WatchFolder>,
+) -> Result<(), ErrorResponse> {
+    watch.stop();
+    watch_folder::save_config(&pool, None).await.map_err(Into::into)
+}
+
+/// 监视文件夹状态（诊断页显示）
+#[tauri::command]
+pub async fn get_watch_folder_status(watch: State<'_, WatchFolder>) -> Result<WatchFolderStatus, ErrorResponse> {
+    Ok(watch.status())
+}
+
+/// 邮件详情
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct EmailDetail {
+    pub id: i64,
+    pub account_id: Option<i64>,
+    pub message_id: String,
+    pub subject: Option<String>,
+    /// "Name <addr>"（显示用）
+    pub sender: Option<String>,
+    pub sender_name: Option<String>,
+    pub sender_address: Option<String>,
+    pub recipients: Option<String>,
+    /// 抄送（JSON 数组）
+    pub cc: Option<String>,
+    pub is_cc_only: bool,
+    /// 识别的语言（ISO 639-1，无法识别为 und）
+    pub lang: Option<String>,
+    /// incoming / outgoing
+    pub direction: Option<String>,
+    pub date: Option<String>,
+    pub body_text: Option<String>,
+    pub body_html: Option<String>,
+    /// 正文超过大小上限，当前内容为截断版本
+    pub body_truncated: bool,
+    pub is_read: bool,
+    pub has_attachments: bool,
+}
+
+/// 获取邮件详情
+///
+/// 超大正文默认返回截断版本（`body_truncated = true`），`full_body` 为 true 时从文件读取完整正文。
+#[tauri::command]
+pub async fn get_email_detail(
+    pool: Db,
+    id: i64,
+    full_body: Option<bool>,
+) -> Result<EmailDetail, ErrorResponse> {
+    let mut detail = sqlx::query_as::<_, EmailDetail>(
+        r#"
+        SELECT id, account_id, message_id, subject, sender, sender_name, sender_address, recipients, cc,
+               COALESCE(is_cc_only, 0) AS is_cc_only, lang, direction, date, body_text, body_html, COALESCE(body_truncated, 0) AS body_truncated,
+               COALESCE(is_read, 0) AS is_read, COALESCE(has_attachments, 0) AS has_attachments
+        FROM emails
+        WHERE id = ?
+        "#
+    )
+    .bind(id)
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(AppError::from)?
+    .ok_or(AppError::EmailNotFound { id })?;
+
+    if detail.body_truncated && full_body.unwrap_or(false) {
+        let body_path: Option<String> = sqlx::query_scalar("SELECT body_path FROM emails WHERE id = ?")
+            .bind(id)
+            .fetch_one(pool.inner())
+            .await
+            .map_err(AppError::from)?;
+
+        if let Some(path) = body_path {
+            let full = body_store::read_full_body(&path).await?;
+            detail.body_text = full.text;
+            detail.body_html = full.html;
+            detail.body_truncated = false;
+        }
+    }
+
+    // 正文引用了离线缓存的图片：记录访问时间并转换为当前平台可加载的地址
+    if let Some(html) = detail.body_html.as_deref() {
+        if html.contains(remote_content::CACHE_SCHEME) {
+            if let Err(e) = RemoteContentCache::new(pool.inner().clone()).touch(html).await {
+                log::warn!("Failed to touch remote content cache for email {}: {}", id, e);
+            }
+            detail.body_html = Some(remote_content::display_html(html));
+        }
+    }
+
+    Ok(detail)
+}
+
+/// 清空远程图片离线缓存，邮件正文恢复为原始图片地址，返回回收的空间
+#[tauri::command]
+pub async fn clear_remote_content_cache(
+    pool: Db,
+) -> Result<CacheClearSummary, ErrorResponse> {
+    RemoteContentCache::new(pool.inner().clone())
+        .clear()
+        .await
+        .map_err(Into::into)
+}
+
+/// 计算回复 / 全部回复的收件人（排除自己的地址，忽略大小写去重）
+#[tauri::command]
+pub async fn compute_reply_recipients(
+    pool: Db,
+    email_id: i64,
+    mode: ReplyMode,
+) -> Result<ReplyRecipients, ErrorResponse> {
+    recipients::compute_reply_recipients(pool.inner(), email_id, mode)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取超大正文文件的绝对路径（正文未截断时返回 None），供前端按需加载
+#[tauri::command]
+pub async fn get_email_body_file(
+    pool: Db,
+    id: i64,
+) -> Result<Option<String>, ErrorResponse> {
+    let body_path: Option<Option<String>> = sqlx::query_scalar(
+        "SELECT body_path FROM emails WHERE id = ? AND body_truncated = 1"
+    )
+    .bind(id)
+    .fetch_optional(pool.inner())
+    .await
+    .map_err(AppError::from)?;
+
+    match body_path.flatten() {
+        Some(path) => Ok(Some(body_store::resolve_body_path(&path)?.to_string_lossy().into_owned())),
+        None => Ok(None),
+    }
+}
+
+/// 将已有的超大正文移出数据库，返回节省的空间
+#[tauri::command]
+pub async fn compact_email_bodies(
+    pool: Db,
+) -> Result<BodyCompactionSummary, ErrorResponse> {
+    body_store::compact_oversized_bodies(pool.inner())
+        .await
+        .map_err(Into::into)
+}
+
+/// 为尚未识别语言的已有邮件识别语言（维护命令），返回处理的邮件数
+#[tauri::command]
+pub async fn backfill_email_languages(
+    pool: Db,
+) -> Result<u64, ErrorResponse> {
+    language::backfill_languages(pool.inner())
+        .await
+        .map_err(Into::into)
+}
+
+/// 将单封邮件导出为 PDF（邮件头、正文、内嵌图片，可选附件列表）
+#[tauri::command]
+pub async fn export_email_pdf(
+    pool: Db,
+    email_id: i64,
+    target_path: String,
+    include_attachments_list: Option<bool>,
+) -> Result<EmailPdfSummary, ErrorResponse> {
+    EmailPdfExporter::new(pool.inner().clone())
+        .export_email(email_id, &target_path, include_attachments_list.unwrap_or(true))
+        .await
+        .map_err(Into::into)
+}
+
+/// 将整个线程按时间顺序导出为一个 PDF
+#[tauri::command]
+pub async fn export_thread_pdf(
+    pool: Db,
+    thread_id: String,
+    target_path: String,
+    include_attachments_list: Option<bool>,
+) -> Result<EmailPdfSummary, ErrorResponse> {
+    EmailPdfExporter::new(pool.inner().clone())
+        .export_thread(&thread_id, &target_path, include_attachments_list.unwrap_or(true))
+        .await
+        .map_err(Into::into)
+}
+
+/// 导出单个线程分享给项目以外的人：`eml` 为 .eml + 附件的压缩包，`html` / `pdf` 为按时间顺序的完整记录
+///
+/// 线程分属多个项目时仍然完整导出，`split_note` 中说明拆分情况。
+#[tauri::command]
+pub async fn export_thread(
+    pool: Db,
+    thread_id: String,
+    target_path: String,
+    format: ThreadExportFormat,
+) -> Result<ThreadExportSummary, ErrorResponse> {
+    ThreadExporter::new(pool.inner().clone())
+        .export(&thread_id, &target_path, format)
+        .await
+        .map_err(Into::into)
+}
+
+/// 翻译邮件主题和正文（去掉引用内容），`target_lang` 如 "EN"、"ZH-HANS"
+///
+/// 没有选择翻译服务或缺少 API 密钥时返回 `TRANSLATION_NOT_CONFIGURED`；翻译过的邮件直接返回缓存。
+#[tauri::command]
+pub async fn translate_email(
+    pool: Db,
+    email_id: i64,
+    target_lang: String,
+) -> Result<EmailTranslation, ErrorResponse> {
+    Translator::new(pool.inner().clone())
+        .translate_email(email_id, &target_lang)
+        .await
+        .map_err(Into::into)
+}
@@ -5,6 +5,15 @@ pub mod artifact;
 pub mod sync;
 pub mod oauth;
 pub mod settings;
+pub mod notification;
+pub mod window;
+pub mod archive;
+
+/// 后台初始化是否已完成（错过 `app-ready` 事件的窗口用来确认状态）
+#[tauri::command]
+pub fn is_app_ready(state: tauri::State<'_, crate::storage::app_state::DatabaseState>) -> bool {
+    state.is_ready()
+}
 
 #[tauri::command]
 pub fn greet_user(name: &str) -> String {
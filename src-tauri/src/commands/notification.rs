@@ -0,0 +1,52 @@
+/// 通知中心命令
+use crate::error::ErrorResponse;
+use crate::events::notifications::{Notification, NotificationStore, DEFAULT_NOTIFICATION_LIMIT};
+use crate::storage::app_state::Db;
+
+/// 获取通知列表
+#[tauri::command]
+pub async fn list_notifications(
+    pool: Db,
+    unread_only: Option<bool>,
+    limit: Option<i64>,
+) -> Result<Vec<Notification>, ErrorResponse> {
+    NotificationStore::new(pool.inner().clone())
+        .list(unread_only.unwrap_or(false), limit.unwrap_or(DEFAULT_NOTIFICATION_LIMIT))
+        .await
+        .map_err(Into::into)
+}
+
+/// 标记通知为已读
+#[tauri::command]
+pub async fn mark_notification_read(
+    pool: Db,
+    id: i64,
+) -> Result<(), ErrorResponse> {
+    NotificationStore::new(pool.inner().clone())
+        .mark_read(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 全部标记为已读
+#[tauri::command]
+pub async fn mark_all_read(
+    pool: Db,
+) -> Result<u64, ErrorResponse> {
+    NotificationStore::new(pool.inner().clone())
+        .mark_all_read()
+        .await
+        .map_err(Into::into)
+}
+
+/// 清除早于指定时间的通知（未指定时清除全部）
+#[tauri::command]
+pub async fn clear_notifications(
+    pool: Db,
+    older_than: Option<String>,
+) -> Result<u64, ErrorResponse> {
+    NotificationStore::new(pool.inner().clone())
+        .clear(older_than.as_deref())
+        .await
+        .map_err(Into::into)
+}
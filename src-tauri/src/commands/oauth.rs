@@ -1,6 +1,11 @@
 /// OAuth 2.0 认证命令
+use crate::commands::sync::insert_oauth_account;
 use crate::error::{AppError, ErrorResponse};
 use crate::mail::oauth::{OAuthClient, OAuthProvider};
+use crate::mail::pending_oauth::{PendingAuthorization, PendingAuthorizations};
+use crate::mail::provider_policy::{self, PolicyProvider};
+use crate::storage::app_state::Db;
+use crate::utils::validation;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -8,6 +13,9 @@ pub struct OAuthConfig {
     pub provider: String,  // "gmail" or "outlook"
     pub client_id: String,
     pub client_secret: Option<String>,
+    /// 用户在授权前输入的邮箱（保存在待完成授权中，恢复时预填）
+    #[serde(default)]
+    pub email_hint: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -16,11 +24,15 @@ pub struct OAuthResult {
     pub refresh_token: Option<String>,
     pub expires_in: Option<i64>,  // 秒数
     pub provider: String,
+    /// 待完成授权 ID（传给 `add_oauth_email_account`，保存失败时为 None）
+    pub pending_id: Option<i64>,
 }
 
 /// 启动 OAuth 2.0 授权流程
+///
+/// 拿到令牌后先保存为待完成授权，创建账户前应用崩溃时可用 `complete_pending_authorization` 恢复。
 #[tauri::command]
-pub async fn start_oauth_flow(config: OAuthConfig) -> Result<OAuthResult, ErrorResponse> {
+pub async fn start_oauth_flow(pool: Db, config: OAuthConfig) -> Result<OAuthResult, ErrorResponse> {
     log::info!("Starting OAuth flow for provider: {}", config.provider);
 
     // 选择提供商配置
@@ -64,14 +76,63 @@ pub async fn start_oauth_flow(config: OAuthConfig) -> Result<OAuthResult, ErrorR
             e
         })?;
 
+    // 保存失败不影响本次添加账户，只是崩溃后无法恢复
+    let pending_id = match PendingAuthorizations::new(pool.inner().clone())
+        .save(&config.provider, config.email_hint.as_deref(), &token_info)
+        .await
+    {
+        Ok(id) => Some(id),
+        Err(e) => {
+            log::warn!("Failed to save pending OAuth authorization: {}", e);
+            None
+        }
+    };
+
     Ok(OAuthResult {
         access_token: token_info.access_token,
         refresh_token: token_info.refresh_token,
         expires_in: token_info.expires_in,
         provider: config.provider,
+        pending_id,
     })
 }
 
+/// 列出未完成的 OAuth 授权（授权后、创建账户前应用退出的记录，保留 15 分钟）
+#[tauri::command]
+pub async fn list_pending_authorizations(pool: Db) -> Result<Vec<PendingAuthorization>, ErrorResponse> {
+    PendingAuthorizations::new(pool.inner().clone())
+        .list()
+        .await
+        .map_err(Into::into)
+}
+
+/// 用待完成授权中的令牌创建账户，返回账户 ID
+#[tauri::command]
+pub async fn complete_pending_authorization(
+    pool: Db,
+    id: i64,
+    email: String,
+) -> Result<i64, ErrorResponse> {
+    validation::email_address("email", &email)?;
+    let pending = PendingAuthorizations::new(pool.inner().clone());
+    let (authorization, token) = pending.get(id).await?;
+    authorization.ensure_provider_matches(&email)?;
+    let account_id = insert_oauth_account(
+        pool.inner(),
+        &email,
+        &token.access_token,
+        token.refresh_token.as_deref(),
+        token.expires_in,
+    )
+    .await?;
+
+    if let Err(e) = pending.remove(id).await {
+        log::warn!("Failed to remove pending authorization {}: {}", id, e);
+    }
+
+    Ok(account_id)
+}
+
 /// 获取 OAuth 配置说明
 #[tauri::command]
 pub fn get_oauth_instructions(provider: String) -> Result<String, ErrorResponse> {
@@ -123,3 +184,12 @@ pub fn get_oauth_instructions(provider: String) -> Result<String, ErrorResponse>
     Ok(instructions.to_string())
 }
 
+/// 获取不支持 OAuth 的服务商（QQ / 163 / 126）开启 IMAP 和生成授权码的步骤
+///
+/// 登录返回 `PROVIDER_POLICY` 错误时，前端用错误详情中的 `provider` 调用。
+#[tauri::command]
+pub fn get_provider_setup_help(provider: String) -> Result<String, ErrorResponse> {
+    let provider = PolicyProvider::from_name(&provider)
+        .ok_or_else(|| AppError::Validation(format!("No setup help for provider: {}", provider)))?;
+    Ok(provider_policy::setup_help(provider).to_string())
+}
@@ -1,14 +1,39 @@
 use crate::error::ErrorResponse;
-use crate::project::{Project, TimelineEvent};
+use crate::events::EventEmitter;
+use crate::export::archive::{ProjectArchiveExporter, ProjectArchiveSummary};
+use crate::export::report::{ReportFormat, ReportGenerator, ReportOptions, ReportSummary};
+use crate::project::classification_log::{ClassificationExplanation, ClassificationLog, ClassifierMetrics};
+use crate::project::classifier::ProjectClassifier;
+use crate::project::merger::{MergeSummary, ProjectMerger};
+use crate::project::preferences::ProjectPreferences;
+use crate::project::splitter::{ProjectSplitter, SplitProposal, SplitSummary};
+use crate::project::statuses::{ProjectStatus, ProjectStatusStore};
+use crate::project::snapshot::{OrganizationSnapshots, RestoreSummary, SnapshotInfo};
+use crate::project::templates::{ApplyTemplateSummary, ProjectTemplate, ProjectTemplateRequest, ProjectTemplateStore};
+use crate::project::undo::{UndoEntry, UndoJournal, UndoResult};
+use crate::project::{DeletedProject, Project, ProjectReview, ProjectSort, ThreadEmail, ThreadView, TimelineEvent};
 use crate::repository::ProjectRepository;
+use crate::storage::app_state::Db;
+use crate::storage::archive::{ArchiveState, DataSource};
+use crate::utils::payload::{envelope, Payload};
 use tauri::State;
 
-/// 获取所有项目列表
+/// 获取所有项目列表（`source` 可指定归档数据库，`sort: "due"` 时逾期和即将到期的项目在前，`status` 按状态筛选）
+///
+/// `utc_offset_minutes` 为客户端的 UTC 偏移，用于计算最近活动的"今天/昨天"。
 #[tauri::command]
 pub async fn list_projects(
-    repo: State<'_, ProjectRepository>,
+    pool: Db,
+    archive: State<'_, ArchiveState>,
+    source: Option<DataSource>,
+    sort: Option<ProjectSort>,
+    status: Option<String>,
+    utc_offset_minutes: Option<i32>,
 ) -> Result<Vec<Project>, ErrorResponse> {
-    repo.list_all()
+    let pool = archive.pool(source.unwrap_or_default(), pool.inner()).await?;
+    ProjectRepository::new(pool)
+        .with_utc_offset(utc_offset_minutes)
+        .list_all(sort.unwrap_or_default(), status.as_deref().filter(|status| !status.is_empty()))
         .await
         .map_err(Into::into)
 }
@@ -16,21 +41,162 @@ pub async fn list_projects(
 /// 根据 ID 获取项目
 #[tauri::command]
 pub async fn get_project(
-    repo: State<'_, ProjectRepository>,
+    repo: ProjectRepository,
     id: i64,
+    utc_offset_minutes: Option<i32>,
 ) -> Result<Project, ErrorResponse> {
-    repo.get_by_id(id)
+    repo.with_utc_offset(utc_offset_minutes)
+        .get_by_id(id)
         .await
         .map_err(Into::into)
 }
 
-/// 获取项目时间线
+/// 获取项目时间线（`source` 可指定归档数据库，`compress` 开启大响应压缩）
 #[tauri::command]
 pub async fn get_project_timeline(
-    repo: State<'_, ProjectRepository>,
+    pool: Db,
+    archive: State<'_, ArchiveState>,
     id: i64,
-) -> Result<Vec<TimelineEvent>, ErrorResponse> {
-    repo.get_timeline(id)
+    source: Option<DataSource>,
+    compress: Option<bool>,
+) -> Result<Payload<Vec<TimelineEvent>>, ErrorResponse> {
+    let pool = archive.pool(source.unwrap_or_default(), pool.inner()).await?;
+    let timeline = ProjectRepository::new(pool).get_timeline(id).await?;
+    envelope("get_project_timeline", timeline, compress.unwrap_or(false)).map_err(Into::into)
+}
+
+/// 获取完整线程（跨项目，用于排查被拆分的线程）
+#[tauri::command]
+pub async fn get_thread(
+    repo: ProjectRepository,
+    thread_id: String,
+) -> Result<ThreadView, ErrorResponse> {
+    repo.get_thread(&thread_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取线程中的邮件（展开折叠的自动通知时 `include_automated: true`）
+#[tauri::command]
+pub async fn get_thread_emails(
+    repo: ProjectRepository,
+    thread_id: String,
+    include_automated: Option<bool>,
+) -> Result<Vec<ThreadEmail>, ErrorResponse> {
+    repo.get_thread_emails(&thread_id, include_automated.unwrap_or(false))
+        .await
+        .map_err(Into::into)
+}
+
+/// 把整个线程移到指定项目，返回被移动的邮件数
+#[tauri::command]
+pub async fn move_thread_to_project(
+    repo: ProjectRepository,
+    thread_id: String,
+    project_id: i64,
+) -> Result<u64, ErrorResponse> {
+    repo.move_thread_to_project(&thread_id, project_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 把单封邮件移到指定项目（可撤销）
+#[tauri::command]
+pub async fn move_email_to_project(
+    repo: ProjectRepository,
+    email_id: i64,
+    project_id: i64,
+) -> Result<(), ErrorResponse> {
+    repo.move_email_to_project(email_id, project_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 把多个项目合并到目标项目（源项目移入回收站，可整体撤销）
+#[tauri::command]
+pub async fn merge_projects(
+    pool: Db,
+    target_id: i64,
+    source_ids: Vec<i64>,
+) -> Result<MergeSummary, ErrorResponse> {
+    ProjectMerger::new(pool.inner().clone())
+        .merge(target_id, &source_ids)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取超过规模上限、建议拆分的项目
+#[tauri::command]
+pub async fn get_projects_needing_review(
+    repo: ProjectRepository,
+) -> Result<Vec<ProjectReview>, ErrorResponse> {
+    repo.list_needing_review().await.map_err(Into::into)
+}
+
+/// 忽略项目的待检查提醒（一周内不再提醒）
+#[tauri::command]
+pub async fn dismiss_project_review(
+    repo: ProjectRepository,
+    project_id: i64,
+) -> Result<(), ErrorResponse> {
+    repo.dismiss_review(project_id).await.map_err(Into::into)
+}
+
+/// 按会话生成项目拆分建议
+#[tauri::command]
+pub async fn propose_project_split(
+    pool: Db,
+    project_id: i64,
+) -> Result<SplitProposal, ErrorResponse> {
+    ProjectSplitter::new(pool.inner().clone())
+        .propose(project_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 把选中的会话分别拆分为新项目
+#[tauri::command]
+pub async fn apply_project_split(
+    pool: Db,
+    project_id: i64,
+    thread_ids: Vec<String>,
+) -> Result<SplitSummary, ErrorResponse> {
+    ProjectSplitter::new(pool.inner().clone())
+        .apply(project_id, &thread_ids)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取可撤销的操作（最新的在前）
+#[tauri::command]
+pub async fn list_undo_actions(
+    pool: Db,
+) -> Result<Vec<UndoEntry>, ErrorResponse> {
+    UndoJournal::new(pool.inner().clone())
+        .list()
+        .await
+        .map_err(Into::into)
+}
+
+/// 撤销最近一次项目操作
+#[tauri::command]
+pub async fn undo_last_action(
+    pool: Db,
+) -> Result<UndoResult, ErrorResponse> {
+    UndoJournal::new(pool.inner().clone())
+        .undo_last()
+        .await
+        .map_err(Into::into)
+}
+
+/// 撤销指定的项目操作
+#[tauri::command]
+pub async fn undo_action(
+    pool: Db,
+    id: i64,
+) -> Result<UndoResult, ErrorResponse> {
+    UndoJournal::new(pool.inner().clone())
+        .undo(id)
         .await
         .map_err(Into::into)
 }
@@ -38,7 +204,7 @@ pub async fn get_project_timeline(
 /// 置顶/取消置顶项目
 #[tauri::command]
 pub async fn toggle_project_pin(
-    repo: State<'_, ProjectRepository>,
+    repo: ProjectRepository,
     id: i64,
 ) -> Result<bool, ErrorResponse> {
     repo.toggle_pin(id)
@@ -46,10 +212,21 @@ pub async fn toggle_project_pin(
         .map_err(Into::into)
 }
 
+/// 手动排序置顶项目
+#[tauri::command]
+pub async fn reorder_pinned_projects(
+    repo: ProjectRepository,
+    ordered_ids: Vec<i64>,
+) -> Result<(), ErrorResponse> {
+    repo.reorder_pinned(&ordered_ids)
+        .await
+        .map_err(Into::into)
+}
+
 /// 归档项目
 #[tauri::command]
 pub async fn archive_project(
-    repo: State<'_, ProjectRepository>,
+    repo: ProjectRepository,
     id: i64,
 ) -> Result<(), ErrorResponse> {
     repo.archive(id)
@@ -60,7 +237,7 @@ pub async fn archive_project(
 /// 取消归档项目
 #[tauri::command]
 pub async fn unarchive_project(
-    repo: State<'_, ProjectRepository>,
+    repo: ProjectRepository,
     id: i64,
 ) -> Result<(), ErrorResponse> {
     repo.unarchive(id)
@@ -68,3 +245,327 @@ pub async fn unarchive_project(
         .map_err(Into::into)
 }
 
+/// 获取项目状态词表
+#[tauri::command]
+pub async fn list_project_statuses(pool: Db) -> Result<Vec<ProjectStatus>, ErrorResponse> {
+    ProjectStatusStore::new(pool.inner().clone())
+        .list()
+        .await
+        .map_err(Into::into)
+}
+
+/// 新增或修改项目状态
+#[tauri::command]
+pub async fn save_project_status(pool: Db, status: ProjectStatus) -> Result<ProjectStatus, ErrorResponse> {
+    ProjectStatusStore::new(pool.inner().clone())
+        .save(status)
+        .await
+        .map_err(Into::into)
+}
+
+/// 删除项目状态（仍有项目使用时拒绝）
+#[tauri::command]
+pub async fn delete_project_status(pool: Db, key: String) -> Result<(), ErrorResponse> {
+    ProjectStatusStore::new(pool.inner().clone())
+        .delete(&key)
+        .await
+        .map_err(Into::into)
+}
+
+/// 设置项目状态（须为状态词表中的状态），返回更新后的项目
+#[tauri::command]
+pub async fn set_project_status(
+    pool: Db,
+    repo: ProjectRepository,
+    project_id: i64,
+    status: String,
+) -> Result<Project, ErrorResponse> {
+    ProjectStatusStore::new(pool.inner().clone())
+        .set_project_status(project_id, status.trim())
+        .await?;
+    repo.get_by_id(project_id).await.map_err(Into::into)
+}
+
+/// 获取项目视图偏好
+#[tauri::command]
+pub async fn get_project_preferences(
+    repo: ProjectRepository,
+    id: i64,
+) -> Result<ProjectPreferences, ErrorResponse> {
+    repo.get_preferences(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 保存项目视图偏好
+#[tauri::command]
+pub async fn set_project_preferences(
+    repo: ProjectRepository,
+    id: i64,
+    prefs: ProjectPreferences,
+) -> Result<(), ErrorResponse> {
+    repo.set_preferences(id, &prefs)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取项目模板列表
+#[tauri::command]
+pub async fn list_project_templates(
+    pool: Db,
+) -> Result<Vec<ProjectTemplate>, ErrorResponse> {
+    ProjectTemplateStore::new(pool.inner().clone())
+        .list()
+        .await
+        .map_err(Into::into)
+}
+
+/// 新建项目模板
+#[tauri::command]
+pub async fn create_project_template(
+    pool: Db,
+    template: ProjectTemplateRequest,
+) -> Result<ProjectTemplate, ErrorResponse> {
+    ProjectTemplateStore::new(pool.inner().clone())
+        .create(&template)
+        .await
+        .map_err(Into::into)
+}
+
+/// 更新项目模板
+#[tauri::command]
+pub async fn update_project_template(
+    pool: Db,
+    id: i64,
+    template: ProjectTemplateRequest,
+) -> Result<ProjectTemplate, ErrorResponse> {
+    ProjectTemplateStore::new(pool.inner().clone())
+        .update(id, &template)
+        .await
+        .map_err(Into::into)
+}
+
+/// 删除项目模板（已套用的项目不受影响）
+#[tauri::command]
+pub async fn delete_project_template(
+    pool: Db,
+    id: i64,
+) -> Result<(), ErrorResponse> {
+    ProjectTemplateStore::new(pool.inner().clone())
+        .delete(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 从模板新建项目，返回新项目
+#[tauri::command]
+pub async fn create_project_from_template(
+    pool: Db,
+    repo: ProjectRepository,
+    template_id: i64,
+    name: String,
+) -> Result<Project, ErrorResponse> {
+    let project_id = ProjectTemplateStore::new(pool.inner().clone())
+        .create_project(template_id, &name)
+        .await?;
+    repo.get_by_id(project_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 把模板套用到已有项目（默认合并，`overwrite` 时替换）
+#[tauri::command]
+pub async fn apply_template(
+    pool: Db,
+    project_id: i64,
+    template_id: i64,
+    overwrite: Option<bool>,
+) -> Result<ApplyTemplateSummary, ErrorResponse> {
+    ProjectTemplateStore::new(pool.inner().clone())
+        .apply(project_id, template_id, overwrite.unwrap_or(false))
+        .await
+        .map_err(Into::into)
+}
+
+/// 删除项目（移入回收站）
+#[tauri::command]
+pub async fn delete_project(
+    repo: ProjectRepository,
+    id: i64,
+) -> Result<(), ErrorResponse> {
+    repo.soft_delete(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取回收站中的项目
+#[tauri::command]
+pub async fn list_deleted_projects(
+    repo: ProjectRepository,
+) -> Result<Vec<DeletedProject>, ErrorResponse> {
+    repo.list_deleted()
+        .await
+        .map_err(Into::into)
+}
+
+/// 从回收站恢复项目
+#[tauri::command]
+pub async fn restore_project(
+    repo: ProjectRepository,
+    id: i64,
+) -> Result<(), ErrorResponse> {
+    repo.restore(id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 设置项目颜色和图标
+#[tauri::command]
+pub async fn set_project_appearance(
+    repo: ProjectRepository,
+    project_id: i64,
+    color: Option<String>,
+    icon: Option<String>,
+) -> Result<(), ErrorResponse> {
+    repo.set_appearance(project_id, color, icon)
+        .await
+        .map_err(Into::into)
+}
+
+/// 设置项目截止日期（`due_date` 为空时清除），返回更新后的项目
+#[tauri::command]
+pub async fn set_project_due_date(
+    repo: ProjectRepository,
+    project_id: i64,
+    due_date: Option<String>,
+    note: Option<String>,
+    create_milestone: Option<bool>,
+) -> Result<Project, ErrorResponse> {
+    repo.set_due_date(project_id, due_date.as_deref(), note.as_deref(), create_milestone.unwrap_or(false))
+        .await?;
+    repo.get_by_id(project_id).await.map_err(Into::into)
+}
+
+/// 获取邮件的分类解释（最新决策及被放弃的候选项）
+#[tauri::command]
+pub async fn get_classification_explanation(
+    pool: Db,
+    email_id: i64,
+) -> Result<Option<ClassificationExplanation>, ErrorResponse> {
+    ClassificationLog::new(pool.inner().clone())
+        .explain(email_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 自动分类准确率：最近 `days` 天（默认 30）自动分类的邮件中被手动移动的比例
+#[tauri::command]
+pub async fn get_classifier_metrics(
+    pool: Db,
+    days: Option<i64>,
+) -> Result<ClassifierMetrics, ErrorResponse> {
+    ClassificationLog::new(pool.inner().clone())
+        .metrics(days.unwrap_or(30))
+        .await
+        .map_err(Into::into)
+}
+
+/// 对所有未分类的邮件运行自动分类，返回归入项目的邮件数
+///
+/// 新建项目数默认受 `max_new_projects_per_sync` 限制；`override_project_cap` 为 true 时不限。
+#[tauri::command]
+pub async fn classify_unassigned_emails(
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
+    override_project_cap: Option<bool>,
+) -> Result<usize, ErrorResponse> {
+    let mut classifier = ProjectClassifier::load(pool.inner().clone())
+        .await
+        .with_event_emitter(emitter.inner().clone());
+    if override_project_cap.unwrap_or(false) {
+        classifier = classifier.without_project_cap();
+    }
+    let classified = classifier.classify_all_unassigned().await?;
+    classifier.emit_project_events();
+    Ok(classified)
+}
+
+/// 重新计算项目统计（维护命令），返回统计发生变化的项目数
+#[tauri::command]
+pub async fn recompute_project_stats(
+    repo: ProjectRepository,
+    project_id: Option<i64>,
+) -> Result<u64, ErrorResponse> {
+    repo.recompute_stats(project_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 生成项目报告（Markdown / PDF）并写入用户选择的路径
+#[tauri::command]
+pub async fn generate_project_report(
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
+    project_id: i64,
+    format: ReportFormat,
+    options: Option<ReportOptions>,
+    target_path: String,
+) -> Result<ReportSummary, ErrorResponse> {
+    log::info!("Generating {:?} report for project {} to {}", format, project_id, target_path);
+
+    let generator = ReportGenerator::with_event_emitter(pool.inner().clone(), emitter.inner().clone());
+    generator
+        .generate(project_id, format, &options.unwrap_or_default(), &target_path)
+        .await
+        .map_err(Into::into)
+}
+
+/// 导出项目为 .zip（附件 + project.json + 校验清单），设置密码时整个压缩包 AES-256 加密
+#[tauri::command]
+pub async fn export_project(
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
+    project_id: i64,
+    target_path: String,
+    password: Option<String>,
+) -> Result<ProjectArchiveSummary, ErrorResponse> {
+    ProjectArchiveExporter::with_event_emitter(pool.inner().clone(), emitter.inner().clone())
+        .export(project_id, &target_path, password.as_deref())
+        .await
+        .map_err(Into::into)
+}
+
+/// 创建项目组织快照（邮件归属、项目元数据和里程碑）
+#[tauri::command]
+pub async fn create_organization_snapshot(
+    pool: Db,
+    label: String,
+) -> Result<SnapshotInfo, ErrorResponse> {
+    OrganizationSnapshots::new(pool.inner().clone())
+        .create(&label)
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取快照列表
+#[tauri::command]
+pub async fn list_snapshots(
+    pool: Db,
+) -> Result<Vec<SnapshotInfo>, ErrorResponse> {
+    OrganizationSnapshots::new(pool.inner().clone())
+        .list()
+        .await
+        .map_err(Into::into)
+}
+
+/// 恢复快照
+#[tauri::command]
+pub async fn restore_snapshot(
+    pool: Db,
+    id: i64,
+) -> Result<RestoreSummary, ErrorResponse> {
+    OrganizationSnapshots::new(pool.inner().clone())
+        .restore(id)
+        .await
+        .map_err(Into::into)
+}
@@ -1,6 +1,114 @@
-// use crate::search;
+use crate::error::ErrorResponse;
+use crate::events::EventEmitter;
+use crate::export::search_csv::{SearchExportColumn, SearchExportRequest, SearchExportSummary, SearchExporter};
+use crate::search::bulk::{BulkSearchAction, BulkSearchRequest, BulkSearchSummary, SearchBulkApplier};
+use crate::search::count::{SearchCount, SearchCountRequest, SearchCounter};
+use crate::search::indexer::{SearchIndexStatus, SearchIndexer};
+use crate::search::quick_switcher::{QuickSwitchItem, QuickSwitcher, DEFAULT_QUICK_SEARCH_LIMIT};
+use crate::search::query::{search_emails, SearchHit, DEFAULT_SEARCH_LIMIT};
+use crate::storage::app_state::Db;
+use crate::storage::archive::{ArchiveState, DataSource};
+use crate::utils::payload::{envelope, Payload};
+use crate::utils::validation;
+use tauri::State;
 
+/// 搜索本地邮件（`source` 可指定归档数据库，`compress` 开启大响应压缩）
 #[tauri::command]
-pub fn search_query(query: String) {
-    println!("Searching for: {}", query);
+pub async fn search_query(
+    pool: Db,
+    archive: State<'_, ArchiveState>,
+    query: String,
+    source: Option<DataSource>,
+    limit: Option<i64>,
+    compress: Option<bool>,
+    lang: Option<String>,
+) -> Result<Payload<Vec<SearchHit>>, ErrorResponse> {
+    validation::max_length("query", &query, validation::MAX_QUERY_CHARS)?;
+    let pool = archive.pool(source.unwrap_or_default(), pool.inner()).await?;
+    let lang = lang.as_deref().map(str::trim).filter(|lang| !lang.is_empty());
+    let hits = search_emails(&pool, &query, lang, limit.unwrap_or(DEFAULT_SEARCH_LIMIT)).await?;
+    envelope("search_query", hits, compress.unwrap_or(false)).map_err(Into::into)
+}
+
+/// 搜索结果数量预览（输入时调用），查询少于 2 个字符时返回 null
+#[tauri::command]
+pub async fn count_search_results(
+    pool: Db,
+    archive: State<'_, ArchiveState>,
+    counter: State<'_, SearchCounter>,
+    request: SearchCountRequest,
+) -> Result<Option<SearchCount>, ErrorResponse> {
+    let pool = archive.pool(request.source.unwrap_or_default(), pool.inner()).await?;
+    counter.count(&pool, &request).await.map_err(Into::into)
+}
+
+/// 把搜索结果导出为 CSV（不分页，`columns` 为空时导出全部列）
+#[tauri::command]
+pub async fn export_search_results(
+    pool: Db,
+    archive: State<'_, ArchiveState>,
+    emitter: State<'_, EventEmitter>,
+    request: SearchExportRequest,
+    target_path: String,
+    columns: Option<Vec<SearchExportColumn>>,
+    source: Option<DataSource>,
+) -> Result<SearchExportSummary, ErrorResponse> {
+    let pool = archive.pool(source.unwrap_or_default(), pool.inner()).await?;
+    SearchExporter::with_event_emitter(pool, emitter.inner().clone())
+        .export(&request, &columns.unwrap_or_default(), &target_path)
+        .await
+        .map_err(Into::into)
+}
+
+/// 对搜索结果批量操作（移到项目、新建项目、加星、标为已读），不分页
+///
+/// 匹配数超过 `bulk_action_cap` 时返回 `BULK_CAP_EXCEEDED`，确认后带 `confirm_over_cap` 重新调用。
+#[tauri::command]
+pub async fn bulk_apply_to_search(
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
+    request: BulkSearchRequest,
+    action: BulkSearchAction,
+) -> Result<BulkSearchSummary, ErrorResponse> {
+    SearchBulkApplier::with_event_emitter(pool.inner().clone(), emitter.inner().clone())
+        .apply(&request, &action)
+        .await
+        .map_err(Into::into)
+}
+
+/// 快速切换器：按前缀返回项目、联系人和最近邮件（按 `kind` 区分）
+#[tauri::command]
+pub async fn quick_search(
+    pool: Db,
+    switcher: State<'_, QuickSwitcher>,
+    prefix: String,
+    limit: Option<i64>,
+) -> Result<Vec<QuickSwitchItem>, ErrorResponse> {
+    switcher
+        .search(pool.inner(), &prefix, limit.unwrap_or(DEFAULT_QUICK_SEARCH_LIMIT))
+        .await
+        .map_err(Into::into)
+}
+
+/// 重建全文索引（索引损坏或更换分词器后使用，可在应用使用中运行）
+#[tauri::command]
+pub async fn rebuild_search_index(
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
+) -> Result<SearchIndexStatus, ErrorResponse> {
+    SearchIndexer::with_event_emitter(pool.inner().clone(), emitter.inner().clone())
+        .rebuild()
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取全文索引状态
+#[tauri::command]
+pub async fn search_index_status(
+    pool: Db,
+) -> Result<SearchIndexStatus, ErrorResponse> {
+    SearchIndexer::new(pool.inner().clone())
+        .status()
+        .await
+        .map_err(Into::into)
 }
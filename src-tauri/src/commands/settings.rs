@@ -1,8 +1,17 @@
 /// 设置相关命令
-use crate::error::ErrorResponse;
+use crate::error::{AppError, ErrorResponse};
+use crate::export::settings_profile::{MergeStrategy, ProfileExportSummary, ProfileImportReport, SettingsProfileManager};
+use crate::index_scheduler::quiet_hours;
+use crate::project::classifier::ClassifierConfig;
+use crate::repository::concurrency::{ensure_swapped, versioned_update_sql};
+use crate::storage::app_state::Db;
+use crate::storage::database::{self, DatabasePragmas};
+use crate::storage::file_manager;
+use crate::utils::i18n::MAX_UTC_OFFSET_MINUTES;
+use crate::utils::translate::{self, ProviderKind};
+use crate::utils::{tray, validation};
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
-use tauri::State;
 
 /// 同步设置
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -12,6 +21,64 @@ pub struct SyncSettings {
     pub auto_sync_enabled: bool,
     pub sync_interval_minutes: i64,
     pub sync_attachments: bool,
+    pub show_duplicates: bool,
+    pub backfill_batch_size: i64,
+    pub backfill_hour: i64,
+    pub blocked_extensions: Option<String>,
+    pub blocked_mime_types: Option<String>,
+    pub locale: Option<String>,
+    pub body_size_cap: i64,
+    pub trash_retention_days: i64,
+    /// 终结状态的项目多少天无更新后自动归档（0 表示不自动归档）
+    pub auto_archive_days: i64,
+    /// 增量同步时把服务器上已删除的收件箱邮件标记为墓碑
+    pub mirror_deletions: bool,
+    /// 墓碑邮件的保留天数
+    pub server_deleted_retention_days: i64,
+    /// 显示"今天/昨天"使用的 UTC 偏移（分钟），为空时使用系统时区
+    pub utc_offset_minutes: Option<i64>,
+    /// 邮件翻译服务（none / deepl / local）
+    pub translation_provider: String,
+    /// 本地翻译服务地址
+    pub translation_endpoint: String,
+    /// 搜索结果批量操作的邮件数上限
+    pub bulk_action_cap: i64,
+    /// 每次同步最多自动新建的项目数（0 表示不限）
+    pub max_new_projects_per_sync: i64,
+    pub deleted_project_match: String,
+    pub quiet_hours_enabled: bool,
+    pub quiet_hours_start: String,
+    pub quiet_hours_end: String,
+    pub quiet_hours_days: String,
+    pub quiet_hours_allow_manual: bool,
+    pub max_bandwidth_kbps: i64,
+    pub metered_mode: bool,
+    pub detect_metered: bool,
+    pub generic_subjects: String,
+    pub remote_images: String,
+    pub prefetch_remote_images: bool,
+    pub remote_cache_max_mb: i64,
+    pub proxy_url: String,
+    pub classifier_window_days: i64,
+    pub classifier_min_subject_len: i64,
+    pub classifier_strip_ticket_ids: String,
+    pub cold_storage_path: String,
+    pub classifier_use_labels: bool,
+    pub imap_trace_enabled: bool,
+    pub run_in_background: bool,
+    pub display_name: String,
+    pub project_limit_weekly_emails: i64,
+    pub project_limit_senders: i64,
+    pub project_limit_subjects: i64,
+    /// 监视文件夹（通过 start/stop_watch_folder 修改）
+    pub watch_folder_enabled: bool,
+    pub watch_folder_path: String,
+    pub watch_folder_account_id: Option<i64>,
+    pub watch_folder_delete_processed: bool,
+    pub sync_shared_mailboxes: bool,
+    pub shared_mailbox_auto_create: bool,
+    /// 版本号（更新时需回传）
+    pub version: i64,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -19,27 +86,53 @@ pub struct SyncSettings {
 /// 获取同步设置
 #[tauri::command]
 pub async fn get_sync_settings(
-    pool: State<'_, SqlitePool>,
+    pool: Db,
 ) -> Result<SyncSettings, ErrorResponse> {
     log::info!("Getting sync settings");
 
-    let settings = sqlx::query_as::<_, SyncSettings>(
+    let settings = load_sync_settings(pool.inner())
+        .await
+        .map_err(|e: sqlx::Error| -> ErrorResponse {
+            log::error!("Failed to get sync settings: {}", e);
+            AppError::Database(e).into()
+        })?;
+
+    log::info!("Sync settings retrieved: {:?}", settings);
+    Ok(settings)
+}
+
+pub(crate) async fn load_sync_settings(pool: &SqlitePool) -> Result<SyncSettings, sqlx::Error> {
+    sqlx::query_as::<_, SyncSettings>(
         r#"
         SELECT id, max_sync_count, auto_sync_enabled, sync_interval_minutes, 
-               sync_attachments, created_at, updated_at
+               sync_attachments, show_duplicates, backfill_batch_size, backfill_hour,
+               blocked_extensions, blocked_mime_types, locale,
+               body_size_cap, trash_retention_days, auto_archive_days, deleted_project_match,
+               mirror_deletions, server_deleted_retention_days, utc_offset_minutes,
+               translation_provider, translation_endpoint, bulk_action_cap, max_new_projects_per_sync,
+               quiet_hours_enabled, quiet_hours_start, quiet_hours_end, quiet_hours_days, quiet_hours_allow_manual,
+               max_bandwidth_kbps, metered_mode, detect_metered,
+               generic_subjects,
+               remote_images, prefetch_remote_images, remote_cache_max_mb, proxy_url,
+               classifier_window_days, classifier_min_subject_len, classifier_strip_ticket_ids,
+               cold_storage_path,
+               classifier_use_labels,
+               imap_trace_enabled,
+               run_in_background,
+               display_name,
+               project_limit_weekly_emails,
+               project_limit_senders,
+               project_limit_subjects,
+               watch_folder_enabled, watch_folder_path, watch_folder_account_id, watch_folder_delete_processed,
+               sync_shared_mailboxes, shared_mailbox_auto_create,
+               version,
+               created_at, updated_at
         FROM sync_settings
         WHERE id = 1
         "#
     )
-    .fetch_one(pool.inner())
+    .fetch_one(pool)
     .await
-    .map_err(|e: sqlx::Error| -> ErrorResponse {
-        log::error!("Failed to get sync settings: {}", e);
-        crate::error::AppError::Database(e).into()
-    })?;
-
-    log::info!("Sync settings retrieved: {:?}", settings);
-    Ok(settings)
 }
 
 /// 更新同步设置请求
@@ -49,39 +142,352 @@ pub struct UpdateSyncSettingsRequest {
     pub auto_sync_enabled: bool,
     pub sync_interval_minutes: i64,
     pub sync_attachments: bool,
+    pub show_duplicates: Option<bool>,
+    pub backfill_batch_size: Option<i64>,
+    pub backfill_hour: Option<i64>,
+    pub blocked_extensions: Option<String>,
+    pub blocked_mime_types: Option<String>,
+    pub locale: Option<String>,
+    pub body_size_cap: Option<i64>,
+    pub trash_retention_days: Option<i64>,
+    pub auto_archive_days: Option<i64>,
+    pub mirror_deletions: Option<bool>,
+    pub server_deleted_retention_days: Option<i64>,
+    pub utc_offset_minutes: Option<i64>,
+    pub translation_provider: Option<String>,
+    pub translation_endpoint: Option<String>,
+    pub bulk_action_cap: Option<i64>,
+    pub max_new_projects_per_sync: Option<i64>,
+    pub deleted_project_match: Option<String>,
+    pub quiet_hours_enabled: Option<bool>,
+    pub quiet_hours_start: Option<String>,
+    pub quiet_hours_end: Option<String>,
+    pub quiet_hours_days: Option<String>,
+    pub quiet_hours_allow_manual: Option<bool>,
+    pub max_bandwidth_kbps: Option<i64>,
+    pub metered_mode: Option<bool>,
+    pub detect_metered: Option<bool>,
+    pub generic_subjects: Option<String>,
+    pub remote_images: Option<String>,
+    pub prefetch_remote_images: Option<bool>,
+    pub remote_cache_max_mb: Option<i64>,
+    pub proxy_url: Option<String>,
+    pub classifier_window_days: Option<i64>,
+    pub classifier_min_subject_len: Option<i64>,
+    pub classifier_strip_ticket_ids: Option<String>,
+    pub cold_storage_path: Option<String>,
+    pub classifier_use_labels: Option<bool>,
+    pub imap_trace_enabled: Option<bool>,
+    pub run_in_background: Option<bool>,
+    pub display_name: Option<String>,
+    pub project_limit_weekly_emails: Option<i64>,
+    pub project_limit_senders: Option<i64>,
+    pub project_limit_subjects: Option<i64>,
+    pub sync_shared_mailboxes: Option<bool>,
+    pub shared_mailbox_auto_create: Option<bool>,
+    /// 客户端读取设置时的版本号
+    pub expected_version: i64,
 }
 
 /// 更新同步设置
+///
+/// 版本号不一致（其他窗口已修改）时返回 `CONFLICT`，`details` 中携带当前设置。
 #[tauri::command]
 pub async fn update_sync_settings(
-    pool: State<'_, SqlitePool>,
+    pool: Db,
     request: UpdateSyncSettingsRequest,
-) -> Result<(), ErrorResponse> {
+) -> Result<SyncSettings, ErrorResponse> {
     log::info!("Updating sync settings: {:?}", request);
 
-    sqlx::query(
+    validate_settings_request(&request)?;
+
+    let sql = versioned_update_sql(
+        "sync_settings",
         r#"
-        UPDATE sync_settings
-        SET max_sync_count = ?,
-            auto_sync_enabled = ?,
-            sync_interval_minutes = ?,
-            sync_attachments = ?,
-            updated_at = CURRENT_TIMESTAMP
-        WHERE id = 1
-        "#
-    )
-    .bind(request.max_sync_count)
-    .bind(request.auto_sync_enabled)
-    .bind(request.sync_interval_minutes)
-    .bind(request.sync_attachments)
-    .execute(pool.inner())
-    .await
-    .map_err(|e: sqlx::Error| -> ErrorResponse {
-        log::error!("Failed to update sync settings: {}", e);
-        crate::error::AppError::Database(e).into()
-    })?;
+        max_sync_count = ?,
+        auto_sync_enabled = ?,
+        sync_interval_minutes = ?,
+        sync_attachments = ?,
+        show_duplicates = COALESCE(?, show_duplicates),
+        backfill_batch_size = COALESCE(?, backfill_batch_size),
+        backfill_hour = COALESCE(?, backfill_hour),
+        blocked_extensions = COALESCE(?, blocked_extensions),
+        blocked_mime_types = COALESCE(?, blocked_mime_types),
+        locale = COALESCE(?, locale),
+        body_size_cap = COALESCE(?, body_size_cap),
+        trash_retention_days = COALESCE(?, trash_retention_days),
+        auto_archive_days = COALESCE(?, auto_archive_days),
+        mirror_deletions = COALESCE(?, mirror_deletions),
+        server_deleted_retention_days = COALESCE(?, server_deleted_retention_days),
+        utc_offset_minutes = COALESCE(?, utc_offset_minutes),
+        translation_provider = COALESCE(?, translation_provider),
+        translation_endpoint = COALESCE(?, translation_endpoint),
+        bulk_action_cap = COALESCE(?, bulk_action_cap),
+        max_new_projects_per_sync = COALESCE(?, max_new_projects_per_sync),
+        deleted_project_match = COALESCE(?, deleted_project_match),
+        quiet_hours_enabled = COALESCE(?, quiet_hours_enabled),
+        quiet_hours_start = COALESCE(?, quiet_hours_start),
+        quiet_hours_end = COALESCE(?, quiet_hours_end),
+        quiet_hours_days = COALESCE(?, quiet_hours_days),
+        quiet_hours_allow_manual = COALESCE(?, quiet_hours_allow_manual),
+        max_bandwidth_kbps = COALESCE(?, max_bandwidth_kbps),
+        metered_mode = COALESCE(?, metered_mode),
+        detect_metered = COALESCE(?, detect_metered),
+        generic_subjects = COALESCE(?, generic_subjects),
+        remote_images = COALESCE(?, remote_images),
+        prefetch_remote_images = COALESCE(?, prefetch_remote_images),
+        remote_cache_max_mb = COALESCE(?, remote_cache_max_mb),
+        proxy_url = COALESCE(?, proxy_url),
+        classifier_window_days = COALESCE(?, classifier_window_days),
+        classifier_min_subject_len = COALESCE(?, classifier_min_subject_len),
+        classifier_strip_ticket_ids = COALESCE(?, classifier_strip_ticket_ids),
+        cold_storage_path = COALESCE(?, cold_storage_path),
+        classifier_use_labels = COALESCE(?, classifier_use_labels),
+        imap_trace_enabled = COALESCE(?, imap_trace_enabled),
+        run_in_background = COALESCE(?, run_in_background),
+        display_name = COALESCE(?, display_name),
+        project_limit_weekly_emails = COALESCE(?, project_limit_weekly_emails),
+        project_limit_senders = COALESCE(?, project_limit_senders),
+        project_limit_subjects = COALESCE(?, project_limit_subjects),
+        sync_shared_mailboxes = COALESCE(?, sync_shared_mailboxes),
+        shared_mailbox_auto_create = COALESCE(?, shared_mailbox_auto_create),
+        updated_at = CURRENT_TIMESTAMP
+        "#,
+    );
+
+    let result = sqlx::query(&sql)
+        .bind(request.max_sync_count)
+        .bind(request.auto_sync_enabled)
+        .bind(request.sync_interval_minutes)
+        .bind(request.sync_attachments)
+        .bind(request.show_duplicates)
+        .bind(request.backfill_batch_size)
+        .bind(request.backfill_hour)
+        .bind(&request.blocked_extensions)
+        .bind(&request.blocked_mime_types)
+        .bind(&request.locale)
+        .bind(request.body_size_cap)
+        .bind(request.trash_retention_days)
+        .bind(request.auto_archive_days)
+        .bind(request.mirror_deletions)
+        .bind(request.server_deleted_retention_days)
+        .bind(request.utc_offset_minutes)
+        .bind(&request.translation_provider)
+        .bind(&request.translation_endpoint)
+        .bind(request.bulk_action_cap)
+        .bind(request.max_new_projects_per_sync)
+        .bind(&request.deleted_project_match)
+        .bind(request.quiet_hours_enabled)
+        .bind(&request.quiet_hours_start)
+        .bind(&request.quiet_hours_end)
+        .bind(&request.quiet_hours_days)
+        .bind(request.quiet_hours_allow_manual)
+        .bind(request.max_bandwidth_kbps)
+        .bind(request.metered_mode)
+        .bind(request.detect_metered)
+        .bind(&request.generic_subjects)
+        .bind(&request.remote_images)
+        .bind(request.prefetch_remote_images)
+        .bind(request.remote_cache_max_mb)
+        .bind(&request.proxy_url)
+        .bind(request.classifier_window_days)
+        .bind(request.classifier_min_subject_len)
+        .bind(&request.classifier_strip_ticket_ids)
+        .bind(&request.cold_storage_path)
+        .bind(request.classifier_use_labels)
+        .bind(request.imap_trace_enabled)
+        .bind(request.run_in_background)
+        .bind(&request.display_name)
+        .bind(request.project_limit_weekly_emails)
+        .bind(request.project_limit_senders)
+        .bind(request.project_limit_subjects)
+        .bind(request.sync_shared_mailboxes)
+        .bind(request.shared_mailbox_auto_create)
+        .bind(1_i64)
+        .bind(request.expected_version)
+        .execute(pool.inner())
+        .await
+        .map_err(|e: sqlx::Error| -> ErrorResponse {
+            log::error!("Failed to update sync settings: {}", e);
+            AppError::Database(e).into()
+        })?;
+
+    let settings = load_sync_settings(pool.inner())
+        .await
+        .map_err(|e| -> ErrorResponse { AppError::Database(e).into() })?;
+
+    if let Err(e) = ensure_swapped(result.rows_affected(), "Sync settings", &settings) {
+        log::warn!(
+            "Sync settings update rejected: expected version {}, current {}",
+            request.expected_version, settings.version
+        );
+        return Err(e.into());
+    }
+
+    file_manager::set_cold_storage_root(&settings.cold_storage_path);
+    tray::set_run_in_background(settings.run_in_background);
 
     log::info!("Sync settings updated successfully");
+    Ok(settings)
+}
+
+/// 校验设置取值（更新设置和导入配置共用）
+pub(crate) fn validate_settings_request(request: &UpdateSyncSettingsRequest) -> Result<(), AppError> {
+    validation::at_least("max_sync_count", request.max_sync_count, 1)?;
+    validation::at_least("sync_interval_minutes", request.sync_interval_minutes, 1)?;
+    if let Some(offset) = request.utc_offset_minutes {
+        let limit = i64::from(MAX_UTC_OFFSET_MINUTES);
+        validation::at_least("utc_offset_minutes", offset, -limit)?;
+        validation::at_most("utc_offset_minutes", offset, limit)?;
+    }
+
+    if let Some(policy) = request.deleted_project_match.as_deref() {
+        if !matches!(policy, "restore" | "new") {
+            return Err(AppError::Validation(format!("Invalid deleted project policy: {}", policy)));
+        }
+    }
+
+    if let Some(provider) = request.translation_provider.as_deref() {
+        match ProviderKind::parse(provider) {
+            Some(kind) if kind.available() => {}
+            Some(_) => {
+                return Err(AppError::Validation(format!(
+                    "Translation provider {} is not available in this build",
+                    provider
+                )));
+            }
+            None => return Err(AppError::Validation(format!("Invalid translation provider: {}", provider))),
+        }
+    }
+    if let Some(endpoint) = request.translation_endpoint.as_deref().map(str::trim).filter(|endpoint| !endpoint.is_empty()) {
+        url::Url::parse(endpoint)
+            .map_err(|e| AppError::Validation(format!("Invalid translation endpoint {}: {}", endpoint, e)))?;
+    }
+
+    if let Some(mode) = request.remote_images.as_deref() {
+        if !matches!(mode, "block" | "allow") {
+            return Err(AppError::Validation(format!("Invalid remote images setting: {}", mode)));
+        }
+    }
+    if let Some(proxy) = request.proxy_url.as_deref().map(str::trim).filter(|proxy| !proxy.is_empty()) {
+        url::Url::parse(proxy)
+            .map_err(|e| AppError::Validation(format!("Invalid proxy URL {}: {}", proxy, e)))?;
+    }
+
+    if let Some(patterns) = request.classifier_strip_ticket_ids.as_deref() {
+        ClassifierConfig::validate_patterns(patterns)?;
+    }
+    if let Some(days) = request.classifier_window_days {
+        validation::at_least("classifier_window_days", days, 1)?;
+    }
+    if let Some(len) = request.classifier_min_subject_len {
+        validation::at_least("classifier_min_subject_len", len, 0)?;
+    }
+    if let Some(days) = request.auto_archive_days {
+        validation::at_least("auto_archive_days", days, 0)?;
+    }
+    if let Some(days) = request.server_deleted_retention_days {
+        validation::at_least("server_deleted_retention_days", days, 0)?;
+    }
+    if let Some(cap) = request.bulk_action_cap {
+        validation::at_least("bulk_action_cap", cap, 1)?;
+    }
+    if let Some(cap) = request.max_new_projects_per_sync {
+        validation::at_least("max_new_projects_per_sync", cap, 0)?;
+    }
+    if let Some(offset) = request.utc_offset_minutes {
+        validation::at_least("utc_offset_minutes", offset, -18 * 60)?;
+        validation::at_most("utc_offset_minutes", offset, 18 * 60)?;
+    }
+    // 项目规模上限为 0 表示不限
+    let limits = [
+        ("project_limit_weekly_emails", request.project_limit_weekly_emails),
+        ("project_limit_senders", request.project_limit_senders),
+        ("project_limit_subjects", request.project_limit_subjects),
+    ];
+    for (field, limit) in limits {
+        if let Some(limit) = limit {
+            validation::at_least(field, limit, 0)?;
+        }
+    }
+    if let Some(name) = request.display_name.as_deref() {
+        validation::max_length("display_name", name, validation::MAX_NAME_CHARS)?;
+    }
+
+    quiet_hours::validate(
+        request.quiet_hours_start.as_deref(),
+        request.quiet_hours_end.as_deref(),
+        request.quiet_hours_days.as_deref(),
+    )?;
+
     Ok(())
 }
 
+/// 导出设置配置包（不含密码和 OAuth 令牌）
+#[tauri::command]
+pub async fn export_settings(
+    pool: Db,
+    target_path: String,
+    include_accounts: Option<bool>,
+) -> Result<ProfileExportSummary, ErrorResponse> {
+    SettingsProfileManager::new(pool.inner().clone())
+        .export(&target_path, include_accounts.unwrap_or(false))
+        .await
+        .map_err(Into::into)
+}
+
+/// 导入设置配置包
+///
+/// 新导入的账户列在 `accounts_needing_credentials` 中，前端需要提示重新输入密码或重新授权。
+#[tauri::command]
+pub async fn import_settings(
+    pool: Db,
+    path: String,
+    merge_strategy: Option<MergeStrategy>,
+) -> Result<ProfileImportReport, ErrorResponse> {
+    let report = SettingsProfileManager::new(pool.inner().clone())
+        .import(&path, merge_strategy.unwrap_or(MergeStrategy::KeepExisting))
+        .await
+        .map_err(ErrorResponse::from)?;
+
+    let settings = load_sync_settings(pool.inner())
+        .await
+        .map_err(|e| -> ErrorResponse { AppError::Database(e).into() })?;
+    tray::set_run_in_background(settings.run_in_background);
+
+    Ok(report)
+}
+
+/// 获取数据库 PRAGMA（诊断用）
+#[tauri::command]
+pub async fn get_database_pragmas(
+    pool: Db,
+    app: tauri::AppHandle,
+) -> Result<DatabasePragmas, ErrorResponse> {
+    database::database_pragmas(&app, pool.inner())
+        .await
+        .map_err(|e| ErrorResponse {
+            code: "DB_ERROR".to_string(),
+            message: format!("Failed to read database pragmas: {}", e),
+            details: None,
+        })
+}
+
+/// 保存翻译服务的 API 密钥到系统钥匙串（`api_key` 为空时删除）
+#[tauri::command]
+pub async fn set_translation_api_key(
+    provider: String,
+    api_key: Option<String>,
+) -> Result<(), ErrorResponse> {
+    let kind = ProviderKind::parse(&provider)
+        .ok_or_else(|| AppError::Validation(format!("Invalid translation provider: {}", provider)))?;
+    translate::set_api_key(kind, api_key.as_deref()).map_err(Into::into)
+}
+
+/// 钥匙串中是否保存了翻译服务的 API 密钥（不返回密钥本身）
+#[tauri::command]
+pub async fn has_translation_api_key(provider: String) -> Result<bool, ErrorResponse> {
+    let kind = ProviderKind::parse(&provider)
+        .ok_or_else(|| AppError::Validation(format!("Invalid translation provider: {}", provider)))?;
+    Ok(translate::has_api_key(kind))
+}
@@ -1,11 +1,20 @@
 /// 邮件同步相关命令
 use crate::error::ErrorResponse;
-use crate::events::EventEmitter;
+use crate::events::notifications::SOURCE_SYNC;
+use crate::events::{EventEmitter, NotificationLevel};
+use crate::index_scheduler::quiet_hours::{BackgroundStatus, QuietHours};
+use crate::mail::dry_run::{DryRunReport, SyncDryRun, DEFAULT_DRY_RUN_LIMIT};
 use crate::mail::imap_client::AuthMethod;
-use crate::mail::providers::{detect_provider, get_provider_configs};
-use crate::mail::sync::{EmailSyncer, SyncProgress};
+use crate::mail::pending_oauth::PendingAuthorizations;
+use crate::mail::providers::{detect_provider, get_provider_configs, ProviderConfig};
+use crate::mail::sync::{ActiveSyncs, EmailSyncer, ResetSummary, SyncProgress};
+use crate::mail::sync_checkpoint::{SyncCheckpoint, SyncCheckpointStore};
+use crate::mail::sync_runs::{SyncRun, SyncRunLog, SyncRunMetrics, DEFAULT_SYNC_RUN_LIMIT};
+use crate::repository::ProjectRepository;
+use crate::storage::app_state::{DatabaseExt, Db};
+use crate::utils::validation;
 use sqlx::SqlitePool;
-use tauri::State;
+use tauri::{Manager, State};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -21,12 +30,18 @@ pub struct AddOAuthAccountRequest {
     pub access_token: String,
     pub refresh_token: Option<String>,
     pub expires_in: Option<i64>,
+    /// `start_oauth_flow` 返回的待完成授权 ID
+    #[serde(default)]
+    pub pending_id: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SyncAccountRequest {
     pub email: String,
     pub password: Option<String>,  // 仅用于密码认证
+    /// 是否为定时触发的自动同步（静默时段内总是跳过）
+    #[serde(default)]
+    pub automatic: bool,
 }
 
 /// 前端兼容的 Provider 结构
@@ -39,12 +54,14 @@ pub struct ProviderResponse {
     pub supports_oauth: bool,
 }
 
-/// 重置账户的同步状态（清空所有邮件和项目，重新开始同步）
+/// 重置账户的同步状态（清空该账户的邮件和仅属于该账户的项目，重新开始同步）
 #[tauri::command]
 pub async fn reset_account_sync(
     email: String,
-    pool: State<'_, SqlitePool>,
-) -> Result<(), ErrorResponse> {
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
+) -> Result<ResetSummary, ErrorResponse> {
+    let email = validation::email_address("email", &email)?;
     log::info!("Resetting sync state for account: {}", email);
 
     // 1. 获取账户 ID
@@ -66,41 +83,20 @@ pub async fn reset_account_sync(
         details: None,
     })?.0;
 
-    // 2. 删除该账户的所有项目 (必须在删除邮件之前进行，因为要依据 emails.project_id 查询)
-    sqlx::query("DELETE FROM projects WHERE id IN (SELECT DISTINCT project_id FROM emails WHERE account_id = ?)")
-        .bind(account_id)
-        .execute(pool.inner())
+    // 2. 删除邮件、附件和空项目（同步位置由邮件的 UID 推导，删除邮件即重置同步状态）
+    let summary = EmailSyncer::new(pool.inner().clone(), emitter.inner().clone())
+        .reset_account(account_id)
         .await
-        .map_err(|e| ErrorResponse {
-            code: "DB_ERROR".to_string(),
-            message: format!("Failed to delete projects: {}", e),
-            details: None,
-        })?;
-
-    // 3. 删除该账户的所有邮件
-    sqlx::query("DELETE FROM emails WHERE account_id = ?")
-        .bind(account_id)
-        .execute(pool.inner())
-        .await
-        .map_err(|e| ErrorResponse {
-            code: "DB_ERROR".to_string(),
-            message: format!("Failed to delete emails: {}", e),
-            details: None,
-        })?;
+        .map_err(|e: crate::error::AppError| -> ErrorResponse { e.into() })?;
 
-    // 4. 重置同步状态
-    sqlx::query("UPDATE accounts SET last_synced_uid = 0 WHERE id = ?")
-        .bind(account_id)
-        .execute(pool.inner())
+    // 3. 重新计算共享项目的统计
+    ProjectRepository::new(pool.inner().clone())
+        .recompute_stats(None)
         .await
-        .map_err(|e| ErrorResponse {
-            code: "DB_ERROR".to_string(),
-            message: format!("Failed to reset sync state: {}", e),
-            details: None,
-        })?;
+        .map_err(|e: crate::error::AppError| -> ErrorResponse { e.into() })?;
 
     log::info!("Successfully reset sync state for account {}", email);
-    Ok(())
+    Ok(summary)
 }
 
 /// 获取支持的邮箱服务商列表
@@ -123,9 +119,11 @@ pub async fn get_email_providers() -> Result<Vec<ProviderResponse>, ErrorRespons
 /// 添加邮件账户
 #[tauri::command]
 pub async fn add_email_account(
-    pool: State<'_, SqlitePool>,
+    pool: Db,
+    emitter: State<'_, EventEmitter>,
     request: AddAccountRequest,
 ) -> Result<i64, ErrorResponse> {
+    validation::email_address("email", &request.email)?;
     log::info!("Adding email account: {}", request.email);
 
     // 自动检测服务商
@@ -139,7 +137,7 @@ pub async fn add_email_account(
     log::info!("Detected provider: {}", provider.name);
 
     // 创建同步器
-    let syncer = EmailSyncer::new(pool.inner().clone());
+    let syncer = EmailSyncer::new(pool.inner().clone(), emitter.inner().clone());
 
     // 添加账户
     let account_id = syncer
@@ -153,18 +151,48 @@ pub async fn add_email_account(
 }
 
 /// 添加 OAuth 邮件账户
+///
+/// `pending_id` 为 `start_oauth_flow` 返回的待完成授权，账户创建后删除。
 #[tauri::command]
 pub async fn add_oauth_email_account(
-    pool: State<'_, SqlitePool>,
+    pool: Db,
     request: AddOAuthAccountRequest,
 ) -> Result<i64, ErrorResponse> {
-    log::info!("Adding OAuth email account: {}", request.email);
+    validation::email_address("email", &request.email)?;
+    validation::required("access_token", &request.access_token, usize::MAX)?;
+    let account_id = insert_oauth_account(
+        pool.inner(),
+        &request.email,
+        &request.access_token,
+        request.refresh_token.as_deref(),
+        request.expires_in,
+    )
+    .await?;
+
+    if let Some(pending_id) = request.pending_id {
+        if let Err(e) = PendingAuthorizations::new(pool.inner().clone()).remove(pending_id).await {
+            log::warn!("Failed to remove pending authorization {}: {}", pending_id, e);
+        }
+    }
+
+    Ok(account_id)
+}
+
+/// 插入 OAuth 账户（`add_oauth_email_account` 和恢复待完成授权共用）
+pub(crate) async fn insert_oauth_account(
+    pool: &SqlitePool,
+    email: &str,
+    access_token: &str,
+    refresh_token: Option<&str>,
+    expires_in: Option<i64>,
+) -> Result<i64, ErrorResponse> {
+    log::info!("Adding OAuth email account: {}", email);
 
     // 自动检测服务商
-    let provider_config = detect_provider(&request.email)
+    let provider_config = detect_provider(email)
         .ok_or_else(|| ErrorResponse {
             code: "UNSUPPORTED_PROVIDER".to_string(),
-            message: format!("Unsupported email provider for: {}", request.email),
+            message: format!("Unsupported email provider for: {}", email),
             details: None,
         })?;
 
@@ -179,7 +207,7 @@ pub async fn add_oauth_email_account(
         })?;
 
     // 计算 token 过期时间
-    let expires_at = request.expires_in.map(|exp| {
+    let expires_at = expires_in.map(|exp| {
         chrono::Utc::now().timestamp() + exp
     });
 
@@ -192,13 +220,13 @@ pub async fn add_oauth_email_account(
         ) VALUES (?, ?, ?, 'oauth', ?, ?, ?)
         "#
     )
-    .bind(&request.email)
+    .bind(email)
     .bind(&provider_config.name)
     .bind(&imap_config)
-    .bind(&request.access_token)
-    .bind(&request.refresh_token)
+    .bind(access_token)
+    .bind(refresh_token)
     .bind(expires_at)
-    .execute(pool.inner())
+    .execute(pool)
     .await
     .map_err(|e| ErrorResponse {
         code: "DB_ERROR".to_string(),
@@ -215,12 +243,175 @@ pub async fn add_oauth_email_account(
 /// 同步邮件账户
 #[tauri::command]
 pub async fn sync_email_account(
-    pool: State<'_, SqlitePool>,
     app: tauri::AppHandle,
     request: SyncAccountRequest,
+) -> Result<SyncProgress, ErrorResponse> {
+    run_account_sync(&app, request).await
+}
+
+/// 同步单个账户（同步命令和托盘菜单共用）
+///
+/// 退出流程中拒绝新的同步；托盘中暂停同步后跳过自动同步，手动同步不受影响。
+pub(crate) async fn run_account_sync(
+    app: &tauri::AppHandle,
+    request: SyncAccountRequest,
 ) -> Result<SyncProgress, ErrorResponse> {
     log::info!("Syncing account: {}", request.email);
+    let pool = app.db().map_err(ErrorResponse::from)?;
+    let writer = app.db_writer().map_err(ErrorResponse::from)?;
+    let active_syncs = app.state::<ActiveSyncs>();
+
+    if active_syncs.is_shutting_down() {
+        return Err(ErrorResponse {
+            code: "SHUTTING_DOWN".to_string(),
+            message: "ThreadLine is quitting".to_string(),
+            details: None,
+        });
+    }
+    if request.automatic && active_syncs.is_paused() {
+        return Err(ErrorResponse {
+            code: "SYNC_PAUSED".to_string(),
+            message: "Syncing is paused".to_string(),
+            details: None,
+        });
+    }
+
+    let quiet_hours = QuietHours::load(&pool)
+        .await
+        .map_err(ErrorResponse::from)?;
+    if (request.automatic || !quiet_hours.allow_manual_override) && quiet_hours.is_quiet_now() {
+        let status = quiet_hours.status();
+        return Err(ErrorResponse {
+            code: "QUIET_HOURS".to_string(),
+            message: status.message.clone().unwrap_or_default(),
+            details: serde_json::to_value(&status).ok(),
+        });
+    }
+
+    let (account_id, auth, provider) =
+        resolve_account_auth(&pool, &request.email, request.password).await?;
+
+    if !active_syncs.begin(account_id) {
+        return Err(ErrorResponse {
+            code: "SYNC_IN_PROGRESS".to_string(),
+            message: format!("Account {} is already syncing", request.email),
+            details: None,
+        });
+    }
+
+    // 创建同步器
+    let event_emitter = app.state::<EventEmitter>().inner().clone();
+    let syncer = EmailSyncer::new(writer.0.clone(), event_emitter.clone());
 
+    let result = syncer
+        .sync_account(account_id, auth, &provider)
+        .await;
+
+    active_syncs.finish(account_id);
+
+    // 所有窗口已关闭时，同步结束后再退出应用
+    if app.webview_windows().is_empty() && !active_syncs.any_active() && !active_syncs.is_shutting_down() {
+        log::info!("All windows closed and no sync active, exiting");
+        app.exit(0);
+    }
+
+    let progress = result.map_err(|e: crate::error::AppError| -> ErrorResponse {
+        // 后台同步失败时窗口可能已关闭，写入通知中心
+        event_emitter.emit_notification_from(
+            "Sync failed",
+            &format!("{}: {}", request.email, e),
+            NotificationLevel::Error,
+            Some(SOURCE_SYNC),
+            Some(&format!("account:{}", account_id)),
+        );
+        e.into()
+    })?;
+
+    log::info!("Sync completed: {:?}", progress);
+
+    Ok(progress)
+}
+
+/// 同步演练：下载并解析最多 `limit` 封待同步邮件，不写数据库，返回每封邮件的解析和分类预览
+///
+/// `trace` 为 true 时记录 IMAP 协议跟踪，报告中返回跟踪文件路径。
+#[tauri::command]
+pub async fn sync_account_dry_run(
+    pool: Db,
+    email: String,
+    password: Option<String>,
+    limit: Option<usize>,
+    trace: Option<bool>,
+) -> Result<DryRunReport, ErrorResponse> {
+    let (account_id, auth, provider) = resolve_account_auth(pool.inner(), &email, password).await?;
+
+    SyncDryRun::new(pool.inner().clone())
+        .run(
+            account_id,
+            auth,
+            &provider,
+            limit.unwrap_or(DEFAULT_DRY_RUN_LIMIT),
+            trace.unwrap_or(false),
+        )
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取后台活动状态（静默时段内返回 "paused until 07:00"）
+#[tauri::command]
+pub async fn get_background_status(
+    pool: Db,
+) -> Result<BackgroundStatus, ErrorResponse> {
+    QuietHours::load(pool.inner())
+        .await
+        .map(|quiet_hours| quiet_hours.status())
+        .map_err(Into::into)
+}
+
+/// 获取同步记录（包括每次同步传输的字节数）
+#[tauri::command]
+pub async fn list_sync_runs(
+    pool: Db,
+    account_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<Vec<SyncRun>, ErrorResponse> {
+    SyncRunLog::new(pool.inner().clone())
+        .list(account_id, limit.unwrap_or(DEFAULT_SYNC_RUN_LIMIT))
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取账户最近几次同步的 IMAP 会话指标（按时间先后排列）
+#[tauri::command]
+pub async fn get_sync_metrics(
+    pool: Db,
+    account_id: i64,
+    last_n_runs: Option<i64>,
+) -> Result<Vec<SyncRunMetrics>, ErrorResponse> {
+    SyncRunLog::new(pool.inner().clone())
+        .metrics_series(account_id, last_n_runs.unwrap_or(DEFAULT_SYNC_RUN_LIMIT))
+        .await
+        .map_err(Into::into)
+}
+
+/// 获取账户未完成的首次同步检查点（没有时返回 None）
+#[tauri::command]
+pub async fn get_sync_checkpoint(
+    pool: Db,
+    account_id: i64,
+) -> Result<Option<SyncCheckpoint>, ErrorResponse> {
+    SyncCheckpointStore::new(pool.inner().clone())
+        .load(account_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 根据账户邮箱加载账户 ID、认证方式和服务商配置
+pub(crate) async fn resolve_account_auth(
+    pool: &SqlitePool,
+    email: &str,
+    password: Option<String>,
+) -> Result<(i64, AuthMethod, ProviderConfig), ErrorResponse> {
     // 从数据库获取账户信息
     #[derive(sqlx::FromRow)]
     #[allow(dead_code)]
@@ -237,8 +428,8 @@ pub async fn sync_email_account(
     let account = sqlx::query_as::<_, AccountRow>(
         "SELECT id, email, provider, imap_config, auth_type, password, oauth_access_token FROM accounts WHERE email = ?"
     )
-    .bind(&request.email)
-    .fetch_optional(pool.inner())
+    .bind(email)
+    .fetch_optional(pool)
     .await
     .map_err(|e| ErrorResponse {
         code: "DB_ERROR".to_string(),
@@ -247,7 +438,7 @@ pub async fn sync_email_account(
     })?
     .ok_or_else(|| ErrorResponse {
         code: "ACCOUNT_NOT_FOUND".to_string(),
-        message: format!("Account {} not found", request.email),
+        message: format!("Account {} not found", email),
         details: None,
     })?;
 
@@ -276,7 +467,7 @@ pub async fn sync_email_account(
             }
         }
         "password" => {
-            let password = request.password
+            let password = password
                 .or(account.password)
                 .ok_or_else(|| ErrorResponse {
                     code: "MISSING_PASSWORD".to_string(),
@@ -299,24 +490,13 @@ pub async fn sync_email_account(
         }
     };
 
-    // 创建事件发射器和同步器
-    let event_emitter = EventEmitter::new(app);
-    let syncer = EmailSyncer::with_event_emitter(pool.inner().clone(), event_emitter);
-
-    let progress = syncer
-        .sync_account(account.id, auth, &provider)
-        .await
-        .map_err(|e: crate::error::AppError| -> ErrorResponse { e.into() })?;
-
-    log::info!("Sync completed: {:?}", progress);
-
-    Ok(progress)
+    Ok((account.id, auth, provider))
 }
 
 /// 获取所有邮件账户
 #[tauri::command]
 pub async fn list_email_accounts(
-    pool: State<'_, SqlitePool>,
+    pool: Db,
 ) -> Result<Vec<EmailAccountInfo>, ErrorResponse> {
     #[derive(sqlx::FromRow)]
     struct AccountRow {
@@ -0,0 +1,33 @@
+/// 窗口相关命令
+use crate::error::{AppError, ErrorResponse};
+use crate::repository::ProjectRepository;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+
+/// 在独立窗口中打开项目
+///
+/// 新窗口与主窗口共享同一个数据库连接池和全局状态；
+/// 同一项目重复打开时聚焦已有窗口
+#[tauri::command]
+pub async fn open_project_window(
+    app: AppHandle,
+    repo: ProjectRepository,
+    project_id: i64,
+) -> Result<(), ErrorResponse> {
+    let project = repo.get_by_id(project_id).await?;
+
+    let label = format!("project-{}", project_id);
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    log::info!("Opening project {} in a new window", project_id);
+    WebviewWindowBuilder::new(&app, &label, WebviewUrl::App(format!("projects/{}", project_id).into()))
+        .title(format!("{} - ThreadLine", project.title))
+        .inner_size(1000.0, 760.0)
+        .build()
+        .map_err(|e| AppError::Generic(format!("Failed to open project window: {}", e)))?;
+
+    Ok(())
+}
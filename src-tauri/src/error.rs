@@ -32,10 +32,41 @@ pub enum AppError {
     #[error("Authentication error: {0}")]
     Auth(String),
 
+    /// OAuth 认证被服务商拒绝（已识别原因）
+    #[error("OAuth authentication rejected by {host}: {}", reason.description())]
+    OAuthRejected {
+        reason: crate::mail::oauth_errors::OAuthFailure,
+        host: String,
+        provider_message: Option<String>,
+        status: Option<String>,
+        scope: Option<String>,
+    },
+
+    /// 服务商的登录策略拒绝了密码登录（如 QQ / 网易要求授权码），`remediation` 为处理建议
+    #[error("Sign-in rejected by {}: {remediation}", provider.name())]
+    ProviderPolicy {
+        provider: crate::mail::provider_policy::PolicyProvider,
+        reason: crate::mail::provider_policy::PolicyReason,
+        remediation: String,
+        provider_message: String,
+    },
+
     /// IMAP 错误
     #[error("IMAP error: {0}")]
     Imap(String),
 
+    /// SMTP 发送错误
+    #[error("SMTP error: {0}")]
+    Smtp(String),
+
+    /// 附件总大小超过服务商上限（`files` 为需要移除的附件）
+    #[error("Attachments exceed the {limit} byte limit")]
+    AttachmentsTooLarge {
+        limit: u64,
+        total: u64,
+        files: Vec<crate::mail::outgoing::OversizedAttachment>,
+    },
+
     /// 解析错误
     #[error("Parse error: {0}")]
     Parse(String),
@@ -48,6 +79,10 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    /// 命令参数校验失败（`field` 为出错的字段名，见 `utils::validation`）
+    #[error("Validation error: {message}")]
+    InvalidField { field: String, message: String },
+
     /// 配置错误
     #[error("Config error: {0}")]
     Config(String),
@@ -56,6 +91,30 @@ pub enum AppError {
     #[error("File system error: {0}")]
     FileSystem(String),
 
+    /// 冷存储（外置磁盘）未挂载
+    #[error("Cold storage not mounted: {0}")]
+    ColdStorageUnavailable(String),
+
+    /// 磁盘剩余空间不足
+    #[error("Not enough disk space under {path}: need {needed} bytes, {available} available")]
+    DiskFull {
+        path: String,
+        needed: u64,
+        available: u64,
+    },
+
+    /// 没有选择翻译服务或缺少 API 密钥
+    #[error("Translation not configured: {0}")]
+    TranslationNotConfigured(String),
+
+    /// 批量操作的邮件数超过上限且未确认
+    #[error("Bulk action matches {matched} emails, above the cap of {cap}")]
+    BulkCapExceeded { matched: usize, cap: usize },
+
+    /// 启动尚未完成（数据库仍在初始化）
+    #[error("ThreadLine is still starting")]
+    NotReady,
+
     /// 任务执行错误
     #[error("Task execution error: {0}")]
     TaskExecution(String),
@@ -64,6 +123,13 @@ pub enum AppError {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    /// 并发修改冲突（携带当前数据，供前端重新提示）
+    #[error("Conflict: {message}")]
+    Conflict {
+        message: String,
+        current: serde_json::Value,
+    },
+
     /// 通用错误
     #[error("{0}")]
     Generic(String),
@@ -84,11 +150,21 @@ pub struct ErrorResponse {
 impl From<AppError> for ErrorResponse {
     fn from(err: AppError) -> Self {
         match err {
+            AppError::Database(e) if is_read_only_error(&e) => ErrorResponse {
+                code: "DB_READ_ONLY".to_string(),
+                message: "The database is read-only (archive databases cannot be modified)".to_string(),
+                details: Some(serde_json::json!({ "cause": e.to_string() })),
+            },
             AppError::Database(e) => ErrorResponse {
                 code: "DB_ERROR".to_string(),
                 message: e.to_string(),
                 details: None,
             },
+            AppError::Io(e) if e.kind() == std::io::ErrorKind::StorageFull => ErrorResponse {
+                code: "DISK_FULL".to_string(),
+                message: e.to_string(),
+                details: None,
+            },
             AppError::Io(e) => ErrorResponse {
                 code: "FS_IO_ERROR".to_string(),
                 message: e.to_string(),
@@ -104,6 +180,25 @@ impl From<AppError> for ErrorResponse {
                 message: e.clone(),
                 details: None,
             },
+            AppError::OAuthRejected { reason, host, provider_message, status, scope } => ErrorResponse {
+                code: reason.code().to_string(),
+                message: reason.description().to_string(),
+                details: Some(serde_json::json!({
+                    "host": host,
+                    "provider_message": provider_message,
+                    "status": status,
+                    "scope": scope,
+                })),
+            },
+            AppError::ProviderPolicy { provider, reason, remediation, provider_message } => ErrorResponse {
+                code: "PROVIDER_POLICY".to_string(),
+                message: remediation,
+                details: Some(serde_json::json!({
+                    "provider": provider,
+                    "reason": reason,
+                    "provider_message": provider_message,
+                })),
+            },
             AppError::ProjectNotFound { id } => ErrorResponse {
                 code: "PROJECT_NOT_FOUND".to_string(),
                 message: format!("Project with id {} not found", id),
@@ -124,6 +219,24 @@ impl From<AppError> for ErrorResponse {
                 message: msg,
                 details: None,
             },
+            AppError::Smtp(msg) => ErrorResponse {
+                code: "NET_SMTP_ERROR".to_string(),
+                message: msg,
+                details: None,
+            },
+            AppError::AttachmentsTooLarge { limit, total, files } => ErrorResponse {
+                code: "ATTACHMENTS_TOO_LARGE".to_string(),
+                message: format!(
+                    "Attachments exceed the {} limit, remove: {}",
+                    crate::mail::outgoing::format_size(limit),
+                    files
+                        .iter()
+                        .map(|file| format!("{} ({})", file.filename, crate::mail::outgoing::format_size(file.size)))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                details: Some(serde_json::json!({ "limit": limit, "total": total, "files": files })),
+            },
             AppError::Parse(msg) => ErrorResponse {
                 code: "PARSE_ERROR".to_string(),
                 message: msg,
@@ -139,6 +252,11 @@ impl From<AppError> for ErrorResponse {
                 message: msg,
                 details: None,
             },
+            AppError::InvalidField { field, message } => ErrorResponse {
+                code: "VAL_ERROR".to_string(),
+                message,
+                details: Some(serde_json::json!({ "field": field })),
+            },
             AppError::Config(msg) => ErrorResponse {
                 code: "CONFIG_ERROR".to_string(),
                 message: msg,
@@ -149,6 +267,31 @@ impl From<AppError> for ErrorResponse {
                 message: msg,
                 details: None,
             },
+            AppError::ColdStorageUnavailable(path) => ErrorResponse {
+                code: "COLD_STORAGE_NOT_MOUNTED".to_string(),
+                message: format!("Cold storage not mounted: {}", path),
+                details: Some(serde_json::json!({ "path": path })),
+            },
+            AppError::DiskFull { path, needed, available } => ErrorResponse {
+                code: "DISK_FULL".to_string(),
+                message: format!("Not enough disk space under {}", path),
+                details: Some(serde_json::json!({ "path": path, "needed": needed, "available": available })),
+            },
+            AppError::TranslationNotConfigured(msg) => ErrorResponse {
+                code: "TRANSLATION_NOT_CONFIGURED".to_string(),
+                message: msg,
+                details: None,
+            },
+            AppError::BulkCapExceeded { matched, cap } => ErrorResponse {
+                code: "BULK_CAP_EXCEEDED".to_string(),
+                message: format!("{} emails match, above the cap of {}; confirm to continue", matched, cap),
+                details: Some(serde_json::json!({ "matched": matched, "cap": cap })),
+            },
+            AppError::NotReady => ErrorResponse {
+                code: "NOT_READY".to_string(),
+                message: "ThreadLine is still starting, try again shortly".to_string(),
+                details: None,
+            },
             AppError::TaskExecution(msg) => ErrorResponse {
                 code: "TASK_ERROR".to_string(),
                 message: msg,
@@ -159,6 +302,11 @@ impl From<AppError> for ErrorResponse {
                 message: e.to_string(),
                 details: None,
             },
+            AppError::Conflict { message, current } => ErrorResponse {
+                code: "CONFLICT".to_string(),
+                message,
+                details: Some(current),
+            },
             AppError::Generic(msg) => ErrorResponse {
                 code: "GENERIC_ERROR".to_string(),
                 message: msg,
@@ -168,6 +316,15 @@ impl From<AppError> for ErrorResponse {
     }
 }
 
+/// SQLite 只读错误（SQLITE_READONLY 及其扩展码），写入只读归档时出现
+fn is_read_only_error(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|db| db.code())
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| code & 0xff == 8)
+}
+
 /// 用于 Tokio task join 错误
 impl From<tokio::task::JoinError> for AppError {
     fn from(err: tokio::task::JoinError) -> Self {
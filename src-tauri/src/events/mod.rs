@@ -1,9 +1,19 @@
 /// 应用事件系统模块
 /// 
-/// 提供统一的事件发送接口，用于后台任务进度通知
+/// 提供统一的事件发送接口，用于后台任务进度通知。
+/// `EventEmitter` 是可以廉价克隆的句柄，在 `setup` 中注册为托管状态，后台服务持有一份即可向前端发送事件；
+/// 没有界面的场景（同步演练、测试）使用 `EventEmitter::noop()`。
 use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use crate::storage::app_state::DatabaseExt;
 use tauri::{AppHandle, Emitter};
 
+pub mod notifications;
+pub mod throttle;
+
+use throttle::ProgressThrottle;
+
 /// 同步进度事件
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncProgressEvent {
@@ -62,58 +72,315 @@ pub enum IndexStatus {
     Failed,
 }
 
+/// 导出进度事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportProgressEvent {
+    pub export_type: String, // "project_report", ...
+    pub target_id: i64,
+    pub current: usize,
+    pub total: usize,
+    pub status: ExportStatus,
+}
+
+/// 导出状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    Starting,
+    Writing,
+    Completed,
+    Failed,
+}
+
+/// 新邮件事件（同步保存了新邮件后发送）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewEmailsEvent {
+    pub account_id: i64,
+    pub count: usize,
+    pub email_ids: Vec<i64>,
+}
+
+/// 项目更新事件（同步把新邮件分类到项目后，每次同步每个项目发送一次）
+///
+/// 前端只重新获取正在查看的这个项目的时间线。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectUpdatedEvent {
+    pub project_id: i64,
+    pub new_email_count: usize,
+    /// 新邮件中最新一封的日期
+    pub latest_event_at: Option<String>,
+}
+
+/// 项目创建事件（分类器为新邮件创建了项目，列表视图据此插入）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectCreatedEvent {
+    pub project_id: i64,
+}
+
+/// 后台任务进度事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobProgressEvent {
+    pub job: String, // "backfill", "integrity_check", ...
+    pub current: usize,
+    pub total: usize,
+    pub status: JobStatus,
+}
+
+/// 后台任务状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Starting,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// 提醒到期事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReminderDueEvent {
+    pub project_id: i64,
+    pub milestone_id: Option<i64>,
+    pub title: String,
+    pub due_at: String,
+}
+
+/// 前端导航事件（deep link 等）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NavigateEvent {
+    pub route: String,
+    pub project_id: Option<i64>,
+}
+
+/// 启动阶段进度事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupProgressEvent {
+    pub phase: String, // "database", "settings", "background"
+    pub current: usize,
+    pub total: usize,
+    pub status: StartupStatus,
+    pub error: Option<String>,
+}
+
+/// 启动阶段状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// 应用就绪事件（数据库可用，之前返回 NOT_READY 的命令可以重试）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppReadyEvent {
+    pub elapsed_ms: u64,
+}
+
+/// 事件发送目标
+pub trait EventSink: Send + Sync {
+    fn emit_json(&self, event: &str, payload: serde_json::Value) -> Result<(), String>;
+
+    /// 保存通知使用的数据库连接池（没有时只发送事件）
+    fn pool(&self) -> Option<SqlitePool>;
+}
+
+impl EventSink for AppHandle {
+    fn emit_json(&self, event: &str, payload: serde_json::Value) -> Result<(), String> {
+        self.emit(event, payload).map_err(|e| e.to_string())
+    }
+
+    fn pool(&self) -> Option<SqlitePool> {
+        self.db().ok()
+    }
+}
+
+/// 丢弃所有事件
+pub struct NoopSink;
+
+impl EventSink for NoopSink {
+    fn emit_json(&self, _event: &str, _payload: serde_json::Value) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn pool(&self) -> Option<SqlitePool> {
+        None
+    }
+}
+
 /// 事件发射器
 /// 
 /// 提供类型安全的事件发送接口
+#[derive(Clone)]
 pub struct EventEmitter {
-    app_handle: AppHandle,
+    sink: Arc<dyn EventSink>,
+    /// 进度事件节流（克隆之间共享）
+    throttle: Arc<ProgressThrottle>,
 }
 
 impl EventEmitter {
     pub fn new(app_handle: AppHandle) -> Self {
-        Self { app_handle }
+        Self::with_sink(Arc::new(app_handle))
+    }
+
+    pub fn with_sink(sink: Arc<dyn EventSink>) -> Self {
+        Self {
+            sink,
+            throttle: Arc::default(),
+        }
+    }
+
+    /// 不发送任何事件的发射器
+    pub fn noop() -> Self {
+        Self::with_sink(Arc::new(NoopSink))
     }
 
-    /// 发送同步进度事件
+    fn emit<T: Serialize>(&self, event: &str, payload: &T, label: &str) {
+        let result = serde_json::to_value(payload)
+            .map_err(|e| e.to_string())
+            .and_then(|payload| self.sink.emit_json(event, payload));
+        if let Err(e) = result {
+            log::warn!("Failed to emit {} event: {}", label, e);
+        }
+    }
+
+    /// 发送同步进度事件（按账户节流，见 `throttle`）
     pub fn emit_sync_progress(&self, event: SyncProgressEvent) {
-        if let Err(e) = self.app_handle.emit("sync-progress", &event) {
-            log::warn!("Failed to emit sync progress event: {}", e);
+        let is_final = matches!(event.status, SyncStatus::Completed | SyncStatus::Failed) || event.current >= event.total;
+        let status = format!("{:?}", event.status);
+        if self.throttle.should_emit("sync-progress", event.account_id.to_string(), &status, is_final) {
+            self.emit("sync-progress", &event, "sync progress");
         }
     }
 
-    /// 发送 OCR 进度事件
+    /// 发送 OCR 进度事件（按附件节流）
     pub fn emit_ocr_progress(&self, event: OcrProgressEvent) {
-        if let Err(e) = self.app_handle.emit("ocr-progress", &event) {
-            log::warn!("Failed to emit OCR progress event: {}", e);
+        let is_final = matches!(event.status, OcrStatus::Completed | OcrStatus::Failed) || event.current >= event.total;
+        let status = format!("{:?}", event.status);
+        if self.throttle.should_emit("ocr-progress", event.attachment_id.to_string(), &status, is_final) {
+            self.emit("ocr-progress", &event, "OCR progress");
         }
     }
 
-    /// 发送索引构建进度事件
+    /// 发送索引构建进度事件（按索引类型节流）
     pub fn emit_index_progress(&self, event: IndexProgressEvent) {
-        if let Err(e) = self.app_handle.emit("index-progress", &event) {
-            log::warn!("Failed to emit index progress event: {}", e);
+        let is_final = matches!(event.status, IndexStatus::Completed | IndexStatus::Failed) || event.current >= event.total;
+        let status = format!("{:?}", event.status);
+        if self.throttle.should_emit("index-progress", event.index_type.clone(), &status, is_final) {
+            self.emit("index-progress", &event, "index progress");
         }
     }
 
+    /// 发送导出进度事件
+    pub fn emit_export_progress(&self, event: ExportProgressEvent) {
+        self.emit("export-progress", &event, "export progress");
+    }
+
+    /// 发送新邮件事件
+    pub fn emit_new_emails(&self, event: NewEmailsEvent) {
+        self.emit("new-emails", &event, "new emails");
+    }
+
+    /// 发送项目更新事件
+    pub fn emit_project_updated(&self, event: ProjectUpdatedEvent) {
+        self.emit("project-updated", &event, "project updated");
+    }
+
+    /// 发送项目创建事件
+    pub fn emit_project_created(&self, event: ProjectCreatedEvent) {
+        self.emit("project-created", &event, "project created");
+    }
+
+    /// 发送后台任务进度事件
+    pub fn emit_job_progress(&self, event: JobProgressEvent) {
+        self.emit("job-progress", &event, "job progress");
+    }
+
+    /// 发送提醒到期事件
+    pub fn emit_reminder_due(&self, event: ReminderDueEvent) {
+        self.emit("reminder-due", &event, "reminder due");
+    }
+
+    /// 发送启动阶段进度事件
+    pub fn emit_startup_progress(&self, event: StartupProgressEvent) {
+        self.emit("startup-progress", &event, "startup progress");
+    }
+
+    /// 发送应用就绪事件
+    pub fn emit_app_ready(&self, event: AppReadyEvent) {
+        self.emit("app-ready", &event, "app ready");
+    }
+
+    /// 发送前端导航事件
+    pub fn emit_navigate(&self, event: NavigateEvent) {
+        self.emit("navigate", &event, "navigate");
+    }
+
     /// 发送通用通知事件
     pub fn emit_notification(&self, title: &str, message: &str, level: NotificationLevel) {
-        let event = NotificationEvent {
+        self.emit_notification_from(title, message, level, None, None);
+    }
+
+    /// 发送通知事件并写入通知表
+    ///
+    /// 先保存再发送，事件中带有通知 ID，前端可据此标记已读。
+    pub fn emit_notification_from(
+        &self,
+        title: &str,
+        message: &str,
+        level: NotificationLevel,
+        source: Option<&str>,
+        related_entity: Option<&str>,
+    ) {
+        let mut event = NotificationEvent {
+            id: None,
             title: title.to_string(),
             message: message.to_string(),
             level,
+            source: source.map(str::to_string),
+            related_entity: related_entity.map(str::to_string),
         };
-        if let Err(e) = self.app_handle.emit("notification", &event) {
-            log::warn!("Failed to emit notification event: {}", e);
-        }
+
+        let Some(pool) = self.sink.pool() else {
+            self.emit_notification_event(&event);
+            return;
+        };
+
+        let emitter = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let store = notifications::NotificationStore::new(pool);
+            match store
+                .insert(
+                    &event.title,
+                    &event.message,
+                    &event.level,
+                    event.source.as_deref(),
+                    event.related_entity.as_deref(),
+                )
+                .await
+            {
+                Ok(id) => event.id = Some(id),
+                Err(e) => log::warn!("Failed to persist notification: {}", e),
+            }
+            emitter.emit_notification_event(&event);
+        });
+    }
+
+    fn emit_notification_event(&self, event: &NotificationEvent) {
+        self.emit("notification", event, "notification");
     }
 }
 
 /// 通知事件
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotificationEvent {
+    /// 通知表中的 ID（保存失败时为空）
+    pub id: Option<i64>,
     pub title: String,
     pub message: String,
     pub level: NotificationLevel,
+    pub source: Option<String>,
+    pub related_entity: Option<String>,
 }
 
 /// 通知级别
@@ -126,3 +393,14 @@ pub enum NotificationLevel {
     Error,
 }
 
+impl NotificationLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationLevel::Info => "info",
+            NotificationLevel::Success => "success",
+            NotificationLevel::Warning => "warning",
+            NotificationLevel::Error => "error",
+        }
+    }
+}
+
@@ -0,0 +1,124 @@
+/// 通知持久化
+///
+/// `EventEmitter` 发出的通知同时写入 `notifications` 表，
+/// 没有窗口打开时发生的后台事件也能在通知中心查看。
+use crate::error::AppError;
+use crate::events::NotificationLevel;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 通知来源
+pub const SOURCE_SYNC: &str = "sync";
+pub const SOURCE_ATTACHMENT_REPAIR: &str = "attachment_repair";
+pub const SOURCE_DEEP_LINK: &str = "deep_link";
+pub const SOURCE_PROJECT_LIFECYCLE: &str = "project_lifecycle";
+pub const SOURCE_BULK_ACTION: &str = "bulk_action";
+pub const SOURCE_VIP: &str = "vip";
+
+/// 通知列表的默认数量
+pub const DEFAULT_NOTIFICATION_LIMIT: i64 = 100;
+
+/// 已保存的通知
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Notification {
+    pub id: i64,
+    pub title: String,
+    pub message: String,
+    pub level: String,
+    pub source: Option<String>,
+    /// 关联实体（如 "project:42"、"account:3"）
+    pub related_entity: Option<String>,
+    pub read: bool,
+    pub created_at: String,
+}
+
+/// 通知存储
+pub struct NotificationStore {
+    pool: SqlitePool,
+}
+
+impl NotificationStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 保存通知，返回 ID
+    pub async fn insert(
+        &self,
+        title: &str,
+        message: &str,
+        level: &NotificationLevel,
+        source: Option<&str>,
+        related_entity: Option<&str>,
+    ) -> Result<i64, AppError> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO notifications (title, message, level, source, related_entity)
+            VALUES (?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(title)
+        .bind(message)
+        .bind(level.as_str())
+        .bind(source)
+        .bind(related_entity)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    /// 获取通知（最新的在前）
+    pub async fn list(&self, unread_only: bool, limit: i64) -> Result<Vec<Notification>, AppError> {
+        let notifications = sqlx::query_as::<_, Notification>(
+            r#"
+            SELECT id, title, message, level, source, related_entity, read,
+                   COALESCE(created_at, '') AS created_at
+            FROM notifications
+            WHERE ? = 0 OR read = 0
+            ORDER BY id DESC
+            LIMIT ?
+            "#
+        )
+        .bind(unread_only)
+        .bind(limit.clamp(1, 1000))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(notifications)
+    }
+
+    /// 标记为已读
+    pub async fn mark_read(&self, id: i64) -> Result<(), AppError> {
+        sqlx::query("UPDATE notifications SET read = 1 WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 全部标记为已读，返回更新的数量
+    pub async fn mark_all_read(&self) -> Result<u64, AppError> {
+        let updated = sqlx::query("UPDATE notifications SET read = 1 WHERE read = 0")
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(updated)
+    }
+
+    /// 删除早于指定时间的通知（未指定时删除全部），返回删除的数量
+    pub async fn clear(&self, older_than: Option<&str>) -> Result<u64, AppError> {
+        let deleted = sqlx::query(
+            "DELETE FROM notifications WHERE ? IS NULL OR datetime(created_at) < datetime(?)"
+        )
+        .bind(older_than)
+        .bind(older_than)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        log::info!("Cleared {} notifications older than {:?}", deleted, older_than);
+        Ok(deleted)
+    }
+}
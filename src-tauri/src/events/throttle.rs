@@ -0,0 +1,117 @@
+/// 进度事件节流
+///
+/// 本地快速同步时每封邮件一个进度事件，每秒上百个事件会塞满 IPC 通道，界面跟着抖动。
+/// 按（事件类型，对象）合并进度事件，每个键每 `MIN_INTERVAL` 最多发送一次；第一个事件、最后一个事件
+/// （进度到达总数或结束状态）和状态变化总是立即发送。只记录时间戳，不启动定时器，
+/// 因此被合并掉的中间进度不会补发，最终值由最后一个事件带到前端。
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 同一个键两次发送之间的最短间隔（约每秒 5 次）
+pub const MIN_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 一个键上次发送的状态
+struct KeyState {
+    sent_at: Instant,
+    status: String,
+}
+
+/// 进度事件节流器（`EventEmitter` 的各个克隆共用一个）
+#[derive(Default)]
+pub struct ProgressThrottle {
+    keys: Mutex<HashMap<(&'static str, String), KeyState>>,
+}
+
+impl ProgressThrottle {
+    /// 判断这次进度是否需要发送
+    ///
+    /// `status` 变化或 `is_final` 时总是发送；结束后清除该键，下一轮的第一个事件也会立即发送。
+    pub fn should_emit(&self, event: &'static str, key: String, status: &str, is_final: bool) -> bool {
+        let now = Instant::now();
+        let mut keys = self.keys.lock().unwrap();
+        let key = (event, key);
+        if is_final {
+            keys.remove(&key);
+            return true;
+        }
+
+        match keys.get_mut(&key) {
+            Some(state) if state.status == status && now.duration_since(state.sent_at) < MIN_INTERVAL => false,
+            Some(state) => {
+                state.sent_at = now;
+                state.status = status.to_string();
+                true
+            }
+            None => {
+                keys.insert(key, KeyState { sent_at: now, status: status.to_string() });
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{EventEmitter, EventSink, SyncProgressEvent, SyncStatus};
+    use sqlx::SqlitePool;
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    /// 记录发送到前端的事件
+    #[derive(Default)]
+    struct CountingSink {
+        events: Mutex<Vec<(String, serde_json::Value)>>,
+    }
+
+    impl EventSink for CountingSink {
+        fn emit_json(&self, event: &str, payload: serde_json::Value) -> Result<(), String> {
+            self.events.lock().unwrap().push((event.to_string(), payload));
+            Ok(())
+        }
+
+        fn pool(&self) -> Option<SqlitePool> {
+            None
+        }
+    }
+
+    #[test]
+    fn thousand_progress_events_are_coalesced_and_keep_the_final_value() {
+        let sink = Arc::new(CountingSink::default());
+        let emitter = EventEmitter::with_sink(sink.clone());
+        // 命令中克隆的发射器共用同一个节流器
+        let clone = emitter.clone();
+
+        let started = Instant::now();
+        for current in 1..=1000 {
+            let target = if current % 2 == 0 { &emitter } else { &clone };
+            target.emit_sync_progress(SyncProgressEvent {
+                account_id: 1,
+                current,
+                total: 1000,
+                status: SyncStatus::Syncing,
+            });
+        }
+        let elapsed = started.elapsed();
+
+        let events = sink.events.lock().unwrap();
+        // 第一个事件、每个间隔最多一个、最后一个
+        let bound = 2 + (elapsed.as_millis() / super::MIN_INTERVAL.as_millis()) as usize + 1;
+        assert!(events.len() <= bound, "{} events emitted in {:?}", events.len(), elapsed);
+        assert_eq!(events.first().unwrap().1["current"], 1);
+        assert_eq!(events.last().unwrap().1["current"], 1000);
+        assert!(events.iter().all(|(event, _)| event == "sync-progress"));
+    }
+
+    #[test]
+    fn status_change_and_other_keys_are_not_suppressed() {
+        let throttle = super::ProgressThrottle::default();
+        assert!(throttle.should_emit("sync-progress", "1".to_string(), "Syncing", false));
+        assert!(!throttle.should_emit("sync-progress", "1".to_string(), "Syncing", false));
+        assert!(throttle.should_emit("sync-progress", "2".to_string(), "Syncing", false));
+        assert!(throttle.should_emit("sync-progress", "1".to_string(), "Starting", false));
+        assert!(throttle.should_emit("sync-progress", "1".to_string(), "Starting", true));
+        // 结束后的下一轮立即发送
+        assert!(throttle.should_emit("sync-progress", "1".to_string(), "Starting", false));
+    }
+}
@@ -0,0 +1,380 @@
+/// 项目导出为 .zip（可选 AES-256 加密）
+///
+/// 压缩包内容：
+/// - `project.json`：项目、里程碑、邮件和附件记录
+/// - `attachments/`：附件文件
+/// - `manifest.json`：每个文件的 SHA-256，附件同时给出数据库中保存的 `content_hash` 和是否一致，
+///   接收方据此确认内容没有被改动
+///
+/// 设置密码时所有条目（包括两个 JSON）都用 AES-256 加密。zip 格式的目录中文件名不加密，
+/// 因此加密导出的附件以 `attachments/{附件 ID}.{扩展名}` 命名，原文件名只记录在加密的 `project.json` 中。
+/// 密码只在内存中传给 zip 写入器，不记录日志，也不保存。
+use crate::error::AppError;
+use crate::events::{EventEmitter, ExportProgressEvent, ExportStatus};
+use crate::mail::sync::{extract_file_extension, sanitize_filename};
+use crate::project::Project;
+use crate::repository::ProjectRepository;
+use crate::storage::file_manager;
+use crate::utils::validation;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{AesMode, CompressionMethod, ZipWriter};
+
+/// 导出密码的最短长度（字符）
+pub const MIN_PASSWORD_CHARS: usize = 8;
+
+/// 每写入多少个附件发送一次进度事件
+const PROGRESS_INTERVAL: usize = 20;
+
+/// 导出结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectArchiveSummary {
+    pub path: String,
+    pub encrypted: bool,
+    pub email_count: usize,
+    pub attachment_count: usize,
+    /// 文件缺失而未能打包的附件
+    pub missing_attachments: Vec<String>,
+    /// 文件内容与保存的哈希不一致的附件（仍然打包，manifest 中标记）
+    pub hash_mismatches: Vec<String>,
+}
+
+/// `project.json`
+#[derive(Serialize)]
+struct ArchiveContents {
+    format_version: u32,
+    exported_at: String,
+    project: Project,
+    milestones: Vec<ArchiveMilestone>,
+    emails: Vec<ArchiveEmail>,
+    attachments: Vec<ArchiveAttachment>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+struct ArchiveMilestone {
+    id: i64,
+    date: Option<String>,
+    title: Option<String>,
+    r#type: Option<String>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+struct ArchiveEmail {
+    id: i64,
+    message_id: String,
+    thread_id: Option<String>,
+    date: Option<String>,
+    sender: Option<String>,
+    recipients: Option<String>,
+    cc: Option<String>,
+    subject: Option<String>,
+    body_text: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ArchiveAttachment {
+    id: i64,
+    email_id: i64,
+    filename: String,
+    file_size: Option<i64>,
+    content_hash: Option<String>,
+    /// 压缩包中的路径，文件缺失时为空
+    archive_path: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct AttachmentRow {
+    id: i64,
+    email_id: i64,
+    filename: Option<String>,
+    file_size: Option<i64>,
+    file_path: Option<String>,
+    content_hash: Option<String>,
+}
+
+/// `manifest.json` 中的一个文件
+#[derive(Serialize)]
+struct ManifestEntry {
+    path: String,
+    size: u64,
+    sha256: String,
+    /// 数据库中保存的附件哈希
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matches_content_hash: Option<bool>,
+}
+
+/// 待打包的附件文件
+struct PendingFile {
+    source: PathBuf,
+    archive_path: String,
+    label: String,
+    content_hash: Option<String>,
+}
+
+/// 项目压缩包导出
+pub struct ProjectArchiveExporter {
+    pool: SqlitePool,
+    event_emitter: Option<EventEmitter>,
+}
+
+impl ProjectArchiveExporter {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            event_emitter: None,
+        }
+    }
+
+    pub fn with_event_emitter(pool: SqlitePool, emitter: EventEmitter) -> Self {
+        Self {
+            pool,
+            event_emitter: Some(emitter),
+        }
+    }
+
+    fn emit_progress(&self, project_id: i64, current: usize, total: usize, status: ExportStatus) {
+        if let Some(emitter) = &self.event_emitter {
+            emitter.emit_export_progress(ExportProgressEvent {
+                export_type: "project_archive".to_string(),
+                target_id: project_id,
+                current,
+                total,
+                status,
+            });
+        }
+    }
+
+    /// 导出项目到 `target_path`，`password` 不为空时加密整个压缩包
+    pub async fn export(
+        &self,
+        project_id: i64,
+        target_path: &str,
+        password: Option<&str>,
+    ) -> Result<ProjectArchiveSummary, AppError> {
+        let password = password.filter(|password| !password.is_empty());
+        if let Some(password) = password {
+            validation::min_length("password", password, MIN_PASSWORD_CHARS)?;
+        }
+        let encrypted = password.is_some();
+
+        let project = ProjectRepository::new(self.pool.clone()).get_by_id(project_id).await?;
+        let milestones = sqlx::query_as::<_, ArchiveMilestone>(
+            "SELECT id, date, title, type FROM milestones WHERE project_id = ? ORDER BY julianday(date), id"
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let emails = sqlx::query_as::<_, ArchiveEmail>(
+            r#"
+            SELECT id, message_id, thread_id, date, sender, recipients, cc, subject, body_text
+            FROM emails
+            WHERE project_id = ?
+            ORDER BY julianday(date), id
+            "#
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let rows = sqlx::query_as::<_, AttachmentRow>(
+            r#"
+            SELECT a.id, a.email_id, a.filename, a.file_size, a.file_path, a.content_hash
+            FROM attachments a
+            JOIN emails e ON e.id = a.email_id
+            WHERE e.project_id = ?
+            ORDER BY a.email_id, a.id
+            "#
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut attachments = Vec::with_capacity(rows.len());
+        let mut files = Vec::new();
+        let mut missing_attachments = Vec::new();
+        let mut used_paths = HashSet::new();
+        for row in rows {
+            let filename = row.filename.unwrap_or_else(|| format!("attachment-{}", row.id));
+            let source = row
+                .file_path
+                .as_deref()
+                .and_then(|path| file_manager::resolve_attachment_path(path).ok())
+                .filter(|path| path.is_file());
+            let archive_path = source.as_ref().map(|_| {
+                let path = if encrypted {
+                    format!("attachments/{}.{}", row.id, extract_file_extension(&filename))
+                } else {
+                    format!("attachments/{}/{}", row.email_id, sanitize_filename(&filename))
+                };
+                // 同一封邮件中的同名附件加上附件 ID 区分
+                if used_paths.insert(path.clone()) {
+                    path
+                } else {
+                    let path = format!("attachments/{}/{}_{}", row.email_id, row.id, sanitize_filename(&filename));
+                    used_paths.insert(path.clone());
+                    path
+                }
+            });
+
+            match (source, &archive_path) {
+                (Some(source), Some(archive_path)) => files.push(PendingFile {
+                    source,
+                    archive_path: archive_path.clone(),
+                    label: filename.clone(),
+                    content_hash: row.content_hash.clone(),
+                }),
+                _ => missing_attachments.push(filename.clone()),
+            }
+            attachments.push(ArchiveAttachment {
+                id: row.id,
+                email_id: row.email_id,
+                filename,
+                file_size: row.file_size,
+                content_hash: row.content_hash,
+                archive_path,
+            });
+        }
+
+        let email_count = emails.len();
+        let attachment_count = files.len();
+        let contents = ArchiveContents {
+            format_version: 1,
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            project,
+            milestones,
+            emails,
+            attachments,
+        };
+        let project_json = serde_json::to_vec_pretty(&contents)?;
+
+        log::info!(
+            "Exporting project {} to {} ({} emails, {} attachments, encrypted: {})",
+            project_id, target_path, email_count, attachment_count, encrypted
+        );
+        self.emit_progress(project_id, 0, attachment_count, ExportStatus::Starting);
+
+        // 先写入临时文件，完成后再改名，失败时不留下不完整的压缩包
+        let target = PathBuf::from(target_path);
+        let partial = target.with_extension("zip.part");
+        let password = password.map(str::to_string);
+        let emitter = self.event_emitter.clone();
+        let write_partial = partial.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            write_archive(&write_partial, &project_json, &files, password.as_deref(), |current| {
+                if current % PROGRESS_INTERVAL == 0 {
+                    if let Some(emitter) = &emitter {
+                        emitter.emit_export_progress(ExportProgressEvent {
+                            export_type: "project_archive".to_string(),
+                            target_id: project_id,
+                            current,
+                            total: attachment_count,
+                            status: ExportStatus::Writing,
+                        });
+                    }
+                }
+            })
+        })
+        .await?;
+
+        let hash_mismatches = match result {
+            Ok(mismatches) => mismatches,
+            Err(e) => {
+                let _ = std::fs::remove_file(&partial);
+                self.emit_progress(project_id, 0, attachment_count, ExportStatus::Failed);
+                return Err(e);
+            }
+        };
+        tokio::fs::rename(&partial, &target).await?;
+        if !hash_mismatches.is_empty() {
+            log::warn!("{} attachments of project {} do not match their stored hash", hash_mismatches.len(), project_id);
+        }
+        self.emit_progress(project_id, attachment_count, attachment_count, ExportStatus::Completed);
+
+        Ok(ProjectArchiveSummary {
+            path: target_path.to_string(),
+            encrypted,
+            email_count,
+            attachment_count,
+            missing_attachments,
+            hash_mismatches,
+        })
+    }
+}
+
+/// 写入压缩包，返回内容与保存的哈希不一致的附件
+fn write_archive(
+    path: &Path,
+    project_json: &[u8],
+    files: &[PendingFile],
+    password: Option<&str>,
+    on_progress: impl Fn(usize),
+) -> Result<Vec<String>, AppError> {
+    let options = || {
+        let options: FileOptions<'_, ()> = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        match password {
+            Some(password) => options.with_aes_encryption(AesMode::Aes256, password),
+            None => options,
+        }
+    };
+    let zip_error = |e: zip::result::ZipError| AppError::FileSystem(format!("Failed to write archive: {}", e));
+
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+    let mut manifest = Vec::with_capacity(files.len() + 1);
+
+    zip.start_file("project.json", options()).map_err(zip_error)?;
+    zip.write_all(project_json)?;
+    manifest.push(ManifestEntry {
+        path: "project.json".to_string(),
+        size: project_json.len() as u64,
+        sha256: format!("{:x}", Sha256::digest(project_json)),
+        content_hash: None,
+        matches_content_hash: None,
+    });
+
+    let mut mismatches = Vec::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    for (index, file) in files.iter().enumerate() {
+        zip.start_file(file.archive_path.as_str(), options()).map_err(zip_error)?;
+        let mut source = File::open(&file.source)?;
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        loop {
+            let read = source.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            zip.write_all(&buffer[..read])?;
+            size += read as u64;
+        }
+
+        let sha256 = format!("{:x}", hasher.finalize());
+        let matches = file.content_hash.as_ref().map(|hash| hash.eq_ignore_ascii_case(&sha256));
+        if matches == Some(false) {
+            mismatches.push(file.label.clone());
+        }
+        manifest.push(ManifestEntry {
+            path: file.archive_path.clone(),
+            size,
+            sha256,
+            content_hash: file.content_hash.clone(),
+            matches_content_hash: matches,
+        });
+        on_progress(index + 1);
+    }
+
+    zip.start_file("manifest.json", options()).map_err(zip_error)?;
+    zip.write_all(&serde_json::to_vec_pretty(&serde_json::json!({
+        "algorithm": "sha256",
+        "files": manifest,
+    }))?)?;
+    zip.finish().map_err(zip_error)?.flush()?;
+    Ok(mismatches)
+}
@@ -0,0 +1,580 @@
+/// 单封邮件 / 整个线程导出为 PDF
+///
+/// 页面结构：邮件头（主题、发件人、收件人、抄送、日期）→ 正文（纯文本优先，否则由 HTML 转换的文本）
+/// → 本地存储中的图片（图片附件和离线缓存的远程图片）→ 附件列表（大小和 SHA-256）。
+/// 相同输入生成的文件逐字节相同：文档 ID 取内容哈希，日期取邮件日期，不写入导出时间。
+use crate::error::AppError;
+use crate::repository::project::thread_id_variants;
+use crate::storage::{body_store, file_manager, remote_content};
+use crate::utils::format_file_size;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+
+/// 单张嵌入图片的大小上限（超过则只在附件列表中列出）
+const MAX_EMBEDDED_IMAGE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// PDF 导出结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailPdfSummary {
+    pub path: String,
+    pub email_count: usize,
+    pub page_count: usize,
+    pub attachment_count: usize,
+    pub embedded_images: usize,
+    /// 是否使用了支持非拉丁文字的嵌入字体（否则只能渲染 Latin-1 字符）
+    pub unicode_font: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct PdfEmailRow {
+    id: i64,
+    subject: Option<String>,
+    sender: Option<String>,
+    recipients: Option<String>,
+    cc: Option<String>,
+    date: Option<String>,
+    body_text: Option<String>,
+    body_html: Option<String>,
+    body_truncated: bool,
+    body_path: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PdfAttachmentRow {
+    email_id: i64,
+    filename: Option<String>,
+    file_size: Option<i64>,
+    mime_type: Option<String>,
+    file_path: Option<String>,
+    content_hash: Option<String>,
+}
+
+/// 渲染用的邮件内容（已读取正文和图片）
+struct PdfEmail {
+    subject: String,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+    images: Vec<Vec<u8>>,
+    attachments: Vec<String>,
+}
+
+/// 邮件 PDF 导出
+pub struct EmailPdfExporter {
+    pool: SqlitePool,
+}
+
+impl EmailPdfExporter {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 导出单封邮件
+    pub async fn export_email(
+        &self,
+        email_id: i64,
+        target_path: &str,
+        include_attachments_list: bool,
+    ) -> Result<EmailPdfSummary, AppError> {
+        let rows = sqlx::query_as::<_, PdfEmailRow>(
+            r#"
+            SELECT id, subject, sender, recipients, cc, date, body_text, body_html,
+                   COALESCE(body_truncated, 0) AS body_truncated, body_path
+            FROM emails
+            WHERE id = ?
+            "#
+        )
+        .bind(email_id)
+        .fetch_all(&self.pool)
+        .await?;
+        if rows.is_empty() {
+            return Err(AppError::EmailNotFound { id: email_id });
+        }
+
+        self.export(rows, target_path, include_attachments_list, None).await
+    }
+
+    /// 按时间顺序导出整个线程（不含重复邮件）
+    pub async fn export_thread(
+        &self,
+        thread_id: &str,
+        target_path: &str,
+        include_attachments_list: bool,
+    ) -> Result<EmailPdfSummary, AppError> {
+        self.export_thread_with_note(thread_id, target_path, include_attachments_list, None).await
+    }
+
+    /// 同 `export_thread`，`note` 不为空时显示在第一封邮件之前（如线程分属多个项目的说明）
+    pub async fn export_thread_with_note(
+        &self,
+        thread_id: &str,
+        target_path: &str,
+        include_attachments_list: bool,
+        note: Option<String>,
+    ) -> Result<EmailPdfSummary, AppError> {
+        let (bare, bracketed) = thread_id_variants(thread_id)?;
+        let rows = sqlx::query_as::<_, PdfEmailRow>(
+            r#"
+            SELECT id, subject, sender, recipients, cc, date, body_text, body_html,
+                   COALESCE(body_truncated, 0) AS body_truncated, body_path
+            FROM emails
+            WHERE thread_id IN (?, ?) AND duplicate_of IS NULL
+            ORDER BY datetime(date) ASC, id ASC
+            "#
+        )
+        .bind(&bare)
+        .bind(&bracketed)
+        .fetch_all(&self.pool)
+        .await?;
+        if rows.is_empty() {
+            return Err(AppError::Validation(format!("Thread {} has no emails", thread_id)));
+        }
+
+        self.export(rows, target_path, include_attachments_list, note).await
+    }
+
+    async fn export(
+        &self,
+        rows: Vec<PdfEmailRow>,
+        target_path: &str,
+        include_attachments_list: bool,
+        note: Option<String>,
+    ) -> Result<EmailPdfSummary, AppError> {
+        let mut attachments_by_email = self.load_attachments(&rows).await?;
+        let date = rows.first().and_then(|row| row.date.clone());
+
+        let mut emails = Vec::with_capacity(rows.len());
+        let mut attachment_count = 0;
+        for row in rows {
+            let attachments = attachments_by_email.remove(&row.id).unwrap_or_default();
+            attachment_count += attachments.len();
+            emails.push(self.prepare(row, attachments, include_attachments_list).await?);
+        }
+        let embedded_images = emails.iter().map(|email| email.images.len()).sum();
+        let email_count = emails.len();
+
+        let path = target_path.to_string();
+        let (page_count, unicode_font) =
+            tokio::task::spawn_blocking(move || render::write_pdf(&emails, date.as_deref(), note.as_deref(), &path)).await??;
+
+        log::info!("Exported {} email(s) to PDF at {} ({} pages)", email_count, target_path, page_count);
+        Ok(EmailPdfSummary {
+            path: target_path.to_string(),
+            email_count,
+            page_count,
+            attachment_count,
+            embedded_images,
+            unicode_font,
+        })
+    }
+
+    async fn load_attachments(&self, rows: &[PdfEmailRow]) -> Result<BTreeMap<i64, Vec<PdfAttachmentRow>>, AppError> {
+        let mut by_email: BTreeMap<i64, Vec<PdfAttachmentRow>> = BTreeMap::new();
+        for row in rows {
+            let attachments = sqlx::query_as::<_, PdfAttachmentRow>(
+                r#"
+                SELECT email_id, filename, file_size, mime_type, file_path, content_hash
+                FROM attachments
+                WHERE email_id = ?
+                ORDER BY filename ASC, id ASC
+                "#
+            )
+            .bind(row.id)
+            .fetch_all(&self.pool)
+            .await?;
+            for attachment in attachments {
+                by_email.entry(attachment.email_id).or_default().push(attachment);
+            }
+        }
+        Ok(by_email)
+    }
+
+    /// 读取完整正文和本地图片
+    async fn prepare(
+        &self,
+        row: PdfEmailRow,
+        attachments: Vec<PdfAttachmentRow>,
+        include_attachments_list: bool,
+    ) -> Result<PdfEmail, AppError> {
+        let (mut text, mut html) = (row.body_text, row.body_html);
+        if row.body_truncated {
+            if let Some(path) = row.body_path.as_deref() {
+                let full = body_store::read_full_body(path).await?;
+                text = full.text;
+                html = full.html;
+            }
+        }
+        let body = match text.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+            Some(text) => text.to_string(),
+            None => html.as_deref().map(html_to_text).unwrap_or_default(),
+        };
+
+        let mut headers = vec![("From", row.sender.unwrap_or_default())];
+        if let Some(to) = address_list(row.recipients.as_deref()) {
+            headers.push(("To", to));
+        }
+        if let Some(cc) = address_list(row.cc.as_deref()) {
+            headers.push(("Cc", cc));
+        }
+        headers.push(("Date", row.date.unwrap_or_default()));
+
+        // 图片附件（按文件名排序）+ 离线缓存的远程图片（按文件名排序）
+        let mut images = Vec::new();
+        for attachment in &attachments {
+            let is_image = attachment
+                .mime_type
+                .as_deref()
+                .is_some_and(|mime| matches!(mime, "image/png" | "image/jpeg" | "image/gif"));
+            let Some(relative) = attachment.file_path.as_deref().filter(|_| is_image) else { continue };
+            if let Some(data) = read_image(file_manager::resolve_attachment_path(relative)).await {
+                images.push(data);
+            }
+        }
+        let cached: Vec<String> = sqlx::query_scalar(
+            "SELECT DISTINCT file_name FROM remote_content_refs WHERE email_id = ? ORDER BY file_name"
+        )
+        .bind(row.id)
+        .fetch_all(&self.pool)
+        .await?;
+        for file_name in cached {
+            if let Some(data) = read_image(remote_content::cache_root().map(|root| root.join(&file_name))).await {
+                images.push(data);
+            }
+        }
+
+        let attachments = if include_attachments_list {
+            attachments
+                .iter()
+                .map(|file| {
+                    format!(
+                        "{} ({}) sha256:{}",
+                        file.filename.as_deref().unwrap_or("unnamed"),
+                        format_file_size(file.file_size.unwrap_or(0)),
+                        file.content_hash.as_deref().unwrap_or("unknown")
+                    )
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok(PdfEmail {
+            subject: row.subject.unwrap_or_else(|| "(No Subject)".to_string()),
+            headers,
+            body,
+            images,
+            attachments,
+        })
+    }
+}
+
+/// 读取本地图片（文件不存在、过大或冷存储未挂载时跳过）
+async fn read_image(path: Result<std::path::PathBuf, AppError>) -> Option<Vec<u8>> {
+    let path = path.ok()?;
+    let metadata = tokio::fs::metadata(&path).await.ok()?;
+    if metadata.len() > MAX_EMBEDDED_IMAGE_BYTES {
+        return None;
+    }
+    tokio::fs::read(&path).await.ok()
+}
+
+/// JSON 地址数组转为逗号分隔的文本
+fn address_list(value: Option<&str>) -> Option<String> {
+    let list: Vec<String> = serde_json::from_str(value?).ok()?;
+    (!list.is_empty()).then(|| list.join(", "))
+}
+
+/// HTML 转为排版后的纯文本：去除脚本、样式和所有标签，块级元素换行，列表项加项目符号
+pub(crate) fn html_to_text(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_ascii_lowercase();
+        rest = &rest[start + end + 1..];
+
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        match name.as_str() {
+            "script" | "style" | "head" if !tag.starts_with('/') => {
+                let close = format!("</{}", name);
+                rest = match rest.to_ascii_lowercase().find(&close) {
+                    Some(pos) => rest[pos..].find('>').map_or("", |gt| &rest[pos + gt + 1..]),
+                    None => "",
+                };
+            }
+            "br" | "tr" => out.push('\n'),
+            "p" | "div" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "table" | "blockquote" | "ul" | "ol" => {
+                out.push('\n')
+            }
+            "li" if !tag.starts_with('/') => out.push_str("\n• "),
+            "td" | "th" if tag.starts_with('/') => out.push('\t'),
+            _ => {}
+        }
+    }
+    out.push_str(rest);
+
+    let decoded = decode_entities(&out);
+    let mut lines: Vec<&str> = Vec::new();
+    for line in decoded.lines().map(str::trim_end) {
+        if line.trim().is_empty() && lines.last().map_or(true, |last| last.trim().is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// 解码常见的 HTML 实体
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';').filter(|&semi| semi <= 10) else {
+            out.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// PDF 排版：A4 页面，按显示宽度折行，超出页面底部时分页
+mod render {
+    use super::PdfEmail;
+    use crate::error::AppError;
+    use crate::export::pdf_text::{load_fonts, wrap};
+    use crate::mail::sync::calculate_sha256;
+    use printpdf::image_crate;
+    use printpdf::{
+        Image, ImageTransform, IndirectFontRef, Mm, OffsetDateTime, PdfDocument, PdfDocumentReference,
+        PdfLayerReference,
+    };
+
+    const PAGE_WIDTH: f32 = 210.0;
+    const PAGE_HEIGHT: f32 = 297.0;
+    const MARGIN: f32 = 18.0;
+    const BODY_SIZE: f32 = 10.0;
+    const HEADER_SIZE: f32 = 9.0;
+    const TITLE_SIZE: f32 = 15.0;
+    /// 每毫米行高 / 字号
+    const LINE_FACTOR: f32 = 0.5;
+    const IMAGE_DPI: f32 = 150.0;
+
+    struct Layout<'a> {
+        doc: &'a PdfDocumentReference,
+        layer: PdfLayerReference,
+        y: f32,
+        pages: usize,
+    }
+
+    impl Layout<'_> {
+        fn new_page(&mut self) {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT - MARGIN;
+            self.pages += 1;
+        }
+
+        /// 剩余空间不足 `height` 时换页
+        fn ensure_space(&mut self, height: f32) {
+            if self.y - height < MARGIN {
+                self.new_page();
+            }
+        }
+
+        fn text(&mut self, text: &str, size: f32, font: &IndirectFontRef, indent: f32) {
+            let line_height = size * LINE_FACTOR;
+            let width = PAGE_WIDTH - 2.0 * MARGIN - indent;
+            for line in wrap(text, width, size) {
+                self.ensure_space(line_height);
+                self.y -= line_height;
+                self.layer.use_text(line, size, Mm(MARGIN + indent), Mm(self.y), font);
+            }
+        }
+
+        fn gap(&mut self, height: f32) {
+            self.y -= height;
+        }
+
+        /// 按原始尺寸（IMAGE_DPI）嵌入图片，超出版心时等比缩小
+        fn image(&mut self, data: &[u8]) -> bool {
+            let Ok(decoded) = image_crate::load_from_memory(data) else {
+                return false;
+            };
+            let px_to_mm = 25.4 / IMAGE_DPI;
+            let natural_width = decoded.width() as f32 * px_to_mm;
+            let natural_height = decoded.height() as f32 * px_to_mm;
+            let max_width = PAGE_WIDTH - 2.0 * MARGIN;
+            let max_height = PAGE_HEIGHT - 2.0 * MARGIN;
+            let scale = (max_width / natural_width).min(max_height / natural_height).min(1.0);
+
+            self.ensure_space(natural_height * scale);
+            self.y -= natural_height * scale;
+            Image::from_dynamic_image(&decoded).add_to_layer(
+                self.layer.clone(),
+                ImageTransform {
+                    translate_x: Some(Mm(MARGIN)),
+                    translate_y: Some(Mm(self.y)),
+                    scale_x: Some(scale),
+                    scale_y: Some(scale),
+                    dpi: Some(IMAGE_DPI),
+                    ..Default::default()
+                },
+            );
+            self.gap(3.0);
+            true
+        }
+    }
+
+    /// 渲染并写入 PDF，返回 (页数, 是否使用了 Unicode 字体)
+    pub fn write_pdf(emails: &[PdfEmail], date: Option<&str>, note: Option<&str>, path: &str) -> Result<(usize, bool), AppError> {
+        let title = emails.first().map(|email| email.subject.as_str()).unwrap_or("Email");
+        let document_id = document_id(emails);
+        let timestamp = date.and_then(parse_date).unwrap_or(OffsetDateTime::UNIX_EPOCH);
+
+        let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        let doc = doc
+            .with_document_id(document_id.clone())
+            .with_creation_date(timestamp)
+            .with_mod_date(timestamp)
+            .with_metadata_date(timestamp)
+            .with_producer("ThreadLine");
+        let fonts = load_fonts(&doc)?;
+
+        let mut layout = Layout {
+            doc: &doc,
+            layer: doc.get_page(page).get_layer(layer),
+            y: PAGE_HEIGHT - MARGIN,
+            pages: 1,
+        };
+
+        if let Some(note) = note {
+            layout.text(note, HEADER_SIZE, &fonts.regular, 0.0);
+            layout.gap(4.0);
+        }
+
+        for (index, email) in emails.iter().enumerate() {
+            if index > 0 {
+                layout.gap(6.0);
+                layout.ensure_space(TITLE_SIZE * LINE_FACTOR * 4.0);
+            }
+            layout.text(&email.subject, TITLE_SIZE, &fonts.bold, 0.0);
+            layout.gap(2.0);
+            for (label, value) in &email.headers {
+                layout.text(&format!("{}: {}", label, value), HEADER_SIZE, &fonts.regular, 0.0);
+            }
+            layout.gap(4.0);
+
+            for line in email.body.lines() {
+                if line.trim().is_empty() {
+                    layout.gap(BODY_SIZE * LINE_FACTOR);
+                } else {
+                    layout.text(line, BODY_SIZE, &fonts.regular, 0.0);
+                }
+            }
+
+            if !email.images.is_empty() {
+                layout.gap(4.0);
+                for data in &email.images {
+                    if !layout.image(data) {
+                        log::warn!("Skipping undecodable image in PDF export");
+                    }
+                }
+            }
+
+            if !email.attachments.is_empty() {
+                layout.gap(4.0);
+                layout.text("Attachments", HEADER_SIZE + 1.0, &fonts.bold, 0.0);
+                for line in &email.attachments {
+                    layout.text(&format!("- {}", line), HEADER_SIZE, &fonts.regular, 3.0);
+                }
+            }
+        }
+
+        let pages = layout.pages;
+        drop(layout);
+        let bytes = doc
+            .save_to_bytes()
+            .map_err(|e| AppError::Generic(format!("Failed to write PDF: {}", e)))?;
+        std::fs::write(path, fix_instance_id(bytes, &document_id))?;
+        Ok((pages, fonts.unicode))
+    }
+
+    fn parse_date(date: &str) -> Option<OffsetDateTime> {
+        let parsed = chrono::DateTime::parse_from_rfc3339(date)
+            .map(|dt| dt.timestamp())
+            .or_else(|_| chrono::DateTime::parse_from_rfc2822(date).map(|dt| dt.timestamp()))
+            .or_else(|_| {
+                chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").map(|dt| dt.and_utc().timestamp())
+            })
+            .ok()?;
+        OffsetDateTime::from_unix_timestamp(parsed).ok()
+    }
+
+    /// 由内容计算的 32 位文档 ID（printpdf 要求 32 个字符）
+    fn document_id(emails: &[PdfEmail]) -> String {
+        let mut content = String::new();
+        for email in emails {
+            content.push_str(&email.subject);
+            for (label, value) in &email.headers {
+                content.push_str(label);
+                content.push_str(value);
+            }
+            content.push_str(&email.body);
+            content.push_str(&email.attachments.join("\n"));
+            content.push_str(&email.images.len().to_string());
+        }
+        calculate_sha256(content.as_bytes())[..32].to_uppercase()
+    }
+
+    /// printpdf 在 trailer 的 /ID 中写入随机的实例 ID，替换为文档 ID 以保证输出可复现
+    ///
+    /// 两者长度相同，替换不影响 xref 偏移。
+    fn fix_instance_id(mut bytes: Vec<u8>, document_id: &str) -> Vec<u8> {
+        let needle = format!("({})(", document_id);
+        let id_len = document_id.len();
+        if let Some(pos) = bytes.windows(needle.len()).rposition(|window| window == needle.as_bytes()) {
+            let start = pos + needle.len();
+            if bytes.get(start + id_len) == Some(&b')') {
+                bytes[start..start + id_len].copy_from_slice(document_id.as_bytes());
+            }
+        }
+        bytes
+    }
+}
@@ -0,0 +1,10 @@
+/// 导出模块
+///
+/// 将项目数据导出为可分享的文档
+pub mod archive;
+pub mod email_pdf;
+pub mod pdf_text;
+pub mod report;
+pub mod search_csv;
+pub mod settings_profile;
+pub mod thread;
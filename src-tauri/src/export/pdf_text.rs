@@ -0,0 +1,133 @@
+/// PDF 文本排版的公共部分
+///
+/// 单封邮件 / 线程 PDF 和项目报告共用：支持 CJK 等非拉丁文字的字体，以及按显示宽度折行。
+use crate::error::AppError;
+use crate::storage::file_manager;
+use printpdf::{BuiltinFont, IndirectFontRef, PdfDocumentReference};
+
+/// 拉丁字符平均宽度（毫米 / 字号）
+const CHAR_WIDTH_FACTOR: f32 = 0.19;
+
+/// 支持 CJK 等非拉丁文字的字体（依次尝试）
+///
+/// 先查找应用数据目录下的 `fonts/`，再查找系统字体。
+const SYSTEM_FONTS: &[&str] = &[
+    "/usr/share/fonts/truetype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simsun.ttc",
+    "C:\\Windows\\Fonts\\arialuni.ttf",
+];
+
+/// 文档中使用的字体
+pub struct PdfFonts {
+    pub regular: IndirectFontRef,
+    pub bold: IndirectFontRef,
+    /// 是否找到了 Unicode 字体（否则为内置 Helvetica，非拉丁文字无法显示）
+    pub unicode: bool,
+}
+
+/// 加载第一个可用的 Unicode 字体，都不可用时退回内置 Helvetica
+pub fn load_fonts(doc: &PdfDocumentReference) -> Result<PdfFonts, AppError> {
+    let bundled = file_manager::app_data_dir()
+        .ok()
+        .map(|dir| dir.join("fonts"))
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .map(|entries| {
+            let mut paths: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+            paths.sort();
+            paths
+        })
+        .unwrap_or_default();
+
+    let candidates = bundled
+        .into_iter()
+        .chain(SYSTEM_FONTS.iter().map(std::path::PathBuf::from));
+    for candidate in candidates {
+        let Ok(data) = std::fs::read(&candidate) else { continue };
+        match doc.add_external_font(data.as_slice()) {
+            Ok(font) => {
+                log::debug!("Using PDF font {:?}", candidate);
+                // 外部字体没有粗体变体，标题用同一字体的更大字号
+                return Ok(PdfFonts { regular: font.clone(), bold: font, unicode: true });
+            }
+            Err(e) => log::debug!("Font {:?} not usable: {}", candidate, e),
+        }
+    }
+
+    log::warn!("No Unicode font found, non-Latin text will not render in the PDF");
+    let font_error = |e| AppError::Generic(format!("Failed to load PDF font: {}", e));
+    Ok(PdfFonts {
+        regular: doc.add_builtin_font(BuiltinFont::Helvetica).map_err(font_error)?,
+        bold: doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(font_error)?,
+        unicode: false,
+    })
+}
+
+/// 按显示宽度折行，优先在空格处断开；CJK 等宽字符按两个拉丁字符计
+pub fn wrap(text: &str, width_mm: f32, size: f32) -> Vec<String> {
+    let max_units = (width_mm / (size * CHAR_WIDTH_FACTOR)).max(10.0) as usize;
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut units = 0;
+    let mut last_space: Option<(usize, usize)> = None;
+
+    for c in text.replace('\t', "    ").chars() {
+        let w = if is_wide(c) { 2 } else { 1 };
+        if units + w > max_units && !current.is_empty() {
+            match last_space.take() {
+                Some((byte, unit)) if byte > 0 => {
+                    let tail = current[byte + 1..].to_string();
+                    current.truncate(byte);
+                    lines.push(std::mem::take(&mut current));
+                    current = tail;
+                    units -= unit + 1;
+                }
+                _ => {
+                    lines.push(std::mem::take(&mut current));
+                    units = 0;
+                }
+            }
+        }
+        if c == ' ' {
+            last_space = Some((current.len(), units));
+        }
+        current.push(c);
+        units += w;
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115F | 0x2E80..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_counts_cjk_as_double_width() {
+        // 10 号字、19 毫米宽：每行 10 个单位
+        let latin = wrap("abcdefghijklmnopqrst", 19.0, 10.0);
+        assert_eq!(latin, vec!["abcdefghij", "klmnopqrst"]);
+
+        let cjk = wrap("项目报告导出中文内容", 19.0, 10.0);
+        assert_eq!(cjk, vec!["项目报告导", "出中文内容"]);
+    }
+
+    #[test]
+    fn wrap_breaks_at_spaces() {
+        assert_eq!(wrap("hello world again", 19.0, 10.0), vec!["hello", "world", "again"]);
+        assert_eq!(wrap("", 19.0, 10.0), vec![""]);
+    }
+}
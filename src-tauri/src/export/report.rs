@@ -0,0 +1,456 @@
+/// 项目报告生成
+///
+/// 报告结构：项目头部（名称、描述、参与者、标签）→ 里程碑 → 按时间顺序的线程摘要（含附件列表）。
+/// Markdown 输出是确定性的：不包含生成时间，所有列表按 (日期, ID) 排序，
+/// 因此两次导出之间的 diff 只反映数据变化。
+use crate::error::AppError;
+use crate::events::{EventEmitter, ExportProgressEvent, ExportStatus};
+use crate::repository::ProjectRepository;
+use crate::utils::format_file_size;
+use crate::utils::i18n::parse_timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+/// 每处理多少个线程发送一次进度事件
+const PROGRESS_INTERVAL: usize = 20;
+
+/// 精简正文的最大字符数
+const STRIPPED_BODY_CHARS: usize = 600;
+
+/// 报告格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Markdown,
+    Pdf,
+}
+
+/// 报告选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportOptions {
+    /// 是否包含完整正文（否则只包含去除引用后的精简文本）
+    pub include_full_bodies: bool,
+    /// 起始日期（包含）
+    pub date_from: Option<String>,
+    /// 截止日期（包含）
+    pub date_to: Option<String>,
+    /// 是否列出附件（大小和哈希）
+    pub include_attachments: bool,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self {
+            include_full_bodies: false,
+            date_from: None,
+            date_to: None,
+            include_attachments: true,
+        }
+    }
+}
+
+/// 报告生成结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportSummary {
+    pub path: String,
+    pub format: ReportFormat,
+    pub thread_count: usize,
+    pub email_count: usize,
+    pub attachment_count: usize,
+}
+
+#[derive(sqlx::FromRow)]
+struct ReportEmailRow {
+    id: i64,
+    thread_id: Option<String>,
+    date: Option<String>,
+    sender: Option<String>,
+    subject: Option<String>,
+    body_text: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ReportAttachmentRow {
+    email_id: i64,
+    filename: Option<String>,
+    file_size: Option<i64>,
+    content_hash: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ReportMilestoneRow {
+    date: Option<String>,
+    title: Option<String>,
+    r#type: Option<String>,
+}
+
+/// 项目报告生成器
+pub struct ReportGenerator {
+    pool: SqlitePool,
+    event_emitter: Option<EventEmitter>,
+}
+
+impl ReportGenerator {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            event_emitter: None,
+        }
+    }
+
+    pub fn with_event_emitter(pool: SqlitePool, emitter: EventEmitter) -> Self {
+        Self {
+            pool,
+            event_emitter: Some(emitter),
+        }
+    }
+
+    /// 发送导出进度事件
+    fn emit_progress(&self, project_id: i64, current: usize, total: usize, status: ExportStatus) {
+        if let Some(emitter) = &self.event_emitter {
+            emitter.emit_export_progress(ExportProgressEvent {
+                export_type: "project_report".to_string(),
+                target_id: project_id,
+                current,
+                total,
+                status,
+            });
+        }
+    }
+
+    /// 生成项目报告并写入目标路径
+    pub async fn generate(
+        &self,
+        project_id: i64,
+        format: ReportFormat,
+        options: &ReportOptions,
+        target_path: &str,
+    ) -> Result<ReportSummary, AppError> {
+        let markdown = match self.render_markdown(project_id, options).await {
+            Ok(result) => result,
+            Err(e) => {
+                self.emit_progress(project_id, 0, 0, ExportStatus::Failed);
+                return Err(e);
+            }
+        };
+
+        let write_result = match format {
+            ReportFormat::Markdown => tokio::fs::write(target_path, markdown.text.as_bytes())
+                .await
+                .map_err(AppError::from),
+            ReportFormat::Pdf => {
+                let text = markdown.text.clone();
+                let path = target_path.to_string();
+                tokio::task::spawn_blocking(move || pdf::write_markdown_as_pdf(&text, &path)).await?
+            }
+        };
+
+        if let Err(e) = write_result {
+            self.emit_progress(project_id, 0, 0, ExportStatus::Failed);
+            return Err(e);
+        }
+
+        self.emit_progress(project_id, markdown.thread_count, markdown.thread_count, ExportStatus::Completed);
+        log::info!("Project {} report written to {}", project_id, target_path);
+
+        Ok(ReportSummary {
+            path: target_path.to_string(),
+            format,
+            thread_count: markdown.thread_count,
+            email_count: markdown.email_count,
+            attachment_count: markdown.attachment_count,
+        })
+    }
+
+    /// 渲染 Markdown 报告
+    async fn render_markdown(
+        &self,
+        project_id: i64,
+        options: &ReportOptions,
+    ) -> Result<RenderedReport, AppError> {
+        let repo = ProjectRepository::new(self.pool.clone());
+        let project = repo.get_by_id(project_id).await?;
+
+        let milestones = sqlx::query_as::<_, ReportMilestoneRow>(
+            r#"
+            SELECT date, title, type
+            FROM milestones
+            WHERE project_id = ?
+              AND (? IS NULL OR datetime(date) >= datetime(?))
+              AND (? IS NULL OR datetime(date) <= datetime(?))
+            ORDER BY julianday(date) ASC, id ASC
+            "#
+        )
+        .bind(project_id)
+        .bind(&options.date_from)
+        .bind(&options.date_from)
+        .bind(&options.date_to)
+        .bind(&options.date_to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let emails = sqlx::query_as::<_, ReportEmailRow>(
+            r#"
+            SELECT id, thread_id, date, sender, subject, body_text
+            FROM emails
+            WHERE project_id = ?
+              AND duplicate_of IS NULL
+              AND (? IS NULL OR datetime(date) >= datetime(?))
+              AND (? IS NULL OR datetime(date) <= datetime(?))
+            ORDER BY julianday(date) ASC, id ASC
+            "#
+        )
+        .bind(project_id)
+        .bind(&options.date_from)
+        .bind(&options.date_from)
+        .bind(&options.date_to)
+        .bind(&options.date_to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let attachments = if options.include_attachments {
+            sqlx::query_as::<_, ReportAttachmentRow>(
+                r#"
+                SELECT a.email_id, a.filename, a.file_size, a.content_hash
+                FROM attachments a
+                JOIN emails e ON e.id = a.email_id
+                WHERE e.project_id = ? AND e.duplicate_of IS NULL
+                ORDER BY a.email_id ASC, a.filename ASC, a.id ASC
+                "#
+            )
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await?
+        } else {
+            Vec::new()
+        };
+
+        let mut attachments_by_email: BTreeMap<i64, Vec<ReportAttachmentRow>> = BTreeMap::new();
+        for attachment in attachments {
+            attachments_by_email.entry(attachment.email_id).or_default().push(attachment);
+        }
+
+        // 按线程分组；线程内按解析后的时间排序，线程按 (首封邮件时间, 首封邮件 ID) 排序，保证顺序稳定
+        // （日期是 RFC 2822 等格式的原始字符串，不能直接按字符串比较）
+        let email_count = emails.len();
+        let mut by_thread: BTreeMap<String, Vec<ReportEmailRow>> = BTreeMap::new();
+        for email in emails {
+            let thread_key = email.thread_id.clone().unwrap_or_else(|| format!("email-{}", email.id));
+            by_thread.entry(thread_key).or_default().push(email);
+        }
+        let mut threads: BTreeMap<(Option<i64>, i64), Vec<ReportEmailRow>> = BTreeMap::new();
+        for mut thread_emails in by_thread.into_values() {
+            thread_emails.sort_by_key(|email| (email_timestamp(email), email.id));
+            let first = &thread_emails[0];
+            threads.insert((email_timestamp(first), first.id), thread_emails);
+        }
+
+        let thread_count = threads.len();
+        self.emit_progress(project_id, 0, thread_count, ExportStatus::Starting);
+
+        let mut out = String::new();
+        let mut attachment_count = 0;
+
+        // 项目头部
+        let _ = writeln!(out, "# {}", project.title);
+        let _ = writeln!(out);
+        if let Some(description) = project.description.as_deref().filter(|d| !d.trim().is_empty()) {
+            let _ = writeln!(out, "{}", description.trim());
+            let _ = writeln!(out);
+        }
+        let _ = writeln!(out, "- Status: {}", project.status);
+        if project.color.is_some() || project.icon.is_some() {
+            let _ = writeln!(
+                out,
+                "- Appearance: {} {}",
+                project.color.as_deref().unwrap_or("-"),
+                project.icon.as_deref().unwrap_or("-")
+            );
+        }
+        if let Some(tags) = project.tags.as_ref().filter(|t| !t.is_empty()) {
+            let _ = writeln!(out, "- Tags: {}", tags.join(", "));
+        }
+        if let Some(participants) = project.participants.as_ref().filter(|p| !p.is_empty()) {
+            let _ = writeln!(out, "- Participants: {}", participants.join(", "));
+        }
+        if options.date_from.is_some() || options.date_to.is_some() {
+            let _ = writeln!(
+                out,
+                "- Date range: {} – {}",
+                options.date_from.as_deref().unwrap_or("…"),
+                options.date_to.as_deref().unwrap_or("…")
+            );
+        }
+        let _ = writeln!(out, "- Threads: {}, emails: {}", thread_count, email_count);
+        let _ = writeln!(out);
+
+        // 里程碑
+        if !milestones.is_empty() {
+            let _ = writeln!(out, "## Milestones");
+            let _ = writeln!(out);
+            for m in &milestones {
+                let _ = writeln!(
+                    out,
+                    "- {} — {} ({})",
+                    m.date.as_deref().unwrap_or(""),
+                    m.title.as_deref().unwrap_or(""),
+                    m.r#type.as_deref().unwrap_or("")
+                );
+            }
+            let _ = writeln!(out);
+        }
+
+        // 线程
+        let _ = writeln!(out, "## Timeline");
+        let _ = writeln!(out);
+        for (index, thread_emails) in threads.values().enumerate() {
+            let subject = thread_emails
+                .first()
+                .and_then(|e| e.subject.as_deref())
+                .unwrap_or("(No Subject)");
+            let _ = writeln!(out, "### {}", subject);
+            let _ = writeln!(out);
+
+            for email in thread_emails {
+                let _ = writeln!(
+                    out,
+                    "**{}** — {}",
+                    email.sender.as_deref().unwrap_or("Unknown"),
+                    email.date.as_deref().unwrap_or("")
+                );
+                let _ = writeln!(out);
+
+                let body = email.body_text.as_deref().unwrap_or_default();
+                let body = if options.include_full_bodies {
+                    body.trim().to_string()
+                } else {
+                    strip_body(body, STRIPPED_BODY_CHARS)
+                };
+                if !body.is_empty() {
+                    for line in body.lines() {
+                        let _ = writeln!(out, "> {}", line);
+                    }
+                    let _ = writeln!(out);
+                }
+
+                if let Some(files) = attachments_by_email.get(&email.id) {
+                    let _ = writeln!(out, "Attachments:");
+                    for file in files {
+                        attachment_count += 1;
+                        let _ = writeln!(
+                            out,
+                            "- {} ({}) sha256:{}",
+                            file.filename.as_deref().unwrap_or("unnamed"),
+                            format_file_size(file.file_size.unwrap_or(0)),
+                            file.content_hash.as_deref().unwrap_or("unknown")
+                        );
+                    }
+                    let _ = writeln!(out);
+                }
+            }
+
+            if (index + 1) % PROGRESS_INTERVAL == 0 {
+                self.emit_progress(project_id, index + 1, thread_count, ExportStatus::Writing);
+            }
+        }
+
+        Ok(RenderedReport {
+            text: out,
+            thread_count,
+            email_count,
+            attachment_count,
+        })
+    }
+}
+
+struct RenderedReport {
+    text: String,
+    thread_count: usize,
+    email_count: usize,
+    attachment_count: usize,
+}
+
+/// 精简正文：去除引用行（以 ">" 开头）和 "On ... wrote:" 之后的内容，合并空行并截断
+fn strip_body(body: &str, max_chars: usize) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('>') {
+            continue;
+        }
+        if trimmed.starts_with("On ") && trimmed.ends_with("wrote:") {
+            break;
+        }
+        if trimmed.is_empty() && lines.last().map(|l| l.trim().is_empty()).unwrap_or(true) {
+            continue;
+        }
+        lines.push(line.trim_end());
+    }
+
+    let text = lines.join("\n").trim().to_string();
+    if text.chars().count() <= max_chars {
+        text
+    } else {
+        let truncated: String = text.chars().take(max_chars).collect();
+        format!("{}…", truncated.trim_end())
+    }
+}
+
+/// 邮件日期的 Unix 时间戳（无法解析时为 None，排在最前）
+fn email_timestamp(email: &ReportEmailRow) -> Option<i64> {
+    email.date.as_deref().and_then(parse_timestamp).map(|date| date.timestamp())
+}
+
+/// 简单的 PDF 渲染：将 Markdown 按行排版到 A4 页面
+mod pdf {
+    use crate::error::AppError;
+    use crate::export::pdf_text::{load_fonts, wrap};
+    use printpdf::{Mm, PdfDocument};
+    use std::fs::File;
+    use std::io::BufWriter;
+
+    const PAGE_WIDTH: f32 = 210.0;
+    const PAGE_HEIGHT: f32 = 297.0;
+    const MARGIN: f32 = 18.0;
+    const LINE_HEIGHT: f32 = 5.5;
+
+    pub fn write_markdown_as_pdf(markdown: &str, path: &str) -> Result<(), AppError> {
+        let (doc, page, layer) =
+            PdfDocument::new("ThreadLine Project Report", Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        let fonts = load_fonts(&doc)?;
+
+        let mut current_layer = doc.get_page(page).get_layer(layer);
+        let mut y = PAGE_HEIGHT - MARGIN;
+
+        for raw_line in markdown.lines() {
+            let (text, size, is_heading) = if let Some(h) = raw_line.strip_prefix("### ") {
+                (h, 12.0, true)
+            } else if let Some(h) = raw_line.strip_prefix("## ") {
+                (h, 14.0, true)
+            } else if let Some(h) = raw_line.strip_prefix("# ") {
+                (h, 18.0, true)
+            } else {
+                (raw_line, 10.0, false)
+            };
+
+            for chunk in wrap(text, PAGE_WIDTH - 2.0 * MARGIN, size) {
+                if y < MARGIN {
+                    let (new_page, new_layer) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+                    current_layer = doc.get_page(new_page).get_layer(new_layer);
+                    y = PAGE_HEIGHT - MARGIN;
+                }
+                let face = if is_heading { &fonts.bold } else { &fonts.regular };
+                current_layer.use_text(chunk, size, Mm(MARGIN), Mm(y), face);
+                y -= if is_heading { LINE_HEIGHT * 1.6 } else { LINE_HEIGHT };
+            }
+        }
+
+        let file = File::create(path)?;
+        doc.save(&mut BufWriter::new(file))
+            .map_err(|e| AppError::Generic(format!("Failed to write PDF: {}", e)))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+/// 搜索结果导出为 CSV
+///
+/// 使用与 `search_query` 相同的搜索条件，但不分页：逐行读取数据库并写入文件，不把全部结果载入内存。
+/// 超过行数上限时截断并在结果中给出警告。可选写入 UTF-8 BOM，Excel 才能正确识别中文。
+use crate::error::AppError;
+use crate::events::{EventEmitter, ExportProgressEvent, ExportStatus};
+use crate::mail::recipients::parse_list;
+use crate::search::query::search_plan;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::io::AsyncWriteExt;
+
+/// 默认行数上限
+pub const DEFAULT_MAX_ROWS: usize = 50_000;
+
+/// 允许设置的最大行数上限
+const MAX_ROWS_LIMIT: usize = 1_000_000;
+
+/// 每写入多少行发送一次进度事件
+const PROGRESS_INTERVAL: usize = 500;
+
+/// 没有保存的预览时摘要取正文的前多少个字符
+const FALLBACK_SNIPPET_CHARS: i64 = 200;
+
+/// 可导出的列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchExportColumn {
+    Date,
+    Sender,
+    Recipients,
+    Subject,
+    Project,
+    HasAttachments,
+    Snippet,
+}
+
+impl SearchExportColumn {
+    /// 默认导出的列
+    pub const DEFAULT: &'static [SearchExportColumn] = &[
+        SearchExportColumn::Date,
+        SearchExportColumn::Sender,
+        SearchExportColumn::Recipients,
+        SearchExportColumn::Subject,
+        SearchExportColumn::Project,
+        SearchExportColumn::HasAttachments,
+        SearchExportColumn::Snippet,
+    ];
+
+    fn header(&self) -> &'static str {
+        match self {
+            SearchExportColumn::Date => "date",
+            SearchExportColumn::Sender => "sender",
+            SearchExportColumn::Recipients => "recipients",
+            SearchExportColumn::Subject => "subject",
+            SearchExportColumn::Project => "project",
+            SearchExportColumn::HasAttachments => "has_attachments",
+            SearchExportColumn::Snippet => "snippet",
+        }
+    }
+}
+
+/// 导出请求（搜索条件与 `search_query` 相同）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchExportRequest {
+    pub query: String,
+    #[serde(default)]
+    pub lang: Option<String>,
+    /// 写入 UTF-8 BOM（Excel 兼容）
+    #[serde(default)]
+    pub include_bom: bool,
+    /// 行数上限（默认 `DEFAULT_MAX_ROWS`）
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+}
+
+/// 导出结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchExportSummary {
+    pub path: String,
+    pub row_count: usize,
+    /// 匹配的邮件总数
+    pub total_matches: usize,
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ExportRow {
+    date: Option<String>,
+    sender: Option<String>,
+    recipients: Option<String>,
+    subject: Option<String>,
+    project_name: Option<String>,
+    has_attachments: bool,
+    snippet: Option<String>,
+}
+
+/// 搜索结果导出器
+pub struct SearchExporter {
+    pool: SqlitePool,
+    event_emitter: Option<EventEmitter>,
+}
+
+impl SearchExporter {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            event_emitter: None,
+        }
+    }
+
+    pub fn with_event_emitter(pool: SqlitePool, emitter: EventEmitter) -> Self {
+        Self {
+            pool,
+            event_emitter: Some(emitter),
+        }
+    }
+
+    /// 发送导出进度事件
+    fn emit_progress(&self, current: usize, total: usize, status: ExportStatus) {
+        if let Some(emitter) = &self.event_emitter {
+            emitter.emit_export_progress(ExportProgressEvent {
+                export_type: "search_results".to_string(),
+                target_id: 0,
+                current,
+                total,
+                status,
+            });
+        }
+    }
+
+    /// 导出搜索结果到 `target_path`，`columns` 为空时导出全部列
+    pub async fn export(
+        &self,
+        request: &SearchExportRequest,
+        columns: &[SearchExportColumn],
+        target_path: &str,
+    ) -> Result<SearchExportSummary, AppError> {
+        let columns = if columns.is_empty() { SearchExportColumn::DEFAULT } else { columns };
+        let max_rows = request.max_rows.unwrap_or(DEFAULT_MAX_ROWS).clamp(1, MAX_ROWS_LIMIT);
+
+        let result = self.write(request, columns, max_rows, target_path).await;
+        match result {
+            Ok(summary) => {
+                self.emit_progress(summary.row_count, summary.row_count, ExportStatus::Completed);
+                log::info!(
+                    "Exported {} of {} search results to {}",
+                    summary.row_count, summary.total_matches, target_path
+                );
+                Ok(summary)
+            }
+            Err(e) => {
+                self.emit_progress(0, 0, ExportStatus::Failed);
+                if let Err(remove_error) = tokio::fs::remove_file(target_path).await {
+                    if remove_error.kind() != std::io::ErrorKind::NotFound {
+                        log::warn!("Failed to remove partial export {}: {}", target_path, remove_error);
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn write(
+        &self,
+        request: &SearchExportRequest,
+        columns: &[SearchExportColumn],
+        max_rows: usize,
+        target_path: &str,
+    ) -> Result<SearchExportSummary, AppError> {
+        let lang = request.lang.as_deref().map(str::trim).filter(|lang| !lang.is_empty());
+        let plan = search_plan(&self.pool, &request.query, lang).await?;
+
+        let file = tokio::fs::File::create(target_path).await?;
+        let mut writer = tokio::io::BufWriter::new(file);
+        if request.include_bom {
+            writer.write_all("\u{feff}".as_bytes()).await?;
+        }
+        let header: Vec<&str> = columns.iter().map(|column| column.header()).collect();
+        writer.write_all(csv_line(header).as_bytes()).await?;
+
+        let Some(plan) = plan else {
+            writer.flush().await?;
+            return Ok(SearchExportSummary {
+                path: target_path.to_string(),
+                row_count: 0,
+                total_matches: 0,
+                truncated: false,
+                warning: None,
+            });
+        };
+
+        let count_sql = format!("SELECT COUNT(*) {} {}", plan.from, plan.filter);
+        let mut count = sqlx::query_scalar::<_, i64>(&count_sql);
+        for value in &plan.binds {
+            count = count.bind(value);
+        }
+        let total_matches = count.fetch_one(&self.pool).await?.max(0) as usize;
+        let total = total_matches.min(max_rows);
+        self.emit_progress(0, total, ExportStatus::Starting);
+
+        let snippet = if columns.contains(&SearchExportColumn::Snippet) {
+            format!(
+                "COALESCE(NULLIF({}, ''), substr(e.body_text, 1, {}))",
+                plan.snippet, FALLBACK_SNIPPET_CHARS
+            )
+        } else {
+            "NULL".to_string()
+        };
+        let sql = format!(
+            r#"
+            SELECT e.date, e.sender, e.recipients, e.subject, p.name AS project_name,
+                   EXISTS (SELECT 1 FROM attachments a WHERE a.email_id = e.id) AS has_attachments,
+                   {} AS snippet
+            {}
+            LEFT JOIN projects p ON p.id = e.project_id
+            {}
+            {}
+            LIMIT ?
+            "#,
+            snippet, plan.from, plan.filter, plan.order
+        );
+        let mut query = sqlx::query_as::<_, ExportRow>(&sql);
+        for value in &plan.binds {
+            query = query.bind(value);
+        }
+        let mut rows = query.bind(max_rows as i64).fetch(&self.pool);
+
+        let mut row_count = 0;
+        while let Some(row) = rows.try_next().await? {
+            let fields: Vec<String> = columns.iter().map(|column| field(&row, *column)).collect();
+            writer.write_all(csv_line(fields).as_bytes()).await?;
+            row_count += 1;
+            if row_count % PROGRESS_INTERVAL == 0 {
+                self.emit_progress(row_count, total, ExportStatus::Writing);
+            }
+        }
+        writer.flush().await?;
+
+        let truncated = total_matches > row_count;
+        let warning = truncated.then(|| {
+            format!(
+                "{} emails match this search; only the first {} were exported",
+                total_matches, row_count
+            )
+        });
+        if let Some(warning) = &warning {
+            log::warn!("Search export truncated: {}", warning);
+        }
+
+        Ok(SearchExportSummary {
+            path: target_path.to_string(),
+            row_count,
+            total_matches,
+            truncated,
+            warning,
+        })
+    }
+}
+
+fn field(row: &ExportRow, column: SearchExportColumn) -> String {
+    match column {
+        SearchExportColumn::Date => row.date.clone().unwrap_or_default(),
+        SearchExportColumn::Sender => row.sender.clone().unwrap_or_default(),
+        SearchExportColumn::Recipients => parse_list(row.recipients.as_deref()).join("; "),
+        SearchExportColumn::Subject => row.subject.clone().unwrap_or_default(),
+        SearchExportColumn::Project => row.project_name.clone().unwrap_or_default(),
+        SearchExportColumn::HasAttachments => if row.has_attachments { "yes" } else { "no" }.to_string(),
+        SearchExportColumn::Snippet => row.snippet.clone().unwrap_or_default(),
+    }
+}
+
+/// 一行 CSV（RFC 4180，CRLF 结尾）
+fn csv_line<S: AsRef<str>>(fields: Vec<S>) -> String {
+    let mut line = fields
+        .iter()
+        .map(|value| escape_csv(value.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// 含逗号、引号或换行的字段加引号，引号写两次
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
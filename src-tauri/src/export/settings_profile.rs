@@ -0,0 +1,762 @@
+/// 设置配置包的导出与导入
+///
+/// 配置包是一个 JSON 文件，包含同步设置（含分类器参数）、发件人规则、回复模板、项目视图偏好、VIP 发件人，
+/// 可选包含账户（只有地址、服务商和服务器配置）。密码和 OAuth 令牌不会写入配置包，
+/// 导入的账户需要重新输入密码或重新授权。
+///
+/// 项目视图偏好按项目名称匹配，因为新机器上的项目 ID 不同；找不到同名项目的偏好会被跳过。
+/// 本机相关的设置（冷存储路径）不导出。
+use crate::commands::settings::{load_sync_settings, validate_settings_request, UpdateSyncSettingsRequest};
+use crate::error::AppError;
+use crate::mail::automated::normalize_pattern;
+use crate::mail::templates::{self, TemplateRequest};
+use crate::mail::vip;
+use crate::project::preferences::ProjectPreferences;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+
+/// 配置包格式版本（格式有不兼容的变化时递增）
+pub const PROFILE_SCHEMA_VERSION: i64 = 1;
+
+/// 不写入配置包的设置列（本机路径和账户 ID 在其他电脑上没有意义）
+const EXCLUDED_SETTINGS: &[&str] = &[
+    "id",
+    "version",
+    "created_at",
+    "updated_at",
+    "cold_storage_path",
+    "watch_folder_enabled",
+    "watch_folder_path",
+    "watch_folder_account_id",
+];
+
+/// 配置包
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsProfile {
+    pub schema_version: i64,
+    pub exported_at: String,
+    #[serde(default)]
+    pub app_version: Option<String>,
+    /// 同步设置（列名 → 值）
+    #[serde(default)]
+    pub sync_settings: Map<String, Value>,
+    #[serde(default)]
+    pub sender_rules: Vec<ProfileSenderRule>,
+    #[serde(default)]
+    pub templates: Vec<TemplateRequest>,
+    #[serde(default)]
+    pub project_preferences: Vec<ProfileProjectPreferences>,
+    #[serde(default)]
+    pub vip_contacts: Vec<ProfileVipContact>,
+    /// 导出时未选择包含账户则为空
+    #[serde(default)]
+    pub accounts: Option<Vec<ProfileAccount>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProfileSenderRule {
+    pub pattern: String,
+    pub is_automated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProfileVipContact {
+    pub address: String,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileProjectPreferences {
+    pub project_name: String,
+    pub preferences: ProjectPreferences,
+}
+
+/// 不含密钥的账户信息
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProfileAccount {
+    pub email: String,
+    pub provider: Option<String>,
+    pub imap_config: Option<String>,
+    pub auth_type: Option<String>,
+}
+
+/// 与本机已有数据冲突时的处理方式（同步设置总是整体应用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// 保留本机已有的规则、模板和偏好
+    KeepExisting,
+    /// 用配置包中的内容覆盖
+    Overwrite,
+}
+
+/// 导出结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileExportSummary {
+    pub path: String,
+    pub settings: usize,
+    pub sender_rules: usize,
+    pub templates: usize,
+    pub project_preferences: usize,
+    pub vip_contacts: usize,
+    pub accounts: usize,
+}
+
+/// 导入冲突
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportConflict {
+    /// "sender_rule" / "template" / "project_preferences" / "vip_contact" / "account"
+    pub kind: String,
+    pub key: String,
+    /// "kept_existing" / "overwritten" / "skipped"
+    pub resolution: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileImportReport {
+    pub settings_applied: usize,
+    pub sender_rules_imported: usize,
+    pub templates_imported: usize,
+    pub project_preferences_imported: usize,
+    pub vip_contacts_imported: usize,
+    pub accounts_imported: usize,
+    pub conflicts: Vec<ImportConflict>,
+    /// 新导入、需要重新输入密码或重新授权的账户
+    pub accounts_needing_credentials: Vec<String>,
+}
+
+impl ProfileImportReport {
+    fn conflict(&mut self, kind: &str, key: &str, resolution: &str, reason: Option<String>) {
+        self.conflicts.push(ImportConflict {
+            kind: kind.to_string(),
+            key: key.to_string(),
+            resolution: resolution.to_string(),
+            reason,
+        });
+    }
+}
+
+/// 配置包导出 / 导入
+pub struct SettingsProfileManager {
+    pool: SqlitePool,
+}
+
+impl SettingsProfileManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 导出配置包到 `target_path`
+    pub async fn export(&self, target_path: &str, include_accounts: bool) -> Result<ProfileExportSummary, AppError> {
+        let profile = self.build(include_accounts).await?;
+        let json = serde_json::to_string_pretty(&profile)?;
+        tokio::fs::write(target_path, json.as_bytes()).await?;
+
+        log::info!("Exported settings profile to {}", target_path);
+        Ok(ProfileExportSummary {
+            path: target_path.to_string(),
+            settings: profile.sync_settings.len(),
+            sender_rules: profile.sender_rules.len(),
+            templates: profile.templates.len(),
+            project_preferences: profile.project_preferences.len(),
+            vip_contacts: profile.vip_contacts.len(),
+            accounts: profile.accounts.as_ref().map_or(0, Vec::len),
+        })
+    }
+
+    async fn build(&self, include_accounts: bool) -> Result<SettingsProfile, AppError> {
+        let settings = load_sync_settings(&self.pool).await?;
+        let mut sync_settings = match serde_json::to_value(&settings)? {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        };
+        for key in EXCLUDED_SETTINGS {
+            sync_settings.remove(*key);
+        }
+
+        let sender_rules = sqlx::query_as::<_, ProfileSenderRule>(
+            "SELECT pattern, COALESCE(is_automated, 0) AS is_automated FROM sender_rules ORDER BY pattern"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let templates = sqlx::query_as::<_, (String, String, String, bool)>(
+            "SELECT name, subject_template, body_template, COALESCE(is_html, 0) FROM templates ORDER BY name COLLATE NOCASE"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|(name, subject_template, body_template, is_html)| TemplateRequest {
+            name,
+            subject_template,
+            body_template,
+            is_html,
+        })
+        .collect();
+
+        let project_preferences = self.export_project_preferences().await?;
+
+        let vip_contacts = sqlx::query_as::<_, ProfileVipContact>(
+            "SELECT address, name FROM vip_contacts ORDER BY created_at, address"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let accounts = if include_accounts {
+            Some(
+                sqlx::query_as::<_, ProfileAccount>(
+                    "SELECT email, provider, imap_config, auth_type FROM accounts ORDER BY id"
+                )
+                .fetch_all(&self.pool)
+                .await?,
+            )
+        } else {
+            None
+        };
+
+        Ok(SettingsProfile {
+            schema_version: PROFILE_SCHEMA_VERSION,
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            sync_settings,
+            sender_rules,
+            templates,
+            project_preferences,
+            vip_contacts,
+            accounts,
+        })
+    }
+
+    async fn export_project_preferences(&self) -> Result<Vec<ProfileProjectPreferences>, AppError> {
+        let rows: Vec<(String, Option<String>, Option<String>, bool, Option<String>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT p.name, pp.default_tab, pp.sort_order, COALESCE(pp.collapsed_threads, 0), pp.custom, pp.extra
+            FROM project_preferences pp
+            JOIN projects p ON p.id = pp.project_id
+            WHERE p.status != 'deleted'
+            ORDER BY p.name
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(project_name, default_tab, sort_order, collapsed_threads, custom, extra)| ProfileProjectPreferences {
+                project_name,
+                preferences: ProjectPreferences {
+                    default_tab,
+                    sort_order,
+                    collapsed_threads,
+                    custom: custom.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default(),
+                    extra: extra.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default(),
+                },
+            })
+            .collect())
+    }
+
+    /// 从 `path` 导入配置包
+    ///
+    /// 先校验格式版本和全部内容，再在一个事务中写入；任何一项校验失败都不会修改本机数据。
+    pub async fn import(&self, path: &str, strategy: MergeStrategy) -> Result<ProfileImportReport, AppError> {
+        let json = tokio::fs::read_to_string(path).await?;
+        let profile: SettingsProfile = serde_json::from_str(&json)
+            .map_err(|e| AppError::Validation(format!("Not a valid settings profile: {}", e)))?;
+        if profile.schema_version < 1 || profile.schema_version > PROFILE_SCHEMA_VERSION {
+            return Err(AppError::Validation(format!(
+                "Unsupported settings profile version {} (supported: 1-{})",
+                profile.schema_version, PROFILE_SCHEMA_VERSION
+            )));
+        }
+
+        let settings = self.validate_settings(&profile.sync_settings).await?;
+        let sender_rules = profile
+            .sender_rules
+            .iter()
+            .map(|rule| Ok((normalize_pattern(&rule.pattern)?, rule.is_automated)))
+            .collect::<Result<Vec<_>, AppError>>()?;
+        for template in &profile.templates {
+            templates::validate(template)?;
+        }
+        for entry in &profile.project_preferences {
+            entry.preferences.validate()?;
+        }
+        let vip_contacts = profile
+            .vip_contacts
+            .iter()
+            .map(|contact| {
+                let name = contact.name.as_deref().map(str::trim).filter(|name| !name.is_empty());
+                Ok((vip::normalize_address(&contact.address)?, name))
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+
+        let mut report = ProfileImportReport::default();
+        let mut tx = self.pool.begin().await?;
+
+        if !settings.is_empty() {
+            let assignments: Vec<String> = settings.iter().map(|(column, _)| format!("{} = ?", column)).collect();
+            let sql = format!(
+                "UPDATE sync_settings SET {}, version = version + 1, updated_at = CURRENT_TIMESTAMP WHERE id = 1",
+                assignments.join(", ")
+            );
+            let mut query = sqlx::query(&sql);
+            for (_, value) in &settings {
+                query = match value {
+                    Value::Bool(value) => query.bind(*value),
+                    Value::Number(number) => match number.as_i64() {
+                        Some(value) => query.bind(value),
+                        None => query.bind(number.as_f64()),
+                    },
+                    Value::String(value) => query.bind(value.clone()),
+                    _ => query.bind(None::<String>),
+                };
+            }
+            query.execute(&mut *tx).await?;
+            report.settings_applied = settings.len();
+        }
+
+        for (pattern, is_automated) in &sender_rules {
+            let existing: Option<bool> = sqlx::query_scalar("SELECT is_automated FROM sender_rules WHERE pattern = ?")
+                .bind(pattern)
+                .fetch_optional(&mut *tx)
+                .await?;
+            match existing {
+                Some(current) if current == *is_automated => continue,
+                Some(_) if strategy == MergeStrategy::KeepExisting => {
+                    report.conflict("sender_rule", pattern, "kept_existing", None);
+                    continue;
+                }
+                Some(_) => report.conflict("sender_rule", pattern, "overwritten", None),
+                None => {}
+            }
+            sqlx::query(
+                r#"
+                INSERT INTO sender_rules (pattern, is_automated) VALUES (?, ?)
+                ON CONFLICT(pattern) DO UPDATE SET is_automated = excluded.is_automated
+                "#
+            )
+            .bind(pattern)
+            .bind(is_automated)
+            .execute(&mut *tx)
+            .await?;
+            report.sender_rules_imported += 1;
+        }
+
+        for template in &profile.templates {
+            let name = template.name.trim();
+            let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM templates WHERE name = ?")
+                .bind(name)
+                .fetch_optional(&mut *tx)
+                .await?;
+            match existing {
+                Some(_) if strategy == MergeStrategy::KeepExisting => {
+                    report.conflict("template", name, "kept_existing", None);
+                }
+                Some(id) => {
+                    sqlx::query(
+                        r#"
+                        UPDATE templates
+                        SET subject_template = ?, body_template = ?, is_html = ?, updated_at = CURRENT_TIMESTAMP
+                        WHERE id = ?
+                        "#
+                    )
+                    .bind(&template.subject_template)
+                    .bind(&template.body_template)
+                    .bind(template.is_html)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                    report.conflict("template", name, "overwritten", None);
+                    report.templates_imported += 1;
+                }
+                None => {
+                    sqlx::query(
+                        "INSERT INTO templates (name, subject_template, body_template, is_html) VALUES (?, ?, ?, ?)"
+                    )
+                    .bind(name)
+                    .bind(&template.subject_template)
+                    .bind(&template.body_template)
+                    .bind(template.is_html)
+                    .execute(&mut *tx)
+                    .await?;
+                    report.templates_imported += 1;
+                }
+            }
+        }
+
+        for entry in &profile.project_preferences {
+            let project_ids: Vec<i64> =
+                sqlx::query_scalar("SELECT id FROM projects WHERE name = ? AND status != 'deleted'")
+                    .bind(&entry.project_name)
+                    .fetch_all(&mut *tx)
+                    .await?;
+            let project_id = match project_ids.as_slice() {
+                [id] => *id,
+                [] => {
+                    report.conflict(
+                        "project_preferences",
+                        &entry.project_name,
+                        "skipped",
+                        Some("No project with this name".to_string()),
+                    );
+                    continue;
+                }
+                _ => {
+                    report.conflict(
+                        "project_preferences",
+                        &entry.project_name,
+                        "skipped",
+                        Some("Several projects have this name".to_string()),
+                    );
+                    continue;
+                }
+            };
+
+            let existing: Option<i64> =
+                sqlx::query_scalar("SELECT project_id FROM project_preferences WHERE project_id = ?")
+                    .bind(project_id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            if existing.is_some() {
+                if strategy == MergeStrategy::KeepExisting {
+                    report.conflict("project_preferences", &entry.project_name, "kept_existing", None);
+                    continue;
+                }
+                report.conflict("project_preferences", &entry.project_name, "overwritten", None);
+            }
+
+            let preferences = &entry.preferences;
+            sqlx::query(
+                r#"
+                INSERT INTO project_preferences (
+                    project_id, default_tab, sort_order, collapsed_threads, custom, extra, updated_at
+                ) VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+                ON CONFLICT(project_id) DO UPDATE SET
+                    default_tab = excluded.default_tab,
+                    sort_order = excluded.sort_order,
+                    collapsed_threads = excluded.collapsed_threads,
+                    custom = excluded.custom,
+                    extra = excluded.extra,
+                    updated_at = excluded.updated_at
+                "#
+            )
+            .bind(project_id)
+            .bind(&preferences.default_tab)
+            .bind(&preferences.sort_order)
+            .bind(preferences.collapsed_threads)
+            .bind(serde_json::to_string(&preferences.custom)?)
+            .bind(serde_json::to_string(&preferences.extra)?)
+            .execute(&mut *tx)
+            .await?;
+            report.project_preferences_imported += 1;
+        }
+
+        for (address, name) in &vip_contacts {
+            let existing: Option<Option<String>> =
+                sqlx::query_scalar("SELECT name FROM vip_contacts WHERE address = ?")
+                    .bind(address)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            match existing {
+                Some(current) if name.is_none() || current.as_deref() == *name => continue,
+                Some(_) if strategy == MergeStrategy::KeepExisting => {
+                    report.conflict("vip_contact", address, "kept_existing", None);
+                    continue;
+                }
+                Some(_) => report.conflict("vip_contact", address, "overwritten", None),
+                None => {}
+            }
+            sqlx::query(
+                r#"
+                INSERT INTO vip_contacts (address, name) VALUES (?, ?)
+                ON CONFLICT(address) DO UPDATE SET name = excluded.name
+                "#
+            )
+            .bind(address)
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+            report.vip_contacts_imported += 1;
+        }
+
+        // 已有账户保留本机的配置和凭据，不受合并策略影响
+        for account in profile.accounts.iter().flatten() {
+            let email = account.email.trim();
+            if email.is_empty() {
+                continue;
+            }
+            let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM accounts WHERE email = ?")
+                .bind(email)
+                .fetch_optional(&mut *tx)
+                .await?;
+            if existing.is_some() {
+                report.conflict(
+                    "account",
+                    email,
+                    "kept_existing",
+                    Some("Account already exists".to_string()),
+                );
+                continue;
+            }
+            sqlx::query("INSERT INTO accounts (email, provider, imap_config, auth_type) VALUES (?, ?, ?, ?)")
+                .bind(email)
+                .bind(&account.provider)
+                .bind(&account.imap_config)
+                .bind(account.auth_type.as_deref().unwrap_or("password"))
+                .execute(&mut *tx)
+                .await?;
+            report.accounts_imported += 1;
+            report.accounts_needing_credentials.push(email.to_string());
+        }
+
+        tx.commit().await?;
+
+        log::info!(
+            "Imported settings profile from {}: {} settings, {} sender rules, {} templates, {} project preferences, {} VIP senders, {} accounts, {} conflicts",
+            path,
+            report.settings_applied,
+            report.sender_rules_imported,
+            report.templates_imported,
+            report.project_preferences_imported,
+            report.vip_contacts_imported,
+            report.accounts_imported,
+            report.conflicts.len()
+        );
+        Ok(report)
+    }
+
+    /// 校验配置包中的同步设置，返回可以写入的 (列名, 值)
+    ///
+    /// 本机不存在的列（较新版本导出的设置）和不导出的列被忽略。
+    async fn validate_settings(&self, values: &Map<String, Value>) -> Result<Vec<(String, Value)>, AppError> {
+        if values.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // 用当前设置补齐缺少的必填字段后按更新请求校验
+        let current = load_sync_settings(&self.pool).await?;
+        let mut request = match serde_json::to_value(&current)? {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        };
+        for (key, value) in values {
+            request.insert(key.clone(), value.clone());
+        }
+        request.insert("expected_version".to_string(), Value::from(current.version));
+        let request: UpdateSyncSettingsRequest = serde_json::from_value(Value::Object(request))
+            .map_err(|e| AppError::Validation(format!("Invalid settings in profile: {}", e)))?;
+        validate_settings_request(&request)?;
+
+        let columns: HashSet<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('sync_settings')")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .collect();
+
+        Ok(values
+            .iter()
+            .filter(|(key, _)| columns.contains(key.as_str()) && !EXCLUDED_SETTINGS.contains(&key.as_str()))
+            .filter(|(_, value)| !matches!(value, Value::Array(_) | Value::Object(_)))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::fixtures::insert_project;
+    use crate::storage::database::test_pool;
+    use std::path::PathBuf;
+
+    fn profile_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("threadline-test-profile-{}-{}.json", std::process::id(), name))
+    }
+
+    async fn write_profile(name: &str, profile: Value) -> String {
+        let path = profile_path(name);
+        tokio::fs::write(&path, profile.to_string()).await.unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    async fn max_sync_count(pool: &SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT max_sync_count FROM sync_settings WHERE id = 1")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn export_omits_secrets_and_round_trips_into_a_new_database() {
+        let (source, _source_dir) = test_pool().await;
+        sqlx::query("UPDATE sync_settings SET max_sync_count = 250, cold_storage_path = '/mnt/cold' WHERE id = 1")
+            .execute(&source)
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO accounts (email, provider, auth_type, password, oauth_access_token, oauth_refresh_token)
+            VALUES ('me@example.com', 'gmail', 'oauth', 'hunter2', 'access-secret', 'refresh-secret')
+            "#
+        )
+        .execute(&source)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO sender_rules (pattern, is_automated) VALUES ('@news.example.com', 1)")
+            .execute(&source)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO templates (name, subject_template, body_template) VALUES ('Thanks', '', 'Thank you, {{sender_name}}')")
+            .execute(&source)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO vip_contacts (address, name) VALUES ('boss@example.com', 'Boss')")
+            .execute(&source)
+            .await
+            .unwrap();
+        let project_id = insert_project(&source, "Alpha").await;
+        sqlx::query("INSERT INTO project_preferences (project_id, default_tab, custom, extra) VALUES (?, 'artifacts', 'null', '{}')")
+            .bind(project_id)
+            .execute(&source)
+            .await
+            .unwrap();
+
+        let path = profile_path("round-trip");
+        let path = path.to_string_lossy().into_owned();
+        let summary = SettingsProfileManager::new(source).export(&path, true).await.unwrap();
+        assert_eq!((summary.sender_rules, summary.templates, summary.vip_contacts), (1, 1, 1));
+        assert_eq!((summary.project_preferences, summary.accounts), (1, 1));
+
+        let json = tokio::fs::read_to_string(&path).await.unwrap();
+        for secret in ["hunter2", "access-secret", "refresh-secret", "/mnt/cold", "cold_storage_path"] {
+            assert!(!json.contains(secret), "profile contains {}", secret);
+        }
+
+        let (target, _target_dir) = test_pool().await;
+        insert_project(&target, "Alpha").await;
+        let report = SettingsProfileManager::new(target.clone())
+            .import(&path, MergeStrategy::KeepExisting)
+            .await
+            .unwrap();
+        assert!(report.conflicts.is_empty(), "{:?}", report.conflicts);
+        assert_eq!(report.accounts_needing_credentials, vec!["me@example.com".to_string()]);
+        assert_eq!(max_sync_count(&target).await, 250);
+
+        let (auth_type, password, refresh): (String, Option<String>, Option<String>) =
+            sqlx::query_as("SELECT auth_type, password, oauth_refresh_token FROM accounts WHERE email = 'me@example.com'")
+                .fetch_one(&target)
+                .await
+                .unwrap();
+        assert_eq!((auth_type.as_str(), password, refresh), ("oauth", None, None));
+        let default_tab: Option<String> = sqlx::query_scalar("SELECT default_tab FROM project_preferences")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert_eq!(default_tab.as_deref(), Some("artifacts"));
+        let cold: Option<String> = sqlx::query_scalar("SELECT cold_storage_path FROM sync_settings WHERE id = 1")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+        assert!(cold.unwrap_or_default().is_empty());
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn merge_strategy_decides_conflicts() {
+        let profile = serde_json::json!({
+            "schema_version": 1,
+            "exported_at": "2024-03-01T00:00:00Z",
+            "sender_rules": [{ "pattern": "@News.Example.com", "is_automated": true }],
+            "templates": [{ "name": "Thanks", "subject_template": "", "body_template": "New body", "is_html": false }],
+        });
+        let path = write_profile("conflicts", profile).await;
+
+        for (strategy, resolution, expected_body, expected_automated) in [
+            (MergeStrategy::KeepExisting, "kept_existing", "Old body", false),
+            (MergeStrategy::Overwrite, "overwritten", "New body", true),
+        ] {
+            let (pool, _db_dir) = test_pool().await;
+            sqlx::query("INSERT INTO sender_rules (pattern, is_automated) VALUES ('@news.example.com', 0)")
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO templates (name, body_template) VALUES ('Thanks', 'Old body')")
+                .execute(&pool)
+                .await
+                .unwrap();
+
+            let report = SettingsProfileManager::new(pool.clone()).import(&path, strategy).await.unwrap();
+            let conflicts: Vec<(&str, &str, &str)> = report
+                .conflicts
+                .iter()
+                .map(|c| (c.kind.as_str(), c.key.as_str(), c.resolution.as_str()))
+                .collect();
+            assert_eq!(
+                conflicts,
+                vec![("sender_rule", "@news.example.com", resolution), ("template", "Thanks", resolution)]
+            );
+
+            let body: String = sqlx::query_scalar("SELECT body_template FROM templates WHERE name = 'Thanks'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            let automated: bool = sqlx::query_scalar("SELECT is_automated FROM sender_rules WHERE pattern = '@news.example.com'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!((body.as_str(), automated), (expected_body, expected_automated));
+        }
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn invalid_profiles_change_nothing() {
+        let (pool, _db_dir) = test_pool().await;
+        let before = max_sync_count(&pool).await;
+        let manager = SettingsProfileManager::new(pool.clone());
+
+        let newer = write_profile("newer", serde_json::json!({
+            "schema_version": PROFILE_SCHEMA_VERSION + 1,
+            "exported_at": "2024-03-01T00:00:00Z",
+            "sync_settings": { "max_sync_count": before + 1 },
+        }))
+        .await;
+        assert!(matches!(manager.import(&newer, MergeStrategy::Overwrite).await, Err(AppError::Validation(_))));
+
+        let bad_rule = write_profile("bad-rule", serde_json::json!({
+            "schema_version": 1,
+            "exported_at": "2024-03-01T00:00:00Z",
+            "sync_settings": { "max_sync_count": before + 1 },
+            "sender_rules": [{ "pattern": "not-an-address", "is_automated": true }],
+        }))
+        .await;
+        assert!(matches!(manager.import(&bad_rule, MergeStrategy::Overwrite).await, Err(AppError::Validation(_))));
+
+        let bad_offset = write_profile("bad-offset", serde_json::json!({
+            "schema_version": 1,
+            "exported_at": "2024-03-01T00:00:00Z",
+            "sync_settings": { "max_sync_count": before + 1, "utc_offset_minutes": 25 * 60 },
+        }))
+        .await;
+        assert!(matches!(
+            manager.import(&bad_offset, MergeStrategy::Overwrite).await,
+            Err(AppError::InvalidField { field, .. }) if field == "utc_offset_minutes"
+        ));
+
+        assert_eq!(max_sync_count(&pool).await, before);
+        let rules: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sender_rules").fetch_one(&pool).await.unwrap();
+        assert_eq!(rules, 0);
+
+        for path in [newer, bad_rule, bad_offset] {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+    }
+}
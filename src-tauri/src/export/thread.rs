@@ -0,0 +1,521 @@
+/// 单个线程导出（分享给项目以外的人）
+///
+/// 三种格式：
+/// - `eml`：.zip，每封邮件一个 .eml（由保存的邮件头、正文和附件重新生成，附件内嵌），
+///   另在 `attachments/{序号}/` 下单独放一份附件，方便不用邮件客户端直接打开
+/// - `html`：单个自包含的 HTML 记录（按时间顺序，含发件人 / 日期等邮件头和附件引用，不引用外部资源）
+/// - `pdf`：单个 PDF 记录，使用邮件 PDF 导出的排版
+///
+/// 线程中的邮件分属多个项目时仍然完整导出，并在记录开头（zip 中为 `README.txt`）说明拆分情况。
+/// 压缩包内的文件名统一清理，在各系统上都能解压。
+use crate::error::AppError;
+use crate::export::email_pdf::{html_to_text, EmailPdfExporter};
+use crate::mail::recipients::{parse_list, split_sender};
+use crate::mail::sync::sanitize_filename;
+use crate::repository::project::thread_id_variants;
+use crate::storage::{body_store, file_manager};
+use crate::utils::format_file_size;
+use crate::utils::i18n::parse_timestamp;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::Message;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// 压缩包内文件名中主题部分的最大字符数
+const MAX_NAME_CHARS: usize = 60;
+
+/// 发件人地址无法解析时 .eml 中使用的占位地址（`.invalid` 为保留域名）
+const UNKNOWN_SENDER: &str = "unknown@unknown.invalid";
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThreadExportFormat {
+    Eml,
+    Html,
+    Pdf,
+}
+
+/// 线程中的邮件所属的项目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadExportProject {
+    /// 未归入项目的邮件为 None
+    pub project_id: Option<i64>,
+    pub name: String,
+    pub email_count: usize,
+}
+
+/// 导出结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThreadExportSummary {
+    pub path: String,
+    pub format: ThreadExportFormat,
+    pub email_count: usize,
+    pub attachment_count: usize,
+    /// 文件缺失而未能打包的附件
+    pub missing_attachments: Vec<String>,
+    pub projects: Vec<ThreadExportProject>,
+    /// 线程分属多个项目时的说明（同时写入导出文件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split_note: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ThreadEmailRow {
+    id: i64,
+    message_id: String,
+    in_reply_to: Option<String>,
+    project_id: Option<i64>,
+    project_name: Option<String>,
+    subject: Option<String>,
+    sender: Option<String>,
+    sender_name: Option<String>,
+    sender_address: Option<String>,
+    recipients: Option<String>,
+    cc: Option<String>,
+    date: Option<String>,
+    body_text: Option<String>,
+    body_html: Option<String>,
+    body_truncated: bool,
+    body_path: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ThreadAttachmentRow {
+    id: i64,
+    filename: Option<String>,
+    file_size: Option<i64>,
+    mime_type: Option<String>,
+    file_path: Option<String>,
+}
+
+/// 读取了完整正文和附件位置的邮件
+struct ExportEmail {
+    row: ThreadEmailRow,
+    text: String,
+    html: Option<String>,
+    attachments: Vec<ExportAttachment>,
+}
+
+struct ExportAttachment {
+    filename: String,
+    file_size: Option<i64>,
+    mime_type: String,
+    /// 本地文件（缺失或冷存储未挂载时为 None）
+    source: Option<PathBuf>,
+}
+
+/// 线程导出
+pub struct ThreadExporter {
+    pool: SqlitePool,
+}
+
+impl ThreadExporter {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 导出线程到 `target_path`
+    pub async fn export(
+        &self,
+        thread_id: &str,
+        target_path: &str,
+        format: ThreadExportFormat,
+    ) -> Result<ThreadExportSummary, AppError> {
+        let (bare, bracketed) = thread_id_variants(thread_id)?;
+        let rows = sqlx::query_as::<_, ThreadEmailRow>(
+            r#"
+            SELECT e.id, e.message_id, e.in_reply_to, e.project_id, p.name AS project_name,
+                   e.subject, e.sender, e.sender_name, e.sender_address, e.recipients, e.cc, e.date,
+                   e.body_text, e.body_html, COALESCE(e.body_truncated, 0) AS body_truncated, e.body_path
+            FROM emails e
+            LEFT JOIN projects p ON p.id = e.project_id
+            WHERE e.thread_id IN (?, ?) AND e.duplicate_of IS NULL
+            ORDER BY datetime(e.date) ASC, e.id ASC
+            "#
+        )
+        .bind(&bare)
+        .bind(&bracketed)
+        .fetch_all(&self.pool)
+        .await?;
+        if rows.is_empty() {
+            return Err(AppError::Validation(format!("Thread {} has no emails", thread_id)));
+        }
+
+        let projects = thread_projects(&rows);
+        let split_note = split_note(&projects);
+        if split_note.is_some() {
+            log::info!("Thread {} spans {} projects, exporting all of it", thread_id, projects.len());
+        }
+
+        if format == ThreadExportFormat::Pdf {
+            let email_count = rows.len();
+            let pdf = EmailPdfExporter::new(self.pool.clone())
+                .export_thread_with_note(thread_id, target_path, true, split_note.clone())
+                .await?;
+            return Ok(ThreadExportSummary {
+                path: pdf.path,
+                format,
+                email_count,
+                attachment_count: pdf.attachment_count,
+                missing_attachments: Vec::new(),
+                projects,
+                split_note,
+            });
+        }
+
+        let emails = self.prepare(rows).await?;
+        let email_count = emails.len();
+        let attachment_count = emails.iter().map(|email| email.attachments.len()).sum();
+        let missing_attachments: Vec<String> = emails
+            .iter()
+            .flat_map(|email| &email.attachments)
+            .filter(|attachment| attachment.source.is_none())
+            .map(|attachment| attachment.filename.clone())
+            .collect();
+
+        let target = PathBuf::from(target_path);
+        match format {
+            ThreadExportFormat::Html => {
+                let html = render_html(&emails, split_note.as_deref());
+                tokio::fs::write(&target, html).await?;
+            }
+            _ => {
+                // 先写入临时文件，完成后再改名，失败时不留下不完整的压缩包
+                let partial = target.with_extension("zip.part");
+                let write_partial = partial.clone();
+                let note = split_note.clone();
+                let result = tokio::task::spawn_blocking(move || write_zip(&write_partial, &emails, note.as_deref())).await?;
+                if let Err(e) = result {
+                    let _ = std::fs::remove_file(&partial);
+                    return Err(e);
+                }
+                tokio::fs::rename(&partial, &target).await?;
+            }
+        }
+
+        log::info!(
+            "Exported thread {} as {:?} to {} ({} emails, {} attachments, {} missing)",
+            thread_id, format, target_path, email_count, attachment_count, missing_attachments.len()
+        );
+        Ok(ThreadExportSummary {
+            path: target_path.to_string(),
+            format,
+            email_count,
+            attachment_count,
+            missing_attachments,
+            projects,
+            split_note,
+        })
+    }
+
+    /// 读取完整正文和附件
+    async fn prepare(&self, rows: Vec<ThreadEmailRow>) -> Result<Vec<ExportEmail>, AppError> {
+        let mut emails = Vec::with_capacity(rows.len());
+        for row in rows {
+            let (mut text, mut html) = (row.body_text.clone(), row.body_html.clone());
+            if row.body_truncated {
+                if let Some(path) = row.body_path.as_deref() {
+                    let full = body_store::read_full_body(path).await?;
+                    text = full.text;
+                    html = full.html;
+                }
+            }
+            let html = html.filter(|html| !html.trim().is_empty());
+            let text = match text.filter(|text| !text.trim().is_empty()) {
+                Some(text) => text,
+                None => html.as_deref().map(html_to_text).unwrap_or_default(),
+            };
+
+            let attachments = sqlx::query_as::<_, ThreadAttachmentRow>(
+                r#"
+                SELECT id, filename, file_size, mime_type, file_path
+                FROM attachments
+                WHERE email_id = ?
+                ORDER BY id ASC
+                "#
+            )
+            .bind(row.id)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|attachment| ExportAttachment {
+                filename: attachment
+                    .filename
+                    .filter(|name| !name.trim().is_empty())
+                    .unwrap_or_else(|| format!("attachment-{}", attachment.id)),
+                file_size: attachment.file_size,
+                mime_type: attachment
+                    .mime_type
+                    .filter(|mime| !mime.trim().is_empty())
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+                source: attachment
+                    .file_path
+                    .as_deref()
+                    .and_then(|path| file_manager::resolve_attachment_path(path).ok())
+                    .filter(|path| path.is_file()),
+            })
+            .collect();
+
+            emails.push(ExportEmail { row, text, html, attachments });
+        }
+        Ok(emails)
+    }
+}
+
+/// 按邮件数统计线程所属的项目（按首次出现的顺序）
+fn thread_projects(rows: &[ThreadEmailRow]) -> Vec<ThreadExportProject> {
+    let mut projects: Vec<ThreadExportProject> = Vec::new();
+    for row in rows {
+        match projects.iter_mut().find(|project| project.project_id == row.project_id) {
+            Some(project) => project.email_count += 1,
+            None => projects.push(ThreadExportProject {
+                project_id: row.project_id,
+                name: row
+                    .project_name
+                    .clone()
+                    .filter(|_| row.project_id.is_some())
+                    .unwrap_or_else(|| "Unassigned".to_string()),
+                email_count: 1,
+            }),
+        }
+    }
+    projects
+}
+
+/// 线程分属多个项目时的说明
+fn split_note(projects: &[ThreadExportProject]) -> Option<String> {
+    if projects.len() < 2 {
+        return None;
+    }
+    let parts: Vec<String> = projects
+        .iter()
+        .map(|project| {
+            let unit = if project.email_count == 1 { "message" } else { "messages" };
+            format!("{} ({} {})", project.name, project.email_count, unit)
+        })
+        .collect();
+    Some(format!(
+        "Note: this conversation is split across {} projects: {}. All messages are included below.",
+        projects.len(),
+        parts.join(", ")
+    ))
+}
+
+/// 压缩包内使用的文件名：去掉路径分隔符和控制字符，截断过长的名称
+fn safe_name(name: &str, max_chars: usize) -> String {
+    let cleaned: String = sanitize_filename(name)
+        .chars()
+        .map(|c| if c.is_control() { '_' } else { c })
+        .collect();
+    let collapsed = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let truncated: String = collapsed.chars().take(max_chars).collect();
+    // Windows 不允许以点或空格结尾
+    let trimmed = truncated.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    if trimmed.is_empty() {
+        "untitled".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// 压缩包内附件文件名：保留扩展名，主体部分截断
+fn safe_attachment_name(filename: &str) -> String {
+    match filename.rsplit_once('.').filter(|(stem, ext)| !stem.is_empty() && !ext.is_empty()) {
+        Some((stem, extension)) => format!("{}.{}", safe_name(stem, MAX_NAME_CHARS), safe_name(extension, 16)),
+        None => safe_name(filename, MAX_NAME_CHARS),
+    }
+}
+
+/// `.eml` 文件名：`序号_日期_主题.eml`
+fn eml_name(index: usize, email: &ExportEmail) -> String {
+    let date = email
+        .row
+        .date
+        .as_deref()
+        .and_then(parse_timestamp)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| "undated".to_string());
+    let subject = safe_name(email.row.subject.as_deref().unwrap_or("No Subject"), MAX_NAME_CHARS);
+    format!("{:03}_{}_{}.eml", index + 1, date, subject)
+}
+
+fn mailbox(name: Option<String>, address: Option<&str>) -> Option<Mailbox> {
+    let address = address?.trim().parse().ok()?;
+    Some(Mailbox::new(name, address))
+}
+
+/// 由保存的邮件头、正文和附件重新生成 MIME 邮件
+fn build_eml(email: &ExportEmail) -> Result<Vec<u8>, AppError> {
+    let row = &email.row;
+    let from = mailbox(row.sender_name.clone(), row.sender_address.as_deref())
+        .or_else(|| {
+            let (name, address) = split_sender(row.sender.as_deref().unwrap_or_default());
+            mailbox(name, address.as_deref())
+        })
+        .unwrap_or_else(|| {
+            Mailbox::new(row.sender.clone(), UNKNOWN_SENDER.parse().expect("valid placeholder address"))
+        });
+
+    let mut builder = Message::builder()
+        .from(from)
+        .subject(row.subject.clone().unwrap_or_default())
+        .message_id(Some(format!("<{}>", row.message_id.trim_matches(|c| c == '<' || c == '>'))));
+    if let Some(date) = row.date.as_deref().and_then(parse_timestamp) {
+        builder = builder.date(date.into());
+    }
+    if let Some(parent) = row.in_reply_to.as_deref().filter(|parent| !parent.trim().is_empty()) {
+        let parent = format!("<{}>", parent.trim().trim_matches(|c| c == '<' || c == '>'));
+        builder = builder.in_reply_to(parent.clone()).references(parent);
+    }
+    for entry in parse_list(row.recipients.as_deref()) {
+        let (name, address) = split_sender(&entry);
+        if let Some(mailbox) = mailbox(name, address.as_deref()) {
+            builder = builder.to(mailbox);
+        }
+    }
+    for entry in parse_list(row.cc.as_deref()) {
+        let (name, address) = split_sender(&entry);
+        if let Some(mailbox) = mailbox(name, address.as_deref()) {
+            builder = builder.cc(mailbox);
+        }
+    }
+
+    let mut mixed = match &email.html {
+        Some(html) => MultiPart::mixed().multipart(MultiPart::alternative_plain_html(email.text.clone(), html.clone())),
+        None => MultiPart::mixed().singlepart(SinglePart::plain(email.text.clone())),
+    };
+    for attachment in &email.attachments {
+        let Some(source) = &attachment.source else { continue };
+        let content_type = ContentType::parse(&attachment.mime_type)
+            .unwrap_or_else(|_| ContentType::parse("application/octet-stream").expect("valid content type"));
+        mixed = mixed.singlepart(Attachment::new(attachment.filename.clone()).body(std::fs::read(source)?, content_type));
+    }
+
+    let message = builder
+        .multipart(mixed)
+        .map_err(|e| AppError::Generic(format!("Failed to build message {}: {}", row.id, e)))?;
+    Ok(message.formatted())
+}
+
+/// 写入压缩包：每封邮件一个 .eml，附件另存一份，拆分说明写入 README.txt
+fn write_zip(path: &Path, emails: &[ExportEmail], note: Option<&str>) -> Result<(), AppError> {
+    let options = || -> FileOptions<'static, ()> { FileOptions::default().compression_method(CompressionMethod::Deflated) };
+    let zip_error = |e: zip::result::ZipError| AppError::FileSystem(format!("Failed to write archive: {}", e));
+
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+    if let Some(note) = note {
+        zip.start_file("README.txt", options()).map_err(zip_error)?;
+        zip.write_all(note.as_bytes())?;
+        zip.write_all(b"\n")?;
+    }
+
+    let mut used_paths = HashSet::new();
+    for (index, email) in emails.iter().enumerate() {
+        zip.start_file(eml_name(index, email), options()).map_err(zip_error)?;
+        zip.write_all(&build_eml(email)?)?;
+
+        for attachment in &email.attachments {
+            let Some(source) = &attachment.source else { continue };
+            let name = safe_attachment_name(&attachment.filename);
+            let mut archive_path = format!("attachments/{:03}/{}", index + 1, name);
+            // 同一封邮件中的同名附件加序号区分
+            let mut counter = 1;
+            while !used_paths.insert(archive_path.clone()) {
+                counter += 1;
+                archive_path = format!("attachments/{:03}/{}_{}", index + 1, counter, name);
+            }
+            zip.start_file(archive_path.as_str(), options()).map_err(zip_error)?;
+            std::io::copy(&mut File::open(source)?, &mut zip)?;
+        }
+    }
+
+    zip.finish().map_err(zip_error)?.flush()?;
+    Ok(())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 自包含的 HTML 记录（内联样式，不引用外部资源和远程图片）
+fn render_html(emails: &[ExportEmail], note: Option<&str>) -> String {
+    let title = emails
+        .first()
+        .and_then(|email| email.row.subject.as_deref())
+        .unwrap_or("Conversation");
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+         body {{ font-family: -apple-system, 'Segoe UI', 'Microsoft YaHei', sans-serif; max-width: 820px; margin: 2em auto; color: #222; }}\n\
+         .note {{ background: #fff8e1; border: 1px solid #f0d58c; padding: 0.6em 1em; }}\n\
+         .message {{ border-top: 1px solid #ddd; padding: 1em 0; }}\n\
+         .headers {{ color: #555; font-size: 0.9em; }}\n\
+         .body {{ white-space: pre-wrap; word-wrap: break-word; }}\n\
+         .attachments {{ font-size: 0.9em; }}\n\
+         </style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape_html(title),
+        escape_html(title)
+    );
+    if let Some(note) = note {
+        let _ = writeln!(out, "<p class=\"note\">{}</p>", escape_html(note));
+    }
+
+    for (index, email) in emails.iter().enumerate() {
+        let row = &email.row;
+        let _ = writeln!(out, "<div class=\"message\" id=\"message-{}\">", index + 1);
+        let _ = writeln!(out, "<h2>{}</h2>", escape_html(row.subject.as_deref().unwrap_or("(No Subject)")));
+        let mut headers = vec![("From", row.sender.clone().unwrap_or_default())];
+        let to = parse_list(row.recipients.as_deref());
+        if !to.is_empty() {
+            headers.push(("To", to.join(", ")));
+        }
+        let cc = parse_list(row.cc.as_deref());
+        if !cc.is_empty() {
+            headers.push(("Cc", cc.join(", ")));
+        }
+        let date = row
+            .date
+            .as_deref()
+            .and_then(parse_timestamp)
+            .map(|date| date.format("%Y-%m-%d %H:%M UTC").to_string())
+            .or_else(|| row.date.clone())
+            .unwrap_or_default();
+        headers.push(("Date", date));
+        out.push_str("<div class=\"headers\">\n");
+        for (label, value) in &headers {
+            let _ = writeln!(out, "<div><strong>{}:</strong> {}</div>", label, escape_html(value));
+        }
+        out.push_str("</div>\n");
+        let _ = writeln!(out, "<div class=\"body\">{}</div>", escape_html(email.text.trim()));
+
+        if !email.attachments.is_empty() {
+            out.push_str("<div class=\"attachments\"><strong>Attachments:</strong>\n<ul>\n");
+            for attachment in &email.attachments {
+                let _ = writeln!(
+                    out,
+                    "<li>{} ({}){}</li>",
+                    escape_html(&attachment.filename),
+                    format_file_size(attachment.file_size.unwrap_or(0)),
+                    if attachment.source.is_none() { " — not available locally" } else { "" }
+                );
+            }
+            out.push_str("</ul>\n</div>\n");
+        }
+        out.push_str("</div>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
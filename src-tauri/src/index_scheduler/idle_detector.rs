@@ -1 +1,20 @@
-pub fn hello() {}
+/// 空闲检测
+///
+/// 没有账户在同步时视为空闲，可以执行 WAL 检查点等维护操作。
+use crate::mail::sync::ActiveSyncs;
+
+/// 空闲检测器
+pub struct IdleDetector {
+    active_syncs: ActiveSyncs,
+}
+
+impl IdleDetector {
+    pub fn new(active_syncs: ActiveSyncs) -> Self {
+        Self { active_syncs }
+    }
+
+    /// 当前是否空闲
+    pub fn is_idle(&self) -> bool {
+        !self.active_syncs.any_active()
+    }
+}
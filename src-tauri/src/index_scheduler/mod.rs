@@ -2,3 +2,4 @@ pub mod queue;
 pub mod scheduler;
 pub mod retry;
 pub mod idle_detector;
+pub mod quiet_hours;
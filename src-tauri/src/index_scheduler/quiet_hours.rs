@@ -0,0 +1,140 @@
+/// 静默时段
+///
+/// 静默时段内不启动自动同步和后台任务（已在运行的任务会正常结束），被推迟的任务在时段结束后统一执行。
+/// 手动同步是否放行由 `quiet_hours_allow_manual` 决定。
+use crate::error::AppError;
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 静默时段设置
+#[derive(Debug, Clone)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// 生效的星期（1=周一 ... 7=周日），按时段开始所在日计
+    pub days: Vec<u32>,
+    pub allow_manual_override: bool,
+}
+
+/// 后台活动状态（供界面解释为什么没有在同步）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackgroundStatus {
+    pub paused: bool,
+    /// 恢复时间（本地 HH:MM）
+    pub paused_until: Option<String>,
+    /// "paused until 07:00"
+    pub message: Option<String>,
+    pub allow_manual_override: bool,
+}
+
+impl QuietHours {
+    /// 从同步设置读取，格式错误的设置按未启用处理
+    pub async fn load(pool: &SqlitePool) -> Result<Self, AppError> {
+        let (enabled, start, end, days, allow_manual): (bool, String, String, String, bool) = sqlx::query_as(
+            r#"
+            SELECT quiet_hours_enabled, quiet_hours_start, quiet_hours_end, quiet_hours_days, quiet_hours_allow_manual
+            FROM sync_settings WHERE id = 1
+            "#
+        )
+        .fetch_one(pool)
+        .await?;
+
+        match (parse_time(&start), parse_time(&end), parse_days(&days)) {
+            (Ok(start), Ok(end), Ok(days)) => Ok(Self {
+                enabled,
+                start,
+                end,
+                days,
+                allow_manual_override: allow_manual,
+            }),
+            _ => {
+                log::warn!("Invalid quiet hours settings ({} - {}, {}), ignoring", start, end, days);
+                Ok(Self {
+                    enabled: false,
+                    start: NaiveTime::MIN,
+                    end: NaiveTime::MIN,
+                    days: Vec::new(),
+                    allow_manual_override: true,
+                })
+            }
+        }
+    }
+
+    /// 当前处于静默时段时返回结束时间
+    pub fn paused_until(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        if !self.enabled || self.start == self.end {
+            return None;
+        }
+
+        // 跨夜时段可能从前一天开始
+        for offset in [0, -1] {
+            let day = now.date() + ChronoDuration::days(offset);
+            if !self.days.contains(&day.weekday().number_from_monday()) {
+                continue;
+            }
+
+            let start = day.and_time(self.start);
+            let mut end = day.and_time(self.end);
+            if self.end < self.start {
+                end += ChronoDuration::days(1);
+            }
+            if start <= now && now < end {
+                return Some(end);
+            }
+        }
+
+        None
+    }
+
+    /// 当前（本地时间）是否处于静默时段
+    pub fn is_quiet_now(&self) -> bool {
+        self.paused_until(Local::now().naive_local()).is_some()
+    }
+
+    /// 距离静默时段结束的时长，不在静默时段时返回 None
+    pub fn remaining(&self) -> Option<std::time::Duration> {
+        let now = Local::now().naive_local();
+        self.paused_until(now).and_then(|end| (end - now).to_std().ok())
+    }
+
+    /// 当前后台活动状态
+    pub fn status(&self) -> BackgroundStatus {
+        let until = self.paused_until(Local::now().naive_local()).map(|end| end.format("%H:%M").to_string());
+        BackgroundStatus {
+            paused: until.is_some(),
+            message: until.as_ref().map(|time| format!("paused until {}", time)),
+            paused_until: until,
+            allow_manual_override: self.allow_manual_override,
+        }
+    }
+}
+
+/// 校验静默时段设置（更新设置时使用）
+pub fn validate(start: Option<&str>, end: Option<&str>, days: Option<&str>) -> Result<(), AppError> {
+    for time in [start, end].into_iter().flatten() {
+        parse_time(time)?;
+    }
+    if let Some(days) = days {
+        parse_days(days)?;
+    }
+    Ok(())
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, AppError> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| AppError::Validation(format!("Invalid quiet hours time: {}", value)))
+}
+
+fn parse_days(value: &str) -> Result<Vec<u32>, AppError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|day| !day.is_empty())
+        .map(|day| match day.parse::<u32>() {
+            Ok(day) if (1..=7).contains(&day) => Ok(day),
+            _ => Err(AppError::Validation(format!("Invalid quiet hours day: {}", day))),
+        })
+        .collect()
+}
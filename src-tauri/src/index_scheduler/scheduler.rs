@@ -1 +1,422 @@
-pub fn hello() {}
+/// 后台任务调度
+///
+/// 每晚在设置的时间（`sync_settings.backfill_hour`，本地时间）对所有账户运行后台任务；
+/// 另有定期维护循环，在空闲时截断 WAL 文件。启动时在后台对最近的附件做一次限时的完整性检查。
+/// 静默时段内不启动任何后台任务，到期的任务推迟到时段结束后统一执行。
+use crate::artifacts::integrity::{
+    AttachmentIntegrity, QuickCheckSummary, RepairSummary, STARTUP_CHECK_BUDGET, STARTUP_CHECK_DAYS,
+};
+use crate::artifacts::sniff;
+use crate::commands::sync::resolve_account_auth;
+use crate::error::AppError;
+use crate::events::notifications::SOURCE_PROJECT_LIFECYCLE;
+use crate::events::{EventEmitter, NotificationLevel, ReminderDueEvent};
+use crate::index_scheduler::idle_detector::IdleDetector;
+use crate::index_scheduler::quiet_hours::QuietHours;
+use crate::mail::backfill::{BackfillOutcome, BodyBackfiller};
+use crate::mail::deletions::ServerDeletions;
+use crate::mail::language;
+use crate::mail::sync::ActiveSyncs;
+use crate::project::lifecycle;
+use crate::repository::project::DUE_SOON_DAYS;
+use crate::repository::ProjectRepository;
+use crate::storage::app_state::DatabaseExt;
+use crate::storage::database;
+use crate::storage::quarantine::{AttachmentQuarantine, QUARANTINE_DAYS};
+use crate::utils::preview;
+use chrono::{Duration as ChronoDuration, Local, NaiveTime};
+use tauri::{AppHandle, Manager};
+
+/// 空闲维护（WAL 检查点）的检查间隔
+const MAINTENANCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// 后台任务类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobKind {
+    /// 补全仅同步了邮件头的邮件正文
+    BackfillBodies { account_id: i64 },
+    /// 永久删除回收站中超过保留期的项目
+    PurgeDeletedProjects,
+    /// 归档已结束且长期无更新的项目
+    AutoArchiveProjects,
+    /// 永久删除超过保留期的服务器已删除邮件
+    PurgeServerDeleted,
+    /// 永久删除隔离区中超过保留期的附件文件
+    PurgeQuarantine,
+    /// 为升级前保存的邮件识别语言
+    DetectLanguages,
+    /// 提醒已逾期和本周到期的项目
+    DueDateReminders,
+    /// 快速检查附件文件（启动检查超出时间预算的部分）
+    CheckAttachments { ids: Vec<i64> },
+    /// 从 IMAP 重新下载损坏的附件
+    RepairAttachments { ids: Vec<i64> },
+    /// 为升级前保存的附件按文件头识别类型
+    SniffAttachments,
+    /// 为升级前保存的邮件计算预览
+    BuildPreviews,
+}
+
+/// 后台任务结果
+#[derive(Debug, Clone)]
+pub enum JobOutcome {
+    BackfillBodies(BackfillOutcome),
+    /// 删除的项目数
+    PurgeDeletedProjects(u64),
+    /// 归档的项目数
+    AutoArchiveProjects(u64),
+    /// 永久删除的邮件数
+    PurgeServerDeleted(u64),
+    /// 永久删除的隔离区文件数
+    PurgeQuarantine(u64),
+    /// 识别语言的邮件数
+    DetectLanguages(u64),
+    /// 已逾期 / 即将到期的项目数
+    DueDateReminders { overdue: usize, due_soon: usize },
+    CheckAttachments(QuickCheckSummary),
+    RepairAttachments(RepairSummary),
+    /// 识别类型的附件数
+    SniffAttachments(u64),
+    /// 计算预览的邮件数
+    BuildPreviews(u64),
+}
+
+/// 后台任务调度器
+pub struct Scheduler {
+    app: AppHandle,
+}
+
+impl Scheduler {
+    pub fn new(app: AppHandle) -> Self {
+        Self { app }
+    }
+
+    /// 启动每晚调度循环
+    pub fn spawn(app: AppHandle) {
+        tauri::async_runtime::spawn(async move {
+            let scheduler = Scheduler::new(app);
+            loop {
+                let hour = scheduler.settings().await.map(|(_, hour)| hour).unwrap_or(3);
+                let wait = duration_until_hour(hour);
+                log::info!("Next nightly job run in {} minutes", wait.as_secs() / 60);
+                tokio::time::sleep(wait).await;
+
+                scheduler.wait_for_quiet_hours().await;
+                scheduler.run_nightly().await;
+            }
+        });
+    }
+
+    /// 启动空闲维护循环
+    pub fn spawn_maintenance(app: AppHandle) {
+        tauri::async_runtime::spawn(async move {
+            let scheduler = Scheduler::new(app);
+            loop {
+                tokio::time::sleep(MAINTENANCE_INTERVAL).await;
+                scheduler.run_maintenance().await;
+            }
+        });
+    }
+
+    /// 启动时在后台检查最近创建的附件，不阻塞窗口显示
+    ///
+    /// 超出时间预算的附件由 `CheckAttachments` 任务继续检查；损坏的附件在静默时段外排队修复。
+    pub fn spawn_startup_checks(app: AppHandle) {
+        tauri::async_runtime::spawn(async move {
+            let scheduler = Scheduler::new(app);
+            let pool = match scheduler.app.db() {
+                Ok(pool) => pool,
+                Err(e) => {
+                    log::warn!("Skipping startup attachment check: {}", e);
+                    return;
+                }
+            };
+            let integrity = AttachmentIntegrity::new(pool);
+            let summary = match integrity.recent_ids(STARTUP_CHECK_DAYS).await {
+                Ok(ids) => integrity.quick_check(&ids, Some(STARTUP_CHECK_BUDGET)).await,
+                Err(e) => Err(e),
+            };
+            let summary = match summary {
+                Ok(summary) => summary,
+                Err(e) => {
+                    log::warn!("Startup attachment check failed: {}", e);
+                    return;
+                }
+            };
+
+            let mut repairable = summary.repairable;
+            if !summary.deferred.is_empty() {
+                log::info!("Deferring check of {} attachments to a background job", summary.deferred.len());
+                match scheduler.run_job(JobKind::CheckAttachments { ids: summary.deferred }).await {
+                    Ok(JobOutcome::CheckAttachments(deferred)) => repairable.extend(deferred.repairable),
+                    Ok(_) => {}
+                    Err(e) => log::warn!("Deferred attachment check failed: {}", e),
+                }
+            }
+            if !repairable.is_empty() {
+                scheduler.wait_for_quiet_hours().await;
+                if let Err(e) = scheduler.run_job(JobKind::RepairAttachments { ids: repairable }).await {
+                    log::warn!("Attachment repair failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// 处于静默时段时等待其结束
+    async fn wait_for_quiet_hours(&self) {
+        let pool = match self.app.db() {
+            Ok(pool) => pool,
+            Err(_) => return,
+        };
+        let remaining = match QuietHours::load(&pool).await {
+            Ok(quiet_hours) => quiet_hours.remaining(),
+            Err(e) => {
+                log::warn!("Failed to load quiet hours: {}", e);
+                None
+            }
+        };
+
+        if let Some(wait) = remaining {
+            log::info!("Quiet hours active, deferring background jobs for {} minutes", wait.as_secs() / 60);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// 空闲时执行 WAL 检查点，同步进行中或静默时段内跳过
+    pub async fn run_maintenance(&self) {
+        let (pool, writer) = match (self.app.db(), self.app.db_writer()) {
+            (Ok(pool), Ok(writer)) => (pool, writer),
+            _ => return,
+        };
+        if QuietHours::load(&pool).await.map(|q| q.is_quiet_now()).unwrap_or(false) {
+            log::debug!("Quiet hours active, skipping maintenance");
+            return;
+        }
+
+        let idle = IdleDetector::new(self.app.state::<ActiveSyncs>().inner().clone());
+        if !idle.is_idle() {
+            log::debug!("Sync active, skipping WAL checkpoint");
+            return;
+        }
+
+        match database::checkpoint_wal(&writer.0).await {
+            Ok((busy, log_pages, checkpointed)) => log::info!(
+                "WAL checkpoint: busy={}, log={} pages, checkpointed={} pages",
+                busy, log_pages, checkpointed
+            ),
+            Err(e) => log::warn!("WAL checkpoint failed: {}", e),
+        }
+    }
+
+    /// 对所有账户运行每晚任务
+    pub async fn run_nightly(&self) {
+        let pool = match self.app.db() {
+            Ok(pool) => pool,
+            Err(e) => {
+                log::error!("Failed to run nightly jobs: {}", e);
+                return;
+            }
+        };
+        let accounts: Vec<(i64,)> = match sqlx::query_as("SELECT id FROM accounts").fetch_all(&pool).await {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                log::error!("Failed to load accounts for nightly jobs: {}", e);
+                return;
+            }
+        };
+
+        for (account_id,) in accounts {
+            if let Err(e) = self.run_job(JobKind::BackfillBodies { account_id }).await {
+                log::warn!("Nightly body backfill failed for account {}: {}", account_id, e);
+            }
+        }
+
+        if let Err(e) = self.run_job(JobKind::PurgeDeletedProjects).await {
+            log::warn!("Nightly trash purge failed: {}", e);
+        }
+
+        if let Err(e) = self.run_job(JobKind::AutoArchiveProjects).await {
+            log::warn!("Nightly project auto-archive failed: {}", e);
+        }
+
+        if let Err(e) = self.run_job(JobKind::PurgeServerDeleted).await {
+            log::warn!("Nightly purge of server-deleted emails failed: {}", e);
+        }
+
+        if let Err(e) = self.run_job(JobKind::PurgeQuarantine).await {
+            log::warn!("Nightly attachment quarantine purge failed: {}", e);
+        }
+
+        if let Err(e) = self.run_job(JobKind::DetectLanguages).await {
+            log::warn!("Nightly language detection failed: {}", e);
+        }
+
+        if let Err(e) = self.run_job(JobKind::SniffAttachments).await {
+            log::warn!("Nightly attachment type detection failed: {}", e);
+        }
+
+        if let Err(e) = self.run_job(JobKind::BuildPreviews).await {
+            log::warn!("Nightly preview backfill failed: {}", e);
+        }
+
+        if let Err(e) = self.run_job(JobKind::DueDateReminders).await {
+            log::warn!("Nightly due date reminders failed: {}", e);
+        }
+    }
+
+    /// 运行单个任务
+    pub async fn run_job(&self, kind: JobKind) -> Result<JobOutcome, AppError> {
+        let pool = self.app.db()?;
+        let emitter = self.app.state::<EventEmitter>().inner().clone();
+
+        match kind {
+            JobKind::BackfillBodies { account_id } => {
+                let active_syncs = self.app.state::<ActiveSyncs>().inner().clone();
+                let backfiller = BodyBackfiller::new(pool.clone(), active_syncs)
+                    .with_event_emitter(emitter);
+
+                if backfiller.pending_count(account_id).await? == 0 {
+                    return Ok(JobOutcome::BackfillBodies(BackfillOutcome {
+                        account_id,
+                        ..Default::default()
+                    }));
+                }
+
+                let email: String = sqlx::query_scalar("SELECT email FROM accounts WHERE id = ?")
+                    .bind(account_id)
+                    .fetch_one(&pool)
+                    .await?;
+                let (_, auth, provider) = resolve_account_auth(&pool, &email, None)
+                    .await
+                    .map_err(|e| AppError::Auth(e.message))?;
+
+                let (batch_size, _) = self.settings().await?;
+                let outcome = backfiller.run(account_id, auth, &provider, batch_size).await?;
+                Ok(JobOutcome::BackfillBodies(outcome))
+            }
+            JobKind::PurgeDeletedProjects => {
+                let retention_days: i64 = sqlx::query_scalar(
+                    "SELECT trash_retention_days FROM sync_settings WHERE id = 1"
+                )
+                .fetch_one(&pool)
+                .await?;
+
+                let purged = ProjectRepository::new(pool).purge_deleted(retention_days).await?;
+                if purged > 0 {
+                    emitter.emit_notification_from(
+                        "Trash emptied",
+                        &format!("{} projects deleted more than {} days ago were removed", purged, retention_days),
+                        NotificationLevel::Info,
+                        Some(SOURCE_PROJECT_LIFECYCLE),
+                        None,
+                    );
+                }
+                Ok(JobOutcome::PurgeDeletedProjects(purged))
+            }
+            JobKind::AutoArchiveProjects => {
+                let inactive_days: i64 = sqlx::query_scalar(
+                    "SELECT auto_archive_days FROM sync_settings WHERE id = 1"
+                )
+                .fetch_one(&pool)
+                .await?;
+
+                let archived = lifecycle::auto_archive(&pool, inactive_days).await?;
+                if archived > 0 {
+                    emitter.emit_notification_from(
+                        "Projects archived",
+                        &format!("{} finished projects without updates for {} days were archived", archived, inactive_days),
+                        NotificationLevel::Info,
+                        Some(SOURCE_PROJECT_LIFECYCLE),
+                        None,
+                    );
+                }
+                Ok(JobOutcome::AutoArchiveProjects(archived))
+            }
+            JobKind::PurgeServerDeleted => {
+                let retention_days: i64 = sqlx::query_scalar(
+                    "SELECT server_deleted_retention_days FROM sync_settings WHERE id = 1"
+                )
+                .fetch_one(&pool)
+                .await?;
+
+                let purged = ServerDeletions::new(pool).purge(retention_days).await?;
+                Ok(JobOutcome::PurgeServerDeleted(purged))
+            }
+            JobKind::PurgeQuarantine => {
+                let purged = AttachmentQuarantine::new(pool).purge(QUARANTINE_DAYS).await?;
+                Ok(JobOutcome::PurgeQuarantine(purged))
+            }
+            JobKind::DetectLanguages => {
+                let processed = language::backfill_languages(&pool).await?;
+                Ok(JobOutcome::DetectLanguages(processed))
+            }
+            JobKind::DueDateReminders => {
+                let due = ProjectRepository::new(pool).due_projects(DUE_SOON_DAYS).await?;
+                let today = Local::now().format("%Y-%m-%d").to_string();
+                let overdue = due.iter().filter(|project| project.due_date < today).count();
+                let due_soon = due.len() - overdue;
+
+                for project in &due {
+                    emitter.emit_reminder_due(ReminderDueEvent {
+                        project_id: project.id,
+                        milestone_id: None,
+                        title: project.name.clone(),
+                        due_at: project.due_date.clone(),
+                    });
+                }
+                if !due.is_empty() {
+                    let names: Vec<&str> = due.iter().map(|project| project.name.as_str()).collect();
+                    let related = (due.len() == 1).then(|| format!("project:{}", due[0].id));
+                    emitter.emit_notification_from(
+                        "Project deadlines",
+                        &format!("{} overdue, {} due this week: {}", overdue, due_soon, names.join(", ")),
+                        if overdue > 0 { NotificationLevel::Warning } else { NotificationLevel::Info },
+                        Some(SOURCE_PROJECT_LIFECYCLE),
+                        related.as_deref(),
+                    );
+                }
+                Ok(JobOutcome::DueDateReminders { overdue, due_soon })
+            }
+            JobKind::CheckAttachments { ids } => {
+                let summary = AttachmentIntegrity::new(pool).quick_check(&ids, None).await?;
+                Ok(JobOutcome::CheckAttachments(summary))
+            }
+            JobKind::RepairAttachments { ids } => {
+                let summary = AttachmentIntegrity::with_event_emitter(pool, emitter).repair_many(&ids).await?;
+                Ok(JobOutcome::RepairAttachments(summary))
+            }
+            JobKind::SniffAttachments => {
+                let processed = sniff::backfill_detected_mime(&pool).await?;
+                Ok(JobOutcome::SniffAttachments(processed))
+            }
+            JobKind::BuildPreviews => {
+                let processed = preview::backfill_previews(&pool).await?;
+                Ok(JobOutcome::BuildPreviews(processed))
+            }
+        }
+    }
+
+    /// 读取调度设置（批大小, 每晚运行时间）
+    async fn settings(&self) -> Result<(usize, u32), AppError> {
+        let pool = self.app.db()?;
+        let (batch_size, hour): (i64, i64) = sqlx::query_as(
+            "SELECT backfill_batch_size, backfill_hour FROM sync_settings WHERE id = 1"
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        Ok((batch_size.max(1) as usize, hour.clamp(0, 23) as u32))
+    }
+}
+
+/// 距离下一次本地时间 `hour:00` 的时长
+fn duration_until_hour(hour: u32) -> std::time::Duration {
+    let now = Local::now().naive_local();
+    let target_time = NaiveTime::from_hms_opt(hour, 0, 0).unwrap_or(NaiveTime::MIN);
+    let mut target = now.date().and_time(target_time);
+    if target <= now {
+        target += ChronoDuration::days(1);
+    }
+
+    (target - now).to_std().unwrap_or_default()
+}
@@ -1,6 +1,7 @@
 pub mod commands;
 pub mod error;
 pub mod events;
+pub mod export;
 pub mod mail;
 pub mod project;
 pub mod repository;
@@ -10,7 +11,81 @@ pub mod index_scheduler;
 pub mod storage;
 pub mod utils;
 
-use tauri::Manager;
+use std::future::Future;
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+/// 启动阶段（依次执行，每个阶段开始和结束时发送 `startup-progress` 事件）
+const STARTUP_PHASES: [&str; 3] = ["database", "settings", "background"];
+
+/// 后台初始化：创建连接池并迁移、读取启动设置、启动后台任务
+///
+/// 设置读取完成后才写入 `DatabaseState`，之前需要数据库的命令返回 `NOT_READY`。
+async fn initialize(app: &AppHandle, emitter: &events::EventEmitter) -> anyhow::Result<()> {
+    let (pool, writer_pool) = startup_phase(emitter, 0, async {
+        let pool = storage::database::init_pool(app).await?;
+        let writer_pool = storage::database::init_writer_pool(app).await?;
+        Ok((pool, writer_pool))
+    })
+    .await?;
+
+    startup_phase(emitter, 1, async {
+        // 冷存储目录（附件分层存储）
+        storage::cold_storage::load_root(&pool).await?;
+        // 后台模式（关闭窗口时隐藏到托盘）
+        utils::tray::load_settings(&pool).await?;
+        Ok(())
+    })
+    .await?;
+    app.state::<storage::app_state::DatabaseState>().set(pool.clone(), writer_pool);
+
+    startup_phase(emitter, 2, async {
+        // 启动每晚后台任务（正文补全等）
+        index_scheduler::scheduler::Scheduler::spawn(app.clone());
+        index_scheduler::scheduler::Scheduler::spawn_maintenance(app.clone());
+        // 启动时的附件完整性检查（限时，其余交给后台任务）
+        index_scheduler::scheduler::Scheduler::spawn_startup_checks(app.clone());
+        // 清除过期的待完成 OAuth 授权（连同钥匙串中的令牌）
+        if let Err(e) = mail::pending_oauth::PendingAuthorizations::new(pool.clone()).purge_expired().await {
+            log::warn!("Failed to purge pending OAuth authorizations: {}", e);
+        }
+        // 恢复 .eml 监视文件夹
+        if let Some(config) = mail::watch_folder::load_config(&pool).await? {
+            app.state::<mail::watch_folder::WatchFolder>().start(pool.clone(), emitter.clone(), config);
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// 执行一个启动阶段并发送进度，失败时以 `failed` 状态通知前端
+async fn startup_phase<T>(
+    emitter: &events::EventEmitter,
+    index: usize,
+    work: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    let phase = STARTUP_PHASES[index];
+    let progress = |status, error| events::StartupProgressEvent {
+        phase: phase.to_string(),
+        current: index + 1,
+        total: STARTUP_PHASES.len(),
+        status,
+        error,
+    };
+
+    emitter.emit_startup_progress(progress(events::StartupStatus::Running, None));
+    match work.await {
+        Ok(value) => {
+            emitter.emit_startup_progress(progress(events::StartupStatus::Completed, None));
+            Ok(value)
+        }
+        Err(e) => {
+            emitter.emit_startup_progress(progress(events::StartupStatus::Failed, Some(e.to_string())));
+            Err(e.context(format!("startup phase {} failed", phase)))
+        }
+    }
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -23,50 +98,231 @@ pub fn run() {
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_deep_link::init())
+        // 离线缓存的远程图片
+        .register_uri_scheme_protocol(storage::remote_content::CACHE_SCHEME, |_ctx, request| {
+            storage::remote_content::protocol_response(request.uri().path())
+        })
+        .on_window_event(utils::tray::on_window_event)
         .setup(|app| {
-            // 使用 tokio runtime 初始化数据库
-            let runtime = tokio::runtime::Runtime::new()?;
-            let pool = runtime.block_on(async {
-                storage::database::init_pool(app.handle()).await
-            })?;
+            // 注册全局状态（连接池在启动任务中创建后写入 DatabaseState）
+            app.manage(storage::app_state::DatabaseState::default());
+            app.manage(mail::sync::ActiveSyncs::default());
+            app.manage(events::EventEmitter::new(app.handle().clone())); // 后台服务共用的事件发射器
+            app.manage(storage::archive::ArchiveState::default()); // 只读归档数据库
+            app.manage(search::quick_switcher::QuickSwitcher::default()); // 快速切换器结果缓存
+            app.manage(search::count::SearchCounter::default()); // 搜索结果数量预览缓存
+            app.manage(mail::watch_folder::WatchFolder::default()); // .eml 监视文件夹
+            let connections = mail::connection_pool::ImapConnectionPool::default(); // 交互操作复用的 IMAP 会话
+            connections.spawn_maintenance();
+            app.manage(connections);
+
+            // 系统托盘
+            utils::tray::init(app.handle())?;
+
+            // 数据库、设置和后台任务在窗口显示后初始化
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let started = Instant::now();
+                let emitter = handle.state::<events::EventEmitter>().inner().clone();
+                match initialize(&handle, &emitter).await {
+                    Ok(()) => {
+                        let elapsed_ms = started.elapsed().as_millis() as u64;
+                        log::info!("Application initialized in {} ms", elapsed_ms);
+                        emitter.emit_app_ready(events::AppReadyEvent { elapsed_ms });
+                    }
+                    Err(e) => log::error!("Application initialization failed: {:#}", e),
+                }
+            });
 
-            // 注册全局状态
-            let project_repo = repository::ProjectRepository::new(pool.clone());
-            app.manage(project_repo);
-            app.manage(pool.clone()); // 注册 SqlitePool 供 sync 命令使用
+            // 注册 deep link（threadline://project/42）
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(e) = app.deep_link().register_all() {
+                log::warn!("Failed to register deep link scheme: {}", e);
+            }
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    utils::deep_link::handle_deep_link(&handle, url.as_str());
+                }
+            });
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                for url in urls {
+                    utils::deep_link::handle_deep_link(app.handle(), url.as_str());
+                }
+            }
 
             // 填充模拟数据（暂时禁用，使用真实 OAuth 账户）
-            // runtime.block_on(async {
-            //     storage::mock_data::seed_mock_data(app.handle()).await
-            // })?;
+            // storage::mock_data::seed_mock_data(app.handle()).await
 
-            log::info!("Application initialized successfully");
+            log::info!("Application window ready, initializing in background");
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::greet_user,
+            commands::is_app_ready,
             commands::mail::fetch_emails,
             commands::mail::get_inbox_emails,
+            commands::mail::get_smart_view,
+            commands::mail::list_server_deleted,
+            commands::mail::restore_server_deleted,
+            commands::mail::translate_email,
+            commands::mail::get_account_namespaces,
+            commands::mail::search_remote,
+            commands::mail::import_remote_email,
+            commands::mail::suggest_recipients,
+            commands::mail::set_contact_muted,
+            commands::mail::list_vip_contacts,
+            commands::mail::add_vip_contact,
+            commands::mail::remove_vip_contact,
+            commands::mail::merge_contacts,
+            commands::mail::unmerge_contact,
+            commands::mail::get_contact_aliases,
+            commands::mail::get_contact_merge_proposals,
+            commands::mail::set_sender_rule,
+            commands::mail::delete_sender_rule,
+            commands::mail::list_sender_rules,
+            commands::mail::list_templates,
+            commands::mail::create_template,
+            commands::mail::update_template,
+            commands::mail::delete_template,
+            commands::mail::render_template,
+            commands::mail::get_email_detail,
+            commands::mail::compute_reply_recipients,
+            commands::mail::get_email_folders,
+            commands::mail::list_identities,
+            commands::mail::add_identity,
+            commands::mail::update_identity,
+            commands::mail::delete_identity,
+            commands::mail::discover_gmail_identities,
+            commands::mail::resolve_sender,
+            commands::mail::send_email,
+            commands::mail::respond_to_invite,
+            commands::mail::start_watch_folder,
+            commands::mail::stop_watch_folder,
+            commands::mail::get_watch_folder_status,
+            commands::mail::clear_remote_content_cache,
+            commands::mail::get_email_body_file,
+            commands::mail::compact_email_bodies,
+            commands::mail::backfill_email_languages,
+            commands::mail::export_email_pdf,
+            commands::mail::export_thread_pdf,
+            commands::mail::export_thread,
             commands::project::list_projects,
             commands::project::get_project,
             commands::project::get_project_timeline,
+            commands::project::get_thread,
+            commands::project::get_thread_emails,
+            commands::project::move_thread_to_project,
+            commands::project::move_email_to_project,
+            commands::project::merge_projects,
+            commands::project::get_projects_needing_review,
+            commands::project::dismiss_project_review,
+            commands::project::propose_project_split,
+            commands::project::apply_project_split,
+            commands::project::list_undo_actions,
+            commands::project::undo_last_action,
+            commands::project::undo_action,
             commands::project::toggle_project_pin,
+            commands::project::reorder_pinned_projects,
             commands::project::archive_project,
             commands::project::unarchive_project,
+            commands::project::list_project_statuses,
+            commands::project::save_project_status,
+            commands::project::delete_project_status,
+            commands::project::set_project_status,
+            commands::project::get_project_preferences,
+            commands::project::set_project_preferences,
+            commands::project::list_project_templates,
+            commands::project::create_project_template,
+            commands::project::update_project_template,
+            commands::project::delete_project_template,
+            commands::project::create_project_from_template,
+            commands::project::apply_template,
+            commands::project::delete_project,
+            commands::project::list_deleted_projects,
+            commands::project::restore_project,
+            commands::project::set_project_appearance,
+            commands::project::set_project_due_date,
+            commands::project::recompute_project_stats,
+            commands::project::get_classification_explanation,
+            commands::project::get_classifier_metrics,
+            commands::project::classify_unassigned_emails,
+            commands::project::generate_project_report,
+            commands::project::export_project,
+            commands::project::create_organization_snapshot,
+            commands::project::list_snapshots,
+            commands::project::restore_snapshot,
             commands::search::search_query,
+            commands::search::count_search_results,
+            commands::search::export_search_results,
+            commands::search::bulk_apply_to_search,
+            commands::search::quick_search,
+            commands::archive::open_archive_database,
+            commands::archive::close_archive_database,
+            commands::archive::get_archive_database,
+            commands::search::rebuild_search_index,
+            commands::search::search_index_status,
             commands::artifact::get_artifact,
             commands::artifact::get_project_artifacts,
+            commands::artifact::open_artifact,
+            commands::artifact::star_artifact,
+            commands::artifact::list_starred_artifacts,
+            commands::artifact::list_recent_artifacts,
+            commands::artifact::backfill_attachment_projects,
+            commands::artifact::list_all_artifacts,
+            commands::artifact::verify_attachments,
+            commands::artifact::verify_all_attachments,
+            commands::artifact::repair_attachment,
+            commands::artifact::repair_all_broken,
+            commands::artifact::find_clobbered_attachments,
+            commands::artifact::extract_attachment_text,
+            commands::artifact::extract_pending_attachments,
+            commands::artifact::add_project_file,
+            commands::artifact::add_project_files,
+            commands::artifact::delete_project_file,
+            commands::artifact::migrate_cold_attachments,
+            commands::artifact::get_storage_stats,
+            commands::artifact::list_deleted_attachments,
+            commands::artifact::restore_deleted_attachment,
             commands::sync::get_email_providers,
             commands::sync::add_email_account,
             commands::sync::add_oauth_email_account,
             commands::sync::sync_email_account,
+            commands::sync::sync_account_dry_run,
+            commands::sync::get_background_status,
+            commands::sync::list_sync_runs,
+            commands::sync::get_sync_checkpoint,
+            commands::sync::get_sync_metrics,
             commands::sync::list_email_accounts,
             commands::sync::reset_account_sync,
             commands::oauth::start_oauth_flow,
             commands::oauth::get_oauth_instructions,
+            commands::oauth::get_provider_setup_help,
+            commands::oauth::list_pending_authorizations,
+            commands::oauth::complete_pending_authorization,
             commands::settings::get_sync_settings,
-            commands::settings::update_sync_settings
+            commands::settings::update_sync_settings,
+            commands::settings::export_settings,
+            commands::settings::import_settings,
+            commands::settings::get_database_pragmas,
+            commands::settings::set_translation_api_key,
+            commands::settings::has_translation_api_key,
+            commands::notification::list_notifications,
+            commands::notification::mark_notification_read,
+            commands::notification::mark_all_read,
+            commands::notification::clear_notifications,
+            commands::window::open_project_window
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            // 关闭最后一个窗口时，如果仍有同步在进行，保持进程运行直到同步结束
+            if let tauri::RunEvent::ExitRequested { api, code: None, .. } = event {
+                if app_handle.state::<mail::sync::ActiveSyncs>().any_active() {
+                    log::info!("Last window closed during sync; keeping app alive until sync finishes");
+                    api.prevent_exit();
+                }
+            }
+        });
 }
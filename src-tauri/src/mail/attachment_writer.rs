@@ -0,0 +1,418 @@
+/// 同步时的附件写入
+///
+/// 哈希计算和文件写入放在阻塞线程池中并行执行（每封邮件最多 `FILE_WRITE_PARALLELISM` 个文件），
+/// 同一封邮件的附件记录用一条 INSERT 写入。同步循环把附件排队后即可下载下一封邮件，
+/// 在途的邮件数超过 `MAX_EMAILS_IN_FLIGHT` 时等待最早的完成。
+///
+/// 附件记录引用的邮件行在排队前已经写入；任一文件写入失败时删除本封邮件已写入的文件，
+/// 不会留下没有记录的文件或只有部分附件的邮件。
+use crate::artifacts::safety::SafetyPolicy;
+use crate::artifacts::sniff;
+use crate::error::AppError;
+use crate::mail::parser::{MimePartInfo, ParsedAttachment};
+use crate::mail::sync::{calculate_sha256, extract_file_extension, sanitize_filename};
+use crate::storage::{disk_space, file_manager};
+use futures::{stream, StreamExt};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tokio::task::JoinSet;
+
+/// 单封邮件并行写入的文件数
+const FILE_WRITE_PARALLELISM: usize = 4;
+
+/// 同时在写附件的邮件数
+const MAX_EMAILS_IN_FLIGHT: usize = 2;
+
+/// 已写入磁盘、等待插入数据库的附件
+struct WrittenAttachment {
+    filename: String,
+    file_type: String,
+    size: usize,
+    content_type: String,
+    /// 按文件头识别的类型（无法识别时为空）
+    detected_mime: String,
+    relative_path: String,
+    absolute_path: PathBuf,
+    content_hash: String,
+    /// 在原始邮件中的 MIME 部分
+    part: Option<MimePartInfo>,
+}
+
+/// 附件写入队列
+pub struct AttachmentWriter {
+    pool: SqlitePool,
+    tasks: JoinSet<(i64, Result<usize, AppError>)>,
+    saved: usize,
+}
+
+impl AttachmentWriter {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            tasks: JoinSet::new(),
+            saved: 0,
+        }
+    }
+
+    /// 排队保存一封邮件的附件（邮件行必须已存在）
+    pub async fn enqueue(&mut self, account_id: i64, email_id: i64, attachments: Vec<ParsedAttachment>) {
+        if attachments.is_empty() {
+            return;
+        }
+        while self.tasks.len() >= MAX_EMAILS_IN_FLIGHT {
+            self.join_next().await;
+        }
+
+        let pool = self.pool.clone();
+        self.tasks.spawn(async move {
+            (email_id, save_email_attachments(&pool, account_id, email_id, attachments).await)
+        });
+    }
+
+    /// 等待目前排队的附件写完（写入失败的邮件只记录日志），之后可以继续排队
+    pub async fn flush(&mut self) {
+        while !self.tasks.is_empty() {
+            self.join_next().await;
+        }
+    }
+
+    /// 等待所有排队的附件写完，返回保存的附件数
+    pub async fn finish(&mut self) -> usize {
+        self.flush().await;
+        self.saved
+    }
+
+    async fn join_next(&mut self) {
+        match self.tasks.join_next().await {
+            Some(Ok((_, Ok(count)))) => self.saved += count,
+            Some(Ok((email_id, Err(e)))) => log::error!("Failed to save attachments for email {}: {}", email_id, e),
+            Some(Err(e)) => log::error!("Attachment writer task failed: {}", e),
+            None => {}
+        }
+    }
+}
+
+/// 写入一封邮件的全部附件并插入记录，返回附件数
+///
+/// 文件写入或插入失败时删除本次写入的所有文件。
+pub async fn save_email_attachments(
+    pool: &SqlitePool,
+    account_id: i64,
+    email_id: i64,
+    attachments: Vec<ParsedAttachment>,
+) -> Result<usize, AppError> {
+    if attachments.is_empty() {
+        return Ok(0);
+    }
+    let policy = SafetyPolicy::load(pool).await?;
+    let root = file_manager::attachments_root()?;
+
+    // 大附件写入前确认磁盘空间足够
+    let total_bytes: u64 = attachments.iter().map(|attachment| attachment.data.len() as u64).sum();
+    if total_bytes >= disk_space::LARGE_WRITE_BYTES {
+        disk_space::ensure_available(&root, total_bytes)?;
+    }
+
+    // 同一封邮件中的重名附件加序号，避免并行写入同一路径
+    let mut used = HashSet::new();
+    let jobs: Vec<(String, ParsedAttachment)> = attachments
+        .into_iter()
+        .map(|attachment| (unique_filename(&sanitize_filename(&attachment.filename), &mut used), attachment))
+        .collect();
+
+    let results: Vec<Result<WrittenAttachment, AppError>> = stream::iter(jobs)
+        .map(|(safe_filename, attachment)| {
+            let root = root.clone();
+            tokio::task::spawn_blocking(move || write_attachment(&root, account_id, email_id, &safe_filename, attachment))
+        })
+        .buffered(FILE_WRITE_PARALLELISM)
+        .map(|joined| joined.map_err(AppError::from).and_then(|result| result))
+        .collect()
+        .await;
+
+    let mut written = Vec::with_capacity(results.len());
+    let mut failure = None;
+    for result in results {
+        match result {
+            Ok(attachment) => written.push(attachment),
+            Err(e) => failure = failure.or(Some(e)),
+        }
+    }
+    if let Some(e) = failure {
+        remove_files(&written).await;
+        return Err(e);
+    }
+
+    if let Err(e) = insert_rows(pool, email_id, &written, &policy).await {
+        remove_files(&written).await;
+        return Err(e);
+    }
+
+    for attachment in &written {
+        log::info!("Saved attachment: {} ({} bytes) to {}", attachment.filename, attachment.size, attachment.relative_path);
+    }
+    Ok(written.len())
+}
+
+/// 计算哈希并写入文件（在阻塞线程中运行）
+///
+/// 存储路径: {attachments_root}/{file_type}/{account_id}/{email_id}/{safe_filename}
+fn write_attachment(
+    root: &std::path::Path,
+    account_id: i64,
+    email_id: i64,
+    safe_filename: &str,
+    attachment: ParsedAttachment,
+) -> Result<WrittenAttachment, AppError> {
+    let file_type = extract_file_extension(&attachment.filename);
+    let dir = root.join(&file_type).join(account_id.to_string()).join(email_id.to_string());
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AppError::Generic(format!("Failed to create attachment directory: {}", e)))?;
+
+    let absolute_path = dir.join(safe_filename);
+    std::fs::write(&absolute_path, &attachment.data).map_err(|e| match disk_space::write_error(e, &absolute_path) {
+        AppError::Io(e) => AppError::Generic(format!("Failed to write attachment file: {}", e)),
+        disk_full => disk_full,
+    })?;
+
+    Ok(WrittenAttachment {
+        content_hash: calculate_sha256(&attachment.data),
+        detected_mime: sniff::sniff(&attachment.data).unwrap_or_default(),
+        relative_path: format!("{}/{}/{}/{}", file_type, account_id, email_id, safe_filename),
+        absolute_path,
+        file_type,
+        size: attachment.size,
+        content_type: attachment.content_type,
+        filename: attachment.filename,
+        part: attachment.part,
+    })
+}
+
+/// 一条语句插入同一封邮件的全部附件记录
+async fn insert_rows(
+    pool: &SqlitePool,
+    email_id: i64,
+    written: &[WrittenAttachment],
+    policy: &SafetyPolicy,
+) -> Result<(), AppError> {
+    // 附件继承邮件当前所属的项目（邮件可能在附件写入之前已被分类）
+    let project_id: Option<i64> = sqlx::query_scalar("SELECT project_id FROM emails WHERE id = ?")
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+
+    let placeholders = vec!["(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"; written.len()].join(", ");
+    let sql = format!(
+        r#"
+        INSERT INTO attachments (
+            email_id, project_id, filename, file_type, file_size, mime_type, detected_mime, file_path,
+            content_hash, danger_level, part_path, transfer_encoding, declared_size
+        ) VALUES {}
+        "#,
+        placeholders
+    );
+
+    let mut query = sqlx::query(&sql);
+    for attachment in written {
+        query = query
+            .bind(email_id)
+            .bind(project_id)
+            .bind(&attachment.filename)
+            .bind(&attachment.file_type)
+            .bind(attachment.size as i64)
+            .bind(&attachment.content_type)
+            .bind(&attachment.detected_mime)
+            .bind(&attachment.relative_path)
+            .bind(&attachment.content_hash)
+            .bind(
+                policy
+                    .classify_detected(
+                        &attachment.filename,
+                        Some(&attachment.content_type),
+                        Some(&attachment.detected_mime),
+                    )
+                    .as_str(),
+            )
+            .bind(attachment.part.as_ref().map(|part| part.path.clone()))
+            .bind(attachment.part.as_ref().map(|part| part.encoding.clone()))
+            .bind(attachment.part.as_ref().map(|part| part.size as i64));
+    }
+    query.execute(pool).await?;
+
+    Ok(())
+}
+
+async fn remove_files(written: &[WrittenAttachment]) {
+    for attachment in written {
+        if let Err(e) = tokio::fs::remove_file(&attachment.absolute_path).await {
+            log::warn!("Failed to remove attachment file {:?}: {}", attachment.absolute_path, e);
+        }
+    }
+}
+
+/// 重名时在扩展名前加序号（"a.png" → "a (2).png"）
+fn unique_filename(name: &str, used: &mut HashSet<String>) -> String {
+    let mut candidate = name.to_string();
+    let mut counter = 1;
+    while !used.insert(candidate.to_lowercase()) {
+        counter += 1;
+        candidate = match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => format!("{} ({}).{}", stem, counter, ext),
+            _ => format!("{} ({})", name, counter),
+        };
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::fixtures::{
+        insert_account, insert_email, insert_project, NewEmail,
+    };
+    use crate::storage::database::test_pool;
+
+    async fn email_in_project(
+        pool: &SqlitePool,
+        account_id: i64,
+        message_id: &str,
+        project_id: i64,
+    ) -> i64 {
+        let email = NewEmail {
+            account_id: Some(account_id),
+            message_id,
+            project_id: Some(project_id),
+            ..Default::default()
+        };
+        insert_email(pool, email).await
+    }
+
+    fn attachment(filename: &str, data: Vec<u8>) -> ParsedAttachment {
+        ParsedAttachment {
+            filename: filename.to_string(),
+            content_type: "application/octet-stream".to_string(),
+            size: data.len(),
+            data,
+            part: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_inserts_one_row_per_attachment() {
+        file_manager::use_test_data_dir();
+
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let projects = [insert_project(&pool, "Alpha").await, insert_project(&pool, "Beta").await];
+
+        // 两封邮件，第一封有重名附件
+        let mut expected = Vec::new();
+        let mut writer = AttachmentWriter::new(pool.clone());
+        for (index, (&project_id, count)) in projects.iter().zip([5, 3]).enumerate() {
+            let message_id = format!("<{}@example.com>", index);
+            let email_id = email_in_project(&pool, account_id, &message_id, project_id).await;
+            let batch: Vec<ParsedAttachment> = (0..count)
+                .map(|i| {
+                    let filename = if i < 2 { "report.pdf".to_string() } else { format!("file-{}.bin", i) };
+                    attachment(&filename, format!("email {} attachment {}", email_id, i).into_bytes())
+                })
+                .collect();
+            for parsed in &batch {
+                expected.push((email_id, project_id, calculate_sha256(&parsed.data)));
+            }
+            writer.enqueue(account_id, email_id, batch).await;
+        }
+        assert_eq!(writer.finish().await, 8);
+
+        let mut rows: Vec<(i64, i64, String, String)> = sqlx::query_as(
+            "SELECT email_id, project_id, content_hash, file_path FROM attachments ORDER BY id"
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(rows.len(), expected.len());
+
+        let root = file_manager::attachments_root().unwrap();
+        for (_, _, hash, path) in &rows {
+            let bytes = std::fs::read(root.join(path)).unwrap();
+            assert_eq!(&calculate_sha256(&bytes), hash);
+        }
+        let paths: HashSet<&String> = rows.iter().map(|row| &row.3).collect();
+        assert_eq!(paths.len(), rows.len(), "duplicate filenames get distinct paths");
+
+        let mut actual: Vec<(i64, i64, String)> = rows.drain(..).map(|(email, project, hash, _)| (email, project, hash)).collect();
+        actual.sort();
+        expected.sort();
+        assert_eq!(actual, expected);
+    }
+
+    /// 并行写入与逐个写入的耗时对比：`cargo test attachment_writer -- --ignored --nocapture`
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "benchmark, writes ~200 MB of fixture attachments"]
+    async fn benchmark_concurrent_vs_sequential_writes() {
+        const EMAILS: usize = 20;
+        const ATTACHMENTS_PER_EMAIL: usize = 20;
+        const ATTACHMENT_SIZE: usize = 256 * 1024;
+
+        file_manager::use_test_data_dir();
+        let (pool, _db_dir) = test_pool().await;
+        let policy = SafetyPolicy::load(&pool).await.unwrap();
+        let root = file_manager::attachments_root().unwrap();
+        let project_id = insert_project(&pool, "Bench").await;
+
+        // 两个账户各一份相同的邮箱，写入路径互不重叠
+        let mut mailboxes = Vec::new();
+        for email in ["sequential@example.com", "concurrent@example.com"] {
+            let account_id = insert_account(&pool, email).await;
+            let mut emails = Vec::new();
+            for index in 0..EMAILS {
+                let message_id = format!("<{}-{}>", email, index);
+                let email_id = email_in_project(&pool, account_id, &message_id, project_id).await;
+                let batch: Vec<ParsedAttachment> = (0..ATTACHMENTS_PER_EMAIL)
+                    .map(|i| attachment(&format!("scan-{}.pdf", i), vec![(index * ATTACHMENTS_PER_EMAIL + i) as u8; ATTACHMENT_SIZE]))
+                    .collect();
+                emails.push((email_id, batch));
+            }
+            mailboxes.push((account_id, emails));
+        }
+        let (concurrent_account, concurrent_emails) = mailboxes.pop().unwrap();
+        let (sequential_account, sequential_emails) = mailboxes.pop().unwrap();
+
+        // 改动前的做法：在同步循环里逐个写文件、逐条插入
+        let started = std::time::Instant::now();
+        for (email_id, batch) in sequential_emails {
+            for parsed in batch {
+                let safe_filename = sanitize_filename(&parsed.filename);
+                let written = write_attachment(&root, sequential_account, email_id, &safe_filename, parsed).unwrap();
+                insert_rows(&pool, email_id, &[written], &policy).await.unwrap();
+            }
+        }
+        let sequential = started.elapsed();
+
+        let started = std::time::Instant::now();
+        let mut writer = AttachmentWriter::new(pool.clone());
+        for (email_id, batch) in concurrent_emails {
+            writer.enqueue(concurrent_account, email_id, batch).await;
+        }
+        assert_eq!(writer.finish().await, EMAILS * ATTACHMENTS_PER_EMAIL);
+        let concurrent = started.elapsed();
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count as usize, 2 * EMAILS * ATTACHMENTS_PER_EMAIL);
+
+        println!(
+            "{} emails x {} attachments ({} KiB): sequential {:?}, concurrent {:?}, speedup {:.2}x",
+            EMAILS,
+            ATTACHMENTS_PER_EMAIL,
+            ATTACHMENT_SIZE / 1024,
+            sequential,
+            concurrent,
+            sequential.as_secs_f64() / concurrent.as_secs_f64()
+        );
+    }
+}
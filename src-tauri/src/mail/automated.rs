@@ -0,0 +1,237 @@
+/// 自动发送邮件识别
+///
+/// CI 机器人、DocuSign 提醒、"文档已被查看"之类的通知会淹没线程中的人工邮件。保存邮件时根据
+/// `Auto-Submitted` / `Precedence` 头、已知通知发件人以及线程内重复的相同主题设置 `is_automated`；
+/// 用户可以在 `sender_rules` 中按发件人（地址或 `@域名`）纠正判断。
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 同一线程中同一发件人的相同主题达到该数量（含本封）时视为自动通知
+const REPEATED_SUBJECT_THRESHOLD: i64 = 3;
+
+/// 通知类发件人的本地部分
+const NOTIFICATION_LOCAL_PARTS: &[&str] = &[
+    "noreply", "no-reply", "no_reply", "donotreply", "do-not-reply", "do_not_reply",
+    "notifications", "notification", "notify", "alerts", "mailer-daemon", "postmaster", "bounce",
+    "bounces",
+];
+
+/// 已知的通知域名
+const NOTIFICATION_DOMAINS: &[&str] = &[
+    "docusign.net", "echosign.com", "adobesign.com", "hellosign.com", "notifications.github.com",
+    "gitlab.com", "atlassian.net", "bitbucket.org", "circleci.com", "travis-ci.com", "travis-ci.org",
+    "sentry.io", "calendly.com", "zoom.us",
+];
+
+/// 邮件头中的自动发送标记
+#[derive(Debug, Clone, Default)]
+pub struct AutomatedHeaders<'a> {
+    pub auto_submitted: Option<&'a str>,
+    pub precedence: Option<&'a str>,
+    /// 含有无法解析的已读回执 / 投递状态报告
+    pub is_report: bool,
+}
+
+/// 按发件人的纠正规则
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SenderRule {
+    /// 小写邮箱地址或 `@域名`
+    pub pattern: String,
+    pub is_automated: bool,
+    pub created_at: Option<String>,
+}
+
+/// 自动邮件识别器
+pub struct AutomatedDetector {
+    pool: SqlitePool,
+}
+
+impl AutomatedDetector {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 判断即将保存的邮件是否为自动发送（发件人规则优先）
+    pub async fn detect(
+        &self,
+        sender: &str,
+        subject: &str,
+        thread_id: Option<&str>,
+        message_id: &str,
+        headers: &AutomatedHeaders<'_>,
+    ) -> Result<bool, AppError> {
+        if let Some(rule) = self.rule_for(sender).await? {
+            return Ok(rule);
+        }
+        if is_automated_by_headers(headers) || is_notification_sender(sender) {
+            return Ok(true);
+        }
+
+        let Some(thread_id) = thread_id else {
+            return Ok(false);
+        };
+        let repeated: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM emails
+            WHERE thread_id = ? AND sender = ? AND subject = ? AND message_id != ?
+            "#
+        )
+        .bind(thread_id)
+        .bind(sender)
+        .bind(subject)
+        .bind(message_id)
+        .fetch_one(&self.pool)
+        .await?;
+        if repeated + 1 < REPEATED_SUBJECT_THRESHOLD {
+            return Ok(false);
+        }
+
+        // 之前保存的相同邮件也一并标记
+        sqlx::query(
+            "UPDATE emails SET is_automated = 1 WHERE thread_id = ? AND sender = ? AND subject = ?"
+        )
+        .bind(thread_id)
+        .bind(sender)
+        .bind(subject)
+        .execute(&self.pool)
+        .await?;
+        Ok(true)
+    }
+
+    /// 发件人规则：先匹配完整地址，再匹配域名
+    async fn rule_for(&self, sender: &str) -> Result<Option<bool>, AppError> {
+        let Some(address) = sender_address(sender) else {
+            return Ok(None);
+        };
+        let domain = address.rsplit_once('@').map(|(_, domain)| format!("@{}", domain));
+
+        let rule: Option<bool> = sqlx::query_scalar(
+            r#"
+            SELECT is_automated FROM sender_rules
+            WHERE pattern IN (?, ?)
+            ORDER BY pattern LIKE '@%' ASC
+            LIMIT 1
+            "#
+        )
+        .bind(&address)
+        .bind(&domain)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(rule)
+    }
+
+    /// 设置发件人规则，并更新该发件人已有邮件的标记，返回更新的邮件数
+    pub async fn set_rule(&self, pattern: &str, is_automated: bool) -> Result<u64, AppError> {
+        let pattern = normalize_pattern(pattern)?;
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO sender_rules (pattern, is_automated) VALUES (?, ?)
+            ON CONFLICT(pattern) DO UPDATE SET is_automated = excluded.is_automated
+            "#
+        )
+        .bind(&pattern)
+        .bind(is_automated)
+        .execute(&mut *tx)
+        .await?;
+
+        // 发件人保存为 "addr" 或 "Name <addr>"
+        let (plain, bracketed) = match pattern.strip_prefix('@') {
+            Some(_) => (format!("%{}", pattern), format!("%{}>", pattern)),
+            None => (pattern.clone(), format!("%<{}>", pattern)),
+        };
+        let updated = sqlx::query(
+            "UPDATE emails SET is_automated = ? WHERE lower(sender) LIKE ? OR lower(sender) LIKE ?"
+        )
+        .bind(is_automated)
+        .bind(&plain)
+        .bind(&bracketed)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+
+        log::info!("Sender rule {} set to automated={} ({} emails updated)", pattern, is_automated, updated);
+        Ok(updated)
+    }
+
+    /// 删除发件人规则（已有邮件的标记保持不变）
+    pub async fn delete_rule(&self, pattern: &str) -> Result<(), AppError> {
+        let pattern = normalize_pattern(pattern)?;
+        sqlx::query("DELETE FROM sender_rules WHERE pattern = ?")
+            .bind(&pattern)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 获取所有发件人规则
+    pub async fn list_rules(&self) -> Result<Vec<SenderRule>, AppError> {
+        let rules = sqlx::query_as::<_, SenderRule>(
+            "SELECT pattern, is_automated, created_at FROM sender_rules ORDER BY pattern"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rules)
+    }
+}
+
+/// `Auto-Submitted` 不为 `no`，或 `Precedence: bulk / junk / auto_reply`
+pub fn is_automated_by_headers(headers: &AutomatedHeaders<'_>) -> bool {
+    let auto_submitted = headers
+        .auto_submitted
+        .map(|value| !value.trim().eq_ignore_ascii_case("no"))
+        .unwrap_or(false);
+    let precedence = headers
+        .precedence
+        .map(|value| {
+            let value = value.trim().to_ascii_lowercase();
+            matches!(value.as_str(), "bulk" | "junk" | "auto_reply")
+        })
+        .unwrap_or(false);
+    auto_submitted || precedence || headers.is_report
+}
+
+/// 发件人是否为已知的通知地址
+pub fn is_notification_sender(sender: &str) -> bool {
+    let Some(address) = sender_address(sender) else {
+        return false;
+    };
+    let Some((local, domain)) = address.rsplit_once('@') else {
+        return false;
+    };
+
+    let local = local.split('+').next().unwrap_or(local);
+    NOTIFICATION_LOCAL_PARTS.contains(&local)
+        || NOTIFICATION_DOMAINS
+            .iter()
+            .any(|known| domain == *known || domain.ends_with(&format!(".{}", known)))
+}
+
+/// 从 "Name <addr>" 中取出小写地址
+fn sender_address(sender: &str) -> Option<String> {
+    let address = match sender.rsplit_once('<') {
+        Some((_, rest)) => rest.trim_end_matches('>'),
+        None => sender,
+    };
+    let address = address.trim().to_lowercase();
+    address.contains('@').then_some(address)
+}
+
+pub(crate) fn normalize_pattern(pattern: &str) -> Result<String, AppError> {
+    let pattern = pattern.trim().to_lowercase();
+    let valid = match pattern.strip_prefix('@') {
+        Some(domain) => !domain.is_empty() && !domain.contains('@'),
+        None => pattern.matches('@').count() == 1 && !pattern.starts_with('@') && !pattern.ends_with('@'),
+    };
+    if !valid {
+        return Err(AppError::Validation(format!(
+            "Sender rule must be an email address or @domain: {}",
+            pattern
+        )));
+    }
+    Ok(pattern)
+}
@@ -0,0 +1,282 @@
+/// 正文补全
+///
+/// 仅同步了邮件头的邮件（`body_state = 'remote'`）在后台分批下载完整内容，
+/// 从最新的邮件开始。同一账户有用户发起的同步时或按流量计费模式下自动暂停，
+/// 服务器上已不存在的 UID 标记为 `missing`，不再重试。
+use crate::error::AppError;
+use crate::events::{EventEmitter, IndexProgressEvent, IndexStatus};
+use crate::mail::attachment_writer::save_email_attachments;
+use crate::mail::dedup::content_fingerprint;
+use crate::mail::imap_client::{AuthMethod, ImapConnection};
+use crate::mail::parser::parse_email;
+use crate::mail::providers::ProviderConfig;
+use crate::mail::receipts::ReceiptStore;
+use crate::mail::sync::ActiveSyncs;
+use crate::mail::throttle::{NetworkPolicy, TransferCounter};
+use crate::storage::body_store::BodyStore;
+use crate::utils::preview::preview_for;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// 正文补全结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackfillOutcome {
+    pub account_id: i64,
+    pub filled: usize,
+    pub missing: usize,
+    pub failed: usize,
+    /// 仍待补全的邮件数
+    pub remaining: i64,
+    /// 是否因用户同步或按流量计费模式而暂停
+    pub paused: bool,
+}
+
+/// 正文补全任务
+pub struct BodyBackfiller {
+    pool: SqlitePool,
+    active_syncs: ActiveSyncs,
+    event_emitter: Option<EventEmitter>,
+}
+
+impl BodyBackfiller {
+    pub fn new(pool: SqlitePool, active_syncs: ActiveSyncs) -> Self {
+        Self {
+            pool,
+            active_syncs,
+            event_emitter: None,
+        }
+    }
+
+    pub fn with_event_emitter(mut self, emitter: EventEmitter) -> Self {
+        self.event_emitter = Some(emitter);
+        self
+    }
+
+    fn emit_progress(&self, current: usize, total: usize, status: IndexStatus) {
+        if let Some(emitter) = &self.event_emitter {
+            emitter.emit_index_progress(IndexProgressEvent {
+                current,
+                total,
+                status,
+                index_type: "body_backfill".to_string(),
+            });
+        }
+    }
+
+    /// 待补全的邮件数
+    pub async fn pending_count(&self, account_id: i64) -> Result<i64, AppError> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM emails WHERE account_id = ? AND body_state = 'remote'"
+        )
+        .bind(account_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 补全账户的邮件正文
+    pub async fn run(
+        &self,
+        account_id: i64,
+        auth: AuthMethod,
+        provider: &ProviderConfig,
+        batch_size: usize,
+    ) -> Result<BackfillOutcome, AppError> {
+        let mut outcome = BackfillOutcome {
+            account_id,
+            ..Default::default()
+        };
+
+        let total = self.pending_count(account_id).await? as usize;
+        if total == 0 {
+            return Ok(outcome);
+        }
+        if self.active_syncs.is_syncing(account_id) {
+            log::info!("Account {} is syncing, body backfill deferred", account_id);
+            outcome.paused = true;
+            outcome.remaining = total as i64;
+            return Ok(outcome);
+        }
+        let policy = NetworkPolicy::load(&self.pool).await;
+        if policy.metered {
+            log::info!("Metered mode active, body backfill deferred for account {}", account_id);
+            outcome.paused = true;
+            outcome.remaining = total as i64;
+            return Ok(outcome);
+        }
+
+        log::info!("Backfilling {} email bodies for account {}", total, account_id);
+        self.emit_progress(0, total, IndexStatus::Starting);
+
+        let mut conn = ImapConnection::connect_with_provider_throttled(
+            provider,
+            auth,
+            policy.bytes_per_sec,
+            TransferCounter::default(),
+        )
+        .await?;
+        conn.select_folder("INBOX").await?;
+
+        let mut processed = 0;
+
+        let result = async {
+            loop {
+                if self.active_syncs.is_syncing(account_id) {
+                    log::info!("Account {} started syncing, pausing body backfill", account_id);
+                    outcome.paused = true;
+                    break;
+                }
+
+                let batch = self.next_batch(account_id, batch_size).await?;
+                if batch.is_empty() {
+                    break;
+                }
+
+                let uids: Vec<u32> = batch.keys().copied().collect();
+                conn.keepalive().await?;
+                let fetched = conn.fetch_emails(&uids).await?;
+
+                let mut seen = Vec::with_capacity(fetched.len());
+                for (uid, raw_data) in fetched {
+                    let Some(&email_id) = batch.get(&uid) else { continue };
+                    seen.push(uid);
+
+                    match self.fill_body(account_id, email_id, &raw_data).await {
+                        Ok(()) => outcome.filled += 1,
+                        Err(e) => {
+                            log::warn!("Failed to backfill email {} (UID {}): {}", email_id, uid, e);
+                            self.mark_state(email_id, "missing").await?;
+                            outcome.failed += 1;
+                        }
+                    }
+                }
+
+                // 服务器未返回的 UID 已被删除
+                for (uid, email_id) in &batch {
+                    if !seen.contains(uid) {
+                        log::info!("UID {} no longer exists on server, marking email {} missing", uid, email_id);
+                        self.mark_state(*email_id, "missing").await?;
+                        outcome.missing += 1;
+                    }
+                }
+
+                processed += batch.len();
+                self.emit_progress(processed.min(total), total, IndexStatus::Building);
+            }
+
+            Ok::<(), AppError>(())
+        }
+        .await;
+
+        conn.logout().await?;
+
+        if let Err(e) = result {
+            self.emit_progress(processed, total, IndexStatus::Failed);
+            return Err(e);
+        }
+
+        outcome.remaining = self.pending_count(account_id).await?;
+        self.emit_progress(processed, total, IndexStatus::Completed);
+        log::info!("Body backfill for account {}: {:?}", account_id, outcome);
+        Ok(outcome)
+    }
+
+    /// 取下一批待补全的邮件（UID -> 邮件 ID），最新的优先
+    async fn next_batch(&self, account_id: i64, batch_size: usize) -> Result<HashMap<u32, i64>, AppError> {
+        let rows: Vec<(i64, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, raw_path
+            FROM emails
+            WHERE account_id = ? AND body_state = 'remote'
+            ORDER BY datetime(date) DESC, id DESC
+            LIMIT ?
+            "#
+        )
+        .bind(account_id)
+        .bind(batch_size.max(1) as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut batch = HashMap::with_capacity(rows.len());
+        for (email_id, raw_path) in rows {
+            match raw_path.as_deref().and_then(|p| p.parse::<u32>().ok()) {
+                Some(uid) => {
+                    batch.insert(uid, email_id);
+                }
+                None => {
+                    // 没有 UID 无法补全
+                    self.mark_state(email_id, "missing").await?;
+                }
+            }
+        }
+
+        Ok(batch)
+    }
+
+    /// 重新解析完整邮件并更新正文和附件
+    async fn fill_body(
+        &self,
+        account_id: i64,
+        email_id: i64,
+        raw_data: &[u8],
+    ) -> Result<(), AppError> {
+        let mut parsed = parse_email(raw_data).map_err(AppError::Parse)?;
+        let fingerprint = content_fingerprint(
+            &parsed.subject,
+            &parsed.date,
+            &parsed.from,
+            parsed.body_text.as_deref(),
+        );
+
+        let body = BodyStore::load(&self.pool)
+            .await
+            .prepare(account_id, &parsed.message_id, parsed.body_text.as_deref(), parsed.body_html.as_deref())
+            .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE emails
+            SET body_text = ?, body_html = ?, body_truncated = ?, body_path = ?,
+                has_attachments = ?, content_fingerprint = ?, in_reply_to = ?, preview = ?, body_state = 'full'
+            WHERE id = ?
+            "#
+        )
+        .bind(&body.text)
+        .bind(&body.html)
+        .bind(body.truncated)
+        .bind(&body.path)
+        .bind(!parsed.attachments.is_empty())
+        .bind(&fingerprint)
+        .bind(&parsed.in_reply_to)
+        .bind(preview_for(parsed.body_text.as_deref()))
+        .bind(email_id)
+        .execute(&self.pool)
+        .await?;
+
+        save_email_attachments(&self.pool, account_id, email_id, std::mem::take(&mut parsed.attachments)).await?;
+
+        // 只有邮件头时无法识别回执，补全正文后记录回执并把这封邮件折叠为自动邮件
+        if let Some(receipt) = &parsed.receipt {
+            ReceiptStore::new(self.pool.clone()).save(account_id, &parsed, receipt).await?;
+        }
+        if parsed.receipt.is_some() || parsed.unparsed_report {
+            sqlx::query("UPDATE emails SET is_automated = 1 WHERE id = ?")
+                .bind(email_id)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn mark_state(&self, email_id: i64, state: &str) -> Result<(), AppError> {
+        sqlx::query("UPDATE emails SET body_state = ? WHERE id = ?")
+            .bind(state)
+            .bind(email_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,559 @@
+/// 会议邀请（iCalendar `METHOD:REQUEST`）和回复
+///
+/// 同步时识别邮件中的 text/calendar 部分（内联或 .ics 附件），按 UID 保存到 `calendar_events`；
+/// 同一 UID 的更新邀请（SEQUENCE 不小于已保存的）覆盖旧记录，之前的回复保留但标记为过期，时间线提示重新回复。
+///
+/// 回复时生成 `METHOD:REPLY` 的 iCalendar 发给组织者。有些组织者的客户端只识别内联的 text/calendar，
+/// 有些只识别 .ics 附件，所以两种形式同时发送。自己不在参与者列表中时（通过群组地址收到的邀请）
+/// 以账户地址回复，并在结果中返回警告。
+use crate::error::AppError;
+use crate::mail::outgoing::{MailSender, OutgoingEmail, SentEmail};
+use crate::mail::recipients;
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// iCalendar 行的最大长度（字节，不含换行）
+const FOLD_WIDTH: usize = 75;
+
+/// 回复时从邀请中原样复制的事件属性
+const REPLY_PROPERTIES: &[&str] = &[
+    "UID", "SEQUENCE", "RECURRENCE-ID", "DTSTART", "DTEND", "DURATION", "ORGANIZER", "SUMMARY", "LOCATION",
+];
+
+/// 对邀请的回复
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InviteResponse {
+    Accepted,
+    Declined,
+    Tentative,
+}
+
+impl InviteResponse {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InviteResponse::Accepted => "accepted",
+            InviteResponse::Declined => "declined",
+            InviteResponse::Tentative => "tentative",
+        }
+    }
+
+    /// iCalendar 的 PARTSTAT 值
+    fn partstat(&self) -> &'static str {
+        match self {
+            InviteResponse::Accepted => "ACCEPTED",
+            InviteResponse::Declined => "DECLINED",
+            InviteResponse::Tentative => "TENTATIVE",
+        }
+    }
+
+    /// 回复邮件的主题前缀（与 Outlook / Google Calendar 一致）
+    fn subject_prefix(&self) -> &'static str {
+        match self {
+            InviteResponse::Accepted => "Accepted",
+            InviteResponse::Declined => "Declined",
+            InviteResponse::Tentative => "Tentative",
+        }
+    }
+}
+
+/// iCalendar 属性（`NAME;PARAMS:VALUE`，已展开折行）
+#[derive(Debug, Clone)]
+struct IcsProperty {
+    /// 大写属性名
+    name: String,
+    /// 原始参数（含开头的分号），如 `;TZID=Asia/Shanghai`
+    params: String,
+    value: String,
+}
+
+impl IcsProperty {
+    /// 参数值（不区分大小写，去除引号）
+    fn param(&self, key: &str) -> Option<&str> {
+        split_params(&self.params)
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.trim_matches('"'))
+    }
+
+    fn line(&self) -> String {
+        format!("{}{}:{}", self.name, self.params, self.value)
+    }
+}
+
+/// 邀请中的参与者
+#[derive(Debug, Clone)]
+struct Attendee {
+    address: String,
+    name: Option<String>,
+}
+
+/// 解析出的会议邀请
+#[derive(Debug, Clone)]
+pub struct ParsedInvite {
+    pub uid: String,
+    pub sequence: i64,
+    pub summary: Option<String>,
+    pub location: Option<String>,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+    pub organizer_address: Option<String>,
+    pub organizer_name: Option<String>,
+    attendees: Vec<Attendee>,
+}
+
+/// 时间线上显示在邀请邮件下的会议信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarInvite {
+    pub id: i64,
+    pub summary: Option<String>,
+    pub location: Option<String>,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+    pub organizer: Option<String>,
+    /// accepted / declined / tentative，未回复时为空
+    pub my_response: Option<String>,
+    /// 回复之后邀请有更新（时间、地点等），需要重新回复
+    pub response_outdated: bool,
+}
+
+/// 回复结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InviteReply {
+    pub calendar_event_id: i64,
+    pub response: InviteResponse,
+    /// 回复使用的参与者地址
+    pub attendee: String,
+    /// 自己不在参与者列表中等需要提示的情况
+    pub warning: Option<String>,
+    pub sent: SentEmail,
+}
+
+/// 解析 METHOD:REQUEST 的邀请（其他 METHOD 或没有 VEVENT / UID 时返回 None）
+pub fn parse_invite(ics: &str) -> Option<ParsedInvite> {
+    let properties = parse_properties(ics);
+    let method = properties
+        .iter()
+        .take_while(|property| !(property.name == "BEGIN" && property.value.eq_ignore_ascii_case("VEVENT")))
+        .find(|property| property.name == "METHOD")?;
+    if !method.value.trim().eq_ignore_ascii_case("REQUEST") {
+        return None;
+    }
+
+    let event = event_properties(&properties);
+    let find = |name: &str| event.iter().find(|property| property.name == name);
+    let text = |name: &str| find(name).map(|property| unescape(&property.value)).filter(|value| !value.is_empty());
+
+    let uid = find("UID")?.value.trim().to_string();
+    if uid.is_empty() {
+        return None;
+    }
+    let organizer = find("ORGANIZER");
+
+    Some(ParsedInvite {
+        sequence: find("SEQUENCE").and_then(|property| property.value.trim().parse().ok()).unwrap_or(0),
+        summary: text("SUMMARY"),
+        location: text("LOCATION"),
+        starts_at: find("DTSTART").map(|property| ics_datetime(&property.value)),
+        ends_at: find("DTEND").map(|property| ics_datetime(&property.value)),
+        organizer_address: organizer.and_then(|property| mailto(&property.value)),
+        organizer_name: organizer.and_then(|property| property.param("CN")).map(str::to_string),
+        attendees: event
+            .iter()
+            .filter(|property| property.name == "ATTENDEE")
+            .filter_map(|property| {
+                Some(Attendee {
+                    address: mailto(&property.value)?,
+                    name: property.param("CN").map(str::to_string),
+                })
+            })
+            .collect(),
+        uid,
+    })
+}
+
+/// 展开折行并拆分属性（只接受 CRLF 或 LF 换行）
+fn parse_properties(ics: &str) -> Vec<IcsProperty> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(previous)) => previous.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+        .into_iter()
+        .filter_map(|line| {
+            let colon = value_separator(&line)?;
+            let (head, value) = (&line[..colon], &line[colon + 1..]);
+            let (name, params) = match head.find(';') {
+                Some(index) => (&head[..index], &head[index..]),
+                None => (head, ""),
+            };
+            Some(IcsProperty {
+                name: name.trim().to_ascii_uppercase(),
+                params: params.to_string(),
+                value: value.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// 属性名/参数与值之间的冒号位置（参数值中带引号的冒号不算）
+fn value_separator(line: &str) -> Option<usize> {
+    let mut quoted = false;
+    for (index, ch) in line.char_indices() {
+        match ch {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 按分号拆分参数（引号内的分号不拆）
+fn split_params(params: &str) -> impl Iterator<Item = &str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (index, ch) in params.char_indices() {
+        match ch {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&params[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&params[start..]);
+    parts.into_iter().filter(|part| !part.is_empty())
+}
+
+/// 第一个 VEVENT 的属性（不含嵌套的 VALARM）
+fn event_properties(properties: &[IcsProperty]) -> Vec<&IcsProperty> {
+    let mut depth = 0;
+    let mut in_event = false;
+    let mut event = Vec::new();
+    for property in properties {
+        match property.name.as_str() {
+            "BEGIN" if in_event => depth += 1,
+            "BEGIN" if property.value.eq_ignore_ascii_case("VEVENT") => in_event = true,
+            "END" if in_event && depth > 0 => depth -= 1,
+            "END" if in_event => break,
+            _ if in_event && depth == 0 => event.push(property),
+            _ => {}
+        }
+    }
+    event
+}
+
+/// VTIMEZONE 组件的原始行（回复中的 TZID 需要对应的时区定义）
+fn timezone_lines(properties: &[IcsProperty]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut in_timezone = false;
+    for property in properties {
+        if property.name == "BEGIN" && property.value.eq_ignore_ascii_case("VTIMEZONE") {
+            in_timezone = true;
+        }
+        if in_timezone {
+            lines.push(property.line());
+        }
+        if property.name == "END" && property.value.eq_ignore_ascii_case("VTIMEZONE") {
+            in_timezone = false;
+        }
+    }
+    lines
+}
+
+/// `mailto:` 地址（小写）
+fn mailto(value: &str) -> Option<String> {
+    let value = value.trim();
+    let address = match value.get(..7) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("mailto:") => &value[7..],
+        _ => value,
+    };
+    let address = address.trim().to_lowercase();
+    address.contains('@').then_some(address)
+}
+
+/// iCalendar 日期时间转换为显示用的格式：UTC 时间为 RFC 3339，全天事件为日期，
+/// 带 TZID 或浮动时间保留为本地时间（不做时区换算）
+fn ics_datetime(value: &str) -> String {
+    let value = value.trim();
+    if let Some(utc) = value.strip_suffix(['Z', 'z']) {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S") {
+            return datetime.and_utc().to_rfc3339();
+        }
+    }
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return datetime.format("%Y-%m-%dT%H:%M:%S").to_string();
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return date.format("%Y-%m-%d").to_string();
+    }
+    value.to_string()
+}
+
+/// TEXT 值的转义还原
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", "\n")
+        .replace("\\N", "\n")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+        .trim()
+        .to_string()
+}
+
+/// 按 75 字节折行（不拆分 UTF-8 字符），行尾 CRLF
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+    for ch in line.chars() {
+        if width + ch.len_utf8() > FOLD_WIDTH {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(ch);
+        width += ch.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// 生成 METHOD:REPLY 的 iCalendar
+fn build_reply(invite_ics: &str, attendee: &str, attendee_name: Option<&str>, response: InviteResponse) -> String {
+    let properties = parse_properties(invite_ics);
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "PRODID:-//ThreadLine//ThreadLine//EN".to_string(),
+        "VERSION:2.0".to_string(),
+        "METHOD:REPLY".to_string(),
+    ];
+    lines.extend(timezone_lines(&properties));
+    lines.push("BEGIN:VEVENT".to_string());
+    for property in event_properties(&properties) {
+        if REPLY_PROPERTIES.contains(&property.name.as_str()) {
+            lines.push(property.line());
+        }
+    }
+    lines.push(format!("DTSTAMP:{}", Utc::now().format("%Y%m%dT%H%M%SZ")));
+    let name = attendee_name
+        .map(|name| format!(";CN=\"{}\"", name.replace('"', "'")))
+        .unwrap_or_default();
+    lines.push(format!("ATTENDEE;PARTSTAT={}{}:mailto:{}", response.partstat(), name, attendee));
+    lines.push("END:VEVENT".to_string());
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// 保存的邀请
+#[derive(sqlx::FromRow)]
+struct CalendarEventRow {
+    account_id: i64,
+    email_id: Option<i64>,
+    sequence: i64,
+    summary: Option<String>,
+    organizer_address: Option<String>,
+    raw_ics: String,
+}
+
+/// 会议邀请存储
+pub struct CalendarStore {
+    pool: SqlitePool,
+}
+
+impl CalendarStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 保存邮件中的邀请；同一 UID 的旧版本（SEQUENCE 更小）被覆盖，更旧的邀请重复同步时忽略
+    pub async fn save_invite(&self, account_id: i64, email_id: i64, ics: &str) -> Result<Option<i64>, AppError> {
+        let Some(invite) = parse_invite(ics) else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO calendar_events (
+                account_id, email_id, uid, sequence, summary, location, starts_at, ends_at,
+                organizer_address, organizer_name, raw_ics
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(account_id, uid) DO UPDATE SET
+                email_id = excluded.email_id,
+                sequence = excluded.sequence,
+                summary = excluded.summary,
+                location = excluded.location,
+                starts_at = excluded.starts_at,
+                ends_at = excluded.ends_at,
+                organizer_address = excluded.organizer_address,
+                organizer_name = excluded.organizer_name,
+                raw_ics = excluded.raw_ics,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE excluded.sequence >= calendar_events.sequence
+            "#
+        )
+        .bind(account_id)
+        .bind(email_id)
+        .bind(&invite.uid)
+        .bind(invite.sequence)
+        .bind(&invite.summary)
+        .bind(&invite.location)
+        .bind(&invite.starts_at)
+        .bind(&invite.ends_at)
+        .bind(&invite.organizer_address)
+        .bind(&invite.organizer_name)
+        .bind(ics)
+        .execute(&self.pool)
+        .await?;
+
+        let id: i64 = sqlx::query_scalar("SELECT id FROM calendar_events WHERE account_id = ? AND uid = ?")
+            .bind(account_id)
+            .bind(&invite.uid)
+            .fetch_one(&self.pool)
+            .await?;
+        log::debug!("Stored calendar invite {} (sequence {}) from email {}", invite.uid, invite.sequence, email_id);
+        Ok(Some(id))
+    }
+
+    /// 回复邀请：向组织者发送 METHOD:REPLY，并记录自己的回复
+    pub async fn respond(&self, calendar_event_id: i64, response: InviteResponse) -> Result<InviteReply, AppError> {
+        let event = sqlx::query_as::<_, CalendarEventRow>(
+            r#"
+            SELECT account_id, email_id, sequence, summary, organizer_address, raw_ics
+            FROM calendar_events WHERE id = ?
+            "#
+        )
+        .bind(calendar_event_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::Validation(format!("Calendar event {} not found", calendar_event_id)))?;
+        let organizer = event
+            .organizer_address
+            .clone()
+            .ok_or_else(|| AppError::Validation("The invite has no organizer to respond to".to_string()))?;
+        let account_email: String = sqlx::query_scalar("SELECT email FROM accounts WHERE id = ?")
+            .bind(event.account_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::Validation(format!("Account {} not found", event.account_id)))?;
+
+        // 参与者列表中自己的地址（别名、+标签地址也算）
+        let mine = recipients::my_addresses(&self.pool).await?;
+        let invite = parse_invite(&event.raw_ics);
+        let listed = invite
+            .as_ref()
+            .and_then(|invite| invite.attendees.iter().find(|attendee| recipients::is_my_address(&attendee.address, &mine)));
+        let (attendee, attendee_name, warning) = match listed {
+            Some(attendee) => (attendee.address.clone(), attendee.name.clone(), None),
+            None => {
+                log::warn!(
+                    "{} is not an attendee of calendar event {}, responding as the account address",
+                    account_email, calendar_event_id
+                );
+                (
+                    account_email.to_lowercase(),
+                    None,
+                    Some(format!(
+                        "{} is not in the attendee list of this invite; the organizer may not match the response",
+                        account_email
+                    )),
+                )
+            }
+        };
+
+        let summary = event.summary.clone().unwrap_or_default();
+        let display = attendee_name.as_deref().unwrap_or(&attendee);
+        let email = OutgoingEmail {
+            account_id: event.account_id,
+            identity_id: None,
+            to: vec![organizer],
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            subject: format!("{}: {}", response.subject_prefix(), summary).trim_end_matches([' ', ':']).to_string(),
+            body_text: format!("{} has {} this invitation.", display, response.as_str()),
+            body_html: None,
+            reply_to_email_id: event.email_id,
+            attachments: Vec::new(),
+            calendar_reply: Some(build_reply(&event.raw_ics, &attendee, attendee_name.as_deref(), response)),
+        };
+        let sent = MailSender::new(self.pool.clone()).send(&email).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE calendar_events
+            SET my_response = ?, responded_sequence = ?, responded_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#
+        )
+        .bind(response.as_str())
+        .bind(event.sequence)
+        .bind(calendar_event_id)
+        .execute(&self.pool)
+        .await?;
+        log::info!("Responded {} to calendar event {}", response.as_str(), calendar_event_id);
+
+        Ok(InviteReply {
+            calendar_event_id,
+            response,
+            attendee,
+            warning,
+            sent,
+        })
+    }
+
+    /// 获取项目中各邀请邮件的会议信息，按邮件 ID 索引
+    pub async fn for_project(&self, project_id: i64) -> Result<HashMap<i64, CalendarInvite>, AppError> {
+        #[derive(sqlx::FromRow)]
+        struct InviteRow {
+            email_id: i64,
+            id: i64,
+            summary: Option<String>,
+            location: Option<String>,
+            starts_at: Option<String>,
+            ends_at: Option<String>,
+            organizer_address: Option<String>,
+            organizer_name: Option<String>,
+            my_response: Option<String>,
+            response_outdated: bool,
+        }
+
+        let rows = sqlx::query_as::<_, InviteRow>(
+            r#"
+            SELECT c.email_id, c.id, c.summary, c.location, c.starts_at, c.ends_at,
+                   c.organizer_address, c.organizer_name, c.my_response,
+                   (c.my_response IS NOT NULL AND c.responded_sequence < c.sequence) AS response_outdated
+            FROM calendar_events c
+            JOIN emails e ON e.id = c.email_id
+            WHERE e.project_id = ?
+            "#
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (row.email_id, CalendarInvite {
+                    id: row.id,
+                    summary: row.summary,
+                    location: row.location,
+                    starts_at: row.starts_at,
+                    ends_at: row.ends_at,
+                    organizer: row.organizer_name.or(row.organizer_address),
+                    my_response: row.my_response,
+                    response_outdated: row.response_outdated,
+                })
+            })
+            .collect())
+    }
+}
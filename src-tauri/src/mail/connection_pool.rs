@@ -0,0 +1,188 @@
+/// 交互操作的 IMAP 连接池
+///
+/// 服务器端搜索、导入单封邮件、修复附件每次都要重新建立连接（TCP + TLS + 认证，约 1–3 秒）。
+/// 连接池为每个账户保留最多一个已认证的会话，交互命令通过租约使用；同一账户的多个命令排队等待这一个会话，
+/// 不会额外打开连接（服务商限制同时连接数）。同步使用自己的专用连接，不经过连接池。
+///
+/// 后台任务定期对空闲会话发送 NOOP 保活，空闲超过 `IDLE_TIMEOUT` 后登出。
+/// 凭据变化（OAuth 令牌已刷新、密码已修改）时丢弃旧会话并重新认证。
+use crate::error::AppError;
+use crate::mail::imap_client::{AuthMethod, ImapConnection};
+use crate::mail::providers::ProviderConfig;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// 空闲会话保留时间
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// 空闲会话的保活间隔（取用前超过该时间未使用也先 NOOP 确认连接仍可用）
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 等待同一账户的会话被归还的最长时间
+const LEASE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 保留的会话
+struct PooledSession {
+    connection: ImapConnection,
+    /// 认证信息的指纹（不保存凭据本身）
+    credentials: String,
+    /// 最近一次归还的时间（空闲超时按此计算）
+    last_used: Instant,
+    /// 最近一次确认连接可用的时间
+    last_checked: Instant,
+}
+
+type Slot = Arc<AsyncMutex<Option<PooledSession>>>;
+
+/// IMAP 连接池（注册为全局状态）
+#[derive(Clone, Default)]
+pub struct ImapConnectionPool {
+    slots: Arc<Mutex<HashMap<i64, Slot>>>,
+}
+
+/// 连接租约，释放时会话归还连接池
+///
+/// 操作中途出错、会话状态不确定时调用 `discard` 丢弃会话，下次取用时重新连接。
+pub struct PooledConnection {
+    guard: OwnedMutexGuard<Option<PooledSession>>,
+}
+
+impl PooledConnection {
+    /// 丢弃会话（不登出，连接随之关闭）
+    pub fn discard(mut self) {
+        self.guard.take();
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = ImapConnection;
+
+    fn deref(&self) -> &ImapConnection {
+        &self.guard.as_ref().expect("leased session").connection
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut ImapConnection {
+        &mut self.guard.as_mut().expect("leased session").connection
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(session) = self.guard.as_mut() {
+            session.last_used = Instant::now();
+            session.last_checked = session.last_used;
+        }
+    }
+}
+
+impl ImapConnectionPool {
+    fn slot(&self, account_id: i64) -> Slot {
+        self.slots.lock().unwrap().entry(account_id).or_default().clone()
+    }
+
+    /// 取得账户的会话：复用空闲会话，没有或凭据已变化时重新连接
+    ///
+    /// 同一账户的会话正在被其他命令使用时排队等待。
+    pub async fn acquire(
+        &self,
+        account_id: i64,
+        auth: AuthMethod,
+        provider: &ProviderConfig,
+    ) -> Result<PooledConnection, AppError> {
+        let mut guard = tokio::time::timeout(LEASE_TIMEOUT, self.slot(account_id).lock_owned())
+            .await
+            .map_err(|_| AppError::Imap(format!("Timed out waiting for the IMAP session of account {}", account_id)))?;
+        let credentials = fingerprint(&auth);
+
+        if let Some(session) = guard.take() {
+            if session.credentials != credentials {
+                log::info!("Credentials of account {} changed, re-authenticating pooled IMAP session", account_id);
+                let _ = session.connection.logout().await;
+            } else if let Some(session) = verify(session).await {
+                log::debug!("Reusing pooled IMAP session for account {}", account_id);
+                *guard = Some(session);
+                return Ok(PooledConnection { guard });
+            } else {
+                log::info!("Pooled IMAP session of account {} is no longer usable, reconnecting", account_id);
+            }
+        }
+
+        let connection = ImapConnection::connect_with_provider(provider, auth).await?;
+        *guard = Some(PooledSession {
+            connection,
+            credentials,
+            last_used: Instant::now(),
+            last_checked: Instant::now(),
+        });
+        Ok(PooledConnection { guard })
+    }
+
+    /// 保活空闲会话，登出空闲超时的会话（正在使用的会话跳过）
+    pub async fn maintain(&self) {
+        let slots: Vec<(i64, Slot)> = self
+            .slots
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(account_id, slot)| (*account_id, slot.clone()))
+            .collect();
+
+        for (account_id, slot) in slots {
+            let Ok(mut guard) = slot.try_lock() else { continue };
+            let Some(session) = guard.take() else { continue };
+            if session.last_used.elapsed() >= IDLE_TIMEOUT {
+                log::info!("Closing idle pooled IMAP session for account {}", account_id);
+                let _ = session.connection.logout().await;
+                continue;
+            }
+            *guard = verify(session).await;
+        }
+    }
+
+    /// 启动后台保活循环
+    pub fn spawn_maintenance(&self) {
+        let pool = self.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(KEEPALIVE_INTERVAL).await;
+                pool.maintain().await;
+            }
+        });
+    }
+}
+
+/// 距上次确认超过保活间隔时 NOOP 确认连接可用，不可用返回 None
+async fn verify(mut session: PooledSession) -> Option<PooledSession> {
+    if session.last_checked.elapsed() < KEEPALIVE_INTERVAL {
+        return Some(session);
+    }
+    match session.connection.noop().await {
+        Ok(()) => {
+            session.last_checked = Instant::now();
+            Some(session)
+        }
+        Err(e) => {
+            log::info!("Pooled IMAP session failed NOOP: {}", e);
+            None
+        }
+    }
+}
+
+fn fingerprint(auth: &AuthMethod) -> String {
+    let (kind, username, secret) = match auth {
+        AuthMethod::Password { username, password } => ("password", username, password),
+        AuthMethod::OAuth { username, access_token } => ("oauth", username, access_token),
+    };
+    let mut hasher = Sha256::new();
+    for part in [kind, username.as_str(), secret.as_str()] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
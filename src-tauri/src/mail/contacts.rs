@@ -0,0 +1,437 @@
+/// 联系人（从同步历史中收集）
+///
+/// 每封邮件的发件人记为"收到"，自己发出的邮件的收件人/抄送记为"发出"。
+/// 用于撰写邮件时的收件人自动补全。
+///
+/// 同一个人的多个地址可以合并：别名的 `canonical_id` 指向主联系人，计算项目参与者、
+/// 互动统计和回复收件人时按主联系人去重。自动规则只提出合并建议（同一域名下的同名联系人、
+/// 只差 "+标签" 或 Gmail 点号的地址），由用户确认，从不跨域名合并。
+use crate::error::AppError;
+use crate::mail::parser::ParsedEmail;
+use crate::mail::recipients::split_sender;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// 忽略本地部分点号的域名
+const GMAIL_DOMAINS: &[&str] = &["gmail.com", "googlemail.com"];
+
+/// 收件人建议
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RecipientSuggestion {
+    pub name: Option<String>,
+    pub address: String,
+    pub last_interaction: Option<String>,
+}
+
+/// 联系人摘要（合并管理用）
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ContactSummary {
+    pub id: i64,
+    pub address: String,
+    pub name: Option<String>,
+    pub received_count: i64,
+    pub sent_count: i64,
+}
+
+/// 合并建议
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeProposal {
+    /// 建议的主联系人（互动最多的地址）
+    pub primary: ContactSummary,
+    pub aliases: Vec<ContactSummary>,
+    /// "same_name" / "address_variant"
+    pub reasons: Vec<String>,
+}
+
+/// 别名地址对应的主联系人
+#[derive(Debug, Clone)]
+pub struct CanonicalContact {
+    pub name: Option<String>,
+    pub address: String,
+}
+
+/// 联系人簿
+pub struct ContactBook {
+    pool: SqlitePool,
+}
+
+impl ContactBook {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 记录一封邮件中的联系人
+    pub async fn record_email(&self, account_id: i64, parsed: &ParsedEmail) -> Result<(), AppError> {
+        // 账户主地址和它的身份
+        let own: Vec<String> = sqlx::query_scalar(
+            "SELECT email FROM accounts WHERE id = ? UNION SELECT email FROM account_identities WHERE account_id = ?"
+        )
+        .bind(account_id)
+        .bind(account_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let own: Vec<String> = own.into_iter().map(|address| address.trim().to_lowercase()).collect();
+
+        let from_address = parsed.from_address.as_deref().unwrap_or_default();
+        if !from_address.is_empty() && own.iter().any(|address| address == from_address) {
+            // 自己发出的邮件：收件人和抄送计为"发出"
+            for recipient in parsed.to.iter().chain(parsed.cc.iter()) {
+                let (name, address) = split_sender(recipient);
+                self.upsert(name.as_deref(), address.as_deref(), &parsed.date, true).await?;
+            }
+        } else {
+            self.upsert(parsed.from_name.as_deref(), parsed.from_address.as_deref(), &parsed.date, false)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn upsert(&self, name: Option<&str>, address: Option<&str>, date: &str, sent: bool) -> Result<(), AppError> {
+        let Some(address) = address.filter(|address| address.contains('@')) else {
+            return Ok(());
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO contacts (
+                address, name, address_folded, name_folded,
+                last_interaction, received_count, sent_count
+            ) VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(address) DO UPDATE SET
+                name = COALESCE(excluded.name, contacts.name),
+                name_folded = COALESCE(excluded.name_folded, contacts.name_folded),
+                last_interaction = MAX(COALESCE(contacts.last_interaction, ''), excluded.last_interaction),
+                received_count = contacts.received_count + excluded.received_count,
+                sent_count = contacts.sent_count + excluded.sent_count
+            "#
+        )
+        .bind(address)
+        .bind(name)
+        .bind(fold(address))
+        .bind(name.map(fold))
+        .bind(normalize_date(date))
+        .bind(if sent { 0 } else { 1 })
+        .bind(if sent { 1 } else { 0 })
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 收件人建议
+    ///
+    /// 前缀匹配姓名和地址（不区分大小写和变音符号），
+    /// 按互动频率和最近程度排序，发送过邮件的联系人权重更高，已屏蔽的联系人不返回。
+    pub async fn suggest(&self, prefix: &str, limit: i64) -> Result<Vec<RecipientSuggestion>, AppError> {
+        let prefix = fold(prefix.trim());
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
+        // 前缀范围查询可以使用索引（LIKE 不区分大小写时无法使用）
+        let upper = format!("{}\u{10FFFF}", prefix);
+
+        // 命中别名时返回主联系人，互动次数按主联系人合计
+        let suggestions = sqlx::query_as::<_, RecipientSuggestion>(
+            r#"
+            SELECT p.name, p.address, MAX(c.last_interaction) AS last_interaction
+            FROM contacts c
+            JOIN contacts p ON p.id = COALESCE(c.canonical_id, c.id)
+            WHERE ((c.address_folded >= ?1 AND c.address_folded < ?2)
+                OR (c.name_folded >= ?1 AND c.name_folded < ?2))
+              AND p.is_muted = 0
+            GROUP BY p.id
+            ORDER BY
+                (SELECT SUM(m.received_count) + 3.0 * SUM(m.sent_count)
+                 FROM contacts m WHERE COALESCE(m.canonical_id, m.id) = p.id)
+                    / (1.0 + MAX(julianday('now') - COALESCE(julianday(MAX(c.last_interaction)), julianday('now') - 365), 0) / 30.0)
+                DESC,
+                p.address ASC
+            LIMIT ?3
+            "#
+        )
+        .bind(&prefix)
+        .bind(&upper)
+        .bind(limit.clamp(1, 50))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(suggestions)
+    }
+
+    /// 屏蔽/取消屏蔽联系人（屏蔽的地址不出现在建议中）
+    pub async fn set_muted(&self, address: &str, muted: bool) -> Result<(), AppError> {
+        let address = address.trim().to_lowercase();
+        sqlx::query(
+            r#"
+            INSERT INTO contacts (address, address_folded, is_muted) VALUES (?, ?, ?)
+            ON CONFLICT(address) DO UPDATE SET is_muted = excluded.is_muted
+            "#
+        )
+        .bind(&address)
+        .bind(fold(&address))
+        .bind(muted)
+        .execute(&self.pool)
+        .await?;
+
+        log::info!("Contact {} muted: {}", address, muted);
+        Ok(())
+    }
+
+    /// 把 `alias_ids` 合并到 `primary_id`
+    ///
+    /// 主联系人本身是别名时合并到它的主联系人；别名原有的别名一并指向新的主联系人。
+    pub async fn merge(&self, primary_id: i64, alias_ids: &[i64]) -> Result<ContactSummary, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let primary: Option<i64> = sqlx::query_scalar("SELECT COALESCE(canonical_id, id) FROM contacts WHERE id = ?")
+            .bind(primary_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let primary = primary.ok_or_else(|| AppError::Validation(format!("Contact {} not found", primary_id)))?;
+
+        for &alias_id in alias_ids {
+            if alias_id == primary {
+                continue;
+            }
+            let result = sqlx::query("UPDATE contacts SET canonical_id = ? WHERE id = ?")
+                .bind(primary)
+                .bind(alias_id)
+                .execute(&mut *tx)
+                .await?;
+            if result.rows_affected() == 0 {
+                return Err(AppError::Validation(format!("Contact {} not found", alias_id)));
+            }
+            sqlx::query("UPDATE contacts SET canonical_id = ? WHERE canonical_id = ?")
+                .bind(primary)
+                .bind(alias_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        log::info!("Merged contacts {:?} into {}", alias_ids, primary);
+        self.summary(primary).await
+    }
+
+    /// 取消合并，别名恢复为独立联系人
+    pub async fn unmerge(&self, contact_id: i64) -> Result<(), AppError> {
+        let result = sqlx::query("UPDATE contacts SET canonical_id = NULL WHERE id = ? AND canonical_id IS NOT NULL")
+            .bind(contact_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::Validation(format!("Contact {} is not merged into another contact", contact_id)));
+        }
+
+        log::info!("Unmerged contact {}", contact_id);
+        Ok(())
+    }
+
+    /// 主联系人的别名
+    pub async fn aliases(&self, primary_id: i64) -> Result<Vec<ContactSummary>, AppError> {
+        let aliases = sqlx::query_as::<_, ContactSummary>(
+            r#"
+            SELECT id, address, name, COALESCE(received_count, 0) AS received_count, COALESCE(sent_count, 0) AS sent_count
+            FROM contacts
+            WHERE canonical_id = ?
+            ORDER BY address
+            "#
+        )
+        .bind(primary_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(aliases)
+    }
+
+    async fn summary(&self, id: i64) -> Result<ContactSummary, AppError> {
+        sqlx::query_as::<_, ContactSummary>(
+            r#"
+            SELECT id, address, name, COALESCE(received_count, 0) AS received_count, COALESCE(sent_count, 0) AS sent_count
+            FROM contacts
+            WHERE id = ?
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::Validation(format!("Contact {} not found", id)))
+    }
+
+    /// 合并建议：同一域名下同名的联系人，或只差 "+标签"（Gmail 还忽略点号）的地址
+    pub async fn propose_merges(&self) -> Result<Vec<MergeProposal>, AppError> {
+        let contacts = sqlx::query_as::<_, ContactSummary>(
+            r#"
+            SELECT id, address, name, COALESCE(received_count, 0) AS received_count, COALESCE(sent_count, 0) AS sent_count
+            FROM contacts
+            WHERE canonical_id IS NULL AND is_muted = 0
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        // 分组键都包含域名，不会跨域名
+        let mut groups: HashMap<(String, String), Vec<usize>> = HashMap::new();
+        for (index, contact) in contacts.iter().enumerate() {
+            let Some((local, domain)) = contact.address.rsplit_once('@') else { continue };
+            groups
+                .entry(("address_variant".to_string(), format!("{}@{}", normalize_local_part(local, domain), domain)))
+                .or_default()
+                .push(index);
+            if let Some(name) = contact.name.as_deref().map(fold).map(|name| name.split_whitespace().collect::<Vec<_>>().join(" ")) {
+                if name.chars().count() >= 3 && !name.contains('@') {
+                    groups
+                        .entry(("same_name".to_string(), format!("{}@{}", name, domain)))
+                        .or_default()
+                        .push(index);
+                }
+            }
+        }
+
+        // 合并有重叠的分组
+        let mut parent: Vec<usize> = (0..contacts.len()).collect();
+        fn root(parent: &mut [usize], mut index: usize) -> usize {
+            while parent[index] != index {
+                parent[index] = parent[parent[index]];
+                index = parent[index];
+            }
+            index
+        }
+        let mut reasons: HashMap<usize, Vec<String>> = HashMap::new();
+        for ((reason, _), members) in groups.iter().filter(|(_, members)| members.len() > 1) {
+            let first = root(&mut parent, members[0]);
+            for &member in &members[1..] {
+                let other = root(&mut parent, member);
+                parent[other] = first;
+            }
+            reasons.entry(members[0]).or_default().push(reason.clone());
+        }
+
+        let mut clusters: HashMap<usize, (Vec<usize>, Vec<String>)> = HashMap::new();
+        for index in 0..contacts.len() {
+            let cluster = root(&mut parent, index);
+            clusters.entry(cluster).or_default().0.push(index);
+        }
+        for (member, member_reasons) in reasons {
+            let cluster = root(&mut parent, member);
+            clusters.entry(cluster).or_default().1.extend(member_reasons);
+        }
+
+        let mut proposals: Vec<MergeProposal> = clusters
+            .into_values()
+            .filter(|(members, _)| members.len() > 1)
+            .map(|(mut members, mut reasons)| {
+                members.sort_by_key(|&index| {
+                    let contact = &contacts[index];
+                    (std::cmp::Reverse(contact.received_count + contact.sent_count), contact.id)
+                });
+                reasons.sort();
+                reasons.dedup();
+                let mut members = members.into_iter().map(|index| contacts[index].clone());
+                let primary = members.next().expect("cluster has at least two members");
+                MergeProposal {
+                    primary,
+                    aliases: members.collect(),
+                    reasons,
+                }
+            })
+            .collect();
+        proposals.sort_by(|a, b| a.primary.address.cmp(&b.primary.address));
+
+        Ok(proposals)
+    }
+
+    /// 从已同步的邮件重建联系人（仅在联系人表为空时）
+    pub async fn rebuild_if_empty(&self) -> Result<(), AppError> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM contacts")
+            .fetch_one(&self.pool)
+            .await?;
+        if count > 0 {
+            return Ok(());
+        }
+
+        let rows: Vec<(Option<String>, String, Option<String>)> = sqlx::query_as(
+            "SELECT sender_name, sender_address, date FROM emails WHERE sender_address IS NOT NULL"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for (name, address, date) in &rows {
+            self.upsert(name.as_deref(), Some(address), date.as_deref().unwrap_or_default(), false)
+                .await?;
+        }
+
+        log::info!("Rebuilt contacts from {} emails", rows.len());
+        Ok(())
+    }
+}
+
+/// 别名地址 → 主联系人
+pub async fn canonical_addresses(pool: &SqlitePool) -> Result<HashMap<String, CanonicalContact>, AppError> {
+    let rows: Vec<(String, Option<String>, String)> = sqlx::query_as(
+        r#"
+        SELECT c.address, p.name, p.address
+        FROM contacts c
+        JOIN contacts p ON p.id = c.canonical_id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(alias, name, address)| (alias, CanonicalContact { name, address }))
+        .collect())
+}
+
+/// 去掉 "+标签"；Gmail 地址还去掉点号
+fn normalize_local_part(local: &str, domain: &str) -> String {
+    let local = local.split('+').next().unwrap_or(local);
+    if GMAIL_DOMAINS.contains(&domain) {
+        local.replace('.', "")
+    } else {
+        local.to_string()
+    }
+}
+
+/// 统一日期格式，保证字符串比较与时间顺序一致
+fn normalize_date(date: &str) -> String {
+    crate::utils::i18n::parse_timestamp(date)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| date.to_string())
+}
+
+/// 小写并去除常见拉丁字母的变音符号
+pub fn fold(value: &str) -> String {
+    let mut folded = String::with_capacity(value.len());
+    for c in value.to_lowercase().chars() {
+        let replacement = match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+            'æ' => "ae",
+            'ç' | 'ć' | 'č' => "c",
+            'ď' | 'đ' => "d",
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+            'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' => "i",
+            'ł' => "l",
+            'ñ' | 'ń' | 'ň' => "n",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+            'œ' => "oe",
+            'ř' => "r",
+            'ś' | 'š' | 'ş' => "s",
+            'ß' => "ss",
+            'ť' | 'ţ' => "t",
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+            'ý' | 'ÿ' => "y",
+            'ź' | 'ż' | 'ž' => "z",
+            _ => {
+                folded.push(c);
+                continue;
+            }
+        };
+        folded.push_str(replacement);
+    }
+    folded
+}
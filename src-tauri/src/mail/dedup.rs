@@ -0,0 +1,161 @@
+/// 跨账户重复邮件检测
+///
+/// 同一封邮件可能同时出现在多个账户（例如工作邮箱和共享邮箱）。
+/// 检测策略：
+/// 1. Message-ID 完全相同（不同账户）
+/// 2. Message-ID 不同，但内容指纹相同（归一化主题 + 日期 + 发件人 + 正文前 N 个字符）
+///
+/// 重复邮件通过 `duplicate_of` 列指向最早入库的规范邮件。
+use crate::error::AppError;
+use crate::project::classification_log::{ClassificationCandidate, ClassificationLog, ClassificationMethod};
+use crate::project::classifier::{normalize_subject, ClassifierConfig};
+use sqlx::SqlitePool;
+
+/// 指纹中参与计算的正文字符数
+const FINGERPRINT_BODY_CHARS: usize = 500;
+
+/// 计算邮件内容指纹
+pub fn content_fingerprint(
+    subject: &str,
+    date: &str,
+    from: &str,
+    body_text: Option<&str>,
+) -> String {
+    use sha2::{Digest, Sha256};
+
+    // 指纹需要稳定，不受分类器设置影响
+    let subject = normalize_subject(subject, None, &ClassifierConfig::default()).to_lowercase();
+    let from = extract_address(from).to_lowercase();
+    let body: String = body_text
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(FINGERPRINT_BODY_CHARS)
+        .collect();
+
+    let mut hasher = Sha256::new();
+    hasher.update(subject.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(date.trim().as_bytes());
+    hasher.update([0u8]);
+    hasher.update(from.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(body.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// 从 "Name <email>" 中提取邮箱地址，不含尖括号时原样返回
+fn extract_address(sender: &str) -> &str {
+    match (sender.find('<'), sender.rfind('>')) {
+        (Some(start), Some(end)) if start < end => sender[start + 1..end].trim(),
+        _ => sender.trim(),
+    }
+}
+
+/// 重复邮件检测器
+pub struct DuplicateDetector {
+    pool: SqlitePool,
+}
+
+impl DuplicateDetector {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 检测邮件是否为已有邮件的重复
+    ///
+    /// 如果是重复，写入 `duplicate_of` 并继承规范邮件的项目，返回规范邮件 ID
+    pub async fn detect(&self, email_id: i64) -> Result<Option<i64>, AppError> {
+        #[derive(sqlx::FromRow)]
+        struct EmailRow {
+            message_id: String,
+            account_id: Option<i64>,
+            content_fingerprint: Option<String>,
+        }
+
+        let email = sqlx::query_as::<_, EmailRow>(
+            "SELECT message_id, account_id, content_fingerprint FROM emails WHERE id = ?"
+        )
+        .bind(email_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(AppError::EmailNotFound { id: email_id })?;
+
+        // 1. Message-ID 完全相同（其他账户）
+        let canonical: Option<(i64, Option<i64>)> = sqlx::query_as(
+            r#"
+            SELECT id, project_id
+            FROM emails
+            WHERE message_id = ?
+              AND id != ?
+              AND account_id IS NOT ?
+              AND duplicate_of IS NULL
+            ORDER BY id ASC
+            LIMIT 1
+            "#
+        )
+        .bind(&email.message_id)
+        .bind(email_id)
+        .bind(email.account_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        // 2. 内容指纹相同
+        let canonical = match (canonical, &email.content_fingerprint) {
+            (Some(found), _) => Some(found),
+            (None, Some(fingerprint)) => {
+                sqlx::query_as(
+                    r#"
+                    SELECT id, project_id
+                    FROM emails
+                    WHERE content_fingerprint = ?
+                      AND id != ?
+                      AND duplicate_of IS NULL
+                    ORDER BY id ASC
+                    LIMIT 1
+                    "#
+                )
+                .bind(fingerprint)
+                .bind(email_id)
+                .fetch_optional(&self.pool)
+                .await?
+            }
+            (None, None) => None,
+        };
+
+        let Some((canonical_id, project_id)) = canonical else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            "UPDATE emails SET duplicate_of = ?, project_id = COALESCE(?, project_id) WHERE id = ?"
+        )
+        .bind(canonical_id)
+        .bind(project_id)
+        .bind(email_id)
+        .execute(&self.pool)
+        .await?;
+
+        if let Some(project_id) = project_id {
+            sqlx::query("UPDATE attachments SET project_id = ? WHERE email_id = ?")
+                .bind(project_id)
+                .bind(email_id)
+                .execute(&self.pool)
+                .await?;
+            let candidate = ClassificationCandidate {
+                method: ClassificationMethod::Duplicate,
+                project_id,
+                matched_value: Some(canonical_id.to_string()),
+                confidence: 1.0,
+            };
+            if let Err(e) = ClassificationLog::new(self.pool.clone()).record(email_id, &candidate, &[]).await {
+                log::warn!("Failed to record classification for email {}: {}", email_id, e);
+            }
+        }
+
+        log::info!("Email {} marked as duplicate of {}", email_id, canonical_id);
+        Ok(Some(canonical_id))
+    }
+}
@@ -0,0 +1,387 @@
+/// 镜像服务器上的删除
+///
+/// 开启 `mirror_deletions` 后，每次增量同步用 `UID SEARCH UID <最小本地 UID>:*` 取得收件箱同步窗口内
+/// 服务器现有的 UID，本地有而服务器没有的邮件标记为墓碑（`server_deleted_at`）：收件箱、时间线和智能视图
+/// 中隐藏，不计入项目统计，可以在列表中查看和恢复，超过保留期后永久删除。
+///
+/// UIDVALIDITY 变化时服务器上的 UID 全部重新编号，本地 UID 无法与服务器比对，这次同步跳过比对（否则会
+/// 把整个收件箱标记为已删除），只记录新的 UIDVALIDITY。Gmail 中归档的邮件也会离开收件箱，标记前先在
+/// All Mail 中按 X-GM-MSGID 确认邮件确实已删除。
+use crate::error::AppError;
+use crate::mail::folders::{resolve_special_folder, SpecialUse};
+use crate::mail::imap_client::ImapConnection;
+use crate::repository::ProjectRepository;
+use crate::storage::body_store;
+use crate::storage::quarantine::{AttachmentQuarantine, RemovedAttachment, REMOVED_ATTACHMENT_COLUMNS};
+use crate::storage::remote_content::RemoteContentCache;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashSet;
+
+/// 删除邮件前需要处理的引用（外键约束开启，引用邮件的行必须先删除或解除关联）
+///
+/// `{}` 替换为选出邮件 ID 的 SQL（单个参数）。会议邀请和里程碑只解除关联，保留本身。
+const EMAIL_REFERENCE_STATEMENTS: [&str; 8] = [
+    "DELETE FROM classification_log WHERE email_id IN ({})",
+    "DELETE FROM classification_corrections WHERE email_id IN ({})",
+    "DELETE FROM email_translations WHERE email_id IN ({})",
+    "DELETE FROM attachments WHERE email_id IN ({})",
+    "DELETE FROM email_folders WHERE email_id IN ({})",
+    "DELETE FROM remote_content_refs WHERE email_id IN ({})",
+    "UPDATE calendar_events SET email_id = NULL WHERE email_id IN ({})",
+    "UPDATE milestones SET email_id = NULL WHERE email_id IN ({})",
+];
+
+/// 墓碑邮件
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ServerDeletedEmail {
+    pub id: i64,
+    pub subject: Option<String>,
+    pub sender: Option<String>,
+    pub date: Option<String>,
+    pub project_id: Option<i64>,
+    pub project_name: Option<String>,
+    pub server_deleted_at: String,
+}
+
+/// 服务器删除的镜像和墓碑管理
+pub struct ServerDeletions {
+    pool: SqlitePool,
+}
+
+impl ServerDeletions {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 是否开启了镜像删除
+    pub async fn enabled(&self) -> bool {
+        sqlx::query_scalar::<_, Option<bool>>("SELECT mirror_deletions FROM sync_settings WHERE id = 1")
+            .fetch_one(&self.pool)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or(false)
+    }
+
+    /// 比对收件箱（须已选中）与本地邮件，标记服务器上已删除的邮件，返回新标记的邮件数
+    pub async fn mirror_inbox(&self, conn: &mut ImapConnection, account_id: i64) -> Result<u64, AppError> {
+        let Some(uid_validity) = conn.uid_validity() else {
+            log::info!("Server did not report UIDVALIDITY for account {}, skipping deletion check", account_id);
+            return Ok(0);
+        };
+        let known: Option<i64> = sqlx::query_scalar("SELECT inbox_uid_validity FROM accounts WHERE id = ?")
+            .bind(account_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .flatten();
+        if known != Some(uid_validity as i64) {
+            if known.is_some() {
+                log::warn!(
+                    "INBOX UIDVALIDITY of account {} changed ({:?} -> {}), skipping deletion check for this sync",
+                    account_id, known, uid_validity
+                );
+            }
+            sqlx::query("UPDATE accounts SET inbox_uid_validity = ? WHERE id = ?")
+                .bind(uid_validity as i64)
+                .bind(account_id)
+                .execute(&self.pool)
+                .await?;
+            return Ok(0);
+        }
+
+        let local: Vec<(i64, String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, raw_path, gm_msgid FROM emails
+            WHERE account_id = ? AND server_deleted_at IS NULL
+              AND raw_path GLOB '[0-9]*' AND raw_path NOT GLOB '*[^0-9]*'
+            "#
+        )
+        .bind(account_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let local: Vec<(i64, u32, Option<String>)> = local
+            .into_iter()
+            .filter_map(|(id, raw_path, gm_msgid)| raw_path.parse().ok().map(|uid| (id, uid, gm_msgid)))
+            .collect();
+        let Some(window_start) = local.iter().map(|(_, uid, _)| *uid).min() else {
+            return Ok(0);
+        };
+
+        let on_server: HashSet<u32> = conn
+            .uid_search(&format!("UID {}:*", window_start), None)
+            .await?
+            .into_iter()
+            .collect();
+        let mut missing: Vec<(i64, Option<String>)> = local
+            .into_iter()
+            .filter(|(_, uid, _)| !on_server.contains(uid))
+            .map(|(id, _, gm_msgid)| (id, gm_msgid))
+            .collect();
+        if missing.is_empty() {
+            return Ok(0);
+        }
+
+        if conn.supports_gmail_labels() {
+            missing = self.exclude_archived(conn, missing).await?;
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for (id, _) in &missing {
+            sqlx::query("UPDATE emails SET server_deleted_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM email_folders WHERE email_id = ? AND folder = 'INBOX'")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        if !missing.is_empty() {
+            ProjectRepository::new(self.pool.clone()).recompute_stats(None).await?;
+            log::info!("Marked {} emails deleted on the server for account {}", missing.len(), account_id);
+        }
+        Ok(missing.len() as u64)
+    }
+
+    /// Gmail：去掉仍在 All Mail 中的邮件（只是被归档，没有删除）；没有 X-GM-MSGID 的邮件无法确认，不标记
+    async fn exclude_archived(
+        &self,
+        conn: &mut ImapConnection,
+        missing: Vec<(i64, Option<String>)>,
+    ) -> Result<Vec<(i64, Option<String>)>, AppError> {
+        let folders = conn.list_folders().await?;
+        let Some(all_mail) = resolve_special_folder(&folders, SpecialUse::All, None) else {
+            log::info!("Gmail All Mail folder not found, skipping deletion check");
+            return Ok(Vec::new());
+        };
+        conn.select_folder(&all_mail).await?;
+
+        let mut deleted = Vec::new();
+        for (id, gm_msgid) in missing {
+            let Some(msgid) = gm_msgid.as_deref().filter(|msgid| msgid.chars().all(|c| c.is_ascii_digit())) else {
+                continue;
+            };
+            if conn.uid_search(&format!("X-GM-MSGID {}", msgid), None).await?.is_empty() {
+                deleted.push((id, gm_msgid));
+            }
+        }
+        conn.select_folder("INBOX").await?;
+        Ok(deleted)
+    }
+
+    /// 账户的墓碑邮件（最近删除的在前）
+    pub async fn list(&self, account_id: i64) -> Result<Vec<ServerDeletedEmail>, AppError> {
+        let emails = sqlx::query_as::<_, ServerDeletedEmail>(
+            r#"
+            SELECT e.id, e.subject, e.sender, e.date, e.project_id, p.name AS project_name, e.server_deleted_at
+            FROM emails e
+            LEFT JOIN projects p ON p.id = e.project_id
+            WHERE e.account_id = ? AND e.server_deleted_at IS NOT NULL
+            ORDER BY e.server_deleted_at DESC, e.id DESC
+            "#
+        )
+        .bind(account_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(emails)
+    }
+
+    /// 恢复墓碑邮件（重新显示并计入统计），返回恢复的邮件数
+    pub async fn restore(&self, email_ids: &[i64]) -> Result<u64, AppError> {
+        let mut restored = 0;
+        let mut tx = self.pool.begin().await?;
+        for id in email_ids {
+            restored += sqlx::query("UPDATE emails SET server_deleted_at = NULL WHERE id = ? AND server_deleted_at IS NOT NULL")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+        tx.commit().await?;
+
+        if restored > 0 {
+            ProjectRepository::new(self.pool.clone()).recompute_stats(None).await?;
+        }
+        Ok(restored)
+    }
+
+    /// 永久删除超过保留期的墓碑邮件（附件文件移入隔离区），返回删除的邮件数
+    pub async fn purge(&self, retention_days: i64) -> Result<u64, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let expired: Vec<(i64, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, body_path FROM emails
+            WHERE server_deleted_at IS NOT NULL
+              AND datetime(server_deleted_at) <= datetime('now', '-' || ? || ' days')
+            "#
+        )
+        .bind(retention_days.max(0))
+        .fetch_all(&mut *tx)
+        .await?;
+        if expired.is_empty() {
+            return Ok(0);
+        }
+
+        let mut removed_attachments: Vec<RemovedAttachment> = Vec::new();
+        for (id, _) in &expired {
+            let files = sqlx::query_as::<_, RemovedAttachment>(&format!(
+                "SELECT {} FROM attachments WHERE email_id = ?",
+                REMOVED_ATTACHMENT_COLUMNS
+            ))
+            .bind(id)
+            .fetch_all(&mut *tx)
+            .await?;
+            removed_attachments.extend(files);
+
+            // 以这封邮件为规范的重复邮件：提升 ID 最小的一封为新的规范邮件
+            sqlx::query(
+                r#"
+                UPDATE emails
+                SET duplicate_of = (SELECT MIN(d.id) FROM emails d WHERE d.duplicate_of = ?1)
+                WHERE duplicate_of = ?1
+                "#
+            )
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("UPDATE emails SET duplicate_of = NULL WHERE duplicate_of = id")
+                .execute(&mut *tx)
+                .await?;
+
+            delete_email_rows(&mut tx, "?", *id).await?;
+        }
+        tx.commit().await?;
+
+        AttachmentQuarantine::new(self.pool.clone()).quarantine_files(&removed_attachments).await;
+        if let Err(e) = RemoteContentCache::new(self.pool.clone()).remove_unreferenced().await {
+            log::warn!("Failed to remove cached remote images of purged emails: {}", e);
+        }
+        for path in expired.iter().filter_map(|(_, path)| path.as_deref()) {
+            if let Ok(path) = body_store::resolve_body_path(path) {
+                if let Err(e) = tokio::fs::remove_file(&path).await {
+                    if e.kind() != std::io::ErrorKind::NotFound {
+                        log::warn!("Failed to remove body file {:?}: {}", path, e);
+                    }
+                }
+            }
+        }
+
+        log::info!("Purged {} emails deleted on the server more than {} days ago", expired.len(), retention_days);
+        Ok(expired.len() as u64)
+    }
+}
+
+/// 删除邮件行及引用它们的行，返回删除的邮件数
+///
+/// `emails` 是选出邮件 ID 的 SQL（如 `?` 或 `SELECT id FROM emails WHERE account_id = ?`），
+/// `param` 绑定到其中的参数。
+/// 附件文件、正文文件和缓存图片由调用方在事务提交后清理。
+pub(crate) async fn delete_email_rows(
+    conn: &mut SqliteConnection,
+    emails: &str,
+    param: i64,
+) -> Result<u64, AppError> {
+    for statement in EMAIL_REFERENCE_STATEMENTS {
+        sqlx::query(&statement.replace("{}", emails))
+            .bind(param)
+            .execute(&mut *conn)
+            .await?;
+    }
+    let deleted = sqlx::query(&format!("DELETE FROM emails WHERE id IN ({})", emails))
+        .bind(param)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::fixtures::{insert_account, insert_email, NewEmail};
+    use crate::storage::database::test_pool;
+    use crate::storage::remote_content::cache_test_image;
+
+    /// 插入一封 `days_ago` 天前被服务器删除的墓碑邮件
+    async fn tombstone(pool: &SqlitePool, account_id: i64, message_id: &str, days_ago: i64) -> i64 {
+        let email = NewEmail { account_id: Some(account_id), message_id, ..Default::default() };
+        let email_id = insert_email(pool, email).await;
+        sqlx::query("UPDATE emails SET server_deleted_at = datetime('now', '-' || ? || ' days') WHERE id = ?")
+            .bind(days_ago)
+            .bind(email_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        email_id
+    }
+
+    #[tokio::test]
+    async fn purge_releases_cached_images_of_expired_emails() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let expired = tombstone(&pool, account_id, "<old@example.com>", 60).await;
+        let recent = tombstone(&pool, account_id, "<new@example.com>", 1).await;
+        let expired_image = cache_test_image(&pool, expired, "purgeexpired.png").await;
+        let recent_image = cache_test_image(&pool, recent, "purgerecent.png").await;
+
+        let purged = ServerDeletions::new(pool.clone()).purge(30).await.unwrap();
+        assert_eq!(purged, 1);
+
+        assert!(!expired_image.exists());
+        assert!(recent_image.exists());
+        let refs: Vec<i64> = sqlx::query_scalar("SELECT email_id FROM remote_content_refs")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(refs, vec![recent]);
+    }
+
+    #[tokio::test]
+    async fn purge_clears_every_row_that_references_the_email() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let expired = tombstone(&pool, account_id, "<old@example.com>", 60).await;
+        for sql in [
+            "INSERT INTO classification_log (email_id, method) VALUES (?, 'subject')",
+            "INSERT INTO classification_corrections (email_id, method) VALUES (?, 'subject')",
+            "INSERT INTO email_translations (email_id, target_lang, provider, body) VALUES (?, 'EN', 'local', 'Hi')",
+            "INSERT INTO attachments (email_id, filename) VALUES (?, 'plan.pdf')",
+            "INSERT INTO email_folders (email_id, folder) VALUES (?, 'Archive')",
+            "INSERT INTO remote_content_refs (email_id, url, file_name) VALUES (?, 'https://example.com/a.png', 'a.png')",
+            "INSERT INTO calendar_events (account_id, email_id, uid, raw_ics) SELECT account_id, id, 'kickoff', '' FROM emails WHERE id = ?",
+            "INSERT INTO milestones (email_id, type, title) VALUES (?, 'note', 'Kickoff')",
+        ] {
+            sqlx::query(sql).bind(expired).execute(&pool).await.unwrap();
+        }
+
+        let purged = ServerDeletions::new(pool.clone()).purge(30).await.unwrap();
+        assert_eq!(purged, 1);
+
+        for table in [
+            "classification_log",
+            "classification_corrections",
+            "email_translations",
+            "attachments",
+            "email_folders",
+            "remote_content_refs",
+            "emails",
+        ] {
+            let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(count, 0, "{} still has rows", table);
+        }
+        // 会议邀请和里程碑保留，只解除与邮件的关联
+        for table in ["calendar_events", "milestones"] {
+            let email_ids: Vec<Option<i64>> = sqlx::query_scalar(&format!("SELECT email_id FROM {}", table))
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+            assert_eq!(email_ids, vec![None], "{}", table);
+        }
+    }
+}
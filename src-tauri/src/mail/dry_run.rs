@@ -0,0 +1,285 @@
+/// 同步演练（dry run）
+///
+/// 按正常同步的规则连接、选择收件箱并挑选 UID，下载和解析最多 N 封邮件，但不写数据库：
+/// 每封邮件返回解析警告、分类器会选择的项目和附件信息，用于在生产邮箱上排查同步问题而不改动数据。
+/// 可同时记录 IMAP 协议跟踪（凭据已脱敏）。
+use crate::error::AppError;
+use crate::events::EventEmitter;
+use crate::mail::automated::{AutomatedDetector, AutomatedHeaders};
+use crate::mail::imap_client::{AuthMethod, ImapConnection};
+use crate::mail::imap_trace::ImapTrace;
+use crate::mail::parser::{generate_thread_id, parse_email, ParsedEmail};
+use crate::mail::providers::ProviderConfig;
+use crate::mail::sync::{pending_uids, EmailSyncer};
+use crate::mail::session_metrics::SessionMetrics;
+use crate::mail::throttle::TransferCounter;
+use crate::project::classifier::{ClassificationPreview, ProjectClassifier};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 默认演练的邮件数
+pub const DEFAULT_DRY_RUN_LIMIT: usize = 10;
+
+/// 单次演练的邮件数上限
+const MAX_DRY_RUN_LIMIT: usize = 200;
+
+/// 附件信息（不保存内容）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub size: usize,
+}
+
+/// 单封邮件的演练结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunMessage {
+    pub uid: u32,
+    pub message_id: Option<String>,
+    pub subject: Option<String>,
+    pub from: Option<String>,
+    pub date: Option<String>,
+    pub size_bytes: usize,
+    /// 数据库中已有相同 Message-ID 的邮件（正常同步时会覆盖）
+    pub already_synced: bool,
+    /// 解析时发现的问题（缺少头部、没有正文等）
+    pub warnings: Vec<String>,
+    /// 分类器会采用的项目
+    pub classification: Option<ClassificationPreview>,
+    pub attachments: Vec<DryRunAttachment>,
+    /// 下载或解析失败的原因
+    pub error: Option<String>,
+}
+
+/// 演练报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunReport {
+    pub account_id: i64,
+    /// 收件箱中的邮件数
+    pub mailbox_total: usize,
+    pub last_synced_uid: u32,
+    /// 正常同步会处理的邮件数（演练只处理其中前 N 封）
+    pub pending_count: usize,
+    pub messages: Vec<DryRunMessage>,
+    pub bytes_transferred: u64,
+    /// 协议跟踪文件
+    pub trace_path: Option<String>,
+}
+
+/// 同步演练
+pub struct SyncDryRun {
+    pool: SqlitePool,
+}
+
+impl SyncDryRun {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 演练最多 `limit` 封邮件；`trace` 为 true 时记录协议跟踪
+    pub async fn run(
+        &self,
+        account_id: i64,
+        auth: AuthMethod,
+        provider: &ProviderConfig,
+        limit: usize,
+        trace: bool,
+    ) -> Result<DryRunReport, AppError> {
+        let limit = limit.clamp(1, MAX_DRY_RUN_LIMIT);
+        let trace = if trace {
+            let name = format!("dry-run-{}-{}", account_id, chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+            Some(ImapTrace::create(&name)?)
+        } else {
+            None
+        };
+        let trace_path = trace.as_ref().map(|trace| trace.path().to_string_lossy().into_owned());
+        log::info!("Starting dry-run sync for account {} (limit {})", account_id, limit);
+
+        let counter = TransferCounter::default();
+        let mut conn = ImapConnection::connect_with_provider_traced(
+            provider,
+            auth,
+            None,
+            counter.clone(),
+            trace,
+            SessionMetrics::default(),
+        )
+        .await?;
+        let mailbox_total = conn.select_folder("INBOX").await? as usize;
+
+        // 与正常同步相同的 UID 选择规则
+        let syncer = EmailSyncer::new(self.pool.clone(), EventEmitter::noop());
+        let last_synced_uid = syncer.get_last_synced_uid(account_id).await?;
+        let max_sync_count = syncer.get_max_sync_count().await.unwrap_or(100);
+        let uids = pending_uids(&mut conn, mailbox_total, last_synced_uid, max_sync_count).await?;
+        let pending_count = uids.len();
+
+        let classifier = ProjectClassifier::load(self.pool.clone()).await;
+        let selected = &uids[..pending_count.min(limit)];
+        let mut gmail = conn.fetch_gmail_metadata(selected).await.unwrap_or_else(|e| {
+            log::warn!("Dry run: failed to fetch Gmail labels: {}", e);
+            Default::default()
+        });
+
+        let mut messages = Vec::with_capacity(selected.len());
+        for uid in selected {
+            let message = match conn.fetch_email(*uid).await {
+                Ok(raw) => self.inspect(account_id, *uid, &raw, gmail.remove(uid).map(|gmail| gmail.labels), &classifier).await,
+                Err(e) => DryRunMessage::failed(*uid, 0, format!("Failed to download: {}", e)),
+            };
+            messages.push(message);
+        }
+
+        if let Err(e) = conn.logout().await {
+            log::warn!("Dry run: logout failed: {}", e);
+        }
+        log::info!("Dry-run sync for account {} inspected {} of {} pending messages", account_id, messages.len(), pending_count);
+
+        Ok(DryRunReport {
+            account_id,
+            mailbox_total,
+            last_synced_uid,
+            pending_count,
+            messages,
+            bytes_transferred: counter.total(),
+            trace_path,
+        })
+    }
+
+    /// 解析单封邮件并预览分类（只读）
+    async fn inspect(
+        &self,
+        account_id: i64,
+        uid: u32,
+        raw: &[u8],
+        gmail_labels: Option<Vec<String>>,
+        classifier: &ProjectClassifier,
+    ) -> DryRunMessage {
+        let parsed = match parse_email(raw) {
+            Ok(parsed) => parsed,
+            Err(e) => return DryRunMessage::failed(uid, raw.len(), format!("Failed to parse: {}", e)),
+        };
+
+        let mut warnings = parse_warnings(raw, &parsed);
+        let already_synced = match sqlx::query_scalar::<_, i64>(
+            "SELECT id FROM emails WHERE message_id = ? AND account_id = ? LIMIT 1"
+        )
+        .bind(&parsed.message_id)
+        .bind(account_id)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(existing) => existing.is_some(),
+            Err(e) => {
+                warnings.push(format!("Duplicate check failed: {}", e));
+                false
+            }
+        };
+
+        if let Some(receipt) = &parsed.receipt {
+            warnings.push(format!(
+                "{} receipt for {}; would be stored as an annotation, not an email",
+                receipt.kind.as_str(), receipt.original_message_id
+            ));
+        }
+
+        let thread_id = generate_thread_id(&parsed);
+        let is_automated = AutomatedDetector::new(self.pool.clone())
+            .detect(
+                &parsed.from,
+                &parsed.subject,
+                Some(thread_id.as_str()),
+                &parsed.message_id,
+                &AutomatedHeaders {
+                    auto_submitted: parsed.auto_submitted.as_deref(),
+                    precedence: parsed.precedence.as_deref(),
+                    is_report: parsed.unparsed_report,
+                },
+            )
+            .await
+            .unwrap_or(false);
+
+        let classification = match classifier.preview(account_id, &parsed, is_automated, gmail_labels.as_deref()).await {
+            Ok(preview) => Some(preview),
+            Err(e) => {
+                warnings.push(format!("Classifier preview failed: {}", e));
+                None
+            }
+        };
+
+        DryRunMessage {
+            uid,
+            message_id: Some(parsed.message_id.clone()),
+            subject: Some(parsed.subject.clone()),
+            from: Some(parsed.from.clone()),
+            date: Some(parsed.date.clone()),
+            size_bytes: raw.len(),
+            already_synced,
+            warnings,
+            classification,
+            attachments: parsed
+                .attachments
+                .iter()
+                .map(|attachment| DryRunAttachment {
+                    filename: attachment.filename.clone(),
+                    content_type: attachment.content_type.clone(),
+                    size: attachment.size,
+                })
+                .collect(),
+            error: None,
+        }
+    }
+}
+
+impl DryRunMessage {
+    fn failed(uid: u32, size_bytes: usize, error: String) -> Self {
+        Self {
+            uid,
+            message_id: None,
+            subject: None,
+            from: None,
+            date: None,
+            size_bytes,
+            already_synced: false,
+            warnings: Vec::new(),
+            classification: None,
+            attachments: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+/// 解析器用默认值填补的字段（缺少头部时解析不会失败，但结果可能不符合预期）
+fn parse_warnings(raw: &[u8], parsed: &ParsedEmail) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if parsed.message_id.starts_with("generated-") {
+        warnings.push("Missing Message-ID header; a generated ID would be used".to_string());
+    }
+    if parsed.subject == "(No Subject)" {
+        warnings.push("Missing Subject header".to_string());
+    }
+    if parsed.from == "Unknown" {
+        warnings.push("Missing or unparseable From header".to_string());
+    }
+    if !has_header(raw, "date") {
+        warnings.push("Missing Date header; sync time would be used".to_string());
+    }
+    if parsed.body_text.is_none() && parsed.body_html.is_none() {
+        warnings.push("No text or HTML body".to_string());
+    }
+    for attachment in parsed.attachments.iter().filter(|attachment| attachment.size == 0) {
+        warnings.push(format!("Attachment {:?} is empty", attachment.filename));
+    }
+    warnings
+}
+
+/// 原始邮件头部是否包含指定字段（忽略大小写）
+fn has_header(raw: &[u8], name: &str) -> bool {
+    let text = String::from_utf8_lossy(raw);
+    let headers = text.split("\r\n\r\n").next().unwrap_or_default();
+    let headers = headers.split("\n\n").next().unwrap_or_default();
+    headers.lines().any(|line| {
+        line.split_once(':')
+            .is_some_and(|(field, _)| !field.starts_with([' ', '\t']) && field.trim().eq_ignore_ascii_case(name))
+    })
+}
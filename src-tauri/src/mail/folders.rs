@@ -0,0 +1,352 @@
+/// 邮件所在文件夹
+///
+/// Gmail 中同一封邮件同时出现在 INBOX、All Mail 和每个标签文件夹中，Message-ID 相同而 UID 各不相同。
+/// 按文件夹同步时如果每次都 `INSERT OR REPLACE`，邮件行会被反复删除重建，丢失按文件夹记录的状态。
+/// Gmail 服务器上用跨文件夹不变的 X-GM-MSGID 作为去重键：已保存过的邮件只在 `email_folders` 中
+/// 增加一条文件夹记录，不再重复下载。All Mail 包含所有邮件，默认不同步。非 Gmail 服务器仍按 Message-ID 保存。
+///
+/// 特殊文件夹（已发送、废纸篓、垃圾邮件等）的名称随服务商和界面语言变化（"Gesendete Elemente"、"已发送邮件"），
+/// 所有需要特殊文件夹的功能都通过 `resolve_special_folder` 查找：优先使用 LIST 返回的 SPECIAL-USE 标记（RFC 6154），
+/// 服务器完全没有返回这类标记时才按名称表匹配。
+use crate::error::AppError;
+use crate::mail::namespaces::NamespaceKind;
+use async_imap::types::{Name, NameAttribute};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Gmail "All Mail" 的常见名称（服务器未返回 `\All` 标记时使用）
+const GMAIL_ALL_MAIL_FOLDERS: &[&str] = &["[Gmail]/All Mail", "[Google Mail]/All Mail"];
+
+/// 特殊用途（RFC 6154）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecialUse {
+    All,
+    Archive,
+    Drafts,
+    Junk,
+    Sent,
+    Trash,
+}
+
+impl SpecialUse {
+    fn from_attribute(attribute: &NameAttribute<'_>) -> Option<Self> {
+        match attribute {
+            NameAttribute::All => Some(Self::All),
+            NameAttribute::Archive => Some(Self::Archive),
+            NameAttribute::Drafts => Some(Self::Drafts),
+            NameAttribute::Junk => Some(Self::Junk),
+            NameAttribute::Sent => Some(Self::Sent),
+            NameAttribute::Trash => Some(Self::Trash),
+            // 部分服务器仍返回 RFC 6154 之前的 XLIST 名称
+            NameAttribute::Extension(name) => match name.to_ascii_lowercase().as_str() {
+                "\\allmail" => Some(Self::All),
+                "\\spam" => Some(Self::Junk),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// 服务器不支持 SPECIAL-USE 时按名称匹配的候选（不区分大小写，也匹配层级路径的最后一段）
+    ///
+    /// 覆盖各服务商的英文名称和常见的本地化名称（Outlook.com 按账户语言命名，QQ / 163 使用中文名称）。
+    fn fallback_names(self) -> &'static [&'static str] {
+        match self {
+            Self::All => GMAIL_ALL_MAIL_FOLDERS,
+            Self::Archive => &["Archive", "Archives", "Archiv", "Archivo", "存档", "归档", "アーカイブ"],
+            Self::Drafts => &[
+                "Drafts", "Draft", "[Gmail]/Drafts", "Entwürfe", "Brouillons", "Borradores",
+                "草稿箱", "草稿", "下書き", "임시 보관함",
+            ],
+            Self::Junk => &[
+                "Junk", "Junk E-mail", "Junk Email", "Spam", "[Gmail]/Spam", "Bulk Mail", "Junk-E-Mail",
+                "Courrier indésirable", "Correo no deseado", "垃圾邮件", "垃圾箱", "迷惑メール", "스팸 메일함",
+            ],
+            Self::Sent => &[
+                "Sent", "Sent Items", "Sent Messages", "Sent Mail", "[Gmail]/Sent Mail", "INBOX.Sent", "INBOX/Sent",
+                "Gesendete Elemente", "Gesendet", "Éléments envoyés", "Elementos enviados",
+                "已发送邮件", "已发送", "已傳送郵件", "寄件備份", "送信済みアイテム", "送信済み", "보낸 편지함",
+            ],
+            Self::Trash => &[
+                "Trash", "Deleted Items", "Deleted Messages", "Deleted", "[Gmail]/Trash", "[Gmail]/Bin", "Bin",
+                "Gelöschte Elemente", "Papierkorb", "Éléments supprimés", "Elementos eliminados",
+                "已删除邮件", "已删除", "废纸篓", "刪除的郵件", "削除済みアイテム", "ゴミ箱", "지운 편지함",
+            ],
+        }
+    }
+}
+
+/// LIST 返回的邮箱文件夹
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailboxFolder {
+    pub name: String,
+    /// 层级分隔符（`/` 或 `.`）
+    pub delimiter: Option<String>,
+    /// 服务器标记的特殊用途
+    pub special_use: Option<SpecialUse>,
+    /// 可以 SELECT（没有 `\Noselect` / `\NonExistent` 标记）
+    pub selectable: bool,
+    /// 所属命名空间（共享邮箱等）
+    #[serde(default)]
+    pub namespace: NamespaceKind,
+}
+
+impl MailboxFolder {
+    pub fn from_name(name: &Name) -> Self {
+        let attributes = name.attributes();
+        Self {
+            name: name.name().to_string(),
+            delimiter: name.delimiter().map(str::to_string),
+            special_use: attributes.iter().find_map(SpecialUse::from_attribute),
+            selectable: !attributes.iter().any(|attribute| match attribute {
+                NameAttribute::NoSelect => true,
+                NameAttribute::Extension(name) => name.eq_ignore_ascii_case("\\NonExistent"),
+                _ => false,
+            }),
+            namespace: NamespaceKind::Personal,
+        }
+    }
+
+    /// 层级路径的最后一段（"INBOX/Gesendete Elemente" → "Gesendete Elemente"）
+    fn leaf_name(&self) -> &str {
+        match self.delimiter.as_deref().filter(|delimiter| !delimiter.is_empty()) {
+            Some(delimiter) => self.name.rsplit(delimiter).next().unwrap_or(&self.name),
+            None => &self.name,
+        }
+    }
+
+    fn matches_name(&self, candidate: &str) -> bool {
+        self.name.eq_ignore_ascii_case(candidate) || self.leaf_name().to_lowercase() == candidate.to_lowercase()
+    }
+}
+
+/// 查找特殊用途文件夹
+///
+/// 服务器返回了任何 SPECIAL-USE 标记时只信任标记，不再按名称猜测（避免把用户自建的 "Sent" 文件夹当成已发送）；
+/// 完全没有标记时依次尝试服务商配置的名称和 `SpecialUse::fallback_names`。
+pub fn resolve_special_folder(
+    folders: &[MailboxFolder],
+    special_use: SpecialUse,
+    configured: Option<&str>,
+) -> Option<String> {
+    // 共享邮箱中的 "Sent" 等文件夹不是自己的特殊文件夹
+    let personal = || folders.iter().filter(|folder| folder.namespace == NamespaceKind::Personal);
+    let selectable = || personal().filter(|folder| folder.selectable);
+
+    if personal().any(|folder| folder.special_use.is_some()) {
+        return selectable()
+            .find(|folder| folder.special_use == Some(special_use))
+            .map(|folder| folder.name.clone());
+    }
+
+    if let Some(configured) = configured {
+        if let Some(folder) = selectable().find(|folder| folder.name == configured) {
+            return Some(folder.name.clone());
+        }
+    }
+
+    special_use
+        .fallback_names()
+        .iter()
+        .find_map(|candidate| selectable().find(|folder| folder.matches_name(candidate)))
+        .map(|folder| folder.name.clone())
+}
+
+/// 邮件所在的文件夹
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct EmailFolder {
+    pub folder: String,
+    /// personal / other / shared
+    pub namespace: String,
+    pub uid: Option<i64>,
+    pub added_at: Option<String>,
+}
+
+/// 文件夹是否参与同步（Gmail 的 All Mail 默认跳过）
+///
+/// `all_mail` 为服务器以 `\All` 标记的文件夹。
+pub fn should_sync_folder(folder: &str, gmail: bool, all_mail: Option<&str>) -> bool {
+    if !gmail {
+        return true;
+    }
+    let is_all_mail = all_mail.is_some_and(|all| all == folder)
+        || GMAIL_ALL_MAIL_FOLDERS.iter().any(|name| name.eq_ignore_ascii_case(folder));
+    !is_all_mail
+}
+
+/// 邮件文件夹记录
+pub struct FolderStore {
+    pool: SqlitePool,
+}
+
+impl FolderStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 按 X-GM-MSGID 查找已保存的邮件
+    pub async fn find_by_gm_msgid(&self, account_id: i64, msgid: &str) -> Result<Option<i64>, AppError> {
+        let email_id = sqlx::query_scalar("SELECT id FROM emails WHERE account_id = ? AND gm_msgid = ?")
+            .bind(account_id)
+            .bind(msgid)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(email_id)
+    }
+
+    /// 账户在文件夹中已保存的最大 UID（共享邮箱按文件夹增量同步）
+    pub async fn last_uid(&self, account_id: i64, folder: &str) -> Result<u32, AppError> {
+        let uid: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT MAX(f.uid) FROM email_folders f
+            JOIN emails e ON e.id = f.email_id
+            WHERE e.account_id = ? AND f.folder = ?
+            "#
+        )
+        .bind(account_id)
+        .bind(folder)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(uid.unwrap_or(0).clamp(0, u32::MAX as i64) as u32)
+    }
+
+    /// 记录邮件所在的文件夹，Gmail 邮件同时保存 X-GM-MSGID
+    pub async fn link(
+        &self,
+        email_id: i64,
+        folder: &str,
+        namespace: NamespaceKind,
+        uid: u32,
+        gm_msgid: Option<&str>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO email_folders (email_id, folder, namespace, uid) VALUES (?, ?, ?, ?)
+            ON CONFLICT(email_id, folder) DO UPDATE SET uid = excluded.uid, namespace = excluded.namespace
+            "#
+        )
+        .bind(email_id)
+        .bind(folder)
+        .bind(namespace.as_str())
+        .bind(uid as i64)
+        .execute(&self.pool)
+        .await?;
+
+        if let Some(msgid) = gm_msgid {
+            sqlx::query("UPDATE emails SET gm_msgid = ? WHERE id = ? AND gm_msgid IS NOT ?")
+                .bind(msgid)
+                .bind(email_id)
+                .bind(msgid)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// 邮件所在的所有文件夹
+    pub async fn folders_for(&self, email_id: i64) -> Result<Vec<EmailFolder>, AppError> {
+        let folders = sqlx::query_as::<_, EmailFolder>(
+            "SELECT folder, COALESCE(namespace, 'personal') AS namespace, uid, added_at FROM email_folders WHERE email_id = ? ORDER BY folder"
+        )
+        .bind(email_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(folders)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(name: &str, special_use: Option<SpecialUse>) -> MailboxFolder {
+        MailboxFolder {
+            name: name.to_string(),
+            delimiter: Some("/".to_string()),
+            special_use,
+            selectable: true,
+            namespace: NamespaceKind::Personal,
+        }
+    }
+
+    #[test]
+    fn special_use_flags_win_over_names() {
+        let folders = vec![
+            folder("INBOX", None),
+            folder("Sent", None),
+            folder("Sent Items", Some(SpecialUse::Sent)),
+            folder("Trash", Some(SpecialUse::Trash)),
+        ];
+        assert_eq!(resolve_special_folder(&folders, SpecialUse::Sent, Some("Sent")).as_deref(), Some("Sent Items"));
+        // 服务器返回了标记但没有 \Junk，不按名称猜测
+        let with_spam = [folders.clone(), vec![folder("Spam", None)]].concat();
+        assert_eq!(resolve_special_folder(&with_spam, SpecialUse::Junk, None), None);
+    }
+
+    #[test]
+    fn without_flags_configured_name_then_localized_names() {
+        let mut folders = vec![
+            folder("INBOX", None),
+            folder("INBOX/Gesendete Elemente", None),
+            folder("Archiv", None),
+            folder("已删除邮件", None),
+        ];
+        assert_eq!(
+            resolve_special_folder(&folders, SpecialUse::Sent, None).as_deref(),
+            Some("INBOX/Gesendete Elemente")
+        );
+        assert_eq!(resolve_special_folder(&folders, SpecialUse::Archive, None).as_deref(), Some("Archiv"));
+        assert_eq!(resolve_special_folder(&folders, SpecialUse::Trash, None).as_deref(), Some("已删除邮件"));
+        assert_eq!(resolve_special_folder(&folders, SpecialUse::Drafts, None), None);
+
+        folders.push(folder("Outbox Copies", None));
+        assert_eq!(
+            resolve_special_folder(&folders, SpecialUse::Sent, Some("Outbox Copies")).as_deref(),
+            Some("Outbox Copies")
+        );
+        // 配置的文件夹不存在时回到名称表
+        assert_eq!(
+            resolve_special_folder(&folders, SpecialUse::Sent, Some("Missing")).as_deref(),
+            Some("INBOX/Gesendete Elemente")
+        );
+    }
+
+    #[test]
+    fn unselectable_and_shared_folders_are_ignored() {
+        let mut noselect = folder("[Gmail]/Sent Mail", Some(SpecialUse::Sent));
+        noselect.selectable = false;
+        let mut shared = folder("Shared/team/Sent", None);
+        shared.namespace = NamespaceKind::Shared;
+        assert_eq!(resolve_special_folder(&[noselect], SpecialUse::Sent, None), None);
+        assert_eq!(resolve_special_folder(&[shared.clone()], SpecialUse::Sent, None), None);
+
+        // 共享文件夹上的标记不影响个人文件夹的名称匹配
+        shared.special_use = Some(SpecialUse::Sent);
+        let folders = vec![shared, folder("Sent", None)];
+        assert_eq!(resolve_special_folder(&folders, SpecialUse::Sent, None).as_deref(), Some("Sent"));
+    }
+
+    #[test]
+    fn xlist_attributes_map_to_special_use() {
+        let attribute = |name: &'static str| NameAttribute::Extension(name.into());
+        assert_eq!(SpecialUse::from_attribute(&attribute("\\AllMail")), Some(SpecialUse::All));
+        assert_eq!(SpecialUse::from_attribute(&attribute("\\Spam")), Some(SpecialUse::Junk));
+        assert_eq!(SpecialUse::from_attribute(&attribute("\\Important")), None);
+        assert_eq!(SpecialUse::from_attribute(&NameAttribute::Sent), Some(SpecialUse::Sent));
+    }
+
+    #[test]
+    fn gmail_skips_all_mail_by_flag_or_name() {
+        assert!(!should_sync_folder("Archive/Everything", true, Some("Archive/Everything")));
+        assert!(!should_sync_folder("[Gmail]/All Mail", true, None));
+        assert!(!should_sync_folder("[google mail]/all mail", true, None));
+        assert!(should_sync_folder("[Gmail]/Sent Mail", true, Some("[Gmail]/All Mail")));
+        // 其他服务商的同名文件夹照常同步
+        assert!(should_sync_folder("[Gmail]/All Mail", false, None));
+    }
+}
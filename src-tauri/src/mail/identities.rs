@@ -0,0 +1,432 @@
+/// 账户身份（别名 / send-as 地址）
+///
+/// 一个账户可以用多个地址发信（例如 Gmail 的 send-as 个人域名）。身份保存在 `account_identities`，
+/// 参与"自己的地址"判断（方向识别、回复收件人排除自己），撰写时按身份 ID 选择 From 头和 SMTP 配置。
+/// 同一地址只能属于一个账户。Gmail OAuth 账户可以通过 Gmail API 的 sendAs 列表自动发现身份。
+use crate::commands::sync::resolve_account_auth;
+use crate::error::AppError;
+use crate::mail::imap_client::AuthMethod;
+use crate::mail::providers::{detect_provider, SmtpConfig};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+/// Gmail sendAs 列表接口
+const GMAIL_SEND_AS_URL: &str = "https://gmail.googleapis.com/gmail/v1/users/me/settings/sendAs";
+
+/// Gmail API 请求超时
+const GMAIL_API_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 账户身份
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Identity {
+    pub id: i64,
+    pub account_id: i64,
+    /// 小写邮箱地址
+    pub email: String,
+    pub display_name: Option<String>,
+    pub is_default: bool,
+    /// 使用与账户不同的 SMTP 服务器时的配置
+    pub smtp_override: Option<SmtpConfig>,
+    /// manual / gmail
+    pub source: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct IdentityRow {
+    id: i64,
+    account_id: i64,
+    email: String,
+    display_name: Option<String>,
+    is_default: bool,
+    smtp_override: Option<String>,
+    source: String,
+}
+
+impl From<IdentityRow> for Identity {
+    fn from(row: IdentityRow) -> Self {
+        Self {
+            id: row.id,
+            account_id: row.account_id,
+            email: row.email,
+            display_name: row.display_name,
+            is_default: row.is_default,
+            smtp_override: row.smtp_override.and_then(|json| serde_json::from_str(&json).ok()),
+            source: row.source,
+        }
+    }
+}
+
+/// 添加或更新身份的请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityRequest {
+    pub email: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub is_default: bool,
+    #[serde(default)]
+    pub smtp_override: Option<SmtpConfig>,
+}
+
+/// 撰写 / 发送时使用的发件身份
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutgoingSender {
+    /// 使用账户主地址时为 None
+    pub identity_id: Option<i64>,
+    pub email: String,
+    pub display_name: Option<String>,
+    /// "Name <addr>"
+    pub from_header: String,
+    pub smtp: SmtpConfig,
+}
+
+const IDENTITY_COLUMNS: &str =
+    "id, account_id, email, display_name, COALESCE(is_default, 0) AS is_default, smtp_override, source";
+
+/// 身份存储
+pub struct IdentityStore {
+    pool: SqlitePool,
+}
+
+impl IdentityStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 获取账户的身份（默认身份在前）
+    pub async fn list(&self, account_id: i64) -> Result<Vec<Identity>, AppError> {
+        let rows = sqlx::query_as::<_, IdentityRow>(&format!(
+            "SELECT {} FROM account_identities WHERE account_id = ? ORDER BY is_default DESC, email",
+            IDENTITY_COLUMNS
+        ))
+        .bind(account_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Identity::from).collect())
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Identity, AppError> {
+        sqlx::query_as::<_, IdentityRow>(&format!("SELECT {} FROM account_identities WHERE id = ?", IDENTITY_COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(Identity::from)
+            .ok_or_else(|| AppError::Validation(format!("Identity {} not found", id)))
+    }
+
+    /// 添加身份，返回新身份
+    pub async fn add(&self, account_id: i64, request: &IdentityRequest) -> Result<Identity, AppError> {
+        let email = self.validate(account_id, None, request).await?;
+        let smtp_override = request.smtp_override.as_ref().map(serde_json::to_string).transpose()?;
+
+        let mut tx = self.pool.begin().await?;
+        if request.is_default {
+            sqlx::query("UPDATE account_identities SET is_default = 0 WHERE account_id = ?")
+                .bind(account_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        let id = sqlx::query(
+            r#"
+            INSERT INTO account_identities (account_id, email, display_name, is_default, smtp_override, source)
+            VALUES (?, ?, ?, ?, ?, 'manual')
+            "#
+        )
+        .bind(account_id)
+        .bind(&email)
+        .bind(display_name(request))
+        .bind(request.is_default)
+        .bind(&smtp_override)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+        tx.commit().await?;
+
+        log::info!("Added identity {} to account {}", email, account_id);
+        self.get(id).await
+    }
+
+    /// 更新身份
+    pub async fn update(&self, id: i64, request: &IdentityRequest) -> Result<Identity, AppError> {
+        let existing = self.get(id).await?;
+        let email = self.validate(existing.account_id, Some(id), request).await?;
+        let smtp_override = request.smtp_override.as_ref().map(serde_json::to_string).transpose()?;
+
+        let mut tx = self.pool.begin().await?;
+        if request.is_default {
+            sqlx::query("UPDATE account_identities SET is_default = 0 WHERE account_id = ? AND id != ?")
+                .bind(existing.account_id)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query(
+            r#"
+            UPDATE account_identities
+            SET email = ?, display_name = ?, is_default = ?, smtp_override = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&email)
+        .bind(display_name(request))
+        .bind(request.is_default)
+        .bind(&smtp_override)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        self.get(id).await
+    }
+
+    pub async fn delete(&self, id: i64) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM account_identities WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 选择发件身份：指定的身份、账户的默认身份，或账户主地址
+    ///
+    /// SMTP 配置优先使用身份的覆盖配置，否则使用账户服务商的配置。
+    pub async fn sender_for(&self, account_id: i64, identity_id: Option<i64>) -> Result<OutgoingSender, AppError> {
+        let account_email: String = sqlx::query_scalar("SELECT email FROM accounts WHERE id = ?")
+            .bind(account_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::Validation(format!("Account {} not found", account_id)))?;
+        let provider_smtp = detect_provider(&account_email)
+            .map(|provider| provider.smtp)
+            .ok_or_else(|| AppError::Config(format!("Unsupported email provider for: {}", account_email)))?;
+
+        let identity = match identity_id {
+            Some(id) => {
+                let identity = self.get(id).await?;
+                if identity.account_id != account_id {
+                    return Err(AppError::Validation(format!(
+                        "Identity {} does not belong to account {}",
+                        id, account_id
+                    )));
+                }
+                Some(identity)
+            }
+            None => self.list(account_id).await?.into_iter().find(|identity| identity.is_default),
+        };
+
+        Ok(match identity {
+            Some(identity) => OutgoingSender {
+                identity_id: Some(identity.id),
+                from_header: from_header(&identity.email, identity.display_name.as_deref()),
+                smtp: identity.smtp_override.unwrap_or(provider_smtp),
+                email: identity.email,
+                display_name: identity.display_name,
+            },
+            None => {
+                let display_name: Option<String> =
+                    sqlx::query_scalar("SELECT NULLIF(TRIM(display_name), '') FROM sync_settings WHERE id = 1")
+                        .fetch_optional(&self.pool)
+                        .await?
+                        .flatten();
+                let email = account_email.trim().to_lowercase();
+                OutgoingSender {
+                    identity_id: None,
+                    from_header: from_header(&email, display_name.as_deref()),
+                    smtp: provider_smtp,
+                    email,
+                    display_name,
+                }
+            }
+        })
+    }
+
+    /// 从 Gmail 的 sendAs 列表导入已验证的身份，返回导入（新增或更新）的数量
+    ///
+    /// 只支持 OAuth 登录的 Gmail 账户；Gmail 的 SMTP 中继配置（smtpMsa）作为覆盖配置保存。
+    pub async fn discover_gmail(&self, account_id: i64) -> Result<usize, AppError> {
+        let email: String = sqlx::query_scalar("SELECT email FROM accounts WHERE id = ?")
+            .bind(account_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::Validation(format!("Account {} not found", account_id)))?;
+        let (_, auth, provider) = resolve_account_auth(&self.pool, &email, None)
+            .await
+            .map_err(|e| AppError::Auth(e.message))?;
+        let access_token = match (provider.name.as_str(), auth) {
+            ("gmail", AuthMethod::OAuth { access_token, .. }) => access_token,
+            _ => {
+                return Err(AppError::Validation(
+                    "Send-as discovery requires a Gmail account signed in with OAuth".to_string(),
+                ))
+            }
+        };
+
+        let client = reqwest::Client::builder()
+            .timeout(GMAIL_API_TIMEOUT)
+            .build()
+            .map_err(|e| AppError::Network(e.to_string()))?;
+        let response = client
+            .get(GMAIL_SEND_AS_URL)
+            .bearer_auth(&access_token)
+            .send()
+            .await
+            .map_err(|e| AppError::Network(e.to_string()))?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| AppError::Network(e.to_string()))?;
+        if !status.is_success() {
+            return Err(AppError::Network(format!("Gmail sendAs request failed ({}): {}", status, body)));
+        }
+        let list: SendAsList = serde_json::from_str(&body)?;
+
+        let mut imported = 0;
+        for send_as in list.send_as {
+            let verified = send_as.is_primary || send_as.verification_status.as_deref() == Some("accepted");
+            let address = send_as.send_as_email.trim().to_lowercase();
+            if !verified || !address.contains('@') {
+                continue;
+            }
+            if let Some(owner) = self.claimed_by_other(account_id, None, &address).await? {
+                log::warn!("Skipping send-as address {}: already used by account {}", address, owner);
+                continue;
+            }
+
+            let smtp_override = send_as.smtp_msa.map(|msa| SmtpConfig {
+                use_tls: msa.security_mode.as_deref() == Some("ssl"),
+                use_starttls: msa.security_mode.as_deref() == Some("starttls"),
+                host: msa.host,
+                port: msa.port,
+            });
+            let display_name = send_as.display_name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty());
+
+            let mut tx = self.pool.begin().await?;
+            if send_as.is_default {
+                sqlx::query("UPDATE account_identities SET is_default = 0 WHERE account_id = ?")
+                    .bind(account_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            sqlx::query(
+                r#"
+                INSERT INTO account_identities (account_id, email, display_name, is_default, smtp_override, source)
+                VALUES (?, ?, ?, ?, ?, 'gmail')
+                ON CONFLICT(email) DO UPDATE SET
+                    display_name = COALESCE(excluded.display_name, display_name),
+                    is_default = excluded.is_default,
+                    smtp_override = excluded.smtp_override
+                "#
+            )
+            .bind(account_id)
+            .bind(&address)
+            .bind(&display_name)
+            .bind(send_as.is_default)
+            .bind(smtp_override.as_ref().map(serde_json::to_string).transpose()?)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            imported += 1;
+        }
+
+        log::info!("Imported {} send-as identities for account {}", imported, account_id);
+        Ok(imported)
+    }
+
+    /// 校验请求，返回规范化（小写）的地址
+    async fn validate(&self, account_id: i64, id: Option<i64>, request: &IdentityRequest) -> Result<String, AppError> {
+        let email = request.email.trim().to_lowercase();
+        if email.is_empty() || !email.contains('@') || email.contains(char::is_whitespace) {
+            return Err(AppError::Validation(format!("Invalid email address: {:?}", request.email)));
+        }
+        if let Some(smtp) = &request.smtp_override {
+            if smtp.host.trim().is_empty() || smtp.port == 0 {
+                return Err(AppError::Validation("SMTP override needs a host and port".to_string()));
+            }
+        }
+        if let Some(owner) = self.claimed_by_other(account_id, id, &email).await? {
+            return Err(AppError::Validation(format!("{} is already used by account {}", email, owner)));
+        }
+        let duplicate: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM account_identities WHERE email = ? AND account_id = ? AND id IS NOT ?"
+        )
+        .bind(&email)
+        .bind(account_id)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        if duplicate.is_some() {
+            return Err(AppError::Validation(format!("{} is already an identity of this account", email)));
+        }
+
+        Ok(email)
+    }
+
+    /// 地址已属于其他账户（主地址或身份）时返回该账户的地址
+    async fn claimed_by_other(&self, account_id: i64, id: Option<i64>, email: &str) -> Result<Option<String>, AppError> {
+        let owner: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT a.email FROM accounts a
+            WHERE a.id != ?
+              AND (LOWER(a.email) = ?
+                   OR EXISTS (SELECT 1 FROM account_identities i
+                              WHERE i.account_id = a.id AND i.email = ? AND i.id IS NOT ?))
+            LIMIT 1
+            "#
+        )
+        .bind(account_id)
+        .bind(email)
+        .bind(email)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(owner)
+    }
+}
+
+fn display_name(request: &IdentityRequest) -> Option<&str> {
+    request.display_name.as_deref().map(str::trim).filter(|name| !name.is_empty())
+}
+
+/// "Name <addr>"，名称含特殊字符时加引号
+fn from_header(email: &str, display_name: Option<&str>) -> String {
+    match display_name {
+        Some(name) if name.contains([',', '"', '<', '>', '@', ';', ':']) => {
+            format!("\"{}\" <{}>", name.replace('\\', "\\\\").replace('"', "\\\""), email)
+        }
+        Some(name) => format!("{} <{}>", name, email),
+        None => email.to_string(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendAsList {
+    #[serde(default)]
+    send_as: Vec<SendAs>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendAs {
+    send_as_email: String,
+    #[serde(default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    is_default: bool,
+    #[serde(default)]
+    is_primary: bool,
+    #[serde(default)]
+    verification_status: Option<String>,
+    #[serde(default)]
+    smtp_msa: Option<SmtpMsa>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SmtpMsa {
+    host: String,
+    port: u16,
+    #[serde(default)]
+    security_mode: Option<String>,
+}
@@ -1,20 +1,60 @@
 /// IMAP 客户端实现
 use async_imap::{Client as ImapClient, Session as ImapSession, Authenticator};
+use async_imap::imap_proto::SectionPath;
 use tokio::net::TcpStream;
 use tokio_native_tls::{TlsConnector, TlsStream};
 use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tokio::time::{timeout, Duration};
 use crate::error::AppError;
+use crate::mail::folders::MailboxFolder;
+use crate::mail::parser::decode_part;
+use crate::mail::namespaces::{parse_namespace_response, Namespace, NamespaceKind, Namespaces};
 use crate::mail::providers::{ImapConfig, ProviderConfig};
+use crate::mail::imap_trace::ImapTrace;
+use crate::mail::oauth_errors;
+use crate::mail::provider_policy;
+use crate::mail::session_metrics::SessionMetrics;
+use crate::mail::throttle::{ThrottledStream, TransferCounter};
+
+/// Gmail IMAP 扩展（X-GM-LABELS / X-GM-MSGID / X-GM-THRID）的能力标识
+const GMAIL_EXTENSION_CAPABILITY: &str = "X-GM-EXT-1";
+
+/// 配额扩展（RFC 2087，GETQUOTAROOT）的能力标识
+const QUOTA_CAPABILITY: &str = "QUOTA";
+
+/// 命名空间扩展（RFC 2342）的能力标识
+const NAMESPACE_CAPABILITY: &str = "NAMESPACE";
+
+/// 限速连接上发送 NOOP 保活的间隔（避免服务器在慢速下载期间断开空闲连接）
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 邮件信封（不下载正文）
+#[derive(Debug, Clone)]
+pub struct RemoteEnvelope {
+    pub uid: u32,
+    pub message_id: Option<String>,
+    pub subject: Option<String>,
+    pub from: Option<String>,
+    pub from_name: Option<String>,
+    /// 发件人地址（小写）
+    pub from_address: Option<String>,
+    pub date: Option<String>,
+    pub size: Option<u32>,
+}
 
 /// XOAUTH2 认证器
+///
+/// 服务器拒绝时的继续响应（Gmail 返回 JSON 说明原因）保存在 `challenges` 中，供认证失败后解析。
 struct XOAuth2Authenticator {
     auth_string: String,
     first_call: bool,
+    challenges: Arc<Mutex<Vec<Vec<u8>>>>,
 }
 
 impl XOAuth2Authenticator {
-    fn new(username: &str, access_token: &str) -> Self {
+    fn new(username: &str, access_token: &str, challenges: Arc<Mutex<Vec<Vec<u8>>>>) -> Self {
         // 构建 XOAUTH2 认证字符串
         // 格式: user=<username>\x01auth=Bearer <access_token>\x01\x01
         let auth_string = format!(
@@ -25,6 +65,7 @@ impl XOAuth2Authenticator {
         Self {
             auth_string,
             first_call: true,
+            challenges,
         }
     }
 }
@@ -32,7 +73,12 @@ impl XOAuth2Authenticator {
 impl Authenticator for XOAuth2Authenticator {
     type Response = String;
 
-    fn process(&mut self, _challenge: &[u8]) -> Self::Response {
+    fn process(&mut self, challenge: &[u8]) -> Self::Response {
+        if !challenge.is_empty() {
+            if let Ok(mut challenges) = self.challenges.lock() {
+                challenges.push(challenge.to_vec());
+            }
+        }
         if self.first_call {
             self.first_call = false;
             self.auth_string.clone()
@@ -54,7 +100,40 @@ pub enum AuthMethod {
 
 /// IMAP 连接会话
 pub struct ImapConnection {
-    session: ImapSession<TlsStream<TcpStream>>,
+    session: ImapSession<ThrottledStream<TlsStream<TcpStream>>>,
+    counter: TransferCounter,
+    metrics: SessionMetrics,
+    throttled: bool,
+    last_keepalive: std::time::Instant,
+    /// 服务器支持 Gmail 扩展（可获取 X-GM-LABELS）
+    gmail_extension: bool,
+    /// 最近一次 SELECT 返回的 UIDNEXT
+    uid_next: Option<u32>,
+    /// 最近一次 SELECT 返回的 UIDVALIDITY
+    uid_validity: Option<u32>,
+    /// 当前选中的文件夹
+    selected_folder: Option<String>,
+    /// 服务器支持 QUOTA 扩展
+    quota_extension: bool,
+    /// 登录后 NAMESPACE 返回的命名空间（服务器不支持时为 None）
+    namespaces: Option<Namespaces>,
+    /// 当前选中的文件夹所属的命名空间
+    selected_namespace: NamespaceKind,
+}
+
+/// 邮箱存储配额（KB，RFC 2087 的 STORAGE 资源）
+#[derive(Debug, Clone, Copy)]
+pub struct MailboxQuota {
+    pub used_kb: u64,
+    pub limit_kb: u64,
+}
+
+/// Gmail 扩展属性（X-GM-MSGID / X-GM-LABELS）
+#[derive(Debug, Clone, Default)]
+pub struct GmailMetadata {
+    /// 跨文件夹不变的邮件 ID（十进制字符串）
+    pub msgid: Option<String>,
+    pub labels: Vec<String>,
 }
 
 impl ImapConnection {
@@ -62,8 +141,31 @@ impl ImapConnection {
     pub async fn connect(
         config: &ImapConfig,
         auth: AuthMethod,
+    ) -> Result<Self, AppError> {
+        Self::connect_throttled(config, auth, None, TransferCounter::default()).await
+    }
+
+    /// 连接到 IMAP 服务器，下载速度限制为 `bytes_per_sec`（None 表示不限速），流量计入 `counter`
+    pub async fn connect_throttled(
+        config: &ImapConfig,
+        auth: AuthMethod,
+        bytes_per_sec: Option<u64>,
+        counter: TransferCounter,
+    ) -> Result<Self, AppError> {
+        Self::connect_traced(config, auth, bytes_per_sec, counter, None, SessionMetrics::default()).await
+    }
+
+    /// 同 `connect_throttled`，`trace` 不为 None 时记录协议跟踪（凭据已脱敏），会话指标计入 `metrics`
+    pub async fn connect_traced(
+        config: &ImapConfig,
+        auth: AuthMethod,
+        bytes_per_sec: Option<u64>,
+        counter: TransferCounter,
+        trace: Option<ImapTrace>,
+        metrics: SessionMetrics,
     ) -> Result<Self, AppError> {
         log::info!("Connecting to IMAP server: {}:{}", config.host, config.port);
+        metrics.record_connect();
 
         // 1. 建立 TCP 连接
         let addr = format!("{}:{}", config.host, config.port);
@@ -72,7 +174,7 @@ impl ImapConnection {
             .map_err(|e| AppError::Network(format!("Failed to connect to {}: {}", addr, e)))?;
 
         // 2. 建立 TLS 连接
-        let tls_connector = native_tls::TlsConnector::new()
+        let tls_connector = tls_connector()
             .map_err(|e| AppError::Network(format!("Failed to create TLS connector: {}", e)))?;
         let tls = TlsConnector::from(tls_connector);
         let tls_stream = tls
@@ -80,8 +182,12 @@ impl ImapConnection {
             .await
             .map_err(|e| AppError::Network(format!("TLS handshake failed: {}", e)))?;
 
-        // 3. 创建 IMAP 客户端
-        let mut client = ImapClient::new(tls_stream);
+        // 3. 创建 IMAP 客户端（外层包裹限速和流量统计）
+        let stream = ThrottledStream::new(tls_stream, bytes_per_sec, counter.clone())
+            .with_trace(trace)
+            .with_metrics(metrics.clone());
+        let throttled = stream.is_throttled();
+        let mut client = ImapClient::new(stream);
 
         // Read IMAP greeting (avoid silent hangs).
         match timeout(Duration::from_secs(5), client.read_response()).await {
@@ -98,14 +204,27 @@ impl ImapConnection {
                 client
                     .login(&username, &password)
                     .await
-                    .map_err(|e| AppError::Auth(format!("Login failed: {:?}", e)))?
+                    .map_err(|(err, _client)| {
+                        let response_text = match &err {
+                            async_imap::error::Error::No(text) | async_imap::error::Error::Bad(text) => text.clone(),
+                            _ => format!("{:?}", err),
+                        };
+                        match provider_policy::detect(&config.host, &response_text) {
+                            Some(rejection) => {
+                                log::warn!("Login rejected by provider policy: {}", rejection.provider_message);
+                                rejection.into()
+                            }
+                            None => AppError::Auth(format!("Login failed: {:?}", err)),
+                        }
+                    })?
             }
             AuthMethod::OAuth { username, access_token } => {
                 log::info!("Authenticating with OAuth for user: {}", username);
                 log::info!("Access token length: {}", access_token.len());
 
                 // 创建 XOAUTH2 认证器
-                let authenticator = XOAuth2Authenticator::new(&username, &access_token);
+                let challenges = Arc::new(Mutex::new(Vec::new()));
+                let authenticator = XOAuth2Authenticator::new(&username, &access_token, challenges.clone());
                 log::info!("XOAUTH2 authenticator created");
 
                 // 使用 XOAUTH2 SASL 机制
@@ -120,7 +239,13 @@ impl ImapConnection {
                 })?
                 .map_err(|(err, _client)| {
                     log::error!("OAuth authentication failed: {:?}", err);
-                    AppError::Auth(format!("OAuth authentication failed: {:?}", err))
+                    let response_text = match &err {
+                        async_imap::error::Error::No(text) | async_imap::error::Error::Bad(text) => Some(text.as_str()),
+                        _ => None,
+                    };
+                    let challenges = challenges.lock().map(|c| c.clone()).unwrap_or_default();
+                    oauth_errors::decode(&challenges, response_text)
+                        .into_app_error(&config.host, format!("{:?}", err))
                 })?;
 
                 log::info!("XOAUTH2 authentication completed");
@@ -129,6 +254,9 @@ impl ImapConnection {
         };
 
         // Read CAPABILITY after authentication.
+        let mut gmail_extension = false;
+        let mut quota_extension = false;
+        let mut namespace_extension = false;
         match timeout(Duration::from_secs(5), session.capabilities()).await {
             Ok(Ok(caps)) => {
                 log::info!("IMAP capabilities received (post-auth)");
@@ -136,13 +264,51 @@ impl ImapConnection {
                     "IMAP AUTH=XOAUTH2 supported: {}",
                     caps.has_str("AUTH=XOAUTH2")
                 );
+                gmail_extension = caps.has_str(GMAIL_EXTENSION_CAPABILITY);
+                quota_extension = caps.has_str(QUOTA_CAPABILITY);
+                namespace_extension = caps.has_str(NAMESPACE_CAPABILITY);
             }
             Ok(Err(e)) => log::warn!("Failed to read IMAP capabilities (post-auth): {:?}", e),
             Err(_) => log::warn!("Timed out waiting for IMAP capabilities (post-auth)"),
         }
 
         log::info!("Successfully connected and authenticated");
-        Ok(Self { session })
+        let mut connection = Self {
+            session,
+            counter,
+            metrics,
+            throttled,
+            last_keepalive: std::time::Instant::now(),
+            gmail_extension,
+            uid_next: None,
+            uid_validity: None,
+            selected_folder: None,
+            quota_extension,
+            namespaces: None,
+            selected_namespace: NamespaceKind::Personal,
+        };
+        if namespace_extension {
+            match connection.query_namespaces().await {
+                Ok(namespaces) => connection.namespaces = namespaces,
+                Err(e) => log::warn!("NAMESPACE failed: {}", e),
+            }
+        }
+        Ok(connection)
+    }
+
+    async fn query_namespaces(&mut self) -> Result<Option<Namespaces>, AppError> {
+        let response = self
+            .run_raw_command("NAMESPACE")
+            .await
+            .map_err(|e| AppError::Imap(format!("Failed to fetch namespaces: {:?}", e)))?;
+        let namespaces = parse_namespace_response(&String::from_utf8_lossy(&response));
+        log::info!("IMAP namespaces: {:?}", namespaces);
+        Ok(namespaces)
+    }
+
+    /// 登录后 NAMESPACE 返回的命名空间（服务器不支持时为 None）
+    pub fn namespaces(&self) -> Option<&Namespaces> {
+        self.namespaces.as_ref()
     }
 
     /// 从预定义配置连接
@@ -153,18 +319,108 @@ impl ImapConnection {
         Self::connect(&provider.imap, auth).await
     }
 
-    /// 列出所有邮箱文件夹
-    pub async fn list_folders(&mut self) -> Result<Vec<String>, AppError> {
+    /// 从预定义配置连接（限速）
+    pub async fn connect_with_provider_throttled(
+        provider: &ProviderConfig,
+        auth: AuthMethod,
+        bytes_per_sec: Option<u64>,
+        counter: TransferCounter,
+    ) -> Result<Self, AppError> {
+        Self::connect_throttled(&provider.imap, auth, bytes_per_sec, counter).await
+    }
+
+    /// 从预定义配置连接（限速，可选协议跟踪，统计会话指标）
+    pub async fn connect_with_provider_traced(
+        provider: &ProviderConfig,
+        auth: AuthMethod,
+        bytes_per_sec: Option<u64>,
+        counter: TransferCounter,
+        trace: Option<ImapTrace>,
+        metrics: SessionMetrics,
+    ) -> Result<Self, AppError> {
+        Self::connect_traced(&provider.imap, auth, bytes_per_sec, counter, trace, metrics).await
+    }
+
+    /// 本连接已传输的字节数（上传 + 下载）
+    pub fn bytes_transferred(&self) -> u64 {
+        self.counter.total()
+    }
+
+    /// 本连接的会话指标
+    pub fn metrics(&self) -> &SessionMetrics {
+        &self.metrics
+    }
+
+    /// 发送 NOOP（确认空闲连接仍然可用）
+    pub async fn noop(&mut self) -> Result<(), AppError> {
+        self.session
+            .noop()
+            .await
+            .map_err(|e| AppError::Imap(format!("NOOP failed: {:?}", e)))?;
+        self.last_keepalive = std::time::Instant::now();
+        Ok(())
+    }
+
+    /// 限速连接上定期发送 NOOP，防止服务器因下载过慢断开连接
+    pub async fn keepalive(&mut self) -> Result<(), AppError> {
+        if !self.throttled || self.last_keepalive.elapsed() < KEEPALIVE_INTERVAL {
+            return Ok(());
+        }
+
+        self.session
+            .noop()
+            .await
+            .map_err(|e| AppError::Imap(format!("NOOP failed: {:?}", e)))?;
+        self.last_keepalive = std::time::Instant::now();
+        Ok(())
+    }
+
+    /// 列出所有邮箱文件夹（带 SPECIAL-USE 标记）
+    pub async fn list_folders(&mut self) -> Result<Vec<MailboxFolder>, AppError> {
         let mut mailboxes = self
             .session
             .list(Some(""), Some("*"))
             .await
-            .map_err(|e| AppError::Generic(format!("Failed to list folders: {:?}", e)))?;
+            .map_err(|e| AppError::Imap(format!("Failed to list folders: {:?}", e)))?;
 
         let mut folders = Vec::new();
         while let Some(mailbox) = mailboxes.next().await {
             if let Ok(name) = mailbox {
-                folders.push(name.name().to_string());
+                let mut folder = MailboxFolder::from_name(&name);
+                // 部分服务器从根目录 LIST 时也返回共享命名空间下的文件夹
+                if let Some(namespaces) = &self.namespaces {
+                    folder.namespace = namespaces.kind_of(&folder.name);
+                }
+                folders.push(folder);
+            }
+        }
+
+        Ok(folders)
+    }
+
+    /// 列出其他用户和共享命名空间下的文件夹（按前缀分别 LIST，各命名空间使用自己的分隔符）
+    pub async fn list_shared_folders(&mut self) -> Result<Vec<MailboxFolder>, AppError> {
+        let roots: Vec<(NamespaceKind, Namespace)> = match &self.namespaces {
+            Some(namespaces) => namespaces.shared_roots().map(|(kind, namespace)| (kind, namespace.clone())).collect(),
+            None => return Ok(Vec::new()),
+        };
+
+        let mut folders: Vec<MailboxFolder> = Vec::new();
+        for (kind, namespace) in roots {
+            let pattern = format!("{}*", namespace.prefix);
+            let mut mailboxes = self
+                .session
+                .list(Some(""), Some(&pattern))
+                .await
+                .map_err(|e| AppError::Imap(format!("Failed to list folders under {}: {:?}", namespace.prefix, e)))?;
+
+            while let Some(mailbox) = mailboxes.next().await {
+                let Ok(name) = mailbox else { continue };
+                let mut folder = MailboxFolder::from_name(&name);
+                folder.namespace = kind;
+                if !folders.iter().any(|existing| existing.name == folder.name) {
+                    folders.push(folder);
+                }
             }
         }
 
@@ -181,10 +437,37 @@ impl ImapConnection {
             .map_err(|e| AppError::Generic(format!("Failed to select folder {}: {:?}", folder, e)))?;
 
         let exists = mailbox.exists;
-        log::info!("Folder {} has {} messages", folder, exists);
+        self.uid_next = mailbox.uid_next;
+        self.uid_validity = mailbox.uid_validity;
+        self.selected_folder = Some(folder.to_string());
+        self.selected_namespace = self
+            .namespaces
+            .as_ref()
+            .map_or(NamespaceKind::Personal, |namespaces| namespaces.kind_of(folder));
+        log::info!("Folder {} has {} messages (UIDNEXT {:?})", folder, exists, mailbox.uid_next);
         Ok(exists)
     }
 
+    /// 当前选中的文件夹
+    pub fn selected_folder(&self) -> Option<&str> {
+        self.selected_folder.as_deref()
+    }
+
+    /// 当前选中的文件夹所属的命名空间
+    pub fn selected_namespace(&self) -> NamespaceKind {
+        self.selected_namespace
+    }
+
+    /// 最近一次选择的文件夹的 UIDNEXT（服务器未返回时为 None）
+    pub fn uid_next(&self) -> Option<u32> {
+        self.uid_next
+    }
+
+    /// 最近一次选择的文件夹的 UIDVALIDITY（变化表示文件夹中的 UID 全部重新编号）
+    pub fn uid_validity(&self) -> Option<u32> {
+        self.uid_validity
+    }
+
     /// 获取邮件 UID 列表
     pub async fn fetch_uids(&mut self, range: &str) -> Result<Vec<u32>, AppError> {
         let mut messages = self
@@ -207,6 +490,7 @@ impl ImapConnection {
 
     /// 获取邮件内容
     pub async fn fetch_email(&mut self, uid: u32) -> Result<Vec<u8>, AppError> {
+        let started = std::time::Instant::now();
         let mut messages = self
             .session
             .uid_fetch(uid.to_string(), "RFC822")
@@ -216,6 +500,7 @@ impl ImapConnection {
         if let Some(msg) = messages.next().await {
             if let Ok(fetch) = msg {
                 if let Some(body) = fetch.body() {
+                    self.metrics.record_fetch(1, started.elapsed());
                     return Ok(body.to_vec());
                 }
             }
@@ -224,6 +509,219 @@ impl ImapConnection {
         Err(AppError::Generic(format!("Email {} not found", uid)))
     }
 
+    /// 只下载邮件的一个 MIME 部分并按传输编码解码
+    ///
+    /// `part` 为同步时记录的 IMAP 部分路径（如 "2.3"），`encoding` 为该部分的传输编码。
+    pub async fn fetch_body_part(&mut self, uid: u32, part: &str, encoding: &str) -> Result<Vec<u8>, AppError> {
+        let section = part
+            .split('.')
+            .map(|number| number.parse::<u32>().ok().filter(|number| *number > 0))
+            .collect::<Option<Vec<u32>>>()
+            .ok_or_else(|| AppError::Validation(format!("Invalid MIME part path: {}", part)))?;
+
+        let started = std::time::Instant::now();
+        let mut messages = self
+            .session
+            .uid_fetch(uid.to_string(), format!("BODY.PEEK[{}]", part))
+            .await
+            .map_err(|e| AppError::Imap(format!("Failed to fetch part {} of email {}: {:?}", part, uid, e)))?;
+
+        let path = SectionPath::Part(section, None);
+        while let Some(msg) = messages.next().await {
+            let Ok(fetch) = msg else { continue };
+            if let Some(data) = fetch.section(&path) {
+                self.metrics.record_fetch(1, started.elapsed());
+                return decode_part(data, encoding).map_err(AppError::Parse);
+            }
+        }
+
+        Err(AppError::Imap(format!("Part {} of email {} not found", part, uid)))
+    }
+
+    /// 批量获取邮件内容，返回 (UID, 原始数据)；服务器上已不存在的 UID 不会出现在结果中
+    pub async fn fetch_emails(&mut self, uids: &[u32]) -> Result<Vec<(u32, Vec<u8>)>, AppError> {
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let started = std::time::Instant::now();
+        let set = uids.iter().map(|uid| uid.to_string()).collect::<Vec<_>>().join(",");
+        let mut messages = self
+            .session
+            .uid_fetch(&set, "(UID RFC822)")
+            .await
+            .map_err(|e| AppError::Generic(format!("Failed to fetch emails {}: {:?}", set, e)))?;
+
+        let mut emails = Vec::new();
+        while let Some(msg) = messages.next().await {
+            let Ok(fetch) = msg else { continue };
+            if let (Some(uid), Some(body)) = (fetch.uid, fetch.body()) {
+                emails.push((uid, body.to_vec()));
+            }
+        }
+
+        self.metrics.record_fetch(emails.len(), started.elapsed());
+        Ok(emails)
+    }
+
+    /// UID SEARCH
+    ///
+    /// `charset` 为 None 时使用服务器默认的 US-ASCII；服务器拒绝指定字符集时返回明确的错误
+    pub async fn uid_search(&mut self, criteria: &str, charset: Option<&str>) -> Result<Vec<u32>, AppError> {
+        let query = match charset {
+            Some(charset) => format!("CHARSET {} {}", charset, criteria),
+            None => criteria.to_string(),
+        };
+        log::debug!("UID SEARCH {}", query);
+
+        let uids = self
+            .session
+            .uid_search(&query)
+            .await
+            .map_err(|e| match (&e, charset) {
+                (async_imap::error::Error::No(msg) | async_imap::error::Error::Bad(msg), Some(charset))
+                    if msg.to_uppercase().contains("CHARSET") =>
+                {
+                    AppError::Imap(format!(
+                        "Server does not support {} search; use ASCII-only search terms ({})",
+                        charset, msg
+                    ))
+                }
+                _ => AppError::Imap(format!("Search failed: {:?}", e)),
+            })?;
+
+        let mut uids: Vec<u32> = uids.into_iter().collect();
+        uids.sort_unstable();
+        Ok(uids)
+    }
+
+    /// 发送命令并返回到完成响应为止的原始响应（含完成行）
+    ///
+    /// async-imap 不解析的扩展（X-GM-*、NAMESPACE、QUOTA）通过这里读取原始文本再自行解析。
+    async fn run_raw_command(&mut self, command: &str) -> async_imap::error::Result<Vec<u8>> {
+        let id = self.session.run_command(command).await?;
+        let mut raw = Vec::new();
+        loop {
+            let response = self
+                .session
+                .read_response()
+                .await?
+                .ok_or(async_imap::error::Error::ConnectionLost)?;
+            raw.extend_from_slice(response.borrow_owner());
+            if let async_imap::imap_proto::Response::Done { tag, status, information, .. } = response.parsed() {
+                if *tag != id {
+                    continue;
+                }
+                let information = information.as_deref().unwrap_or_default().to_string();
+                return match status {
+                    async_imap::imap_proto::Status::Ok => Ok(raw),
+                    async_imap::imap_proto::Status::No => Err(async_imap::error::Error::No(information)),
+                    _ => Err(async_imap::error::Error::Bad(information)),
+                };
+            }
+        }
+    }
+
+    /// 服务器是否支持 Gmail 标签（X-GM-EXT-1）
+    pub fn supports_gmail_labels(&self) -> bool {
+        self.gmail_extension
+    }
+
+    /// 获取 Gmail 邮件 ID 和标签（UID → 属性），服务器不支持时返回空
+    ///
+    /// async-imap 不解析 X-GM-MSGID / X-GM-LABELS，这里直接发送命令并解析原始响应。
+    pub async fn fetch_gmail_metadata(&mut self, uids: &[u32]) -> Result<HashMap<u32, GmailMetadata>, AppError> {
+        if !self.gmail_extension || uids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let set = uids.iter().map(|uid| uid.to_string()).collect::<Vec<_>>().join(",");
+        let response = self
+            .run_raw_command(&format!("UID FETCH {} (UID X-GM-MSGID X-GM-LABELS)", set))
+            .await
+            .map_err(|e| AppError::Imap(format!("Failed to fetch Gmail metadata: {:?}", e)))?;
+
+        Ok(parse_gmail_metadata(&String::from_utf8_lossy(&response)))
+    }
+
+    /// 获取收件箱所在配额根的存储用量，服务器不支持 QUOTA 或没有存储限制时返回 None
+    ///
+    /// 与 Gmail 扩展一样直接发送命令并解析原始响应。
+    pub async fn fetch_quota(&mut self) -> Result<Option<MailboxQuota>, AppError> {
+        if !self.quota_extension {
+            return Ok(None);
+        }
+
+        let response = self
+            .run_raw_command("GETQUOTAROOT INBOX")
+            .await
+            .map_err(|e| AppError::Imap(format!("Failed to fetch quota: {:?}", e)))?;
+
+        Ok(parse_storage_quota(&String::from_utf8_lossy(&response)))
+    }
+
+    /// 获取邮件信封
+    pub async fn fetch_envelopes(&mut self, uids: &[u32]) -> Result<Vec<RemoteEnvelope>, AppError> {
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let started = std::time::Instant::now();
+        let set = uids.iter().map(|uid| uid.to_string()).collect::<Vec<_>>().join(",");
+        let mut messages = self
+            .session
+            .uid_fetch(&set, "(UID RFC822.SIZE ENVELOPE)")
+            .await
+            .map_err(|e| AppError::Imap(format!("Failed to fetch envelopes: {:?}", e)))?;
+
+        let mut envelopes = Vec::new();
+        while let Some(msg) = messages.next().await {
+            let Ok(fetch) = msg else { continue };
+            let Some(uid) = fetch.uid else { continue };
+            let envelope = fetch.envelope();
+
+            let sender = envelope
+                .and_then(|env| env.from.as_ref())
+                .and_then(|addrs| addrs.first())
+                .map(|addr| {
+                    let mailbox = addr.mailbox.as_deref().map(String::from_utf8_lossy).unwrap_or_default();
+                    let host = addr.host.as_deref().map(String::from_utf8_lossy).unwrap_or_default();
+                    let name = addr
+                        .name
+                        .as_deref()
+                        .map(decode_header_value)
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty());
+                    (name, format!("{}@{}", mailbox, host).to_lowercase())
+                });
+            let from = sender.as_ref().map(|(name, address)| match name {
+                Some(name) => format!("{} <{}>", name, address),
+                None => address.clone(),
+            });
+            let (from_name, from_address) = sender.unzip();
+
+            envelopes.push(RemoteEnvelope {
+                uid,
+                message_id: envelope
+                    .and_then(|env| env.message_id.as_deref())
+                    .map(|v| String::from_utf8_lossy(v).to_string()),
+                subject: envelope
+                    .and_then(|env| env.subject.as_deref())
+                    .map(decode_header_value),
+                from,
+                from_name: from_name.flatten(),
+                from_address,
+                date: envelope
+                    .and_then(|env| env.date.as_deref())
+                    .map(|v| String::from_utf8_lossy(v).to_string()),
+                size: fetch.size,
+            });
+        }
+
+        self.metrics.record_fetch(envelopes.len(), started.elapsed());
+        Ok(envelopes)
+    }
+
     /// 登出并关闭连接
     pub async fn logout(mut self) -> Result<(), AppError> {
         self.session
@@ -233,3 +731,151 @@ impl ImapConnection {
         Ok(())
     }
 }
+
+/// TLS 连接器
+#[cfg(not(test))]
+fn tls_connector() -> native_tls::Result<native_tls::TlsConnector> {
+    native_tls::TlsConnector::new()
+}
+
+/// 测试构建连接进程内的测试服务器，接受它的自签名证书
+#[cfg(test)]
+fn tls_connector() -> native_tls::Result<native_tls::TlsConnector> {
+    native_tls::TlsConnector::builder().danger_accept_invalid_certs(true).build()
+}
+
+/// 解码信封中的 RFC 2047 编码字段
+fn decode_header_value(raw: &[u8]) -> String {
+    let raw = String::from_utf8_lossy(raw);
+    let header = format!("Subject: {}\r\n\r\n", raw);
+    mail_parser::MessageParser::default()
+        .parse(header.as_bytes())
+        .and_then(|msg| msg.subject().map(|s| s.to_string()))
+        .unwrap_or_else(|| raw.to_string())
+}
+
+/// 解析 `* n FETCH (UID 12 X-GM-MSGID 1278455344230334865 X-GM-LABELS (\Inbox "Client A" Work))` 形式的响应行
+fn parse_gmail_metadata(response: &str) -> HashMap<u32, GmailMetadata> {
+    let mut metadata = HashMap::new();
+    for line in response.lines() {
+        let upper = line.to_ascii_uppercase();
+        let Some(uid) = upper.find("UID ").and_then(|pos| leading_digits(&line[pos + 4..])) else {
+            continue;
+        };
+        let msgid = upper
+            .find("X-GM-MSGID ")
+            .and_then(|pos| leading_digits(&line[pos + "X-GM-MSGID ".len()..]))
+            .map(str::to_string);
+        let labels = upper
+            .find("X-GM-LABELS (")
+            .map(|pos| parse_label_list(&line[pos + "X-GM-LABELS (".len()..]))
+            .unwrap_or_default();
+        let Ok(uid) = uid.parse::<u32>() else {
+            continue;
+        };
+        metadata.insert(uid, GmailMetadata { msgid, labels });
+    }
+    metadata
+}
+
+/// 解析 `* QUOTA "" (STORAGE 10240 15728640)` 形式的响应行（多个配额根时取使用率最高的）
+fn parse_storage_quota(response: &str) -> Option<MailboxQuota> {
+    response
+        .lines()
+        .filter(|line| line.to_ascii_uppercase().starts_with("* QUOTA "))
+        .filter_map(|line| {
+            // 资源列表是 (名称 用量 上限) 的三元组序列
+            let list = &line[line.rfind('(')? + 1..];
+            let tokens: Vec<&str> = list.trim_end().trim_end_matches(')').split_whitespace().collect();
+            tokens.chunks(3).find_map(|resource| match resource {
+                [name, used, limit] if name.eq_ignore_ascii_case("STORAGE") => {
+                    let used_kb = used.parse::<u64>().ok()?;
+                    let limit_kb = limit.parse::<u64>().ok()?;
+                    (limit_kb > 0).then_some(MailboxQuota { used_kb, limit_kb })
+                }
+                _ => None,
+            })
+        })
+        .max_by(|a, b| {
+            let ratio = |quota: &MailboxQuota| quota.used_kb as f64 / quota.limit_kb as f64;
+            ratio(a).total_cmp(&ratio(b))
+        })
+}
+
+/// 开头的连续数字（没有数字时返回 None）
+fn leading_digits(value: &str) -> Option<&str> {
+    let end = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    (end > 0).then(|| &value[..end])
+}
+
+/// 解析标签列表（原子或带引号的字符串，以右括号结束），并解码修改版 UTF-7
+fn parse_label_list(list: &str) -> Vec<String> {
+    let mut labels = Vec::new();
+    let mut chars = list.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            ')' => break,
+            ' ' => {
+                chars.next();
+            }
+            '"' => {
+                chars.next();
+                let mut label = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => label.extend(chars.next()),
+                        '"' => break,
+                        _ => label.push(c),
+                    }
+                }
+                labels.push(decode_modified_utf7(&label));
+            }
+            _ => {
+                let mut label = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == ' ' || c == ')' {
+                        break;
+                    }
+                    label.push(c);
+                    chars.next();
+                }
+                labels.push(decode_modified_utf7(&label));
+            }
+        }
+    }
+    labels
+}
+
+/// 解码 IMAP 修改版 UTF-7（RFC 3501 5.1.3），"&-" 表示 "&"
+fn decode_modified_utf7(value: &str) -> String {
+    use base64::Engine;
+
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start + 1..].find('-') else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let encoded = &rest[start + 1..start + 1 + len];
+        if encoded.is_empty() {
+            out.push('&');
+        } else {
+            let decoded = base64::engine::general_purpose::STANDARD_NO_PAD
+                .decode(encoded.replace(',', "/"))
+                .ok()
+                .map(|bytes| {
+                    let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+                    String::from_utf16_lossy(&units)
+                });
+            match decoded {
+                Some(text) => out.push_str(&text),
+                None => out.push_str(&rest[start..start + 2 + len]),
+            }
+        }
+        rest = &rest[start + 2 + len..];
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,218 @@
+/// IMAP 协议跟踪
+///
+/// 把连接上的原始命令（`C:`）和响应（`S:`）逐行写入跟踪文件，用于诊断与特殊服务器之间的协议问题。
+/// LOGIN 和 AUTHENTICATE 的凭据替换为 `<redacted>`；字面量（邮件正文等）只保留开头部分，超长的行被截断。
+/// 跟踪是尽力而为的：写文件失败不影响同步。
+use crate::error::AppError;
+use crate::storage::file_manager;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// 跟踪文件目录（位于应用数据目录下）
+const TRACE_DIR: &str = "traces";
+
+/// 每个字面量保留的字节数
+const LITERAL_PREVIEW_BYTES: usize = 256;
+
+/// 单行保留的最大字节数
+const MAX_LINE_BYTES: usize = 2048;
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Client,
+    Server,
+}
+
+/// 单个方向上尚未写出的行
+#[derive(Default)]
+struct LineBuffer {
+    line: Vec<u8>,
+    /// 行内超出上限被丢弃的字节数
+    dropped: usize,
+    /// 当前字面量剩余未读的字节数
+    literal_remaining: usize,
+    literal_total: usize,
+    literal_kept: usize,
+}
+
+impl LineBuffer {
+    fn push(&mut self, data: &[u8]) {
+        let room = MAX_LINE_BYTES.saturating_sub(self.line.len());
+        let kept = data.len().min(room);
+        self.line.extend_from_slice(&data[..kept]);
+        self.dropped += data.len() - kept;
+    }
+
+    /// 行尾的 `{N}` / `{N+}` 字面量长度
+    fn pending_literal(&self) -> Option<usize> {
+        let line = String::from_utf8_lossy(&self.line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        let inner = line.strip_suffix('}')?;
+        let start = inner.rfind('{')?;
+        inner[start + 1..].trim_end_matches('+').parse().ok()
+    }
+
+    fn take(&mut self) -> String {
+        let mut text = String::from_utf8_lossy(&self.line).trim_end_matches(['\r', '\n']).to_string();
+        if self.dropped > 0 {
+            text.push_str(&format!(" ...<{} bytes truncated>", self.dropped));
+        }
+        self.line.clear();
+        self.dropped = 0;
+        text
+    }
+}
+
+struct TraceState {
+    writer: BufWriter<File>,
+    started: Instant,
+    client: LineBuffer,
+    server: LineBuffer,
+    /// AUTHENTICATE 进行中：客户端发送的行都是凭据
+    in_authenticate: bool,
+}
+
+impl TraceState {
+    fn feed(&mut self, direction: Direction, mut data: &[u8]) {
+        while !data.is_empty() {
+            let buffer = match direction {
+                Direction::Client => &mut self.client,
+                Direction::Server => &mut self.server,
+            };
+
+            if buffer.literal_remaining > 0 {
+                let consumed = data.len().min(buffer.literal_remaining);
+                let keep = consumed.min(LITERAL_PREVIEW_BYTES.saturating_sub(buffer.literal_kept));
+                buffer.line.extend_from_slice(&data[..keep]);
+                buffer.literal_kept += keep;
+                buffer.literal_remaining -= consumed;
+                if buffer.literal_remaining == 0 && buffer.literal_total > buffer.literal_kept {
+                    let note = format!(" ...<{} literal bytes truncated>", buffer.literal_total - buffer.literal_kept);
+                    buffer.line.extend_from_slice(note.as_bytes());
+                }
+                data = &data[consumed..];
+                continue;
+            }
+
+            match data.iter().position(|b| *b == b'\n') {
+                Some(end) => {
+                    buffer.push(&data[..=end]);
+                    data = &data[end + 1..];
+                    if let Some(length) = buffer.pending_literal() {
+                        // 字面量内容接在同一条记录中
+                        buffer.line.extend_from_slice(b" ");
+                        buffer.literal_remaining = length;
+                        buffer.literal_total = length;
+                        buffer.literal_kept = 0;
+                        continue;
+                    }
+                    let line = buffer.take();
+                    self.write_line(direction, line);
+                }
+                None => {
+                    buffer.push(data);
+                    data = &[];
+                }
+            }
+        }
+    }
+
+    fn write_line(&mut self, direction: Direction, line: String) {
+        let (prefix, line) = match direction {
+            Direction::Client => ("C", self.redact_client(line)),
+            Direction::Server => {
+                // 带标签的响应（非 `*` / `+`）结束进行中的 AUTHENTICATE
+                if !line.starts_with('*') && !line.starts_with('+') {
+                    self.in_authenticate = false;
+                }
+                ("S", line)
+            }
+        };
+        let elapsed = self.started.elapsed().as_millis();
+        if let Err(e) = writeln!(self.writer, "{:>8} {}: {}", elapsed, prefix, line).and_then(|_| self.writer.flush()) {
+            log::debug!("Failed to write IMAP trace: {}", e);
+        }
+    }
+
+    fn redact_client(&mut self, line: String) -> String {
+        if self.in_authenticate {
+            return REDACTED.to_string();
+        }
+
+        let mut parts = line.splitn(3, ' ');
+        let (tag, command) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+        if command.eq_ignore_ascii_case("LOGIN") {
+            format!("{} LOGIN {}", tag, REDACTED)
+        } else if command.eq_ignore_ascii_case("AUTHENTICATE") {
+            self.in_authenticate = true;
+            let mechanism = parts.next().unwrap_or_default().split(' ').next().unwrap_or_default();
+            format!("{} AUTHENTICATE {} {}", tag, mechanism, REDACTED)
+        } else {
+            line
+        }
+    }
+}
+
+/// 跟踪记录器（可克隆，挂在连接的读写流上）
+#[derive(Clone)]
+pub struct ImapTrace {
+    state: Arc<Mutex<TraceState>>,
+    path: PathBuf,
+}
+
+impl ImapTrace {
+    /// 在应用数据目录下创建跟踪文件（`traces/<name>.log`）
+    pub fn create(name: &str) -> Result<Self, AppError> {
+        let dir = file_manager::app_data_dir()?.join(TRACE_DIR);
+        std::fs::create_dir_all(&dir)?;
+        Self::create_at(&dir.join(format!("{}.log", name)))
+    }
+
+    pub fn create_at(path: &Path) -> Result<Self, AppError> {
+        let file = File::create(path)?;
+        Ok(Self {
+            state: Arc::new(Mutex::new(TraceState {
+                writer: BufWriter::new(file),
+                started: Instant::now(),
+                client: LineBuffer::default(),
+                server: LineBuffer::default(),
+                in_authenticate: false,
+            })),
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 记录客户端发送的字节
+    pub fn record_sent(&self, data: &[u8]) {
+        self.record(Direction::Client, data);
+    }
+
+    /// 记录服务器返回的字节
+    pub fn record_received(&self, data: &[u8]) {
+        self.record(Direction::Server, data);
+    }
+
+    fn record(&self, direction: Direction, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        if let Ok(mut state) = self.state.lock() {
+            state.feed(direction, data);
+        }
+    }
+}
+
+impl std::fmt::Debug for ImapTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImapTrace").field("path", &self.path).finish()
+    }
+}
@@ -0,0 +1,127 @@
+/// 邮件语言识别
+///
+/// 保存邮件时用 whatlang 识别主题和正文的语言，写入 `emails.lang`（ISO 639-1，如 `zh`、`en`；
+/// 无法可靠识别时为 `und`）。语言决定全文索引的分词路径（中日韩文本进入 trigram 索引）
+/// 和主题规范化使用的前缀列表。
+use crate::error::AppError;
+use sqlx::SqlitePool;
+use whatlang::Lang;
+
+/// 无法识别的语言
+pub const UNDETERMINED: &str = "und";
+
+/// 参与识别的最大字符数（主题 + 正文开头）
+const SAMPLE_CHARS: usize = 2000;
+
+/// 回填时每批处理的邮件数
+const BACKFILL_BATCH_SIZE: i64 = 500;
+
+/// 识别邮件语言
+pub fn detect_language(subject: Option<&str>, body: Option<&str>) -> String {
+    let sample: String = [subject.unwrap_or_default(), body.unwrap_or_default()]
+        .join("\n")
+        .chars()
+        .take(SAMPLE_CHARS)
+        .collect();
+    if sample.trim().is_empty() {
+        return UNDETERMINED.to_string();
+    }
+
+    match whatlang::detect(&sample) {
+        Some(info) if info.is_reliable() => language_code(info.lang()).to_string(),
+        // 短文本识别不可靠，但汉字占多数时可以确定是中文
+        _ if cjk_ratio(&sample) >= 0.3 => "zh".to_string(),
+        _ => UNDETERMINED.to_string(),
+    }
+}
+
+/// 是否为需要 trigram 分词的中日韩语言
+pub fn is_cjk(lang: Option<&str>) -> bool {
+    matches!(lang, Some("zh" | "ja" | "ko"))
+}
+
+/// 文本是否包含中日韩字符（用于选择搜索路径）
+pub fn contains_cjk(text: &str) -> bool {
+    text.chars().any(is_cjk_char)
+}
+
+/// 为尚未识别语言的已有邮件补充 `lang`，返回处理的邮件数
+pub async fn backfill_languages(pool: &SqlitePool) -> Result<u64, AppError> {
+    let mut processed = 0u64;
+    loop {
+        let batch: Vec<(i64, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT id, subject, body_text FROM emails WHERE lang IS NULL ORDER BY id LIMIT ?"
+        )
+        .bind(BACKFILL_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+        if batch.is_empty() {
+            break;
+        }
+
+        let mut tx = pool.begin().await?;
+        for (id, subject, body) in &batch {
+            sqlx::query("UPDATE emails SET lang = ? WHERE id = ?")
+                .bind(detect_language(subject.as_deref(), body.as_deref()))
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        processed += batch.len() as u64;
+    }
+
+    if processed > 0 {
+        log::info!("Detected language for {} existing emails", processed);
+    }
+    Ok(processed)
+}
+
+/// whatlang 的 ISO 639-3 代码转换为常用的 ISO 639-1 代码
+fn language_code(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Cmn => "zh",
+        Lang::Eng => "en",
+        Lang::Jpn => "ja",
+        Lang::Kor => "ko",
+        Lang::Deu => "de",
+        Lang::Fra => "fr",
+        Lang::Spa => "es",
+        Lang::Por => "pt",
+        Lang::Ita => "it",
+        Lang::Nld => "nl",
+        Lang::Rus => "ru",
+        Lang::Ukr => "uk",
+        Lang::Pol => "pl",
+        Lang::Swe => "sv",
+        Lang::Dan => "da",
+        Lang::Fin => "fi",
+        Lang::Tur => "tr",
+        Lang::Ara => "ar",
+        Lang::Heb => "he",
+        Lang::Hin => "hi",
+        Lang::Vie => "vi",
+        Lang::Tha => "th",
+        Lang::Ind => "id",
+        other => other.code(),
+    }
+}
+
+fn cjk_ratio(text: &str) -> f64 {
+    let letters = text.chars().filter(|c| c.is_alphabetic()).count();
+    if letters == 0 {
+        return 0.0;
+    }
+    text.chars().filter(|c| is_cjk_char(*c)).count() as f64 / letters as f64
+}
+
+fn is_cjk_char(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x4E00..=0x9FFF       // 中日韩统一表意文字
+            | 0x3400..=0x4DBF // 扩展 A
+            | 0x3040..=0x30FF // 平假名、片假名
+            | 0xAC00..=0xD7AF // 韩文音节
+            | 0xF900..=0xFAFF // 兼容表意文字
+    )
+}
@@ -1,6 +1,38 @@
 pub mod providers;
 pub mod imap_client;
+pub mod connection_pool;
+pub mod imap_trace;
 pub mod parser;
+pub mod receipts;
+pub mod calendar;
 pub mod thread;
 pub mod sync;
+pub mod attachment_writer;
 pub mod oauth;
+pub mod oauth_errors;
+pub mod pending_oauth;
+pub mod provider_policy;
+pub mod dedup;
+pub mod deletions;
+pub mod backfill;
+pub mod contacts;
+pub mod vip;
+pub mod remote_search;
+pub mod sync_runs;
+pub mod sync_checkpoint;
+pub mod dry_run;
+pub mod folders;
+pub mod namespaces;
+pub mod smart_views;
+pub mod quota;
+pub mod throttle;
+pub mod session_metrics;
+pub mod automated;
+pub mod recipients;
+pub mod identities;
+pub mod outgoing;
+pub mod watch_folder;
+pub mod templates;
+pub mod language;
+#[cfg(test)]
+pub(crate) mod test_server;
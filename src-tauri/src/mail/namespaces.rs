@@ -0,0 +1,196 @@
+/// IMAP 命名空间（RFC 2342）
+///
+/// 公司邮箱的共享邮箱和公共文件夹（"Shared/"、"Public Folders/"）不在个人命名空间下，从个人根目录 LIST
+/// 看不到。服务器声明 NAMESPACE 能力时登录后查询一次，个人 / 其他用户 / 共享三类前缀保存在账户上。
+/// 各命名空间的层级分隔符可以不同（个人 "."，共享 "/"），文件夹名称按 LIST 返回的分隔符处理，不做替换。
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 文件夹所属的命名空间
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NamespaceKind {
+    #[default]
+    Personal,
+    /// 其他用户的邮箱（委托访问）
+    Other,
+    /// 共享邮箱和公共文件夹
+    Shared,
+}
+
+impl NamespaceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Personal => "personal",
+            Self::Other => "other",
+            Self::Shared => "shared",
+        }
+    }
+}
+
+/// 一个命名空间前缀
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Namespace {
+    /// 前缀（通常以分隔符结尾，如 "Shared/"；个人命名空间常为空）
+    pub prefix: String,
+    /// 该命名空间的层级分隔符（NIL 表示没有层级）
+    pub delimiter: Option<String>,
+}
+
+/// 服务器返回的全部命名空间
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Namespaces {
+    pub personal: Vec<Namespace>,
+    pub other: Vec<Namespace>,
+    pub shared: Vec<Namespace>,
+}
+
+impl Namespaces {
+    /// 个人命名空间以外的前缀（其他用户 + 共享）
+    pub fn shared_roots(&self) -> impl Iterator<Item = (NamespaceKind, &Namespace)> {
+        self.other
+            .iter()
+            .map(|namespace| (NamespaceKind::Other, namespace))
+            .chain(self.shared.iter().map(|namespace| (NamespaceKind::Shared, namespace)))
+            .filter(|(_, namespace)| !namespace.prefix.is_empty())
+    }
+
+    /// 文件夹所属的命名空间（按最长前缀匹配，都不匹配时为个人）
+    pub fn kind_of(&self, folder: &str) -> NamespaceKind {
+        self.shared_roots()
+            .filter(|(_, namespace)| {
+                folder.starts_with(&namespace.prefix)
+                    || namespace
+                        .delimiter
+                        .as_deref()
+                        .is_some_and(|delimiter| namespace.prefix.strip_suffix(delimiter) == Some(folder))
+            })
+            .max_by_key(|(_, namespace)| namespace.prefix.len())
+            .map_or(NamespaceKind::Personal, |(kind, _)| kind)
+    }
+}
+
+/// 解析 `* NAMESPACE (("" "/")) (("Other Users/" "/")) (("Shared/" "/"))` 响应
+///
+/// 三组依次为个人、其他用户、共享，每组为 NIL 或若干 `(前缀 分隔符 [扩展...])`。
+pub fn parse_namespace_response(response: &str) -> Option<Namespaces> {
+    let line = response.lines().find(|line| line.to_ascii_uppercase().starts_with("* NAMESPACE "))?;
+    let tokens = tokenize(&line["* NAMESPACE ".len()..]);
+    let mut position = 0;
+    let personal = parse_group(&tokens, &mut position)?;
+    let other = parse_group(&tokens, &mut position)?;
+    let shared = parse_group(&tokens, &mut position)?;
+    Some(Namespaces { personal, other, shared })
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Nil,
+    Text(String),
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '"' => {
+                let mut text = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => text.extend(chars.next()),
+                        '"' => break,
+                        c => text.push(c),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut atom = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '(' || next == ')' {
+                        break;
+                    }
+                    atom.push(next);
+                    chars.next();
+                }
+                if atom.eq_ignore_ascii_case("NIL") {
+                    tokens.push(Token::Nil);
+                } else {
+                    tokens.push(Token::Text(atom));
+                }
+            }
+        }
+    }
+    tokens
+}
+
+fn parse_group(tokens: &[Token], position: &mut usize) -> Option<Vec<Namespace>> {
+    match tokens.get(*position)? {
+        Token::Nil => {
+            *position += 1;
+            return Some(Vec::new());
+        }
+        Token::Open => *position += 1,
+        _ => return None,
+    }
+
+    let mut namespaces = Vec::new();
+    loop {
+        match tokens.get(*position)? {
+            Token::Close => {
+                *position += 1;
+                return Some(namespaces);
+            }
+            Token::Open => {
+                *position += 1;
+                let Token::Text(prefix) = tokens.get(*position)? else { return None };
+                let delimiter = match tokens.get(*position + 1)? {
+                    Token::Text(delimiter) => Some(delimiter.clone()),
+                    Token::Nil => None,
+                    _ => return None,
+                };
+                namespaces.push(Namespace { prefix: prefix.clone(), delimiter });
+                *position += 2;
+                // 跳过扩展字段直到这一项结束
+                let mut depth = 1;
+                while depth > 0 {
+                    match tokens.get(*position)? {
+                        Token::Open => depth += 1,
+                        Token::Close => depth -= 1,
+                        _ => {}
+                    }
+                    *position += 1;
+                }
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// 保存账户的命名空间（服务器不支持 NAMESPACE 时保存为空）
+pub async fn save(pool: &SqlitePool, account_id: i64, namespaces: Option<&Namespaces>) -> Result<(), AppError> {
+    let json = namespaces.map(|namespaces| serde_json::to_string(namespaces).unwrap_or_default());
+    sqlx::query("UPDATE accounts SET namespaces = ? WHERE id = ?")
+        .bind(json)
+        .bind(account_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 读取账户上次保存的命名空间
+pub async fn load(pool: &SqlitePool, account_id: i64) -> Result<Option<Namespaces>, AppError> {
+    let json: Option<String> = sqlx::query_scalar("SELECT namespaces FROM accounts WHERE id = ?")
+        .bind(account_id)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+    Ok(json.and_then(|json| serde_json::from_str(&json).ok()))
+}
@@ -0,0 +1,239 @@
+/// XOAUTH2 认证失败原因解析
+///
+/// Gmail 拒绝 XOAUTH2 时先在继续响应（`+ <base64>`）中返回 JSON，例如
+/// `{"status":"400","schemes":"Bearer","scope":"https://mail.google.com/"}`，
+/// 随后的 NO 响应文本（`[ALERT] Please log in via your web browser ...`）说明账户层面的问题。
+/// Outlook 不返回 JSON，只在 NO 响应文本中给出原因（`AUTHENTICATE failed.`、
+/// `User is authenticated but not connected.`）。这里把两者归类为前端可以提示操作的错误代码。
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+
+/// 已识别的 OAuth 认证失败原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuthFailure {
+    /// 令牌缺少邮箱权限（授权时未勾选或 scope 配置错误）
+    InsufficientScope,
+    /// 令牌无效或已过期
+    InvalidToken,
+    /// 账户被停用
+    AccountDisabled,
+    /// 服务商要求先在网页上登录确认
+    WebLoginRequired,
+    /// 账户未开启 IMAP
+    ImapDisabled,
+}
+
+impl OAuthFailure {
+    /// 前端错误代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InsufficientScope => "INSUFFICIENT_SCOPE",
+            Self::InvalidToken => "OAUTH_TOKEN_INVALID",
+            Self::AccountDisabled => "ACCOUNT_DISABLED",
+            Self::WebLoginRequired => "WEB_LOGIN_REQUIRED",
+            Self::ImapDisabled => "IMAP_DISABLED",
+        }
+    }
+
+    /// 给用户的操作提示
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::InsufficientScope => "The authorization does not include mail access. Sign in again and allow access to your mailbox.",
+            Self::InvalidToken => "The sign-in has expired or was revoked. Sign in again to reconnect this account.",
+            Self::AccountDisabled => "The mail provider reports that this account is disabled.",
+            Self::WebLoginRequired => "The mail provider requires you to sign in through its website before this app can connect.",
+            Self::ImapDisabled => "IMAP access is not enabled for this account. Enable it in your mail provider's settings.",
+        }
+    }
+}
+
+/// Gmail 在继续响应中返回的 JSON
+#[derive(Debug, Deserialize)]
+struct ChallengePayload {
+    status: Option<String>,
+    scope: Option<String>,
+}
+
+/// 解析后的失败信息
+#[derive(Debug, Clone, Default)]
+pub struct DecodedOAuthError {
+    pub reason: Option<OAuthFailure>,
+    /// 服务商的原始说明（去掉 `[ALERT]` 等响应码）
+    pub provider_message: Option<String>,
+    /// 继续响应中的 HTTP 风格状态码
+    pub status: Option<String>,
+    pub scope: Option<String>,
+}
+
+/// 从继续响应（已 base64 解码）和 NO / BAD 响应文本中解析失败原因
+pub fn decode(challenges: &[Vec<u8>], response_text: Option<&str>) -> DecodedOAuthError {
+    let payload = challenges
+        .iter()
+        .rev()
+        .find_map(|challenge| serde_json::from_slice::<ChallengePayload>(challenge).ok());
+    let (status, scope) = payload.map(|p| (p.status, p.scope)).unwrap_or_default();
+
+    let provider_message = response_text.map(strip_response_codes).filter(|text| !text.is_empty());
+    let reason = provider_message
+        .as_deref()
+        .and_then(reason_from_text)
+        .or_else(|| reason_from_status(status.as_deref(), scope.as_deref()))
+        .or_else(|| {
+            // Outlook 对无效令牌和缺少 IMAP.AccessAsUser.All 权限都只返回这一句
+            provider_message
+                .as_deref()
+                .filter(|text| text.to_lowercase().contains("authenticate failed"))
+                .map(|_| OAuthFailure::InvalidToken)
+        });
+
+    DecodedOAuthError {
+        reason,
+        provider_message,
+        status,
+        scope,
+    }
+}
+
+impl DecodedOAuthError {
+    /// 转换为应用错误；无法识别原因时保留原有的通用认证错误
+    pub fn into_app_error(self, host: &str, fallback: String) -> AppError {
+        match self.reason {
+            Some(reason) => AppError::OAuthRejected {
+                reason,
+                host: host.to_string(),
+                provider_message: self.provider_message,
+                status: self.status,
+                scope: self.scope,
+            },
+            None => AppError::Auth(format!(
+                "OAuth authentication failed: {}",
+                self.provider_message.unwrap_or(fallback)
+            )),
+        }
+    }
+}
+
+/// 账户层面的问题只出现在响应文本中
+fn reason_from_text(text: &str) -> Option<OAuthFailure> {
+    let text = text.to_lowercase();
+    if text.contains("web browser") || text.contains("web login") || text.contains("webalert") {
+        Some(OAuthFailure::WebLoginRequired)
+    } else if text.contains("not enabled for imap")
+        || text.contains("imap access is disabled")
+        || text.contains("imap is disabled")
+        // Outlook：IMAP 被管理员关闭或邮箱未开通
+        || text.contains("authenticated but not connected")
+    {
+        Some(OAuthFailure::ImapDisabled)
+    } else if text.contains("account is disabled")
+        || text.contains("account disabled")
+        || text.contains("account has been disabled")
+        || text.contains("suspended")
+    {
+        Some(OAuthFailure::AccountDisabled)
+    } else if text.contains("insufficient scope") || text.contains("invalid_scope") {
+        Some(OAuthFailure::InsufficientScope)
+    } else {
+        None
+    }
+}
+
+/// Gmail 的状态码：401 表示令牌无效或过期，400 / 403 表示令牌没有邮箱权限
+fn reason_from_status(status: Option<&str>, scope: Option<&str>) -> Option<OAuthFailure> {
+    match status? {
+        "401" => Some(OAuthFailure::InvalidToken),
+        "400" | "403" if scope.is_some() => Some(OAuthFailure::InsufficientScope),
+        "400" | "403" => Some(OAuthFailure::InvalidToken),
+        _ => None,
+    }
+}
+
+/// 去掉开头的 `[ALERT]` / `[AUTHENTICATIONFAILED]` 等响应码
+fn strip_response_codes(text: &str) -> String {
+    let mut rest = text.trim();
+    while let Some(stripped) = rest.strip_prefix('[') {
+        match stripped.find(']') {
+            Some(end) => rest = stripped[end + 1..].trim_start(),
+            None => break,
+        }
+    }
+    rest.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorResponse;
+
+    fn gmail_challenge(json: &str) -> Vec<Vec<u8>> {
+        vec![json.as_bytes().to_vec()]
+    }
+
+    #[test]
+    fn gmail_scope_rejection_is_insufficient_scope() {
+        let challenges = gmail_challenge(r#"{"status":"400","schemes":"Bearer","scope":"https://mail.google.com/"}"#);
+        let decoded = decode(&challenges, Some("[AUTHENTICATIONFAILED] Invalid credentials (Failure)"));
+        assert_eq!(decoded.reason, Some(OAuthFailure::InsufficientScope));
+        assert_eq!(decoded.status.as_deref(), Some("400"));
+        assert_eq!(decoded.scope.as_deref(), Some("https://mail.google.com/"));
+        assert_eq!(decoded.provider_message.as_deref(), Some("Invalid credentials (Failure)"));
+    }
+
+    #[test]
+    fn gmail_expired_token_is_invalid_token() {
+        let challenges = gmail_challenge(r#"{"status":"401","schemes":"Bearer"}"#);
+        assert_eq!(decode(&challenges, None).reason, Some(OAuthFailure::InvalidToken));
+        let challenges = gmail_challenge(r#"{"status":"400","schemes":"Bearer"}"#);
+        assert_eq!(decode(&challenges, None).reason, Some(OAuthFailure::InvalidToken));
+    }
+
+    #[test]
+    fn account_problems_in_response_text_take_precedence() {
+        let challenges = gmail_challenge(r#"{"status":"400","scope":"https://mail.google.com/"}"#);
+        let decoded = decode(&challenges, Some("[ALERT] Please log in via your web browser: https://support.google.com/mail/accounts/answer/78754 (Failure)"));
+        assert_eq!(decoded.reason, Some(OAuthFailure::WebLoginRequired));
+        assert!(decoded.provider_message.unwrap().starts_with("Please log in"));
+
+        assert_eq!(
+            decode(&[], Some("[ALERT] Your account is not enabled for IMAP use.")).reason,
+            Some(OAuthFailure::ImapDisabled)
+        );
+        assert_eq!(decode(&[], Some("Account disabled")).reason, Some(OAuthFailure::AccountDisabled));
+    }
+
+    #[test]
+    fn outlook_messages_are_decoded_from_text() {
+        assert_eq!(decode(&[], Some("AUTHENTICATE failed.")).reason, Some(OAuthFailure::InvalidToken));
+        assert_eq!(
+            decode(&[], Some("User is authenticated but not connected.")).reason,
+            Some(OAuthFailure::ImapDisabled)
+        );
+    }
+
+    #[test]
+    fn unrecognized_failures_keep_the_generic_auth_error() {
+        let decoded = decode(&[b"not json".to_vec()], Some("Something else went wrong"));
+        assert_eq!(decoded.reason, None);
+        match decoded.into_app_error("imap.example.com", "fallback".to_string()) {
+            AppError::Auth(message) => assert!(message.contains("Something else went wrong"), "{}", message),
+            other => panic!("unexpected error {:?}", other),
+        }
+        match decode(&[], None).into_app_error("imap.example.com", "connection closed".to_string()) {
+            AppError::Auth(message) => assert!(message.contains("connection closed"), "{}", message),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn recognized_failures_map_to_error_codes() {
+        let challenges = gmail_challenge(r#"{"status":"400","scope":"https://mail.google.com/"}"#);
+        let error = decode(&challenges, None).into_app_error("imap.gmail.com", String::new());
+        let response = ErrorResponse::from(error);
+        assert_eq!(response.code, "INSUFFICIENT_SCOPE");
+        assert_eq!(response.message, OAuthFailure::InsufficientScope.description());
+        let details = response.details.unwrap();
+        assert_eq!(details["host"], "imap.gmail.com");
+        assert_eq!(details["status"], "400");
+    }
+}
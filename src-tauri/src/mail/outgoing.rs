@@ -0,0 +1,524 @@
+/// 发送邮件
+///
+/// 撰写的邮件可以附带本地文件，或转发已保存的附件。连接 SMTP 之前按账户服务商的上限
+/// （`ProviderConfig::max_attachment_bytes`，按 base64 编码后的大小计算）检查附件总大小，
+/// 超出时返回 `ATTACHMENTS_TOO_LARGE` 并列出需要移除的文件。附件的 Content-Type 优先按文件头识别，
+/// 非 ASCII 文件名由 lettre 按 RFC 2231 编码。
+///
+/// 发送成功后邮件以 `direction = 'outgoing'` 保存，附件记录关联到这封邮件；内容哈希与已有附件相同的文件
+/// 直接引用已有文件，转发附件不会在磁盘上重复保存。
+use crate::artifacts::safety::SafetyPolicy;
+use crate::artifacts::sniff;
+use crate::commands::sync::resolve_account_auth;
+use crate::error::AppError;
+use crate::mail::identities::{IdentityStore, OutgoingSender};
+use crate::mail::imap_client::AuthMethod;
+use crate::mail::providers::SmtpConfig;
+use crate::mail::sync::{calculate_sha256, extract_file_extension, sanitize_filename, MailDirection};
+use crate::repository::ProjectRepository;
+use crate::storage::{disk_space, file_manager};
+use crate::utils::preview::preview_for;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+
+/// 附件来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum AttachmentSource {
+    /// 本地文件
+    File { path: String },
+    /// 已保存的附件（转发）
+    Attachment { id: i64 },
+}
+
+/// 待发送的邮件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutgoingEmail {
+    pub account_id: i64,
+    /// 发件身份，为空时使用默认身份
+    #[serde(default)]
+    pub identity_id: Option<i64>,
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub bcc: Vec<String>,
+    pub subject: String,
+    pub body_text: String,
+    #[serde(default)]
+    pub body_html: Option<String>,
+    /// 回复或转发的邮件 ID（设置 In-Reply-To / References，并归入同一线程和项目）
+    #[serde(default)]
+    pub reply_to_email_id: Option<i64>,
+    #[serde(default)]
+    pub attachments: Vec<AttachmentSource>,
+    /// 会议邀请回复（METHOD:REPLY 的 iCalendar，由 `CalendarStore::respond` 生成）
+    #[serde(skip)]
+    pub calendar_reply: Option<String>,
+}
+
+/// 发送结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentEmail {
+    pub email_id: i64,
+    pub message_id: String,
+    pub attachment_count: usize,
+    /// 附件原始大小之和（字节）
+    pub attachment_bytes: u64,
+}
+
+/// 超过大小上限时需要移除的附件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OversizedAttachment {
+    pub filename: String,
+    pub size: u64,
+}
+
+/// 读取完成的附件
+struct OutgoingAttachment {
+    filename: String,
+    content_type: String,
+    detected_mime: String,
+    data: Vec<u8>,
+    content_hash: String,
+    /// 转发的已有附件的存储路径
+    stored_path: Option<String>,
+}
+
+/// 回复的原邮件
+#[derive(sqlx::FromRow)]
+struct ParentEmail {
+    message_id: String,
+    thread_id: Option<String>,
+    project_id: Option<i64>,
+}
+
+/// 邮件发送
+pub struct MailSender {
+    pool: SqlitePool,
+}
+
+impl MailSender {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 检查附件、发送邮件并保存发出的副本
+    pub async fn send(&self, email: &OutgoingEmail) -> Result<SentEmail, AppError> {
+        if email.to.iter().chain(&email.cc).chain(&email.bcc).all(|address| address.trim().is_empty()) {
+            return Err(AppError::Validation("At least one recipient is required".to_string()));
+        }
+
+        let account_email: String = sqlx::query_scalar("SELECT email FROM accounts WHERE id = ?")
+            .bind(email.account_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::Validation(format!("Account {} not found", email.account_id)))?;
+        let (_, auth, provider) = resolve_account_auth(&self.pool, &account_email, None)
+            .await
+            .map_err(|e| AppError::Auth(e.message))?;
+
+        // 大小检查在任何 SMTP 通信之前完成
+        let attachments = self.load_attachments(&email.attachments).await?;
+        check_size(
+            attachments.iter().map(|attachment| (attachment.filename.as_str(), attachment.data.len() as u64)),
+            provider.max_attachment_bytes,
+        )?;
+
+        let sender = IdentityStore::new(self.pool.clone())
+            .sender_for(email.account_id, email.identity_id)
+            .await?;
+        let parent = match email.reply_to_email_id {
+            Some(id) => Some(
+                sqlx::query_as::<_, ParentEmail>("SELECT message_id, thread_id, project_id FROM emails WHERE id = ?")
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await?
+                    .ok_or(AppError::EmailNotFound { id })?,
+            ),
+            None => None,
+        };
+
+        let domain = sender.email.rsplit_once('@').map(|(_, domain)| domain).unwrap_or("threadline");
+        let message_id = format!("{}@{}", uuid::Uuid::new_v4(), domain);
+        let message = build_message(email, &sender, &message_id, parent.as_ref(), &attachments)?;
+
+        send_smtp(&sender.smtp, auth, message).await?;
+        log::info!(
+            "Sent email {} from {} with {} attachments",
+            message_id, sender.email, attachments.len()
+        );
+
+        let email_id = self.record_sent(email, &sender, &message_id, parent.as_ref()).await?;
+        let project_id = parent.as_ref().and_then(|parent| parent.project_id);
+        // 邮件已发出，保存附件副本失败只记录日志
+        if let Err(e) = self.save_attachments(email_id, project_id, &attachments).await {
+            log::error!("Failed to save attachments of sent email {}: {}", email_id, e);
+        }
+
+        Ok(SentEmail {
+            email_id,
+            message_id,
+            attachment_count: attachments.len(),
+            attachment_bytes: attachments.iter().map(|attachment| attachment.data.len() as u64).sum(),
+        })
+    }
+
+    /// 读取本地文件和转发的附件
+    async fn load_attachments(&self, sources: &[AttachmentSource]) -> Result<Vec<OutgoingAttachment>, AppError> {
+        let mut attachments = Vec::with_capacity(sources.len());
+        for source in sources {
+            let attachment = match source {
+                AttachmentSource::File { path } => {
+                    let path = PathBuf::from(path);
+                    let metadata = tokio::fs::metadata(&path).await?;
+                    if !metadata.is_file() {
+                        return Err(AppError::Validation(format!("Not a file: {}", path.display())));
+                    }
+                    let filename = path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .map(str::to_string)
+                        .ok_or_else(|| AppError::Validation(format!("Invalid file name: {}", path.display())))?;
+                    let data = tokio::fs::read(&path).await?;
+                    let detected_mime = sniff::sniff(&data).unwrap_or_default();
+                    OutgoingAttachment {
+                        content_type: content_type_for(&filename, None, &detected_mime),
+                        content_hash: calculate_sha256(&data),
+                        filename,
+                        detected_mime,
+                        data,
+                        stored_path: None,
+                    }
+                }
+                AttachmentSource::Attachment { id } => {
+                    let row: Option<(String, Option<String>, Option<String>, Option<String>, Option<String>)> =
+                        sqlx::query_as(
+                            "SELECT filename, mime_type, detected_mime, file_path, content_hash FROM attachments WHERE id = ?"
+                        )
+                        .bind(id)
+                        .fetch_optional(&self.pool)
+                        .await?;
+                    let (filename, mime_type, detected_mime, file_path, content_hash) =
+                        row.ok_or(AppError::AttachmentNotFound { id: *id })?;
+                    let file_path = file_path.ok_or(AppError::AttachmentNotFound { id: *id })?;
+                    let data = tokio::fs::read(file_manager::resolve_attachment_path(&file_path)?).await?;
+                    let detected_mime = detected_mime.unwrap_or_else(|| sniff::sniff(&data).unwrap_or_default());
+                    OutgoingAttachment {
+                        content_type: content_type_for(&filename, mime_type.as_deref(), &detected_mime),
+                        content_hash: content_hash.unwrap_or_else(|| calculate_sha256(&data)),
+                        filename,
+                        detected_mime,
+                        data,
+                        stored_path: Some(file_path),
+                    }
+                }
+            };
+            attachments.push(attachment);
+        }
+        Ok(attachments)
+    }
+
+    /// 保存发出的邮件
+    async fn record_sent(
+        &self,
+        email: &OutgoingEmail,
+        sender: &OutgoingSender,
+        message_id: &str,
+        parent: Option<&ParentEmail>,
+    ) -> Result<i64, AppError> {
+        let thread_id = parent
+            .and_then(|parent| parent.thread_id.clone())
+            .unwrap_or_else(|| message_id.to_string());
+        let email_id = sqlx::query(
+            r#"
+            INSERT INTO emails (
+                message_id, account_id, thread_id, in_reply_to, project_id, subject, sender, sender_name,
+                sender_address, recipients, cc, date, body_text, body_html, has_attachments, is_read, direction, preview
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)
+            "#
+        )
+        .bind(message_id)
+        .bind(email.account_id)
+        .bind(&thread_id)
+        .bind(parent.map(|parent| parent.message_id.as_str()))
+        .bind(parent.and_then(|parent| parent.project_id))
+        .bind(&email.subject)
+        .bind(&sender.from_header)
+        .bind(&sender.display_name)
+        .bind(&sender.email)
+        .bind(serde_json::to_string(&email.to)?)
+        .bind(serde_json::to_string(&email.cc)?)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(&email.body_text)
+        .bind(&email.body_html)
+        .bind(!email.attachments.is_empty())
+        .bind(MailDirection::Outgoing.as_str())
+        .bind(preview_for(Some(&email.body_text)))
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(email_id)
+    }
+
+    /// 保存附件记录，内容相同的已有文件直接复用
+    async fn save_attachments(
+        &self,
+        email_id: i64,
+        project_id: Option<i64>,
+        attachments: &[OutgoingAttachment],
+    ) -> Result<(), AppError> {
+        if attachments.is_empty() {
+            return Ok(());
+        }
+        let policy = SafetyPolicy::load(&self.pool).await?;
+
+        for attachment in attachments {
+            let file_type = extract_file_extension(&attachment.filename);
+            let file_path = match self.reusable_path(attachment).await? {
+                Some(path) => path,
+                None => self.store_file(email_id, &file_type, attachment).await?,
+            };
+            let danger_level = policy.classify_detected(
+                &attachment.filename,
+                Some(&attachment.content_type),
+                Some(&attachment.detected_mime),
+            );
+
+            sqlx::query(
+                r#"
+                INSERT INTO attachments (
+                    email_id, project_id, filename, file_type, file_size, mime_type, detected_mime,
+                    file_path, content_hash, danger_level
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#
+            )
+            .bind(email_id)
+            .bind(project_id)
+            .bind(&attachment.filename)
+            .bind(&file_type)
+            .bind(attachment.data.len() as i64)
+            .bind(&attachment.content_type)
+            .bind(&attachment.detected_mime)
+            .bind(&file_path)
+            .bind(&attachment.content_hash)
+            .bind(danger_level.as_str())
+            .execute(&self.pool)
+            .await?;
+        }
+
+        if project_id.is_some() {
+            ProjectRepository::new(self.pool.clone()).recompute_stats(project_id).await?;
+        }
+        Ok(())
+    }
+
+    /// 内容哈希相同、文件仍存在的已有附件路径
+    async fn reusable_path(&self, attachment: &OutgoingAttachment) -> Result<Option<String>, AppError> {
+        let mut candidates: Vec<String> = attachment.stored_path.clone().into_iter().collect();
+        let by_hash: Vec<String> = sqlx::query_scalar(
+            "SELECT DISTINCT file_path FROM attachments WHERE content_hash = ? AND file_path IS NOT NULL"
+        )
+        .bind(&attachment.content_hash)
+        .fetch_all(&self.pool)
+        .await?;
+        candidates.extend(by_hash);
+
+        for path in candidates {
+            let Ok(absolute) = file_manager::resolve_attachment_path(&path) else { continue };
+            if tokio::fs::try_exists(&absolute).await.unwrap_or(false) {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 写入附件存储：`{file_type}/sent/{email_id}/{filename}`
+    async fn store_file(&self, email_id: i64, file_type: &str, attachment: &OutgoingAttachment) -> Result<String, AppError> {
+        let relative = format!("{}/sent/{}/{}", file_type, email_id, sanitize_filename(&attachment.filename));
+        let absolute = file_manager::resolve_attachment_path(&relative)?;
+        if attachment.data.len() as u64 >= disk_space::LARGE_WRITE_BYTES {
+            disk_space::ensure_available(&absolute, attachment.data.len() as u64)?;
+        }
+        if let Some(parent) = absolute.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&absolute, &attachment.data)
+            .await
+            .map_err(|e| disk_space::write_error(e, &absolute))?;
+        Ok(relative)
+    }
+}
+
+/// 检查附件总大小（按 base64 编码后计算），超出时从最大的文件开始列出需要移除的附件
+pub fn check_size<'a>(files: impl IntoIterator<Item = (&'a str, u64)>, limit: u64) -> Result<(), AppError> {
+    let mut files: Vec<(&str, u64)> = files.into_iter().collect();
+    let total: u64 = files.iter().map(|(_, size)| encoded_size(*size)).sum();
+    if total <= limit {
+        return Ok(());
+    }
+
+    files.sort_by(|a, b| b.1.cmp(&a.1));
+    let mut remaining = total;
+    let mut oversized = Vec::new();
+    for (filename, size) in files {
+        if remaining <= limit {
+            break;
+        }
+        remaining -= encoded_size(size);
+        oversized.push(OversizedAttachment { filename: filename.to_string(), size });
+    }
+
+    Err(AppError::AttachmentsTooLarge { limit, total, files: oversized })
+}
+
+/// 人类可读的大小（错误消息用）
+pub fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
+
+/// base64 编码后的大小
+fn encoded_size(size: u64) -> u64 {
+    size.div_ceil(3) * 4
+}
+
+/// 附件的 Content-Type：按文件头识别的类型优先，其次是已保存的声明类型
+fn content_type_for(filename: &str, declared: Option<&str>, detected: &str) -> String {
+    if !detected.is_empty() {
+        return detected.to_string();
+    }
+    if let Some(declared) = declared.filter(|mime| !mime.trim().is_empty()) {
+        return declared.trim().to_string();
+    }
+    let extension = filename.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "txt" | "log" => "text/plain",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "md" => "text/markdown",
+        "ics" => "text/calendar",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+/// 构建 MIME 邮件（正文 + 附件的 multipart/mixed）
+fn build_message(
+    email: &OutgoingEmail,
+    sender: &OutgoingSender,
+    message_id: &str,
+    parent: Option<&ParentEmail>,
+    attachments: &[OutgoingAttachment],
+) -> Result<Message, AppError> {
+    let mailbox = |address: &str| {
+        address
+            .trim()
+            .parse::<Mailbox>()
+            .map_err(|e| AppError::Validation(format!("Invalid address {}: {}", address, e)))
+    };
+
+    let mut builder = Message::builder()
+        .from(mailbox(&sender.from_header)?)
+        .subject(email.subject.clone())
+        .message_id(Some(format!("<{}>", message_id)));
+    for address in email.to.iter().filter(|address| !address.trim().is_empty()) {
+        builder = builder.to(mailbox(address)?);
+    }
+    for address in email.cc.iter().filter(|address| !address.trim().is_empty()) {
+        builder = builder.cc(mailbox(address)?);
+    }
+    for address in email.bcc.iter().filter(|address| !address.trim().is_empty()) {
+        builder = builder.bcc(mailbox(address)?);
+    }
+    if let Some(parent) = parent {
+        builder = builder
+            .in_reply_to(format!("<{}>", parent.message_id))
+            .references(format!("<{}>", parent.message_id));
+    }
+
+    let plain = SinglePart::plain(email.body_text.clone());
+    if let Some(ics) = &email.calendar_reply {
+        // 会议回复同时作为内联 text/calendar 和 .ics 附件发送
+        let calendar = SinglePart::builder()
+            .header(ContentType::parse("text/calendar; method=REPLY; charset=UTF-8").expect("valid content type"))
+            .body(ics.clone());
+        let attachment = Attachment::new("invite.ics".to_string())
+            .body(ics.clone(), ContentType::parse("application/ics").expect("valid content type"));
+        let message = builder.multipart(
+            MultiPart::mixed()
+                .multipart(MultiPart::alternative().singlepart(plain).singlepart(calendar))
+                .singlepart(attachment),
+        );
+        return message.map_err(|e| AppError::Validation(format!("Failed to build message: {}", e)));
+    }
+
+    let message = match (&email.body_html, attachments.is_empty()) {
+        (None, true) => builder.singlepart(plain),
+        (Some(html), true) => {
+            builder.multipart(MultiPart::alternative_plain_html(email.body_text.clone(), html.clone()))
+        }
+        (html, false) => {
+            let mut mixed = match html {
+                Some(html) => MultiPart::mixed()
+                    .multipart(MultiPart::alternative_plain_html(email.body_text.clone(), html.clone())),
+                None => MultiPart::mixed().singlepart(plain),
+            };
+            for attachment in attachments {
+                let content_type = ContentType::parse(&attachment.content_type)
+                    .unwrap_or_else(|_| ContentType::parse("application/octet-stream").expect("valid content type"));
+                // 文件名按 RFC 2231 编码（filename*=utf-8''...）
+                mixed = mixed.singlepart(
+                    Attachment::new(attachment.filename.clone()).body(attachment.data.clone(), content_type),
+                );
+            }
+            builder.multipart(mixed)
+        }
+    };
+
+    message.map_err(|e| AppError::Validation(format!("Failed to build message: {}", e)))
+}
+
+/// 通过 SMTP 发送（lettre 的同步传输放在阻塞线程池中执行）
+async fn send_smtp(smtp: &SmtpConfig, auth: AuthMethod, message: Message) -> Result<(), AppError> {
+    let builder = if smtp.use_tls {
+        SmtpTransport::relay(&smtp.host)
+    } else if smtp.use_starttls {
+        SmtpTransport::starttls_relay(&smtp.host)
+    } else {
+        Ok(SmtpTransport::builder_dangerous(&smtp.host))
+    }
+    .map_err(|e| AppError::Smtp(format!("Invalid SMTP server {}: {}", smtp.host, e)))?;
+
+    let (credentials, mechanisms) = match auth {
+        AuthMethod::Password { username, password } => {
+            (Credentials::new(username, password), vec![Mechanism::Plain, Mechanism::Login])
+        }
+        AuthMethod::OAuth { username, access_token } => {
+            (Credentials::new(username, access_token), vec![Mechanism::Xoauth2])
+        }
+    };
+    let transport = builder
+        .port(smtp.port)
+        .credentials(credentials)
+        .authentication(mechanisms)
+        .build();
+
+    tokio::task::spawn_blocking(move || transport.send(&message))
+        .await
+        .map_err(|e| AppError::TaskExecution(e.to_string()))?
+        .map_err(|e| AppError::Smtp(e.to_string()))?;
+    Ok(())
+}
@@ -1,12 +1,21 @@
 /// 邮件解析器
-use mail_parser::{MessageParser, MimeHeaders};
+use crate::mail::receipts::{extract_receipt, ParsedReceipt, ReportPart};
+use mail_parser::{Message, MessageParser, MimeHeaders, PartType};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedEmail {
     pub message_id: String,
     pub subject: String,
+    /// "Name <addr>"（显示用）
     pub from: String,
+    /// 发件人显示名称
+    #[serde(default)]
+    pub from_name: Option<String>,
+    /// 发件人地址（小写）
+    #[serde(default)]
+    pub from_address: Option<String>,
     pub to: Vec<String>,
     pub cc: Vec<String>,
     pub date: String,
@@ -15,6 +24,21 @@ pub struct ParsedEmail {
     pub attachments: Vec<ParsedAttachment>,
     pub in_reply_to: Option<String>,
     pub references: Vec<String>,
+    /// `Auto-Submitted` 头（自动发送识别）
+    #[serde(default)]
+    pub auto_submitted: Option<String>,
+    /// `Precedence` 头（bulk / list / junk）
+    #[serde(default)]
+    pub precedence: Option<String>,
+    /// 已读回执 / 投递状态通知（不作为邮件保存）
+    #[serde(default)]
+    pub receipt: Option<ParsedReceipt>,
+    /// 含有无法解析的回执报告（按自动邮件保存）
+    #[serde(default)]
+    pub unparsed_report: bool,
+    /// 会议邀请的 iCalendar 内容（内联 text/calendar 或 .ics 附件）
+    #[serde(default)]
+    pub calendar: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,10 +47,46 @@ pub struct ParsedAttachment {
     pub content_type: String,
     pub size: usize,
     pub data: Vec<u8>,
+    /// 附件在原始邮件中的 MIME 部分
+    #[serde(default)]
+    pub part: Option<MimePartInfo>,
+}
+
+/// MIME 部分的位置和编码（之后可以只重新下载这个部分，见 `ImapConnection::fetch_body_part`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MimePartInfo {
+    /// IMAP 部分路径，如 "2" 或 "2.3"
+    pub path: String,
+    /// 传输编码（小写）：base64 / quoted-printable / 7bit / 8bit / binary
+    pub encoding: String,
+    /// 编码后的大小（字节，与 BODYSTRUCTURE 中的大小一致）
+    pub size: usize,
 }
 
 /// 解析邮件
 pub fn parse_email(raw_data: &[u8]) -> Result<ParsedEmail, String> {
+    parse(raw_data, None)
+}
+
+/// 解析要保存到账户中的邮件，没有 Message-ID 时生成的 ID 带上账户 ID
+pub fn parse_email_for_account(raw_data: &[u8], account_id: i64) -> Result<ParsedEmail, String> {
+    parse(raw_data, Some(account_id))
+}
+
+/// 没有 Message-ID 的邮件（退信、部分系统通知）使用的 ID
+///
+/// 同一秒内解析的两封邮件只靠时间戳会得到相同的 ID，违反 `UNIQUE (account_id, message_id)`，
+/// 因此加上随机部分。
+fn generated_message_id(account_id: Option<i64>) -> String {
+    let timestamp = chrono::Utc::now().timestamp();
+    let random = uuid::Uuid::new_v4().simple();
+    match account_id {
+        Some(account_id) => format!("generated-{}-{}-{}", account_id, timestamp, random),
+        None => format!("generated-{}-{}", timestamp, random),
+    }
+}
+
+fn parse(raw_data: &[u8], account_id: Option<i64>) -> Result<ParsedEmail, String> {
     let message = MessageParser::default()
         .parse(raw_data)
         .ok_or_else(|| "Failed to parse email".to_string())?;
@@ -35,7 +95,7 @@ pub fn parse_email(raw_data: &[u8]) -> Result<ParsedEmail, String> {
     let message_id = message
         .message_id()
         .map(|id| id.to_string())
-        .unwrap_or_else(|| format!("generated-{}", chrono::Utc::now().timestamp()));
+        .unwrap_or_else(|| generated_message_id(account_id));
 
     // 提取主题
     let subject = message
@@ -44,11 +104,18 @@ pub fn parse_email(raw_data: &[u8]) -> Result<ParsedEmail, String> {
         .to_string();
 
     // 提取发件人
-    let from = message
-        .from()
-        .and_then(|addrs| addrs.first())
-        .map(|addr| format_address(addr))
+    let sender = message.from().and_then(|addrs| addrs.first());
+    let from = sender
+        .map(format_address)
         .unwrap_or_else(|| "Unknown".to_string());
+    let from_name = sender
+        .and_then(|addr| addr.name())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+    let from_address = sender
+        .and_then(|addr| addr.address())
+        .map(|address| address.trim().to_lowercase())
+        .filter(|address| !address.is_empty());
 
     // 提取收件人
     let to = message
@@ -72,9 +139,11 @@ pub fn parse_email(raw_data: &[u8]) -> Result<ParsedEmail, String> {
     let body_text = message.body_text(0).map(|s| s.to_string());
     let body_html = message.body_html(0).map(|s| s.to_string());
 
-    // 提取附件
+    // 提取附件（同时记录 MIME 部分路径）
+    let part_paths = part_paths(&message);
     let mut attachments = Vec::new();
-    for attachment in message.attachments() {
+    for &part_id in &message.attachments {
+        let Some(attachment) = message.parts.get(part_id) else { continue };
         if let Some(filename) = attachment.attachment_name() {
             let content_type = attachment
                 .content_type()
@@ -84,11 +153,21 @@ pub fn parse_email(raw_data: &[u8]) -> Result<ParsedEmail, String> {
             let data = attachment.contents().to_vec();
             let size = data.len();
 
+            let part = part_paths.get(&part_id).map(|path| MimePartInfo {
+                path: path.clone(),
+                encoding: attachment
+                    .content_transfer_encoding()
+                    .map(|encoding| encoding.trim().to_lowercase())
+                    .unwrap_or_else(|| "7bit".to_string()),
+                size: attachment.raw_end_offset().saturating_sub(attachment.raw_body_offset()),
+            });
+
             attachments.push(ParsedAttachment {
                 filename: filename.to_string(),
                 content_type,
                 size,
                 data,
+                part,
             });
         }
     }
@@ -111,10 +190,37 @@ pub fn parse_email(raw_data: &[u8]) -> Result<ParsedEmail, String> {
         _ => vec![],
     };
 
+    let auto_submitted = message.header_raw("Auto-Submitted").map(|value| value.trim().to_string());
+    let precedence = message.header_raw("Precedence").map(|value| value.trim().to_string());
+
+    let (receipt, unparsed_report) = match extract_receipt(&message, in_reply_to.as_deref(), &references) {
+        Some(ReportPart::Receipt(receipt)) => (Some(receipt), false),
+        Some(ReportPart::Unparseable) => (None, true),
+        None => (None, false),
+    };
+
+    // 会议邀请：内联的 text/calendar 部分优先，其次是 .ics 附件
+    let calendar = message
+        .parts
+        .iter()
+        .find(|part| {
+            part.content_type().is_some_and(|ct| {
+                ct.ctype().eq_ignore_ascii_case("text") && ct.subtype().is_some_and(|sub| sub.eq_ignore_ascii_case("calendar"))
+            })
+        })
+        .or_else(|| {
+            message.attachments().find(|part| {
+                part.attachment_name().is_some_and(|name| name.to_lowercase().ends_with(".ics"))
+            })
+        })
+        .map(|part| String::from_utf8_lossy(part.contents()).into_owned());
+
     Ok(ParsedEmail {
         message_id,
         subject,
         from,
+        from_name,
+        from_address,
         to,
         cc,
         date,
@@ -123,9 +229,92 @@ pub fn parse_email(raw_data: &[u8]) -> Result<ParsedEmail, String> {
         attachments,
         in_reply_to,
         references,
+        auto_submitted,
+        precedence,
+        receipt,
+        unparsed_report,
+        calendar,
     })
 }
 
+/// 计算各 MIME 部分的 IMAP 部分路径（部分 ID → "2.3"）
+///
+/// 与 BODYSTRUCTURE 的编号一致：多部分邮件的子部分从 1 开始编号，嵌套的多部分在前面加上父部分的路径；
+/// 单部分邮件的正文是 "1"。内嵌邮件（message/rfc822）作为一个整体编号，不展开其中的部分。
+fn part_paths(message: &Message) -> HashMap<usize, String> {
+    let mut paths = HashMap::new();
+    match message.parts.first().map(|part| &part.body) {
+        Some(PartType::Multipart(children)) => number_parts(message, children, None, &mut paths),
+        Some(_) => {
+            paths.insert(0, "1".to_string());
+        }
+        None => {}
+    }
+    paths
+}
+
+fn number_parts(message: &Message, children: &[usize], parent: Option<&str>, paths: &mut HashMap<usize, String>) {
+    for (index, &part_id) in children.iter().enumerate() {
+        let path = match parent {
+            Some(parent) => format!("{}.{}", parent, index + 1),
+            None => (index + 1).to_string(),
+        };
+        if let Some(PartType::Multipart(grandchildren)) = message.parts.get(part_id).map(|part| &part.body) {
+            number_parts(message, grandchildren, Some(&path), paths);
+        }
+        paths.insert(part_id, path);
+    }
+}
+
+/// 按传输编码解码单独下载的 MIME 部分
+pub fn decode_part(data: &[u8], encoding: &str) -> Result<Vec<u8>, String> {
+    match encoding.trim().to_lowercase().as_str() {
+        "base64" => {
+            use base64::Engine;
+            let compact: Vec<u8> = data.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+            base64::engine::general_purpose::STANDARD
+                .decode(&compact)
+                .map_err(|e| format!("Invalid base64 part: {}", e))
+        }
+        "quoted-printable" => Ok(decode_quoted_printable(data)),
+        "" | "7bit" | "8bit" | "binary" => Ok(data.to_vec()),
+        other => Err(format!("Unsupported transfer encoding: {}", other)),
+    }
+}
+
+/// 解码 quoted-printable（软换行 "=\r\n" 去掉，无效的转义原样保留）
+fn decode_quoted_printable(data: &[u8]) -> Vec<u8> {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let mut decoded = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] != b'=' {
+            decoded.push(data[i]);
+            i += 1;
+            continue;
+        }
+        match (data.get(i + 1).copied(), data.get(i + 2).copied()) {
+            (Some(b'\r'), Some(b'\n')) => i += 3,
+            (Some(b'\n'), _) => i += 2,
+            (Some(high), Some(low)) => match (hex(high), hex(low)) {
+                (Some(high), Some(low)) => {
+                    decoded.push(high << 4 | low);
+                    i += 3;
+                }
+                _ => {
+                    decoded.push(b'=');
+                    i += 1;
+                }
+            },
+            _ => {
+                decoded.push(b'=');
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
 /// 格式化邮件地址
 fn format_address(addr: &mail_parser::Addr) -> String {
     if let Some(name) = addr.name() {
@@ -156,3 +345,184 @@ pub fn generate_thread_id(parsed: &ParsedEmail) -> String {
     // 否则使用自己的 message_id 作为新线程
     parsed.message_id.clone()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mail::sync::calculate_sha256;
+    use base64::Engine;
+
+    /// 嵌套 multipart、base64 / quoted-printable 附件和附加的邮件
+    fn fixture() -> Vec<u8> {
+        let binary: Vec<u8> = (0..=255u8).cycle().take(700).collect();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&binary);
+        let wrapped = encoded
+            .as_bytes()
+            .chunks(76)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        format!(
+            r#"From: Alice <alice@example.com>
+To: bob@example.com
+Subject: Fixture
+Message-ID: <fixture@example.com>
+MIME-Version: 1.0
+Content-Type: multipart/mixed; boundary="outer"
+
+--outer
+Content-Type: multipart/alternative; boundary="inner"
+
+--inner
+Content-Type: text/plain
+
+Hello
+--inner
+Content-Type: text/html
+
+<p>Hello</p>
+--inner--
+
+--outer
+Content-Type: multipart/mixed; boundary="nested"
+
+--nested
+Content-Type: text/plain
+
+See the attached data.
+--nested
+Content-Type: application/octet-stream; name="data.bin"
+Content-Disposition: attachment; filename="data.bin"
+Content-Transfer-Encoding: base64
+
+{}
+--nested--
+
+--outer
+Content-Type: text/plain; charset=utf-8; name="notes.txt"
+Content-Disposition: attachment; filename="notes.txt"
+Content-Transfer-Encoding: quoted-printable
+
+Caf=C3=A9 notes with a soft line break that continues on the next=
+ line and an equals sign =3D here.
+--outer
+Content-Type: message/rfc822; name="forwarded.eml"
+Content-Disposition: attachment; filename="forwarded.eml"
+
+From: Carol <carol@example.com>
+Subject: Forwarded
+Message-ID: <inner@example.com>
+
+Forwarded body
+--outer--
+"#,
+            wrapped
+        )
+        .replace('\n', "\r\n")
+        .into_bytes()
+    }
+
+    fn attachment<'a>(parsed: &'a ParsedEmail, filename: &str) -> &'a ParsedAttachment {
+        parsed
+            .attachments
+            .iter()
+            .find(|attachment| attachment.filename == filename)
+            .unwrap_or_else(|| panic!("attachment {} not parsed", filename))
+    }
+
+    #[test]
+    fn part_paths_follow_imap_numbering() {
+        let raw = fixture();
+        let message = MessageParser::default().parse(&raw[..]).unwrap();
+        let paths = part_paths(&message);
+
+        let path_of = |filename: &str| {
+            let id = message
+                .attachments
+                .iter()
+                .copied()
+                .find(|&id| message.parts[id].attachment_name() == Some(filename))
+                .unwrap();
+            paths[&id].clone()
+        };
+        assert_eq!(path_of("data.bin"), "2.2");
+        assert_eq!(path_of("notes.txt"), "3");
+        assert_eq!(path_of("forwarded.eml"), "4");
+
+        let mut all: Vec<&str> = paths.values().map(String::as_str).collect();
+        all.sort();
+        assert_eq!(all, vec!["1", "1.1", "1.2", "2", "2.1", "2.2", "3", "4"]);
+    }
+
+    #[test]
+    fn single_part_message_is_part_one() {
+        let raw = b"From: a@example.com\r\nSubject: Plain\r\n\r\nBody\r\n";
+        let message = MessageParser::default().parse(&raw[..]).unwrap();
+        assert_eq!(part_paths(&message).get(&0).map(String::as_str), Some("1"));
+    }
+
+    /// 单独下载的部分（原始邮件中该部分的正文）解码后与解析出的附件内容一致
+    #[test]
+    fn decoded_part_matches_parsed_attachment() {
+        let raw = fixture();
+        let parsed = parse_email(&raw).unwrap();
+        assert_eq!(parsed.attachments.len(), 3);
+
+        let message = MessageParser::default().parse(&raw[..]).unwrap();
+        for (filename, encoding) in [("data.bin", "base64"), ("notes.txt", "quoted-printable"), ("forwarded.eml", "7bit")] {
+            let attachment = attachment(&parsed, filename);
+            let part = attachment.part.as_ref().unwrap();
+            assert_eq!(part.encoding, encoding, "{}", filename);
+
+            let mime = message
+                .parts
+                .iter()
+                .find(|mime| mime.attachment_name() == Some(filename))
+                .unwrap();
+            let body = &raw[mime.raw_body_offset()..mime.raw_end_offset()];
+            assert_eq!(part.size, body.len(), "{}", filename);
+
+            let decoded = decode_part(body, &part.encoding).unwrap();
+            assert_eq!(
+                calculate_sha256(&decoded),
+                calculate_sha256(&attachment.data),
+                "{} decoded from part {}",
+                filename,
+                part.path
+            );
+        }
+    }
+
+    #[test]
+    fn quoted_printable_decoding() {
+        assert_eq!(decode_quoted_printable(b"Caf=C3=A9"), "Café".as_bytes());
+        assert_eq!(decode_quoted_printable(b"soft=\r\nbreak"), b"softbreak");
+        assert_eq!(decode_quoted_printable(b"soft=\nbreak"), b"softbreak");
+        assert_eq!(decode_quoted_printable(b"a=3Db"), b"a=b");
+        // 无效的转义原样保留
+        assert_eq!(decode_quoted_printable(b"50=ZZ off="), b"50=ZZ off=");
+    }
+
+    #[test]
+    fn decode_part_handles_encodings() {
+        assert_eq!(decode_part(b"aGVs\r\nbG8=\r\n", "base64").unwrap(), b"hello");
+        assert_eq!(decode_part(b"raw", "8bit").unwrap(), b"raw");
+        assert_eq!(decode_part(b"raw", " BINARY ").unwrap(), b"raw");
+        assert!(decode_part(b"???", "base64").is_err());
+        assert!(decode_part(b"data", "x-uuencode").is_err());
+    }
+
+    #[test]
+    fn mail_without_message_id_gets_a_unique_generated_id() {
+        let raw = b"From: MAILER-DAEMON@example.com\r\nSubject: Undelivered Mail Returned to Sender\r\n\r\nBounce.\r\n";
+        let first = parse_email_for_account(raw, 7).unwrap();
+        let second = parse_email_for_account(raw, 7).unwrap();
+        assert!(first.message_id.starts_with("generated-7-"), "{}", first.message_id);
+        assert_ne!(first.message_id, second.message_id);
+        assert!(parse_email(raw).unwrap().message_id.starts_with("generated-"));
+
+        let with_id = parse_email_for_account(b"Message-ID: <x@example.com>\r\nSubject: Hi\r\n\r\nBody\r\n", 7).unwrap();
+        assert_eq!(with_id.message_id, "x@example.com");
+    }
+}
@@ -0,0 +1,211 @@
+/// 待完成的 OAuth 授权
+///
+/// `start_oauth_flow` 拿到令牌后、前端调用 `add_oauth_email_account` 之前应用崩溃时，令牌会丢失，
+/// 用户只能重新授权。授权流程结束时先在这里保存一条待完成记录：服务商、邮箱提示和创建时间写入
+/// `pending_authorizations`，令牌只保存在系统钥匙串中（与翻译 API 密钥相同），不写入数据库。
+///
+/// 账户创建后删除记录；超过 `PENDING_TTL_MINUTES` 的记录在启动时连同钥匙串中的令牌一起清除。
+use crate::error::AppError;
+use crate::mail::oauth::OAuthTokenInfo;
+use crate::mail::providers::detect_provider;
+use crate::utils::secrets;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+/// 待完成记录的保留时间（分钟）
+pub const PENDING_TTL_MINUTES: i64 = 15;
+
+/// 待完成的授权（不含令牌）
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PendingAuthorization {
+    pub id: i64,
+    pub provider: String,
+    /// 授权前用户输入的邮箱（如有）
+    pub email_hint: Option<String>,
+    pub created_at: String,
+    /// 访问令牌过期时间（Unix 时间戳）
+    pub expires_at: Option<i64>,
+}
+
+impl PendingAuthorization {
+    /// 检查邮箱属于授权的服务商，避免把 Gmail 的令牌保存到 Outlook 地址上
+    pub fn ensure_provider_matches(&self, email: &str) -> Result<(), AppError> {
+        match detect_provider(email) {
+            Some(provider) if provider.name == self.provider => Ok(()),
+            Some(provider) => Err(AppError::Validation(format!(
+                "{} is a {} address but pending authorization {} was granted by {}",
+                email, provider.name, self.id, self.provider
+            ))),
+            None => Err(AppError::Validation(format!(
+                "Cannot tell which provider {} belongs to; pending authorization {} was granted by {}",
+                email, self.id, self.provider
+            ))),
+        }
+    }
+}
+
+/// 待完成授权存储
+pub struct PendingAuthorizations {
+    pool: SqlitePool,
+}
+
+impl PendingAuthorizations {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 保存授权结果，返回记录 ID
+    pub async fn save(
+        &self,
+        provider: &str,
+        email_hint: Option<&str>,
+        token: &OAuthTokenInfo,
+    ) -> Result<i64, AppError> {
+        let email_hint = email_hint.map(str::trim).filter(|hint| !hint.is_empty());
+        let id = sqlx::query(
+            "INSERT INTO pending_authorizations (provider, email_hint, expires_in) VALUES (?, ?, ?)",
+        )
+        .bind(provider.to_lowercase())
+        .bind(email_hint)
+        .bind(token.expires_in)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        let stored = serde_json::to_string(token)
+            .map_err(AppError::from)
+            .and_then(|json| {
+                keyring_entry(id)?
+                    .set_password(&json)
+                    .map_err(|e| AppError::Config(format!("Failed to save OAuth tokens to the keychain: {}", e)))
+            });
+        if let Err(e) = stored {
+            // 令牌没有保存成功时不保留无法完成的记录
+            sqlx::query("DELETE FROM pending_authorizations WHERE id = ?")
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+            return Err(e);
+        }
+
+        Ok(id)
+    }
+
+    /// 列出未过期的待完成授权（最新的在前）
+    pub async fn list(&self) -> Result<Vec<PendingAuthorization>, AppError> {
+        let pending = sqlx::query_as::<_, PendingAuthorization>(&format!(
+            r#"
+            SELECT id, provider, email_hint, created_at,
+                   CAST(strftime('%s', created_at) AS INTEGER) + expires_in AS expires_at
+            FROM pending_authorizations
+            WHERE created_at >= datetime('now', '-{} minutes')
+            ORDER BY created_at DESC, id DESC
+            "#,
+            PENDING_TTL_MINUTES
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(pending)
+    }
+
+    /// 读取未过期的待完成授权及其令牌
+    ///
+    /// 返回的 `expires_in` 已按记录创建后经过的时间扣减。
+    pub async fn get(&self, id: i64) -> Result<(PendingAuthorization, OAuthTokenInfo), AppError> {
+        let pending = self
+            .list()
+            .await?
+            .into_iter()
+            .find(|pending| pending.id == id)
+            .ok_or_else(|| AppError::Validation(format!("Pending authorization {} not found or expired", id)))?;
+
+        let json = match keyring_entry(id)?.get_password() {
+            Ok(json) => json,
+            Err(keyring::Error::NoEntry) => {
+                return Err(AppError::Validation(format!(
+                    "Tokens for pending authorization {} are no longer available, please sign in again",
+                    id
+                )));
+            }
+            Err(e) => return Err(AppError::Config(format!("Failed to read OAuth tokens from the keychain: {}", e))),
+        };
+        let mut token: OAuthTokenInfo = serde_json::from_str(&json)?;
+        token.expires_in = pending
+            .expires_at
+            .map(|expires_at| (expires_at - chrono::Utc::now().timestamp()).max(0));
+
+        Ok((pending, token))
+    }
+
+    /// 删除待完成授权及钥匙串中的令牌
+    ///
+    /// 钥匙串删除失败时保留记录，下次启动时重试清除。
+    pub async fn remove(&self, id: i64) -> Result<(), AppError> {
+        delete_tokens(id)?;
+        sqlx::query("DELETE FROM pending_authorizations WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 清除过期的待完成授权（启动时调用），返回清除的记录数
+    pub async fn purge_expired(&self) -> Result<usize, AppError> {
+        let expired: Vec<i64> = sqlx::query_scalar(&format!(
+            "SELECT id FROM pending_authorizations WHERE created_at < datetime('now', '-{} minutes')",
+            PENDING_TTL_MINUTES
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut purged = 0;
+        for id in expired {
+            match self.remove(id).await {
+                Ok(()) => purged += 1,
+                Err(e) => log::warn!("Failed to purge pending authorization {}: {}", id, e),
+            }
+        }
+        if purged > 0 {
+            log::info!("Purged {} expired pending OAuth authorizations", purged);
+        }
+
+        Ok(purged)
+    }
+}
+
+/// 删除钥匙串中的令牌（不存在时忽略）
+fn delete_tokens(id: i64) -> Result<(), AppError> {
+    match keyring_entry(id)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(AppError::Config(format!("Failed to remove OAuth tokens from the keychain: {}", e))),
+    }
+}
+
+fn keyring_entry(id: i64) -> Result<keyring::Entry, AppError> {
+    secrets::entry(&format!("oauth-pending-{}", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(provider: &str) -> PendingAuthorization {
+        PendingAuthorization {
+            id: 7,
+            provider: provider.to_string(),
+            email_hint: None,
+            created_at: "2026-10-17 09:00:00".to_string(),
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn provider_must_match_email_domain() {
+        assert!(pending("gmail").ensure_provider_matches("someone@gmail.com").is_ok());
+        assert!(pending("outlook").ensure_provider_matches("someone@Hotmail.com").is_ok());
+        assert!(pending("gmail").ensure_provider_matches("someone@outlook.com").is_err());
+        assert!(pending("outlook").ensure_provider_matches("someone@example.org").is_err());
+    }
+}
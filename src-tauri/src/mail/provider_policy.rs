@@ -0,0 +1,212 @@
+/// 服务商登录策略拒绝的识别
+///
+/// QQ 邮箱和网易邮箱（163 / 126）不接受账户密码直接登录 IMAP，需要在网页设置中开启 IMAP 服务并生成授权码。
+/// 拒绝时返回的是服务商自定义的文本（如 `Unsafe Login. Please contact kefu@188.com for help`），
+/// 这里按服务商匹配已知的文本片段，转换为带处理建议的错误。
+///
+/// 服务商修改措辞时只需在 `KNOWN_REJECTIONS` 中追加新的片段。
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+
+/// 有登录策略限制的服务商
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyProvider {
+    Qq,
+    NetEase163,
+    NetEase126,
+}
+
+impl PolicyProvider {
+    /// 按 IMAP 服务器地址识别服务商
+    pub fn from_host(host: &str) -> Option<Self> {
+        let host = host.trim().trim_end_matches('.').to_lowercase();
+        let matches = |domain: &str| host == domain || host.ends_with(&format!(".{}", domain));
+        if matches("qq.com") {
+            Some(Self::Qq)
+        } else if matches("163.com") {
+            Some(Self::NetEase163)
+        } else if matches("126.com") {
+            Some(Self::NetEase126)
+        } else {
+            None
+        }
+    }
+
+    /// 按服务商名称识别（与 `providers::get_provider_configs` 中的 `name` 一致）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "qq" => Some(Self::Qq),
+            "163" => Some(Self::NetEase163),
+            "126" => Some(Self::NetEase126),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Qq => "qq",
+            Self::NetEase163 => "163",
+            Self::NetEase126 => "126",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Qq => "QQ Mail",
+            Self::NetEase163 => "163",
+            Self::NetEase126 => "126",
+        }
+    }
+
+    fn is_netease(&self) -> bool {
+        matches!(self, Self::NetEase163 | Self::NetEase126)
+    }
+}
+
+/// 已识别的拒绝原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyReason {
+    /// 需要使用授权码而不是账户密码
+    AuthorizationCodeRequired,
+    /// 服务商判定为不安全登录（网易：未开启 IMAP 服务或使用了账户密码）
+    UnsafeLogin,
+    /// 账户未开启 IMAP 服务
+    ImapDisabled,
+    /// 登录过于频繁被暂时限制
+    LoginThrottled,
+}
+
+/// 已知的拒绝文本（小写片段）
+pub struct KnownRejection {
+    pub provider: PolicyProvider,
+    pub phrase: &'static str,
+    pub reason: PolicyReason,
+}
+
+/// 已知的拒绝文本，按顺序匹配（更具体的片段放在前面）
+pub const KNOWN_REJECTIONS: &[KnownRejection] = &[
+    // 网易 163 / 126
+    KnownRejection { provider: PolicyProvider::NetEase163, phrase: "unsafe login", reason: PolicyReason::UnsafeLogin },
+    KnownRejection { provider: PolicyProvider::NetEase163, phrase: "kefu@188.com", reason: PolicyReason::UnsafeLogin },
+    KnownRejection { provider: PolicyProvider::NetEase163, phrase: "err.login.reqcode", reason: PolicyReason::AuthorizationCodeRequired },
+    KnownRejection { provider: PolicyProvider::NetEase163, phrase: "err.login.passerr", reason: PolicyReason::AuthorizationCodeRequired },
+    KnownRejection { provider: PolicyProvider::NetEase163, phrase: "授权码", reason: PolicyReason::AuthorizationCodeRequired },
+    KnownRejection { provider: PolicyProvider::NetEase163, phrase: "err.login.imapdisabled", reason: PolicyReason::ImapDisabled },
+    KnownRejection { provider: PolicyProvider::NetEase163, phrase: "err.login.freqlimit", reason: PolicyReason::LoginThrottled },
+    KnownRejection { provider: PolicyProvider::NetEase126, phrase: "unsafe login", reason: PolicyReason::UnsafeLogin },
+    KnownRejection { provider: PolicyProvider::NetEase126, phrase: "kefu@188.com", reason: PolicyReason::UnsafeLogin },
+    KnownRejection { provider: PolicyProvider::NetEase126, phrase: "err.login.reqcode", reason: PolicyReason::AuthorizationCodeRequired },
+    KnownRejection { provider: PolicyProvider::NetEase126, phrase: "err.login.passerr", reason: PolicyReason::AuthorizationCodeRequired },
+    KnownRejection { provider: PolicyProvider::NetEase126, phrase: "授权码", reason: PolicyReason::AuthorizationCodeRequired },
+    KnownRejection { provider: PolicyProvider::NetEase126, phrase: "err.login.imapdisabled", reason: PolicyReason::ImapDisabled },
+    KnownRejection { provider: PolicyProvider::NetEase126, phrase: "err.login.freqlimit", reason: PolicyReason::LoginThrottled },
+    // QQ 邮箱
+    KnownRejection { provider: PolicyProvider::Qq, phrase: "login frequency limited", reason: PolicyReason::LoginThrottled },
+    KnownRejection { provider: PolicyProvider::Qq, phrase: "service is not open", reason: PolicyReason::ImapDisabled },
+    KnownRejection { provider: PolicyProvider::Qq, phrase: "authorized code", reason: PolicyReason::AuthorizationCodeRequired },
+    KnownRejection { provider: PolicyProvider::Qq, phrase: "authorization code", reason: PolicyReason::AuthorizationCodeRequired },
+    KnownRejection { provider: PolicyProvider::Qq, phrase: "weixin token", reason: PolicyReason::AuthorizationCodeRequired },
+    KnownRejection { provider: PolicyProvider::Qq, phrase: "授权码", reason: PolicyReason::AuthorizationCodeRequired },
+    KnownRejection { provider: PolicyProvider::Qq, phrase: "login fail", reason: PolicyReason::AuthorizationCodeRequired },
+];
+
+/// 识别出的登录策略拒绝
+#[derive(Debug, Clone)]
+pub struct PolicyRejection {
+    pub provider: PolicyProvider,
+    pub reason: PolicyReason,
+    /// 服务商返回的原始文本
+    pub provider_message: String,
+}
+
+impl PolicyRejection {
+    /// 给用户的处理建议
+    pub fn remediation(&self) -> String {
+        let label = self.provider.label();
+        match self.reason {
+            PolicyReason::AuthorizationCodeRequired if self.provider.is_netease() => format!(
+                "{} requires an IMAP authorization code instead of your account password — generate one in webmail settings (POP3/SMTP/IMAP)",
+                label
+            ),
+            PolicyReason::AuthorizationCodeRequired => format!(
+                "{} requires an authorization code instead of your QQ password — generate one in webmail settings (Account > POP3/IMAP/SMTP service)",
+                label
+            ),
+            PolicyReason::UnsafeLogin => format!(
+                "{} blocked this sign-in as unsafe — enable IMAP in webmail settings and sign in with an authorization code instead of your password",
+                label
+            ),
+            PolicyReason::ImapDisabled => format!(
+                "IMAP is not enabled for this {} account — turn on the IMAP/SMTP service in webmail settings",
+                label
+            ),
+            PolicyReason::LoginThrottled => format!(
+                "{} is temporarily limiting sign-ins for this account — wait a few minutes before trying again",
+                label
+            ),
+        }
+    }
+}
+
+/// 按服务器地址和服务商返回的文本识别登录策略拒绝
+pub fn detect(host: &str, response_text: &str) -> Option<PolicyRejection> {
+    let provider = PolicyProvider::from_host(host)?;
+    let text = response_text.to_lowercase();
+    let known = KNOWN_REJECTIONS
+        .iter()
+        .find(|known| known.provider == provider && text.contains(known.phrase))?;
+
+    Some(PolicyRejection {
+        provider,
+        reason: known.reason,
+        provider_message: response_text.trim().to_string(),
+    })
+}
+
+impl From<PolicyRejection> for AppError {
+    fn from(rejection: PolicyRejection) -> Self {
+        AppError::ProviderPolicy {
+            remediation: rejection.remediation(),
+            provider: rejection.provider,
+            reason: rejection.reason,
+            provider_message: rejection.provider_message,
+        }
+    }
+}
+
+/// 服务商的设置步骤（开启 IMAP 并生成授权码）
+pub fn setup_help(provider: PolicyProvider) -> &'static str {
+    match provider {
+        PolicyProvider::Qq => {
+            r#"QQ 邮箱 IMAP 设置步骤：
+
+1. 在浏览器中登录 QQ 邮箱：https://mail.qq.com/
+2. 点击页面顶部的 "设置" > "账户"
+3. 找到 "POP3/IMAP/SMTP/Exchange/CardDAV/CalDAV服务"
+4. 开启 "IMAP/SMTP服务"，按提示用绑定的手机发送短信验证
+5. 验证后页面会显示一个 16 位授权码
+6. 在 ThreadLine 中使用授权码作为密码（不是 QQ 密码）
+
+注意事项：
+- 授权码只显示一次，可以随时生成新的授权码
+- 修改 QQ 密码后需要重新生成授权码
+- 提示 "登录频率受限" 时请等待几分钟再试"#
+        }
+        PolicyProvider::NetEase163 | PolicyProvider::NetEase126 => {
+            r#"网易邮箱（163 / 126）IMAP 设置步骤：
+
+1. 在浏览器中登录网易邮箱（https://mail.163.com/ 或 https://mail.126.com/）
+2. 点击页面顶部的 "设置" > "POP3/SMTP/IMAP"
+3. 开启 "IMAP/SMTP服务"，按提示用手机验证
+4. 验证后页面会显示授权码（或在 "授权密码管理" 中新增授权码）
+5. 在 ThreadLine 中使用授权码作为密码（不是邮箱登录密码）
+
+注意事项：
+- 提示 "Unsafe Login" 表示 IMAP 服务未开启或使用了邮箱登录密码
+- 授权码只显示一次，丢失后删除旧授权码并新增一个
+- 修改邮箱密码后需要重新生成授权码"#
+        }
+    }
+}
@@ -25,6 +25,19 @@ pub struct ProviderConfig {
     pub smtp: SmtpConfig,
     pub oauth_supported: bool,
     pub oauth_client_id: Option<String>,
+    /// 已发送文件夹名称（服务器不返回 SPECIAL-USE 标记时使用）
+    #[serde(default)]
+    pub sent_folder: Option<String>,
+    /// 单封邮件附件总大小上限（字节，按 base64 编码后计算）
+    #[serde(default = "default_max_attachment_bytes")]
+    pub max_attachment_bytes: u64,
+}
+
+/// 未配置时的附件大小上限（25 MB）
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+
+fn default_max_attachment_bytes() -> u64 {
+    DEFAULT_MAX_ATTACHMENT_BYTES
 }
 
 /// 预定义的邮箱服务商配置
@@ -48,6 +61,8 @@ pub fn get_provider_configs() -> Vec<ProviderConfig> {
             },
             oauth_supported: true,
             oauth_client_id: None, // 需要用户配置
+            sent_folder: Some("[Gmail]/Sent Mail".to_string()),
+            max_attachment_bytes: DEFAULT_MAX_ATTACHMENT_BYTES,
         },
         
         // Outlook / Hotmail / Office 365
@@ -68,6 +83,8 @@ pub fn get_provider_configs() -> Vec<ProviderConfig> {
             },
             oauth_supported: true,
             oauth_client_id: None,
+            sent_folder: Some("Sent Items".to_string()),
+            max_attachment_bytes: 20 * 1024 * 1024,
         },
         
         // QQ 邮箱
@@ -88,6 +105,8 @@ pub fn get_provider_configs() -> Vec<ProviderConfig> {
             },
             oauth_supported: false,
             oauth_client_id: None,
+            sent_folder: Some("Sent Messages".to_string()),
+            max_attachment_bytes: 50 * 1024 * 1024,
         },
         
         // 163 邮箱
@@ -108,6 +127,8 @@ pub fn get_provider_configs() -> Vec<ProviderConfig> {
             },
             oauth_supported: false,
             oauth_client_id: None,
+            sent_folder: Some("&XfJT0ZAB-".to_string()), // "已发送"（IMAP 修改版 UTF-7）
+            max_attachment_bytes: 50 * 1024 * 1024,
         },
         
         // 126 邮箱
@@ -128,6 +149,8 @@ pub fn get_provider_configs() -> Vec<ProviderConfig> {
             },
             oauth_supported: false,
             oauth_client_id: None,
+            sent_folder: Some("&XfJT0ZAB-".to_string()), // "已发送"（IMAP 修改版 UTF-7）
+            max_attachment_bytes: 50 * 1024 * 1024,
         },
         
         // iCloud
@@ -148,6 +171,8 @@ pub fn get_provider_configs() -> Vec<ProviderConfig> {
             },
             oauth_supported: false,
             oauth_client_id: None,
+            sent_folder: Some("Sent Messages".to_string()),
+            max_attachment_bytes: 20 * 1024 * 1024,
         },
     ]
 }
@@ -0,0 +1,99 @@
+/// 邮箱存储配额
+///
+/// 接近配额上限的邮箱（尤其是 Gmail）会开始拒绝 APPEND，同步也会以难以理解的方式失败。
+/// 服务器支持 QUOTA 扩展时每次同步查询收件箱的配额根，用量保存在账户上，在存储统计中显示；
+/// 使用率首次达到 90% 时发出警告通知。
+use crate::error::AppError;
+use crate::mail::imap_client::MailboxQuota;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 使用率达到该比例时发出警告
+pub const QUOTA_WARNING_RATIO: f64 = 0.9;
+
+/// 账户的邮箱配额
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountQuota {
+    pub account_id: i64,
+    pub email: String,
+    pub used_bytes: i64,
+    pub limit_bytes: i64,
+    pub used_ratio: f64,
+    /// 使用率达到警告阈值
+    pub warning: bool,
+    pub checked_at: Option<String>,
+}
+
+/// 配额存储
+pub struct QuotaStore {
+    pool: SqlitePool,
+}
+
+impl QuotaStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 保存查询到的配额，本次使用率首次达到警告阈值时返回配额（用于发出通知）
+    pub async fn record(&self, account_id: i64, quota: MailboxQuota) -> Result<Option<AccountQuota>, AppError> {
+        let previous: Option<(Option<i64>, Option<i64>)> =
+            sqlx::query_as("SELECT quota_used_kb, quota_limit_kb FROM accounts WHERE id = ?")
+                .bind(account_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        let was_warning = match previous {
+            Some((Some(used), Some(limit))) if limit > 0 => used as f64 / limit as f64 >= QUOTA_WARNING_RATIO,
+            _ => false,
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE accounts
+            SET quota_used_kb = ?, quota_limit_kb = ?, quota_checked_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#
+        )
+        .bind(quota.used_kb.min(i64::MAX as u64) as i64)
+        .bind(quota.limit_kb.min(i64::MAX as u64) as i64)
+        .bind(account_id)
+        .execute(&self.pool)
+        .await?;
+
+        let current = self.get(account_id).await?;
+        Ok(current.filter(|quota| quota.warning && !was_warning))
+    }
+
+    pub async fn get(&self, account_id: i64) -> Result<Option<AccountQuota>, AppError> {
+        Ok(self.list().await?.into_iter().find(|quota| quota.account_id == account_id))
+    }
+
+    /// 所有已查询到配额的账户
+    pub async fn list(&self) -> Result<Vec<AccountQuota>, AppError> {
+        let rows: Vec<(i64, String, i64, i64, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, email, quota_used_kb, quota_limit_kb, quota_checked_at
+            FROM accounts
+            WHERE quota_limit_kb > 0
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(account_id, email, used_kb, limit_kb, checked_at)| {
+                let used_ratio = used_kb as f64 / limit_kb as f64;
+                AccountQuota {
+                    account_id,
+                    email,
+                    used_bytes: used_kb.saturating_mul(1024),
+                    limit_bytes: limit_kb.saturating_mul(1024),
+                    used_ratio: (used_ratio * 1000.0).round() / 1000.0,
+                    warning: used_ratio >= QUOTA_WARNING_RATIO,
+                    checked_at,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,238 @@
+/// 已读回执（MDN，`message/disposition-notification`）和投递状态通知（DSN，`message/delivery-status`）
+///
+/// 这类邮件正文几乎为空，作为普通邮件保存时会在线程中显示成奇怪的空白邮件。解析时识别报告部分，
+/// 保存到 `email_receipts`，按 Message-ID 关联到原邮件；时间线在原邮件上显示"Read receipt from X"。
+/// 无法解析的报告仍按普通邮件保存，并标记为自动邮件。
+use crate::error::AppError;
+use crate::mail::parser::ParsedEmail;
+use crate::utils::i18n::Locale;
+use mail_parser::{Message, MimeHeaders};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// 回执类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReceiptKind {
+    /// 已读回执（MDN）
+    Read,
+    /// 投递状态通知（DSN）
+    Delivery,
+}
+
+impl ReceiptKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReceiptKind::Read => "read",
+            ReceiptKind::Delivery => "delivery",
+        }
+    }
+}
+
+/// 从报告部分解析出的回执
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedReceipt {
+    pub kind: ReceiptKind,
+    /// 被回执的邮件的 Message-ID（不含尖括号）
+    pub original_message_id: String,
+    /// MDN 的 disposition 类型（displayed / deleted ...）或 DSN 的 action（delivered / failed ...）
+    pub status: String,
+    /// Final-Recipient 地址
+    pub recipient: Option<String>,
+}
+
+/// 邮件中的报告部分
+#[derive(Debug, Clone)]
+pub enum ReportPart {
+    Receipt(ParsedReceipt),
+    /// 有报告部分但无法解析（原邮件或状态缺失）
+    Unparseable,
+}
+
+/// 时间线上显示在原邮件下的回执
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailReceipt {
+    pub kind: String,
+    /// 显示文本（"Read receipt from Alice"）
+    pub label: String,
+    pub status: String,
+    pub reporter: Option<String>,
+    pub recipient: Option<String>,
+    pub date: Option<String>,
+}
+
+/// 识别邮件中的 MDN / DSN 报告部分（没有报告部分时返回 None）
+///
+/// 原邮件优先取报告中的 `Original-Message-ID`，其次是 `In-Reply-To` 和 `References` 的最后一项。
+pub fn extract_receipt(
+    message: &Message<'_>,
+    in_reply_to: Option<&str>,
+    references: &[String],
+) -> Option<ReportPart> {
+    let (kind, body) = message.parts.iter().find_map(|part| {
+        let content_type = part.content_type()?;
+        if !content_type.ctype().eq_ignore_ascii_case("message") {
+            return None;
+        }
+        let kind = match content_type.subtype()?.to_ascii_lowercase().as_str() {
+            "disposition-notification" => ReceiptKind::Read,
+            "delivery-status" => ReceiptKind::Delivery,
+            _ => return None,
+        };
+        Some((kind, String::from_utf8_lossy(part.contents()).into_owned()))
+    })?;
+
+    let fields = report_fields(&body);
+    let status = match kind {
+        // "manual-action/MDN-sent-manually; displayed"
+        ReceiptKind::Read => fields
+            .get("disposition")
+            .and_then(|value| value.rsplit(';').next())
+            .map(|value| value.split('/').next().unwrap_or(value).trim().to_ascii_lowercase()),
+        ReceiptKind::Delivery => fields.get("action").map(|value| value.trim().to_ascii_lowercase()),
+    }
+    .filter(|status| !status.is_empty());
+    let original_message_id = fields
+        .get("original-message-id")
+        .map(|value| strip_angle_brackets(value))
+        .or_else(|| in_reply_to.map(strip_angle_brackets))
+        .or_else(|| references.last().map(|id| strip_angle_brackets(id)))
+        .filter(|id| !id.is_empty());
+    let recipient = fields
+        .get("final-recipient")
+        .or_else(|| fields.get("original-recipient"))
+        .map(|value| value.rsplit(';').next().unwrap_or(value).trim().to_lowercase())
+        .filter(|address| !address.is_empty());
+
+    match (status, original_message_id) {
+        (Some(status), Some(original_message_id)) => Some(ReportPart::Receipt(ParsedReceipt {
+            kind,
+            original_message_id,
+            status,
+            recipient,
+        })),
+        _ => Some(ReportPart::Unparseable),
+    }
+}
+
+/// 解析报告正文中的字段（小写字段名，取第一次出现的值，处理折行）
+///
+/// DSN 由多个以空行分隔的字段块组成（整封邮件一块，每个收件人一块），只关心首个收件人时取第一次出现的值即可。
+fn report_fields(body: &str) -> HashMap<String, String> {
+    let mut fields: HashMap<String, String> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in body.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some(value) = current.as_ref().and_then(|name| fields.get_mut(name)) {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        current = None;
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = name.trim().to_ascii_lowercase();
+        if name.is_empty() || fields.contains_key(&name) {
+            continue;
+        }
+        fields.insert(name.clone(), value.trim().to_string());
+        current = Some(name);
+    }
+    fields
+}
+
+/// 回执的显示文本
+fn receipt_label(kind: &str, status: &str, reporter: Option<&str>, recipient: Option<&str>, locale: Locale) -> String {
+    let reporter = reporter.or(recipient).unwrap_or("?");
+    let recipient = recipient.unwrap_or(reporter);
+    match (kind, status, locale) {
+        ("read", "displayed", Locale::En) => format!("Read receipt from {}", reporter),
+        ("read", "displayed", Locale::Zh) => format!("{} 已读", reporter),
+        ("read", _, Locale::En) => format!("Receipt from {}: {}", reporter, status),
+        ("read", _, Locale::Zh) => format!("{} 的回执：{}", reporter, status),
+        (_, "delivered", Locale::En) => format!("Delivered to {}", recipient),
+        (_, "delivered", Locale::Zh) => format!("已投递给 {}", recipient),
+        (_, "failed", Locale::En) => format!("Delivery to {} failed", recipient),
+        (_, "failed", Locale::Zh) => format!("投递给 {} 失败", recipient),
+        (_, "delayed", Locale::En) => format!("Delivery to {} delayed", recipient),
+        (_, "delayed", Locale::Zh) => format!("投递给 {} 延迟", recipient),
+        (_, _, Locale::En) => format!("Delivery status for {}: {}", recipient, status),
+        (_, _, Locale::Zh) => format!("{} 的投递状态：{}", recipient, status),
+    }
+}
+
+fn strip_angle_brackets(id: &str) -> String {
+    id.trim().trim_start_matches('<').trim_end_matches('>').trim().to_string()
+}
+
+/// 回执存储
+pub struct ReceiptStore {
+    pool: SqlitePool,
+}
+
+impl ReceiptStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 保存回执（同一封回执邮件重复同步时忽略）
+    pub async fn save(&self, account_id: i64, parsed: &ParsedEmail, receipt: &ParsedReceipt) -> Result<(), AppError> {
+        let reporter = parsed.from_name.as_ref().or(parsed.from_address.as_ref());
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO email_receipts (
+                account_id, message_id, original_message_id, kind, status, reporter, recipient, date
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(account_id)
+        .bind(&parsed.message_id)
+        .bind(&receipt.original_message_id)
+        .bind(receipt.kind.as_str())
+        .bind(&receipt.status)
+        .bind(reporter)
+        .bind(&receipt.recipient)
+        .bind(&parsed.date)
+        .execute(&self.pool)
+        .await?;
+
+        log::debug!(
+            "Stored {} receipt ({}) for message {}",
+            receipt.kind.as_str(), receipt.status, receipt.original_message_id
+        );
+        Ok(())
+    }
+
+    /// 获取项目中各邮件的回执，按邮件 ID 分组
+    pub async fn for_project(&self, project_id: i64, locale: Locale) -> Result<HashMap<i64, Vec<EmailReceipt>>, AppError> {
+        let rows: Vec<(i64, String, String, Option<String>, Option<String>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT e.id, r.kind, r.status, r.reporter, r.recipient, r.date
+            FROM email_receipts r
+            JOIN emails e ON e.message_id = r.original_message_id
+            WHERE e.project_id = ?
+            ORDER BY julianday(r.date) ASC
+            "#
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut receipts: HashMap<i64, Vec<EmailReceipt>> = HashMap::new();
+        for (email_id, kind, status, reporter, recipient, date) in rows {
+            let label = receipt_label(&kind, &status, reporter.as_deref(), recipient.as_deref(), locale);
+            receipts.entry(email_id).or_default().push(EmailReceipt {
+                kind,
+                label,
+                status,
+                reporter,
+                recipient,
+                date,
+            });
+        }
+        Ok(receipts)
+    }
+}
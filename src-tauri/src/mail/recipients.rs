@@ -0,0 +1,232 @@
+/// 回复收件人计算
+///
+/// 根据原邮件的发件人、收件人和抄送计算回复 / 全部回复的 To 和 Cc：排除自己的地址（所有已添加账户，
+/// 包括带 "+标签" 的变体），按地址忽略大小写去重；合并过的联系人的多个地址只保留先出现的一个。
+use crate::error::AppError;
+use crate::mail::contacts::{self, CanonicalContact};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// 回复方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyMode {
+    Reply,
+    ReplyAll,
+}
+
+/// 回复的收件人
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplyRecipients {
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    /// 原邮件发给的身份（回复时默认用它发信）
+    pub identity_id: Option<i64>,
+}
+
+/// 自己的地址（所有账户及其身份，小写）
+pub async fn my_addresses(pool: &SqlitePool) -> Result<Vec<String>, AppError> {
+    let mut addresses: Vec<String> = sqlx::query_scalar("SELECT email FROM accounts")
+        .fetch_all(pool)
+        .await?;
+    // 旧归档库没有身份表
+    let identities: Vec<String> = sqlx::query_scalar("SELECT email FROM account_identities")
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default();
+    addresses.extend(identities);
+    Ok(addresses.into_iter().map(|address| address.trim().to_lowercase()).collect())
+}
+
+/// 自己只在抄送中（不在收件人中）
+pub fn is_cc_only(to: &[String], cc: &[String], mine: &[String]) -> bool {
+    let contains_me = |list: &[String]| list.iter().any(|entry| is_mine(entry, mine));
+    contains_me(cc) && !contains_me(to)
+}
+
+/// 计算回复的收件人
+pub async fn compute_reply_recipients(
+    pool: &SqlitePool,
+    email_id: i64,
+    mode: ReplyMode,
+) -> Result<ReplyRecipients, AppError> {
+    let row: Option<(Option<String>, Option<String>, Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT sender, sender_address, recipients, cc FROM emails WHERE id = ?"
+    )
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await?;
+    let (sender, sender_address, recipients, cc) = row.ok_or(AppError::EmailNotFound { id: email_id })?;
+
+    let sender = sender.filter(|sender| !sender.trim().is_empty());
+    let original_to = parse_list(recipients.as_deref());
+    let original_cc = parse_list(cc.as_deref());
+    let mine = my_addresses(pool).await?;
+    let aliases = contacts::canonical_addresses(pool).await?;
+    let push = |list: &mut Vec<String>, entry: &str, exclude: &[String]| {
+        push_unique(list, entry, &mine, exclude, &aliases)
+    };
+
+    // 回复自己发出的邮件时，回给原收件人
+    let sent_by_me = sender_address.as_deref().is_some_and(|address| is_my_address(address, &mine));
+    let mut to = Vec::new();
+    match (sent_by_me, &sender) {
+        (false, Some(sender)) => push(&mut to, sender, &[]),
+        _ => {
+            for entry in &original_to {
+                push(&mut to, entry, &[]);
+            }
+        }
+    }
+
+    let identity_id = addressed_identity(pool, original_to.iter().chain(&original_cc)).await?;
+    let mut result = ReplyRecipients { to, cc: Vec::new(), identity_id };
+    if mode == ReplyMode::Reply {
+        return Ok(result);
+    }
+
+    if !sent_by_me {
+        for entry in &original_to {
+            push(&mut result.to, entry, &[]);
+        }
+    }
+    for entry in &original_cc {
+        push(&mut result.cc, entry, &result.to);
+    }
+
+    Ok(result)
+}
+
+/// 收件人中第一个属于某个身份的地址对应的身份 ID
+async fn addressed_identity<'a>(
+    pool: &SqlitePool,
+    entries: impl Iterator<Item = &'a String>,
+) -> Result<Option<i64>, AppError> {
+    for entry in entries {
+        let address = address_of(entry);
+        let identity: Option<i64> = sqlx::query_scalar("SELECT id FROM account_identities WHERE email IN (?, ?)")
+            .bind(&address)
+            .bind(strip_plus_tag(&address))
+            .fetch_optional(pool)
+            .await?;
+        if identity.is_some() {
+            return Ok(identity);
+        }
+    }
+    Ok(None)
+}
+
+/// 数据库中的收件人列表（JSON 数组）
+pub fn parse_list(value: Option<&str>) -> Vec<String> {
+    value
+        .and_then(|value| serde_json::from_str::<Vec<String>>(value).ok())
+        .unwrap_or_default()
+}
+
+/// 从 "Name <addr>" 中取出小写地址
+pub fn address_of(entry: &str) -> String {
+    let address = match entry.rsplit_once('<') {
+        Some((_, rest)) => rest.trim_end_matches('>'),
+        None => entry,
+    };
+    address.trim().to_lowercase()
+}
+
+/// 拆分发件人字符串为 (显示名称, 小写地址)
+///
+/// 兼容 `"Name" <addr>`、`Name <addr>`、`<addr>`、裸地址、缺少尖括号的 `Name addr@host`
+/// 以及旧式的 `addr (Name)`；找不到地址时整段视为名称。新邮件在保存时直接使用解析器给出的结构化地址，
+/// 这里只用于迁移旧数据和收件人列表。
+pub fn split_sender(raw: &str) -> (Option<String>, Option<String>) {
+    let raw = raw.trim();
+    let clean_name = |name: &str| {
+        let name = name.trim().trim_matches('"').trim().replace("\\\"", "\"");
+        (!name.is_empty()).then_some(name)
+    };
+
+    // Name <addr> / <addr>（缺少结尾的 '>' 时取到末尾）
+    if let Some((name, rest)) = raw.rsplit_once('<') {
+        let address = rest.split('>').next().unwrap_or(rest).trim();
+        if is_bare_address(address) {
+            return (clean_name(name), Some(address.to_lowercase()));
+        }
+    }
+
+    // addr (Name)
+    if let Some((address, comment)) = raw.split_once('(') {
+        let address = address.trim();
+        if is_bare_address(address) {
+            return (clean_name(comment.trim_end_matches(')')), Some(address.to_lowercase()));
+        }
+    }
+
+    // 裸地址或 Name addr@host：取最后一个含 @ 的词，其余作为名称
+    let words: Vec<&str> = raw.split_whitespace().collect();
+    if let Some(index) = words.iter().rposition(|word| word.contains('@')) {
+        let address = words[index].trim_matches(|c: char| matches!(c, '<' | '>' | '"' | '\'' | ',' | ';'));
+        if is_bare_address(address) {
+            let name: Vec<&str> = words
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != index)
+                .map(|(_, word)| *word)
+                .collect();
+            return (clean_name(&name.join(" ")), Some(address.to_lowercase()));
+        }
+    }
+
+    (clean_name(raw), None)
+}
+
+/// `local@domain` 形式且不含空白
+fn is_bare_address(value: &str) -> bool {
+    match value.rsplit_once('@') {
+        Some((local, domain)) => !local.is_empty() && !domain.is_empty() && !value.contains(char::is_whitespace),
+        None => false,
+    }
+}
+
+/// 去掉本地部分的 "+标签"（"me+news@example.com" → "me@example.com"）
+pub fn strip_plus_tag(address: &str) -> String {
+    match address.rsplit_once('@') {
+        Some((local, domain)) => match local.split_once('+') {
+            Some((base, _)) if !base.is_empty() => format!("{}@{}", base, domain),
+            _ => address.to_string(),
+        },
+        None => address.to_string(),
+    }
+}
+
+/// 是否为自己的地址（含 "+标签" 变体）
+pub fn is_my_address(address: &str, mine: &[String]) -> bool {
+    mine.iter().any(|own| own == address) || mine.contains(&strip_plus_tag(address))
+}
+
+fn is_mine(entry: &str, mine: &[String]) -> bool {
+    is_my_address(&address_of(entry), mine)
+}
+
+/// 追加收件人：跳过自己、空地址以及已在 `list` 或 `exclude` 中的地址（按合并后的主联系人比较）
+fn push_unique(
+    list: &mut Vec<String>,
+    entry: &str,
+    mine: &[String],
+    exclude: &[String],
+    aliases: &HashMap<String, CanonicalContact>,
+) {
+    let address = address_of(entry);
+    if address.is_empty() || !address.contains('@') || is_my_address(&address, mine) {
+        return;
+    }
+    let canonical = |address: String| match aliases.get(&address) {
+        Some(contact) => contact.address.clone(),
+        None => address,
+    };
+    let key = canonical(address);
+    let seen = |existing: &String| canonical(address_of(existing)) == key;
+    if list.iter().any(seen) || exclude.iter().any(seen) {
+        return;
+    }
+    list.push(entry.trim().to_string());
+}
@@ -0,0 +1,170 @@
+/// 服务器端搜索（查找尚未同步到本地的邮件）
+///
+/// 查询语法：`from:alice subject:预算 关键词`，
+/// `from:` / `subject:` 之外的词作为 TEXT 条件，日期范围单独传入（YYYY-MM-DD）。
+use crate::error::AppError;
+use crate::mail::imap_client::{ImapConnection, RemoteEnvelope};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 单次搜索返回的最大结果数（取最新的）
+const REMOTE_SEARCH_LIMIT: usize = 50;
+
+/// 结构化的服务器端查询
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteSearchQuery {
+    pub text: Vec<String>,
+    pub from: Option<String>,
+    pub subject: Option<String>,
+    pub since: Option<NaiveDate>,
+    pub before: Option<NaiveDate>,
+}
+
+impl RemoteSearchQuery {
+    /// 解析查询字符串和日期范围
+    pub fn parse(query: &str, since: Option<&str>, before: Option<&str>) -> Result<Self, AppError> {
+        let mut parsed = Self {
+            since: since.map(parse_date).transpose()?,
+            before: before.map(parse_date).transpose()?,
+            ..Default::default()
+        };
+
+        for token in query.split_whitespace() {
+            if let Some(value) = token.strip_prefix("from:").filter(|v| !v.is_empty()) {
+                parsed.from = Some(value.to_string());
+            } else if let Some(value) = token.strip_prefix("subject:").filter(|v| !v.is_empty()) {
+                parsed.subject = Some(value.to_string());
+            } else {
+                parsed.text.push(token.to_string());
+            }
+        }
+
+        if parsed.text.is_empty()
+            && parsed.from.is_none()
+            && parsed.subject.is_none()
+            && parsed.since.is_none()
+            && parsed.before.is_none()
+        {
+            return Err(AppError::Validation("Search query is empty".to_string()));
+        }
+
+        Ok(parsed)
+    }
+
+    /// 是否仅包含 ASCII 字符（可以使用服务器默认的 US-ASCII 字符集）
+    pub fn is_ascii(&self) -> bool {
+        self.text.iter().all(|t| t.is_ascii())
+            && self.from.as_deref().map_or(true, str::is_ascii)
+            && self.subject.as_deref().map_or(true, str::is_ascii)
+    }
+
+    /// 构建 IMAP SEARCH 条件
+    pub fn to_imap_criteria(&self) -> String {
+        let mut criteria = Vec::new();
+
+        if !self.text.is_empty() {
+            criteria.push(format!("TEXT {}", quote(&self.text.join(" "))));
+        }
+        if let Some(from) = &self.from {
+            criteria.push(format!("FROM {}", quote(from)));
+        }
+        if let Some(subject) = &self.subject {
+            criteria.push(format!("SUBJECT {}", quote(subject)));
+        }
+        if let Some(since) = self.since {
+            criteria.push(format!("SINCE {}", since.format("%d-%b-%Y")));
+        }
+        if let Some(before) = self.before {
+            criteria.push(format!("BEFORE {}", before.format("%d-%b-%Y")));
+        }
+
+        criteria.join(" ")
+    }
+}
+
+/// 服务器端搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteEmailPreview {
+    pub uid: u32,
+    pub message_id: Option<String>,
+    pub subject: Option<String>,
+    pub sender: Option<String>,
+    pub date: Option<String>,
+    pub size: Option<u32>,
+    /// 始终为 true，用于前端区分本地邮件
+    pub remote: bool,
+    /// 本地是否已有该邮件
+    pub already_synced: bool,
+}
+
+/// 在 INBOX 中执行服务器端搜索（使用连接池中的会话，不登出）
+pub async fn search_remote(
+    pool: &SqlitePool,
+    account_id: i64,
+    conn: &mut ImapConnection,
+    query: &RemoteSearchQuery,
+) -> Result<Vec<RemoteEmailPreview>, AppError> {
+    conn.select_folder("INBOX").await?;
+
+    // 非 ASCII 查询需要 UTF-8 字符集，服务器不支持时 uid_search 返回明确的错误
+    let charset = if query.is_ascii() { None } else { Some("UTF-8") };
+    let criteria = query.to_imap_criteria();
+    let result = conn.uid_search(&criteria, charset).await;
+
+    let envelopes = match result {
+        Ok(mut uids) => {
+            log::info!("Remote search matched {} messages", uids.len());
+            if uids.len() > REMOTE_SEARCH_LIMIT {
+                uids.drain(..uids.len() - REMOTE_SEARCH_LIMIT);
+            }
+            conn.fetch_envelopes(&uids).await
+        }
+        Err(e) => Err(e),
+    };
+
+    let mut envelopes = envelopes?;
+    envelopes.sort_by(|a, b| b.uid.cmp(&a.uid));
+
+    let mut previews = Vec::with_capacity(envelopes.len());
+    for envelope in envelopes {
+        let already_synced = is_synced(pool, account_id, &envelope).await?;
+        previews.push(RemoteEmailPreview {
+            uid: envelope.uid,
+            message_id: envelope.message_id,
+            subject: envelope.subject,
+            sender: envelope.from,
+            date: envelope.date,
+            size: envelope.size,
+            remote: true,
+            already_synced,
+        });
+    }
+
+    Ok(previews)
+}
+
+/// 本地是否已有该邮件（按 UID 或 Message-ID）
+async fn is_synced(pool: &SqlitePool, account_id: i64, envelope: &RemoteEnvelope) -> Result<bool, AppError> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM emails WHERE account_id = ? AND (raw_path = ? OR message_id = ?)"
+    )
+    .bind(account_id)
+    .bind(envelope.uid.to_string())
+    .bind(envelope.message_id.as_deref().map(|id| id.trim_matches(|c| c == '<' || c == '>')))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count > 0)
+}
+
+/// 解析 YYYY-MM-DD 日期
+fn parse_date(value: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| AppError::Validation(format!("Invalid date {}, expected YYYY-MM-DD", value)))
+}
+
+/// IMAP 字符串引用
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
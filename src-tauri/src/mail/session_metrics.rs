@@ -0,0 +1,98 @@
+/// IMAP 会话指标
+///
+/// 用于调整批大小和限速：每次同步统计下载字节数、获取的邮件数、平均获取延迟、重新连接次数、
+/// 限速等待次数和解析失败数，结束时以紧凑 JSON 写入 `sync_runs.metrics` 并输出一行日志。
+/// 计数器是原子变量，由 `ImapConnection` 和限速流共享；累加时饱和而不是溢出。
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Default)]
+struct Counters {
+    bytes_downloaded: AtomicU64,
+    messages_fetched: AtomicU64,
+    fetch_requests: AtomicU64,
+    fetch_micros: AtomicU64,
+    connects: AtomicU64,
+    throttle_sleeps: AtomicU64,
+    throttle_sleep_micros: AtomicU64,
+    parse_failures: AtomicU64,
+}
+
+/// 会话指标收集器（可在多个连接间共享）
+#[derive(Debug, Clone, Default)]
+pub struct SessionMetrics(Arc<Counters>);
+
+impl SessionMetrics {
+    pub fn record_download(&self, bytes: u64) {
+        saturating_add(&self.0.bytes_downloaded, bytes);
+    }
+
+    /// 一次 FETCH 请求获取了 `messages` 封邮件，耗时 `elapsed`
+    pub fn record_fetch(&self, messages: usize, elapsed: Duration) {
+        saturating_add(&self.0.messages_fetched, messages as u64);
+        saturating_add(&self.0.fetch_requests, 1);
+        saturating_add(&self.0.fetch_micros, elapsed.as_micros().min(u64::MAX as u128) as u64);
+    }
+
+    pub fn record_connect(&self) {
+        saturating_add(&self.0.connects, 1);
+    }
+
+    pub fn record_throttle_sleep(&self, duration: Duration) {
+        saturating_add(&self.0.throttle_sleeps, 1);
+        saturating_add(&self.0.throttle_sleep_micros, duration.as_micros().min(u64::MAX as u128) as u64);
+    }
+
+    pub fn record_parse_failure(&self) {
+        saturating_add(&self.0.parse_failures, 1);
+    }
+
+    /// 当前计数的快照
+    pub fn snapshot(&self) -> SyncMetrics {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let fetch_requests = load(&self.0.fetch_requests);
+        let avg_fetch_ms = if fetch_requests == 0 {
+            0.0
+        } else {
+            load(&self.0.fetch_micros) as f64 / fetch_requests as f64 / 1000.0
+        };
+
+        SyncMetrics {
+            bytes_downloaded: load(&self.0.bytes_downloaded),
+            messages_fetched: load(&self.0.messages_fetched),
+            fetch_requests,
+            avg_fetch_ms: (avg_fetch_ms * 10.0).round() / 10.0,
+            reconnects: load(&self.0.connects).saturating_sub(1),
+            throttle_sleeps: load(&self.0.throttle_sleeps),
+            throttle_sleep_ms: load(&self.0.throttle_sleep_micros) / 1000,
+            parse_failures: load(&self.0.parse_failures),
+        }
+    }
+}
+
+/// 单次同步的指标（写入 `sync_runs.metrics`）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncMetrics {
+    pub bytes_downloaded: u64,
+    pub messages_fetched: u64,
+    pub fetch_requests: u64,
+    /// 每次 FETCH 请求的平均耗时（毫秒）
+    pub avg_fetch_ms: f64,
+    /// 首次连接之后的重新连接次数
+    pub reconnects: u64,
+    pub throttle_sleeps: u64,
+    pub throttle_sleep_ms: u64,
+    pub parse_failures: u64,
+}
+
+/// 饱和累加（计数达到上限后保持不变）
+fn saturating_add(counter: &AtomicU64, value: u64) {
+    if value == 0 {
+        return;
+    }
+    let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+        (current != u64::MAX).then(|| current.saturating_add(value))
+    });
+}
@@ -0,0 +1,197 @@
+/// 跨项目的智能视图（星标、带附件、未读、待回复、最近）
+///
+/// 每种视图只是 `SMART_VIEWS` 中的一个条件片段，共用同一个查询：关联项目名称，排除自动邮件、
+/// 被折叠的重复邮件、服务器上已删除的邮件和 Gmail 垃圾邮件，可限定账户、只看 VIP 发件人。按日期倒序用游标分页（`julianday:id`），
+/// 翻页期间新同步的邮件不会让后面的页重复或跳过。新增视图只需在表中加一项。
+use crate::commands::mail::EmailPreview;
+use crate::error::AppError;
+use crate::mail::vip::VIP_ADDRESSES_SQL;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 每页默认条数
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+
+/// 每页最多条数
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+/// 智能视图定义
+pub struct SmartViewDefinition {
+    pub kind: &'static str,
+    /// WHERE 条件片段（邮件表别名为 `e`，不含参数）
+    condition: &'static str,
+}
+
+/// 已注册的智能视图
+pub const SMART_VIEWS: &[SmartViewDefinition] = &[
+    SmartViewDefinition {
+        kind: "starred",
+        condition: "e.is_starred = 1",
+    },
+    SmartViewDefinition {
+        kind: "with_attachments",
+        condition: "e.has_attachments = 1",
+    },
+    SmartViewDefinition {
+        kind: "unread",
+        condition: "COALESCE(e.is_read, 0) = 0 AND COALESCE(e.direction, 'incoming') = 'incoming'",
+    },
+    // 线程中最后一封是别人发来的（自己不只是抄送），之后自己没有回复
+    SmartViewDefinition {
+        kind: "awaiting_reply",
+        condition: r#"
+            COALESCE(e.direction, 'incoming') = 'incoming'
+            AND COALESCE(e.is_cc_only, 0) = 0
+            AND e.thread_id IS NOT NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM emails later
+                WHERE later.account_id = e.account_id AND later.thread_id = e.thread_id
+                  AND later.id != e.id AND julianday(later.date) >= julianday(e.date)
+            )
+        "#,
+    },
+    SmartViewDefinition {
+        kind: "recent",
+        condition: "julianday(e.date) >= julianday('now', '-7 days')",
+    },
+];
+
+/// 查找视图定义
+pub fn find_view(kind: &str) -> Option<&'static SmartViewDefinition> {
+    SMART_VIEWS.iter().find(|view| view.kind == kind)
+}
+
+/// 视图中的一封邮件（邮件预览 + 所属项目）
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SmartViewEmail {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub email: EmailPreview,
+    pub project_id: Option<i64>,
+    pub project_name: Option<String>,
+    #[serde(skip)]
+    sort_key: f64,
+}
+
+/// 一页结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SmartViewPage {
+    pub kind: String,
+    pub emails: Vec<SmartViewEmail>,
+    /// 视图中的邮件总数（不受分页影响）
+    pub total: i64,
+    /// 下一页的游标，没有更多时为空
+    pub next_cursor: Option<String>,
+}
+
+/// 游标：上一页最后一封邮件的排序键
+struct Cursor {
+    sort_key: f64,
+    id: i64,
+}
+
+impl Cursor {
+    fn parse(value: &str) -> Result<Self, AppError> {
+        value
+            .split_once(':')
+            .and_then(|(sort_key, id)| Some(Self { sort_key: sort_key.parse().ok()?, id: id.parse().ok()? }))
+            .ok_or_else(|| AppError::Validation(format!("Invalid cursor: {}", value)))
+    }
+
+    fn encode(&self) -> String {
+        format!("{}:{}", self.sort_key, self.id)
+    }
+}
+
+/// 智能视图查询
+pub struct SmartViews {
+    pool: SqlitePool,
+}
+
+impl SmartViews {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 读取一页，`account_id` 为空时包含所有账户，`vip_only` 时只包含 VIP 发件人的邮件
+    pub async fn page(
+        &self,
+        kind: &str,
+        account_id: Option<i64>,
+        limit: Option<i64>,
+        cursor: Option<&str>,
+        vip_only: bool,
+    ) -> Result<SmartViewPage, AppError> {
+        let view = find_view(kind).ok_or_else(|| AppError::Validation(format!("Unknown smart view: {}", kind)))?;
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let cursor = cursor.filter(|cursor| !cursor.is_empty()).map(Cursor::parse).transpose()?;
+
+        let show_duplicates: bool = sqlx::query_scalar("SELECT show_duplicates FROM sync_settings WHERE id = 1")
+            .fetch_one(&self.pool)
+            .await
+            .unwrap_or(false);
+        let filter = format!(
+            r#"
+            ({})
+            AND (?1 IS NULL OR e.account_id = ?1)
+            AND COALESCE(e.is_automated, 0) = 0
+            AND (?2 OR e.duplicate_of IS NULL)
+            AND e.server_deleted_at IS NULL
+            AND (e.gmail_labels IS NULL OR e.gmail_labels NOT LIKE '%"\\Spam"%')
+            AND (NOT ?3 OR e.sender_address IN ({}))
+            "#,
+            view.condition, VIP_ADDRESSES_SQL
+        );
+
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM emails e WHERE {}", filter))
+            .bind(account_id)
+            .bind(show_duplicates)
+            .bind(vip_only)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let mut emails = sqlx::query_as::<_, SmartViewEmail>(&format!(
+            r#"
+            SELECT * FROM (
+                SELECT
+                    e.id, e.account_id, e.subject, e.sender, e.date,
+                    e.body_text, e.preview, COALESCE(e.is_read, 0) AS is_read, COALESCE(e.has_attachments, 0) AS has_attachments,
+                    (SELECT COUNT(*) FROM emails d WHERE d.duplicate_of = e.id) AS duplicate_count,
+                    COALESCE(e.is_cc_only, 0) AS is_cc_only,
+                    COALESCE(e.sender_address IN ({}), 0) AS is_vip,
+                    e.project_id, p.name AS project_name,
+                    COALESCE(julianday(e.date), 0.0) AS sort_key
+                FROM emails e
+                LEFT JOIN projects p ON p.id = e.project_id
+                WHERE {}
+            )
+            WHERE ?4 IS NULL OR sort_key < ?4 OR (sort_key = ?4 AND id < ?5)
+            ORDER BY sort_key DESC, id DESC
+            LIMIT ?6
+            "#,
+            VIP_ADDRESSES_SQL, filter
+        ))
+        .bind(account_id)
+        .bind(show_duplicates)
+        .bind(vip_only)
+        .bind(cursor.as_ref().map(|cursor| cursor.sort_key))
+        .bind(cursor.as_ref().map(|cursor| cursor.id))
+        .bind(limit + 1)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let next_cursor = if emails.len() as i64 > limit {
+            emails.truncate(limit as usize);
+            emails.last().map(|last| Cursor { sort_key: last.sort_key, id: last.email.id }.encode())
+        } else {
+            None
+        };
+
+        Ok(SmartViewPage {
+            kind: view.kind.to_string(),
+            emails,
+            total,
+            next_cursor,
+        })
+    }
+}
@@ -1,11 +1,91 @@
 /// 邮件同步模块
 use crate::error::AppError;
-use crate::events::{EventEmitter, SyncProgressEvent, SyncStatus};
-use crate::mail::imap_client::{AuthMethod, ImapConnection};
-use crate::mail::parser::{parse_email, generate_thread_id, ParsedEmail};
+use crate::events::notifications::SOURCE_SYNC;
+use crate::events::{EventEmitter, NewEmailsEvent, NotificationLevel, SyncProgressEvent, SyncStatus};
+use crate::mail::attachment_writer::AttachmentWriter;
+use crate::mail::automated::{AutomatedDetector, AutomatedHeaders};
+use crate::mail::contacts::ContactBook;
+use crate::mail::dedup::{content_fingerprint, DuplicateDetector};
+use crate::mail::deletions::{delete_email_rows, ServerDeletions};
+use crate::mail::folders::{resolve_special_folder, should_sync_folder, FolderStore, SpecialUse};
+use crate::mail::imap_client::{AuthMethod, GmailMetadata, ImapConnection, RemoteEnvelope};
+use crate::mail::imap_trace::ImapTrace;
+use crate::mail::language::detect_language;
+use crate::mail::namespaces::{self, NamespaceKind};
+use crate::mail::parser::{parse_email_for_account, generate_thread_id, ParsedEmail};
 use crate::mail::providers::ProviderConfig;
+use crate::mail::quota::QuotaStore;
+use crate::mail::calendar::CalendarStore;
+use crate::mail::receipts::ReceiptStore;
+use crate::mail::recipients::{is_cc_only, is_my_address, my_addresses};
+use crate::mail::session_metrics::SessionMetrics;
+use crate::mail::sync_checkpoint::SyncCheckpointStore;
+use crate::mail::sync_runs::SyncRunLog;
+use crate::mail::vip::VipList;
+use crate::mail::throttle::{NetworkPolicy, TransferCounter};
+use crate::project::classification_log::{ClassificationLog, CLASSIFICATION_LOG_RETENTION_DAYS};
+use crate::project::classifier::ProjectClassifier;
+use crate::storage::body_store::{self, BodyStore};
+use crate::storage::disk_space;
+use crate::storage::file_manager;
+use crate::storage::quarantine::{AttachmentQuarantine, RemovedAttachment, REMOVED_ATTACHMENT_COLUMNS};
+use crate::storage::remote_content::RemoteContentCache;
+use crate::utils::preview::preview_for;
 use sqlx::SqlitePool;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 按流量计费模式下每批获取的邮件头数量
+const HEADER_BATCH_SIZE: usize = 50;
+
+/// 全量下载时每处理多少封邮件推进一次检查点（推进前等待这些邮件的附件写完）
+const CHECKPOINT_BATCH_SIZE: usize = 20;
+
+/// 已发送文件夹补充时检查的最近活跃线程数
+const SENT_PASS_MAX_THREADS: i64 = 50;
+
+/// 已发送文件夹中邮件的 raw_path 前缀（避免与收件箱 UID 混淆）
+const SENT_RAW_PATH_PREFIX: &str = "sent:";
+
+/// 每次同步刷新 Gmail 标签的最近邮件数
+const GMAIL_LABEL_REFRESH_LIMIT: i64 = 500;
+
+/// 首次同步估算邮件大小时抽样的邮件数
+const FIRST_SYNC_SIZE_SAMPLE: usize = 50;
+
+/// 服务器没有返回 RFC822.SIZE 时假定的平均邮件大小
+const DEFAULT_MESSAGE_SIZE: u64 = 75 * 1024;
+
+/// 共享命名空间中邮件的 raw_path 前缀（`shared:{文件夹}:{UID}`，不会被当作收件箱 UID）
+const SHARED_RAW_PATH_PREFIX: &str = "shared:";
+
+/// 邮件方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailDirection {
+    /// 收件箱中收到的邮件
+    Incoming,
+    /// 已发送文件夹中自己发出的邮件
+    Outgoing,
+}
+
+impl MailDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MailDirection::Incoming => "incoming",
+            MailDirection::Outgoing => "outgoing",
+        }
+    }
+
+    /// 存入 raw_path 的 UID（已发送文件夹的 UID 加前缀）
+    fn raw_path(&self, uid: u32) -> String {
+        match self {
+            MailDirection::Incoming => uid.to_string(),
+            MailDirection::Outgoing => format!("{}{}", SENT_RAW_PATH_PREFIX, uid),
+        }
+    }
+}
 
 /// 邮件账户
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,41 +105,110 @@ pub struct SyncProgress {
     pub status: String,
 }
 
+/// 正在进行中的同步（按账户 ID 记录）
+///
+/// 注册为全局状态，所有窗口共享。用于防止同一账户并发同步，
+/// 以及在关闭最后一个窗口时保持后台同步继续运行。
+/// 同时记录托盘中的"暂停同步"状态和退出流程（退出时不再开始新的同步）。
+#[derive(Debug, Clone, Default)]
+pub struct ActiveSyncs {
+    accounts: Arc<Mutex<HashSet<i64>>>,
+    paused: Arc<AtomicBool>,
+    shutting_down: Arc<AtomicBool>,
+}
+
+impl ActiveSyncs {
+    /// 标记账户开始同步，如果该账户已在同步中返回 false
+    pub fn begin(&self, account_id: i64) -> bool {
+        self.accounts.lock().unwrap().insert(account_id)
+    }
+
+    /// 标记账户同步结束
+    pub fn finish(&self, account_id: i64) {
+        self.accounts.lock().unwrap().remove(&account_id);
+    }
+
+    /// 账户是否正在同步
+    pub fn is_syncing(&self, account_id: i64) -> bool {
+        self.accounts.lock().unwrap().contains(&account_id)
+    }
+
+    /// 是否有任何同步在进行
+    pub fn any_active(&self) -> bool {
+        !self.accounts.lock().unwrap().is_empty()
+    }
+
+    /// 暂停或恢复自动同步
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// 自动同步是否已暂停
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// 进入退出流程，之后不再开始新的同步
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+}
+
+/// 账户重置结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResetSummary {
+    pub account_id: i64,
+    pub deleted_emails: u64,
+    pub deleted_attachments: u64,
+    pub deleted_projects: u64,
+}
+
+/// 单次同步连接上的统计和跟踪
+struct SessionInstruments {
+    /// 同步运行记录 ID（记录失败时为 None）
+    run_id: Option<i64>,
+    counter: TransferCounter,
+    trace: Option<ImapTrace>,
+    metrics: SessionMetrics,
+}
+
+/// 单封邮件的处理结果
+pub enum ProcessedMessage {
+    /// 新保存的邮件
+    Saved(i64),
+    /// 已保存过的邮件（Gmail 中其他文件夹的同一封邮件只记录所在文件夹，导入的文件按 Message-ID 去重）
+    Linked(i64),
+    /// 已读回执或投递状态通知
+    Receipt,
+}
+
 /// 邮件同步器
 pub struct EmailSyncer {
     pool: SqlitePool,
-    event_emitter: Option<EventEmitter>,
+    event_emitter: EventEmitter,
 }
 
 impl EmailSyncer {
-    pub fn new(pool: SqlitePool) -> Self {
-        Self {
-            pool,
-            event_emitter: None,
-        }
-    }
-
-    pub fn with_event_emitter(pool: SqlitePool, emitter: EventEmitter) -> Self {
-        Self {
-            pool,
-            event_emitter: Some(emitter),
-        }
+    pub fn new(pool: SqlitePool, event_emitter: EventEmitter) -> Self {
+        Self { pool, event_emitter }
     }
 
     /// 发送同步进度事件
     fn emit_progress(&self, account_id: i64, current: usize, total: usize, status: SyncStatus) {
-        if let Some(emitter) = &self.event_emitter {
-            emitter.emit_sync_progress(SyncProgressEvent {
-                account_id,
-                current,
-                total,
-                status,
-            });
-        }
+        self.event_emitter.emit_sync_progress(SyncProgressEvent {
+            account_id,
+            current,
+            total,
+            status,
+        });
     }
 
     /// 从数据库读取最大同步数量配置
-    async fn get_max_sync_count(&self) -> Result<usize, AppError> {
+    pub(crate) async fn get_max_sync_count(&self) -> Result<usize, AppError> {
         let result: (i64,) = sqlx::query_as(
             "SELECT max_sync_count FROM sync_settings WHERE id = 1"
         )
@@ -90,8 +239,18 @@ impl EmailSyncer {
         Ok(result.last_insert_rowid())
     }
 
+    /// 是否开启了 IMAP 协议跟踪
+    async fn trace_enabled(&self) -> bool {
+        sqlx::query_scalar::<_, bool>("SELECT imap_trace_enabled FROM sync_settings WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or(false)
+    }
+
     /// 获取账户的最后同步 UID
-    async fn get_last_synced_uid(&self, account_id: i64) -> Result<u32, AppError> {
+    pub(crate) async fn get_last_synced_uid(&self, account_id: i64) -> Result<u32, AppError> {
         let result: Option<(i64,)> = sqlx::query_as(
             "SELECT MAX(CAST(raw_path AS INTEGER)) FROM emails WHERE account_id = ? AND raw_path GLOB '[0-9]*'"
         )
@@ -103,16 +262,99 @@ impl EmailSyncer {
     }
 
     /// 同步单个账户的邮件
+    ///
+    /// 按网络策略限速；按流量计费模式下只同步邮件头（正文由后台补全）。
+    /// 每次同步写入同步记录，包括传输的字节数；开启协议跟踪时同步记录指向跟踪文件。
     pub async fn sync_account(
         &self,
         account_id: i64,
         auth: AuthMethod,
         provider: &ProviderConfig,
     ) -> Result<SyncProgress, AppError> {
-        log::info!("Starting sync for account {}", account_id);
+        let policy = NetworkPolicy::load(&self.pool).await;
+        let counter = TransferCounter::default();
+        let run_log = SyncRunLog::new(self.pool.clone());
+        let run_id = match run_log.start(account_id, policy.metered).await {
+            Ok(id) => Some(id),
+            Err(e) => {
+                log::warn!("Failed to record sync run for account {}: {}", account_id, e);
+                None
+            }
+        };
+
+        let trace = match run_id {
+            Some(run_id) if self.trace_enabled().await => match ImapTrace::create(&format!("sync-run-{}", run_id)) {
+                Ok(trace) => {
+                    if let Err(e) = run_log.set_trace_path(run_id, &trace.path().to_string_lossy()).await {
+                        log::warn!("Failed to record trace path for sync run {}: {}", run_id, e);
+                    }
+                    Some(trace)
+                }
+                Err(e) => {
+                    log::warn!("Failed to create IMAP trace for sync run {}: {}", run_id, e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        let metrics = SessionMetrics::default();
+        let session = SessionInstruments {
+            run_id,
+            counter: counter.clone(),
+            trace,
+            metrics: metrics.clone(),
+        };
+        let result = self.run_sync(account_id, auth, provider, policy, session).await;
+
+        let snapshot = metrics.snapshot();
+        if let Ok(json) = serde_json::to_string(&snapshot) {
+            log::info!("Sync metrics for account {}: {}", account_id, json);
+        }
+        if let Some(run_id) = run_id {
+            let (synced, error) = match &result {
+                Ok(progress) => (progress.current, None),
+                Err(e) => (0, Some(e.to_string())),
+            };
+            if let Err(e) = run_log.finish(run_id, synced, counter.total(), error.as_deref(), Some(&snapshot)).await {
+                log::warn!("Failed to finish sync run {}: {}", run_id, e);
+            }
+        }
+
+        result
+    }
+
+    async fn run_sync(
+        &self,
+        account_id: i64,
+        auth: AuthMethod,
+        provider: &ProviderConfig,
+        policy: NetworkPolicy,
+        session: SessionInstruments,
+    ) -> Result<SyncProgress, AppError> {
+        log::info!("Starting sync for account {} ({:?})", account_id, policy);
+        let run_id = session.run_id;
 
         // 1. 连接到 IMAP 服务器
-        let mut conn = ImapConnection::connect_with_provider(provider, auth).await?;
+        let mut conn = ImapConnection::connect_with_provider_traced(
+            provider,
+            auth,
+            policy.bytes_per_sec,
+            session.counter,
+            session.trace,
+            session.metrics,
+        )
+        .await?;
+
+        // 记录服务器的命名空间（共享邮箱前缀）
+        if let Err(e) = namespaces::save(&self.pool, account_id, conn.namespaces()).await {
+            log::warn!("Failed to save namespaces for account {}: {}", account_id, e);
+        }
+
+        // 邮箱配额接近上限时提醒（APPEND 和同步会开始失败）
+        if let Err(e) = self.check_quota(&mut conn, account_id).await {
+            log::warn!("Failed to check mailbox quota for account {}: {}", account_id, e);
+        }
 
         // 2. 选择收件箱
         let total = conn.select_folder("INBOX").await? as usize;
@@ -124,42 +366,46 @@ impl EmailSyncer {
 
         // 4. 从数据库读取同步配置
         let max_sync_count = self.get_max_sync_count().await.unwrap_or(100);
-        let sync_all = max_sync_count >= 999999; // 999999 表示同步全部
-
-        // 5. 获取需要同步的 UID 列表
-        let uids = if last_uid == 0 {
-            // 首次同步：获取全部 UID 后截取最新的
-            log::info!("First sync. Fetching ALL UIDs to find the latest...");
-            let mut all_uids = conn.fetch_uids("1:*").await?;
-            all_uids.sort_unstable(); // 保证有序
-            
-            if !sync_all && all_uids.len() > max_sync_count {
-                // 只取最新的 max_sync_count 个 UID
-                log::info!("Sync mode: Latest {} emails out of {}", max_sync_count, all_uids.len());
-                let start_idx = all_uids.len() - max_sync_count;
-                all_uids[start_idx..].to_vec()
-            } else {
-                log::info!("Sync mode: ALL emails ({})", all_uids.len());
-                all_uids
+
+        // 5. 获取需要同步的 UID 列表：有未完成的首次同步时只取检查点范围内剩余的 UID
+        let checkpoints = SyncCheckpointStore::new(self.pool.clone());
+        let checkpoint = checkpoints.load(account_id).await?;
+        let uids = match &checkpoint {
+            Some(checkpoint) => {
+                let mut uids = match checkpoint.remaining_range() {
+                    Some(range) => conn.fetch_uids(&range).await?,
+                    None => Vec::new(),
+                };
+                uids.sort_unstable();
+                uids.dedup();
+                // "N:M" 在范围内没有邮件时也可能返回边界外的邮件
+                uids.retain(|&uid| {
+                    (uid as i64) > checkpoint.last_completed_uid && (uid as i64) <= checkpoint.last_uid
+                });
+                log::info!(
+                    "Resuming first sync for account {}: {}/{} done, {} remaining",
+                    account_id, checkpoint.processed(), checkpoint.total, uids.len()
+                );
+                uids
             }
-        } else {
-            // 增量同步：获取上次同步后的新邮件
-            log::info!("Sync mode: Incremental from UID {}", last_uid + 1);
-            let range = format!("{}:*", last_uid + 1);
-            let mut new_uids = conn.fetch_uids(&range).await?;
-            new_uids.sort_unstable();
-
-            // 过滤掉因为 "N:*" 语法可能带出的旧邮件（如果最高UID没变，也会返回上次的最后一条）
-            new_uids.retain(|&uid| uid > last_uid);
-            
-            if !sync_all && new_uids.len() > max_sync_count {
-                log::info!("Incremental sync found {} emails. Limiting to newest {}.", new_uids.len(), max_sync_count);
-                let start_idx = new_uids.len() - max_sync_count;
-                new_uids[start_idx..].to_vec()
-            } else {
-                new_uids
+            None => {
+                let uids = pending_uids(&mut conn, total, last_uid, max_sync_count).await?;
+                if last_uid == 0 {
+                    // 首次同步前确认磁盘能放下预计下载的邮件（按流量计费模式只保存邮件头，不检查）
+                    if !policy.metered {
+                        ensure_first_sync_space(&mut conn, &uids).await?;
+                    }
+                    checkpoints.start(account_id, &uids).await?;
+                }
+                uids
             }
         };
+        let resumable = checkpoint.is_some() || (last_uid == 0 && !uids.is_empty());
+        // 进度按首次同步的原始总数计算
+        let (progress_offset, progress_total) = match &checkpoint {
+            Some(checkpoint) => (checkpoint.processed(), checkpoint.processed() + uids.len()),
+            None => (0, uids.len()),
+        };
 
         log::info!("Found {} new messages to process", uids.len());
 
@@ -172,61 +418,56 @@ impl EmailSyncer {
         let uids_to_sync = uids;
         log::info!("Syncing {} messages", uids_to_sync.len());
 
-        // 5. 下载并保存邮件
+        // 分类器设置每次同步读取一次
+        let classifier = ProjectClassifier::load(self.pool.clone())
+            .await
+            .with_event_emitter(self.event_emitter.clone());
+
+        // 5. 下载并保存邮件（按流量计费模式只保存邮件头，正文由后台补全）
+        if policy.metered {
+            let header_checkpoints = resumable.then_some(&checkpoints);
+            let progress = (progress_offset, progress_total);
+            self.sync_headers(&mut conn, account_id, &uids_to_sync, &classifier, header_checkpoints, progress).await?;
+        }
+        let full_uids: &[u32] = if policy.metered { &[] } else { &uids_to_sync };
+
+        // 附件在后台写入，与下一封邮件的下载重叠
+        let mut attachments = AttachmentWriter::new(self.pool.clone());
         let mut current = 0;
-        for uid in &uids_to_sync {
+        let mut new_email_ids = Vec::new();
+        // 已处理但尚未写入检查点的邮件数（成功, 失败）
+        let mut unrecorded = (0, 0);
+        for uid in full_uids {
+            // 限速连接上定期保活
+            if let Err(e) = conn.keepalive().await {
+                log::warn!("IMAP keepalive failed: {}", e);
+            }
+
             current += 1;
 
-            log::info!("Fetching email {}/{} (UID: {})", current, uids_to_sync.len(), uid);
+            log::info!("Fetching email {}/{} (UID: {})", progress_offset + current, progress_total, uid);
 
             // 发送进度事件
-            self.emit_progress(account_id, current, uids_to_sync.len(), SyncStatus::Syncing);
-
-            // 使用 match 捕获所有错误并记录详细信息
-            let result = async {
-                // 下载邮件
-                log::debug!("Downloading email UID {}", uid);
-                let raw_data = conn.fetch_email(*uid).await
-                    .map_err(|e| AppError::Generic(format!("Failed to download email UID {}: {}", uid, e)))?;
-                log::debug!("Downloaded {} bytes for UID {}", raw_data.len(), uid);
-
-                // 解析邮件
-                log::debug!("Parsing email UID {}", uid);
-                let parsed = parse_email(&raw_data)
-                    .map_err(|e| AppError::Generic(format!("Failed to parse email UID {}: {}", uid, e)))?;
-                log::debug!("Parsed email UID {}, subject: {:?}", uid, parsed.subject);
-
-                // 保存到数据库
-                log::debug!("Saving email UID {} to database", uid);
-                self.save_email(account_id, *uid, &parsed).await
-                    .map_err(|e| AppError::Generic(format!("Failed to save email UID {}: {}", uid, e)))?;
-
-                // 获取刚保存的邮件 ID
-                log::debug!("Getting email ID for message_id: {}", parsed.message_id);
-                let email_id = self.get_email_id_by_message_id(&parsed.message_id, account_id).await
-                    .map_err(|e| AppError::Generic(format!("Failed to get email ID for UID {}: {}", uid, e)))?;
-
-                // 自动分类到项目
-                log::debug!("Classifying email {}", email_id);
-                let classifier = crate::project::classifier::ProjectClassifier::new(self.pool.clone());
-                if let Err(e) = classifier.classify_email(email_id).await {
-                    log::warn!("Failed to classify email {}: {}", email_id, e);
-                }
-
-                // 保存附件
-                log::debug!("Saving {} attachments for email {}", parsed.attachments.len(), email_id);
-                for (idx, attachment) in parsed.attachments.iter().enumerate() {
-                    self.save_attachment(account_id, &parsed.message_id, idx, attachment).await
-                        .map_err(|e| AppError::Generic(format!("Failed to save attachment {} for UID {}: {}", idx, uid, e)))?;
-                }
+            self.emit_progress(account_id, progress_offset + current, progress_total, SyncStatus::Syncing);
 
-                Ok::<(), AppError>(())
-            }.await;
+            let result = self
+                .process_message(&mut conn, account_id, *uid, MailDirection::Incoming, &classifier, &mut attachments)
+                .await;
 
             // 处理错误
-            match result {
-                Ok(_) => {
+            let succeeded = match result {
+                Ok(ProcessedMessage::Saved(email_id)) => {
                     log::info!("Successfully processed email UID {}", uid);
+                    new_email_ids.push(email_id);
+                    true
+                }
+                Ok(ProcessedMessage::Linked(email_id)) => {
+                    log::info!("Email UID {} is already stored as email {}", uid, email_id);
+                    true
+                }
+                Ok(ProcessedMessage::Receipt) => {
+                    log::info!("Stored email UID {} as a receipt", uid);
+                    true
                 }
                 Err(e) => {
                     // 如果是 "not found" 错误，说明邮件已被删除，这是正常情况
@@ -236,19 +477,114 @@ impl EmailSyncer {
                         log::error!("Failed to process email UID {}: {}", uid, e);
                     }
                     // 继续处理下一封邮件，而不是中断整个同步
-                    continue;
+                    false
+                }
+            };
+
+            // 每批邮件的附件写完后再推进检查点，中断后不会跳过附件还没写入的邮件
+            if resumable {
+                if succeeded {
+                    unrecorded.0 += 1;
+                } else {
+                    unrecorded.1 += 1;
+                }
+                if unrecorded.0 + unrecorded.1 >= CHECKPOINT_BATCH_SIZE {
+                    self.advance_checkpoint(&checkpoints, &mut attachments, account_id, *uid, &mut unrecorded).await;
+                }
+            }
+        }
+        if let (true, Some(last)) = (resumable, full_uids.last()) {
+            self.advance_checkpoint(&checkpoints, &mut attachments, account_id, *last, &mut unrecorded).await;
+        }
+
+        // Gmail：刷新最近邮件的标签（用户可能在网页端修改过）
+        if conn.supports_gmail_labels() {
+            if let Err(e) = self.refresh_gmail_labels(&mut conn, account_id).await {
+                log::warn!("Failed to refresh Gmail labels for account {}: {}", account_id, e);
+            }
+        }
+
+        // 增量同步时把服务器上已删除的收件箱邮件标记为墓碑（需在设置中开启）
+        let deletions = ServerDeletions::new(self.pool.clone());
+        if !resumable && last_uid > 0 && deletions.enabled().await {
+            if let Err(e) = deletions.mirror_inbox(&mut conn, account_id).await {
+                log::warn!("Deletion check failed for account {}: {}", account_id, e);
+            }
+        }
+
+        // 6. 补充已发送文件夹中对已有线程的回复（按流量计费模式跳过）
+        if !policy.metered {
+            match self.sync_sent_replies(&mut conn, account_id, provider, &classifier, &mut attachments).await {
+                Ok(count) if count > 0 => log::info!("Saved {} sent replies for account {}", count, account_id),
+                Ok(_) => {}
+                Err(e) => log::warn!("Sent folder pass failed for account {}: {}", account_id, e),
+            }
+        }
+
+        // 7. 共享邮箱和公共文件夹（需在设置中开启，按流量计费模式跳过）
+        if !policy.metered && self.sync_shared_enabled().await {
+            match self.sync_shared_mailboxes(&mut conn, account_id, &classifier, &mut attachments).await {
+                Ok(saved) if !saved.is_empty() => {
+                    log::info!("Saved {} emails from shared mailboxes for account {}", saved.len(), account_id);
+                    new_email_ids.extend(saved);
                 }
+                Ok(_) => {}
+                Err(e) => log::warn!("Shared mailbox pass failed for account {}: {}", account_id, e),
             }
         }
 
-        // 6. 登出
+        // 8. 登出，等待附件写完
         conn.logout().await?;
+        let saved_attachments = attachments.finish().await;
+        log::info!("Saved {} attachments for account {}", saved_attachments, account_id);
+
+        // 首次同步的范围已处理完，附件也已全部写入
+        if resumable {
+            checkpoints.clear(account_id).await?;
+        }
+
+        if let Some(usage) = disk_space::take_warning() {
+            self.event_emitter.emit_notification_from(
+                "Disk almost full",
+                &format!(
+                    "The disk holding ThreadLine's data is {:.0}% full ({} MB free). Move older attachments to cold storage or free up space.",
+                    usage.used_ratio * 100.0,
+                    usage.available_bytes / (1024 * 1024)
+                ),
+                NotificationLevel::Warning,
+                Some(SOURCE_SYNC),
+                None,
+            );
+        }
+
+        // 清理过期的分类日志
+        let classification_log = ClassificationLog::new(self.pool.clone());
+        if let Err(e) = classification_log.prune(CLASSIFICATION_LOG_RETENTION_DAYS).await {
+            log::warn!("Failed to prune classification log: {}", e);
+        }
 
         let synced_count = uids_to_sync.len();
         log::info!("Sync completed for account {}: {} new emails", account_id, synced_count);
 
         // 发送完成事件
-        self.emit_progress(account_id, synced_count, synced_count, SyncStatus::Completed);
+        self.emit_progress(account_id, progress_total, progress_total, SyncStatus::Completed);
+        if let Err(e) = VipList::new(self.pool.clone()).notify_new_emails(&self.event_emitter, &new_email_ids).await {
+            log::warn!("Failed to notify VIP emails for account {}: {}", account_id, e);
+        }
+        if !new_email_ids.is_empty() {
+            self.event_emitter.emit_new_emails(NewEmailsEvent {
+                account_id,
+                count: new_email_ids.len(),
+                email_ids: new_email_ids,
+            });
+        }
+        classifier.emit_project_events();
+        let capped = classifier.capped_emails();
+        if let (Some(run_id), true) = (run_id, capped > 0) {
+            if let Err(e) = SyncRunLog::new(self.pool.clone()).set_projects_capped(run_id, capped).await {
+                log::warn!("Failed to record capped projects for sync run {}: {}", run_id, e);
+            }
+        }
 
         Ok(SyncProgress {
             account_id,
@@ -258,165 +594,1673 @@ impl EmailSyncer {
         })
     }
 
-    /// 保存邮件到数据库
-    async fn save_email(
+    /// 查询并保存邮箱配额，使用率首次达到警告阈值时发出通知
+    async fn check_quota(&self, conn: &mut ImapConnection, account_id: i64) -> Result<(), AppError> {
+        let Some(quota) = conn.fetch_quota().await? else {
+            return Ok(());
+        };
+        log::info!("Mailbox quota for account {}: {} / {} KB", account_id, quota.used_kb, quota.limit_kb);
+
+        if let Some(quota) = QuotaStore::new(self.pool.clone()).record(account_id, quota).await? {
+            self.event_emitter.emit_notification_from(
+                "Mailbox almost full",
+                &format!(
+                    "{} is using {:.0}% of its storage quota ({} MB of {} MB). Sending and syncing may start failing.",
+                    quota.email,
+                    quota.used_ratio * 100.0,
+                    quota.used_bytes / (1024 * 1024),
+                    quota.limit_bytes / (1024 * 1024)
+                ),
+                NotificationLevel::Warning,
+                Some(SOURCE_SYNC),
+                Some(&format!("account:{}", account_id)),
+            );
+        }
+        Ok(())
+    }
+
+    /// 已发送文件夹中回复已有线程的邮件：按线程 ID 做 UID SEARCH HEADER，只下载匹配的邮件
+    ///
+    /// 返回保存的邮件数。
+    async fn sync_sent_replies(
         &self,
+        conn: &mut ImapConnection,
         account_id: i64,
-        uid: u32,
-        parsed: &ParsedEmail,
-    ) -> Result<(), AppError> {
-        let thread_id = generate_thread_id(parsed);
-        let recipients = serde_json::to_string(&parsed.to).unwrap_or_default();
+        provider: &ProviderConfig,
+        classifier: &ProjectClassifier,
+        attachments: &mut AttachmentWriter,
+    ) -> Result<usize, AppError> {
+        let Some(folder) = resolve_sent_folder(conn, provider).await? else {
+            log::info!("No sent folder found for account {}, skipping sent pass", account_id);
+            return Ok(0);
+        };
 
-        sqlx::query(
+        let thread_ids: Vec<String> = sqlx::query_scalar(
             r#"
-            INSERT OR REPLACE INTO emails (
-                message_id, account_id, thread_id, subject, sender, recipients,
-                date, body_text, body_html, has_attachments, raw_path
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            SELECT thread_id FROM emails
+            WHERE account_id = ? AND thread_id IS NOT NULL AND project_id IS NOT NULL
+            GROUP BY thread_id
+            ORDER BY MAX(datetime(date)) DESC
+            LIMIT ?
             "#
         )
-        .bind(&parsed.message_id)
         .bind(account_id)
-        .bind(&thread_id)
-        .bind(&parsed.subject)
-        .bind(&parsed.from)
-        .bind(&recipients)
-        .bind(&parsed.date)
-        .bind(&parsed.body_text)
-        .bind(&parsed.body_html)
-        .bind(!parsed.attachments.is_empty())
-        .bind(uid.to_string()) // 使用 UID 作为 raw_path
-        .execute(&self.pool)
+        .bind(SENT_PASS_MAX_THREADS)
+        .fetch_all(&self.pool)
         .await?;
+        if thread_ids.is_empty() {
+            return Ok(0);
+        }
 
-        Ok(())
-    }
-
-    /// 根据 message_id 获取邮件 ID
-    async fn get_email_id_by_message_id(&self, message_id: &str, account_id: i64) -> Result<i64, AppError> {
-        let result: (i64,) = sqlx::query_as(
-            "SELECT id FROM emails WHERE message_id = ? AND account_id = ?"
+        conn.select_folder(&folder).await?;
+        let last_sent_uid: Option<i64> = sqlx::query_scalar(
+            "SELECT MAX(CAST(substr(raw_path, ?) AS INTEGER)) FROM emails WHERE account_id = ? AND raw_path LIKE ?"
         )
-        .bind(message_id)
+        .bind(SENT_RAW_PATH_PREFIX.len() as i64 + 1)
         .bind(account_id)
+        .bind(format!("{}%", SENT_RAW_PATH_PREFIX))
         .fetch_one(&self.pool)
         .await?;
+        let uid_range = format!("{}:*", last_sent_uid.unwrap_or(0) + 1);
 
-        Ok(result.0)
+        let mut uids = Vec::new();
+        for thread_id in &thread_ids {
+            // HEADER 搜索按子串匹配，去掉尖括号同时匹配两种存储形式
+            let id = thread_id.trim().trim_start_matches('<').trim_end_matches('>');
+            if id.is_empty() || id.contains(['"', '\\', '\r', '\n']) {
+                continue;
+            }
+            let criteria = format!(
+                "UID {} OR HEADER References \"{}\" HEADER In-Reply-To \"{}\"",
+                uid_range, id, id
+            );
+            match conn.uid_search(&criteria, None).await {
+                Ok(found) => uids.extend(found),
+                Err(e) => log::warn!("Sent folder search failed for thread {}: {}", thread_id, e),
+            }
+        }
+        uids.sort_unstable();
+        uids.dedup();
+        uids.retain(|uid| i64::from(*uid) > last_sent_uid.unwrap_or(0));
+
+        let mut saved = 0;
+        for uid in uids {
+            if let Err(e) = conn.keepalive().await {
+                log::warn!("IMAP keepalive failed: {}", e);
+            }
+            match self.process_message(conn, account_id, uid, MailDirection::Outgoing, classifier, attachments).await {
+                Ok(ProcessedMessage::Saved(_)) => saved += 1,
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to save sent email UID {}: {}", uid, e),
+            }
+        }
+
+        Ok(saved)
+    }
+
+    async fn sync_shared_enabled(&self) -> bool {
+        sqlx::query_scalar("SELECT sync_shared_mailboxes FROM sync_settings WHERE id = 1")
+            .fetch_one(&self.pool)
+            .await
+            .unwrap_or(false)
     }
 
-    /// 保存附件到数据库和文件系统
-    async fn save_attachment(
+    /// 同步其他用户和共享命名空间中的文件夹：每个文件夹按已保存的最大 UID 增量同步
+    ///
+    /// 返回新保存的邮件 ID。邮件的文件夹记录带上命名空间，分类时可据此限制自动建项目。
+    async fn sync_shared_mailboxes(
         &self,
+        conn: &mut ImapConnection,
         account_id: i64,
-        message_id: &str,
-        _index: usize,
-        attachment: &crate::mail::parser::ParsedAttachment,
-    ) -> Result<(), AppError> {
-        // 首先获取邮件的 ID
-        let email_id: Option<(i64,)> = sqlx::query_as(
-            "SELECT id FROM emails WHERE message_id = ? AND account_id = ?"
-        )
-        .bind(message_id)
-        .bind(account_id)
-        .fetch_optional(&self.pool)
-        .await?;
+        classifier: &ProjectClassifier,
+        attachments: &mut AttachmentWriter,
+    ) -> Result<Vec<i64>, AppError> {
+        let shared_folders = conn.list_shared_folders().await?;
+        if shared_folders.is_empty() {
+            return Ok(Vec::new());
+        }
+        let max_sync_count = self.get_max_sync_count().await.unwrap_or(100);
+        let folders = FolderStore::new(self.pool.clone());
 
-        if let Some((email_id,)) = email_id {
-            // 保存附件文件到文件系统
-            let file_path = self.save_attachment_file(account_id, email_id, attachment).await?;
+        let mut saved = Vec::new();
+        for folder in shared_folders.iter().filter(|folder| folder.selectable) {
+            let exists = match conn.select_folder(&folder.name).await {
+                Ok(exists) => exists as usize,
+                Err(e) => {
+                    log::warn!("Failed to select shared folder {}: {}", folder.name, e);
+                    continue;
+                }
+            };
+            let last_uid = folders.last_uid(account_id, &folder.name).await?;
+            let uids = pending_uids(conn, exists, last_uid, max_sync_count).await?;
+            log::info!(
+                "Shared folder {} ({}): {} new messages after UID {}",
+                folder.name, folder.namespace.as_str(), uids.len(), last_uid
+            );
 
-            // 计算文件哈希
-            let content_hash = calculate_sha256(&attachment.data);
+            for uid in uids {
+                if let Err(e) = conn.keepalive().await {
+                    log::warn!("IMAP keepalive failed: {}", e);
+                }
+                match self.process_message(conn, account_id, uid, MailDirection::Incoming, classifier, attachments).await {
+                    Ok(ProcessedMessage::Saved(email_id)) => saved.push(email_id),
+                    Ok(_) => {}
+                    Err(e) => log::warn!("Failed to save email UID {} from shared folder {}: {}", uid, folder.name, e),
+                }
+            }
+        }
 
-            sqlx::query(
-                r#"
-                INSERT INTO attachments (
-                    email_id, filename, file_type, file_size, mime_type, file_path, content_hash
-                ) VALUES (?, ?, ?, ?, ?, ?, ?)
-                "#
-            )
-            .bind(email_id)
-            .bind(&attachment.filename)
-            .bind(extract_file_extension(&attachment.filename))
-            .bind(attachment.size as i64)
-            .bind(&attachment.content_type)
-            .bind(&file_path)
-            .bind(&content_hash)
-            .execute(&self.pool)
-            .await?;
+        Ok(saved)
+    }
+
+    /// 导入单封服务器端邮件（例如服务器端搜索命中但尚未同步的旧邮件），使用连接池中的会话
+    pub async fn import_remote_email(
+        &self,
+        account_id: i64,
+        conn: &mut ImapConnection,
+        uid: u32,
+    ) -> Result<i64, AppError> {
+        conn.select_folder("INBOX").await?;
+
+        let classifier = ProjectClassifier::load(self.pool.clone())
+            .await
+            .with_event_emitter(self.event_emitter.clone());
+        let mut attachments = AttachmentWriter::new(self.pool.clone());
+        let result = self
+            .process_message(conn, account_id, uid, MailDirection::Incoming, &classifier, &mut attachments)
+            .await;
+        attachments.finish().await;
+        classifier.emit_project_events();
+
+        let email_id = match result? {
+            ProcessedMessage::Saved(email_id) | ProcessedMessage::Linked(email_id) => email_id,
+            ProcessedMessage::Receipt => {
+                return Err(AppError::Validation(format!("Email UID {} is a read receipt or delivery report", uid)));
+            }
+        };
+        log::info!("Imported remote email UID {} as email {}", uid, email_id);
+        Ok(email_id)
+    }
+
+    /// 下载、解析、保存并分类单封邮件
+    ///
+    /// 已读回执和投递状态通知只保存到 `email_receipts`。Gmail 中已在其他文件夹保存过的邮件
+    /// （X-GM-MSGID 相同）不再下载，只记录所在文件夹。
+    /// 附件交给 `attachments` 在后台写入，调用方结束前需要 `finish`。
+    async fn process_message(
+        &self,
+        conn: &mut ImapConnection,
+        account_id: i64,
+        uid: u32,
+        direction: MailDirection,
+        classifier: &ProjectClassifier,
+        attachments: &mut AttachmentWriter,
+    ) -> Result<ProcessedMessage, AppError> {
+        let folder = conn.selected_folder().unwrap_or("INBOX").to_string();
+        let namespace = conn.selected_namespace();
+        let folders = FolderStore::new(self.pool.clone());
+
+        // Gmail 邮件 ID 和标签（标签在分类前保存，供按标签归类使用）
+        let gmail = if conn.supports_gmail_labels() {
+            match conn.fetch_gmail_metadata(&[uid]).await {
+                Ok(mut metadata) => metadata.remove(&uid),
+                Err(e) => {
+                    log::warn!("Failed to fetch Gmail metadata for UID {}: {}", uid, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let gm_msgid = gmail.as_ref().and_then(|gmail| gmail.msgid.as_deref());
+        if let Some(msgid) = gm_msgid {
+            if let Some(email_id) = folders.find_by_gm_msgid(account_id, msgid).await? {
+                folders.link(email_id, &folder, namespace, uid, None).await?;
+                if let Some(gmail) = &gmail {
+                    if let Err(e) = self.store_gmail_labels(email_id, &gmail.labels).await {
+                        log::warn!("Failed to store Gmail labels for email {}: {}", email_id, e);
+                    }
+                }
+                log::debug!("Email UID {} in {} is already stored as email {}", uid, folder, email_id);
+                return Ok(ProcessedMessage::Linked(email_id));
+            }
+        }
+
+        // 下载邮件
+        log::debug!("Downloading email UID {}", uid);
+        let raw_data = conn.fetch_email(uid).await
+            .map_err(|e| AppError::Generic(format!("Failed to download email UID {}: {}", uid, e)))?;
+        log::debug!("Downloaded {} bytes for UID {}", raw_data.len(), uid);
 
-            log::info!("Saved attachment: {} ({} bytes) to {}", attachment.filename, attachment.size, file_path);
+        // 解析邮件
+        log::debug!("Parsing email UID {}", uid);
+        let mut parsed = parse_email_for_account(&raw_data, account_id).map_err(|e| {
+            conn.metrics().record_parse_failure();
+            AppError::Generic(format!("Failed to parse email UID {}: {}", uid, e))
+        })?;
+        log::debug!("Parsed email UID {}, subject: {:?}", uid, parsed.subject);
+
+        if let Some(receipt) = &parsed.receipt {
+            ReceiptStore::new(self.pool.clone())
+                .save(account_id, &parsed, receipt)
+                .await
+                .map_err(|e| AppError::Generic(format!("Failed to save receipt UID {}: {}", uid, e)))?;
+            return Ok(ProcessedMessage::Receipt);
         }
 
-        Ok(())
+        // 保存到数据库（同一封邮件重新保存时原地更新，邮件 ID 不变）
+        log::debug!("Saving email UID {} to database", uid);
+        let raw_path = match namespace {
+            NamespaceKind::Personal => direction.raw_path(uid),
+            _ => format!("{}{}:{}", SHARED_RAW_PATH_PREFIX, folder, uid),
+        };
+        let written = self.save_email(account_id, &raw_path, &parsed, direction).await
+            .map_err(|e| AppError::Generic(format!("Failed to save email UID {}: {}", uid, e)))?;
+
+        // 获取刚保存的邮件 ID
+        log::debug!("Getting email ID for message_id: {}", parsed.message_id);
+        let email_id = self.get_email_id_by_message_id(&parsed.message_id, account_id).await
+            .map_err(|e| AppError::Generic(format!("Failed to get email ID for UID {}: {}", uid, e)))?;
+
+        if let Err(e) = folders.link(email_id, &folder, namespace, uid, gm_msgid).await {
+            log::warn!("Failed to record folder {} for email {}: {}", folder, email_id, e);
+        }
+        if !written {
+            log::info!(
+                "Message-ID {} of UID {} is already stored as email {} from another message, keeping the existing row",
+                parsed.message_id, uid, email_id
+            );
+            return Ok(ProcessedMessage::Linked(email_id));
+        }
+        if let Some(gmail) = &gmail {
+            if let Err(e) = self.store_gmail_labels(email_id, &gmail.labels).await {
+                log::warn!("Failed to store Gmail labels for email {}: {}", email_id, e);
+            }
+        }
+
+        self.process_saved(account_id, email_id, &mut parsed, classifier, attachments).await;
+        Ok(ProcessedMessage::Saved(email_id))
     }
 
-    /// 保存附件文件到文件系统
-    async fn save_attachment_file(
+    /// 导入本地 .eml 文件（监视文件夹），与 IMAP 邮件走相同的保存和分类流程
+    ///
+    /// 账户中已有相同 Message-ID 的邮件时不重复保存，返回 `Linked`。`raw_path` 记录来源文件，不会被当作 UID。
+    pub async fn import_eml(
         &self,
         account_id: i64,
-        email_id: i64,
-        attachment: &crate::mail::parser::ParsedAttachment,
-    ) -> Result<String, AppError> {
-        use tokio::fs;
-
-        // 获取应用数据目录（使用环境变量或默认路径）
-        let app_data_dir = std::env::var("APPDATA")
-            .or_else(|_| std::env::var("HOME").map(|h| format!("{}/.config", h)))
-            .map(|p| std::path::PathBuf::from(p).join("com.threadline.app"))
-            .map_err(|e| AppError::Generic(format!("Failed to get app data directory: {}", e)))?;
-
-        // 构建附件存储路径: ~/.threadline/attachments/{file_type}/{account_id}/{email_id}/
-        let file_type = extract_file_extension(&attachment.filename);
-        let attachment_dir = app_data_dir
-            .join("attachments")
-            .join(&file_type)
-            .join(account_id.to_string())
-            .join(email_id.to_string());
-
-        // 创建目录
-        fs::create_dir_all(&attachment_dir).await
-            .map_err(|e| AppError::Generic(format!("Failed to create attachment directory: {}", e)))?;
-
-        // 生成安全的文件名（避免路径遍历攻击）
-        let safe_filename = sanitize_filename(&attachment.filename);
-        let file_path = attachment_dir.join(&safe_filename);
-
-        // 写入文件
-        fs::write(&file_path, &attachment.data).await
-            .map_err(|e| AppError::Generic(format!("Failed to write attachment file: {}", e)))?;
-
-        // 返回相对路径（用于数据库存储）
-        Ok(format!("{}/{}/{}/{}", file_type, account_id, email_id, safe_filename))
+        raw_data: &[u8],
+        raw_path: &str,
+        classifier: &ProjectClassifier,
+        attachments: &mut AttachmentWriter,
+    ) -> Result<ProcessedMessage, AppError> {
+        let mut parsed = parse_email_for_account(raw_data, account_id).map_err(|e| AppError::Parse(e.to_string()))?;
+
+        if let Some(receipt) = &parsed.receipt {
+            ReceiptStore::new(self.pool.clone()).save(account_id, &parsed, receipt).await?;
+            return Ok(ProcessedMessage::Receipt);
+        }
+        if let Ok(email_id) = self.get_email_id_by_message_id(&parsed.message_id, account_id).await {
+            log::debug!("Imported message {} is already stored as email {}", parsed.message_id, email_id);
+            return Ok(ProcessedMessage::Linked(email_id));
+        }
+
+        if !self.save_email(account_id, raw_path, &parsed, MailDirection::Incoming).await? {
+            let email_id = self.get_email_id_by_message_id(&parsed.message_id, account_id).await?;
+            return Ok(ProcessedMessage::Linked(email_id));
+        }
+        let email_id = self.get_email_id_by_message_id(&parsed.message_id, account_id).await?;
+        self.process_saved(account_id, email_id, &mut parsed, classifier, attachments).await;
+        Ok(ProcessedMessage::Saved(email_id))
     }
-}
 
-/// 提取文件扩展名
-fn extract_file_extension(filename: &str) -> String {
-    std::path::Path::new(filename)
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("unknown")
-        .to_string()
-}
+    /// 邮件行写入后：记录联系人、重复检测、分类、预取远程内容并排队保存附件
+    async fn process_saved(
+        &self,
+        account_id: i64,
+        email_id: i64,
+        parsed: &mut ParsedEmail,
+        classifier: &ProjectClassifier,
+        attachments: &mut AttachmentWriter,
+    ) {
+        // 记录联系人
+        if let Err(e) = ContactBook::new(self.pool.clone()).record_email(account_id, parsed).await {
+            log::warn!("Failed to record contacts for email {}: {}", email_id, e);
+        }
 
-/// 计算 SHA256 哈希
-fn calculate_sha256(data: &[u8]) -> String {
-    use sha2::{Sha256, Digest};
-    let mut hasher = Sha256::new();
-    hasher.update(data);
-    format!("{:x}", hasher.finalize())
-}
+        // 跨账户重复检测（重复邮件继承规范邮件的项目）
+        let detector = DuplicateDetector::new(self.pool.clone());
+        if let Err(e) = detector.detect(email_id).await {
+            log::warn!("Failed to run duplicate detection for email {}: {}", email_id, e);
+        }
 
-/// 清理文件名，移除不安全字符
-fn sanitize_filename(filename: &str) -> String {
-    filename
-        .chars()
+        // 自动分类到项目
+        log::debug!("Classifying email {}", email_id);
+        if let Err(e) = classifier.classify_email(email_id).await {
+            log::warn!("Failed to classify email {}: {}", email_id, e);
+        }
+
+        // 后台预取置顶项目邮件中的远程图片（离线阅读）
+        let remote_cache = RemoteContentCache::new(self.pool.clone());
+        tokio::spawn(async move {
+            if let Err(e) = remote_cache.prefetch_email(email_id).await {
+                log::warn!("Failed to prefetch remote content for email {}: {}", email_id, e);
+            }
+        });
+
+        // 会议邀请
+        if let Some(ics) = &parsed.calendar {
+            if let Err(e) = CalendarStore::new(self.pool.clone()).save_invite(account_id, email_id, ics).await {
+                log::warn!("Failed to save calendar invite of email {}: {}", email_id, e);
+            }
+        }
+
+        // 保存附件（邮件行已写入，附件记录可以引用它）
+        log::debug!("Queueing {} attachments for email {}", parsed.attachments.len(), email_id);
+        attachments.enqueue(account_id, email_id, std::mem::take(&mut parsed.attachments)).await;
+    }
+
+    /// 重置账户的同步数据
+    ///
+    /// 删除该账户的邮件、附件记录、附件文件和只被这些邮件引用的缓存图片，并删除不再包含任何邮件（来自任何账户）的项目。
+    /// 与其他账户共享的项目会保留。数据库操作在同一事务中完成，文件在提交后删除。
+    pub async fn reset_account(&self, account_id: i64) -> Result<ResetSummary, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        // 1. 在删除邮件之前记录受影响的项目
+        let affected_projects: Vec<(i64,)> = sqlx::query_as(
+            "SELECT DISTINCT project_id FROM emails WHERE account_id = ? AND project_id IS NOT NULL"
+        )
+        .bind(account_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        // 2. 记录需要删除的附件（文件在提交后移入隔离区）
+        let removed_attachments = sqlx::query_as::<_, RemovedAttachment>(&format!(
+            "SELECT {} FROM attachments WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?)",
+            REMOVED_ATTACHMENT_COLUMNS
+        ))
+        .bind(account_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        // 3. 其他账户中以本账户邮件为规范的重复邮件：提升 ID 最小的一封为新的规范邮件
+        //    先在临时表中算出 旧规范 → 新规范 的映射再改写，避免 UPDATE 过程中子查询看到已改写的行
+        sqlx::query("DROP TABLE IF EXISTS temp.reset_canonical")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            r#"
+            CREATE TEMP TABLE reset_canonical AS
+            SELECT duplicate_of AS old_id, MIN(id) AS new_id
+            FROM emails
+            WHERE duplicate_of IN (SELECT id FROM emails WHERE account_id = ?)
+              AND account_id IS NOT ?
+            GROUP BY duplicate_of
+            "#
+        )
+        .bind(account_id)
+        .bind(account_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE emails
+            SET duplicate_of = (SELECT new_id FROM temp.reset_canonical WHERE old_id = emails.duplicate_of)
+            WHERE duplicate_of IN (SELECT old_id FROM temp.reset_canonical)
+              AND account_id IS NOT ?
+            "#
+        )
+        .bind(account_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE emails SET duplicate_of = NULL WHERE id IN (SELECT new_id FROM temp.reset_canonical)")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DROP TABLE temp.reset_canonical")
+            .execute(&mut *tx)
+            .await?;
+
+        // 4. 删除本账户的会议邀请，再删除邮件及引用它们的行（分类日志、附件记录、远程图片引用等）
+        sqlx::query("DELETE FROM calendar_events WHERE account_id = ?")
+            .bind(account_id)
+            .execute(&mut *tx)
+            .await?;
+        let deleted_emails =
+            delete_email_rows(&mut tx, "SELECT id FROM emails WHERE account_id = ?", account_id).await?;
+        let deleted_attachments = removed_attachments.len() as u64;
+
+        sqlx::query("DELETE FROM email_receipts WHERE account_id = ?")
+            .bind(account_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM sync_checkpoints WHERE account_id = ?")
+            .bind(account_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // 5. 删除不再包含任何邮件的受影响项目
+        let mut deleted_projects = 0;
+        for (project_id,) in &affected_projects {
+            let remaining: (i64,) = sqlx::query_as(
+                "SELECT COUNT(*) FROM emails WHERE project_id = ?"
+            )
+            .bind(project_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if remaining.0 > 0 {
+                continue;
+            }
+
+            sqlx::query("DELETE FROM milestones WHERE project_id = ?")
+                .bind(project_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE attachments SET project_id = NULL WHERE project_id = ?")
+                .bind(project_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM project_preferences WHERE project_id = ?")
+                .bind(project_id)
+                .execute(&mut *tx)
+                .await?;
+            deleted_projects += sqlx::query("DELETE FROM projects WHERE id = ?")
+                .bind(project_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        tx.commit().await?;
+
+        // 6. 提交后把附件文件移入隔离区，删除只被本账户邮件引用的缓存图片
+        AttachmentQuarantine::new(self.pool.clone()).quarantine_files(&removed_attachments).await;
+        if let Err(e) = RemoteContentCache::new(self.pool.clone()).remove_unreferenced().await {
+            log::warn!("Failed to remove cached remote images of account {}: {}", account_id, e);
+        }
+        if let Ok(dir) = body_store::resolve_body_path(&account_id.to_string()) {
+            match tokio::fs::remove_dir_all(&dir).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => log::warn!("Failed to remove body files {:?}: {}", dir, e),
+            }
+        }
+
+        log::info!(
+            "Reset account {}: {} emails, {} attachments, {} projects deleted",
+            account_id, deleted_emails, deleted_attachments, deleted_projects
+        );
+
+        Ok(ResetSummary {
+            account_id,
+            deleted_emails,
+            deleted_attachments,
+            deleted_projects,
+        })
+    }
+
+    /// 保存邮件的 Gmail 标签（JSON 数组），返回是否有变化
+    async fn store_gmail_labels(&self, email_id: i64, labels: &[String]) -> Result<bool, AppError> {
+        let json = serde_json::to_string(labels)
+            .map_err(|e| AppError::Generic(format!("Failed to serialize Gmail labels: {}", e)))?;
+        let result = sqlx::query(
+            "UPDATE emails SET gmail_labels = ? WHERE id = ? AND gmail_labels IS NOT ?"
+        )
+        .bind(&json)
+        .bind(email_id)
+        .bind(&json)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 刷新收件箱中最近邮件的 Gmail 标签（需已选中 INBOX），返回有变化的邮件数
+    async fn refresh_gmail_labels(&self, conn: &mut ImapConnection, account_id: i64) -> Result<usize, AppError> {
+        let rows: Vec<(i64, String)> = sqlx::query_as(
+            r#"
+            SELECT id, raw_path FROM emails
+            WHERE account_id = ? AND raw_path GLOB '[0-9]*' AND raw_path NOT GLOB '*[^0-9]*'
+            ORDER BY CAST(raw_path AS INTEGER) DESC
+            LIMIT ?
+            "#
+        )
+        .bind(account_id)
+        .bind(GMAIL_LABEL_REFRESH_LIMIT)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let by_uid: HashMap<u32, i64> = rows
+            .into_iter()
+            .filter_map(|(id, raw_path)| raw_path.parse::<u32>().ok().map(|uid| (uid, id)))
+            .collect();
+        let uids: Vec<u32> = by_uid.keys().copied().collect();
+
+        let mut changed = 0;
+        for chunk in uids.chunks(HEADER_BATCH_SIZE) {
+            conn.keepalive().await?;
+            for (uid, metadata) in conn.fetch_gmail_metadata(chunk).await? {
+                if let Some(email_id) = by_uid.get(&uid) {
+                    if self.store_gmail_labels(*email_id, &metadata.labels).await? {
+                        changed += 1;
+                    }
+                }
+            }
+        }
+
+        if changed > 0 {
+            log::info!("Updated Gmail labels on {} emails for account {}", changed, account_id);
+        }
+        Ok(changed)
+    }
+
+    /// 等待已排队的附件写完，再把检查点推进到 `last_uid`
+    async fn advance_checkpoint(
+        &self,
+        checkpoints: &SyncCheckpointStore,
+        attachments: &mut AttachmentWriter,
+        account_id: i64,
+        last_uid: u32,
+        unrecorded: &mut (usize, usize),
+    ) {
+        attachments.flush().await;
+        let (completed, failed) = std::mem::take(unrecorded);
+        if let Err(e) = checkpoints.advance(account_id, last_uid, completed, failed).await {
+            log::warn!("Failed to update sync checkpoint for account {}: {}", account_id, e);
+        }
+    }
+
+    /// 分批获取并保存邮件头
+    ///
+    /// `progress` 为（之前已完成的数量, 总数）；首次同步时每批保存后推进 `checkpoint`。
+    async fn sync_headers(
+        &self,
+        conn: &mut ImapConnection,
+        account_id: i64,
+        uids: &[u32],
+        classifier: &ProjectClassifier,
+        checkpoint: Option<&SyncCheckpointStore>,
+        progress: (usize, usize),
+    ) -> Result<(), AppError> {
+        let (progress_offset, progress_total) = progress;
+        let folder = conn.selected_folder().unwrap_or("INBOX").to_string();
+        let mut current = 0;
+        for chunk in uids.chunks(HEADER_BATCH_SIZE) {
+            conn.keepalive().await?;
+            let gmail = conn.fetch_gmail_metadata(chunk).await.unwrap_or_else(|e| {
+                log::warn!("Failed to fetch Gmail metadata: {}", e);
+                HashMap::new()
+            });
+            let mut saved = 0;
+            for envelope in conn.fetch_envelopes(chunk).await? {
+                match self.save_header(account_id, &folder, &envelope, gmail.get(&envelope.uid), classifier).await {
+                    Ok(()) => saved += 1,
+                    Err(e) => log::error!("Failed to save header for UID {}: {}", envelope.uid, e),
+                }
+            }
+
+            if let (Some(checkpoints), Some(last)) = (checkpoint, chunk.last()) {
+                if let Err(e) = checkpoints.advance(account_id, *last, saved, chunk.len().saturating_sub(saved)).await {
+                    log::warn!("Failed to update sync checkpoint for account {}: {}", account_id, e);
+                }
+            }
+
+            current += chunk.len();
+            self.emit_progress(account_id, progress_offset + current, progress_total, SyncStatus::Syncing);
+        }
+
+        log::info!("Saved {} headers for account {} (metered mode)", uids.len(), account_id);
+        Ok(())
+    }
+
+    /// 只保存邮件头（`body_state = 'remote'`），并分类到项目
+    ///
+    /// Gmail 中已在其他文件夹保存过的邮件只记录所在文件夹。
+    async fn save_header(
+        &self,
+        account_id: i64,
+        folder: &str,
+        envelope: &RemoteEnvelope,
+        gmail: Option<&GmailMetadata>,
+        classifier: &ProjectClassifier,
+    ) -> Result<(), AppError> {
+        let folders = FolderStore::new(self.pool.clone());
+        let gm_msgid = gmail.and_then(|gmail| gmail.msgid.as_deref());
+        if let Some(msgid) = gm_msgid {
+            if let Some(email_id) = folders.find_by_gm_msgid(account_id, msgid).await? {
+                return folders.link(email_id, folder, NamespaceKind::Personal, envelope.uid, None).await;
+            }
+        }
+
+        let message_id = envelope
+            .message_id
+            .clone()
+            .unwrap_or_else(|| format!("<uid-{}-{}@threadline>", account_id, envelope.uid));
+        let date = envelope
+            .date
+            .as_deref()
+            .map(|date| {
+                chrono::DateTime::parse_from_rfc2822(date.trim())
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_else(|_| date.to_string())
+            })
+            .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+
+        // 只有邮件头时无法读取 Auto-Submitted 等标记，按发件人和规则判断
+        let is_automated = AutomatedDetector::new(self.pool.clone())
+            .detect(
+                envelope.from.as_deref().unwrap_or_default(),
+                envelope.subject.as_deref().unwrap_or_default(),
+                None,
+                &message_id,
+                &AutomatedHeaders::default(),
+            )
+            .await?;
+
+        let inserted = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO emails (
+                message_id, account_id, thread_id, subject, sender, sender_name, sender_address, date,
+                raw_path, body_state, is_automated, lang, gmail_labels
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 'remote', ?, ?, ?)
+            "#
+        )
+        .bind(&message_id)
+        .bind(account_id)
+        .bind(&message_id)
+        .bind(&envelope.subject)
+        .bind(&envelope.from)
+        .bind(&envelope.from_name)
+        .bind(&envelope.from_address)
+        .bind(&date)
+        .bind(envelope.uid.to_string())
+        .bind(is_automated)
+        .bind(detect_language(envelope.subject.as_deref(), None))
+        .bind(gmail.map(|gmail| serde_json::to_string(&gmail.labels).unwrap_or_default()))
+        .execute(&self.pool)
+        .await?;
+        let email_id = self.get_email_id_by_message_id(&message_id, account_id).await?;
+        folders.link(email_id, folder, NamespaceKind::Personal, envelope.uid, gm_msgid).await?;
+        if inserted.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        if let Err(e) = classifier.classify_email(email_id).await {
+            log::warn!("Failed to classify email {}: {}", email_id, e);
+        }
+
+        Ok(())
+    }
+
+    /// 保存邮件到数据库，返回是否写入
+    ///
+    /// 账户中已有相同 Message-ID 的邮件时，只有来源相同（同一 UID / 文件，即同一封邮件重新下载）才原地更新，
+    /// 保留项目、已读、星标等状态；来源不同的邮件不会覆盖已有的邮件行，返回 `false`。
+    async fn save_email(
+        &self,
+        account_id: i64,
+        raw_path: &str,
+        parsed: &ParsedEmail,
+        direction: MailDirection,
+    ) -> Result<bool, AppError> {
+        let thread_id = generate_thread_id(parsed);
+        let recipients = serde_json::to_string(&parsed.to).unwrap_or_default();
+        let cc = serde_json::to_string(&parsed.cc).unwrap_or_default();
+        let mine = my_addresses(&self.pool).await?;
+        let cc_only = is_cc_only(&parsed.to, &parsed.cc, &mine);
+        // 收件文件夹中由自己（包括别名身份）发出的邮件也记为发出
+        let sent_by_me = parsed
+            .from_address
+            .as_deref()
+            .is_some_and(|address| is_my_address(address, &mine));
+        let stored_direction = if sent_by_me { MailDirection::Outgoing } else { direction };
+        let fingerprint = content_fingerprint(
+            &parsed.subject,
+            &parsed.date,
+            &parsed.from,
+            parsed.body_text.as_deref(),
+        );
+
+        // 超过大小上限的正文写入文件，数据库中只保留截断内容
+        let body = BodyStore::load(&self.pool)
+            .await
+            .prepare(account_id, &parsed.message_id, parsed.body_text.as_deref(), parsed.body_html.as_deref())
+            .await?;
+
+        let is_automated = AutomatedDetector::new(self.pool.clone())
+            .detect(
+                &parsed.from,
+                &parsed.subject,
+                Some(thread_id.as_str()),
+                &parsed.message_id,
+                &AutomatedHeaders {
+                    auto_submitted: parsed.auto_submitted.as_deref(),
+                    precedence: parsed.precedence.as_deref(),
+                    is_report: parsed.unparsed_report,
+                },
+            )
+            .await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO emails (
+                message_id, account_id, thread_id, in_reply_to, subject, sender, sender_name, sender_address,
+                recipients, cc, is_cc_only, date, body_text, body_html, body_truncated, body_path,
+                has_attachments, raw_path, content_fingerprint, is_automated, lang, direction, preview
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(account_id, message_id) DO UPDATE SET
+                thread_id = excluded.thread_id,
+                in_reply_to = excluded.in_reply_to,
+                subject = excluded.subject,
+                sender = excluded.sender,
+                sender_name = excluded.sender_name,
+                sender_address = excluded.sender_address,
+                recipients = excluded.recipients,
+                cc = excluded.cc,
+                is_cc_only = excluded.is_cc_only,
+                date = excluded.date,
+                body_text = excluded.body_text,
+                body_html = excluded.body_html,
+                body_truncated = excluded.body_truncated,
+                body_path = excluded.body_path,
+                has_attachments = excluded.has_attachments,
+                content_fingerprint = excluded.content_fingerprint,
+                is_automated = excluded.is_automated,
+                lang = excluded.lang,
+                direction = excluded.direction,
+                preview = excluded.preview,
+                body_state = 'full'
+            WHERE emails.raw_path IS excluded.raw_path
+            "#
+        )
+        .bind(&parsed.message_id)
+        .bind(account_id)
+        .bind(&thread_id)
+        .bind(&parsed.in_reply_to)
+        .bind(&parsed.subject)
+        .bind(&parsed.from)
+        .bind(&parsed.from_name)
+        .bind(&parsed.from_address)
+        .bind(&recipients)
+        .bind(&cc)
+        .bind(cc_only)
+        .bind(&parsed.date)
+        .bind(&body.text)
+        .bind(&body.html)
+        .bind(body.truncated)
+        .bind(&body.path)
+        .bind(!parsed.attachments.is_empty())
+        .bind(raw_path)
+        .bind(&fingerprint)
+        .bind(is_automated)
+        .bind(detect_language(Some(&parsed.subject), parsed.body_text.as_deref()))
+        .bind(stored_direction.as_str())
+        .bind(preview_for(parsed.body_text.as_deref()))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 根据 message_id 获取邮件 ID
+    async fn get_email_id_by_message_id(&self, message_id: &str, account_id: i64) -> Result<i64, AppError> {
+        let result: (i64,) = sqlx::query_as(
+            "SELECT id FROM emails WHERE message_id = ? AND account_id = ?"
+        )
+        .bind(message_id)
+        .bind(account_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.0)
+    }
+}
+
+/// 计算本次需要同步的 UID（升序，最多 `max_sync_count` 个，999999 表示不限）
+///
+/// 首次同步从 UIDNEXT 往前取一个窗口，不再拉取整个邮箱的 UID。删除邮件后 UID 可能很稀疏，
+/// 窗口内的邮件会少于预期；部分服务器（如 163.com）对 `N:*` 甚至返回空集。
+/// 结果少于邮箱中实际的邮件数时改用 `UID SEARCH ALL` 取最新的部分。
+pub(crate) async fn pending_uids(
+    conn: &mut ImapConnection,
+    exists: usize,
+    last_uid: u32,
+    max_sync_count: usize,
+) -> Result<Vec<u32>, AppError> {
+    let window = UidWindow::new(exists, last_uid, conn.uid_next(), max_sync_count);
+    let Some(range) = window.fetch_range() else {
+        if exists > 0 {
+            log::info!("Sync mode: Incremental, no UIDs after {}", last_uid);
+        }
+        return Ok(Vec::new());
+    };
+    if last_uid == 0 {
+        log::info!("First sync. Fetching UIDs {}", range);
+    } else {
+        log::info!("Sync mode: Incremental from UID {}", last_uid + 1);
+    }
+    let mut uids = window.normalize(conn.fetch_uids(&range).await?);
+
+    if window.is_short(&uids) {
+        log::warn!(
+            "UID FETCH returned {} UIDs but the mailbox has {} messages (UIDNEXT {:?}); falling back to UID SEARCH ALL",
+            uids.len(), exists, window.uid_next
+        );
+        let searched = conn.uid_search("ALL", None).await?;
+        uids = window.merge_search(uids, searched);
+    }
+
+    if let Some(limit) = window.limit.filter(|&limit| uids.len() > limit) {
+        log::info!("Found {} new emails. Limiting to newest {}.", uids.len(), limit);
+    }
+    Ok(window.newest(uids))
+}
+
+/// `pending_uids` 中不依赖连接的部分：取哪段 UID、结果是否偏少、如何截断
+#[derive(Debug, Clone, Copy)]
+struct UidWindow {
+    exists: usize,
+    last_uid: u32,
+    uid_next: Option<u32>,
+    /// 最多同步的邮件数（999999 表示不限，对应 `None`）
+    limit: Option<usize>,
+}
+
+impl UidWindow {
+    fn new(exists: usize, last_uid: u32, uid_next: Option<u32>, max_sync_count: usize) -> Self {
+        Self {
+            exists,
+            last_uid,
+            uid_next,
+            limit: (max_sync_count < 999999).then_some(max_sync_count),
+        }
+    }
+
+    /// `UID FETCH` 的范围，没有需要同步的邮件时为空
+    fn fetch_range(&self) -> Option<String> {
+        if self.exists == 0 {
+            return None;
+        }
+        if self.last_uid == 0 {
+            return Some(match (self.limit, self.uid_next) {
+                (Some(limit), Some(uid_next)) => format!("{}:*", uid_next.saturating_sub(limit as u32).max(1)),
+                _ => "1:*".to_string(),
+            });
+        }
+        if self.uid_next.is_some_and(|uid_next| uid_next <= self.last_uid + 1) {
+            return None;
+        }
+        Some(format!("{}:*", self.last_uid + 1))
+    }
+
+    /// 排序去重，去掉已同步的 UID（"N:*" 在没有更大的 UID 时也会返回最后一封邮件）
+    fn normalize(&self, mut uids: Vec<u32>) -> Vec<u32> {
+        uids.sort_unstable();
+        uids.dedup();
+        uids.retain(|&uid| uid > self.last_uid);
+        uids
+    }
+
+    /// `UID FETCH` 的结果是否少于邮箱中实际应有的邮件数
+    fn is_short(&self, uids: &[u32]) -> bool {
+        if self.last_uid == 0 {
+            uids.len() < self.limit.map_or(self.exists, |limit| limit.min(self.exists))
+        } else {
+            uids.is_empty() && self.uid_next.is_some_and(|uid_next| uid_next > self.last_uid + 1)
+        }
+    }
+
+    /// `UID SEARCH ALL` 找到更多邮件时改用搜索结果
+    fn merge_search(&self, uids: Vec<u32>, searched: Vec<u32>) -> Vec<u32> {
+        let searched = self.normalize(searched);
+        if searched.len() > uids.len() {
+            searched
+        } else {
+            uids
+        }
+    }
+
+    /// 超过上限时只保留最新（UID 最大）的部分
+    fn newest(&self, mut uids: Vec<u32>) -> Vec<u32> {
+        if let Some(limit) = self.limit {
+            if uids.len() > limit {
+                uids.drain(..uids.len() - limit);
+            }
+        }
+        uids
+    }
+}
+
+/// 提取文件扩展名
+pub(crate) fn extract_file_extension(filename: &str) -> String {
+    std::path::Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// 计算 SHA256 哈希
+pub(crate) fn calculate_sha256(data: &[u8]) -> String {
+    use sha2::{Sha256, Digest};
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    format!("{:x}", hasher.finalize())
+}
+
+/// 清理文件名，移除不安全字符
+pub(crate) fn sanitize_filename(filename: &str) -> String {
+    filename
+        .chars()
         .map(|c| match c {
             '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
             _ => c,
         })
         .collect()
 }
+
+/// 首次同步前按抽样的平均邮件大小估算下载量，磁盘放不下时返回 `DiskFull`
+async fn ensure_first_sync_space(conn: &mut ImapConnection, uids: &[u32]) -> Result<(), AppError> {
+    if uids.is_empty() {
+        return Ok(());
+    }
+    let step = (uids.len() / FIRST_SYNC_SIZE_SAMPLE).max(1);
+    let sample: Vec<u32> = uids.iter().step_by(step).take(FIRST_SYNC_SIZE_SAMPLE).copied().collect();
+    let sizes: Vec<u64> = conn
+        .fetch_envelopes(&sample)
+        .await?
+        .iter()
+        .filter_map(|envelope| envelope.size.map(u64::from))
+        .collect();
+    let average = if sizes.is_empty() {
+        DEFAULT_MESSAGE_SIZE
+    } else {
+        sizes.iter().sum::<u64>() / sizes.len() as u64
+    };
+
+    let estimated = average.saturating_mul(uids.len() as u64);
+    log::info!("First sync of {} messages estimated at {} bytes", uids.len(), estimated);
+    disk_space::ensure_available(&file_manager::app_data_dir()?, estimated)
+}
+
+/// 确定已发送文件夹（`\Sent` 标记，服务器不支持 SPECIAL-USE 时按服务商配置和本地化名称匹配）
+async fn resolve_sent_folder(conn: &mut ImapConnection, provider: &ProviderConfig) -> Result<Option<String>, AppError> {
+    let folders = conn.list_folders().await?;
+    let sent = resolve_special_folder(&folders, SpecialUse::Sent, provider.sent_folder.as_deref());
+    // Gmail 的 All Mail 包含所有邮件，即使被配置为已发送文件夹也不同步
+    if conn.supports_gmail_labels() {
+        let all_mail = resolve_special_folder(&folders, SpecialUse::All, None);
+        return Ok(sent.filter(|folder| should_sync_folder(folder, true, all_mail.as_deref())));
+    }
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::ProjectRepository;
+    use crate::storage::database::fixtures::{
+        insert_account, insert_email, insert_project, NewEmail,
+    };
+    use crate::storage::database::test_pool;
+    use crate::storage::remote_content::cache_test_image;
+
+    async fn add_email(
+        pool: &SqlitePool,
+        account_id: i64,
+        message_id: &str,
+        project_id: Option<i64>,
+        duplicate_of: Option<i64>,
+    ) -> i64 {
+        let email = NewEmail {
+            account_id: Some(account_id),
+            message_id,
+            project_id,
+            duplicate_of,
+            ..Default::default()
+        };
+        insert_email(pool, email).await
+    }
+
+    async fn insert_attachment(pool: &SqlitePool, email_id: i64, project_id: Option<i64>) {
+        sqlx::query("INSERT INTO attachments (email_id, project_id, filename) VALUES (?, ?, 'plan.pdf')")
+            .bind(email_id)
+            .bind(project_id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn duplicate_of(pool: &SqlitePool, email_id: i64) -> Option<i64> {
+        sqlx::query_scalar("SELECT duplicate_of FROM emails WHERE id = ?")
+            .bind(email_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn reset_account_promotes_one_canonical_per_duplicate_group() {
+        let (pool, _db_dir) = test_pool().await;
+        let reset = insert_account(&pool, "reset@example.com").await;
+        let other = insert_account(&pool, "other@example.com").await;
+        let third = insert_account(&pool, "third@example.com").await;
+
+        let shared = insert_project(&pool, "Shared").await;
+        let private = insert_project(&pool, "Private").await;
+
+        // 两组重复邮件，规范邮件都在被重置的账户中
+        let first = add_email(&pool, reset, "<a@example.com>", Some(shared), None).await;
+        let second = add_email(&pool, reset, "<b@example.com>", Some(private), None).await;
+        let first_dups = [
+            add_email(&pool, other, "<a@example.com>", Some(shared), Some(first)).await,
+            add_email(&pool, third, "<a@example.com>", Some(shared), Some(first)).await,
+        ];
+        let second_dups = [
+            add_email(&pool, other, "<b@example.com>", None, Some(second)).await,
+            add_email(&pool, third, "<b@example.com>", None, Some(second)).await,
+        ];
+
+        insert_attachment(&pool, first, Some(shared)).await;
+        insert_attachment(&pool, first_dups[0], Some(shared)).await;
+        sqlx::query("UPDATE projects SET email_count = 3, attachment_count = 2 WHERE id = ?")
+            .bind(shared)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let summary = EmailSyncer::new(pool.clone(), EventEmitter::noop())
+            .reset_account(reset)
+            .await
+            .unwrap();
+        assert_eq!(summary.deleted_emails, 2);
+        assert_eq!(summary.deleted_attachments, 1);
+
+        for dups in [first_dups, second_dups] {
+            assert_eq!(duplicate_of(&pool, dups[0]).await, None, "lowest surviving id becomes canonical");
+            assert_eq!(duplicate_of(&pool, dups[1]).await, Some(dups[0]));
+        }
+
+        // 仍有其他账户邮件的项目保留，只剩本账户邮件的项目被删除
+        let projects: Vec<i64> = sqlx::query_scalar("SELECT id FROM projects ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(projects, vec![shared]);
+        assert_eq!(summary.deleted_projects, 1);
+
+        // 与 reset_account_sync 命令相同，重置后重新计算共享项目的统计：只剩提升后的规范邮件及其附件
+        ProjectRepository::new(pool.clone()).recompute_stats(None).await.unwrap();
+        let stats: (i64, i64) = sqlx::query_as("SELECT email_count, attachment_count FROM projects WHERE id = ?")
+            .bind(shared)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stats, (1, 1));
+    }
+
+    #[tokio::test]
+    async fn reset_account_deletes_calendar_events_before_their_emails() {
+        let (pool, _db_dir) = test_pool().await;
+        let reset = insert_account(&pool, "reset@example.com").await;
+        let other = insert_account(&pool, "other@example.com").await;
+
+        for account_id in [reset, other] {
+            let email_id = add_email(&pool, account_id, "<invite@example.com>", None, None).await;
+            sqlx::query("INSERT INTO calendar_events (account_id, email_id, uid, raw_ics) VALUES (?, ?, 'kickoff', '')")
+                .bind(account_id)
+                .bind(email_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let summary = EmailSyncer::new(pool.clone(), EventEmitter::noop())
+            .reset_account(reset)
+            .await
+            .unwrap();
+        assert_eq!(summary.deleted_emails, 1);
+
+        let events: Vec<i64> = sqlx::query_scalar("SELECT account_id FROM calendar_events")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(events, vec![other]);
+    }
+
+    #[tokio::test]
+    async fn reset_account_releases_cached_images_only_it_referenced() {
+        let (pool, _db_dir) = test_pool().await;
+        let reset = insert_account(&pool, "reset@example.com").await;
+        let other = insert_account(&pool, "other@example.com").await;
+        let reset_email = add_email(&pool, reset, "<a@example.com>", None, None).await;
+        let other_email = add_email(&pool, other, "<b@example.com>", None, None).await;
+
+        let private = cache_test_image(&pool, reset_email, "resetprivate.png").await;
+        let shared = cache_test_image(&pool, reset_email, "resetshared.png").await;
+        cache_test_image(&pool, other_email, "resetshared.png").await;
+
+        EmailSyncer::new(pool.clone(), EventEmitter::noop())
+            .reset_account(reset)
+            .await
+            .unwrap();
+
+        assert!(!private.exists());
+        assert!(shared.exists());
+        let cached: Vec<String> = sqlx::query_scalar("SELECT file_name FROM remote_content_cache")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(cached, vec!["resetshared.png"]);
+        let refs: Vec<i64> = sqlx::query_scalar("SELECT email_id FROM remote_content_refs")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(refs, vec![other_email]);
+    }
+
+    #[test]
+    fn first_sync_fetches_a_window_below_uidnext() {
+        let window = UidWindow::new(500, 0, Some(1001), 100);
+        assert_eq!(window.fetch_range().as_deref(), Some("901:*"));
+        assert_eq!(UidWindow::new(500, 0, Some(50), 100).fetch_range().as_deref(), Some("1:*"));
+        assert_eq!(UidWindow::new(500, 0, Some(1001), 999999).fetch_range().as_deref(), Some("1:*"));
+        assert_eq!(UidWindow::new(500, 0, None, 100).fetch_range().as_deref(), Some("1:*"));
+        assert_eq!(UidWindow::new(0, 0, Some(1001), 100).fetch_range(), None);
+    }
+
+    #[test]
+    fn incremental_sync_skips_fetch_when_uidnext_has_not_moved() {
+        assert_eq!(UidWindow::new(10, 40, Some(41), 100).fetch_range(), None);
+        assert_eq!(UidWindow::new(10, 40, Some(45), 100).fetch_range().as_deref(), Some("41:*"));
+        assert_eq!(UidWindow::new(10, 40, None, 100).fetch_range().as_deref(), Some("41:*"));
+    }
+
+    #[test]
+    fn normalize_drops_already_synced_uid_returned_by_open_range() {
+        let window = UidWindow::new(10, 40, Some(41), 100);
+        assert_eq!(window.normalize(vec![40]), Vec::<u32>::new());
+        assert_eq!(window.normalize(vec![43, 41, 43, 39]), vec![41, 43]);
+    }
+
+    #[test]
+    fn sparse_first_sync_falls_back_to_search() {
+        // 删除邮件后 UID 稀疏：窗口 901:* 内只有 3 封，邮箱共 500 封
+        let window = UidWindow::new(500, 0, Some(1001), 100);
+        let fetched = window.normalize(vec![950, 960, 1000]);
+        assert!(window.is_short(&fetched));
+
+        let searched: Vec<u32> = (1..=500).map(|i| i * 2).collect();
+        let uids = window.newest(window.merge_search(fetched, searched));
+        assert_eq!(uids.len(), 100);
+        assert_eq!(uids.first(), Some(&802));
+        assert_eq!(uids.last(), Some(&1000));
+    }
+
+    #[test]
+    fn full_first_sync_window_is_not_short() {
+        let window = UidWindow::new(500, 0, Some(1001), 100);
+        assert!(!window.is_short(&(901..=1000).collect::<Vec<u32>>()));
+        // 邮件数少于上限时按邮箱中的邮件数判断
+        let small = UidWindow::new(3, 0, Some(4), 100);
+        assert!(!small.is_short(&[1, 2, 3]));
+        assert!(small.is_short(&[]));
+        // 不限数量时按邮箱中的邮件数判断
+        let unlimited = UidWindow::new(5, 0, Some(6), 999999);
+        assert!(unlimited.is_short(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn incremental_sync_is_short_only_when_empty_and_uidnext_moved() {
+        let window = UidWindow::new(10, 40, Some(45), 100);
+        assert!(window.is_short(&[]));
+        assert!(!window.is_short(&[41]));
+        assert!(!UidWindow::new(10, 40, None, 100).is_short(&[]));
+    }
+
+    #[test]
+    fn merge_search_keeps_fetch_result_unless_search_finds_more() {
+        let window = UidWindow::new(10, 40, Some(45), 100);
+        assert_eq!(window.merge_search(vec![41, 42], vec![10, 41]), vec![41, 42]);
+        assert_eq!(window.merge_search(vec![], vec![44, 10, 42]), vec![42, 44]);
+    }
+
+    #[test]
+    fn newest_trims_to_limit_from_the_top() {
+        let window = UidWindow::new(10, 0, Some(11), 3);
+        assert_eq!(window.newest(vec![1, 2, 3, 4, 5]), vec![3, 4, 5]);
+        assert_eq!(window.newest(vec![1, 2]), vec![1, 2]);
+        let unlimited = UidWindow::new(10, 0, Some(11), 999999);
+        assert_eq!(unlimited.newest((1..=10).collect()).len(), 10);
+    }
+
+    // ---- 与进程内 IMAP 服务器的集成测试 ----
+
+    use crate::mail::test_server::TestImapServer;
+
+    fn message(id: u32, subject: &str) -> Vec<u8> {
+        format!(
+            "From: Alice <alice@example.com>\r\n\
+             To: me@example.com\r\n\
+             Subject: {}\r\n\
+             Message-ID: <msg-{}@example.com>\r\n\
+             Date: Mon, 12 Oct 2026 09:{:02}:00 +0000\r\n\
+             \r\n\
+             Body of message {}.\r\n",
+            subject, id, id % 60, id
+        )
+        .into_bytes()
+    }
+
+    async fn sync_with(pool: &SqlitePool, account_id: i64, server: &TestImapServer) -> SyncProgress {
+        file_manager::use_test_data_dir();
+        EmailSyncer::new(pool.clone(), EventEmitter::noop())
+            .sync_account(account_id, server.auth(), &server.provider())
+            .await
+            .expect("sync against the test server")
+    }
+
+    async fn set_max_sync_count(pool: &SqlitePool, count: i64) {
+        sqlx::query("UPDATE sync_settings SET max_sync_count = ? WHERE id = 1")
+            .bind(count)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn synced_uids(pool: &SqlitePool, account_id: i64) -> Vec<i64> {
+        sqlx::query_scalar(
+            "SELECT CAST(raw_path AS INTEGER) FROM emails WHERE account_id = ? AND raw_path GLOB '[0-9]*' ORDER BY 1"
+        )
+        .bind(account_id)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    fn downloaded(server: &TestImapServer) -> Vec<String> {
+        server
+            .commands()
+            .into_iter()
+            .filter(|command| command.starts_with("UID FETCH") && command.ends_with(" RFC822"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn first_sync_downloads_the_newest_messages_up_to_the_limit() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        set_max_sync_count(&pool, 3).await;
+        let server = TestImapServer::start().await;
+        for id in 1..=5 {
+            server.deliver("INBOX", &message(id, &format!("Quarterly report {}", id)));
+        }
+
+        let progress = sync_with(&pool, account_id, &server).await;
+        assert_eq!(progress.current, 3);
+        assert_eq!(synced_uids(&pool, account_id).await, vec![3, 4, 5]);
+        assert_eq!(downloaded(&server), vec!["UID FETCH 3 RFC822", "UID FETCH 4 RFC822", "UID FETCH 5 RFC822"]);
+
+        let unclassified: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails WHERE project_id IS NULL")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(unclassified, 0);
+        // 首次同步完成后清除检查点
+        assert!(SyncCheckpointStore::new(pool.clone()).load(account_id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn incremental_sync_fetches_only_new_messages() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let server = TestImapServer::start().await;
+        server.deliver("INBOX", &message(1, "Kickoff meeting notes"));
+        server.deliver("INBOX", &message(2, "Budget draft for review"));
+        assert_eq!(sync_with(&pool, account_id, &server).await.current, 2);
+
+        server.deliver("INBOX", &message(3, "Venue options"));
+        server.deliver("INBOX", &message(4, "Catering quotes"));
+        server.clear_commands();
+        let progress = sync_with(&pool, account_id, &server).await;
+
+        assert_eq!(progress.current, 2);
+        assert!(server.commands().iter().any(|command| command == "UID FETCH 3:* UID"));
+        assert_eq!(downloaded(&server), vec!["UID FETCH 3 RFC822", "UID FETCH 4 RFC822"]);
+        assert_eq!(synced_uids(&pool, account_id).await, vec![1, 2, 3, 4]);
+
+        // 没有新邮件时 "5:*" 返回最后一封，不会重复下载
+        server.clear_commands();
+        assert_eq!(sync_with(&pool, account_id, &server).await.current, 0);
+        assert!(downloaded(&server).is_empty());
+    }
+
+    #[tokio::test]
+    async fn deleted_messages_leave_gaps_that_fall_back_to_search() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        set_max_sync_count(&pool, 3).await;
+        let server = TestImapServer::start().await;
+        for id in 1..=42 {
+            server.deliver("INBOX", &message(id, &format!("Site survey photos {}", id)));
+        }
+        for uid in 3..=40 {
+            server.expunge("INBOX", uid);
+        }
+
+        // UIDNEXT 43、限额 3：按范围只取到 41 和 42，改用 UID SEARCH ALL 补齐
+        let progress = sync_with(&pool, account_id, &server).await;
+        assert_eq!(progress.current, 3);
+        assert!(server.commands().iter().any(|command| command == "UID SEARCH ALL"));
+        assert_eq!(synced_uids(&pool, account_id).await, vec![2, 41, 42]);
+    }
+
+    #[tokio::test]
+    async fn a_message_that_fails_to_parse_is_skipped() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let server = TestImapServer::start().await;
+        server.deliver("INBOX", &message(1, "Contract signed"));
+        server.deliver("INBOX", b"");
+        server.deliver("INBOX", &message(3, "Invoice attached"));
+
+        let progress = sync_with(&pool, account_id, &server).await;
+        assert_eq!(progress.status, "completed");
+        assert_eq!(downloaded(&server).len(), 3);
+        assert_eq!(synced_uids(&pool, account_id).await, vec![1, 3]);
+
+        let run: (i64, Option<String>) =
+            sqlx::query_as("SELECT emails_synced, error FROM sync_runs WHERE account_id = ?")
+                .bind(account_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(run, (3, None));
+    }
+
+    #[tokio::test]
+    async fn attachments_are_saved_with_the_email() {
+        use base64::Engine;
+
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let server = TestImapServer::start().await;
+        let content = b"Floor plan, revision 3".to_vec();
+        let raw = format!(
+            "From: Alice <alice@example.com>\r\n\
+             To: me@example.com\r\n\
+             Subject: Floor plan for the new office\r\n\
+             Message-ID: <plan@example.com>\r\n\
+             Date: Mon, 12 Oct 2026 09:00:00 +0000\r\n\
+             MIME-Version: 1.0\r\n\
+             Content-Type: multipart/mixed; boundary=\"b1\"\r\n\
+             \r\n\
+             --b1\r\n\
+             Content-Type: text/plain; charset=utf-8\r\n\
+             \r\n\
+             Plan attached.\r\n\
+             --b1\r\n\
+             Content-Type: application/octet-stream; name=\"plan.bin\"\r\n\
+             Content-Disposition: attachment; filename=\"plan.bin\"\r\n\
+             Content-Transfer-Encoding: base64\r\n\
+             \r\n\
+             {}\r\n\
+             --b1--\r\n",
+            base64::engine::general_purpose::STANDARD.encode(&content)
+        );
+        server.deliver("INBOX", raw.as_bytes());
+
+        sync_with(&pool, account_id, &server).await;
+
+        let (email_id, project_id): (i64, Option<i64>) =
+            sqlx::query_as("SELECT id, project_id FROM emails WHERE message_id = 'plan@example.com'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        let (filename, attachment_project, path): (String, Option<i64>, String) =
+            sqlx::query_as("SELECT filename, project_id, file_path FROM attachments WHERE email_id = ?")
+                .bind(email_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(filename, "plan.bin");
+        assert!(project_id.is_some());
+        assert_eq!(attachment_project, project_id);
+        let stored = std::fs::read(file_manager::attachments_root().unwrap().join(path)).unwrap();
+        assert_eq!(stored, content);
+    }
+
+    #[tokio::test]
+    async fn gmail_messages_in_several_folders_are_stored_once() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let server = TestImapServer::start_gmail().await;
+        server.add_folder("[Gmail]/Sent Mail", Some("\\Sent"));
+        server.add_folder("[Gmail]/All Mail", Some("\\All"));
+
+        let question = message(1, "Warehouse lease renewal");
+        // 发给自己的回复同时在收件箱和已发送中，X-GM-MSGID 相同
+        let own_reply = "From: Me <me@example.com>\r\n\
+             To: me@example.com, alice@example.com\r\n\
+             Subject: Re: Warehouse lease renewal\r\n\
+             Message-ID: <own-reply@example.com>\r\n\
+             In-Reply-To: <msg-1@example.com>\r\n\
+             References: <msg-1@example.com>\r\n\
+             Date: Mon, 12 Oct 2026 10:00:00 +0000\r\n\
+             \r\n\
+             Looping myself in.\r\n";
+        let sent_reply = own_reply
+            .replace("own-reply@", "sent-reply@")
+            .replace("me@example.com, alice@example.com", "alice@example.com")
+            .replace("10:00:00", "11:00:00");
+        for folder in ["INBOX", "[Gmail]/All Mail"] {
+            server.deliver_gmail(folder, &question, 1001, &["\\Inbox"]);
+            server.deliver_gmail(folder, own_reply.as_bytes(), 1002, &["\\Inbox", "\\Sent"]);
+        }
+        server.deliver_gmail("[Gmail]/All Mail", sent_reply.as_bytes(), 1003, &["\\Sent"]);
+        server.deliver_gmail("[Gmail]/Sent Mail", own_reply.as_bytes(), 1002, &["\\Inbox", "\\Sent"]);
+        server.deliver_gmail("[Gmail]/Sent Mail", sent_reply.as_bytes(), 1003, &["\\Sent"]);
+
+        sync_with(&pool, account_id, &server).await;
+
+        let emails: Vec<(i64, String, Option<String>)> =
+            sqlx::query_as("SELECT id, message_id, gm_msgid FROM emails WHERE account_id = ? ORDER BY gm_msgid")
+                .bind(account_id)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        let gm_msgids: Vec<Option<String>> = emails.iter().map(|email| email.2.clone()).collect();
+        assert_eq!(gm_msgids, vec![Some("1001".into()), Some("1002".into()), Some("1003".into())]);
+
+        let folders = FolderStore::new(pool.clone());
+        let own_reply_folders: Vec<(String, Option<i64>)> = folders
+            .folders_for(emails[1].0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|folder| (folder.folder, folder.uid))
+            .collect();
+        assert_eq!(
+            own_reply_folders,
+            vec![("INBOX".to_string(), Some(2)), ("[Gmail]/Sent Mail".to_string(), Some(1))]
+        );
+
+        // All Mail 从不选择；已发送中只下载收件箱里没有的那封
+        let commands = server.commands();
+        assert!(!commands.iter().any(|command| command.contains("All Mail\"") && command.starts_with("SELECT")));
+        let sent_selected = commands
+            .iter()
+            .position(|command| command == "SELECT \"[Gmail]/Sent Mail\"")
+            .expect("sent folder is selected");
+        let sent_downloads: Vec<&String> = commands[sent_selected..]
+            .iter()
+            .filter(|command| command.ends_with(" RFC822"))
+            .collect();
+        assert_eq!(sent_downloads, vec!["UID FETCH 2 RFC822"]);
+        assert_eq!(downloaded(&server).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn sent_replies_are_found_in_a_localized_sent_folder() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let server = TestImapServer::start().await;
+        // 服务器没有 SPECIAL-USE 标记，按本地化名称找到已发送文件夹
+        server.add_folder("Papierkorb", None);
+        server.add_folder("Gesendete Elemente", None);
+        server.deliver("INBOX", &message(1, "Angebot Renovierung"));
+        let reply = "From: Me <me@example.com>\r\n\
+             To: alice@example.com\r\n\
+             Subject: Re: Angebot Renovierung\r\n\
+             Message-ID: <reply-1@example.com>\r\n\
+             In-Reply-To: <msg-1@example.com>\r\n\
+             Date: Mon, 12 Oct 2026 10:00:00 +0000\r\n\
+             \r\n\
+             Danke, passt.\r\n";
+        server.deliver("Gesendete Elemente", reply.as_bytes());
+        server.deliver("Gesendete Elemente", &message(2, "Unrelated newsletter signup"));
+
+        sync_with(&pool, account_id, &server).await;
+
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT message_id, raw_path FROM emails WHERE account_id = ? ORDER BY id")
+                .bind(account_id)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("msg-1@example.com".to_string(), "1".to_string()),
+                ("reply-1@example.com".to_string(), format!("{}1", SENT_RAW_PATH_PREFIX)),
+            ]
+        );
+        assert!(server.commands().iter().any(|command| command == "SELECT \"Gesendete Elemente\""));
+    }
+
+    #[tokio::test]
+    async fn messages_without_message_id_are_all_kept() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let server = TestImapServer::start().await;
+        let bounce = b"From: MAILER-DAEMON@example.com\r\n\
+            To: me@example.com\r\n\
+            Subject: Undelivered Mail Returned to Sender\r\n\
+            Date: Mon, 12 Oct 2026 09:00:00 +0000\r\n\
+            \r\n\
+            Delivery failed.\r\n";
+        for _ in 0..3 {
+            server.deliver("INBOX", bounce);
+        }
+
+        sync_with(&pool, account_id, &server).await;
+        assert_eq!(synced_uids(&pool, account_id).await, vec![1, 2, 3]);
+        let distinct: i64 = sqlx::query_scalar(
+            "SELECT COUNT(DISTINCT message_id) FROM emails WHERE account_id = ? AND message_id LIKE 'generated-%'"
+        )
+        .bind(account_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(distinct, 3);
+    }
+
+    #[tokio::test]
+    async fn another_message_with_the_same_message_id_does_not_replace_the_row() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let server = TestImapServer::start().await;
+        server.deliver("INBOX", &message(1, "Signed contract"));
+        sync_with(&pool, account_id, &server).await;
+        let (email_id, project_id): (i64, Option<i64>) =
+            sqlx::query_as("SELECT id, project_id FROM emails WHERE account_id = ?")
+                .bind(account_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        sqlx::query("UPDATE emails SET is_starred = 1, is_read = 1 WHERE id = ?")
+            .bind(email_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // 不同内容、相同 Message-ID 的另一封邮件
+        let impostor = String::from_utf8(message(1, "Completely different subject"))
+            .unwrap()
+            .replace("Body of message 1.", "Other body.");
+        server.deliver("INBOX", impostor.as_bytes());
+        sync_with(&pool, account_id, &server).await;
+
+        let rows: Vec<(i64, String, String, Option<i64>, bool, bool)> = sqlx::query_as(
+            "SELECT id, subject, raw_path, project_id, is_starred, is_read FROM emails WHERE account_id = ?"
+        )
+        .bind(account_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(rows, vec![(email_id, "Signed contract".to_string(), "1".to_string(), project_id, true, true)]);
+        let uids: Vec<(String, Option<i64>)> = FolderStore::new(pool.clone())
+            .folders_for(email_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|folder| (folder.folder, folder.uid))
+            .collect();
+        assert_eq!(uids, vec![("INBOX".to_string(), Some(2))]);
+    }
+
+    #[tokio::test]
+    async fn resaving_the_same_message_updates_in_place() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let syncer = EmailSyncer::new(pool.clone(), EventEmitter::noop());
+        let mut parsed = parse_email_for_account(&message(5, "Draft agenda"), account_id).unwrap();
+
+        assert!(syncer.save_email(account_id, "5", &parsed, MailDirection::Incoming).await.unwrap());
+        let email_id = syncer.get_email_id_by_message_id(&parsed.message_id, account_id).await.unwrap();
+        sqlx::query("UPDATE emails SET is_starred = 1 WHERE id = ?")
+            .bind(email_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // 同一 UID 重新下载：原地更新，ID 和状态不变
+        parsed.subject = "Final agenda".to_string();
+        assert!(syncer.save_email(account_id, "5", &parsed, MailDirection::Incoming).await.unwrap());
+        // 来源不同：不写入
+        parsed.subject = "Overwritten".to_string();
+        assert!(!syncer.save_email(account_id, "9", &parsed, MailDirection::Incoming).await.unwrap());
+
+        let row: (i64, String, bool) = sqlx::query_as("SELECT id, subject, is_starred FROM emails WHERE account_id = ?")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(row, (email_id, "Final agenda".to_string(), true));
+    }
+
+    #[tokio::test]
+    async fn preview_is_stored_when_the_email_is_saved() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let raw = "From: Alice <alice@example.com>\r\n\
+            Subject: Re: Budget\r\n\
+            Message-ID: <budget-reply@example.com>\r\n\
+            \r\n\
+            Approved, go ahead.\r\n\
+            \r\n\
+            On Mon, 12 Oct 2026, Bob <bob@example.com> wrote:\r\n\
+            > Can we spend more?\r\n";
+        let parsed = parse_email_for_account(raw.as_bytes(), account_id).unwrap();
+        let syncer = EmailSyncer::new(pool.clone(), EventEmitter::noop());
+        syncer.save_email(account_id, "1", &parsed, MailDirection::Incoming).await.unwrap();
+
+        let preview: Option<String> = sqlx::query_scalar("SELECT preview FROM emails WHERE account_id = ?")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(preview.as_deref(), Some("Approved, go ahead."));
+    }
+}
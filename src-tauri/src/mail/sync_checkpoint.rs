@@ -0,0 +1,126 @@
+/// 首次同步检查点
+///
+/// 首次同步可能要下载几千封邮件，中途关闭应用后需要从断点继续，而不是重新计算范围。
+/// 首次同步开始时记录目标 UID 范围和总数，每处理完一批（完整下载时为一封，按流量计费模式为一批邮件头）
+/// 更新已完成的最大 UID 和计数；下次同步发现检查点时只处理范围内剩余的 UID，进度按原来的总数计算。
+/// 范围处理完后删除检查点，之后的同步恢复为增量同步。
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 账户的首次同步检查点
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SyncCheckpoint {
+    pub account_id: i64,
+    /// 目标范围内最小的 UID
+    pub first_uid: i64,
+    /// 目标范围内最大的 UID
+    pub last_uid: i64,
+    /// 已处理的最大 UID（0 表示尚未开始）
+    pub last_completed_uid: i64,
+    /// 首次同步开始时计算出的邮件总数
+    pub total: i64,
+    pub completed: i64,
+    pub failed: i64,
+    pub started_at: String,
+    pub updated_at: String,
+}
+
+impl SyncCheckpoint {
+    /// 已处理（成功或失败）的邮件数
+    pub fn processed(&self) -> usize {
+        (self.completed + self.failed).max(0) as usize
+    }
+
+    /// 剩余 UID 的 FETCH 范围（范围已处理完时返回 None）
+    pub fn remaining_range(&self) -> Option<String> {
+        let start = (self.last_completed_uid + 1).max(self.first_uid);
+        (start <= self.last_uid).then(|| format!("{}:{}", start, self.last_uid))
+    }
+}
+
+/// 检查点存储
+pub struct SyncCheckpointStore {
+    pool: SqlitePool,
+}
+
+impl SyncCheckpointStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn load(&self, account_id: i64) -> Result<Option<SyncCheckpoint>, AppError> {
+        let checkpoint = sqlx::query_as::<_, SyncCheckpoint>(
+            r#"
+            SELECT account_id, first_uid, last_uid, last_completed_uid, total, completed, failed,
+                   started_at, updated_at
+            FROM sync_checkpoints
+            WHERE account_id = ?
+            "#
+        )
+        .bind(account_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(checkpoint)
+    }
+
+    /// 记录首次同步的目标范围（`uids` 升序）
+    pub async fn start(&self, account_id: i64, uids: &[u32]) -> Result<(), AppError> {
+        let (Some(first), Some(last)) = (uids.first(), uids.last()) else {
+            return Ok(());
+        };
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO sync_checkpoints (
+                account_id, first_uid, last_uid, last_completed_uid, total, completed, failed,
+                started_at, updated_at
+            ) VALUES (?, ?, ?, 0, ?, 0, 0, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            "#
+        )
+        .bind(account_id)
+        .bind(*first as i64)
+        .bind(*last as i64)
+        .bind(uids.len() as i64)
+        .execute(&self.pool)
+        .await?;
+
+        log::info!(
+            "Started first sync checkpoint for account {}: UIDs {}..{} ({} messages)",
+            account_id, first, last, uids.len()
+        );
+        Ok(())
+    }
+
+    /// 一批处理完成后推进检查点
+    pub async fn advance(&self, account_id: i64, last_uid: u32, completed: usize, failed: usize) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE sync_checkpoints
+            SET last_completed_uid = MAX(last_completed_uid, ?),
+                completed = completed + ?,
+                failed = failed + ?,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE account_id = ?
+            "#
+        )
+        .bind(last_uid as i64)
+        .bind(completed as i64)
+        .bind(failed as i64)
+        .bind(account_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn clear(&self, account_id: i64) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM sync_checkpoints WHERE account_id = ?")
+            .bind(account_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+/// 同步记录
+///
+/// 每次同步写入一条记录（同步数量、传输字节数、是否按流量计费模式），供界面查看历史。
+/// 分类器达到单次新建项目上限时记录未分类的邮件数。
+/// 开启协议跟踪时记录跟踪文件路径；IMAP 会话指标以 JSON 保存在 `metrics` 列。
+use crate::error::AppError;
+use crate::mail::session_metrics::SyncMetrics;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 同步记录列表的默认数量
+pub const DEFAULT_SYNC_RUN_LIMIT: i64 = 50;
+
+/// 单次同步记录
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SyncRun {
+    pub id: i64,
+    pub account_id: i64,
+    pub status: String,
+    pub metered: bool,
+    pub emails_synced: i64,
+    pub bytes_transferred: i64,
+    pub error: Option<String>,
+    pub trace_path: Option<String>,
+    /// 达到新建项目上限后保持未分类的邮件数
+    pub projects_capped: i64,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+/// 同步指标序列中的一项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRunMetrics {
+    pub run_id: i64,
+    pub status: String,
+    pub metered: bool,
+    pub started_at: String,
+    pub metrics: SyncMetrics,
+}
+
+/// 同步记录存储
+pub struct SyncRunLog {
+    pool: SqlitePool,
+}
+
+impl SyncRunLog {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 记录同步开始，返回记录 ID
+    pub async fn start(&self, account_id: i64, metered: bool) -> Result<i64, AppError> {
+        let id = sqlx::query("INSERT INTO sync_runs (account_id, metered) VALUES (?, ?)")
+            .bind(account_id)
+            .bind(metered)
+            .execute(&self.pool)
+            .await?
+            .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    /// 记录本次同步的协议跟踪文件
+    pub async fn set_trace_path(&self, run_id: i64, path: &str) -> Result<(), AppError> {
+        sqlx::query("UPDATE sync_runs SET trace_path = ? WHERE id = ?")
+            .bind(path)
+            .bind(run_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 记录本次同步因达到新建项目上限而未分类的邮件数
+    pub async fn set_projects_capped(&self, run_id: i64, capped: usize) -> Result<(), AppError> {
+        sqlx::query("UPDATE sync_runs SET projects_capped = ? WHERE id = ?")
+            .bind(capped as i64)
+            .bind(run_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 记录同步结束
+    pub async fn finish(
+        &self,
+        run_id: i64,
+        emails_synced: usize,
+        bytes_transferred: u64,
+        error: Option<&str>,
+        metrics: Option<&SyncMetrics>,
+    ) -> Result<(), AppError> {
+        let metrics = metrics.map(serde_json::to_string).transpose()?;
+        sqlx::query(
+            r#"
+            UPDATE sync_runs
+            SET status = ?, emails_synced = ?, bytes_transferred = ?, error = ?, metrics = ?,
+                finished_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#
+        )
+        .bind(if error.is_some() { "failed" } else { "completed" })
+        .bind(emails_synced as i64)
+        .bind(bytes_transferred as i64)
+        .bind(error)
+        .bind(&metrics)
+        .bind(run_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 获取同步记录（最新的在前，可限定账户）
+    pub async fn list(&self, account_id: Option<i64>, limit: i64) -> Result<Vec<SyncRun>, AppError> {
+        let runs = sqlx::query_as::<_, SyncRun>(
+            r#"
+            SELECT id, account_id, status, metered, emails_synced, bytes_transferred, error, trace_path,
+                   COALESCE(projects_capped, 0) AS projects_capped,
+                   COALESCE(started_at, '') AS started_at, finished_at
+            FROM sync_runs
+            WHERE ? IS NULL OR account_id = ?
+            ORDER BY id DESC
+            LIMIT ?
+            "#
+        )
+        .bind(account_id)
+        .bind(account_id)
+        .bind(limit.max(1))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(runs)
+    }
+
+    /// 账户最近 `last_n_runs` 次同步的指标，按时间先后排列（供诊断图表使用）
+    pub async fn metrics_series(&self, account_id: i64, last_n_runs: i64) -> Result<Vec<SyncRunMetrics>, AppError> {
+        let rows: Vec<(i64, String, bool, String, String)> = sqlx::query_as(
+            r#"
+            SELECT id, status, metered, COALESCE(started_at, ''), metrics
+            FROM sync_runs
+            WHERE account_id = ? AND metrics IS NOT NULL
+            ORDER BY id DESC
+            LIMIT ?
+            "#
+        )
+        .bind(account_id)
+        .bind(last_n_runs.max(1))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut series: Vec<SyncRunMetrics> = rows
+            .into_iter()
+            .filter_map(|(run_id, status, metered, started_at, metrics)| {
+                let metrics = serde_json::from_str(&metrics)
+                    .map_err(|e| log::warn!("Ignoring unreadable metrics for sync run {}: {}", run_id, e))
+                    .ok()?;
+                Some(SyncRunMetrics {
+                    run_id,
+                    status,
+                    metered,
+                    started_at,
+                    metrics,
+                })
+            })
+            .collect();
+        series.reverse();
+        Ok(series)
+    }
+}
@@ -0,0 +1,333 @@
+/// 常用回复模板
+///
+/// 模板的主题和正文中可以使用 `{{placeholder}}` 占位符，渲染时从目标邮件、所属项目和设置中取值；
+/// `{{name|默认值}}` 在取不到值时使用默认值，没有默认值时替换为空并在结果中列出。
+/// 只有标记为 HTML 的模板才对替换的值做 HTML 转义，纯文本模板原样填入。
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// 支持的占位符
+pub const PLACEHOLDERS: &[&str] = &[
+    "sender_name",
+    "sender_first_name",
+    "sender_email",
+    "subject",
+    "project_name",
+    "my_name",
+    "my_email",
+    "date",
+    "today",
+];
+
+/// 回复模板
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct EmailTemplate {
+    pub id: i64,
+    pub name: String,
+    pub subject_template: String,
+    pub body_template: String,
+    pub is_html: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// 创建或更新模板的请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateRequest {
+    pub name: String,
+    #[serde(default)]
+    pub subject_template: String,
+    #[serde(default)]
+    pub body_template: String,
+    #[serde(default)]
+    pub is_html: bool,
+}
+
+/// 渲染结果（交给撰写 / 发送使用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderedTemplate {
+    pub template_id: i64,
+    pub email_id: i64,
+    pub subject: String,
+    pub body: String,
+    pub is_html: bool,
+    /// 取不到值且没有默认值的占位符
+    pub missing: Vec<String>,
+}
+
+/// 模板存储
+pub struct TemplateStore {
+    pool: SqlitePool,
+}
+
+impl TemplateStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 获取所有模板（按名称排序）
+    pub async fn list(&self) -> Result<Vec<EmailTemplate>, AppError> {
+        let templates = sqlx::query_as::<_, EmailTemplate>(
+            r#"
+            SELECT id, name, subject_template, body_template, COALESCE(is_html, 0) AS is_html,
+                   created_at, updated_at
+            FROM templates
+            ORDER BY name COLLATE NOCASE
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(templates)
+    }
+
+    pub async fn get(&self, id: i64) -> Result<EmailTemplate, AppError> {
+        sqlx::query_as::<_, EmailTemplate>(
+            r#"
+            SELECT id, name, subject_template, body_template, COALESCE(is_html, 0) AS is_html,
+                   created_at, updated_at
+            FROM templates
+            WHERE id = ?
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::Validation(format!("Template {} not found", id)))
+    }
+
+    /// 创建模板，返回 ID
+    pub async fn create(&self, request: &TemplateRequest) -> Result<i64, AppError> {
+        let name = validate(request)?;
+        let id = sqlx::query(
+            "INSERT INTO templates (name, subject_template, body_template, is_html) VALUES (?, ?, ?, ?)"
+        )
+        .bind(name)
+        .bind(&request.subject_template)
+        .bind(&request.body_template)
+        .bind(request.is_html)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| duplicate_name_error(e, name))?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    pub async fn update(&self, id: i64, request: &TemplateRequest) -> Result<EmailTemplate, AppError> {
+        let name = validate(request)?;
+        let result = sqlx::query(
+            r#"
+            UPDATE templates
+            SET name = ?, subject_template = ?, body_template = ?, is_html = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#
+        )
+        .bind(name)
+        .bind(&request.subject_template)
+        .bind(&request.body_template)
+        .bind(request.is_html)
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| duplicate_name_error(e, name))?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::Validation(format!("Template {} not found", id)));
+        }
+
+        self.get(id).await
+    }
+
+    pub async fn delete(&self, id: i64) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM templates WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 用目标邮件填充模板
+    pub async fn render(&self, template_id: i64, email_id: i64) -> Result<RenderedTemplate, AppError> {
+        let template = self.get(template_id).await?;
+        let values = self.placeholder_values(email_id).await?;
+        let original_subject = values.get("subject").cloned().unwrap_or_default();
+
+        let mut missing = Vec::new();
+        let subject = if template.subject_template.trim().is_empty() {
+            reply_subject(&original_subject)
+        } else {
+            // 主题总是纯文本
+            fill(&template.subject_template, &values, false, &mut missing)
+        };
+        let body = fill(&template.body_template, &values, template.is_html, &mut missing);
+        missing.sort();
+        missing.dedup();
+
+        Ok(RenderedTemplate {
+            template_id,
+            email_id,
+            subject,
+            body,
+            is_html: template.is_html,
+            missing,
+        })
+    }
+
+    /// 占位符的取值（取不到的不放入）
+    async fn placeholder_values(&self, email_id: i64) -> Result<HashMap<&'static str, String>, AppError> {
+        #[derive(sqlx::FromRow)]
+        struct SourceRow {
+            sender_name: Option<String>,
+            sender_address: Option<String>,
+            subject: Option<String>,
+            date: Option<String>,
+            project_name: Option<String>,
+            my_email: Option<String>,
+        }
+
+        let row = sqlx::query_as::<_, SourceRow>(
+            r#"
+            SELECT e.sender_name, e.sender_address, e.subject, e.date, p.name AS project_name, a.email AS my_email
+            FROM emails e
+            LEFT JOIN projects p ON p.id = e.project_id
+            LEFT JOIN accounts a ON a.id = e.account_id
+            WHERE e.id = ?
+            "#
+        )
+        .bind(email_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(AppError::EmailNotFound { id: email_id })?;
+
+        let my_name: Option<String> = sqlx::query_scalar("SELECT display_name FROM sync_settings WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?
+            .flatten();
+
+        let mut values = HashMap::new();
+        if let Some(first) = row.sender_name.as_deref().and_then(|name| name.split_whitespace().next()) {
+            values.insert("sender_first_name", first.to_string());
+        }
+        let pairs = [
+            ("sender_name", row.sender_name),
+            ("sender_email", row.sender_address),
+            ("subject", row.subject),
+            ("project_name", row.project_name),
+            ("my_name", my_name),
+            ("my_email", row.my_email),
+            ("date", row.date.map(|date| date.chars().take(10).collect())),
+            ("today", Some(chrono::Local::now().format("%Y-%m-%d").to_string())),
+        ];
+        for (key, value) in pairs {
+            if let Some(value) = value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty()) {
+                values.insert(key, value);
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+/// 校验名称和占位符，返回去掉首尾空白的名称
+pub(crate) fn validate(request: &TemplateRequest) -> Result<&str, AppError> {
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation("Template name is required".to_string()));
+    }
+    for text in [&request.subject_template, &request.body_template] {
+        for (key, _) in placeholders(text) {
+            if !PLACEHOLDERS.contains(&key.as_str()) {
+                return Err(AppError::Validation(format!(
+                    "Unknown placeholder {{{{{}}}}} (supported: {})",
+                    key,
+                    PLACEHOLDERS.join(", ")
+                )));
+            }
+        }
+    }
+    Ok(name)
+}
+
+fn duplicate_name_error(error: sqlx::Error, name: &str) -> AppError {
+    let unique = error
+        .as_database_error()
+        .is_some_and(|db| db.message().contains("UNIQUE"));
+    if unique {
+        AppError::Validation(format!("A template named {:?} already exists", name))
+    } else {
+        AppError::Database(error)
+    }
+}
+
+/// 模板中的占位符：(名称, 默认值)
+fn placeholders(text: &str) -> Vec<(String, Option<String>)> {
+    let mut found = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        found.push(parse_placeholder(&after[..end]));
+        rest = &after[end + 2..];
+    }
+    found
+}
+
+fn parse_placeholder(inner: &str) -> (String, Option<String>) {
+    match inner.split_once('|') {
+        Some((key, default)) => (key.trim().to_lowercase(), Some(default.trim().to_string())),
+        None => (inner.trim().to_lowercase(), None),
+    }
+}
+
+/// 替换占位符；`html` 为 true 时对替换的值做转义（模板本身的 HTML 不变）
+fn fill(text: &str, values: &HashMap<&'static str, String>, html: bool, missing: &mut Vec<String>) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        output.push_str(&rest[..start]);
+
+        let (key, default) = parse_placeholder(&after[..end]);
+        let value = match values.get(key.as_str()) {
+            Some(value) => Some(value.as_str()),
+            None => {
+                if default.is_none() {
+                    missing.push(key.clone());
+                }
+                default.as_deref()
+            }
+        };
+        if let Some(value) = value {
+            if html {
+                output.push_str(&escape_html(value));
+            } else {
+                output.push_str(value);
+            }
+        }
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// 回复主题（已有 "Re:" 前缀时不重复添加）
+fn reply_subject(subject: &str) -> String {
+    let subject = subject.trim();
+    if subject.to_lowercase().starts_with("re:") {
+        subject.to_string()
+    } else {
+        format!("Re: {}", subject)
+    }
+}
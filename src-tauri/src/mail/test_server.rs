@@ -0,0 +1,463 @@
+/// 测试用的进程内 IMAP 服务器
+///
+/// 只实现同步流程用到的命令：CAPABILITY、LOGIN、LIST、SELECT / EXAMINE、UID FETCH（UID、RFC822、
+/// RFC822.SIZE、ENVELOPE、X-GM-MSGID、X-GM-LABELS）、UID SEARCH（ALL / UID / HEADER / OR / NOT）、NOOP 和 LOGOUT。
+/// 使用 testdata 中的自签名证书，测试构建的客户端不校验证书。收到的命令（不含标签）按顺序记录，供测试断言。
+use crate::mail::imap_client::AuthMethod;
+use crate::mail::providers::{ImapConfig, ProviderConfig, SmtpConfig, DEFAULT_MAX_ATTACHMENT_BYTES};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_native_tls::TlsStream;
+
+const CERT: &[u8] = include_bytes!("testdata/imap-test-cert.pem");
+const KEY: &[u8] = include_bytes!("testdata/imap-test-key.pem");
+
+pub const USERNAME: &str = "me@example.com";
+pub const PASSWORD: &str = "secret";
+
+/// 文件夹中的一封邮件
+#[derive(Debug, Clone)]
+struct TestMessage {
+    uid: u32,
+    raw: Vec<u8>,
+    gm_msgid: Option<u64>,
+    labels: Vec<String>,
+}
+
+#[derive(Debug)]
+struct TestFolder {
+    name: String,
+    /// SPECIAL-USE 标记（如 `\Sent`）
+    special_use: Option<String>,
+    uid_validity: u32,
+    uid_next: u32,
+    messages: Vec<TestMessage>,
+}
+
+#[derive(Debug, Default)]
+struct ServerState {
+    folders: Vec<TestFolder>,
+    gmail: bool,
+    commands: Vec<String>,
+}
+
+impl ServerState {
+    fn folder_mut(&mut self, name: &str) -> &mut TestFolder {
+        self.folders
+            .iter_mut()
+            .find(|folder| folder.name == name)
+            .unwrap_or_else(|| panic!("test server has no folder {}", name))
+    }
+}
+
+/// 监听 127.0.0.1 随机端口的 IMAP 服务器，drop 时停止接受连接
+pub struct TestImapServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<ServerState>>,
+    accept: JoinHandle<()>,
+}
+
+impl TestImapServer {
+    /// 只有空收件箱的普通服务器
+    pub async fn start() -> Self {
+        Self::spawn(false).await
+    }
+
+    /// 带 X-GM-EXT-1 扩展的 Gmail 服务器
+    pub async fn start_gmail() -> Self {
+        Self::spawn(true).await
+    }
+
+    async fn spawn(gmail: bool) -> Self {
+        let identity = native_tls::Identity::from_pkcs8(CERT, KEY).expect("test server identity");
+        let acceptor = tokio_native_tls::TlsAcceptor::from(
+            native_tls::TlsAcceptor::new(identity).expect("test server TLS acceptor"),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind test server");
+        let addr = listener.local_addr().expect("test server address");
+
+        let state = Arc::new(Mutex::new(ServerState { gmail, ..Default::default() }));
+        let server_state = state.clone();
+        let accept = tokio::spawn(async move {
+            while let Ok((tcp, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    if let Ok(tls) = acceptor.accept(tcp).await {
+                        if let Err(e) = serve(tls, state).await {
+                            log::debug!("Test IMAP connection closed: {}", e);
+                        }
+                    }
+                });
+            }
+        });
+
+        let server = Self { addr, state, accept };
+        server.add_folder("INBOX", None);
+        server
+    }
+
+    fn state(&self) -> MutexGuard<'_, ServerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 添加文件夹，`special_use` 为 SPECIAL-USE 标记（如 `\Sent`、`\All`）
+    pub fn add_folder(&self, name: &str, special_use: Option<&str>) {
+        self.state().folders.push(TestFolder {
+            name: name.to_string(),
+            special_use: special_use.map(str::to_string),
+            uid_validity: 1,
+            uid_next: 1,
+            messages: Vec::new(),
+        });
+    }
+
+    /// 投递邮件，返回分配的 UID
+    pub fn deliver(&self, folder: &str, raw: &[u8]) -> u32 {
+        self.push(folder, raw, None, &[])
+    }
+
+    /// 投递带 Gmail 邮件 ID 和标签的邮件，返回分配的 UID
+    pub fn deliver_gmail(&self, folder: &str, raw: &[u8], gm_msgid: u64, labels: &[&str]) -> u32 {
+        self.push(folder, raw, Some(gm_msgid), labels)
+    }
+
+    fn push(&self, folder: &str, raw: &[u8], gm_msgid: Option<u64>, labels: &[&str]) -> u32 {
+        let mut state = self.state();
+        let folder = state.folder_mut(folder);
+        let uid = folder.uid_next;
+        folder.uid_next += 1;
+        folder.messages.push(TestMessage {
+            uid,
+            raw: raw.to_vec(),
+            gm_msgid,
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+        });
+        uid
+    }
+
+    /// 删除邮件（UID 不会重用，留下空洞）
+    pub fn expunge(&self, folder: &str, uid: u32) {
+        self.state().folder_mut(folder).messages.retain(|message| message.uid != uid);
+    }
+
+    /// 收到的命令（不含标签），按接收顺序
+    pub fn commands(&self) -> Vec<String> {
+        self.state().commands.clone()
+    }
+
+    pub fn clear_commands(&self) {
+        self.state().commands.clear();
+    }
+
+    /// 指向本服务器的服务商配置
+    pub fn provider(&self) -> ProviderConfig {
+        ProviderConfig {
+            name: "test".to_string(),
+            display_name: "Test IMAP".to_string(),
+            imap: ImapConfig {
+                host: self.addr.ip().to_string(),
+                port: self.addr.port(),
+                use_tls: true,
+                use_starttls: false,
+            },
+            smtp: SmtpConfig {
+                host: self.addr.ip().to_string(),
+                port: 0,
+                use_tls: true,
+                use_starttls: false,
+            },
+            oauth_supported: false,
+            oauth_client_id: None,
+            sent_folder: None,
+            max_attachment_bytes: DEFAULT_MAX_ATTACHMENT_BYTES,
+        }
+    }
+
+    pub fn auth(&self) -> AuthMethod {
+        AuthMethod::Password {
+            username: USERNAME.to_string(),
+            password: PASSWORD.to_string(),
+        }
+    }
+}
+
+impl Drop for TestImapServer {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+/// 处理一个连接，直到 LOGOUT 或客户端断开
+async fn serve(stream: TlsStream<TcpStream>, state: Arc<Mutex<ServerState>>) -> std::io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    stream.write_all(b"* OK ThreadLine test server ready\r\n").await?;
+
+    let mut selected: Option<String> = None;
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            return Ok(());
+        }
+        let (response, logout) = {
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            respond(&mut state, &mut selected, line.trim_end_matches(['\r', '\n']))
+        };
+        stream.write_all(&response).await?;
+        stream.flush().await?;
+        if logout {
+            return Ok(());
+        }
+    }
+}
+
+/// 执行一条命令，返回响应和是否已登出
+fn respond(state: &mut ServerState, selected: &mut Option<String>, line: &str) -> (Vec<u8>, bool) {
+    let mut cursor = Cursor::new(line);
+    let Some(tag) = cursor.next() else {
+        return (Vec::new(), false);
+    };
+    let command = cursor.next().unwrap_or_default().to_ascii_uppercase();
+    state.commands.push(line[tag.len()..].trim().to_string());
+
+    let response = match command.as_str() {
+        "CAPABILITY" => {
+            let gmail = if state.gmail { " X-GM-EXT-1" } else { "" };
+            format!("* CAPABILITY IMAP4rev1 SPECIAL-USE{}\r\n{} OK CAPABILITY completed\r\n", gmail, tag)
+        }
+        "LOGIN" => {
+            let (username, password) = (cursor.next(), cursor.next());
+            if username.as_deref() == Some(USERNAME) && password.as_deref() == Some(PASSWORD) {
+                format!("{} OK LOGIN completed\r\n", tag)
+            } else {
+                format!("{} NO [AUTHENTICATIONFAILED] Invalid credentials\r\n", tag)
+            }
+        }
+        "NOOP" => format!("{} OK NOOP completed\r\n", tag),
+        "LIST" => list(state, &tag),
+        "SELECT" | "EXAMINE" => {
+            let name = cursor.next().unwrap_or_default();
+            match state.folders.iter().find(|folder| folder.name.eq_ignore_ascii_case(&name)) {
+                Some(folder) => {
+                    *selected = Some(folder.name.clone());
+                    format!(
+                        "* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft)\r\n\
+                         * {} EXISTS\r\n\
+                         * 0 RECENT\r\n\
+                         * OK [UIDVALIDITY {}] UIDs valid\r\n\
+                         * OK [UIDNEXT {}] Predicted next UID\r\n\
+                         {} OK [READ-WRITE] {} completed\r\n",
+                        folder.messages.len(), folder.uid_validity, folder.uid_next, tag, command
+                    )
+                }
+                None => format!("{} NO Mailbox does not exist\r\n", tag),
+            }
+        }
+        "UID" => {
+            let folder = selected
+                .as_deref()
+                .and_then(|name| state.folders.iter().find(|folder| folder.name == name));
+            return match (folder, cursor.next().unwrap_or_default().to_ascii_uppercase().as_str()) {
+                (Some(folder), "FETCH") => {
+                    let set = cursor.next().unwrap_or_default();
+                    (uid_fetch(folder, state.gmail, &set, &cursor.rest().to_ascii_uppercase(), &tag), false)
+                }
+                (Some(folder), "SEARCH") => {
+                    let mut keys = Vec::new();
+                    while let Some(key) = cursor.next() {
+                        keys.push(key);
+                    }
+                    (uid_search(folder, &keys, &tag).into_bytes(), false)
+                }
+                (None, _) => (format!("{} BAD No mailbox selected\r\n", tag).into_bytes(), false),
+                _ => (format!("{} BAD Unsupported UID command\r\n", tag).into_bytes(), false),
+            };
+        }
+        "LOGOUT" => return (format!("* BYE Logging out\r\n{} OK LOGOUT completed\r\n", tag).into_bytes(), true),
+        _ => format!("{} BAD Unsupported command\r\n", tag),
+    };
+    (response.into_bytes(), false)
+}
+
+fn list(state: &ServerState, tag: &str) -> String {
+    let mut out = String::new();
+    for folder in &state.folders {
+        let flags = match &folder.special_use {
+            Some(special_use) => format!("\\HasNoChildren {}", special_use),
+            None => "\\HasNoChildren".to_string(),
+        };
+        out.push_str(&format!("* LIST ({}) \"/\" {}\r\n", flags, quoted(&folder.name)));
+    }
+    out.push_str(&format!("{} OK LIST completed\r\n", tag));
+    out
+}
+
+fn uid_fetch(folder: &TestFolder, gmail: bool, set: &str, items: &str, tag: &str) -> Vec<u8> {
+    let max_uid = folder.messages.last().map_or(0, |message| message.uid);
+    let wants = |item: &str| items.split([' ', '(', ')']).any(|token| token == item);
+
+    let mut out = Vec::new();
+    for (index, message) in folder.messages.iter().enumerate() {
+        if !in_uid_set(set, message.uid, max_uid) {
+            continue;
+        }
+        out.extend_from_slice(format!("* {} FETCH (UID {}", index + 1, message.uid).as_bytes());
+        if wants("RFC822.SIZE") {
+            out.extend_from_slice(format!(" RFC822.SIZE {}", message.raw.len()).as_bytes());
+        }
+        if wants("ENVELOPE") {
+            let field = |name: &str| header(&message.raw, name).map_or("NIL".to_string(), |value| quoted(&value));
+            out.extend_from_slice(
+                format!(
+                    " ENVELOPE ({} {} NIL NIL NIL NIL NIL NIL NIL {})",
+                    field("Date"), field("Subject"), field("Message-ID")
+                )
+                .as_bytes(),
+            );
+        }
+        if gmail && wants("X-GM-MSGID") {
+            if let Some(msgid) = message.gm_msgid {
+                out.extend_from_slice(format!(" X-GM-MSGID {}", msgid).as_bytes());
+            }
+        }
+        if gmail && wants("X-GM-LABELS") {
+            let labels: Vec<String> = message
+                .labels
+                .iter()
+                .map(|label| if label.starts_with('\\') { label.clone() } else { quoted(label) })
+                .collect();
+            out.extend_from_slice(format!(" X-GM-LABELS ({})", labels.join(" ")).as_bytes());
+        }
+        if wants("RFC822") {
+            out.extend_from_slice(format!(" RFC822 {{{}}}\r\n", message.raw.len()).as_bytes());
+            out.extend_from_slice(&message.raw);
+        }
+        out.extend_from_slice(b")\r\n");
+    }
+    out.extend_from_slice(format!("{} OK FETCH completed\r\n", tag).as_bytes());
+    out
+}
+
+fn uid_search(folder: &TestFolder, keys: &[String], tag: &str) -> String {
+    let max_uid = folder.messages.last().map_or(0, |message| message.uid);
+    let uids: Vec<String> = folder
+        .messages
+        .iter()
+        .filter(|message| {
+            let mut pos = 0;
+            let mut matched = true;
+            while pos < keys.len() {
+                matched &= search_key(keys, &mut pos, message, max_uid);
+            }
+            matched
+        })
+        .map(|message| message.uid.to_string())
+        .collect();
+
+    let mut out = String::from("* SEARCH");
+    for uid in uids {
+        out.push(' ');
+        out.push_str(&uid);
+    }
+    out.push_str(&format!("\r\n{} OK SEARCH completed\r\n", tag));
+    out
+}
+
+/// 计算一个搜索条件并前移 `pos`；不支持的条件不匹配任何邮件
+fn search_key(keys: &[String], pos: &mut usize, message: &TestMessage, max_uid: u32) -> bool {
+    let mut next = || {
+        let key = keys.get(*pos).cloned().unwrap_or_default();
+        *pos += 1;
+        key
+    };
+    match next().to_ascii_uppercase().as_str() {
+        "ALL" => true,
+        "CHARSET" => {
+            next();
+            true
+        }
+        "UID" => in_uid_set(&next(), message.uid, max_uid),
+        "HEADER" => {
+            let (name, value) = (next(), next());
+            header(&message.raw, &name)
+                .is_some_and(|header| header.to_lowercase().contains(&value.to_lowercase()))
+        }
+        "OR" => {
+            let left = search_key(keys, pos, message, max_uid);
+            let right = search_key(keys, pos, message, max_uid);
+            left || right
+        }
+        "NOT" => !search_key(keys, pos, message, max_uid),
+        _ => false,
+    }
+}
+
+/// UID 集合（`1,3:5,7:*`）；`*` 表示最大的 UID，`n:*` 在没有 n 以上的邮件时包含最后一封（RFC 3501）
+fn in_uid_set(set: &str, uid: u32, max_uid: u32) -> bool {
+    let value = |part: &str| if part == "*" { Some(max_uid) } else { part.parse::<u32>().ok() };
+    set.split(',').any(|part| match part.split_once(':') {
+        Some((start, end)) => match (value(start), value(end)) {
+            (Some(start), Some(end)) => (start.min(end)..=start.max(end)).contains(&uid),
+            _ => false,
+        },
+        None => value(part) == Some(uid),
+    })
+}
+
+/// 原始邮件中第一个名为 `name` 的邮件头（已展开折行）
+fn header(raw: &[u8], name: &str) -> Option<String> {
+    let text = String::from_utf8_lossy(raw);
+    let headers = text.split("\r\n\r\n").next().unwrap_or_default().replace("\r\n ", " ").replace("\r\n\t", " ");
+    headers.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+    })
+}
+
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// 按空格切分命令参数，支持带转义的引号字符串
+struct Cursor<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line: &'a str) -> Self {
+        Self { rest: line }
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let s = self.rest.trim_start();
+        if s.is_empty() {
+            return None;
+        }
+        if let Some(body) = s.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = body.char_indices();
+            while let Some((index, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        self.rest = &body[index + 1..];
+                        return Some(value);
+                    }
+                    '\\' => value.extend(chars.next().map(|(_, escaped)| escaped)),
+                    _ => value.push(c),
+                }
+            }
+            self.rest = "";
+            return Some(value);
+        }
+        let end = s.find(' ').unwrap_or(s.len());
+        self.rest = &s[end..];
+        Some(s[..end].to_string())
+    }
+
+    fn rest(&self) -> &str {
+        self.rest.trim()
+    }
+}
@@ -0,0 +1,252 @@
+/// IMAP 连接带宽限制
+///
+/// 令牌桶包在 TLS 流外层，只限制读取（下载）速度；同时统计双向传输的字节数，
+/// 用于同步记录中的流量统计。
+use crate::mail::imap_trace::ImapTrace;
+use crate::mail::session_metrics::SessionMetrics;
+use crate::utils::network::detect_metered_connection;
+use sqlx::SqlitePool;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{Instant, Sleep};
+
+/// 令牌桶最小容量，避免单次读取过小导致 TLS 记录被切得很碎
+const MIN_BUCKET_BYTES: u64 = 4 * 1024;
+
+/// 连接传输的字节数（可在多个连接间共享）
+#[derive(Debug, Clone, Default)]
+pub struct TransferCounter(Arc<AtomicU64>);
+
+impl TransferCounter {
+    pub fn add(&self, bytes: u64) {
+        self.0.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn total(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 当前生效的网络策略（带宽上限、按流量计费模式）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NetworkPolicy {
+    /// 下载速度上限，None 表示不限速
+    pub bytes_per_sec: Option<u64>,
+    /// 按流量计费：只同步邮件头，不下载正文和附件（覆盖账户设置）
+    pub metered: bool,
+}
+
+impl NetworkPolicy {
+    /// 从同步设置读取；开启自动检测时，系统报告按流量计费的网络也会启用计费模式
+    pub async fn load(pool: &SqlitePool) -> Self {
+        let row: Option<(i64, bool, bool)> = sqlx::query_as(
+            "SELECT max_bandwidth_kbps, metered_mode, detect_metered FROM sync_settings WHERE id = 1"
+        )
+        .fetch_optional(pool)
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to load network settings: {}", e);
+            None
+        });
+        let Some((kbps, metered_mode, detect_metered)) = row else {
+            return Self::default();
+        };
+
+        let detected = if detect_metered && !metered_mode {
+            detect_metered_connection().await.unwrap_or(false)
+        } else {
+            false
+        };
+        if detected {
+            log::info!("Metered connection detected, switching to headers-only sync");
+        }
+
+        Self {
+            bytes_per_sec: (kbps > 0).then(|| kbps as u64 * 1024),
+            metered: metered_mode || detected,
+        }
+    }
+}
+
+/// 令牌桶（容量为一秒的流量）
+#[derive(Debug)]
+struct TokenBucket {
+    rate: u64,
+    capacity: u64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(bytes_per_sec: u64) -> Self {
+        let capacity = bytes_per_sec.max(MIN_BUCKET_BYTES);
+        Self {
+            rate: bytes_per_sec.max(1),
+            capacity,
+            tokens: capacity as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.capacity as f64);
+        self.last_refill = now;
+    }
+
+    /// 当前可读取的字节数
+    fn available(&mut self) -> usize {
+        self.refill();
+        self.tokens.max(0.0) as usize
+    }
+
+    fn consume(&mut self, bytes: usize) {
+        self.tokens -= bytes as f64;
+    }
+
+    /// 攒够一次最小读取所需的等待时间
+    fn wait_time(&self) -> Duration {
+        let needed = (MIN_BUCKET_BYTES.min(self.capacity) as f64 - self.tokens).max(1.0);
+        Duration::from_secs_f64(needed / self.rate as f64)
+    }
+}
+
+/// 限速流
+pub struct ThrottledStream<S> {
+    inner: S,
+    bucket: Option<TokenBucket>,
+    sleep: Option<Pin<Box<Sleep>>>,
+    counter: TransferCounter,
+    trace: Option<ImapTrace>,
+    metrics: Option<SessionMetrics>,
+}
+
+impl<S> ThrottledStream<S> {
+    /// `bytes_per_sec` 为 None 时不限速，只统计流量
+    pub fn new(inner: S, bytes_per_sec: Option<u64>, counter: TransferCounter) -> Self {
+        Self {
+            inner,
+            bucket: bytes_per_sec.filter(|rate| *rate > 0).map(TokenBucket::new),
+            sleep: None,
+            counter,
+            trace: None,
+            metrics: None,
+        }
+    }
+
+    /// 把经过的明文字节写入协议跟踪
+    pub fn with_trace(mut self, trace: Option<ImapTrace>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// 统计下载字节数和限速等待
+    pub fn with_metrics(mut self, metrics: SessionMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        self.bucket.is_some()
+    }
+}
+
+impl<S> fmt::Debug for ThrottledStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThrottledStream")
+            .field("bucket", &self.bucket)
+            .field("transferred", &self.counter.total())
+            .finish()
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ThrottledStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+
+        let Some(bucket) = this.bucket.as_mut() else {
+            let before = buf.filled().len();
+            let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+            let read = (buf.filled().len() - before) as u64;
+            this.counter.add(read);
+            if let Some(metrics) = &this.metrics {
+                metrics.record_download(read);
+            }
+            if let Some(trace) = &this.trace {
+                trace.record_received(&buf.filled()[before..]);
+            }
+            return result;
+        };
+
+        loop {
+            if let Some(sleep) = this.sleep.as_mut() {
+                if sleep.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                this.sleep = None;
+            }
+
+            let available = bucket.available();
+            if available == 0 {
+                let wait = bucket.wait_time();
+                if let Some(metrics) = &this.metrics {
+                    metrics.record_throttle_sleep(wait);
+                }
+                this.sleep = Some(Box::pin(tokio::time::sleep(wait)));
+                continue;
+            }
+
+            let limit = available.min(buf.remaining());
+            let mut limited = ReadBuf::new(buf.initialize_unfilled_to(limit));
+            let result = Pin::new(&mut this.inner).poll_read(cx, &mut limited);
+            let read = limited.filled().len();
+            if let Some(trace) = &this.trace {
+                trace.record_received(limited.filled());
+            }
+            buf.advance(read);
+            bucket.consume(read);
+            this.counter.add(read as u64);
+            if let Some(metrics) = &this.metrics {
+                metrics.record_download(read as u64);
+            }
+            return result;
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ThrottledStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = &result {
+            this.counter.add(*written as u64);
+            if let Some(trace) = &this.trace {
+                trace.record_sent(&buf[..*written]);
+            }
+        }
+        result
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
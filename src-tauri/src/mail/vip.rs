@@ -0,0 +1,176 @@
+/// VIP 发件人
+///
+/// 用户标记的一小组重要联系人：他们的邮件在收件箱和智能视图中带 `is_vip` 标记、可以只看 VIP，
+/// 同步到新邮件时即使不在置顶项目中也发出通知（静默时段内不通知）。
+///
+/// 按规范化的 `sender_address`（小写）匹配。VIP 地址被合并为联系人别名（或有别名）时，
+/// 同一联系人的所有地址都算 VIP，见 `VIP_ADDRESSES_SQL`。
+use crate::error::AppError;
+use crate::events::notifications::SOURCE_VIP;
+use crate::events::{EventEmitter, NotificationLevel};
+use crate::index_scheduler::quiet_hours::QuietHours;
+use crate::utils::validation;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+
+/// 单独通知的 VIP 邮件数，超过时合并为一条通知
+const MAX_INDIVIDUAL_NOTIFICATIONS: usize = 3;
+
+/// 所有算作 VIP 的地址（VIP 地址本身及其所在联系人的主地址和别名），用于 `sender_address IN (...)`
+pub const VIP_ADDRESSES_SQL: &str = r#"
+    SELECT v.address FROM vip_contacts v
+    UNION
+    SELECT alias.address FROM vip_contacts v
+    JOIN contacts c ON c.address = v.address
+    JOIN contacts alias ON COALESCE(alias.canonical_id, alias.id) = COALESCE(c.canonical_id, c.id)
+"#;
+
+/// VIP 联系人
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct VipContact {
+    pub address: String,
+    pub name: Option<String>,
+    pub created_at: String,
+}
+
+/// VIP 列表
+pub struct VipList {
+    pool: SqlitePool,
+}
+
+impl VipList {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 所有 VIP（按添加顺序）
+    pub async fn list(&self) -> Result<Vec<VipContact>, AppError> {
+        let contacts = sqlx::query_as::<_, VipContact>(
+            "SELECT address, name, created_at FROM vip_contacts ORDER BY created_at, address"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(contacts)
+    }
+
+    /// 添加 VIP（已存在时只更新名字），名字为空时使用联系人簿中的名字
+    pub async fn add(&self, address: &str, name: Option<&str>) -> Result<VipContact, AppError> {
+        let address = normalize_address(address)?;
+        let name = name.map(str::trim).filter(|name| !name.is_empty());
+        sqlx::query(
+            r#"
+            INSERT INTO vip_contacts (address, name)
+            VALUES (?1, COALESCE(?2, (SELECT name FROM contacts WHERE address = ?1)))
+            ON CONFLICT(address) DO UPDATE SET name = COALESCE(?2, vip_contacts.name)
+            "#
+        )
+        .bind(&address)
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+
+        log::info!("Added VIP sender {}", address);
+        let contact = sqlx::query_as::<_, VipContact>(
+            "SELECT address, name, created_at FROM vip_contacts WHERE address = ?"
+        )
+        .bind(&address)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(contact)
+    }
+
+    /// 移除 VIP，返回是否存在
+    pub async fn remove(&self, address: &str) -> Result<bool, AppError> {
+        let address = address.trim().to_lowercase();
+        let removed = sqlx::query("DELETE FROM vip_contacts WHERE address = ?")
+            .bind(&address)
+            .execute(&self.pool)
+            .await?
+            .rows_affected()
+            > 0;
+
+        if removed {
+            log::info!("Removed VIP sender {}", address);
+        }
+        Ok(removed)
+    }
+
+    /// 所有算作 VIP 的地址（含合并联系人的别名）
+    pub async fn addresses(&self) -> Result<HashSet<String>, AppError> {
+        let addresses: Vec<String> = sqlx::query_scalar(VIP_ADDRESSES_SQL)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(addresses.into_iter().collect())
+    }
+
+    /// 为新同步的邮件中来自 VIP 的邮件发送通知（静默时段内不通知）
+    pub async fn notify_new_emails(&self, emitter: &EventEmitter, email_ids: &[i64]) -> Result<usize, AppError> {
+        if email_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let placeholders = vec!["?"; email_ids.len()].join(", ");
+        let sql = format!(
+            r#"
+            SELECT e.id, COALESCE(e.sender_name, e.sender_address, e.sender), e.subject
+            FROM emails e
+            WHERE e.id IN ({})
+              AND COALESCE(e.direction, 'incoming') = 'incoming'
+              AND e.sender_address IN ({})
+            ORDER BY julianday(e.date), e.id
+            "#,
+            placeholders, VIP_ADDRESSES_SQL
+        );
+        let mut query = sqlx::query_as::<_, (i64, Option<String>, Option<String>)>(&sql);
+        for id in email_ids {
+            query = query.bind(id);
+        }
+        let emails = query.fetch_all(&self.pool).await?;
+        if emails.is_empty() {
+            return Ok(0);
+        }
+
+        if QuietHours::load(&self.pool).await?.is_quiet_now() {
+            log::info!("Quiet hours: not notifying {} new VIP emails", emails.len());
+            return Ok(0);
+        }
+
+        if emails.len() > MAX_INDIVIDUAL_NOTIFICATIONS {
+            let mut senders: Vec<String> = Vec::new();
+            for (_, sender, _) in &emails {
+                let sender = sender.clone().unwrap_or_default();
+                if !senders.contains(&sender) {
+                    senders.push(sender);
+                }
+            }
+            emitter.emit_notification_from(
+                "New VIP emails",
+                &format!("{} new emails from {}", emails.len(), senders.join(", ")),
+                NotificationLevel::Info,
+                Some(SOURCE_VIP),
+                None,
+            );
+        } else {
+            for (id, sender, subject) in &emails {
+                emitter.emit_notification_from(
+                    &format!("VIP: {}", sender.as_deref().unwrap_or_default()),
+                    subject.as_deref().filter(|subject| !subject.is_empty()).unwrap_or("(no subject)"),
+                    NotificationLevel::Info,
+                    Some(SOURCE_VIP),
+                    Some(&format!("email:{}", id)),
+                );
+            }
+        }
+
+        Ok(emails.len())
+    }
+}
+
+/// 校验并规范化地址（与 `sender_address` 一致：去空白、小写）
+pub fn normalize_address(address: &str) -> Result<String, AppError> {
+    Ok(validation::email_address("address", address)?.to_lowercase())
+}
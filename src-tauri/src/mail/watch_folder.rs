@@ -0,0 +1,428 @@
+/// 监视文件夹导入
+///
+/// 扫描仪和内部工具会把 .eml 文件放到共享目录。开启后监视设置的目录（notify 文件事件，另有定期扫描兜底），
+/// 新文件的大小和修改时间在 `STABLE_FOR` 内不再变化（写入完成）后才导入：解析、按 Message-ID 去重、分类，
+/// 与 IMAP 邮件走相同的保存流程（`EmailSyncer::import_eml`），完成后移动到 `processed/`（或按设置删除）。
+/// 导入失败的文件移动到 `failed/`，旁边写一个 `<文件名>.error.txt` 说明原因。
+/// 目录暂时不可用（网络共享断开）时记录状态并定期重试，恢复后继续监视。
+use crate::error::AppError;
+use crate::events::{EventEmitter, NewEmailsEvent};
+use crate::mail::attachment_writer::AttachmentWriter;
+use crate::mail::sync::{EmailSyncer, ProcessedMessage};
+use crate::project::classifier::ProjectClassifier;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{mpsc, Notify};
+
+/// 导入成功的文件移动到的子目录
+pub const PROCESSED_DIR: &str = "processed";
+
+/// 导入失败的文件移动到的子目录
+pub const FAILED_DIR: &str = "failed";
+
+/// 导入邮件的 raw_path 前缀（记录来源文件名，不会被当作 UID）
+pub const IMPORTED_RAW_PATH_PREFIX: &str = "eml:";
+
+/// 文件大小和修改时间保持不变多久后视为写入完成
+const STABLE_FOR: Duration = Duration::from_secs(3);
+
+/// 有等待写入完成的文件时的检查间隔
+const PENDING_SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 没有文件事件时的兜底扫描间隔（网络共享上文件事件不可靠）
+const IDLE_SCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 目录不可用时的重试间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 监视文件夹设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchFolderConfig {
+    pub path: String,
+    /// 导入的邮件归属的账户
+    pub account_id: i64,
+    /// 导入后删除文件，而不是移动到 processed/
+    #[serde(default)]
+    pub delete_processed: bool,
+}
+
+/// 监视文件夹状态（诊断用）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatchFolderStatus {
+    pub running: bool,
+    pub path: Option<String>,
+    pub account_id: Option<i64>,
+    /// 目录当前是否可访问
+    pub available: bool,
+    /// 等待写入完成的文件数
+    pub pending: usize,
+    pub imported: u64,
+    /// 按 Message-ID 判定为已有邮件的文件数
+    pub duplicates: u64,
+    pub failed: u64,
+    pub last_import_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// 等待写入完成的文件
+struct Observation {
+    len: u64,
+    modified: Option<SystemTime>,
+    since: Instant,
+}
+
+/// 监视文件夹（全局状态，同时只监视一个目录）
+#[derive(Default)]
+pub struct WatchFolder {
+    /// 运行中任务的停止信号
+    stop: Mutex<Option<Arc<Notify>>>,
+    status: Arc<Mutex<WatchFolderStatus>>,
+}
+
+impl WatchFolder {
+    pub fn status(&self) -> WatchFolderStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// 开始监视（已在监视时先停止原来的任务）
+    pub fn start(&self, pool: SqlitePool, emitter: EventEmitter, config: WatchFolderConfig) {
+        self.stop();
+        log::info!("Watching {} for .eml files (account {})", config.path, config.account_id);
+
+        *self.status.lock().unwrap() = WatchFolderStatus {
+            running: true,
+            path: Some(config.path.clone()),
+            account_id: Some(config.account_id),
+            ..Default::default()
+        };
+        let stop = Arc::new(Notify::new());
+        let watcher = FolderWatcher {
+            syncer: EmailSyncer::new(pool.clone(), emitter.clone()),
+            pool,
+            emitter,
+            root: PathBuf::from(&config.path),
+            config,
+            status: self.status.clone(),
+        };
+        tauri::async_runtime::spawn(watcher.run(stop.clone()));
+        *self.stop.lock().unwrap() = Some(stop);
+    }
+
+    /// 停止监视（正在导入的文件处理完后退出）
+    pub fn stop(&self) {
+        let Some(stop) = self.stop.lock().unwrap().take() else { return };
+        stop.notify_one();
+        self.status.lock().unwrap().running = false;
+        log::info!("Stopped watch folder");
+    }
+}
+
+/// 读取设置中的监视文件夹（未开启或未配置时返回 None）
+pub async fn load_config(pool: &SqlitePool) -> Result<Option<WatchFolderConfig>, AppError> {
+    let row: Option<(bool, String, Option<i64>, bool)> = sqlx::query_as(
+        r#"
+        SELECT watch_folder_enabled, watch_folder_path, watch_folder_account_id, watch_folder_delete_processed
+        FROM sync_settings
+        WHERE id = 1
+        "#
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(match row {
+        Some((true, path, Some(account_id), delete_processed)) if !path.trim().is_empty() => Some(WatchFolderConfig {
+            path,
+            account_id,
+            delete_processed,
+        }),
+        _ => None,
+    })
+}
+
+/// 保存监视文件夹设置（`config` 为 None 时关闭，保留原来的路径）
+pub async fn save_config(pool: &SqlitePool, config: Option<&WatchFolderConfig>) -> Result<(), AppError> {
+    match config {
+        Some(config) => {
+            sqlx::query(
+                r#"
+                UPDATE sync_settings
+                SET watch_folder_enabled = 1, watch_folder_path = ?, watch_folder_account_id = ?,
+                    watch_folder_delete_processed = ?, version = version + 1, updated_at = CURRENT_TIMESTAMP
+                WHERE id = 1
+                "#
+            )
+            .bind(&config.path)
+            .bind(config.account_id)
+            .bind(config.delete_processed)
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query(
+                "UPDATE sync_settings SET watch_folder_enabled = 0, version = version + 1, updated_at = CURRENT_TIMESTAMP WHERE id = 1"
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// 校验设置：目录存在、账户存在
+pub async fn validate_config(pool: &SqlitePool, config: &WatchFolderConfig) -> Result<(), AppError> {
+    if !Path::new(&config.path).is_dir() {
+        return Err(AppError::Validation(format!("Watch folder does not exist: {}", config.path)));
+    }
+    let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM accounts WHERE id = ?")
+        .bind(config.account_id)
+        .fetch_optional(pool)
+        .await?;
+    if exists.is_none() {
+        return Err(AppError::Validation(format!("Account {} not found", config.account_id)));
+    }
+    Ok(())
+}
+
+/// 监视任务
+struct FolderWatcher {
+    pool: SqlitePool,
+    emitter: EventEmitter,
+    syncer: EmailSyncer,
+    root: PathBuf,
+    config: WatchFolderConfig,
+    status: Arc<Mutex<WatchFolderStatus>>,
+}
+
+impl FolderWatcher {
+    async fn run(self, stop: Arc<Notify>) {
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+        // 持有订阅（drop 时取消监视）
+        let mut _watcher: Option<RecommendedWatcher> = None;
+        let mut pending: HashMap<PathBuf, Observation> = HashMap::new();
+        // 无法移出监视目录的文件（权限问题等），不再重复导入
+        let mut stuck: HashSet<PathBuf> = HashSet::new();
+
+        loop {
+            let wait = match tokio::fs::metadata(&self.root).await {
+                Ok(metadata) if metadata.is_dir() => {
+                    // 首次启动或目录恢复可访问时重新订阅文件事件
+                    if !self.status.lock().unwrap().available {
+                        _watcher = self.watch(events_tx.clone());
+                        self.update(|status| status.available = true);
+                    }
+                    match self.scan(&mut pending, &stuck).await {
+                        Ok(ready) => {
+                            if !ready.is_empty() {
+                                self.import_batch(ready, &mut stuck).await;
+                            }
+                        }
+                        Err(e) => log::warn!("Failed to scan watch folder {:?}: {}", self.root, e),
+                    }
+                    self.update(|status| status.pending = pending.len());
+                    if pending.is_empty() { IDLE_SCAN_INTERVAL } else { PENDING_SCAN_INTERVAL }
+                }
+                _ => {
+                    _watcher = None;
+                    if self.status.lock().unwrap().available {
+                        log::warn!("Watch folder {:?} is unavailable, retrying every {}s", self.root, RETRY_INTERVAL.as_secs());
+                    }
+                    pending.clear();
+                    self.update(|status| {
+                        status.available = false;
+                        status.pending = 0;
+                        status.last_error = Some(format!("Folder unavailable: {}", self.root.display()));
+                    });
+                    RETRY_INTERVAL
+                }
+            };
+
+            tokio::select! {
+                _ = stop.notified() => break,
+                _ = events.recv() => {
+                    // 一次写入会产生多个事件，合并为一次扫描
+                    while events.try_recv().is_ok() {}
+                }
+                _ = tokio::time::sleep(wait) => {}
+            }
+        }
+    }
+
+    /// 订阅目录的文件事件（失败时只靠定期扫描）
+    fn watch(&self, events: mpsc::UnboundedSender<()>) -> Option<RecommendedWatcher> {
+        let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            if result.is_ok() {
+                let _ = events.send(());
+            }
+        })
+        .and_then(|mut watcher| {
+            watcher.watch(&self.root, RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        });
+
+        match watcher {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                log::warn!("Failed to watch {:?}, falling back to polling: {}", self.root, e);
+                None
+            }
+        }
+    }
+
+    /// 扫描目录，返回写入完成的 .eml 文件
+    async fn scan(
+        &self,
+        pending: &mut HashMap<PathBuf, Observation>,
+        stuck: &HashSet<PathBuf>,
+    ) -> std::io::Result<Vec<PathBuf>> {
+        let mut seen = HashSet::new();
+        let mut ready = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.root).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if !is_eml(&path) || stuck.contains(&path) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata().await else { continue };
+            if !metadata.is_file() {
+                continue;
+            }
+            let (len, modified) = (metadata.len(), metadata.modified().ok());
+            seen.insert(path.clone());
+
+            match pending.get_mut(&path) {
+                Some(observation) if observation.len == len && observation.modified == modified => {
+                    if len > 0 && observation.since.elapsed() >= STABLE_FOR {
+                        ready.push(path);
+                    }
+                }
+                Some(observation) => {
+                    *observation = Observation { len, modified, since: Instant::now() };
+                }
+                None => {
+                    pending.insert(path, Observation { len, modified, since: Instant::now() });
+                }
+            }
+        }
+
+        pending.retain(|path, _| seen.contains(path));
+        for path in &ready {
+            pending.remove(path);
+        }
+        Ok(ready)
+    }
+
+    /// 导入一批文件并移动到 processed/ 或 failed/
+    async fn import_batch(&self, files: Vec<PathBuf>, stuck: &mut HashSet<PathBuf>) {
+        let classifier = ProjectClassifier::load(self.pool.clone())
+            .await
+            .with_event_emitter(self.emitter.clone());
+        let mut attachments = AttachmentWriter::new(self.pool.clone());
+        let mut new_ids = Vec::new();
+
+        for path in files {
+            let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            let result = match tokio::fs::read(&path).await {
+                Ok(data) => {
+                    let raw_path = format!("{}{}", IMPORTED_RAW_PATH_PREFIX, file_name);
+                    self.syncer
+                        .import_eml(self.config.account_id, &data, &raw_path, &classifier, &mut attachments)
+                        .await
+                }
+                Err(e) => Err(e.into()),
+            };
+
+            let moved = match result {
+                Ok(processed) => {
+                    self.update(|status| {
+                        match processed {
+                            ProcessedMessage::Linked(_) => status.duplicates += 1,
+                            ProcessedMessage::Saved(_) | ProcessedMessage::Receipt => status.imported += 1,
+                        }
+                        status.last_import_at = Some(chrono::Utc::now().to_rfc3339());
+                    });
+                    if let ProcessedMessage::Saved(email_id) = processed {
+                        log::info!("Imported {} as email {}", file_name, email_id);
+                        new_ids.push(email_id);
+                    }
+                    self.finish_processed(&path).await
+                }
+                Err(e) => {
+                    log::warn!("Failed to import {}: {}", file_name, e);
+                    self.update(|status| {
+                        status.failed += 1;
+                        status.last_error = Some(format!("{}: {}", file_name, e));
+                    });
+                    self.finish_failed(&path, &e).await
+                }
+            };
+            if let Err(e) = moved {
+                log::error!("Failed to move {:?} out of the watch folder: {}", path, e);
+                stuck.insert(path);
+            }
+        }
+
+        attachments.finish().await;
+        if !new_ids.is_empty() {
+            self.emitter.emit_new_emails(NewEmailsEvent {
+                account_id: self.config.account_id,
+                count: new_ids.len(),
+                email_ids: new_ids,
+            });
+        }
+        classifier.emit_project_events();
+    }
+
+    /// 导入成功：删除或移动到 processed/
+    async fn finish_processed(&self, path: &Path) -> std::io::Result<()> {
+        if self.config.delete_processed {
+            return tokio::fs::remove_file(path).await;
+        }
+        move_into(path, &self.root.join(PROCESSED_DIR)).await.map(|_| ())
+    }
+
+    /// 导入失败：移动到 failed/，旁边写入错误说明
+    async fn finish_failed(&self, path: &Path, error: &AppError) -> std::io::Result<()> {
+        let target = move_into(path, &self.root.join(FAILED_DIR)).await?;
+        let mut sidecar = target.clone().into_os_string();
+        sidecar.push(".error.txt");
+        let message = format!("{}\n{}\n", chrono::Utc::now().to_rfc3339(), error);
+        if let Err(e) = tokio::fs::write(&sidecar, message).await {
+            log::warn!("Failed to write error file {:?}: {}", sidecar, e);
+        }
+        Ok(())
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut WatchFolderStatus)) {
+        apply(&mut self.status.lock().unwrap());
+    }
+}
+
+/// 移动到子目录，重名时追加序号，返回新路径
+async fn move_into(path: &Path, dir: &Path) -> std::io::Result<PathBuf> {
+    tokio::fs::create_dir_all(dir).await?;
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let (stem, ext) = match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), format!(".{}", ext)),
+        _ => (file_name.clone(), String::new()),
+    };
+
+    let mut target = dir.join(&file_name);
+    let mut counter = 1;
+    while tokio::fs::try_exists(&target).await? {
+        counter += 1;
+        target = dir.join(format!("{} ({}){}", stem, counter, ext));
+    }
+    tokio::fs::rename(path, &target).await?;
+    Ok(target)
+}
+
+fn is_eml(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("eml"))
+}
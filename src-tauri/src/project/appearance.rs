@@ -0,0 +1,61 @@
+/// 项目外观（颜色与图标）
+use crate::error::AppError;
+use crate::utils::validation;
+
+/// 自动分配的项目颜色调色板
+pub const PALETTE: &[&str] = &[
+    "#3B82F6", // blue
+    "#10B981", // emerald
+    "#F59E0B", // amber
+    "#EF4444", // red
+    "#8B5CF6", // violet
+    "#EC4899", // pink
+    "#14B8A6", // teal
+    "#F97316", // orange
+    "#6366F1", // indigo
+    "#84CC16", // lime
+];
+
+/// 允许的项目图标（与前端 lucide 图标名称对应）
+pub const ALLOWED_ICONS: &[&str] = &[
+    "folder",
+    "briefcase",
+    "file-text",
+    "handshake",
+    "scale",
+    "code",
+    "package",
+    "megaphone",
+    "calendar",
+    "star",
+    "users",
+    "shopping-cart",
+];
+
+/// 根据项目名称确定性地选择调色板颜色（FNV-1a 哈希，跨版本稳定）
+pub fn palette_color_for(name: &str) -> &'static str {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in name.as_bytes() {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    PALETTE[(hash as usize) % PALETTE.len()]
+}
+
+/// 校验颜色为 #RGB 或 #RRGGBB 格式，返回大写形式
+pub fn validate_color(color: &str) -> Result<String, AppError> {
+    validation::hex_color("color", color)
+}
+
+/// 校验图标在允许列表中
+pub fn validate_icon(icon: &str) -> Result<String, AppError> {
+    if ALLOWED_ICONS.contains(&icon) {
+        Ok(icon.to_string())
+    } else {
+        Err(AppError::Validation(format!(
+            "Invalid icon: {} (allowed: {})",
+            icon,
+            ALLOWED_ICONS.join(", ")
+        )))
+    }
+}
@@ -0,0 +1,322 @@
+/// 分类决策日志
+///
+/// 记录每封邮件被分配到项目的原因（策略、匹配值、置信度）以及被放弃的候选项，
+/// 用于解释"为什么这封邮件出现在这个项目里"。
+///
+/// 用户手动移动自动分类的邮件时另外记录一条改正，据此统计分类准确率。
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// 日志保留天数（每封邮件最新的一条记录始终保留）
+pub const CLASSIFICATION_LOG_RETENTION_DAYS: i64 = 90;
+
+/// 分类策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClassificationMethod {
+    Thread,
+    Subject,
+    Label,
+    Rule,
+    Manual,
+    New,
+    Duplicate,
+}
+
+impl ClassificationMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClassificationMethod::Thread => "thread",
+            ClassificationMethod::Subject => "subject",
+            ClassificationMethod::Label => "label",
+            ClassificationMethod::Rule => "rule",
+            ClassificationMethod::Manual => "manual",
+            ClassificationMethod::New => "new",
+            ClassificationMethod::Duplicate => "duplicate",
+        }
+    }
+}
+
+/// 分类候选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassificationCandidate {
+    pub method: ClassificationMethod,
+    pub project_id: i64,
+    pub matched_value: Option<String>,
+    pub confidence: f64,
+}
+
+/// 分类解释
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClassificationExplanation {
+    pub email_id: i64,
+    pub project_id: Option<i64>,
+    pub project_name: Option<String>,
+    pub method: String,
+    pub matched_value: Option<String>,
+    pub confidence: f64,
+    pub created_at: String,
+    /// 同时命中但未被采用的候选项
+    pub alternatives: Vec<ClassificationCandidate>,
+}
+
+/// 分类准确率统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassifierMetrics {
+    pub days: i64,
+    /// 自动分类的邮件数
+    pub assigned: i64,
+    /// 其中被手动移动的邮件数
+    pub corrected: i64,
+    /// 1 - corrected / assigned（没有自动分类时为 None）
+    pub precision: Option<f64>,
+    pub by_method: Vec<MethodMetrics>,
+    /// 按分类日期的趋势（只包含有自动分类的日期）
+    pub trend: Vec<DailyMetrics>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MethodMetrics {
+    pub method: String,
+    pub assigned: i64,
+    pub corrected: i64,
+    #[sqlx(skip)]
+    pub precision: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DailyMetrics {
+    pub date: String,
+    pub assigned: i64,
+    pub corrected: i64,
+}
+
+fn precision(assigned: i64, corrected: i64) -> Option<f64> {
+    (assigned > 0).then(|| 1.0 - corrected as f64 / assigned as f64)
+}
+
+/// 统计窗口内的自动分类（每封邮件取窗口内最早的一次自动分类）及其改正
+const AUTO_ASSIGNMENTS_SQL: &str = r#"
+    WITH auto AS (
+        SELECT cl.id, cl.email_id, cl.method, date(cl.created_at) AS day
+        FROM classification_log cl
+        WHERE cl.method != 'manual'
+          AND datetime(cl.created_at) >= datetime('now', ?)
+          AND cl.id = (
+              SELECT MIN(first.id) FROM classification_log first
+              WHERE first.email_id = cl.email_id
+                AND first.method != 'manual'
+                AND datetime(first.created_at) >= datetime('now', ?)
+          )
+    ),
+    scored AS (
+        SELECT auto.method, auto.day,
+               EXISTS (SELECT 1 FROM classification_corrections cc WHERE cc.log_id = auto.id) AS corrected
+        FROM auto
+    )
+"#;
+
+/// 分类日志
+pub struct ClassificationLog {
+    pool: SqlitePool,
+}
+
+impl ClassificationLog {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 记录一次分类决策
+    pub async fn record(
+        &self,
+        email_id: i64,
+        chosen: &ClassificationCandidate,
+        alternatives: &[ClassificationCandidate],
+    ) -> Result<(), AppError> {
+        let mut conn = self.pool.acquire().await?;
+        Self::record_on(&mut conn, email_id, chosen, alternatives).await
+    }
+
+    /// 在给定连接（可以是事务）上记录分类决策
+    pub async fn record_on(
+        conn: &mut SqliteConnection,
+        email_id: i64,
+        chosen: &ClassificationCandidate,
+        alternatives: &[ClassificationCandidate],
+    ) -> Result<(), AppError> {
+        let alternatives = serde_json::to_string(alternatives)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO classification_log (
+                email_id, project_id, method, matched_value, confidence, alternatives
+            ) VALUES (?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(email_id)
+        .bind(chosen.project_id)
+        .bind(chosen.method.as_str())
+        .bind(&chosen.matched_value)
+        .bind(chosen.confidence)
+        .bind(&alternatives)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 邮件被手动移到 `to_project_id` 时，如果最新的分类是自动分类，记录一条改正
+    ///
+    /// 必须在写入本次手动分类日志之前调用。返回是否记录了改正。
+    pub async fn record_correction_on(
+        conn: &mut SqliteConnection,
+        email_id: i64,
+        to_project_id: i64,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO classification_corrections (
+                email_id, log_id, method, from_project_id, to_project_id, classified_at
+            )
+            SELECT email_id, id, method, project_id, ?, created_at
+            FROM classification_log
+            WHERE id = (SELECT MAX(id) FROM classification_log WHERE email_id = ?)
+              AND method != 'manual'
+              AND project_id IS NOT ?
+            "#
+        )
+        .bind(to_project_id)
+        .bind(email_id)
+        .bind(to_project_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 最近 `days` 天的自动分类准确率（按策略和日期汇总）
+    ///
+    /// 分类日志超过保留期后会被清理，`days` 超过保留期时早期数据不完整。
+    pub async fn metrics(&self, days: i64) -> Result<ClassifierMetrics, AppError> {
+        if days < 1 {
+            return Err(AppError::Validation("Metrics window must be at least 1 day".to_string()));
+        }
+        let window = format!("-{} days", days);
+
+        let mut by_method = sqlx::query_as::<_, MethodMetrics>(&format!(
+            r#"
+            {}
+            SELECT method, COUNT(*) AS assigned, COALESCE(SUM(corrected), 0) AS corrected
+            FROM scored
+            GROUP BY method
+            ORDER BY assigned DESC
+            "#,
+            AUTO_ASSIGNMENTS_SQL
+        ))
+        .bind(&window)
+        .bind(&window)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let trend = sqlx::query_as::<_, DailyMetrics>(&format!(
+            r#"
+            {}
+            SELECT day AS date, COUNT(*) AS assigned, COALESCE(SUM(corrected), 0) AS corrected
+            FROM scored
+            GROUP BY day
+            ORDER BY day
+            "#,
+            AUTO_ASSIGNMENTS_SQL
+        ))
+        .bind(&window)
+        .bind(&window)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for entry in &mut by_method {
+            entry.precision = precision(entry.assigned, entry.corrected);
+        }
+        let assigned = by_method.iter().map(|entry| entry.assigned).sum();
+        let corrected = by_method.iter().map(|entry| entry.corrected).sum();
+
+        Ok(ClassifierMetrics {
+            days,
+            assigned,
+            corrected,
+            precision: precision(assigned, corrected),
+            by_method,
+            trend,
+        })
+    }
+
+    /// 获取邮件最新的分类解释
+    pub async fn explain(&self, email_id: i64) -> Result<Option<ClassificationExplanation>, AppError> {
+        #[derive(sqlx::FromRow)]
+        struct LogRow {
+            project_id: Option<i64>,
+            project_name: Option<String>,
+            method: String,
+            matched_value: Option<String>,
+            confidence: Option<f64>,
+            alternatives: Option<String>,
+            created_at: Option<String>,
+        }
+
+        let row = sqlx::query_as::<_, LogRow>(
+            r#"
+            SELECT
+                cl.project_id,
+                p.name AS project_name,
+                cl.method,
+                cl.matched_value,
+                cl.confidence,
+                cl.alternatives,
+                cl.created_at
+            FROM classification_log cl
+            LEFT JOIN projects p ON p.id = cl.project_id
+            WHERE cl.email_id = ?
+            ORDER BY cl.id DESC
+            LIMIT 1
+            "#
+        )
+        .bind(email_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| ClassificationExplanation {
+            email_id,
+            project_id: row.project_id,
+            project_name: row.project_name,
+            method: row.method,
+            matched_value: row.matched_value,
+            confidence: row.confidence.unwrap_or(0.0),
+            created_at: row.created_at.unwrap_or_default(),
+            alternatives: row
+                .alternatives
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+        }))
+    }
+
+    /// 清理过期日志，每封邮件最新的一条记录始终保留
+    pub async fn prune(&self, retention_days: i64) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM classification_log
+            WHERE datetime(created_at) < datetime('now', ?)
+              AND id NOT IN (
+                  SELECT MAX(id) FROM classification_log GROUP BY email_id
+              )
+            "#
+        )
+        .bind(format!("-{} days", retention_days))
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            log::info!("Pruned {} classification log entries", result.rows_affected());
+        }
+        Ok(result.rows_affected())
+    }
+}
@@ -2,11 +2,43 @@
 ///
 /// MVP 阶段策略：
 /// 1. 基于 Thread ID 的强规则聚合
-/// 2. 基于主题相似度的聚合
-/// 3. 保守策略：只在高置信度时自动创建项目
+/// 2. （可选）Gmail 标签与已有项目同名
+/// 3. 基于主题相似度的聚合
+/// 4. 保守策略：只在高置信度时自动创建项目
+///
+/// 主题匹配偶尔会把大量无关邮件聚到一个项目里。项目超过设置的规模上限（每周邮件数、
+/// 不同发件人数、不同规范化主题数）时标记为待检查（`needs_review`）并发出通知，
+/// 之后不再按主题归入新邮件；线程和标签策略不受影响。
+///
+/// 单次运行（一次同步、一次导入）自动新建的项目数有上限（`max_new_projects_per_sync`），
+/// 避免首次同步满是一次性通知的邮箱时建出上千个项目。达到上限后没有匹配到已有项目的邮件保持未分类，
+/// 运行结束时发出一条警告通知；归入已有项目不受影响。
 
 use crate::error::AppError;
+use crate::events::notifications::SOURCE_PROJECT_LIFECYCLE;
+use crate::events::{EventEmitter, NotificationLevel, ProjectCreatedEvent, ProjectUpdatedEvent};
+use crate::project::appearance::palette_color_for;
+use crate::project::classification_log::{ClassificationCandidate, ClassificationLog, ClassificationMethod};
+use crate::project::naming::{load_generic_subjects, name_key, project_name, unique_project_name};
+use crate::project::templates::ProjectTemplateStore;
+use crate::mail::language::detect_language;
+use crate::mail::parser::{generate_thread_id, ParsedEmail};
+use crate::repository::ProjectRepository;
+use crate::utils::i18n::parse_timestamp;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// 各策略的置信度
+const THREAD_CONFIDENCE: f64 = 0.95;
+const LABEL_CONFIDENCE: f64 = 0.8;
+const SUBJECT_CONFIDENCE: f64 = 0.6;
+const NEW_PROJECT_CONFIDENCE: f64 = 0.3;
+
+/// 忽略待检查提醒后多少天内不再标记
+const REVIEW_SNOOZE_DAYS: i64 = 7;
 
 /// 安全地截断 UTF-8 字符串到指定字节长度
 /// 确保不会在多字节字符的中间截断
@@ -24,14 +56,198 @@ fn safe_truncate(s: &str, max_bytes: usize) -> String {
     s[..end].to_string()
 }
 
+/// 分类器设置（每次同步读取一次，而不是每封邮件读取）
+#[derive(Debug, Clone)]
+pub struct ClassifierConfig {
+    /// 按主题归类时匹配的时间窗口（天）
+    pub window_days: i64,
+    /// 规范化后的主题短于该字符数时跳过主题归类
+    pub min_subject_len: usize,
+    /// 规范化时额外去除的模式（如 `\[JIRA-\d+\]`）
+    pub strip_patterns: Vec<Regex>,
+    /// 按 Gmail 标签匹配同名的已有项目
+    pub use_labels: bool,
+    /// 项目规模上限
+    pub limits: ProjectLimits,
+    /// 共享邮箱（其他用户 / 共享命名空间）中的邮件没有匹配项目时自动创建项目
+    pub shared_mailbox_auto_create: bool,
+    /// 单次运行最多自动新建的项目数（0 表示不限）
+    pub max_new_projects: usize,
+}
+
+/// 项目规模上限（0 表示不限）
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectLimits {
+    /// 最近 7 天的邮件数
+    pub weekly_emails: i64,
+    /// 主题归类时间窗口内的不同发件人数
+    pub senders: i64,
+    /// 主题归类时间窗口内的不同规范化主题数
+    pub subjects: i64,
+}
+
+impl Default for ProjectLimits {
+    fn default() -> Self {
+        Self {
+            weekly_emails: 150,
+            senders: 40,
+            subjects: 30,
+        }
+    }
+}
+
+impl ProjectLimits {
+    fn is_disabled(&self) -> bool {
+        self.weekly_emails <= 0 && self.senders <= 0 && self.subjects <= 0
+    }
+}
+
+impl Default for ClassifierConfig {
+    fn default() -> Self {
+        Self {
+            window_days: 30,
+            min_subject_len: 3,
+            strip_patterns: Vec::new(),
+            use_labels: false,
+            limits: ProjectLimits::default(),
+            shared_mailbox_auto_create: true,
+            max_new_projects: 100,
+        }
+    }
+}
+
+impl ClassifierConfig {
+    /// 从设置读取；读取失败时使用默认值，无效的正则被跳过
+    pub async fn load(pool: &SqlitePool) -> Self {
+        let row: Result<(i64, i64, String, bool, i64, i64, i64, bool, i64), sqlx::Error> = sqlx::query_as(
+            r#"
+            SELECT classifier_window_days, classifier_min_subject_len, classifier_strip_ticket_ids,
+                   classifier_use_labels, project_limit_weekly_emails, project_limit_senders,
+                   project_limit_subjects, shared_mailbox_auto_create, max_new_projects_per_sync
+            FROM sync_settings WHERE id = 1
+            "#
+        )
+        .fetch_one(pool)
+        .await;
+
+        match row {
+            Ok((window_days, min_subject_len, patterns, use_labels, weekly_emails, senders, subjects, shared_mailbox_auto_create, max_new_projects)) => Self {
+                window_days: window_days.max(1),
+                min_subject_len: min_subject_len.max(0) as usize,
+                strip_patterns: patterns
+                    .lines()
+                    .map(str::trim)
+                    .filter(|pattern| !pattern.is_empty())
+                    .filter_map(|pattern| match Regex::new(pattern) {
+                        Ok(regex) => Some(regex),
+                        Err(e) => {
+                            log::warn!("Ignoring invalid classifier pattern {:?}: {}", pattern, e);
+                            None
+                        }
+                    })
+                    .collect(),
+                use_labels,
+                limits: ProjectLimits {
+                    weekly_emails,
+                    senders,
+                    subjects,
+                },
+                shared_mailbox_auto_create,
+                max_new_projects: max_new_projects.max(0) as usize,
+            },
+            Err(e) => {
+                log::warn!("Failed to load classifier settings, using defaults: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// 校验正则列表（每行一个），返回第一个无效的模式
+    pub fn validate_patterns(patterns: &str) -> Result<(), AppError> {
+        for pattern in patterns.lines().map(str::trim).filter(|pattern| !pattern.is_empty()) {
+            Regex::new(pattern)
+                .map_err(|e| AppError::Validation(format!("Invalid classifier pattern {:?}: {}", pattern, e)))?;
+        }
+        Ok(())
+    }
+}
+
+/// 分类预览结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassificationPreview {
+    /// 采用的策略（thread / label / subject / new）
+    pub method: String,
+    /// 已有项目 ID；新建项目时为 None
+    pub project_id: Option<i64>,
+    pub project_name: String,
+    pub confidence: f64,
+}
+
 /// 项目分类器
 pub struct ProjectClassifier {
     pool: SqlitePool,
+    config: ClassifierConfig,
+    event_emitter: Option<EventEmitter>,
+    /// 本次同步分类过的项目，`emit_project_events` 时汇总发送
+    activity: Mutex<ProjectActivity>,
+    /// 本次运行新建的项目数和因达到上限而未分类的邮件数
+    creation: Mutex<CreationTally>,
+}
+
+/// 本次运行的项目新建统计
+#[derive(Default)]
+struct CreationTally {
+    created: usize,
+    capped: usize,
+    /// 上限通知已发送
+    notified: bool,
+}
+
+/// 分类器创建和更新过的项目
+#[derive(Default)]
+struct ProjectActivity {
+    created: Vec<i64>,
+    updated: HashMap<i64, ProjectUpdatedEvent>,
 }
 
 impl ProjectClassifier {
-    pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+    pub fn new(pool: SqlitePool, config: ClassifierConfig) -> Self {
+        Self {
+            pool,
+            config,
+            event_emitter: None,
+            activity: Mutex::default(),
+            creation: Mutex::default(),
+        }
+    }
+
+    /// 取消新建项目数上限（用户主动运行批量分类时）
+    pub fn without_project_cap(mut self) -> Self {
+        self.config.max_new_projects = 0;
+        self
+    }
+
+    /// 本次运行因达到新建项目上限而未分类的邮件数
+    pub fn capped_emails(&self) -> usize {
+        self.creation.lock().unwrap().capped
+    }
+
+    /// 本次运行是否已达到新建项目上限
+    fn project_cap_reached(&self) -> bool {
+        let max = self.config.max_new_projects;
+        max > 0 && self.creation.lock().unwrap().created >= max
+    }
+
+    /// 项目超过规模上限时通过事件发射器发送通知
+    pub fn with_event_emitter(mut self, emitter: EventEmitter) -> Self {
+        self.event_emitter = Some(emitter);
+        self
+    }
+
+    /// 读取分类器设置并创建分类器
+    pub async fn load(pool: SqlitePool) -> Self {
+        let config = ClassifierConfig::load(&pool).await;
+        Self::new(pool, config)
     }
 
     /// 为新同步的邮件自动分配项目
@@ -39,45 +255,305 @@ impl ProjectClassifier {
     /// 策略：
     /// 1. 如果邮件有 thread_id，查找同一 thread 的其他邮件
     /// 2. 如果找到已分配项目的邮件，使用相同项目
-    /// 3. 如果没有，基于主题相似度查找
-    /// 4. 如果都没有，创建新项目
-    pub async fn classify_email(&self, email_id: i64) -> Result<i64, AppError> {
+    /// 3. 开启标签匹配时，查找与 Gmail 用户标签同名的项目
+    /// 4. 如果没有，基于主题相似度查找
+    /// 5. 如果都没有，创建新项目（关闭共享邮箱自动建项目时，共享邮箱中的邮件保持未分类，返回 None）
+    ///
+    /// 每次决策都会写入分类日志（包括命中但未被采用的候选项）
+    ///
+    /// 与其他同步并发分类同一封邮件时，只有先写入项目的一方生效（`project_id IS NULL` 时才写入），
+    /// 另一方返回已分配的项目，不重复记录。
+    pub async fn classify_email(&self, email_id: i64) -> Result<Option<i64>, AppError> {
         // 1. 获取邮件信息
         let email = self.get_email_info(email_id).await?;
 
         // 2. 如果已经有项目，直接返回
         if let Some(project_id) = email.project_id {
-            return Ok(project_id);
+            return Ok(Some(project_id));
+        }
+
+        // 3. 收集候选项（按策略优先级：thread > label > subject）
+        let mut candidates = self.collect_candidates(&email).await?;
+
+        // 4. 处理命中回收站中项目的候选项
+        self.resolve_deleted_candidates(&mut candidates, true).await?;
+
+        // 5. 采用优先级最高的候选项
+        if let Some(chosen) = candidates.first() {
+            if !self.assign_email_to_project(email_id, chosen.project_id).await? {
+                return self.claimed_project(email_id).await;
+            }
+            self.record_decision(email_id, chosen, &candidates[1..]).await;
+            log::info!(
+                "Assigned email {} to project {} (by {})",
+                email_id, chosen.project_id, chosen.method.as_str()
+            );
+            if let Err(e) = self.check_limits(chosen.project_id).await {
+                log::warn!("Failed to check size limits of project {}: {}", chosen.project_id, e);
+            }
+            self.record_activity(chosen.project_id, email.date.as_deref(), false);
+            return Ok(Some(chosen.project_id));
+        }
+
+        if email.from_shared_mailbox && !self.config.shared_mailbox_auto_create {
+            log::info!("Email {} is from a shared mailbox, not creating a project", email_id);
+            return Ok(None);
+        }
+
+        // 6. 创建新项目（达到本次运行的上限后只复用已有的同名自动项目）
+        let allow_create = !self.project_cap_reached();
+        let Some((project_id, created)) = self.create_project_for_email(&email, allow_create).await? else {
+            self.creation.lock().unwrap().capped += 1;
+            log::info!(
+                "New project cap ({}) reached, leaving email {} unassigned",
+                self.config.max_new_projects, email_id
+            );
+            return Ok(None);
+        };
+        if !self.assign_email_to_project(email_id, project_id).await? {
+            if created {
+                self.discard_empty_project(project_id).await?;
+            }
+            return self.claimed_project(email_id).await;
         }
+        if created {
+            self.creation.lock().unwrap().created += 1;
+            self.apply_matching_template(project_id, &email).await;
+        }
+        let chosen = ClassificationCandidate {
+            method: ClassificationMethod::New,
+            project_id,
+            matched_value: email.subject.clone(),
+            confidence: NEW_PROJECT_CONFIDENCE,
+        };
+        self.record_decision(email_id, &chosen, &[]).await;
+        if created {
+            log::info!("Created new project {} for email {}", project_id, email_id);
+        } else {
+            log::info!("Reused auto-created project {} for email {}", project_id, email_id);
+        }
+        self.record_activity(project_id, email.date.as_deref(), created);
+
+        Ok(Some(project_id))
+    }
+
+    /// 记录分到项目的新邮件（没有事件发射器时不记录）
+    fn record_activity(&self, project_id: i64, date: Option<&str>, created: bool) {
+        if self.event_emitter.is_none() {
+            return;
+        }
+        let mut activity = self.activity.lock().unwrap();
+        if created {
+            activity.created.push(project_id);
+        }
+        let update = activity.updated.entry(project_id).or_insert_with(|| ProjectUpdatedEvent {
+            project_id,
+            new_email_count: 0,
+            latest_event_at: None,
+        });
+        update.new_email_count += 1;
+        let newer = match (date.and_then(parse_timestamp), update.latest_event_at.as_deref().and_then(parse_timestamp)) {
+            (Some(date), Some(latest)) => date > latest,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if newer {
+            update.latest_event_at = date.map(str::to_string);
+        }
+    }
+
+    /// 发送本次分类累积的 `project-created` / `project-updated` 事件并清空（同步结束时调用）
+    ///
+    /// 达到新建项目上限时同时发出一条警告通知（每次运行一条）。
+    pub fn emit_project_events(&self) {
+        let Some(emitter) = &self.event_emitter else {
+            return;
+        };
+        {
+            let mut creation = self.creation.lock().unwrap();
+            if creation.capped > 0 && !creation.notified {
+                creation.notified = true;
+                emitter.emit_notification_from(
+                    "Project creation paused",
+                    &format!(
+                        "{} new projects were created, the limit for one sync. {} emails without a matching project were left unassigned.",
+                        creation.created, creation.capped
+                    ),
+                    NotificationLevel::Warning,
+                    Some(SOURCE_PROJECT_LIFECYCLE),
+                    None,
+                );
+            }
+        }
+        let activity = std::mem::take(&mut *self.activity.lock().unwrap());
+        for project_id in activity.created {
+            emitter.emit_project_created(ProjectCreatedEvent { project_id });
+        }
+        for event in activity.updated.into_values() {
+            emitter.emit_project_updated(event);
+        }
+    }
+
+    /// 预览尚未保存的邮件会被分到哪个项目（不写数据库，不恢复已删除项目）
+    pub async fn preview(
+        &self,
+        account_id: i64,
+        parsed: &ParsedEmail,
+        is_automated: bool,
+        gmail_labels: Option<&[String]>,
+    ) -> Result<ClassificationPreview, AppError> {
+        let email = EmailInfo {
+            id: 0,
+            message_id: parsed.message_id.clone(),
+            thread_id: Some(generate_thread_id(parsed)),
+            subject: Some(parsed.subject.clone()),
+            sender: Some(parsed.from.clone()),
+            date: Some(parsed.date.clone()),
+            project_id: None,
+            account_id,
+            is_automated: Some(is_automated),
+            lang: Some(detect_language(Some(&parsed.subject), parsed.body_text.as_deref())),
+            gmail_labels: gmail_labels.and_then(|labels| serde_json::to_string(labels).ok()),
+            from_shared_mailbox: false,
+        };
+
+        let mut candidates = self.collect_candidates(&email).await?;
+        self.resolve_deleted_candidates(&mut candidates, false).await?;
+
+        if let Some(chosen) = candidates.first() {
+            let project_name: Option<String> = sqlx::query_scalar("SELECT name FROM projects WHERE id = ?")
+                .bind(chosen.project_id)
+                .fetch_optional(&self.pool)
+                .await?;
+            return Ok(ClassificationPreview {
+                method: chosen.method.as_str().to_string(),
+                project_id: Some(chosen.project_id),
+                project_name: project_name.unwrap_or_default(),
+                confidence: chosen.confidence,
+            });
+        }
+
+        let generic_subjects = load_generic_subjects(&self.pool).await;
+        let base_name = project_name(email.subject.as_deref(), email.sender.as_deref(), &generic_subjects, is_automated);
+        Ok(ClassificationPreview {
+            method: ClassificationMethod::New.as_str().to_string(),
+            project_id: None,
+            project_name: unique_project_name(&mut *self.pool.acquire().await?, &base_name).await?,
+            confidence: NEW_PROJECT_CONFIDENCE,
+        })
+    }
+
+    /// 按策略优先级收集候选项（只读）
+    async fn collect_candidates(&self, email: &EmailInfo) -> Result<Vec<ClassificationCandidate>, AppError> {
+        let mut candidates = Vec::new();
 
-        // 3. 基于 Thread ID 查找项目
         if let Some(thread_id) = &email.thread_id {
             if let Some(project_id) = self.find_project_by_thread(thread_id).await? {
-                self.assign_email_to_project(email_id, project_id).await?;
-                log::info!("Assigned email {} to project {} (by thread)", email_id, project_id);
-                return Ok(project_id);
+                candidates.push(ClassificationCandidate {
+                    method: ClassificationMethod::Thread,
+                    project_id,
+                    matched_value: Some(thread_id.clone()),
+                    confidence: THREAD_CONFIDENCE,
+                });
+            }
+        }
+
+        if self.config.use_labels {
+            if let Some((project_id, label)) = self.find_project_by_label(email.gmail_labels.as_deref()).await? {
+                candidates.push(ClassificationCandidate {
+                    method: ClassificationMethod::Label,
+                    project_id,
+                    matched_value: Some(label),
+                    confidence: LABEL_CONFIDENCE,
+                });
             }
         }
 
-        // 4. 基于主题相似度查找项目
         if let Some(subject) = &email.subject {
-            let normalized_subject = normalize_subject(subject);
-            if let Some(project_id) = self.find_project_by_subject(&normalized_subject).await? {
-                self.assign_email_to_project(email_id, project_id).await?;
-                log::info!("Assigned email {} to project {} (by subject)", email_id, project_id);
-                return Ok(project_id);
+            let normalized_subject = normalize_subject(subject, email.lang.as_deref(), &self.config);
+            // 太短的主题（"Hi"、"?"）容易误匹配，跳过主题归类
+            let long_enough = normalized_subject.chars().count() >= self.config.min_subject_len.max(1);
+            let matched = if long_enough {
+                self.find_project_by_subject(&normalized_subject).await?
+            } else {
+                None
+            };
+            if let Some(project_id) = matched {
+                candidates.push(ClassificationCandidate {
+                    method: ClassificationMethod::Subject,
+                    project_id,
+                    matched_value: Some(normalized_subject),
+                    confidence: SUBJECT_CONFIDENCE,
+                });
             }
         }
 
-        // 5. 创建新项目
-        let project_id = self.create_project_for_email(&email).await?;
-        self.assign_email_to_project(email_id, project_id).await?;
-        log::info!("Created new project {} for email {}", project_id, email_id);
+        Ok(candidates)
+    }
 
-        Ok(project_id)
+    /// 处理指向已删除项目的候选项
+    ///
+    /// 按设置 `deleted_project_match`：`restore` 时恢复被采用的项目；
+    /// `new` 时丢弃这些候选项，没有其他候选项时会新建项目。`apply` 为 false 时只做判断，不恢复项目。
+    async fn resolve_deleted_candidates(
+        &self,
+        candidates: &mut Vec<ClassificationCandidate>,
+        apply: bool,
+    ) -> Result<(), AppError> {
+        let mut deleted = Vec::new();
+        for candidate in candidates.iter() {
+            let status: Option<String> = sqlx::query_scalar("SELECT status FROM projects WHERE id = ?")
+                .bind(candidate.project_id)
+                .fetch_optional(&self.pool)
+                .await?;
+            if status.as_deref() == Some("deleted") {
+                deleted.push(candidate.project_id);
+            }
+        }
+        if deleted.is_empty() {
+            return Ok(());
+        }
+
+        let policy: Option<String> = sqlx::query_scalar(
+            "SELECT deleted_project_match FROM sync_settings WHERE id = 1"
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .flatten();
+
+        match policy.as_deref() {
+            Some("new") => {
+                candidates.retain(|c| !deleted.contains(&c.project_id));
+            }
+            _ => {
+                if let Some(chosen) = candidates.first().filter(|_| apply) {
+                    if deleted.contains(&chosen.project_id) {
+                        ProjectRepository::new(self.pool.clone()).restore(chosen.project_id).await?;
+                        log::info!("Restored deleted project {} for new matching email", chosen.project_id);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 写入分类日志（失败不影响分类结果）
+    async fn record_decision(
+        &self,
+        email_id: i64,
+        chosen: &ClassificationCandidate,
+        alternatives: &[ClassificationCandidate],
+    ) {
+        let log = ClassificationLog::new(self.pool.clone());
+        if let Err(e) = log.record(email_id, chosen, alternatives).await {
+            log::warn!("Failed to record classification for email {}: {}", email_id, e);
+        }
     }
 
-    /// 批量分类邮件（用于初次同步）
+    /// 批量分类邮件（用于初次同步，或用户在未分类邮件视图中手动运行）
+    ///
+    /// 新建项目数受本次运行的上限约束，手动运行时可用 `without_project_cap` 取消。
     pub async fn classify_all_unassigned(&self) -> Result<usize, AppError> {
         let unassigned_emails = self.get_unassigned_emails().await?;
         let count = unassigned_emails.len();
@@ -97,7 +573,11 @@ impl ProjectClassifier {
             r#"
             SELECT
                 id, message_id, thread_id, subject, sender,
-                date, project_id, account_id
+                date, project_id, account_id, is_automated, lang, gmail_labels,
+                EXISTS(
+                    SELECT 1 FROM email_folders f
+                    WHERE f.email_id = emails.id AND COALESCE(f.namespace, 'personal') != 'personal'
+                ) AS from_shared_mailbox
             FROM emails
             WHERE id = ?
             "#
@@ -126,20 +606,56 @@ impl ProjectClassifier {
         Ok(result.map(|(id,)| id))
     }
 
-    /// 基于主题相似度查找项目
+    /// 查找与 Gmail 用户标签同名的项目（忽略大小写），返回 (项目 ID, 匹配的标签)
+    ///
+    /// 系统标签（`\Inbox`、`\Important` 等）跳过；嵌套标签（"Clients/Acme"）同时尝试完整名称和最后一段。
+    async fn find_project_by_label(&self, labels: Option<&str>) -> Result<Option<(i64, String)>, AppError> {
+        let labels: Vec<String> = labels
+            .and_then(|labels| serde_json::from_str(labels).ok())
+            .unwrap_or_default();
+
+        for label in labels.iter().filter(|label| !label.starts_with('\\')) {
+            let mut names = vec![label.as_str()];
+            if let Some((_, leaf)) = label.rsplit_once('/') {
+                names.push(leaf);
+            }
+            for name in names.into_iter().map(str::trim).filter(|name| !name.is_empty()) {
+                let project_id: Option<i64> = sqlx::query_scalar(
+                    r#"
+                    SELECT id FROM projects
+                    WHERE name = ? COLLATE NOCASE AND status != 'deleted'
+                    ORDER BY updated_at DESC
+                    LIMIT 1
+                    "#
+                )
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await?;
+                if let Some(project_id) = project_id {
+                    return Ok(Some((project_id, label.clone())));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// 基于主题相似度查找项目（跳过待检查的项目）
     async fn find_project_by_subject(&self, normalized_subject: &str) -> Result<Option<i64>, AppError> {
-        // 查找最近 30 天内主题相似的邮件
+        // 查找时间窗口内主题相似的邮件
         let result: Option<(i64,)> = sqlx::query_as(
             r#"
-            SELECT project_id
-            FROM emails
-            WHERE project_id IS NOT NULL
-              AND datetime(date) > datetime('now', '-30 days')
-              AND subject LIKE ?
-            ORDER BY date DESC
+            SELECT e.project_id
+            FROM emails e
+            JOIN projects p ON p.id = e.project_id
+            WHERE COALESCE(p.needs_review, 0) = 0
+              AND datetime(e.date) > datetime('now', '-' || ? || ' days')
+              AND e.subject LIKE ?
+            ORDER BY julianday(e.date) DESC
             LIMIT 1
             "#
         )
+        .bind(self.config.window_days)
         .bind(format!("%{}%", normalized_subject))
         .fetch_optional(&self.pool)
         .await?;
@@ -147,41 +663,254 @@ impl ProjectClassifier {
         Ok(result.map(|(id,)| id))
     }
 
-    /// 为邮件创建新项目
-    async fn create_project_for_email(&self, email: &EmailInfo) -> Result<i64, AppError> {
-        // 使用主题作为项目名称（去除 Re: / Fwd: 等前缀）
-        let project_name = email.subject
-            .as_ref()
-            .map(|s| normalize_subject(s))
-            .unwrap_or_else(|| format!("Project from {}", email.sender.as_deref().unwrap_or("Unknown")));
+    /// 为邮件创建新项目，返回 (项目 ID, 是否新建)
+    ///
+    /// 同一账户中规范化名称相同的自动创建项目只有一个（`idx_projects_auto_key`）：并发同步同时创建
+    /// "Invoice" 时，后插入的一方复用先创建的项目。去重键被回收站中或待检查的项目占用时，释放后重新创建。
+    /// `allow_create` 为 false（达到新建上限）时只复用可用的已有项目，没有时返回 None。
+    async fn create_project_for_email(&self, email: &EmailInfo, allow_create: bool) -> Result<Option<(i64, bool)>, AppError> {
+        // 使用清理后的主题命名，笼统主题加上发件人组织
+        let generic_subjects = load_generic_subjects(&self.pool).await;
+        let base_name = project_name(
+            email.subject.as_deref(),
+            email.sender.as_deref(),
+            &generic_subjects,
+            email.is_automated.unwrap_or(false),
+        );
+        let key = name_key(&base_name);
+        if !allow_create {
+            let holder: Option<i64> = sqlx::query_scalar(
+                r#"
+                SELECT id FROM projects
+                WHERE auto_account_id = ? AND auto_key = ?
+                  AND COALESCE(status, '') != 'deleted' AND COALESCE(needs_review, 0) = 0
+                "#
+            )
+            .bind(email.account_id)
+            .bind(&key)
+            .fetch_optional(&self.pool)
+            .await?;
+            return Ok(holder.map(|holder| (holder, false)));
+        }
+        let project_name = unique_project_name(&mut *self.pool.acquire().await?, &base_name).await?;
+
+        // 原始主题保存在描述中，避免清理时丢失信息
+        let description = email
+            .subject
+            .as_deref()
+            .map(str::trim)
+            .filter(|subject| !subject.is_empty() && *subject != project_name)
+            .map(|subject| format!("Original subject: {}", subject));
+
+        // 根据名称确定性地分配颜色，避免界面全是灰色
+        let color = palette_color_for(&project_name);
+
+        // 先写后读：事务一开始就取得写锁，读到的占用者不会在提交前变化
+        let mut tx = self.pool.begin().await?;
+        loop {
+            let result = sqlx::query(
+                r#"
+                INSERT INTO projects (name, description, status, color, email_count, attachment_count,
+                                      auto_key, auto_account_id, created_at, updated_at)
+                VALUES (?, ?, 'active', ?, 0, 0, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+                ON CONFLICT DO NOTHING
+                "#
+            )
+            .bind(&project_name)
+            .bind(&description)
+            .bind(color)
+            .bind(&key)
+            .bind(email.account_id)
+            .execute(&mut *tx)
+            .await?;
+            if result.rows_affected() > 0 {
+                let project_id = result.last_insert_rowid();
+                tx.commit().await?;
+                return Ok(Some((project_id, true)));
+            }
+
+            let (holder, status, needs_review): (i64, Option<String>, Option<bool>) = sqlx::query_as(
+                "SELECT id, status, needs_review FROM projects WHERE auto_account_id = ? AND auto_key = ?"
+            )
+            .bind(email.account_id)
+            .bind(&key)
+            .fetch_one(&mut *tx)
+            .await?;
+            if status.as_deref() != Some("deleted") && !needs_review.unwrap_or(false) {
+                tx.commit().await?;
+                return Ok(Some((holder, false)));
+            }
+            sqlx::query("UPDATE projects SET auto_key = NULL, auto_account_id = NULL WHERE id = ?")
+                .bind(holder)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    /// 新建的项目命中模板匹配条件时套用模板（合并方式）；失败不影响分类
+    async fn apply_matching_template(&self, project_id: i64, email: &EmailInfo) {
+        let templates = ProjectTemplateStore::new(self.pool.clone());
+        match templates.matching(email.sender.as_deref(), email.subject.as_deref()).await {
+            Ok(Some(template)) => {
+                if let Err(e) = templates.apply(project_id, template.id, false).await {
+                    log::warn!("Failed to apply template {:?} to project {}: {}", template.name, project_id, e);
+                } else {
+                    log::info!("Applied template {:?} to new project {}", template.name, project_id);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("Failed to load project templates: {}", e),
+        }
+    }
+
+    /// 删除为邮件新建、但邮件已被其他同步分配的空项目
+    async fn discard_empty_project(&self, project_id: i64) -> Result<(), AppError> {
+        sqlx::query(
+            "DELETE FROM projects WHERE id = ? AND NOT EXISTS (SELECT 1 FROM emails WHERE project_id = ?)"
+        )
+        .bind(project_id)
+        .bind(project_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 邮件已被其他同步分配时返回其项目
+    async fn claimed_project(&self, email_id: i64) -> Result<Option<i64>, AppError> {
+        log::debug!("Email {} was classified concurrently, keeping the existing assignment", email_id);
+        let project_id: Option<i64> = sqlx::query_scalar("SELECT project_id FROM emails WHERE id = ?")
+            .bind(email_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .flatten();
+        Ok(project_id)
+    }
+
+    /// 项目超过规模上限时标记为待检查并发出通知（已标记或最近忽略过提醒的项目跳过）
+    async fn check_limits(&self, project_id: i64) -> Result<(), AppError> {
+        let limits = self.config.limits;
+        if limits.is_disabled() {
+            return Ok(());
+        }
+
+        let row: Option<(String, bool, bool)> = sqlx::query_as(
+            r#"
+            SELECT name, COALESCE(needs_review, 0),
+                   COALESCE(datetime(review_dismissed_at) > datetime('now', '-' || ? || ' days'), 0)
+            FROM projects WHERE id = ?
+            "#
+        )
+        .bind(REVIEW_SNOOZE_DAYS)
+        .bind(project_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some((name, flagged, snoozed)) = row else {
+            return Ok(());
+        };
+        if flagged || snoozed {
+            return Ok(());
+        }
 
-        let result = sqlx::query(
+        let (weekly, window_emails, senders): (i64, i64, i64) = sqlx::query_as(
             r#"
-            INSERT INTO projects (name, status, email_count, attachment_count, created_at, updated_at)
-            VALUES (?, 'active', 0, 0, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            SELECT
+                COALESCE(SUM(datetime(date) > datetime('now', '-7 days')), 0),
+                COUNT(*),
+                COUNT(DISTINCT sender_address)
+            FROM emails
+            WHERE project_id = ? AND duplicate_of IS NULL
+              AND datetime(date) > datetime('now', '-' || ? || ' days')
             "#
         )
-        .bind(&project_name)
+        .bind(project_id)
+        .bind(self.config.window_days.max(7))
+        .fetch_one(&self.pool)
+        .await?;
+
+        let mut reasons = Vec::new();
+        if limits.weekly_emails > 0 && weekly > limits.weekly_emails {
+            reasons.push(format!("{} emails in the last 7 days (limit {})", weekly, limits.weekly_emails));
+        }
+        if limits.senders > 0 && senders > limits.senders {
+            reasons.push(format!("{} different senders (limit {})", senders, limits.senders));
+        }
+        // 邮件数不超过上限时不可能有更多不同主题，不必逐封规范化
+        if limits.subjects > 0 && window_emails > limits.subjects {
+            let rows: Vec<(Option<String>, Option<String>)> = sqlx::query_as(
+                r#"
+                SELECT subject, lang FROM emails
+                WHERE project_id = ? AND duplicate_of IS NULL
+                  AND datetime(date) > datetime('now', '-' || ? || ' days')
+                "#
+            )
+            .bind(project_id)
+            .bind(self.config.window_days.max(7))
+            .fetch_all(&self.pool)
+            .await?;
+            let subjects: HashSet<String> = rows
+                .iter()
+                .filter_map(|(subject, lang)| {
+                    let subject = subject.as_deref()?;
+                    Some(normalize_subject(subject, lang.as_deref(), &self.config).to_lowercase())
+                })
+                .collect();
+            if subjects.len() as i64 > limits.subjects {
+                reasons.push(format!("{} different subjects (limit {})", subjects.len(), limits.subjects));
+            }
+        }
+        if reasons.is_empty() {
+            return Ok(());
+        }
+
+        let reason = reasons.join(", ");
+        sqlx::query(
+            "UPDATE projects SET needs_review = 1, review_reason = ?, review_flagged_at = CURRENT_TIMESTAMP WHERE id = ?"
+        )
+        .bind(&reason)
+        .bind(project_id)
         .execute(&self.pool)
         .await?;
+        log::warn!("Project {} exceeded size limits: {}", project_id, reason);
 
-        Ok(result.last_insert_rowid())
+        if let Some(emitter) = &self.event_emitter {
+            emitter.emit_notification_from(
+                "Project may need splitting",
+                &format!(
+                    "\"{}\" has {}. New emails will no longer be added to it by subject; consider splitting it by thread.",
+                    name, reason
+                ),
+                NotificationLevel::Warning,
+                Some(SOURCE_PROJECT_LIFECYCLE),
+                Some(&format!("project:{}", project_id)),
+            );
+        }
+        Ok(())
     }
 
-    /// 将邮件分配到项目
-    async fn assign_email_to_project(&self, email_id: i64, project_id: i64) -> Result<(), AppError> {
-        sqlx::query(
-            "UPDATE emails SET project_id = ? WHERE id = ?"
+    /// 将未分配的邮件分配到项目，邮件已有项目（被其他同步抢先分配）时返回 false
+    async fn assign_email_to_project(&self, email_id: i64, project_id: i64) -> Result<bool, AppError> {
+        let claimed = sqlx::query(
+            "UPDATE emails SET project_id = ? WHERE id = ? AND project_id IS NULL"
         )
         .bind(project_id)
         .bind(email_id)
         .execute(&self.pool)
-        .await?;
+        .await?
+        .rows_affected()
+            > 0;
+        if !claimed {
+            return Ok(false);
+        }
+        sqlx::query("UPDATE attachments SET project_id = ? WHERE email_id = ?")
+            .bind(project_id)
+            .bind(email_id)
+            .execute(&self.pool)
+            .await?;
 
         // 更新项目统计
         self.update_project_stats(project_id).await?;
 
-        Ok(())
+        Ok(true)
     }
 
     /// 更新项目统计信息
@@ -190,8 +919,9 @@ impl ProjectClassifier {
             r#"
             UPDATE projects
             SET
-                email_count = (SELECT COUNT(*) FROM emails WHERE project_id = ?),
-                attachment_count = (SELECT COUNT(*) FROM attachments WHERE email_id IN (SELECT id FROM emails WHERE project_id = ?)),
+                email_count = (SELECT COUNT(*) FROM emails WHERE project_id = ? AND duplicate_of IS NULL AND server_deleted_at IS NULL),
+                attachment_count = (SELECT COUNT(*) FROM attachments WHERE email_id IN (SELECT id FROM emails WHERE project_id = ? AND duplicate_of IS NULL AND server_deleted_at IS NULL))
+                    + (SELECT COUNT(*) FROM attachments WHERE email_id IS NULL AND project_id = ?),
                 updated_at = CURRENT_TIMESTAMP
             WHERE id = ?
             "#
@@ -199,6 +929,7 @@ impl ProjectClassifier {
         .bind(project_id)
         .bind(project_id)
         .bind(project_id)
+        .bind(project_id)
         .execute(&self.pool)
         .await?;
 
@@ -208,7 +939,7 @@ impl ProjectClassifier {
     /// 获取未分配项目的邮件
     async fn get_unassigned_emails(&self) -> Result<Vec<i64>, AppError> {
         let rows: Vec<(i64,)> = sqlx::query_as(
-            "SELECT id FROM emails WHERE project_id IS NULL ORDER BY date DESC"
+            "SELECT id FROM emails WHERE project_id IS NULL ORDER BY julianday(date) DESC"
         )
         .fetch_all(&self.pool)
         .await?;
@@ -228,14 +959,40 @@ struct EmailInfo {
     date: Option<String>,
     project_id: Option<i64>,
     account_id: i64,
+    is_automated: Option<bool>,
+    lang: Option<String>,
+    gmail_labels: Option<String>,
+    /// 来自共享邮箱（其他用户 / 共享命名空间的文件夹）
+    from_shared_mailbox: bool,
 }
 
-/// 规范化主题（去除 Re: / Fwd: / 数字后缀等）
-fn normalize_subject(subject: &str) -> String {
+/// 回复/转发前缀（所有邮件）
+const LATIN_REPLY_PREFIXES: &[&str] = &["Re:", "RE:", "re:", "Fwd:", "FWD:", "Fw:", "FW:", "AW:", "WG:", "SV:"];
+
+/// 中文回复/转发前缀（Outlook 中文版使用 "答复"，繁体客户端使用 "回覆" / "轉寄"）
+const CHINESE_REPLY_PREFIXES: &[&str] = &[
+    "回复:", "回复：", "答复:", "答复：", "转发:", "转发：", "回覆:", "回覆：", "轉寄:", "轉寄：",
+    "轉發:", "轉發：",
+];
+
+/// 规范化主题（去除 Re: / Fwd: 前缀和设置中的工单编号模式）
+///
+/// 中文前缀只对中文或未识别语言（`lang` 为 None / und）的邮件检查。
+pub(crate) fn normalize_subject(subject: &str, lang: Option<&str>, config: &ClassifierConfig) -> String {
     let mut normalized = subject.to_string();
+    if !config.strip_patterns.is_empty() {
+        for pattern in &config.strip_patterns {
+            normalized = pattern.replace_all(&normalized, " ").into_owned();
+        }
+        normalized = normalized.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
 
     // 去除常见前缀
-    let prefixes = ["Re:", "RE:", "Fwd:", "FWD:", "Fw:", "回复:", "转发:"];
+    let chinese: &[&str] = match lang {
+        None | Some("zh") | Some(crate::mail::language::UNDETERMINED) => CHINESE_REPLY_PREFIXES,
+        Some(_) => &[],
+    };
+    let prefixes: Vec<&str> = LATIN_REPLY_PREFIXES.iter().chain(chinese).copied().collect();
     loop {
         let mut changed = false;
         for prefix in &prefixes {
@@ -256,3 +1013,265 @@ fn normalize_subject(subject: &str) -> String {
     // 限制长度（安全地截断 UTF-8 字符串）
     safe_truncate(&normalized, 100)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::fixtures::{
+        insert_account, insert_email, insert_project, NewEmail,
+    };
+    use crate::storage::database::test_pool;
+    use chrono::{Duration, Utc};
+
+    /// `days_ago` 天前来自同一发件人的邮件
+    async fn email_with_subject(
+        pool: &SqlitePool,
+        account_id: i64,
+        subject: &str,
+        days_ago: i64,
+        project_id: Option<i64>,
+    ) -> i64 {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails").fetch_one(pool).await.unwrap();
+        let message_id = format!("<{}@example.com>", count + 1);
+        let date = (Utc::now() - Duration::days(days_ago)).format("%Y-%m-%d %H:%M:%S").to_string();
+        let email = NewEmail {
+            account_id: Some(account_id),
+            message_id: &message_id,
+            subject: Some(subject),
+            sender: Some("Ann <ann@client-a.com>"),
+            date: Some(&date),
+            project_id,
+            ..Default::default()
+        };
+        insert_email(pool, email).await
+    }
+
+    fn config_with(window_days: i64, min_subject_len: usize) -> ClassifierConfig {
+        ClassifierConfig {
+            window_days,
+            min_subject_len,
+            ..ClassifierConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn config_is_loaded_from_settings_and_invalid_patterns_are_skipped() {
+        let (pool, _db_dir) = test_pool().await;
+        sqlx::query(
+            r#"
+            UPDATE sync_settings
+            SET classifier_window_days = 0, classifier_min_subject_len = 5,
+                classifier_strip_ticket_ids = '\[JIRA-\d+\]' || char(10) || '  ' || char(10) || '(unclosed'
+            WHERE id = 1
+            "#
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let config = ClassifierConfig::load(&pool).await;
+        assert_eq!(config.window_days, 1);
+        assert_eq!(config.min_subject_len, 5);
+        assert_eq!(config.strip_patterns.len(), 1);
+    }
+
+    #[test]
+    fn validate_patterns_reports_the_invalid_pattern() {
+        assert!(ClassifierConfig::validate_patterns("\\[JIRA-\\d+\\]\n\n#\\d+").is_ok());
+        let error = ClassifierConfig::validate_patterns("ok\n(unclosed").unwrap_err();
+        assert!(error.to_string().contains("(unclosed"), "{}", error);
+    }
+
+    #[test]
+    fn normalize_subject_applies_strip_patterns_and_prefixes() {
+        let config = ClassifierConfig {
+            strip_patterns: vec![Regex::new(r"\[JIRA-\d+\]").unwrap()],
+            ..ClassifierConfig::default()
+        };
+        assert_eq!(normalize_subject("Re: [JIRA-42] Login   fails", Some("en"), &config), "Login fails");
+        assert_eq!(normalize_subject("回复：预算", None, &config), "预算");
+        // 中文前缀只对中文或未识别语言的邮件去除
+        assert_eq!(normalize_subject("回复：预算", Some("en"), &config), "回复：预算");
+    }
+
+    /// 已有项目中有一封 `days_ago` 天前的邮件，用给定设置分类一封新邮件，返回 (已有项目, 分到的项目)
+    async fn classify_against_existing(
+        config: ClassifierConfig,
+        existing_subject: &str,
+        days_ago: i64,
+        subject: &str,
+    ) -> (i64, Option<i64>) {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let project_id = insert_project(&pool, "Existing").await;
+        email_with_subject(&pool, account_id, existing_subject, days_ago, Some(project_id)).await;
+
+        let email = email_with_subject(&pool, account_id, subject, 0, None).await;
+        let assigned = ProjectClassifier::new(pool, config).classify_email(email).await.unwrap();
+        (project_id, assigned)
+    }
+
+    #[tokio::test]
+    async fn subject_match_respects_the_window() {
+        let (existing, assigned) =
+            classify_against_existing(config_with(30, 3), "Quarterly budget review", 10, "Re: Quarterly budget review").await;
+        assert_eq!(assigned, Some(existing));
+
+        let (existing, assigned) =
+            classify_against_existing(config_with(5, 3), "Quarterly budget review", 10, "Re: Quarterly budget review").await;
+        assert!(assigned.is_some_and(|id| id != existing), "outside the window a new project is created");
+    }
+
+    #[tokio::test]
+    async fn short_subjects_skip_subject_matching() {
+        let (existing, assigned) = classify_against_existing(config_with(30, 3), "Hi there", 1, "Re: Hi").await;
+        assert!(assigned.is_some_and(|id| id != existing));
+
+        let (existing, assigned) = classify_against_existing(config_with(30, 2), "Hi there", 1, "Re: Hi").await;
+        assert_eq!(assigned, Some(existing));
+    }
+
+    #[tokio::test]
+    async fn classified_email_moves_its_attachments() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let project_id = insert_project(&pool, "Budget").await;
+        email_with_subject(&pool, account_id, "Quarterly budget review", 1, Some(project_id)).await;
+        let subject = "Re: Quarterly budget review";
+        let email = email_with_subject(&pool, account_id, subject, 0, None).await;
+        sqlx::query("INSERT INTO attachments (email_id, filename) VALUES (?, 'budget.xlsx')")
+            .bind(email)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let classifier = ProjectClassifier::new(pool.clone(), ClassifierConfig::default());
+        assert_eq!(classifier.classify_email(email).await.unwrap(), Some(project_id));
+
+        let attachment_project: Option<i64> = sqlx::query_scalar("SELECT project_id FROM attachments WHERE email_id = ?")
+            .bind(email)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(attachment_project, Some(project_id));
+        let attachment_count: i64 = sqlx::query_scalar("SELECT attachment_count FROM projects WHERE id = ?")
+            .bind(project_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(attachment_count, 1);
+    }
+
+    async fn project_count(pool: &SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM projects").fetch_one(pool).await.unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_classifiers_assign_an_email_once() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let first = ProjectClassifier::new(pool.clone(), ClassifierConfig::default());
+        let second = ProjectClassifier::new(pool.clone(), ClassifierConfig::default());
+
+        for round in 0..10 {
+            let subject = format!("Kickoff meeting {}", round);
+            let email = email_with_subject(&pool, account_id, &subject, 0, None).await;
+            let (a, b) = tokio::join!(first.classify_email(email), second.classify_email(email));
+            let (a, b) = (a.unwrap(), b.unwrap());
+            assert!(a.is_some());
+            assert_eq!(a, b, "both syncs report the winning project");
+
+            let stored: Option<i64> = sqlx::query_scalar("SELECT project_id FROM emails WHERE id = ?")
+                .bind(email)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(stored, a);
+        }
+        // 落败一方新建的项目被复用或清理，不留下空项目
+        assert_eq!(project_count(&pool).await, 10);
+        let empty: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM projects p WHERE NOT EXISTS (SELECT 1 FROM emails WHERE project_id = p.id)"
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(empty, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_creation_reuses_the_auto_project() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let first_email = email_with_subject(&pool, account_id, "Invoice", 0, None).await;
+        let second_email = email_with_subject(&pool, account_id, "Invoice", 0, None).await;
+        let first = ProjectClassifier::new(pool.clone(), ClassifierConfig::default());
+        let second = ProjectClassifier::new(pool.clone(), ClassifierConfig::default());
+        let first_info = first.get_email_info(first_email).await.unwrap();
+        let second_info = second.get_email_info(second_email).await.unwrap();
+
+        let (a, b) = tokio::join!(
+            first.create_project_for_email(&first_info, true),
+            second.create_project_for_email(&second_info, true)
+        );
+        let (a, b) = (a.unwrap().unwrap(), b.unwrap().unwrap());
+        assert_eq!(a.0, b.0);
+        assert!(a.1 != b.1, "exactly one side creates the project");
+        assert_eq!(project_count(&pool).await, 1);
+    }
+
+    #[tokio::test]
+    async fn auto_key_is_unique_per_account() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let other_account = insert_account(&pool, "other@example.com").await;
+        let insert = |name: &'static str, account: Option<i64>, key: Option<&'static str>| {
+            sqlx::query("INSERT INTO projects (name, status, auto_account_id, auto_key) VALUES (?, 'active', ?, ?)")
+                .bind(name)
+                .bind(account)
+                .bind(key)
+                .execute(&pool)
+        };
+
+        insert("Invoice", Some(account_id), Some("invoice")).await.unwrap();
+        assert!(insert("Invoice (2)", Some(account_id), Some("invoice")).await.is_err());
+        insert("Invoice (3)", Some(other_account), Some("invoice")).await.unwrap();
+        // 手动创建的项目没有去重键
+        insert("Manual", None, None).await.unwrap();
+        insert("Manual (2)", None, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn deleted_or_flagged_holder_releases_the_auto_key() {
+        for holder_state in ["status = 'deleted'", "needs_review = 1"] {
+            let (pool, _db_dir) = test_pool().await;
+            let account_id = insert_account(&pool, "me@example.com").await;
+            let classifier = ProjectClassifier::new(pool.clone(), ClassifierConfig::default());
+            let first_email = email_with_subject(&pool, account_id, "Invoice", 0, None).await;
+            let info = classifier.get_email_info(first_email).await.unwrap();
+            let (holder, created) = classifier.create_project_for_email(&info, true).await.unwrap().unwrap();
+            assert!(created);
+            sqlx::query(&format!("UPDATE projects SET {} WHERE id = ?", holder_state))
+                .bind(holder)
+                .execute(&pool)
+                .await
+                .unwrap();
+
+            // 达到新建上限时不复用不可用的占用者
+            assert_eq!(classifier.create_project_for_email(&info, false).await.unwrap(), None);
+
+            let (project, created) = classifier.create_project_for_email(&info, true).await.unwrap().unwrap();
+            assert!(created, "{}", holder_state);
+            assert_ne!(project, holder);
+            let holder_key: Option<String> = sqlx::query_scalar("SELECT auto_key FROM projects WHERE id = ?")
+                .bind(holder)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(holder_key, None, "{}", holder_state);
+
+            // 可用的占用者在达到上限时仍被复用
+            assert_eq!(classifier.create_project_for_email(&info, false).await.unwrap(), Some((project, false)));
+        }
+    }
+}
@@ -1 +1,42 @@
-pub fn hello() {}
+/// 项目生命周期规则
+///
+/// 终结状态（如 done）的项目超过 `sync_settings.auto_archive_days` 天没有更新后自动归档，
+/// 由每晚的后台任务运行。进行中的状态（waiting_on_client、blocked 等）即使长期没有新邮件也不归档。
+use crate::error::AppError;
+use crate::project::statuses;
+use sqlx::SqlitePool;
+
+/// 自动归档终结状态中闲置超过 `inactive_days` 天的项目，返回归档的项目数（`inactive_days` 为 0 时不处理）
+pub async fn auto_archive(pool: &SqlitePool, inactive_days: i64) -> Result<u64, AppError> {
+    if inactive_days <= 0 {
+        return Ok(0);
+    }
+
+    let mut tx = pool.begin().await?;
+    let archived = statuses::find(&mut *tx, "archived")
+        .await?
+        .ok_or_else(|| AppError::Validation("Project status archived is missing".to_string()))?;
+    let ids: Vec<i64> = sqlx::query_scalar(
+        r#"
+        SELECT p.id
+        FROM projects p
+        JOIN project_statuses s ON s.key = p.status
+        WHERE s.is_terminal = 1
+          AND p.status != 'archived'
+          AND julianday(p.updated_at) < julianday('now', ?)
+        "#
+    )
+    .bind(format!("-{} days", inactive_days))
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for &id in &ids {
+        statuses::change_status(&mut *tx, id, &archived).await?;
+    }
+    tx.commit().await?;
+
+    if !ids.is_empty() {
+        log::info!("Auto-archived {} finished projects idle for more than {} days", ids.len(), inactive_days);
+    }
+    Ok(ids.len() as u64)
+}
@@ -1 +1,151 @@
-pub fn hello() {}
+/// 项目合并
+///
+/// 把源项目的邮件、附件、手动添加的文件和里程碑移到目标项目，源项目移入回收站。
+/// 整个合并在一个事务中完成，并记录一条撤销日志，撤销时一次恢复所有源项目。
+use crate::error::AppError;
+use crate::project::undo::{EmailAssignment, MergedSource, UndoJournal, UndoOperation};
+use crate::repository::ProjectRepository;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 合并结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeSummary {
+    pub target_id: i64,
+    pub merged_projects: Vec<i64>,
+    pub moved_emails: usize,
+    /// 撤销日志 ID（传给 `undo_action`）
+    pub undo_id: i64,
+}
+
+/// 项目合并器
+pub struct ProjectMerger {
+    pool: SqlitePool,
+}
+
+impl ProjectMerger {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 把 `source_ids` 合并到 `target_id`
+    pub async fn merge(&self, target_id: i64, source_ids: &[i64]) -> Result<MergeSummary, AppError> {
+        let mut sources: Vec<i64> = Vec::new();
+        for id in source_ids {
+            if *id == target_id {
+                return Err(AppError::Validation("Cannot merge a project into itself".to_string()));
+            }
+            if !sources.contains(id) {
+                sources.push(*id);
+            }
+        }
+        if sources.is_empty() {
+            return Err(AppError::Validation("No projects to merge".to_string()));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let mut names = Vec::new();
+        for id in std::iter::once(&target_id).chain(&sources) {
+            let row: Option<(String, Option<String>)> = sqlx::query_as("SELECT name, status FROM projects WHERE id = ?")
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await?;
+            match row {
+                Some((name, status)) if status.as_deref() != Some("deleted") => names.push(name),
+                _ => return Err(AppError::ProjectNotFound { id: *id }),
+            }
+        }
+
+        let mut merged = Vec::new();
+        let mut assignments = Vec::new();
+        for source_id in &sources {
+            let (is_pinned, pin_order): (Option<bool>, Option<i64>) =
+                sqlx::query_as("SELECT is_pinned, pin_order FROM projects WHERE id = ?")
+                    .bind(source_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+            let milestone_ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM milestones WHERE project_id = ?")
+                .bind(source_id)
+                .fetch_all(&mut *tx)
+                .await?;
+            let manual_file_ids: Vec<i64> = sqlx::query_scalar(
+                "SELECT id FROM attachments WHERE project_id = ? AND email_id IS NULL"
+            )
+            .bind(source_id)
+            .fetch_all(&mut *tx)
+            .await?;
+            let email_ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM emails WHERE project_id = ?")
+                .bind(source_id)
+                .fetch_all(&mut *tx)
+                .await?;
+
+            sqlx::query("UPDATE emails SET project_id = ? WHERE project_id = ?")
+                .bind(target_id)
+                .bind(source_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE attachments SET project_id = ? WHERE project_id = ?")
+                .bind(target_id)
+                .bind(source_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE milestones SET project_id = ? WHERE project_id = ?")
+                .bind(target_id)
+                .bind(source_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(
+                r#"
+                UPDATE projects
+                SET status_before_delete = status,
+                    status = 'deleted',
+                    deleted_at = CURRENT_TIMESTAMP,
+                    is_pinned = 0,
+                    pin_order = NULL
+                WHERE id = ?
+                "#
+            )
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await?;
+
+            assignments.extend(email_ids.into_iter().map(|email_id| EmailAssignment {
+                email_id,
+                previous_project_id: Some(*source_id),
+                current_project_id: target_id,
+            }));
+            merged.push(MergedSource {
+                project_id: *source_id,
+                is_pinned: is_pinned.unwrap_or(false),
+                pin_order,
+                milestone_ids,
+                manual_file_ids,
+            });
+        }
+
+        let moved_emails = assignments.len();
+        let description = format!("Merge {} into \"{}\"", names[1..].join(", "), names[0]);
+        let inverse = UndoOperation::Unmerge {
+            target_id,
+            sources: merged,
+            assignments,
+        };
+        let undo_id = UndoJournal::record_on(&mut *tx, "merge_projects", &description, &inverse).await?;
+
+        tx.commit().await?;
+
+        let repo = ProjectRepository::new(self.pool.clone());
+        for id in std::iter::once(&target_id).chain(&sources) {
+            repo.recompute_stats(Some(*id)).await?;
+        }
+
+        log::info!("Merged projects {:?} into {} ({} emails)", sources, target_id, moved_emails);
+        Ok(MergeSummary {
+            target_id,
+            merged_projects: sources,
+            moved_emails,
+            undo_id,
+        })
+    }
+}
@@ -1,8 +1,22 @@
+use crate::mail::calendar::CalendarInvite;
+use crate::mail::receipts::EmailReceipt;
+use crate::utils::i18n::ActivityBucket;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
+pub mod appearance;
 pub mod classifier;
+pub mod classification_log;
 pub mod lifecycle;
 pub mod merger;
+pub mod naming;
+pub mod preferences;
+pub mod snapshot;
+pub mod statuses;
+pub mod splitter;
+pub mod summary;
+pub mod templates;
+pub mod undo;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Project {
@@ -10,18 +24,90 @@ pub struct Project {
     pub title: String, // DB column is 'name', but UI uses 'title'. Let's map it or use rename. UI 'ProjectData' has 'title'.
     pub description: Option<String>,
     pub status: String,
+    /// 状态的显示名称和颜色（来自状态词表）
+    #[serde(default)]
+    pub status_label: Option<String>,
+    #[serde(default)]
+    pub status_color: Option<String>,
     pub is_pinned: bool,
+    pub color: Option<String>,
+    pub icon: Option<String>,
     pub last_updated: String, // DB 'updated_at'
+    /// 本地化的相对时间（"2 天前" / "2 days ago"）
+    #[serde(default)]
+    pub last_updated_relative: Option<String>,
     pub stats: ProjectStats,
     pub tags: Option<Vec<String>>,
     pub last_activity: Option<LastActivity>,
+    /// 按用户时区显示的最近活动时间（"Today" / "Yesterday" / "Mon" / "3 weeks ago"）
+    #[serde(default)]
+    pub last_activity_display: Option<String>,
+    /// 最近活动所在的时间段（列表分组用）
+    #[serde(default)]
+    pub activity_bucket: Option<ActivityBucket>,
     pub participants: Option<Vec<String>>,
+    /// 截止日期（YYYY-MM-DD）
+    #[serde(default)]
+    pub due_date: Option<String>,
+    #[serde(default)]
+    pub due_note: Option<String>,
+    /// 超过规模上限，建议拆分
+    #[serde(default)]
+    pub needs_review: bool,
+    /// 视图偏好（仅 get_project 返回）
+    #[serde(default)]
+    pub preferences: Option<preferences::ProjectPreferences>,
+}
+
+/// 项目列表排序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectSort {
+    /// 置顶优先，其次按更新时间
+    #[default]
+    Default,
+    /// 已逾期和一周内到期的项目在最前（按截止日期），其余按默认顺序
+    Due,
+}
+
+/// 已逾期或即将到期的项目（提醒用）
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DueProject {
+    pub id: i64,
+    pub name: String,
+    pub due_date: String,
+    pub due_note: Option<String>,
+}
+
+/// 待检查的项目（超过规模上限）
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProjectReview {
+    pub id: i64,
+    pub name: String,
+    /// 超出的上限（"212 emails in the last 7 days (limit 150)"）
+    pub review_reason: Option<String>,
+    pub review_flagged_at: Option<String>,
+    pub email_count: i64,
+}
+
+/// 回收站中的项目
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DeletedProject {
+    pub id: i64,
+    pub name: String,
+    pub email_count: i64,
+    pub deleted_at: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LastActivity {
     pub sender: String,
     pub date: String,
+    #[serde(default)]
+    pub relative_date: Option<String>,
+    /// 仅附件邮件的摘要（"Sent contract.pdf (2.1 MB)"），正文有内容时为空
+    #[serde(default)]
+    pub summary: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,11 +117,64 @@ pub struct ProjectStats {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "lowercase")] // 'milestone' | 'email' | 'thread'
+#[serde(tag = "type", rename_all = "lowercase")] // 'milestone' | 'email' | 'thread' | 'automated'
 pub enum TimelineEvent {
     Milestone(MilestoneEvent),
     Email(EmailEvent),
     Thread(ThreadEvent),
+    Automated(AutomatedGroupEvent),
+}
+
+impl TimelineEvent {
+    /// 事件 ID（`{类型}:{ID}`，不同类型的事件 ID 不会相同）
+    pub fn id(&self) -> &str {
+        match self {
+            TimelineEvent::Milestone(m) => &m.id,
+            TimelineEvent::Email(e) => &e.id,
+            TimelineEvent::Thread(t) => &t.id,
+            TimelineEvent::Automated(g) => &g.id,
+        }
+    }
+
+    pub fn date(&self) -> &str {
+        match self {
+            TimelineEvent::Milestone(m) => &m.date,
+            TimelineEvent::Email(e) => &e.date,
+            TimelineEvent::Thread(t) => &t.date,
+            TimelineEvent::Automated(g) => &g.date,
+        }
+    }
+
+    /// 同一时间的事件的排列顺序（小的在前）
+    pub fn type_priority(&self) -> u8 {
+        match self {
+            TimelineEvent::Milestone(_) => 0,
+            TimelineEvent::Thread(_) => 1,
+            TimelineEvent::Email(_) => 2,
+            TimelineEvent::Automated(_) => 3,
+        }
+    }
+
+    /// 同一时间、同一类型的事件的排列键：数字 ID 按数值比较（`email:9` 在 `email:10` 之前），
+    /// 线程事件的 ID 是哈希，按字符串比较
+    pub fn id_sort_key(&self) -> (Option<i64>, String) {
+        let numeric = match self {
+            TimelineEvent::Thread(_) => None,
+            _ => self.id().rsplit_once(':').and_then(|(_, id)| id.parse().ok()),
+        };
+        (numeric, self.id().to_string())
+    }
+}
+
+/// 时间线事件 ID：`{类型}:{数字 ID}`（如 `email:42`）
+pub fn timeline_id(kind: &str, id: i64) -> String {
+    format!("{}:{}", kind, id)
+}
+
+/// 线程事件 ID：线程 ID 是任意字符串（Message-ID 或 Gmail 线程号），取哈希，如 `thread:3f2a…`
+pub fn thread_event_id(thread_id: &str) -> String {
+    let hash = format!("{:x}", Sha256::digest(thread_id.as_bytes()));
+    format!("thread:{}", &hash[..16])
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -61,14 +200,108 @@ pub struct EmailEvent {
     pub date: String,
     pub sender: String,
     pub content: String,
+    /// 保存时计算的一行预览（折叠显示用）
+    #[serde(default)]
+    pub preview: Option<String>,
     pub subject: String,
     pub attachments: Option<Vec<Attachment>>,
+    /// 被折叠的重复邮件数量（其他账户收到的同一封邮件）
+    pub duplicate_count: i64,
+    /// 分类策略（thread / subject / rule / manual / new / duplicate），用于界面徽标
+    pub classified_by: Option<String>,
+    /// 仅附件邮件的摘要，正文有实质内容时为空
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// 这封邮件收到的已读回执和投递状态通知
+    #[serde(default)]
+    pub receipts: Vec<EmailReceipt>,
+    /// 线程中的回复层级（0 为顶层），用于缩进
+    #[serde(default)]
+    pub depth: usize,
+    /// 所回复的邮件 ID（在同一线程中时）
+    #[serde(default)]
+    pub parent_email_id: Option<i64>,
+    /// 回复的邮件不在本项目中
+    #[serde(default)]
+    pub parent_missing: bool,
+    /// 邮件中的会议邀请和自己的回复状态
+    #[serde(default)]
+    pub invite: Option<CalendarInvite>,
+    /// 发件人是 VIP（含合并联系人的别名）
+    #[serde(default)]
+    pub is_vip: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ThreadEvent {
     pub id: String,
+    /// 原始线程 ID（`get_thread_emails` 使用）
+    #[serde(default)]
+    pub thread_id: Option<String>,
     pub date: String, // Latest date in thread
-    pub children: Vec<TimelineEvent>, // Usually EmailEvents
+    pub children: Vec<TimelineEvent>, // Usually EmailEvents, oldest first
+    /// 线程中的邮件数（含折叠的自动通知）
+    #[serde(default)]
+    pub message_count: Option<usize>,
+    /// 参与者显示名（按首次出现的顺序去重，最多 4 个，自己显示为"我"）
+    #[serde(default)]
+    pub participants: Option<Vec<String>>,
+    /// 超出显示上限的参与者数
+    #[serde(default)]
+    pub participants_overflow: Option<usize>,
+    #[serde(default)]
+    pub has_attachments: Option<bool>,
+    /// 首封邮件的主题（去除 Re: / Fwd: 前缀）
+    #[serde(default)]
+    pub subject: Option<String>,
+}
+
+/// 线程中连续的自动通知邮件折叠后的事件（"4 automated notifications"）
+///
+/// 展开时调用 `get_thread_emails(thread_id, include_automated: true)`。
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AutomatedGroupEvent {
+    pub id: String,
+    pub thread_id: String,
+    /// 最新一封的日期
+    pub date: String,
+    pub count: usize,
+    pub label: String,
+    pub senders: Vec<String>,
+    pub email_ids: Vec<i64>,
+}
+
+/// 跨项目查看的完整线程
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThreadView {
+    pub thread_id: String,
+    /// 按时间从早到晚排列
+    pub emails: Vec<ThreadEmail>,
+    /// 线程涉及的项目（邮件数多的在前）
+    pub projects_involved: Vec<ThreadProject>,
 }
 
+/// 线程中的单封邮件
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThreadEmail {
+    pub id: i64,
+    pub message_id: String,
+    pub date: String,
+    pub sender: String,
+    pub subject: String,
+    pub project_id: Option<i64>,
+    pub project_name: Option<String>,
+    #[serde(default)]
+    pub is_automated: bool,
+    pub is_read: bool,
+    pub is_starred: bool,
+    pub attachments: Vec<Attachment>,
+}
+
+/// 线程涉及的项目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadProject {
+    pub id: i64,
+    pub name: String,
+    pub email_count: i64,
+}
@@ -0,0 +1,337 @@
+/// 自动创建项目时的命名
+///
+/// 从邮件主题中去掉回复/转发前缀、邮件列表标签（`[dev]`）、工单编号和 emoji；
+/// 主题过于笼统（"hi"、"invoice" 等，可在设置中配置）时，用发件人组织加主题命名，
+/// 如 "client-a.com – Invoice"。
+use crate::error::AppError;
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// 项目名称最大长度（字节）
+const MAX_NAME_BYTES: usize = 100;
+
+/// 个人邮箱域名，不代表组织，改用发件人姓名
+const PERSONAL_DOMAINS: &[&str] = &[
+    "gmail.com", "googlemail.com", "outlook.com", "hotmail.com", "live.com", "yahoo.com",
+    "icloud.com", "me.com", "qq.com", "163.com", "126.com", "foxmail.com", "proton.me",
+    "protonmail.com",
+];
+
+/// 回复/转发前缀
+const REPLY_PREFIXES: &[&str] = &["re", "fw", "fwd", "aw", "wg", "sv", "vs", "tr", "回复", "答复", "转发"];
+
+/// 工单前缀关键词（"Ticket #123:"、"Case 4567 -"）
+const TICKET_WORDS: &[&str] = &["ticket", "case", "issue", "bug", "request", "req", "incident"];
+
+/// 读取笼统主题列表
+pub async fn load_generic_subjects(pool: &SqlitePool) -> Vec<String> {
+    let value: Option<String> = sqlx::query_scalar("SELECT generic_subjects FROM sync_settings WHERE id = 1")
+        .fetch_optional(pool)
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to load generic subjects: {}", e);
+            None
+        });
+
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(|subject| subject.trim().to_lowercase())
+        .filter(|subject| !subject.is_empty())
+        .collect()
+}
+
+/// 根据邮件主题和发件人生成项目名称（不保证唯一）
+///
+/// 自动通知邮件的主题不代表项目内容，始终按笼统主题处理。
+pub fn project_name(subject: Option<&str>, sender: Option<&str>, generic_subjects: &[String], automated: bool) -> String {
+    let cleaned = subject.map(clean_subject).unwrap_or_default();
+    let organization = sender.and_then(sender_organization);
+
+    let name = match organization {
+        Some(organization) if cleaned.is_empty() => format!("Project from {}", organization),
+        Some(organization) if automated || is_generic(&cleaned, generic_subjects) => {
+            format!("{} – {}", organization, capitalize(&cleaned))
+        }
+        _ if cleaned.is_empty() => format!("Project from {}", sender.unwrap_or("Unknown")),
+        _ => cleaned,
+    };
+
+    truncate(&name)
+}
+
+/// 与现有项目重名时追加序号（"Invoice (2)"）
+pub async fn unique_project_name(conn: &mut SqliteConnection, base_name: &str) -> Result<String, AppError> {
+    let mut candidate = base_name.to_string();
+    let mut counter = 1;
+    loop {
+        let exists: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM projects WHERE name = ? AND status != 'deleted' LIMIT 1"
+        )
+        .bind(&candidate)
+        .fetch_optional(&mut *conn)
+        .await?;
+        if exists.is_none() {
+            return Ok(candidate);
+        }
+        counter += 1;
+        candidate = format!("{} ({})", base_name, counter);
+    }
+}
+
+/// 自动创建项目的去重键：忽略大小写和多余空白
+pub fn name_key(base_name: &str) -> String {
+    collapse_whitespace(base_name).to_lowercase()
+}
+
+/// 清理主题：去掉回复前缀、列表标签、工单编号和 emoji
+pub fn clean_subject(subject: &str) -> String {
+    let without_emoji: String = subject.chars().filter(|c| !is_emoji(*c)).collect();
+    let mut text = collapse_whitespace(&without_emoji);
+
+    loop {
+        let before = text.len();
+        text = strip_reply_prefix(&text);
+        text = strip_leading_tag(&text);
+        text = strip_ticket_prefix(&text);
+        text = text.trim_start_matches(|c: char| matches!(c, '-' | ':' | '|' | '–' | '—' | '·') || c.is_whitespace()).to_string();
+        if text.len() == before {
+            break;
+        }
+    }
+
+    text.trim_end_matches(|c: char| matches!(c, '-' | ':' | '|' | '–' | '—') || c.is_whitespace())
+        .to_string()
+}
+
+/// 主题是否过于笼统（忽略大小写和结尾标点）
+fn is_generic(cleaned: &str, generic_subjects: &[String]) -> bool {
+    let key = cleaned
+        .trim_end_matches(|c: char| c.is_ascii_punctuation() || matches!(c, '？' | '！' | '。'))
+        .trim()
+        .to_lowercase();
+    key.is_empty() || generic_subjects.iter().any(|generic| *generic == key)
+}
+
+/// 发件人的组织：邮箱域名（去掉 www./mail. 等），个人邮箱使用发件人姓名
+fn sender_organization(sender: &str) -> Option<String> {
+    let (display_name, address) = match sender.rsplit_once('<') {
+        Some((name, rest)) => (name.trim().trim_matches('"').trim(), rest.trim_end_matches('>').trim()),
+        None => ("", sender.trim()),
+    };
+    let (local, domain) = address.rsplit_once('@')?;
+    let mut domain = domain.trim().trim_end_matches('.').to_lowercase();
+    if domain.is_empty() {
+        return None;
+    }
+    for prefix in ["www.", "mail.", "email.", "mx.", "smtp."] {
+        if let Some(stripped) = domain.strip_prefix(prefix) {
+            if stripped.contains('.') {
+                domain = stripped.to_string();
+            }
+        }
+    }
+
+    if PERSONAL_DOMAINS.contains(&domain.as_str()) {
+        let name = if display_name.is_empty() { local } else { display_name };
+        return Some(name.to_string());
+    }
+    Some(domain)
+}
+
+fn strip_reply_prefix(text: &str) -> String {
+    let Some((head, rest)) = text.split_once([':', '：']) else {
+        return text.to_string();
+    };
+    // 允许 "Re[2]:"、"RE(3):" 形式的计数
+    let word = head
+        .trim()
+        .trim_end_matches(|c: char| c.is_ascii_digit() || matches!(c, '[' | ']' | '(' | ')'))
+        .to_lowercase();
+    if REPLY_PREFIXES.contains(&word.as_str()) {
+        rest.trim_start().to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// 去掉开头的 `[dev]`、`(#123)`、`【通知】` 一类标签
+fn strip_leading_tag(text: &str) -> String {
+    for (open, close) in [('[', ']'), ('(', ')'), ('【', '】')] {
+        if let Some(rest) = text.strip_prefix(open) {
+            if let Some((_, after)) = rest.split_once(close) {
+                return after.trim_start().to_string();
+            }
+        }
+    }
+    text.to_string()
+}
+
+/// 去掉开头的工单编号：`#1234`、`ABC-123:`、`Ticket #123 -`
+fn strip_ticket_prefix(text: &str) -> String {
+    let mut words = text.splitn(2, char::is_whitespace);
+    let first = words.next().unwrap_or_default();
+    let rest = words.next().unwrap_or_default().trim_start();
+    let token = first.trim_end_matches([':', ',']);
+
+    if TICKET_WORDS.contains(&token.to_lowercase().as_str()) {
+        let mut parts = rest.splitn(2, char::is_whitespace);
+        let number = parts.next().unwrap_or_default().trim_end_matches([':', ',']);
+        if is_ticket_number(number) {
+            return parts.next().unwrap_or_default().to_string();
+        }
+        return text.to_string();
+    }
+
+    if is_ticket_number(token) || is_issue_key(token) {
+        return rest.to_string();
+    }
+    text.to_string()
+}
+
+/// `#1234` 或 `1234`
+fn is_ticket_number(token: &str) -> bool {
+    let digits = token.strip_prefix('#').unwrap_or(token);
+    !digits.is_empty() && digits.len() <= 10 && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+/// `ABC-123` 形式的工单键
+fn is_issue_key(token: &str) -> bool {
+    let Some((project, number)) = token.split_once('-') else {
+        return false;
+    };
+    project.len() >= 2
+        && project.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        && project.starts_with(|c: char| c.is_ascii_uppercase())
+        && is_ticket_number(number)
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF   // 表情、符号、国旗
+            | 0x2600..=0x27BF   // 杂项符号、装饰符号
+            | 0x2B00..=0x2BFF   // 箭头、星形
+            | 0xFE00..=0xFE0F   // 变体选择符
+            | 0x200D            // 零宽连接符
+            | 0x20E3            // 组合键帽
+            | 0xE0020..=0xE007F // 标签字符
+    )
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// 按字符边界截断到最大长度
+fn truncate(name: &str) -> String {
+    if name.len() <= MAX_NAME_BYTES {
+        return name.to_string();
+    }
+    let mut end = MAX_NAME_BYTES;
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    name[..end].trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::test_pool;
+
+    fn generic() -> Vec<String> {
+        ["hi", "invoice", "meeting"].iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn clean_subject_strips_prefixes_tags_and_tickets() {
+        let cases = [
+            ("Re: Fwd: Website redesign", "Website redesign"),
+            ("RE[2]: 回复：预算审批", "预算审批"),
+            ("[dev] Re: Release plan", "Release plan"),
+            ("【通知】 年会安排", "年会安排"),
+            ("Ticket #1234: Login fails", "Login fails"),
+            ("Case 4567 - Refund", "Refund"),
+            ("ABC-123: Crash on start", "Crash on start"),
+            ("#88 Migration", "Migration"),
+            ("🚀 Launch   day 🎉", "Launch day"),
+            ("Re: Status -", "Status"),
+            ("Case study review", "Case study review"),
+        ];
+        for (subject, expected) in cases {
+            assert_eq!(clean_subject(subject), expected, "{}", subject);
+        }
+    }
+
+    #[test]
+    fn specific_subject_is_used_as_is() {
+        let name = project_name(Some("Re: Q3 roadmap"), Some("Ann <ann@client-a.com>"), &generic(), false);
+        assert_eq!(name, "Q3 roadmap");
+    }
+
+    #[test]
+    fn generic_subject_is_prefixed_with_sender_organization() {
+        let sender = Some("Billing <billing@mail.client-a.com>");
+        assert_eq!(project_name(Some("Fwd: invoice!"), sender, &generic(), false), "client-a.com – Invoice!");
+        assert_eq!(project_name(Some(""), sender, &generic(), false), "Project from client-a.com");
+        // 自动通知邮件的主题始终按笼统主题处理
+        assert_eq!(project_name(Some("Build passed"), sender, &generic(), true), "client-a.com – Build passed");
+    }
+
+    #[test]
+    fn personal_domains_use_the_sender_name() {
+        let name = project_name(Some("Hi"), Some("\"Li Wei\" <liwei@qq.com>"), &generic(), false);
+        assert_eq!(name, "Li Wei – Hi");
+        let name = project_name(Some("Hi"), Some("liwei@gmail.com"), &generic(), false);
+        assert_eq!(name, "liwei – Hi");
+    }
+
+    #[test]
+    fn names_without_organization_fall_back_to_sender() {
+        assert_eq!(project_name(None, Some("not an address"), &generic(), false), "Project from not an address");
+        assert_eq!(project_name(None, None, &generic(), false), "Project from Unknown");
+        assert_eq!(project_name(Some("Hi"), None, &generic(), false), "Hi");
+    }
+
+    #[test]
+    fn long_names_are_truncated_on_char_boundaries() {
+        let subject = "项目".repeat(60);
+        let name = project_name(Some(&subject), None, &[], false);
+        assert!(name.len() <= MAX_NAME_BYTES);
+        assert!(subject.starts_with(&name));
+    }
+
+    #[tokio::test]
+    async fn unique_name_appends_counter_and_ignores_deleted_projects() {
+        let (pool, _db_dir) = test_pool().await;
+        for (name, status) in [("Invoice", "active"), ("Invoice (2)", "active"), ("Invoice (3)", "deleted")] {
+            sqlx::query("INSERT INTO projects (name, status) VALUES (?, ?)")
+                .bind(name)
+                .bind(status)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let mut conn = pool.acquire().await.unwrap();
+        assert_eq!(unique_project_name(&mut conn, "Invoice").await.unwrap(), "Invoice (3)");
+        assert_eq!(unique_project_name(&mut conn, "Roadmap").await.unwrap(), "Roadmap");
+    }
+
+    #[tokio::test]
+    async fn generic_subjects_are_loaded_from_settings() {
+        let (pool, _db_dir) = test_pool().await;
+        sqlx::query("UPDATE sync_settings SET generic_subjects = ' Hi , INVOICE,, ' WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(load_generic_subjects(&pool).await, vec!["hi".to_string(), "invoice".to_string()]);
+    }
+}
@@ -0,0 +1,45 @@
+/// 项目视图偏好（默认标签页、排序等）
+///
+/// 未识别的字段原样保存在 `extra` 中，旧版本写入时不会丢失新版本前端添加的设置。
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+
+/// 允许的默认标签页（与前端 Tabs 的 value 对应）
+pub const ALLOWED_TABS: &[&str] = &["timeline", "artifacts"];
+
+/// 允许的时间线排序
+pub const ALLOWED_SORT_ORDERS: &[&str] = &["newest_first", "oldest_first"];
+
+/// 项目视图偏好
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectPreferences {
+    #[serde(default)]
+    pub default_tab: Option<String>,
+    #[serde(default)]
+    pub sort_order: Option<String>,
+    #[serde(default)]
+    pub collapsed_threads: bool,
+    /// 前端自定义数据，原样保存
+    #[serde(default)]
+    pub custom: serde_json::Value,
+    /// 未识别的字段
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ProjectPreferences {
+    /// 校验标签页和排序取值
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(tab) = &self.default_tab {
+            if !ALLOWED_TABS.contains(&tab.as_str()) {
+                return Err(AppError::Validation(format!("Invalid default tab: {}", tab)));
+            }
+        }
+        if let Some(order) = &self.sort_order {
+            if !ALLOWED_SORT_ORDERS.contains(&order.as_str()) {
+                return Err(AppError::Validation(format!("Invalid sort order: {}", order)));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,493 @@
+/// 项目组织快照
+///
+/// 保存邮件与项目的对应关系、项目元数据和里程碑（不含邮件正文），
+/// 用于在大规模重新分类或合并之前留一个撤销点。
+/// 邮件通过 (account_id, message_id) 定位，重置账户后重新同步的邮件也能匹配。
+/// 项目 ID 在删除后会被复用，恢复时按创建时间加 ID 或名称匹配项目，匹配不到时新建项目并改写归属。
+use crate::error::AppError;
+use crate::repository::ProjectRepository;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+
+/// 最多保留的快照数量，超出时删除最旧的
+const MAX_SNAPSHOTS: i64 = 20;
+
+/// 快照摘要
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SnapshotInfo {
+    pub id: i64,
+    pub label: String,
+    pub project_count: i64,
+    pub email_count: i64,
+    pub created_at: String,
+}
+
+/// 快照恢复结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreSummary {
+    pub snapshot_id: i64,
+    pub restored_assignments: usize,
+    pub created_projects: usize,
+    pub restored_milestones: usize,
+    /// 快照中存在但已被删除的邮件（message_id）
+    pub missing_emails: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+struct SnapshotProject {
+    id: i64,
+    name: String,
+    description: Option<String>,
+    status: Option<String>,
+    color: Option<String>,
+    icon: Option<String>,
+    is_pinned: Option<bool>,
+    pin_order: Option<i64>,
+    tags: Option<String>,
+    /// 旧版本快照中没有
+    #[serde(default)]
+    created_at: Option<String>,
+}
+
+/// 邮件归属：(account_id, message_id, project_id)
+type Assignment = (Option<i64>, String, i64);
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+struct SnapshotMilestone {
+    project_id: i64,
+    account_id: Option<i64>,
+    message_id: Option<String>,
+    #[sqlx(rename = "type")]
+    kind: Option<String>,
+    title: Option<String>,
+    date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotData {
+    projects: Vec<SnapshotProject>,
+    assignments: Vec<Assignment>,
+    milestones: Vec<SnapshotMilestone>,
+}
+
+/// 组织快照
+pub struct OrganizationSnapshots {
+    pool: SqlitePool,
+}
+
+impl OrganizationSnapshots {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 创建快照
+    pub async fn create(&self, label: &str) -> Result<SnapshotInfo, AppError> {
+        let label = label.trim();
+        if label.is_empty() {
+            return Err(AppError::Validation("Snapshot label cannot be empty".to_string()));
+        }
+
+        let projects = sqlx::query_as::<_, SnapshotProject>(
+            r#"
+            SELECT id, name, description, status, color, icon, is_pinned, pin_order, tags, created_at
+            FROM projects
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let assignments: Vec<Assignment> = sqlx::query_as(
+            "SELECT account_id, message_id, project_id FROM emails WHERE project_id IS NOT NULL"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let milestones = sqlx::query_as::<_, SnapshotMilestone>(
+            r#"
+            SELECT m.project_id, e.account_id, e.message_id, m.type, m.title, m.date
+            FROM milestones m
+            LEFT JOIN emails e ON e.id = m.email_id
+            WHERE m.project_id IS NOT NULL
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let project_count = projects.len() as i64;
+        let email_count = assignments.len() as i64;
+        let data = serde_json::to_string(&SnapshotData {
+            projects,
+            assignments,
+            milestones,
+        })?;
+
+        let id = sqlx::query(
+            r#"
+            INSERT INTO organization_snapshots (label, project_count, email_count, data)
+            VALUES (?, ?, ?, ?)
+            "#
+        )
+        .bind(label)
+        .bind(project_count)
+        .bind(email_count)
+        .bind(&data)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        // 删除超出数量上限的旧快照
+        sqlx::query(
+            r#"
+            DELETE FROM organization_snapshots
+            WHERE id NOT IN (SELECT id FROM organization_snapshots ORDER BY id DESC LIMIT ?)
+            "#
+        )
+        .bind(MAX_SNAPSHOTS)
+        .execute(&self.pool)
+        .await?;
+
+        log::info!(
+            "Created organization snapshot {} '{}': {} projects, {} assignments ({} bytes)",
+            id, label, project_count, email_count, data.len()
+        );
+
+        self.get(id).await
+    }
+
+    /// 获取快照列表（最新的在前）
+    pub async fn list(&self) -> Result<Vec<SnapshotInfo>, AppError> {
+        let snapshots = sqlx::query_as::<_, SnapshotInfo>(
+            r#"
+            SELECT id, label, project_count, email_count, COALESCE(created_at, '') AS created_at
+            FROM organization_snapshots
+            ORDER BY id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(snapshots)
+    }
+
+    async fn get(&self, id: i64) -> Result<SnapshotInfo, AppError> {
+        sqlx::query_as::<_, SnapshotInfo>(
+            r#"
+            SELECT id, label, project_count, email_count, COALESCE(created_at, '') AS created_at
+            FROM organization_snapshots
+            WHERE id = ?
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::Validation(format!("Snapshot {} not found", id)))
+    }
+
+    /// 恢复快照
+    ///
+    /// 重新应用邮件归属和项目元数据，缺失的项目会重新创建；快照之后新增的邮件保持不变。
+    /// 快照中的项目 ID 可能已被之后新建的项目复用，只有创建时间相同的项目才视为同一个项目。
+    pub async fn restore(&self, id: i64) -> Result<RestoreSummary, AppError> {
+        let data: Option<(String,)> = sqlx::query_as(
+            "SELECT data FROM organization_snapshots WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let data: SnapshotData = match data {
+            Some((data,)) => serde_json::from_str(&data)?,
+            None => return Err(AppError::Validation(format!("Snapshot {} not found", id))),
+        };
+
+        let mut summary = RestoreSummary {
+            snapshot_id: id,
+            restored_assignments: 0,
+            created_projects: 0,
+            restored_milestones: 0,
+            missing_emails: Vec::new(),
+        };
+
+        let mut tx = self.pool.begin().await?;
+
+        // 1. 项目元数据（匹配不到的项目新建，记录 快照 ID → 当前 ID）
+        let mut project_ids: HashMap<i64, i64> = HashMap::new();
+        for project in &data.projects {
+            let existing = match_project(&mut tx, project).await?;
+            let project_id = match existing {
+                Some(project_id) => {
+                    sqlx::query(
+                        r#"
+                        UPDATE projects
+                        SET name = ?, description = ?, status = ?, color = ?, icon = ?,
+                            is_pinned = ?, pin_order = ?, tags = ?
+                        WHERE id = ?
+                        "#
+                    )
+                    .bind(&project.name)
+                    .bind(&project.description)
+                    .bind(&project.status)
+                    .bind(&project.color)
+                    .bind(&project.icon)
+                    .bind(project.is_pinned.unwrap_or(false))
+                    .bind(project.pin_order)
+                    .bind(&project.tags)
+                    .bind(project_id)
+                    .execute(&mut *tx)
+                    .await?;
+                    project_id
+                }
+                None => {
+                    // 保留快照中的创建时间，再次恢复同一快照时能匹配到这个项目
+                    let project_id = sqlx::query(
+                        r#"
+                        INSERT INTO projects (name, description, status, color, icon, is_pinned, pin_order, tags, created_at)
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP))
+                        "#
+                    )
+                    .bind(&project.name)
+                    .bind(&project.description)
+                    .bind(project.status.as_deref().unwrap_or("active"))
+                    .bind(&project.color)
+                    .bind(&project.icon)
+                    .bind(project.is_pinned.unwrap_or(false))
+                    .bind(project.pin_order)
+                    .bind(&project.tags)
+                    .bind(&project.created_at)
+                    .execute(&mut *tx)
+                    .await?
+                    .last_insert_rowid();
+                    summary.created_projects += 1;
+                    project_id
+                }
+            };
+            project_ids.insert(project.id, project_id);
+        }
+
+        // 2. 邮件归属
+        for (account_id, message_id, project_id) in &data.assignments {
+            let project_id = project_ids.get(project_id).copied().unwrap_or(*project_id);
+            let updated = sqlx::query(
+                "UPDATE emails SET project_id = ? WHERE account_id IS ? AND message_id = ?"
+            )
+            .bind(project_id)
+            .bind(account_id)
+            .bind(message_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+            if updated == 0 {
+                summary.missing_emails.push(message_id.clone());
+            } else {
+                sqlx::query(
+                    r#"
+                    UPDATE attachments SET project_id = ?
+                    WHERE email_id IN (SELECT id FROM emails WHERE account_id IS ? AND message_id = ?)
+                    "#
+                )
+                .bind(project_id)
+                .bind(account_id)
+                .bind(message_id)
+                .execute(&mut *tx)
+                .await?;
+                summary.restored_assignments += 1;
+            }
+        }
+
+        // 3. 里程碑（替换快照中项目的里程碑）
+        let mut email_ids: HashMap<(Option<i64>, String), Option<i64>> = HashMap::new();
+        for project_id in project_ids.values() {
+            sqlx::query("DELETE FROM milestones WHERE project_id = ?")
+                .bind(project_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        for milestone in &data.milestones {
+            let email_id = match &milestone.message_id {
+                Some(message_id) => {
+                    let key = (milestone.account_id, message_id.clone());
+                    match email_ids.get(&key) {
+                        Some(id) => *id,
+                        None => {
+                            let id: Option<i64> = sqlx::query_scalar(
+                                "SELECT id FROM emails WHERE account_id IS ? AND message_id = ?"
+                            )
+                            .bind(milestone.account_id)
+                            .bind(message_id)
+                            .fetch_optional(&mut *tx)
+                            .await?;
+                            email_ids.insert(key, id);
+                            id
+                        }
+                    }
+                }
+                None => None,
+            };
+
+            sqlx::query(
+                "INSERT INTO milestones (project_id, email_id, type, title, date) VALUES (?, ?, ?, ?, ?)"
+            )
+            .bind(project_ids.get(&milestone.project_id).copied().unwrap_or(milestone.project_id))
+            .bind(email_id)
+            .bind(&milestone.kind)
+            .bind(&milestone.title)
+            .bind(&milestone.date)
+            .execute(&mut *tx)
+            .await?;
+            summary.restored_milestones += 1;
+        }
+
+        tx.commit().await?;
+
+        ProjectRepository::new(self.pool.clone())
+            .recompute_stats(None)
+            .await?;
+
+        log::info!(
+            "Restored snapshot {}: {} assignments, {} projects created, {} emails missing",
+            id, summary.restored_assignments, summary.created_projects, summary.missing_emails.len()
+        );
+        Ok(summary)
+    }
+}
+
+/// 查找与快照项目对应的当前项目
+///
+/// 创建时间相同且 ID 或名称相同（改名后按 ID，重新创建后按名称）；旧版本快照没有创建时间，
+/// 要求 ID 和名称都相同。
+async fn match_project(conn: &mut SqliteConnection, project: &SnapshotProject) -> Result<Option<i64>, AppError> {
+    let project_id = match &project.created_at {
+        Some(created_at) => {
+            sqlx::query_scalar(
+                r#"
+                SELECT id FROM projects
+                WHERE created_at = ? AND (id = ? OR name = ?)
+                ORDER BY id = ? DESC, id
+                LIMIT 1
+                "#
+            )
+            .bind(created_at)
+            .bind(project.id)
+            .bind(&project.name)
+            .bind(project.id)
+            .fetch_optional(&mut *conn)
+            .await?
+        }
+        None => {
+            sqlx::query_scalar("SELECT id FROM projects WHERE id = ? AND name = ?")
+                .bind(project.id)
+                .bind(&project.name)
+                .fetch_optional(&mut *conn)
+                .await?
+        }
+    };
+
+    Ok(project_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::fixtures::{self, insert_email, NewEmail};
+    use crate::storage::database::test_pool;
+
+    /// 快照按创建时间匹配项目，测试中固定创建时间
+    async fn insert_project(pool: &SqlitePool, name: &str, created_at: &str) -> i64 {
+        let project_id = fixtures::insert_project(pool, name).await;
+        sqlx::query("UPDATE projects SET created_at = ? WHERE id = ?")
+            .bind(created_at)
+            .bind(project_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        project_id
+    }
+
+    async fn project_of(pool: &SqlitePool, email_id: i64) -> Option<i64> {
+        sqlx::query_scalar("SELECT project_id FROM emails WHERE id = ?")
+            .bind(email_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn project_name(pool: &SqlitePool, project_id: i64) -> String {
+        sqlx::query_scalar("SELECT name FROM projects WHERE id = ?")
+            .bind(project_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn restore_does_not_overwrite_project_that_reused_the_id() {
+        let (pool, _db_dir) = test_pool().await;
+        let original = insert_project(&pool, "Original", "2026-01-01 09:00:00").await;
+        let email = NewEmail {
+            message_id: "<a@example.com>",
+            project_id: Some(original),
+            ..Default::default()
+        };
+        let email = insert_email(&pool, email).await;
+        sqlx::query("INSERT INTO milestones (project_id, email_id, type, title) VALUES (?, ?, 'note', 'Kickoff')")
+            .bind(original)
+            .bind(email)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let snapshots = OrganizationSnapshots::new(pool.clone());
+        let snapshot = snapshots.create("before cleanup").await.unwrap();
+
+        // 项目被永久删除后，新项目复用了同一个 ID
+        sqlx::query("DELETE FROM milestones").execute(&pool).await.unwrap();
+        sqlx::query("UPDATE emails SET project_id = NULL").execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM projects").execute(&pool).await.unwrap();
+        let newcomer = insert_project(&pool, "Newcomer", "2026-03-01 09:00:00").await;
+        assert_eq!(newcomer, original);
+        sqlx::query("INSERT INTO milestones (project_id, type, title) VALUES (?, 'note', 'Keep me')")
+            .bind(newcomer)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let summary = snapshots.restore(snapshot.id).await.unwrap();
+        assert_eq!(summary.created_projects, 1);
+        assert_eq!(summary.restored_assignments, 1);
+
+        assert_eq!(project_name(&pool, newcomer).await, "Newcomer");
+        let restored = project_of(&pool, email).await.unwrap();
+        assert_ne!(restored, newcomer);
+        assert_eq!(project_name(&pool, restored).await, "Original");
+
+        let milestones: Vec<(i64, String)> = sqlx::query_as("SELECT project_id, title FROM milestones ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(milestones, vec![(newcomer, "Keep me".to_string()), (restored, "Kickoff".to_string())]);
+
+        // 再次恢复同一快照匹配到刚才重新创建的项目
+        let again = snapshots.restore(snapshot.id).await.unwrap();
+        assert_eq!(again.created_projects, 0);
+        assert_eq!(project_of(&pool, email).await, Some(restored));
+    }
+
+    #[tokio::test]
+    async fn restore_reverts_rename_of_the_same_project() {
+        let (pool, _db_dir) = test_pool().await;
+        let project = insert_project(&pool, "Before", "2026-01-01 09:00:00").await;
+        let snapshots = OrganizationSnapshots::new(pool.clone());
+        let snapshot = snapshots.create("rename").await.unwrap();
+
+        sqlx::query("UPDATE projects SET name = 'After' WHERE id = ?")
+            .bind(project)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let summary = snapshots.restore(snapshot.id).await.unwrap();
+        assert_eq!(summary.created_projects, 0);
+        assert_eq!(project_name(&pool, project).await, "Before");
+    }
+}
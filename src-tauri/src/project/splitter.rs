@@ -0,0 +1,234 @@
+/// 项目拆分
+///
+/// 主题归类可能把许多无关会话聚到一个项目里（见分类器的规模上限）。
+/// 拆分按会话进行：`propose` 列出项目中的各个会话及建议的新项目名称，最大的会话留在原项目；
+/// `apply` 把选中的会话分别移到新项目，记录为手动分类，并清除原项目的待检查标记。
+/// 拆分不写撤销日志，需要还原时把新项目合并回原项目即可。
+use crate::error::AppError;
+use crate::project::appearance::palette_color_for;
+use crate::project::classification_log::{ClassificationCandidate, ClassificationLog, ClassificationMethod};
+use crate::project::naming::{load_generic_subjects, project_name, unique_project_name};
+use crate::repository::ProjectRepository;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+
+/// 项目中的一个会话（拆分候选）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadPartition {
+    pub thread_id: String,
+    pub suggested_name: String,
+    /// 会话中第一封邮件的主题
+    pub subject: Option<String>,
+    pub email_ids: Vec<i64>,
+    pub email_count: usize,
+    pub first_date: String,
+    pub last_date: String,
+}
+
+/// 拆分建议
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitProposal {
+    pub project_id: i64,
+    pub project_name: String,
+    /// 留在原项目的会话（邮件最多的会话）
+    pub keep_thread_id: Option<String>,
+    /// 其余会话，邮件多的在前
+    pub partitions: Vec<ThreadPartition>,
+}
+
+/// 拆分结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitSummary {
+    pub project_id: i64,
+    pub created_projects: Vec<i64>,
+    pub moved_emails: usize,
+}
+
+/// 项目拆分器
+pub struct ProjectSplitter {
+    pool: SqlitePool,
+}
+
+impl ProjectSplitter {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 按会话生成拆分建议
+    pub async fn propose(&self, project_id: i64) -> Result<SplitProposal, AppError> {
+        let project_name = self.project_name(project_id).await?;
+        let mut partitions = self.partitions(project_id).await?;
+
+        partitions.sort_by(|a, b| b.email_count.cmp(&a.email_count).then(a.first_date.cmp(&b.first_date)));
+        let keep_thread_id = if partitions.is_empty() {
+            None
+        } else {
+            Some(partitions.remove(0).thread_id)
+        };
+
+        // 建议名称只保证与现有项目不重名，应用时再重新检查
+        let mut conn = self.pool.acquire().await?;
+        let mut taken: HashSet<String> = HashSet::new();
+        for partition in &mut partitions {
+            let mut name = unique_project_name(&mut *conn, &partition.suggested_name).await?;
+            let mut counter = 1;
+            while taken.contains(&name) {
+                counter += 1;
+                name = format!("{} ({})", partition.suggested_name, counter);
+            }
+            taken.insert(name.clone());
+            partition.suggested_name = name;
+        }
+
+        Ok(SplitProposal {
+            project_id,
+            project_name,
+            keep_thread_id,
+            partitions,
+        })
+    }
+
+    /// 把 `thread_ids` 中的每个会话移到一个新项目
+    pub async fn apply(&self, project_id: i64, thread_ids: &[String]) -> Result<SplitSummary, AppError> {
+        let partitions = self.partitions(project_id).await?;
+        let selected: HashSet<&str> = thread_ids.iter().map(String::as_str).collect();
+        if selected.is_empty() {
+            return Err(AppError::Validation("No threads selected".to_string()));
+        }
+        if let Some(unknown) = selected.iter().find(|id| !partitions.iter().any(|p| p.thread_id == **id)) {
+            return Err(AppError::Validation(format!("Thread {} does not belong to project {}", unknown, project_id)));
+        }
+        if selected.len() == partitions.len() {
+            return Err(AppError::Validation("At least one thread must stay in the project".to_string()));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let mut created_projects = Vec::new();
+        let mut moved_emails = 0;
+        for partition in partitions.iter().filter(|p| selected.contains(p.thread_id.as_str())) {
+            let name = unique_project_name(&mut *tx, &partition.suggested_name).await?;
+            let description = partition
+                .subject
+                .as_deref()
+                .map(str::trim)
+                .filter(|subject| !subject.is_empty() && *subject != name)
+                .map(|subject| format!("Original subject: {}", subject));
+            let new_id = sqlx::query(
+                r#"
+                INSERT INTO projects (name, description, status, color, email_count, attachment_count, created_at, updated_at)
+                VALUES (?, ?, 'active', ?, 0, 0, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+                "#
+            )
+            .bind(&name)
+            .bind(&description)
+            .bind(palette_color_for(&name))
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+
+            let chosen = ClassificationCandidate {
+                method: ClassificationMethod::Manual,
+                project_id: new_id,
+                matched_value: None,
+                confidence: 1.0,
+            };
+            for email_id in &partition.email_ids {
+                sqlx::query("UPDATE emails SET project_id = ? WHERE id = ?")
+                    .bind(new_id)
+                    .bind(email_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("UPDATE attachments SET project_id = ? WHERE email_id = ?")
+                    .bind(new_id)
+                    .bind(email_id)
+                    .execute(&mut *tx)
+                    .await?;
+                ClassificationLog::record_correction_on(&mut *tx, *email_id, new_id).await?;
+                ClassificationLog::record_on(&mut *tx, *email_id, &chosen, &[]).await?;
+            }
+
+            moved_emails += partition.email_ids.len();
+            created_projects.push(new_id);
+        }
+
+        sqlx::query("UPDATE projects SET needs_review = 0, review_reason = NULL WHERE id = ?")
+            .bind(project_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        let repo = ProjectRepository::new(self.pool.clone());
+        for id in std::iter::once(&project_id).chain(&created_projects) {
+            repo.recompute_stats(Some(*id)).await?;
+        }
+
+        log::info!(
+            "Split {} threads ({} emails) out of project {} into {:?}",
+            created_projects.len(), moved_emails, project_id, created_projects
+        );
+        Ok(SplitSummary {
+            project_id,
+            created_projects,
+            moved_emails,
+        })
+    }
+
+    async fn project_name(&self, project_id: i64) -> Result<String, AppError> {
+        let row: Option<(String, Option<String>)> = sqlx::query_as("SELECT name, status FROM projects WHERE id = ?")
+            .bind(project_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some((name, status)) if status.as_deref() != Some("deleted") => Ok(name),
+            _ => Err(AppError::ProjectNotFound { id: project_id }),
+        }
+    }
+
+    /// 按会话分组项目中的邮件（没有 thread_id 的邮件自成一组），建议名称尚未去重
+    async fn partitions(&self, project_id: i64) -> Result<Vec<ThreadPartition>, AppError> {
+        self.project_name(project_id).await?;
+
+        let rows: Vec<(i64, String, Option<String>, Option<String>, String, bool)> = sqlx::query_as(
+            r#"
+            SELECT id, COALESCE(thread_id, message_id, 'email-' || id), subject, sender, date,
+                   COALESCE(is_automated, 0)
+            FROM emails
+            WHERE project_id = ?
+            ORDER BY datetime(date) ASC, id ASC
+            "#
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let generic_subjects = load_generic_subjects(&self.pool).await;
+        let mut partitions: Vec<ThreadPartition> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for (id, thread_id, subject, sender, date, automated) in rows {
+            match index.get(&thread_id) {
+                Some(&i) => {
+                    let partition = &mut partitions[i];
+                    partition.email_ids.push(id);
+                    partition.email_count += 1;
+                    partition.last_date = date;
+                }
+                None => {
+                    index.insert(thread_id.clone(), partitions.len());
+                    partitions.push(ThreadPartition {
+                        suggested_name: project_name(subject.as_deref(), sender.as_deref(), &generic_subjects, automated),
+                        thread_id,
+                        subject,
+                        email_ids: vec![id],
+                        email_count: 1,
+                        first_date: date.clone(),
+                        last_date: date,
+                    });
+                }
+            }
+        }
+
+        Ok(partitions)
+    }
+}
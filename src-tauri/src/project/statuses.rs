@@ -0,0 +1,193 @@
+/// 项目状态词表
+///
+/// 项目状态（`projects.status`）取自 `project_statuses` 表，默认有 active、waiting_on_client、
+/// waiting_on_me、blocked、done、archived，用户可以增加或修改。`deleted` 不在词表中，只由回收站使用。
+/// 终结状态（done、archived）表示项目已结束，自动归档只从终结状态进行。
+/// 每次状态变化在时间线上追加一个 `status_change` 里程碑。
+use crate::error::AppError;
+use crate::project::undo::{UndoJournal, UndoOperation};
+use crate::utils::validation;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// 回收站使用的状态，不能作为词表中的状态
+pub const DELETED_STATUS: &str = "deleted";
+
+/// 内置状态（新建项目和归档使用），不能删除
+const BUILTIN_STATUSES: [&str; 2] = ["active", "archived"];
+
+/// 状态变化里程碑的类型
+pub const STATUS_CHANGE_MILESTONE: &str = "status_change";
+
+/// 词表中的一个状态
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProjectStatus {
+    pub key: String,
+    pub label: String,
+    pub color: Option<String>,
+    pub is_terminal: bool,
+    pub sort_order: i64,
+}
+
+/// 项目状态词表存储
+pub struct ProjectStatusStore {
+    pool: SqlitePool,
+}
+
+impl ProjectStatusStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 全部状态（按排序）
+    pub async fn list(&self) -> Result<Vec<ProjectStatus>, AppError> {
+        let statuses = sqlx::query_as::<_, ProjectStatus>(
+            r#"
+            SELECT key, label, color, COALESCE(is_terminal, 0) AS is_terminal, COALESCE(sort_order, 0) AS sort_order
+            FROM project_statuses
+            ORDER BY sort_order, key
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(statuses)
+    }
+
+    /// 新增或修改状态
+    pub async fn save(&self, status: ProjectStatus) -> Result<ProjectStatus, AppError> {
+        let key = validation::required("key", &status.key, 64)?;
+        if key == DELETED_STATUS || !key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+            return Err(AppError::InvalidField {
+                field: "key".to_string(),
+                message: format!("Invalid status key: {}", key),
+            });
+        }
+        let label = validation::required("label", &status.label, validation::MAX_NAME_CHARS)?;
+        let color = status
+            .color
+            .as_deref()
+            .filter(|color| !color.trim().is_empty())
+            .map(|color| validation::hex_color("color", color))
+            .transpose()?;
+        // 归档必须保持终结状态，否则自动归档后的项目又会被视为进行中
+        let is_terminal = status.is_terminal || key == "archived";
+
+        sqlx::query(
+            r#"
+            INSERT INTO project_statuses (key, label, color, is_terminal, sort_order)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(key) DO UPDATE SET
+                label = excluded.label,
+                color = excluded.color,
+                is_terminal = excluded.is_terminal,
+                sort_order = excluded.sort_order
+            "#
+        )
+        .bind(&key)
+        .bind(&label)
+        .bind(&color)
+        .bind(is_terminal)
+        .bind(status.sort_order)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(ProjectStatus { key, label, color, is_terminal, sort_order: status.sort_order })
+    }
+
+    /// 删除状态（内置状态和仍有项目使用的状态不能删除）
+    pub async fn delete(&self, key: &str) -> Result<(), AppError> {
+        if BUILTIN_STATUSES.contains(&key) {
+            return Err(AppError::Validation(format!("Built-in status {} cannot be deleted", key)));
+        }
+        let in_use: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects WHERE status = ? OR status_before_delete = ?")
+            .bind(key)
+            .bind(key)
+            .fetch_one(&self.pool)
+            .await?;
+        if in_use > 0 {
+            return Err(AppError::Validation(format!(
+                "Status {} is still used by {} projects",
+                key, in_use
+            )));
+        }
+        sqlx::query("DELETE FROM project_statuses WHERE key = ?")
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 设置项目状态（校验词表），追加状态变化里程碑并记录撤销日志
+    pub async fn set_project_status(&self, project_id: i64, key: &str) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        let status = find(&mut *tx, key)
+            .await?
+            .ok_or_else(|| AppError::InvalidField {
+                field: "status".to_string(),
+                message: format!("Unknown project status: {}", key),
+            })?;
+
+        let row: Option<(String, Option<String>)> = sqlx::query_as("SELECT name, status FROM projects WHERE id = ?")
+            .bind(project_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let (name, previous) = match row {
+            Some((name, status)) if status.as_deref() != Some(DELETED_STATUS) => {
+                (name, status.unwrap_or_else(|| "active".to_string()))
+            }
+            _ => return Err(AppError::ProjectNotFound { id: project_id }),
+        };
+        if previous == status.key {
+            return Ok(());
+        }
+
+        change_status(&mut *tx, project_id, &status).await?;
+        let inverse = UndoOperation::SetStatus {
+            project_id,
+            status: previous,
+            expected_status: status.key.clone(),
+        };
+        UndoJournal::record_on(
+            &mut *tx,
+            "set_project_status",
+            &format!("Mark \"{}\" as {}", name, status.label),
+            &inverse,
+        )
+        .await?;
+        tx.commit().await?;
+
+        log::info!("Project {} status set to {}", project_id, status.key);
+        Ok(())
+    }
+}
+
+/// 按 key 查找状态
+pub async fn find(conn: &mut SqliteConnection, key: &str) -> Result<Option<ProjectStatus>, AppError> {
+    let status = sqlx::query_as::<_, ProjectStatus>(
+        r#"
+        SELECT key, label, color, COALESCE(is_terminal, 0) AS is_terminal, COALESCE(sort_order, 0) AS sort_order
+        FROM project_statuses
+        WHERE key = ?
+        "#
+    )
+    .bind(key)
+    .fetch_optional(conn)
+    .await?;
+    Ok(status)
+}
+
+/// 更新项目状态并追加状态变化里程碑
+pub async fn change_status(conn: &mut SqliteConnection, project_id: i64, status: &ProjectStatus) -> Result<(), AppError> {
+    sqlx::query("UPDATE projects SET status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(&status.key)
+        .bind(project_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("INSERT INTO milestones (project_id, type, title, date) VALUES (?, ?, ?, datetime('now'))")
+        .bind(project_id)
+        .bind(STATUS_CHANGE_MILESTONE)
+        .bind(format!("Status: {}", status.label))
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
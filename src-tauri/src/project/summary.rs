@@ -0,0 +1,92 @@
+/// 仅附件邮件的时间线摘要
+///
+/// 对方只发了 "见附件" 之类的短正文时，时间线条目几乎是空的。
+/// 正文去掉引用和签名后短于阈值且带有附件时，生成 "Sent contract_v3_final.pdf (2.1 MB) and 1 other file" 这样的摘要。
+/// 签名里的内嵌小图片不计入附件。
+use crate::utils::i18n::{format_file_size, Locale};
+
+/// 正文（去掉引用和签名后）短于该字符数时视为无实质内容
+pub const SHORT_BODY_THRESHOLD: usize = 80;
+
+/// 小于该大小的图片视为签名内嵌图片
+const SIGNATURE_IMAGE_MAX_BYTES: i64 = 32 * 1024;
+
+/// 参与摘要计算的附件信息
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SummaryAttachment {
+    pub filename: String,
+    pub mime_type: Option<String>,
+    pub file_size: Option<i64>,
+}
+
+/// 生成仅附件邮件的摘要；正文有实质内容或没有真实附件时返回 None
+pub fn attachment_summary(body: &str, attachments: &[SummaryAttachment], locale: Locale) -> Option<String> {
+    if !is_short_body(body) {
+        return None;
+    }
+
+    let files: Vec<&SummaryAttachment> = attachments
+        .iter()
+        .filter(|a| !is_signature_image(a))
+        .collect();
+    // 最大的文件作为主附件展示
+    let primary = files.iter().max_by_key(|a| a.file_size.unwrap_or(0))?;
+    let size = format_file_size(primary.file_size.unwrap_or(0), locale);
+    let others = files.len() - 1;
+
+    Some(match (locale, others) {
+        (Locale::En, 0) => format!("Sent {} ({})", primary.filename, size),
+        (Locale::En, 1) => format!("Sent {} ({}) and 1 other file", primary.filename, size),
+        (Locale::En, n) => format!("Sent {} ({}) and {} other files", primary.filename, size, n),
+        (Locale::Zh, 0) => format!("发送了 {}（{}）", primary.filename, size),
+        (Locale::Zh, n) => format!("发送了 {}（{}）及其他 {} 个文件", primary.filename, size, n),
+    })
+}
+
+/// 正文去掉引用和签名后是否短于阈值
+pub fn is_short_body(body: &str) -> bool {
+    meaningful_body_len(body) < SHORT_BODY_THRESHOLD
+}
+
+/// 去掉引用行和签名后的正文字符数（不计空白）
+fn meaningful_body_len(body: &str) -> usize {
+    body.lines()
+        .take_while(|line| line.trim_end() != "--" && !is_quote_header(line))
+        .filter(|line| !line.trim_start().starts_with('>'))
+        .map(|line| line.chars().filter(|c| !c.is_whitespace()).count())
+        .sum()
+}
+
+/// 回复引用的开头（"On ... wrote:" / "在 ... 写道："）
+fn is_quote_header(line: &str) -> bool {
+    let line = line.trim();
+    (line.starts_with("On ") && line.ends_with("wrote:"))
+        || line.ends_with("写道：")
+        || line.ends_with("写道:")
+        || line.starts_with("-----Original Message-----")
+}
+
+/// 签名里的内嵌图片（image001.png 之类，或很小的图片）
+fn is_signature_image(attachment: &SummaryAttachment) -> bool {
+    let is_image = attachment
+        .mime_type
+        .as_deref()
+        .map(|mime| mime.to_ascii_lowercase().starts_with("image/"))
+        .unwrap_or(false);
+    if !is_image {
+        return false;
+    }
+
+    let stem = attachment
+        .filename
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(&attachment.filename)
+        .to_ascii_lowercase();
+    let outlook_inline = stem
+        .strip_prefix("image")
+        .map(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()))
+        .unwrap_or(false);
+
+    outlook_inline || attachment.file_size.unwrap_or(0) < SIGNATURE_IMAGE_MAX_BYTES
+}
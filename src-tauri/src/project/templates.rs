@@ -0,0 +1,480 @@
+/// 项目模板
+///
+/// 同类项目（"新客户接入"、"季度审计"）每次都要手动添加相同的标签、里程碑和说明。模板保存这些设置，
+/// 可以从模板新建项目，也可以套用到已有项目：默认合并（补充缺少的标签和里程碑，只填写空着的说明和截止日期），
+/// `overwrite` 时替换。模板可以设置发件人/主题匹配条件，分类器自动创建的项目命中时按合并方式套用。
+use crate::error::AppError;
+use crate::project::appearance::palette_color_for;
+use crate::project::naming::unique_project_name;
+use crate::project::preferences::ProjectPreferences;
+use crate::repository::ProjectRepository;
+use chrono::{Duration, Local};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 模板中的里程碑（日期相对于套用当天）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateMilestone {
+    pub title: String,
+    #[serde(rename = "type", default = "default_milestone_type")]
+    pub kind: String,
+    #[serde(default)]
+    pub offset_days: i64,
+}
+
+fn default_milestone_type() -> String {
+    "milestone".to_string()
+}
+
+/// 项目模板
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectTemplate {
+    pub id: i64,
+    pub name: String,
+    pub tags: Vec<String>,
+    pub milestones: Vec<TemplateMilestone>,
+    /// 项目说明，`{name}` 替换为项目名称
+    pub notes_template: Option<String>,
+    /// 截止日期为套用当天之后的天数
+    pub due_offset_days: Option<i64>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub preferences: Option<ProjectPreferences>,
+    /// 自动创建项目时匹配的发件人（地址或 `@域名`）
+    pub match_senders: Vec<String>,
+    /// 自动创建项目时匹配的主题关键词
+    pub match_subject: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// 新建或更新模板的请求
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProjectTemplateRequest {
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub milestones: Vec<TemplateMilestone>,
+    #[serde(default)]
+    pub notes_template: Option<String>,
+    #[serde(default)]
+    pub due_offset_days: Option<i64>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub preferences: Option<ProjectPreferences>,
+    #[serde(default)]
+    pub match_senders: Vec<String>,
+    #[serde(default)]
+    pub match_subject: Option<String>,
+}
+
+impl ProjectTemplateRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        if self.name.trim().is_empty() {
+            return Err(AppError::Validation("Template name is required".to_string()));
+        }
+        if self.milestones.iter().any(|milestone| milestone.title.trim().is_empty()) {
+            return Err(AppError::Validation("Milestone title is required".to_string()));
+        }
+        if self.due_offset_days.is_some_and(|days| days < 0) {
+            return Err(AppError::Validation("Due date offset must not be negative".to_string()));
+        }
+        if let Some(preferences) = &self.preferences {
+            preferences.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// 套用模板的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApplyTemplateSummary {
+    pub project_id: i64,
+    pub tags_added: usize,
+    pub milestones_added: usize,
+    pub notes_set: bool,
+    pub due_date_set: bool,
+    pub preferences_set: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct TemplateRow {
+    id: i64,
+    name: String,
+    tags: Option<String>,
+    milestones: Option<String>,
+    notes_template: Option<String>,
+    due_offset_days: Option<i64>,
+    color: Option<String>,
+    icon: Option<String>,
+    preferences: Option<String>,
+    match_senders: Option<String>,
+    match_subject: Option<String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+}
+
+impl From<TemplateRow> for ProjectTemplate {
+    fn from(row: TemplateRow) -> Self {
+        ProjectTemplate {
+            id: row.id,
+            name: row.name,
+            tags: row.tags.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default(),
+            milestones: row.milestones.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default(),
+            notes_template: row.notes_template,
+            due_offset_days: row.due_offset_days,
+            color: row.color,
+            icon: row.icon,
+            preferences: row.preferences.and_then(|s| serde_json::from_str(&s).ok()),
+            match_senders: split_patterns(row.match_senders.as_deref()),
+            match_subject: row.match_subject,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+const TEMPLATE_COLUMNS: &str = "id, name, tags, milestones, notes_template, due_offset_days, color, icon, \
+    preferences, match_senders, match_subject, created_at, updated_at";
+
+/// 项目模板存储
+pub struct ProjectTemplateStore {
+    pool: SqlitePool,
+}
+
+impl ProjectTemplateStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn list(&self) -> Result<Vec<ProjectTemplate>, AppError> {
+        let rows = sqlx::query_as::<_, TemplateRow>(&format!(
+            "SELECT {} FROM project_templates ORDER BY name COLLATE NOCASE",
+            TEMPLATE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn get(&self, id: i64) -> Result<ProjectTemplate, AppError> {
+        sqlx::query_as::<_, TemplateRow>(&format!("SELECT {} FROM project_templates WHERE id = ?", TEMPLATE_COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(Into::into)
+            .ok_or_else(|| AppError::Validation(format!("Project template {} not found", id)))
+    }
+
+    pub async fn create(&self, request: &ProjectTemplateRequest) -> Result<ProjectTemplate, AppError> {
+        request.validate()?;
+        let name = request.name.trim();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO project_templates (
+                name, tags, milestones, notes_template, due_offset_days, color, icon, preferences,
+                match_senders, match_subject
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(name)
+        .bind(serde_json::to_string(&clean_tags(&request.tags))?)
+        .bind(serde_json::to_string(&request.milestones)?)
+        .bind(non_empty(request.notes_template.as_deref()))
+        .bind(request.due_offset_days)
+        .bind(non_empty(request.color.as_deref()))
+        .bind(non_empty(request.icon.as_deref()))
+        .bind(request.preferences.as_ref().map(serde_json::to_string).transpose()?)
+        .bind(join_patterns(&request.match_senders))
+        .bind(non_empty(request.match_subject.as_deref()))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| duplicate_name_error(e, name))?;
+
+        self.get(result.last_insert_rowid()).await
+    }
+
+    pub async fn update(&self, id: i64, request: &ProjectTemplateRequest) -> Result<ProjectTemplate, AppError> {
+        request.validate()?;
+        let name = request.name.trim();
+        let updated = sqlx::query(
+            r#"
+            UPDATE project_templates
+            SET name = ?, tags = ?, milestones = ?, notes_template = ?, due_offset_days = ?, color = ?,
+                icon = ?, preferences = ?, match_senders = ?, match_subject = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#
+        )
+        .bind(name)
+        .bind(serde_json::to_string(&clean_tags(&request.tags))?)
+        .bind(serde_json::to_string(&request.milestones)?)
+        .bind(non_empty(request.notes_template.as_deref()))
+        .bind(request.due_offset_days)
+        .bind(non_empty(request.color.as_deref()))
+        .bind(non_empty(request.icon.as_deref()))
+        .bind(request.preferences.as_ref().map(serde_json::to_string).transpose()?)
+        .bind(join_patterns(&request.match_senders))
+        .bind(non_empty(request.match_subject.as_deref()))
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| duplicate_name_error(e, name))?
+        .rows_affected();
+        if updated == 0 {
+            return Err(AppError::Validation(format!("Project template {} not found", id)));
+        }
+
+        self.get(id).await
+    }
+
+    pub async fn delete(&self, id: i64) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM project_templates WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 从模板新建项目
+    pub async fn create_project(&self, template_id: i64, name: &str) -> Result<i64, AppError> {
+        let template = self.get(template_id).await?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::Validation("Project name is required".to_string()));
+        }
+        let name = unique_project_name(&mut *self.pool.acquire().await?, name).await?;
+        let color = template.color.clone().unwrap_or_else(|| palette_color_for(&name).to_string());
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO projects (name, status, color, icon, email_count, attachment_count, created_at, updated_at)
+            VALUES (?, 'active', ?, ?, 0, 0, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            "#
+        )
+        .bind(&name)
+        .bind(&color)
+        .bind(&template.icon)
+        .execute(&self.pool)
+        .await?;
+        let project_id = result.last_insert_rowid();
+
+        self.apply_template(project_id, &template, false).await?;
+        log::info!("Created project {} from template {:?}", project_id, template.name);
+        Ok(project_id)
+    }
+
+    /// 把模板套用到已有项目
+    pub async fn apply(&self, project_id: i64, template_id: i64, overwrite: bool) -> Result<ApplyTemplateSummary, AppError> {
+        let template = self.get(template_id).await?;
+        self.apply_template(project_id, &template, overwrite).await
+    }
+
+    /// 自动创建的项目命中的模板（按发件人和主题匹配，没有匹配条件的模板不参与）
+    ///
+    /// `sender` 可以是纯地址或 `Name <addr>`。
+    pub async fn matching(&self, sender: Option<&str>, subject: Option<&str>) -> Result<Option<ProjectTemplate>, AppError> {
+        let sender = sender.map(sender_address).unwrap_or_default();
+        let subject = subject.map(str::to_lowercase).unwrap_or_default();
+        let templates = self.list().await?;
+
+        Ok(templates.into_iter().find(|template| {
+            let keyword = template.match_subject.as_deref().map(str::trim).filter(|k| !k.is_empty());
+            if template.match_senders.is_empty() && keyword.is_none() {
+                return false;
+            }
+            let sender_matches = template.match_senders.is_empty()
+                || template.match_senders.iter().any(|pattern| sender_matches(&sender, pattern));
+            let subject_matches = keyword.is_none_or(|keyword| subject.contains(&keyword.to_lowercase()));
+            sender_matches && subject_matches
+        }))
+    }
+
+    async fn apply_template(
+        &self,
+        project_id: i64,
+        template: &ProjectTemplate,
+        overwrite: bool,
+    ) -> Result<ApplyTemplateSummary, AppError> {
+        let mut summary = ApplyTemplateSummary {
+            project_id,
+            ..Default::default()
+        };
+        let today = Local::now().date_naive();
+
+        let mut tx = self.pool.begin().await?;
+        let row: Option<(String, Option<String>, Option<String>, Option<String>, Option<String>)> =
+            sqlx::query_as("SELECT name, status, tags, description, due_date FROM projects WHERE id = ?")
+                .bind(project_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let (name, tags, description, due_date) = match row {
+            Some((name, status, tags, description, due_date)) if status.as_deref() != Some("deleted") => {
+                (name, tags, description, due_date)
+            }
+            _ => return Err(AppError::ProjectNotFound { id: project_id }),
+        };
+
+        // 标签：合并时取并集
+        let mut merged: Vec<String> = if overwrite {
+            Vec::new()
+        } else {
+            tags.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default()
+        };
+        for tag in &template.tags {
+            if !merged.iter().any(|existing| existing.eq_ignore_ascii_case(tag)) {
+                merged.push(tag.clone());
+                summary.tags_added += 1;
+            }
+        }
+
+        // 说明：合并时只填写空说明
+        let notes = template
+            .notes_template
+            .as_deref()
+            .filter(|_| overwrite || description.as_deref().is_none_or(|d| d.trim().is_empty()))
+            .map(|notes| notes.replace("{name}", &name));
+        summary.notes_set = notes.is_some();
+
+        sqlx::query(
+            r#"
+            UPDATE projects
+            SET tags = ?, description = COALESCE(?, description),
+                color = CASE WHEN ? THEN COALESCE(?, color) ELSE color END,
+                icon = CASE WHEN ? OR icon IS NULL THEN COALESCE(?, icon) ELSE icon END,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#
+        )
+        .bind(serde_json::to_string(&merged)?)
+        .bind(&notes)
+        .bind(overwrite)
+        .bind(&template.color)
+        .bind(overwrite)
+        .bind(&template.icon)
+        .bind(project_id)
+        .execute(&mut *tx)
+        .await?;
+
+        // 里程碑：合并时跳过已有同名里程碑；覆盖时替换之前套用的模板里程碑（不动邮件关联的和截止日期里程碑）
+        if overwrite {
+            let titles: Vec<String> = template.milestones.iter().map(|m| m.title.trim().to_string()).collect();
+            for title in &titles {
+                sqlx::query(
+                    "DELETE FROM milestones WHERE project_id = ? AND email_id IS NULL AND type != 'deadline' AND title = ?",
+                )
+                .bind(project_id)
+                .bind(title)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        let existing: Vec<String> = sqlx::query_scalar("SELECT title FROM milestones WHERE project_id = ? AND title IS NOT NULL")
+            .bind(project_id)
+            .fetch_all(&mut *tx)
+            .await?;
+        for milestone in &template.milestones {
+            let title = milestone.title.trim();
+            if existing.iter().any(|existing| existing.eq_ignore_ascii_case(title)) {
+                continue;
+            }
+            let date = today + Duration::days(milestone.offset_days);
+            sqlx::query("INSERT INTO milestones (project_id, type, title, date) VALUES (?, ?, ?, ?)")
+                .bind(project_id)
+                .bind(&milestone.kind)
+                .bind(title)
+                .bind(format!("{} 09:00:00", date.format("%Y-%m-%d")))
+                .execute(&mut *tx)
+                .await?;
+            summary.milestones_added += 1;
+        }
+
+        let has_preferences: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM project_preferences WHERE project_id = ?)")
+                .bind(project_id)
+                .fetch_one(&mut *tx)
+                .await?;
+        tx.commit().await?;
+
+        // 截止日期和视图偏好沿用项目仓库的写入逻辑（维护 deadline 里程碑、校验偏好）
+        let repo = ProjectRepository::new(self.pool.clone());
+        if let Some(days) = template.due_offset_days {
+            if overwrite || due_date.is_none() {
+                let date = (today + Duration::days(days)).format("%Y-%m-%d").to_string();
+                repo.set_due_date(project_id, Some(&date), None, true).await?;
+                summary.due_date_set = true;
+            }
+        }
+        if let Some(preferences) = &template.preferences {
+            if overwrite || !has_preferences {
+                repo.set_preferences(project_id, preferences).await?;
+                summary.preferences_set = true;
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+/// 取出 `Name <addr>` 中的地址（小写）
+fn sender_address(sender: &str) -> String {
+    let address = match (sender.rfind('<'), sender.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &sender[start + 1..end],
+        _ => sender,
+    };
+    address.trim().to_lowercase()
+}
+
+/// 发件人是否匹配模式（完整地址或 `@域名`）
+fn sender_matches(sender: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+    if pattern.starts_with('@') {
+        sender.ends_with(&pattern)
+    } else {
+        !pattern.is_empty() && sender == pattern
+    }
+}
+
+fn split_patterns(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(|pattern| pattern.trim().to_lowercase())
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
+fn join_patterns(patterns: &[String]) -> Option<String> {
+    let joined = split_patterns(Some(&patterns.join(","))).join(",");
+    (!joined.is_empty()).then_some(joined)
+}
+
+fn clean_tags(tags: &[String]) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|tag| tag.trim()).filter(|tag| !tag.is_empty()) {
+        if !cleaned.iter().any(|existing| existing.eq_ignore_ascii_case(tag)) {
+            cleaned.push(tag.to_string());
+        }
+    }
+    cleaned
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+fn duplicate_name_error(error: sqlx::Error, name: &str) -> AppError {
+    let unique = error
+        .as_database_error()
+        .is_some_and(|db| db.message().contains("UNIQUE"));
+    if unique {
+        AppError::Validation(format!("A project template named {:?} already exists", name))
+    } else {
+        AppError::Database(error)
+    }
+}
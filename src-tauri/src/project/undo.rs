@@ -0,0 +1,381 @@
+/// 项目操作撤销日志
+///
+/// 归档、合并、移动邮件和删除项目时，在同一事务中把逆操作写入 `undo_log`（有效期 `UNDO_TTL_MINUTES`）。
+/// 合并等多步操作只记录一条日志，撤销时在一个事务中整体恢复。执行逆操作前会检查相关项目和邮件
+/// 是否仍处于操作后的状态，已过期或已不适用的日志返回说明而不修改数据。
+use crate::error::AppError;
+use crate::repository::ProjectRepository;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// 撤销日志有效期（分钟）
+pub const UNDO_TTL_MINUTES: i64 = 60;
+
+/// 邮件原来的归属
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailAssignment {
+    pub email_id: i64,
+    /// 操作前所属项目
+    pub previous_project_id: Option<i64>,
+    /// 操作后所属项目（撤销前检查邮件是否仍在这里）
+    pub current_project_id: i64,
+}
+
+/// 被合并的源项目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergedSource {
+    pub project_id: i64,
+    pub is_pinned: bool,
+    pub pin_order: Option<i64>,
+    pub milestone_ids: Vec<i64>,
+    pub manual_file_ids: Vec<i64>,
+}
+
+/// 逆操作描述
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum UndoOperation {
+    /// 恢复项目状态（撤销归档）
+    SetStatus {
+        project_id: i64,
+        status: String,
+        expected_status: String,
+    },
+    /// 从回收站恢复项目（撤销删除）
+    RestoreDeleted {
+        project_id: i64,
+        is_pinned: bool,
+        pin_order: Option<i64>,
+    },
+    /// 恢复邮件归属（撤销移动）
+    Reassign { assignments: Vec<EmailAssignment> },
+    /// 恢复所有源项目及其邮件、里程碑和文件（撤销合并）
+    Unmerge {
+        target_id: i64,
+        sources: Vec<MergedSource>,
+        assignments: Vec<EmailAssignment>,
+    },
+}
+
+/// 撤销日志条目
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UndoEntry {
+    pub id: i64,
+    /// archive_project / merge_projects / move_email_to_project / delete_project
+    pub action: String,
+    pub description: String,
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+/// 撤销结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoResult {
+    pub id: i64,
+    pub action: String,
+    pub description: String,
+    /// 统计已重新计算的项目
+    pub affected_projects: Vec<i64>,
+}
+
+/// 撤销日志
+pub struct UndoJournal {
+    pool: SqlitePool,
+}
+
+impl UndoJournal {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 在给定连接（通常是执行操作的事务）上记录逆操作，返回日志 ID
+    pub async fn record_on(
+        conn: &mut SqliteConnection,
+        action: &str,
+        description: &str,
+        inverse: &UndoOperation,
+    ) -> Result<i64, AppError> {
+        let inverse = serde_json::to_string(inverse)?;
+        let id = sqlx::query(
+            r#"
+            INSERT INTO undo_log (action, description, inverse, expires_at)
+            VALUES (?, ?, ?, datetime('now', '+' || ? || ' minutes'))
+            "#
+        )
+        .bind(action)
+        .bind(description)
+        .bind(&inverse)
+        .bind(UNDO_TTL_MINUTES)
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    /// 可撤销的操作（未过期、未撤销，最新的在前），同时清理过期日志
+    pub async fn list(&self) -> Result<Vec<UndoEntry>, AppError> {
+        sqlx::query("DELETE FROM undo_log WHERE datetime(expires_at) <= datetime('now')")
+            .execute(&self.pool)
+            .await?;
+
+        let entries = sqlx::query_as::<_, UndoEntry>(
+            r#"
+            SELECT id, action, description, created_at, expires_at
+            FROM undo_log
+            WHERE undone_at IS NULL
+            ORDER BY id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(entries)
+    }
+
+    /// 撤销最近一次操作
+    pub async fn undo_last(&self) -> Result<UndoResult, AppError> {
+        let id: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM undo_log
+            WHERE undone_at IS NULL AND datetime(expires_at) > datetime('now')
+            ORDER BY id DESC LIMIT 1
+            "#
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match id {
+            Some(id) => self.undo(id).await,
+            None => Err(AppError::Validation("Nothing to undo".to_string())),
+        }
+    }
+
+    /// 撤销指定操作
+    pub async fn undo(&self, id: i64) -> Result<UndoResult, AppError> {
+        let row: Option<(String, String, String, Option<String>, bool)> = sqlx::query_as(
+            r#"
+            SELECT action, description, inverse, undone_at,
+                   datetime(expires_at) <= datetime('now') AS expired
+            FROM undo_log WHERE id = ?
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some((action, description, inverse, undone_at, expired)) = row else {
+            return Err(AppError::Validation(format!("Undo entry {} no longer exists", id)));
+        };
+        if undone_at.is_some() {
+            return Err(AppError::Validation(format!("\"{}\" has already been undone", description)));
+        }
+        if expired {
+            return Err(AppError::Validation(format!(
+                "\"{}\" can no longer be undone (undo expires after {} minutes)",
+                description, UNDO_TTL_MINUTES
+            )));
+        }
+
+        let inverse: UndoOperation = serde_json::from_str(&inverse)?;
+
+        let mut tx = self.pool.begin().await?;
+        let affected = apply(&mut *tx, &inverse)
+            .await
+            .map_err(|e| match e {
+                AppError::Validation(reason) => {
+                    AppError::Validation(format!("Cannot undo \"{}\": {}", description, reason))
+                }
+                other => other,
+            })?;
+        sqlx::query("UPDATE undo_log SET undone_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        let repo = ProjectRepository::new(self.pool.clone());
+        for project_id in &affected {
+            repo.recompute_stats(Some(*project_id)).await?;
+        }
+
+        log::info!("Undid {} #{}: {}", action, id, description);
+        Ok(UndoResult {
+            id,
+            action,
+            description,
+            affected_projects: affected,
+        })
+    }
+}
+
+/// 执行逆操作，返回需要重新计算统计的项目
+async fn apply(conn: &mut SqliteConnection, inverse: &UndoOperation) -> Result<Vec<i64>, AppError> {
+    match inverse {
+        UndoOperation::SetStatus { project_id, status, expected_status } => {
+            let current = project_status(conn, *project_id).await?;
+            if current != *expected_status {
+                return Err(AppError::Validation(format!(
+                    "project {} is now {}",
+                    project_id, current
+                )));
+            }
+            sqlx::query("UPDATE projects SET status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(status)
+                .bind(project_id)
+                .execute(&mut *conn)
+                .await?;
+            Ok(vec![*project_id])
+        }
+        UndoOperation::RestoreDeleted { project_id, is_pinned, pin_order } => {
+            let current = project_status(conn, *project_id).await?;
+            if current != "deleted" {
+                return Err(AppError::Validation(format!(
+                    "project {} has already been restored",
+                    project_id
+                )));
+            }
+            restore_project(conn, *project_id, *is_pinned, *pin_order).await?;
+            Ok(vec![*project_id])
+        }
+        UndoOperation::Reassign { assignments } => {
+            check_assignments(conn, assignments).await?;
+            let affected = reassign(conn, assignments).await?;
+            Ok(affected)
+        }
+        UndoOperation::Unmerge { target_id, sources, assignments } => {
+            project_status(conn, *target_id).await?;
+            for source in sources {
+                let current = project_status(conn, source.project_id).await?;
+                if current != "deleted" {
+                    return Err(AppError::Validation(format!(
+                        "merged project {} has been restored separately",
+                        source.project_id
+                    )));
+                }
+            }
+            check_assignments(conn, assignments).await?;
+
+            let mut affected = reassign(conn, assignments).await?;
+            for source in sources {
+                restore_project(conn, source.project_id, source.is_pinned, source.pin_order).await?;
+                for milestone_id in &source.milestone_ids {
+                    sqlx::query("UPDATE milestones SET project_id = ? WHERE id = ? AND project_id = ?")
+                        .bind(source.project_id)
+                        .bind(milestone_id)
+                        .bind(target_id)
+                        .execute(&mut *conn)
+                        .await?;
+                }
+                for file_id in &source.manual_file_ids {
+                    sqlx::query("UPDATE attachments SET project_id = ? WHERE id = ? AND project_id = ?")
+                        .bind(source.project_id)
+                        .bind(file_id)
+                        .bind(target_id)
+                        .execute(&mut *conn)
+                        .await?;
+                }
+                if !affected.contains(&source.project_id) {
+                    affected.push(source.project_id);
+                }
+            }
+            if !affected.contains(target_id) {
+                affected.push(*target_id);
+            }
+            Ok(affected)
+        }
+    }
+}
+
+/// 项目当前状态；项目已被永久删除时返回说明
+async fn project_status(conn: &mut SqliteConnection, project_id: i64) -> Result<String, AppError> {
+    let status: Option<Option<String>> = sqlx::query_scalar("SELECT status FROM projects WHERE id = ?")
+        .bind(project_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    match status {
+        Some(status) => Ok(status.unwrap_or_else(|| "active".to_string())),
+        None => Err(AppError::Validation(format!(
+            "project {} has been permanently deleted",
+            project_id
+        ))),
+    }
+}
+
+/// 邮件必须仍在操作后的项目中，原项目必须仍然存在
+async fn check_assignments(conn: &mut SqliteConnection, assignments: &[EmailAssignment]) -> Result<(), AppError> {
+    for assignment in assignments {
+        let current: Option<Option<i64>> = sqlx::query_scalar("SELECT project_id FROM emails WHERE id = ?")
+            .bind(assignment.email_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        match current {
+            None => {
+                return Err(AppError::Validation(format!(
+                    "email {} has been deleted",
+                    assignment.email_id
+                )))
+            }
+            Some(project_id) if project_id != Some(assignment.current_project_id) => {
+                return Err(AppError::Validation(format!(
+                    "email {} has since been moved to another project",
+                    assignment.email_id
+                )))
+            }
+            Some(_) => {}
+        }
+        if let Some(previous) = assignment.previous_project_id {
+            project_status(conn, previous).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 把邮件及其附件恢复到原项目，返回涉及的项目
+async fn reassign(conn: &mut SqliteConnection, assignments: &[EmailAssignment]) -> Result<Vec<i64>, AppError> {
+    let mut affected = Vec::new();
+    for assignment in assignments {
+        sqlx::query("UPDATE emails SET project_id = ? WHERE id = ?")
+            .bind(assignment.previous_project_id)
+            .bind(assignment.email_id)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("UPDATE attachments SET project_id = ? WHERE email_id = ?")
+            .bind(assignment.previous_project_id)
+            .bind(assignment.email_id)
+            .execute(&mut *conn)
+            .await?;
+
+        for project_id in [assignment.previous_project_id, Some(assignment.current_project_id)].into_iter().flatten() {
+            if !affected.contains(&project_id) {
+                affected.push(project_id);
+            }
+        }
+    }
+    Ok(affected)
+}
+
+async fn restore_project(
+    conn: &mut SqliteConnection,
+    project_id: i64,
+    is_pinned: bool,
+    pin_order: Option<i64>,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE projects
+        SET status = COALESCE(status_before_delete, 'active'),
+            status_before_delete = NULL,
+            deleted_at = NULL,
+            is_pinned = ?,
+            pin_order = ?,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#
+    )
+    .bind(is_pinned)
+    .bind(pin_order)
+    .bind(project_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,235 @@
+use crate::artifacts::safety::DangerLevel;
+use crate::artifacts::Artifact;
+use crate::error::AppError;
+use sqlx::SqlitePool;
+
+/// 附件查询的公共列（包含项目和来源邮件上下文）
+const ARTIFACT_COLUMNS: &str = r#"
+    a.id,
+    a.filename,
+    COALESCE(a.file_type, 'unknown') AS file_type,
+    COALESCE(a.file_size, 0) AS file_size,
+    a.mime_type,
+    NULLIF(a.detected_mime, '') AS detected_mime,
+    a.email_id AS source_email_id,
+    COALESCE(a.created_at, '') AS created_at,
+    COALESCE(a.is_starred, 0) AS is_starred,
+    a.danger_level,
+    COALESCE(a.project_id, e.project_id) AS project_id,
+    p.name AS project_name,
+    e.subject AS email_subject,
+    e.date AS email_date,
+    COALESCE(a.origin, 'email') AS origin,
+    a.note
+"#;
+
+/// 附件来源的公共连接
+const ARTIFACT_JOINS: &str = r#"
+    FROM attachments a
+    LEFT JOIN emails e ON e.id = a.email_id
+    LEFT JOIN projects p ON p.id = COALESCE(a.project_id, e.project_id)
+"#;
+
+/// 附件数据仓库
+#[derive(Clone)]
+pub struct ArtifactRepository {
+    pool: SqlitePool,
+}
+
+impl ArtifactRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 获取项目附件（星标优先，其次按来源邮件日期倒序）
+    pub async fn list_by_project(&self, project_id: i64) -> Result<Vec<Artifact>, AppError> {
+        let sql = format!(
+            "SELECT {} {} WHERE COALESCE(a.project_id, e.project_id) = ? \
+             ORDER BY a.is_starred DESC, julianday(COALESCE(e.date, a.created_at)) DESC, a.id DESC",
+            ARTIFACT_COLUMNS, ARTIFACT_JOINS
+        );
+
+        let artifacts = sqlx::query_as::<_, Artifact>(&sql)
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(artifacts)
+    }
+
+    /// 根据 ID 获取附件
+    pub async fn get_by_id(&self, id: i64) -> Result<Artifact, AppError> {
+        let sql = format!("SELECT {} {} WHERE a.id = ?", ARTIFACT_COLUMNS, ARTIFACT_JOINS);
+
+        sqlx::query_as::<_, Artifact>(&sql)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(AppError::AttachmentNotFound { id })
+    }
+
+    /// 获取附件文件的相对路径
+    pub async fn get_file_path(&self, id: i64) -> Result<Option<String>, AppError> {
+        let row: Option<(Option<String>,)> = sqlx::query_as(
+            "SELECT file_path FROM attachments WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|(path,)| path).ok_or(AppError::AttachmentNotFound { id })
+    }
+
+    /// 更新附件危险等级
+    pub async fn set_danger_level(&self, id: i64, level: DangerLevel) -> Result<(), AppError> {
+        sqlx::query("UPDATE attachments SET danger_level = ? WHERE id = ?")
+            .bind(level.as_str())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 设置附件星标状态
+    pub async fn set_starred(&self, id: i64, starred: bool) -> Result<(), AppError> {
+        let result = sqlx::query("UPDATE attachments SET is_starred = ? WHERE id = ?")
+            .bind(starred)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::AttachmentNotFound { id });
+        }
+
+        log::info!("Attachment {} starred: {}", id, starred);
+        Ok(())
+    }
+
+    /// 让邮件附件的 `project_id` 与所属邮件一致（修复旧版本同步的附件），返回修改的行数
+    pub async fn sync_project_ids(&self) -> Result<u64, AppError> {
+        let updated = sqlx::query(
+            r#"
+            UPDATE attachments
+            SET project_id = (SELECT e.project_id FROM emails e WHERE e.id = attachments.email_id)
+            WHERE email_id IS NOT NULL
+              AND project_id IS NOT (SELECT e.project_id FROM emails e WHERE e.id = attachments.email_id)
+            "#
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        log::info!("Synced project_id for {} attachments", updated);
+        Ok(updated)
+    }
+
+    /// 获取所有星标附件
+    pub async fn list_starred(&self) -> Result<Vec<Artifact>, AppError> {
+        let sql = format!(
+            "SELECT {} {} WHERE a.is_starred = 1 ORDER BY julianday(e.date) DESC, a.id DESC",
+            ARTIFACT_COLUMNS, ARTIFACT_JOINS
+        );
+
+        let artifacts = sqlx::query_as::<_, Artifact>(&sql)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(artifacts)
+    }
+
+    /// 获取所有附件（最新的在前）
+    pub async fn list_all(&self) -> Result<Vec<Artifact>, AppError> {
+        let sql = format!(
+            "SELECT {} {} ORDER BY datetime(COALESCE(e.date, a.created_at)) DESC, a.id DESC",
+            ARTIFACT_COLUMNS, ARTIFACT_JOINS
+        );
+
+        let artifacts = sqlx::query_as::<_, Artifact>(&sql)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(artifacts)
+    }
+
+    /// 获取最近的附件（按来源邮件日期，跨所有项目）
+    pub async fn list_recent(&self, limit: i64) -> Result<Vec<Artifact>, AppError> {
+        let sql = format!(
+            "SELECT {} {} ORDER BY datetime(COALESCE(e.date, a.created_at)) DESC, a.id DESC LIMIT ?",
+            ARTIFACT_COLUMNS, ARTIFACT_JOINS
+        );
+
+        let artifacts = sqlx::query_as::<_, Artifact>(&sql)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(artifacts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::fixtures::{
+        insert_account, insert_email, insert_project, NewEmail,
+    };
+    use crate::storage::database::test_pool;
+
+    async fn attachment_project(pool: &SqlitePool, attachment_id: i64) -> Option<i64> {
+        sqlx::query_scalar("SELECT project_id FROM attachments WHERE id = ?")
+            .bind(attachment_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn sync_project_ids_follows_the_email() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = insert_account(&pool, "me@example.com").await;
+        let alpha = insert_project(&pool, "Alpha").await;
+        let beta = insert_project(&pool, "Beta").await;
+
+        let mut emails = Vec::new();
+        for (message_id, project_id) in [("<a@example.com>", Some(alpha)), ("<b@example.com>", None)] {
+            let email = NewEmail {
+                account_id: Some(account_id),
+                message_id,
+                project_id,
+                ..Default::default()
+            };
+            emails.push(insert_email(&pool, email).await);
+        }
+
+        // 旧版本同步留下的附件：项目为空、指向旧项目；手动添加的文件没有邮件
+        let mut attachments = Vec::new();
+        for (email_id, project_id) in [
+            (Some(emails[0]), None),
+            (Some(emails[0]), Some(beta)),
+            (Some(emails[0]), Some(alpha)),
+            (Some(emails[1]), Some(beta)),
+            (None, Some(beta)),
+        ] {
+            attachments.push(
+                sqlx::query("INSERT INTO attachments (email_id, project_id, filename) VALUES (?, ?, 'file.pdf')")
+                    .bind(email_id)
+                    .bind(project_id)
+                    .execute(&pool)
+                    .await
+                    .unwrap()
+                    .last_insert_rowid(),
+            );
+        }
+
+        let repo = ArtifactRepository::new(pool.clone());
+        assert_eq!(repo.sync_project_ids().await.unwrap(), 3);
+        assert_eq!(attachment_project(&pool, attachments[0]).await, Some(alpha));
+        assert_eq!(attachment_project(&pool, attachments[1]).await, Some(alpha));
+        assert_eq!(attachment_project(&pool, attachments[2]).await, Some(alpha));
+        assert_eq!(attachment_project(&pool, attachments[3]).await, None, "unassigned email clears the project");
+        assert_eq!(attachment_project(&pool, attachments[4]).await, Some(beta), "manual files keep their project");
+
+        assert_eq!(repo.sync_project_ids().await.unwrap(), 0);
+    }
+}
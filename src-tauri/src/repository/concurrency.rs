@@ -0,0 +1,28 @@
+/// 乐观并发控制
+///
+/// 表中维护 `version` 列，客户端更新时带上读取到的版本号，
+/// UPDATE 只在版本一致时生效并将版本加一；影响行数为 0 说明数据已被其他窗口修改。
+use crate::error::AppError;
+use serde::Serialize;
+
+/// 生成带版本检查的 UPDATE 语句
+///
+/// 调用方先按顺序绑定 `assignments` 中的参数，再绑定行 ID 和期望的版本号。
+pub fn versioned_update_sql(table: &str, assignments: &str) -> String {
+    format!(
+        "UPDATE {} SET {}, version = version + 1 WHERE id = ? AND version = ?",
+        table, assignments
+    )
+}
+
+/// 检查版本化更新是否生效，未生效时返回携带当前数据的冲突错误
+pub fn ensure_swapped<T: Serialize>(rows_affected: u64, what: &str, current: &T) -> Result<(), AppError> {
+    if rows_affected > 0 {
+        return Ok(());
+    }
+
+    Err(AppError::Conflict {
+        message: format!("{} was modified elsewhere, please review and try again", what),
+        current: serde_json::to_value(current)?,
+    })
+}
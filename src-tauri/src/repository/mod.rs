@@ -1,4 +1,6 @@
+pub mod artifact;
+pub mod concurrency;
 pub mod project;
 
+pub use artifact::ArtifactRepository;
 pub use project::ProjectRepository;
-
@@ -1,40 +1,87 @@
 use crate::error::AppError;
-use crate::project::{Project, ProjectStats, TimelineEvent, MilestoneEvent, EmailEvent, ThreadEvent, Attachment, LastActivity};
-use sqlx::SqlitePool;
-use std::collections::HashMap;
+use crate::mail::contacts::{self, CanonicalContact};
+use crate::mail::recipients;
+use crate::mail::vip::VipList;
+use crate::mail::calendar::{CalendarInvite, CalendarStore};
+use crate::mail::receipts::{EmailReceipt, ReceiptStore};
+use crate::project::{AutomatedGroupEvent, DeletedProject, DueProject, Project, ProjectReview, ProjectSort, ProjectStats, TimelineEvent, MilestoneEvent, EmailEvent, ThreadEvent, Attachment, thread_event_id, timeline_id, LastActivity, ThreadEmail, ThreadProject, ThreadView};
+use crate::project::classifier::{normalize_subject, ClassifierConfig};
+use crate::project::classification_log::{ClassificationCandidate, ClassificationLog, ClassificationMethod};
+use crate::project::appearance::{validate_color, validate_icon};
+use crate::project::preferences::ProjectPreferences;
+use crate::project::statuses;
+use crate::project::summary::{attachment_summary, is_short_body, SummaryAttachment};
+use crate::project::undo::{EmailAssignment, UndoJournal, UndoOperation};
+use crate::storage::quarantine::{AttachmentQuarantine, RemovedAttachment, REMOVED_ATTACHMENT_COLUMNS};
+use crate::utils::i18n::{activity_display, format_file_size, parse_timestamp, relative_time, tr, DisplayZone, Locale, Message};
+use chrono::{Duration, NaiveDate, Utc};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 /// 项目数据仓库
 #[derive(Clone)]
 pub struct ProjectRepository {
     pool: SqlitePool,
+    /// 客户端给出的 UTC 偏移（分钟），为空时使用设置或系统时区
+    utc_offset: Option<i32>,
 }
 
 impl ProjectRepository {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self { pool, utc_offset: None }
     }
 
-    /// 获取所有项目列表
-    pub async fn list_all(&self) -> Result<Vec<Project>, AppError> {
-        let rows = sqlx::query_as::<_, ProjectRow>(
+    /// 按客户端的 UTC 偏移计算"今天/昨天"
+    pub fn with_utc_offset(mut self, utc_offset: Option<i32>) -> Self {
+        self.utc_offset = utc_offset;
+        self
+    }
+
+    /// 获取所有项目列表，`status` 不为空时只返回该状态的项目
+    ///
+    /// `ProjectSort::Due` 时已逾期和一周内到期的未归档项目排在最前（按截止日期升序）。
+    pub async fn list_all(&self, sort: ProjectSort, status: Option<&str>) -> Result<Vec<Project>, AppError> {
+        let order = match sort {
+            ProjectSort::Default => "",
+            ProjectSort::Due => {
+                "CASE WHEN p.due_date IS NOT NULL AND p.status != 'archived' AND p.due_date <= date('now', 'localtime', ?) THEN 0 ELSE 1 END, \
+                 CASE WHEN p.status != 'archived' THEN p.due_date END ASC NULLS LAST,"
+            }
+        };
+        let sql = format!(
             r#"
             SELECT
-                id,
-                name,
-                description,
-                status,
-                is_pinned,
-                updated_at,
-                email_count,
-                attachment_count,
-                tags
-            FROM projects
-            ORDER BY is_pinned DESC, updated_at DESC
-            "#
-        )
-        .fetch_all(&self.pool)
-        .await?;
+                p.id,
+                p.name,
+                p.description,
+                p.status,
+                s.label AS status_label,
+                s.color AS status_color,
+                p.is_pinned,
+                p.color,
+                p.icon,
+                p.updated_at,
+                p.email_count,
+                p.attachment_count,
+                p.tags,
+                p.due_date,
+                p.due_note,
+                COALESCE(p.needs_review, 0) AS needs_review
+            FROM projects p
+            LEFT JOIN project_statuses s ON s.key = p.status
+            WHERE p.status != 'deleted' AND (? IS NULL OR p.status = ?)
+            ORDER BY {} p.is_pinned DESC, p.pin_order ASC NULLS LAST, p.updated_at DESC
+            "#,
+            order
+        );
+        let mut query = sqlx::query_as::<_, ProjectRow>(&sql).bind(status).bind(status);
+        if sort == ProjectSort::Due {
+            query = query.bind(format!("+{} days", DUE_SOON_DAYS));
+        }
+        let rows = query.fetch_all(&self.pool).await?;
 
+        let locale = Locale::load(&self.pool).await;
+        let now = Utc::now();
         let mut projects: Vec<Project> = rows
             .into_iter()
             .map(|row| Project {
@@ -42,22 +89,40 @@ impl ProjectRepository {
                 title: row.name,
                 description: row.description,
                 status: row.status,
+                status_label: row.status_label,
+                status_color: row.status_color,
                 is_pinned: row.is_pinned,
-                last_updated: row.updated_at.unwrap_or_else(|| "Unknown".to_string()),
+                color: row.color,
+                icon: row.icon,
+                last_updated_relative: row
+                    .updated_at
+                    .as_deref()
+                    .and_then(|date| relative_time(date, now, locale)),
+                last_updated: row
+                    .updated_at
+                    .unwrap_or_else(|| tr(locale, Message::Unknown).to_string()),
                 stats: ProjectStats {
                     emails: row.email_count.unwrap_or(0),
                     attachments: row.attachment_count.unwrap_or(0),
                 },
                 tags: row.tags.and_then(|s: String| serde_json::from_str(&s).ok()),
                 last_activity: None,
+                last_activity_display: None,
+                activity_bucket: None,
                 participants: None,
+                due_date: row.due_date,
+                due_note: row.due_note,
+                needs_review: row.needs_review,
+                preferences: None,
             })
             .collect();
 
         // 填充 last_activity 和 participants
+        let zone = DisplayZone::load(&self.pool, self.utc_offset).await;
         for project in &mut projects {
-            project.last_activity = self.get_last_activity(project.id).await.ok();
+            project.last_activity = self.get_last_activity(project.id, locale).await.ok();
             project.participants = self.get_participants(project.id).await.ok();
+            fill_activity_display(project, now, zone, locale);
         }
 
         Ok(projects)
@@ -68,17 +133,25 @@ impl ProjectRepository {
         let row = sqlx::query_as::<_, ProjectRow>(
             r#"
             SELECT
-                id,
-                name,
-                description,
-                status,
-                is_pinned,
-                updated_at,
-                email_count,
-                attachment_count,
-                tags
-            FROM projects
-            WHERE id = ?
+                p.id,
+                p.name,
+                p.description,
+                p.status,
+                s.label AS status_label,
+                s.color AS status_color,
+                p.is_pinned,
+                p.color,
+                p.icon,
+                p.updated_at,
+                p.email_count,
+                p.attachment_count,
+                p.tags,
+                p.due_date,
+                p.due_note,
+                COALESCE(p.needs_review, 0) AS needs_review
+            FROM projects p
+            LEFT JOIN project_statuses s ON s.key = p.status
+            WHERE p.id = ?
             "#
         )
         .bind(id)
@@ -86,77 +159,210 @@ impl ProjectRepository {
         .await?
         .ok_or(AppError::ProjectNotFound { id })?;
 
+        let locale = Locale::load(&self.pool).await;
+
         let mut project = Project {
             id: row.id,
             title: row.name,
             description: row.description,
             status: row.status,
+            status_label: row.status_label,
+            status_color: row.status_color,
             is_pinned: row.is_pinned,
-            last_updated: row.updated_at.unwrap_or_else(|| "Unknown".to_string()),
+            color: row.color,
+            icon: row.icon,
+            last_updated_relative: row
+                .updated_at
+                .as_deref()
+                .and_then(|date| relative_time(date, Utc::now(), locale)),
+            last_updated: row
+                .updated_at
+                .unwrap_or_else(|| tr(locale, Message::Unknown).to_string()),
             stats: ProjectStats {
                 emails: row.email_count.unwrap_or(0),
                 attachments: row.attachment_count.unwrap_or(0),
             },
             tags: row.tags.and_then(|s: String| serde_json::from_str(&s).ok()),
             last_activity: None,
+            last_activity_display: None,
+            activity_bucket: None,
             participants: None,
+            due_date: row.due_date,
+            due_note: row.due_note,
+            needs_review: row.needs_review,
+            preferences: None,
         };
 
-        project.last_activity = self.get_last_activity(id).await.ok();
+        project.last_activity = self.get_last_activity(id, locale).await.ok();
         project.participants = self.get_participants(id).await.ok();
+        fill_activity_display(&mut project, Utc::now(), DisplayZone::load(&self.pool, self.utc_offset).await, locale);
+        project.preferences = Some(self.get_preferences(id).await?);
 
         Ok(project)
     }
 
+    /// 获取项目视图偏好（未设置时返回默认值）
+    pub async fn get_preferences(&self, project_id: i64) -> Result<ProjectPreferences, AppError> {
+        #[derive(sqlx::FromRow)]
+        struct PreferencesRow {
+            default_tab: Option<String>,
+            sort_order: Option<String>,
+            collapsed_threads: bool,
+            custom: Option<String>,
+            extra: Option<String>,
+        }
+
+        let row = sqlx::query_as::<_, PreferencesRow>(
+            r#"
+            SELECT default_tab, sort_order, collapsed_threads, custom, extra
+            FROM project_preferences
+            WHERE project_id = ?
+            "#
+        )
+        .bind(project_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(ProjectPreferences::default());
+        };
+
+        Ok(ProjectPreferences {
+            default_tab: row.default_tab,
+            sort_order: row.sort_order,
+            collapsed_threads: row.collapsed_threads,
+            custom: row
+                .custom
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            extra: row
+                .extra
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// 保存项目视图偏好（整体替换）
+    pub async fn set_preferences(&self, project_id: i64, preferences: &ProjectPreferences) -> Result<(), AppError> {
+        preferences.validate()?;
+
+        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM projects WHERE id = ?")
+            .bind(project_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if exists.is_none() {
+            return Err(AppError::ProjectNotFound { id: project_id });
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO project_preferences (
+                project_id, default_tab, sort_order, collapsed_threads, custom, extra, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+            ON CONFLICT(project_id) DO UPDATE SET
+                default_tab = excluded.default_tab,
+                sort_order = excluded.sort_order,
+                collapsed_threads = excluded.collapsed_threads,
+                custom = excluded.custom,
+                extra = excluded.extra,
+                updated_at = excluded.updated_at
+            "#
+        )
+        .bind(project_id)
+        .bind(&preferences.default_tab)
+        .bind(&preferences.sort_order)
+        .bind(preferences.collapsed_threads)
+        .bind(serde_json::to_string(&preferences.custom)?)
+        .bind(serde_json::to_string(&preferences.extra)?)
+        .execute(&self.pool)
+        .await?;
+
+        log::info!("Saved preferences for project {}", project_id);
+        Ok(())
+    }
+
     /// 获取项目的最后活动
-    async fn get_last_activity(&self, project_id: i64) -> Result<LastActivity, AppError> {
+    async fn get_last_activity(&self, project_id: i64, locale: Locale) -> Result<LastActivity, AppError> {
         #[derive(sqlx::FromRow)]
         struct ActivityRow {
+            id: i64,
             sender: Option<String>,
             date: Option<String>,
+            body_text: Option<String>,
         }
 
         let row = sqlx::query_as::<_, ActivityRow>(
-            "SELECT sender, date FROM emails WHERE project_id = ? ORDER BY date DESC LIMIT 1"
+            "SELECT id, sender, date, body_text FROM emails WHERE project_id = ? AND server_deleted_at IS NULL ORDER BY julianday(date) DESC LIMIT 1"
         )
         .bind(project_id)
         .fetch_optional(&self.pool)
         .await?
         .ok_or(AppError::Generic("No activity found".to_string()))?;
 
+        let date = row.date.unwrap_or_default();
+        let summary = self.summarize_email(row.id, row.body_text.as_deref().unwrap_or_default(), locale).await;
         Ok(LastActivity {
             sender: row.sender.unwrap_or_default(),
-            date: row.date.unwrap_or_default(),
+            relative_date: relative_time(&date, Utc::now(), locale),
+            date,
+            summary,
         })
     }
 
     /// 获取项目参与者
+    ///
+    /// 合并过的联系人按主联系人去重，自己带 "+标签" 的地址视为同一个地址。
     async fn get_participants(&self, project_id: i64) -> Result<Vec<String>, AppError> {
         #[derive(sqlx::FromRow)]
         struct ParticipantRow {
-            sender: Option<String>,
+            sender_address: String,
+            sender_name: Option<String>,
         }
 
         let rows = sqlx::query_as::<_, ParticipantRow>(
-            "SELECT DISTINCT sender FROM emails WHERE project_id = ? ORDER BY date DESC LIMIT 5"
+            // 自动通知的发件人排在人工参与者之后；多取一些，去重后保留前 5 个
+            r#"
+            SELECT sender_address, MAX(sender_name) AS sender_name
+            FROM emails
+            WHERE project_id = ? AND sender_address IS NOT NULL AND server_deleted_at IS NULL
+            GROUP BY sender_address
+            ORDER BY MIN(is_automated) ASC, MAX(julianday(date)) DESC
+            LIMIT 50
+            "#
         )
         .bind(project_id)
         .fetch_all(&self.pool)
         .await?;
 
-        let participants: Vec<String> = rows
-            .into_iter()
-            .filter_map(|row| row.sender)
-            .map(|sender| {
-                // Extract name from "Name <email>" format
-                if let Some(start) = sender.find('<') {
-                    sender[..start].trim().to_string()
-                } else {
-                    sender
+        let aliases = contacts::canonical_addresses(&self.pool).await?;
+        let mine = recipients::my_addresses(&self.pool).await?;
+
+        let mut seen = HashSet::new();
+        let mut participants = Vec::new();
+        for row in rows {
+            let canonical = aliases.get(&row.sender_address);
+            let key = match canonical {
+                Some(contact) => contact.address.clone(),
+                None if recipients::is_my_address(&row.sender_address, &mine) => {
+                    recipients::strip_plus_tag(&row.sender_address)
                 }
-            })
-            .filter(|name| !name.is_empty())
-            .collect();
+                None => row.sender_address.clone(),
+            };
+            if !seen.insert(key) {
+                continue;
+            }
+
+            let name = canonical
+                .and_then(|contact| contact.name.clone())
+                .or(row.sender_name)
+                .unwrap_or(row.sender_address);
+            if !name.is_empty() {
+                participants.push(name);
+            }
+            if participants.len() == 5 {
+                break;
+            }
+        }
 
         Ok(participants)
     }
@@ -164,6 +370,7 @@ impl ProjectRepository {
     /// 获取项目时间线
     pub async fn get_timeline(&self, project_id: i64) -> Result<Vec<TimelineEvent>, AppError> {
         let mut events: Vec<TimelineEvent> = Vec::new();
+        let locale = Locale::load(&self.pool).await;
 
         // 1. 获取里程碑
         #[derive(sqlx::FromRow)]
@@ -175,7 +382,7 @@ impl ProjectRepository {
         }
 
         let milestones = sqlx::query_as::<_, MilestoneRow>(
-            "SELECT id, date, title, type FROM milestones WHERE project_id = ? ORDER BY date DESC"
+            "SELECT id, date, title, type FROM milestones WHERE project_id = ? ORDER BY julianday(date) DESC, id DESC"
         )
         .bind(project_id)
         .fetch_all(&self.pool)
@@ -183,7 +390,7 @@ impl ProjectRepository {
 
         for m in milestones {
             events.push(TimelineEvent::Milestone(MilestoneEvent {
-                id: format!("m{}", m.id),
+                id: timeline_id("milestone", m.id),
                 date: m.date.unwrap_or_default(),
                 title: m.title.unwrap_or_default(),
                 status: m.r#type.unwrap_or_default(),
@@ -195,42 +402,109 @@ impl ProjectRepository {
         #[derive(sqlx::FromRow)]
         struct EmailRow {
             id: i64,
+            message_id: String,
             thread_id: Option<String>,
+            in_reply_to: Option<String>,
             date: Option<String>,
             sender: Option<String>,
+            sender_address: Option<String>,
+            sender_name: Option<String>,
             body_text: Option<String>,
+            preview: Option<String>,
             subject: Option<String>,
+            lang: Option<String>,
+            duplicate_count: i64,
+            classified_by: Option<String>,
+            is_automated: Option<bool>,
+            has_attachments: bool,
         }
 
+        let show_duplicates = self.show_duplicates().await;
+
         let emails = sqlx::query_as::<_, EmailRow>(
             r#"
             SELECT
                 id,
+                message_id,
                 thread_id,
+                in_reply_to,
                 date,
                 sender,
+                sender_address,
+                sender_name,
                 body_text,
-                subject
+                preview,
+                subject,
+                lang,
+                (SELECT COUNT(*) FROM emails d WHERE d.duplicate_of = emails.id) AS duplicate_count,
+                (SELECT cl.method FROM classification_log cl
+                 WHERE cl.email_id = emails.id ORDER BY cl.id DESC LIMIT 1) AS classified_by,
+                is_automated,
+                EXISTS(SELECT 1 FROM attachments a WHERE a.email_id = emails.id) AS has_attachments
             FROM emails
-            WHERE project_id = ?
-            ORDER BY date DESC
+            WHERE project_id = ? AND (? OR duplicate_of IS NULL) AND server_deleted_at IS NULL
+            ORDER BY julianday(date) DESC, id DESC
             "#
         )
         .bind(project_id)
+        .bind(show_duplicates)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut thread_map: HashMap<String, Vec<RawEmail>> = HashMap::new();
+        // 回执显示在原邮件上，不作为单独的事件（旧的归档数据库没有回执表）
+        let mut receipts = match ReceiptStore::new(self.pool.clone()).for_project(project_id, locale).await {
+            Ok(receipts) => receipts,
+            Err(e) => {
+                log::warn!("Failed to load receipts for project {}: {}", project_id, e);
+                HashMap::new()
+            }
+        };
+        let mut invites = match CalendarStore::new(self.pool.clone()).for_project(project_id).await {
+            Ok(invites) => invites,
+            Err(e) => {
+                log::warn!("Failed to load calendar invites for project {}: {}", project_id, e);
+                HashMap::new()
+            }
+        };
+        // 旧的归档数据库没有 VIP 表
+        let vip_addresses = match VipList::new(self.pool.clone()).addresses().await {
+            Ok(addresses) => addresses,
+            Err(e) => {
+                log::warn!("Failed to load VIP senders: {}", e);
+                HashSet::new()
+            }
+        };
+
+        // 按线程 ID 有序遍历，同一时间的线程每次按相同顺序输出
+        let mut thread_map: BTreeMap<String, Vec<RawEmail>> = BTreeMap::new();
         let mut standalone_emails: Vec<RawEmail> = Vec::new();
 
         for email in emails {
+            let is_vip = email
+                .sender_address
+                .as_ref()
+                .is_some_and(|address| vip_addresses.contains(address));
             let raw_email = RawEmail {
                 id: email.id,
+                message_id: email.message_id,
                 thread_id: email.thread_id,
+                in_reply_to: email.in_reply_to,
                 date: email.date.unwrap_or_default(),
                 sender: email.sender.unwrap_or_default(),
+                sender_address: email.sender_address,
+                sender_name: email.sender_name,
                 body: email.body_text.unwrap_or_default(),
+                preview: email.preview.filter(|preview| !preview.is_empty()),
                 subject: email.subject.unwrap_or_default(),
+                lang: email.lang,
+                duplicate_count: email.duplicate_count,
+                classified_by: email.classified_by,
+                is_automated: email.is_automated.unwrap_or(false),
+                has_attachments: email.has_attachments,
+                is_vip,
+                receipts: receipts.remove(&email.id).unwrap_or_default(),
+                invite: invites.remove(&email.id),
+                reply: ReplyPosition::default(),
             };
 
             if let Some(tid) = &raw_email.thread_id {
@@ -240,64 +514,198 @@ impl ProjectRepository {
             }
         }
 
-        // 3. 转换线程
+        // 3. 转换线程：子事件按时间顺序排列，线程在外层时间线中按最新邮件排序
+        //    折叠标题的参与者、邮件数和主题在分组时一并计算，不按线程额外查询
+        let participant_names = ParticipantNames {
+            aliases: contacts::canonical_addresses(&self.pool).await?,
+            mine: recipients::my_addresses(&self.pool).await?,
+            me: tr(locale, Message::Me),
+        };
+        let classifier_config = ClassifierConfig::load(&self.pool).await;
         for (tid, mut thread_emails) in thread_map {
-            thread_emails.sort_by(|a, b| b.date.cmp(&a.date));
-            let latest_date = thread_emails[0].date.clone();
+            thread_emails.sort_by_key(|e| (parse_timestamp(&e.date), e.id));
+            let latest_date = thread_emails.last().map(|e| e.date.clone()).unwrap_or_default();
+            assign_reply_positions(&mut thread_emails);
 
+            let message_count = thread_emails.len();
+            let has_attachments = thread_emails.iter().any(|e| e.has_attachments);
+            let subject = thread_emails
+                .first()
+                .map(|e| normalize_subject(&e.subject, e.lang.as_deref(), &classifier_config));
+            let (participants, participants_overflow) = participant_names.collect(&thread_emails);
+
+            // 连续的自动通知折叠为一个事件
             let mut children = Vec::new();
+            let mut automated_run: Vec<RawEmail> = Vec::new();
             for e in thread_emails {
-                let attachments = self.get_email_attachments(e.id).await.ok();
+                if e.is_automated {
+                    automated_run.push(e);
+                    continue;
+                }
+                self.flush_automated_run(&tid, &mut automated_run, &mut children, locale).await;
+
+                let attachments = self.get_email_attachments(e.id, locale).await.ok();
+                let summary = self.summarize_email(e.id, &e.body, locale).await;
                 children.push(TimelineEvent::Email(EmailEvent {
-                    id: format!("e{}", e.id),
+                    id: timeline_id("email", e.id),
                     date: e.date,
                     sender: e.sender,
                     content: e.body,
+                    preview: e.preview,
                     subject: e.subject,
                     attachments,
+                    duplicate_count: e.duplicate_count,
+                    classified_by: e.classified_by,
+                    summary,
+                    receipts: e.receipts,
+                    depth: e.reply.depth,
+                    parent_email_id: e.reply.parent_email_id,
+                    parent_missing: e.reply.parent_missing,
+                    invite: e.invite,
+                    is_vip: e.is_vip,
                 }));
             }
+            self.flush_automated_run(&tid, &mut automated_run, &mut children, locale).await;
 
             events.push(TimelineEvent::Thread(ThreadEvent {
-                id: tid,
+                id: thread_event_id(&tid),
+                thread_id: Some(tid),
                 date: latest_date,
                 children,
+                message_count: Some(message_count),
+                participants: Some(participants),
+                participants_overflow: Some(participants_overflow),
+                has_attachments: Some(has_attachments),
+                subject,
             }));
         }
 
         // 4. 转换独立邮件
-        for e in standalone_emails {
-            let attachments = self.get_email_attachments(e.id).await.ok();
+        for mut e in standalone_emails {
+            e.reply.parent_missing = e.in_reply_to.is_some();
+            let attachments = self.get_email_attachments(e.id, locale).await.ok();
+            let summary = self.summarize_email(e.id, &e.body, locale).await;
             events.push(TimelineEvent::Email(EmailEvent {
-                id: format!("e{}", e.id),
+                id: timeline_id("email", e.id),
                 date: e.date,
                 sender: e.sender,
                 content: e.body,
+                preview: e.preview,
                 subject: e.subject,
                 attachments,
+                duplicate_count: e.duplicate_count,
+                classified_by: e.classified_by,
+                summary,
+                receipts: e.receipts,
+                depth: e.reply.depth,
+                parent_email_id: e.reply.parent_email_id,
+                parent_missing: e.reply.parent_missing,
+                invite: e.invite,
+                is_vip: e.is_vip,
             }));
         }
 
-        // 5. 按日期排序
-        events.sort_by(|a, b| {
-            let date_a = match a {
-                TimelineEvent::Milestone(m) => &m.date,
-                TimelineEvent::Email(e) => &e.date,
-                TimelineEvent::Thread(t) => &t.date,
-            };
-            let date_b = match b {
-                TimelineEvent::Milestone(m) => &m.date,
-                TimelineEvent::Email(e) => &e.date,
-                TimelineEvent::Thread(t) => &t.date,
-            };
-            date_b.cmp(date_a)
+        // 5. 按日期排序（解析后比较，RFC 3339 带时区的日期和 SQLite 格式的日期可以混排）；
+        //    同一时间按事件类型、再按数字 ID 排列，刷新后顺序不变
+        events.sort_by_cached_key(|event| {
+            (
+                std::cmp::Reverse(parse_timestamp(event.date())),
+                event.type_priority(),
+                event.id_sort_key(),
+            )
         });
 
         Ok(events)
     }
 
+    /// 把累积的连续自动通知写入线程子事件：两封及以上折叠为一个事件，单封按普通邮件显示
+    async fn flush_automated_run(
+        &self,
+        thread_id: &str,
+        run: &mut Vec<RawEmail>,
+        children: &mut Vec<TimelineEvent>,
+        locale: Locale,
+    ) {
+        match run.len() {
+            0 => {}
+            1 => {
+                let e = run.remove(0);
+                let attachments = self.get_email_attachments(e.id, locale).await.ok();
+                let summary = self.summarize_email(e.id, &e.body, locale).await;
+                children.push(TimelineEvent::Email(EmailEvent {
+                    id: timeline_id("email", e.id),
+                    date: e.date,
+                    sender: e.sender,
+                    content: e.body,
+                    preview: e.preview,
+                    subject: e.subject,
+                    attachments,
+                    duplicate_count: e.duplicate_count,
+                    classified_by: e.classified_by,
+                    summary,
+                    receipts: e.receipts,
+                    depth: e.reply.depth,
+                    parent_email_id: e.reply.parent_email_id,
+                    parent_missing: e.reply.parent_missing,
+                    invite: e.invite,
+                    is_vip: e.is_vip,
+                }));
+            }
+            count => {
+                let mut senders: Vec<String> = Vec::new();
+                for e in run.iter() {
+                    if !senders.contains(&e.sender) {
+                        senders.push(e.sender.clone());
+                    }
+                }
+                let label = match locale {
+                    Locale::En => format!("{} automated notifications", count),
+                    Locale::Zh => format!("{} 封自动通知", count),
+                };
+                children.push(TimelineEvent::Automated(AutomatedGroupEvent {
+                    id: timeline_id("automated", run[0].id),
+                    thread_id: thread_id.to_string(),
+                    date: run[0].date.clone(),
+                    count,
+                    label,
+                    senders,
+                    email_ids: run.iter().map(|e| e.id).collect(),
+                }));
+                run.clear();
+            }
+        }
+    }
+
+    /// 是否显示重复邮件（读取同步设置，默认折叠）
+    async fn show_duplicates(&self) -> bool {
+        let result: Result<(bool,), sqlx::Error> = sqlx::query_as(
+            "SELECT show_duplicates FROM sync_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|(show,)| show).unwrap_or(false)
+    }
+
+    /// 仅附件邮件的摘要（正文有实质内容时不查询附件）
+    async fn summarize_email(&self, email_id: i64, body: &str, locale: Locale) -> Option<String> {
+        if !is_short_body(body) {
+            return None;
+        }
+
+        let attachments = sqlx::query_as::<_, SummaryAttachment>(
+            "SELECT filename, mime_type, file_size FROM attachments WHERE email_id = ? ORDER BY id"
+        )
+        .bind(email_id)
+        .fetch_all(&self.pool)
+        .await
+        .ok()?;
+
+        attachment_summary(body, &attachments, locale)
+    }
+
     /// 获取邮件附件
-    async fn get_email_attachments(&self, email_id: i64) -> Result<Vec<Attachment>, AppError> {
+    async fn get_email_attachments(&self, email_id: i64, locale: Locale) -> Result<Vec<Attachment>, AppError> {
         #[derive(sqlx::FromRow)]
         struct AttachmentRow {
             filename: Option<String>,
@@ -316,7 +724,7 @@ impl ProjectRepository {
             .into_iter()
             .map(|row| {
                 let file_size = row.file_size.unwrap_or(0);
-                let size_str = format_file_size(file_size);
+                let size_str = format_file_size(file_size, locale);
 
                 Attachment {
                     name: row.filename.unwrap_or_default(),
@@ -333,6 +741,320 @@ impl ProjectRepository {
         }
     }
 
+    /// 重新计算项目统计（邮件数、附件数、最后更新时间）
+    ///
+    /// 使用分组 SQL 一次性计算所有项目，而不是逐个项目循环。
+    /// 没有邮件的项目统计归零。返回统计发生变化的项目数。
+    pub async fn recompute_stats(&self, project_id: Option<i64>) -> Result<u64, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            CREATE TEMP TABLE IF NOT EXISTS recomputed_stats (
+                project_id INTEGER PRIMARY KEY,
+                email_count INTEGER NOT NULL,
+                attachment_count INTEGER NOT NULL,
+                last_date TEXT
+            );
+            DELETE FROM recomputed_stats;
+            "#
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO recomputed_stats (project_id, email_count, attachment_count, last_date)
+            SELECT
+                p.id,
+                COALESCE(es.email_count, 0),
+                COALESCE(ats.attachment_count, 0),
+                es.last_date
+            FROM projects p
+            LEFT JOIN (
+                SELECT project_id, COUNT(*) AS email_count, MAX(datetime(date)) AS last_date
+                FROM emails
+                WHERE project_id IS NOT NULL AND duplicate_of IS NULL AND server_deleted_at IS NULL
+                GROUP BY project_id
+            ) es ON es.project_id = p.id
+            LEFT JOIN (
+                SELECT COALESCE(e.project_id, a.project_id) AS project_id, COUNT(*) AS attachment_count
+                FROM attachments a
+                LEFT JOIN emails e ON e.id = a.email_id
+                WHERE (e.project_id IS NOT NULL AND e.duplicate_of IS NULL AND e.server_deleted_at IS NULL)
+                   OR (a.email_id IS NULL AND a.project_id IS NOT NULL)
+                GROUP BY COALESCE(e.project_id, a.project_id)
+            ) ats ON ats.project_id = p.id
+            WHERE ? IS NULL OR p.id = ?
+            "#
+        )
+        .bind(project_id)
+        .bind(project_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let changed = sqlx::query(
+            r#"
+            UPDATE projects
+            SET email_count = s.email_count,
+                attachment_count = s.attachment_count
+            FROM recomputed_stats s
+            WHERE projects.id = s.project_id
+              AND (projects.email_count IS NOT s.email_count
+                   OR projects.attachment_count IS NOT s.attachment_count)
+            "#
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        sqlx::query(
+            r#"
+            UPDATE projects
+            SET updated_at = s.last_date
+            FROM recomputed_stats s
+            WHERE projects.id = s.project_id
+              AND s.last_date IS NOT NULL
+              AND projects.updated_at IS NOT s.last_date
+            "#
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM recomputed_stats")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        log::info!("Recomputed project stats ({:?}): {} projects changed", project_id, changed);
+        Ok(changed)
+    }
+
+    /// 获取完整线程（不限项目，按时间从早到晚）
+    pub async fn get_thread(&self, thread_id: &str) -> Result<ThreadView, AppError> {
+        #[derive(sqlx::FromRow)]
+        struct ThreadRow {
+            id: i64,
+            message_id: String,
+            date: Option<String>,
+            sender: Option<String>,
+            subject: Option<String>,
+            project_id: Option<i64>,
+            project_name: Option<String>,
+            is_automated: Option<bool>,
+            is_read: Option<bool>,
+            is_starred: Option<bool>,
+        }
+
+        let (bare, bracketed) = thread_id_variants(thread_id)?;
+        let rows = sqlx::query_as::<_, ThreadRow>(
+            r#"
+            SELECT
+                e.id, e.message_id, e.date, e.sender, e.subject,
+                e.project_id, p.name AS project_name, e.is_automated, e.is_read, e.is_starred
+            FROM emails e
+            LEFT JOIN projects p ON p.id = e.project_id
+            WHERE e.thread_id IN (?, ?) AND e.server_deleted_at IS NULL
+            ORDER BY datetime(e.date) ASC, e.id ASC
+            "#
+        )
+        .bind(&bare)
+        .bind(&bracketed)
+        .fetch_all(&self.pool)
+        .await?;
+
+        if rows.is_empty() {
+            return Err(AppError::Validation(format!("Thread not found: {}", thread_id)));
+        }
+
+        let locale = Locale::load(&self.pool).await;
+        let mut projects_involved: Vec<ThreadProject> = Vec::new();
+        let mut emails = Vec::with_capacity(rows.len());
+        for row in rows {
+            if let (Some(id), Some(name)) = (row.project_id, row.project_name.as_ref()) {
+                match projects_involved.iter_mut().find(|p| p.id == id) {
+                    Some(project) => project.email_count += 1,
+                    None => projects_involved.push(ThreadProject { id, name: name.clone(), email_count: 1 }),
+                }
+            }
+
+            emails.push(ThreadEmail {
+                attachments: self.get_email_attachments(row.id, locale).await.unwrap_or_default(),
+                id: row.id,
+                message_id: row.message_id,
+                date: row.date.unwrap_or_default(),
+                sender: row.sender.unwrap_or_default(),
+                subject: row.subject.unwrap_or_default(),
+                project_id: row.project_id,
+                project_name: row.project_name,
+                is_automated: row.is_automated.unwrap_or(false),
+                is_read: row.is_read.unwrap_or(false),
+                is_starred: row.is_starred.unwrap_or(false),
+            });
+        }
+        projects_involved.sort_by(|a, b| b.email_count.cmp(&a.email_count));
+
+        Ok(ThreadView {
+            thread_id: thread_id.to_string(),
+            emails,
+            projects_involved,
+        })
+    }
+
+    /// 获取线程中的邮件（按时间从早到晚），`include_automated` 为 false 时排除自动通知
+    pub async fn get_thread_emails(&self, thread_id: &str, include_automated: bool) -> Result<Vec<ThreadEmail>, AppError> {
+        let thread = self.get_thread(thread_id).await?;
+        Ok(thread
+            .emails
+            .into_iter()
+            .filter(|email| include_automated || !email.is_automated)
+            .collect())
+    }
+
+    /// 把整个线程移到指定项目（单个事务，每封被移动的邮件写入分类日志）
+    ///
+    /// 返回被移动的邮件数。
+    pub async fn move_thread_to_project(&self, thread_id: &str, project_id: i64) -> Result<u64, AppError> {
+        let (bare, bracketed) = thread_id_variants(thread_id)?;
+        let mut tx = self.pool.begin().await?;
+
+        let target: Option<String> = sqlx::query_scalar("SELECT status FROM projects WHERE id = ?")
+            .bind(project_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if matches!(target.as_deref(), None | Some("deleted")) {
+            return Err(AppError::ProjectNotFound { id: project_id });
+        }
+
+        let moving: Vec<(i64, Option<i64>)> = sqlx::query_as(
+            "SELECT id, project_id FROM emails WHERE thread_id IN (?, ?) AND project_id IS NOT ?"
+        )
+        .bind(&bare)
+        .bind(&bracketed)
+        .bind(project_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let chosen = ClassificationCandidate {
+            method: ClassificationMethod::Manual,
+            project_id,
+            matched_value: Some(thread_id.to_string()),
+            confidence: 1.0,
+        };
+        let mut affected: Vec<i64> = vec![project_id];
+        for (email_id, previous) in &moving {
+            sqlx::query("UPDATE emails SET project_id = ? WHERE id = ?")
+                .bind(project_id)
+                .bind(email_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE attachments SET project_id = ? WHERE email_id = ?")
+                .bind(project_id)
+                .bind(email_id)
+                .execute(&mut *tx)
+                .await?;
+            ClassificationLog::record_correction_on(&mut *tx, *email_id, project_id).await?;
+            ClassificationLog::record_on(&mut *tx, *email_id, &chosen, &[]).await?;
+
+            if let Some(previous) = previous {
+                if !affected.contains(previous) {
+                    affected.push(*previous);
+                }
+            }
+        }
+
+        if !moving.is_empty() {
+            let inverse = UndoOperation::Reassign {
+                assignments: moving
+                    .iter()
+                    .map(|(email_id, previous)| EmailAssignment {
+                        email_id: *email_id,
+                        previous_project_id: *previous,
+                        current_project_id: project_id,
+                    })
+                    .collect(),
+            };
+            let description = format!("Move thread {} ({} emails)", thread_id, moving.len());
+            UndoJournal::record_on(&mut *tx, "move_thread_to_project", &description, &inverse).await?;
+        }
+
+        tx.commit().await?;
+
+        for id in affected {
+            self.recompute_stats(Some(id)).await?;
+        }
+
+        log::info!("Moved {} emails of thread {} to project {}", moving.len(), thread_id, project_id);
+        Ok(moving.len() as u64)
+    }
+
+    /// 把单封邮件（及其附件）移到指定项目，记录为手动分类并写入撤销日志
+    pub async fn move_email_to_project(&self, email_id: i64, project_id: i64) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let target: Option<(String, Option<String>)> = sqlx::query_as("SELECT name, status FROM projects WHERE id = ?")
+            .bind(project_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let target_name = match target {
+            Some((name, status)) if status.as_deref() != Some("deleted") => name,
+            _ => return Err(AppError::ProjectNotFound { id: project_id }),
+        };
+
+        let row: Option<(Option<i64>, Option<String>)> = sqlx::query_as("SELECT project_id, subject FROM emails WHERE id = ?")
+            .bind(email_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let (previous, subject) = row.ok_or(AppError::EmailNotFound { id: email_id })?;
+        if previous == Some(project_id) {
+            return Ok(());
+        }
+
+        sqlx::query("UPDATE emails SET project_id = ? WHERE id = ?")
+            .bind(project_id)
+            .bind(email_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE attachments SET project_id = ? WHERE email_id = ?")
+            .bind(project_id)
+            .bind(email_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let chosen = ClassificationCandidate {
+            method: ClassificationMethod::Manual,
+            project_id,
+            matched_value: None,
+            confidence: 1.0,
+        };
+        ClassificationLog::record_correction_on(&mut *tx, email_id, project_id).await?;
+        ClassificationLog::record_on(&mut *tx, email_id, &chosen, &[]).await?;
+
+        let inverse = UndoOperation::Reassign {
+            assignments: vec![EmailAssignment {
+                email_id,
+                previous_project_id: previous,
+                current_project_id: project_id,
+            }],
+        };
+        let description = format!(
+            "Move \"{}\" to \"{}\"",
+            subject.unwrap_or_else(|| "(no subject)".to_string()),
+            target_name
+        );
+        UndoJournal::record_on(&mut *tx, "move_email_to_project", &description, &inverse).await?;
+
+        tx.commit().await?;
+
+        for id in std::iter::once(project_id).chain(previous) {
+            self.recompute_stats(Some(id)).await?;
+        }
+
+        log::info!("Moved email {} from project {:?} to {}", email_id, previous, project_id);
+        Ok(())
+    }
+
     /// 切换项目置顶状态
     pub async fn toggle_pin(&self, id: i64) -> Result<bool, AppError> {
         // 获取当前状态
@@ -345,11 +1067,20 @@ impl ProjectRepository {
 
         let new_state = !current.0;
 
-        // 更新状态
+        // 更新状态：新置顶的项目排在置顶区末尾，取消置顶清除排序值
         sqlx::query(
-            "UPDATE projects SET is_pinned = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+            r#"
+            UPDATE projects
+            SET is_pinned = ?,
+                pin_order = CASE WHEN ? THEN
+                    (SELECT COALESCE(MAX(pin_order), 0) + 1 FROM projects WHERE is_pinned = 1)
+                ELSE NULL END,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#
         )
         .bind(new_state)
+        .bind(new_state)
         .bind(id)
         .execute(&self.pool)
         .await?;
@@ -358,31 +1089,416 @@ impl ProjectRepository {
         Ok(new_state)
     }
 
-    /// 归档项目
-    pub async fn archive(&self, id: i64) -> Result<(), AppError> {
-        sqlx::query(
-            "UPDATE projects SET status = 'archived', updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+    /// 手动排序置顶项目
+    ///
+    /// `ordered_ids` 中的项目依次获得排序值 1..n，未列出的置顶项目保持原有相对顺序排在其后。
+    /// 所有 ID 必须是当前已置顶的项目。
+    pub async fn reorder_pinned(&self, ordered_ids: &[i64]) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let pinned: Vec<(i64,)> = sqlx::query_as(
+            "SELECT id FROM projects WHERE is_pinned = 1 ORDER BY pin_order ASC NULLS LAST, updated_at DESC"
         )
+        .fetch_all(&mut *tx)
+        .await?;
+        let pinned: Vec<i64> = pinned.into_iter().map(|(id,)| id).collect();
+
+        let mut seen = std::collections::HashSet::new();
+        for id in ordered_ids {
+            if !pinned.contains(id) {
+                return Err(AppError::Validation(format!("Project {} is not pinned", id)));
+            }
+            if !seen.insert(*id) {
+                return Err(AppError::Validation(format!("Project {} appears more than once", id)));
+            }
+        }
+
+        let remaining = pinned.iter().filter(|id| !seen.contains(id));
+        for (index, id) in ordered_ids.iter().chain(remaining).enumerate() {
+            sqlx::query("UPDATE projects SET pin_order = ? WHERE id = ?")
+                .bind(index as i64 + 1)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        log::info!("Reordered pinned projects: {:?}", ordered_ids);
+        Ok(())
+    }
+
+    /// 设置项目外观（颜色和图标），传入 None 表示清除
+    pub async fn set_appearance(
+        &self,
+        id: i64,
+        color: Option<String>,
+        icon: Option<String>,
+    ) -> Result<(), AppError> {
+        let color = color.as_deref().map(validate_color).transpose()?;
+        let icon = icon.as_deref().map(validate_icon).transpose()?;
+
+        let result = sqlx::query(
+            "UPDATE projects SET color = ?, icon = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+        )
+        .bind(&color)
+        .bind(&icon)
         .bind(id)
         .execute(&self.pool)
         .await?;
 
+        if result.rows_affected() == 0 {
+            return Err(AppError::ProjectNotFound { id });
+        }
+
+        log::info!("Project {} appearance set to color={:?}, icon={:?}", id, color, icon);
+        Ok(())
+    }
+
+    /// 设置或清除项目截止日期（`due_date` 为 None 时清除）
+    ///
+    /// `create_milestone` 为 true 时同步维护一个 deadline 里程碑；清除截止日期时一并删除该里程碑。
+    pub async fn set_due_date(
+        &self,
+        id: i64,
+        due_date: Option<&str>,
+        note: Option<&str>,
+        create_milestone: bool,
+    ) -> Result<(), AppError> {
+        let due_date = due_date
+            .map(str::trim)
+            .filter(|date| !date.is_empty())
+            .map(|date| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .map_err(|_| AppError::Validation(format!("Invalid due date {:?}, expected YYYY-MM-DD", date)))
+            })
+            .transpose()?;
+        let note = note.map(str::trim).filter(|note| !note.is_empty());
+
+        let mut tx = self.pool.begin().await?;
+        let row: Option<(String, Option<String>, Option<i64>)> =
+            sqlx::query_as("SELECT name, status, due_milestone_id FROM projects WHERE id = ?")
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let (name, milestone_id) = match row {
+            Some((name, status, milestone_id)) if status.as_deref() != Some("deleted") => (name, milestone_id),
+            _ => return Err(AppError::ProjectNotFound { id }),
+        };
+
+        let milestone_id = match (&due_date, milestone_id) {
+            (Some(date), Some(milestone_id)) if create_milestone => {
+                let updated = sqlx::query("UPDATE milestones SET date = ?, title = ? WHERE id = ? AND project_id = ?")
+                    .bind(date)
+                    .bind(deadline_title(&name, note))
+                    .bind(milestone_id)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+                if updated > 0 {
+                    Some(milestone_id)
+                } else {
+                    // 里程碑已被手动删除，重新创建
+                    Some(insert_deadline_milestone(&mut *tx, id, date, &deadline_title(&name, note)).await?)
+                }
+            }
+            (Some(date), None) if create_milestone => {
+                Some(insert_deadline_milestone(&mut *tx, id, date, &deadline_title(&name, note)).await?)
+            }
+            (_, Some(milestone_id)) => {
+                sqlx::query("DELETE FROM milestones WHERE id = ? AND project_id = ?")
+                    .bind(milestone_id)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                None
+            }
+            (_, None) => None,
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE projects
+            SET due_date = ?, due_note = ?, due_milestone_id = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#
+        )
+        .bind(&due_date)
+        .bind(due_date.as_ref().and(note))
+        .bind(milestone_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        log::info!("Project {} due date set to {:?}", id, due_date);
+        Ok(())
+    }
+
+    /// 已逾期或 `within_days` 天内到期的项目（不含已归档、已删除项目），按截止日期升序
+    pub async fn due_projects(&self, within_days: i64) -> Result<Vec<DueProject>, AppError> {
+        let horizon = (chrono::Local::now().date_naive() + Duration::days(within_days))
+            .format("%Y-%m-%d")
+            .to_string();
+        let projects = sqlx::query_as::<_, DueProject>(
+            r#"
+            SELECT id, name, due_date, due_note
+            FROM projects
+            WHERE due_date IS NOT NULL
+              AND status NOT IN ('archived', 'deleted')
+              AND due_date <= ?
+            ORDER BY due_date ASC, name COLLATE NOCASE
+            "#
+        )
+        .bind(horizon)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(projects)
+    }
+
+    /// 归档项目（记录撤销日志）
+    pub async fn archive(&self, id: i64) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let row: Option<(String, Option<String>)> = sqlx::query_as("SELECT name, status FROM projects WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let (name, previous) = match row {
+            Some((name, status)) if status.as_deref() != Some("deleted") => {
+                (name, status.unwrap_or_else(|| "active".to_string()))
+            }
+            _ => return Err(AppError::ProjectNotFound { id }),
+        };
+        if previous == "archived" {
+            return Ok(());
+        }
+
+        let archived = statuses::find(&mut *tx, "archived")
+            .await?
+            .ok_or_else(|| AppError::Validation("Project status archived is missing".to_string()))?;
+        statuses::change_status(&mut *tx, id, &archived).await?;
+
+        let inverse = UndoOperation::SetStatus {
+            project_id: id,
+            status: previous,
+            expected_status: "archived".to_string(),
+        };
+        UndoJournal::record_on(&mut *tx, "archive_project", &format!("Archive \"{}\"", name), &inverse).await?;
+        tx.commit().await?;
+
         log::info!("Project {} archived", id);
         Ok(())
     }
 
     /// 取消归档项目
     pub async fn unarchive(&self, id: i64) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        let status: Option<String> = sqlx::query_scalar("SELECT status FROM projects WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if status.as_deref() != Some("archived") {
+            return Ok(());
+        }
+        let active = statuses::find(&mut *tx, "active")
+            .await?
+            .ok_or_else(|| AppError::Validation("Project status active is missing".to_string()))?;
+        statuses::change_status(&mut *tx, id, &active).await?;
+        tx.commit().await?;
+
+        log::info!("Project {} unarchived", id);
+        Ok(())
+    }
+
+    /// 删除项目（移入回收站）
+    ///
+    /// 项目状态设为 `deleted` 并记录删除时间，原状态保存在 `status_before_delete` 以便恢复。
+    /// 邮件归属保持不变，超过保留期后由定时任务永久删除。
+    pub async fn soft_delete(&self, id: i64) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let row: Option<(String, Option<bool>, Option<i64>)> = sqlx::query_as(
+            "SELECT name, is_pinned, pin_order FROM projects WHERE id = ? AND status != 'deleted'"
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some((name, is_pinned, pin_order)) = row else {
+            return Err(AppError::ProjectNotFound { id });
+        };
+
         sqlx::query(
-            "UPDATE projects SET status = 'active', updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+            r#"
+            UPDATE projects
+            SET status_before_delete = status,
+                status = 'deleted',
+                deleted_at = CURRENT_TIMESTAMP,
+                is_pinned = 0,
+                pin_order = NULL
+            WHERE id = ?
+            "#
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        let inverse = UndoOperation::RestoreDeleted {
+            project_id: id,
+            is_pinned: is_pinned.unwrap_or(false),
+            pin_order,
+        };
+        UndoJournal::record_on(&mut *tx, "delete_project", &format!("Delete \"{}\"", name), &inverse).await?;
+        tx.commit().await?;
+
+        log::info!("Project {} moved to trash", id);
+        Ok(())
+    }
+
+    /// 获取因超过规模上限而待检查的项目（最近标记的在前）
+    pub async fn list_needing_review(&self) -> Result<Vec<ProjectReview>, AppError> {
+        let projects = sqlx::query_as::<_, ProjectReview>(
+            r#"
+            SELECT id, name, review_reason, review_flagged_at, COALESCE(email_count, 0) AS email_count
+            FROM projects
+            WHERE needs_review = 1 AND status != 'deleted'
+            ORDER BY review_flagged_at DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(projects)
+    }
+
+    /// 忽略待检查提醒：项目重新参与主题归类，一周内不再标记
+    pub async fn dismiss_review(&self, id: i64) -> Result<(), AppError> {
+        let result = sqlx::query(
+            "UPDATE projects SET needs_review = 0, review_dismissed_at = CURRENT_TIMESTAMP WHERE id = ?"
         )
         .bind(id)
         .execute(&self.pool)
         .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::ProjectNotFound { id });
+        }
 
-        log::info!("Project {} unarchived", id);
+        log::info!("Project {} review dismissed", id);
+        Ok(())
+    }
+
+    /// 获取回收站中的项目（最近删除的在前）
+    pub async fn list_deleted(&self) -> Result<Vec<DeletedProject>, AppError> {
+        let projects = sqlx::query_as::<_, DeletedProject>(
+            r#"
+            SELECT id, name, COALESCE(email_count, 0) AS email_count, COALESCE(deleted_at, '') AS deleted_at
+            FROM projects
+            WHERE status = 'deleted'
+            ORDER BY deleted_at DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(projects)
+    }
+
+    /// 从回收站恢复项目（恢复删除前的状态）
+    pub async fn restore(&self, id: i64) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE projects
+            SET status = COALESCE(status_before_delete, 'active'),
+                status_before_delete = NULL,
+                deleted_at = NULL,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND status = 'deleted'
+            "#
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::ProjectNotFound { id });
+        }
+
+        log::info!("Project {} restored from trash", id);
         Ok(())
     }
+
+    /// 永久删除回收站中超过保留天数的项目
+    ///
+    /// 邮件和附件解除与项目的关联（之后可被重新分类），里程碑一并删除。返回删除的项目数。
+    pub async fn purge_deleted(&self, retention_days: i64) -> Result<u64, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let expired: Vec<(i64,)> = sqlx::query_as(
+            r#"
+            SELECT id FROM projects
+            WHERE status = 'deleted'
+              AND datetime(deleted_at) <= datetime('now', '-' || ? || ' days')
+            "#
+        )
+        .bind(retention_days.max(0))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut manual_files: Vec<RemovedAttachment> = Vec::new();
+        for (id,) in &expired {
+            // 手动添加的文件只属于该项目，随项目一起删除（文件移入隔离区）
+            let files = sqlx::query_as::<_, RemovedAttachment>(&format!(
+                "SELECT {} FROM attachments WHERE project_id = ? AND email_id IS NULL AND origin = 'manual'",
+                REMOVED_ATTACHMENT_COLUMNS
+            ))
+            .bind(id)
+            .fetch_all(&mut *tx)
+            .await?;
+            manual_files.extend(files);
+            sqlx::query("DELETE FROM attachments WHERE project_id = ? AND email_id IS NULL AND origin = 'manual'")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query("UPDATE emails SET project_id = NULL WHERE project_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE attachments SET project_id = NULL WHERE project_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            // 分类日志保留（邮件仍在，用于统计分类准确率），只解除与项目的关联
+            sqlx::query("UPDATE classification_log SET project_id = NULL WHERE project_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM milestones WHERE project_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM project_preferences WHERE project_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM projects WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        AttachmentQuarantine::new(self.pool.clone()).quarantine_files(&manual_files).await;
+
+        if !expired.is_empty() {
+            log::info!("Purged {} projects deleted more than {} days ago", expired.len(), retention_days);
+        }
+        Ok(expired.len() as u64)
+    }
 }
 
 // 辅助结构体
@@ -392,29 +1508,336 @@ struct ProjectRow {
     name: String,
     description: Option<String>,
     status: String,
+    status_label: Option<String>,
+    status_color: Option<String>,
     is_pinned: bool,
+    color: Option<String>,
+    icon: Option<String>,
     updated_at: Option<String>,
     email_count: Option<i64>,
     attachment_count: Option<i64>,
     tags: Option<String>,
+    due_date: Option<String>,
+    due_note: Option<String>,
+    needs_review: bool,
 }
 
 struct RawEmail {
     id: i64,
+    message_id: String,
     thread_id: Option<String>,
+    in_reply_to: Option<String>,
     date: String,
     sender: String,
+    sender_address: Option<String>,
+    sender_name: Option<String>,
     body: String,
+    preview: Option<String>,
     subject: String,
+    lang: Option<String>,
+    duplicate_count: i64,
+    classified_by: Option<String>,
+    is_automated: bool,
+    has_attachments: bool,
+    is_vip: bool,
+    receipts: Vec<EmailReceipt>,
+    invite: Option<CalendarInvite>,
+    reply: ReplyPosition,
+}
+
+/// 线程折叠标题最多显示的参与者数
+const THREAD_PARTICIPANT_LIMIT: usize = 4;
+
+/// 线程参与者显示名的解析（合并过的联系人用主联系人的名字，自己的地址显示为"我"）
+struct ParticipantNames {
+    aliases: HashMap<String, CanonicalContact>,
+    mine: Vec<String>,
+    me: &'static str,
 }
 
-fn format_file_size(bytes: i64) -> String {
-    if bytes < 1024 {
-        format!("{} B", bytes)
-    } else if bytes < 1024 * 1024 {
-        format!("{:.1} KB", bytes as f64 / 1024.0)
-    } else {
-        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+impl ParticipantNames {
+    /// 按首次出现的顺序去重，返回前 `THREAD_PARTICIPANT_LIMIT` 个名字和超出的人数
+    fn collect(&self, emails: &[RawEmail]) -> (Vec<String>, usize) {
+        let mut seen = HashSet::new();
+        let mut names = Vec::new();
+        for e in emails {
+            let (key, name) = match e.sender_address.as_deref() {
+                Some(address) if recipients::is_my_address(address, &self.mine) => {
+                    (String::new(), self.me.to_string())
+                }
+                Some(address) => match self.aliases.get(address) {
+                    Some(contact) => (
+                        contact.address.clone(),
+                        contact.name.clone().or_else(|| e.sender_name.clone()).unwrap_or_else(|| contact.address.clone()),
+                    ),
+                    None => (
+                        address.to_string(),
+                        e.sender_name.clone().filter(|name| !name.is_empty()).unwrap_or_else(|| address.to_string()),
+                    ),
+                },
+                None => (e.sender.clone(), e.sender.clone()),
+            };
+            if name.is_empty() || !seen.insert(key) {
+                continue;
+            }
+            names.push(name);
+        }
+
+        let overflow = names.len().saturating_sub(THREAD_PARTICIPANT_LIMIT);
+        names.truncate(THREAD_PARTICIPANT_LIMIT);
+        (names, overflow)
     }
 }
 
+/// 邮件在线程中的回复位置
+#[derive(Debug, Clone, Copy, Default)]
+struct ReplyPosition {
+    depth: usize,
+    parent_email_id: Option<i64>,
+    parent_missing: bool,
+}
+
+/// 根据 In-Reply-To 计算线程中每封邮件的回复层级
+///
+/// 父邮件不在本项目中（或没有保存 In-Reply-To）的回复层级为 0，前者标记 `parent_missing`。
+fn assign_reply_positions(emails: &mut [RawEmail]) {
+    let index: HashMap<&str, usize> = emails
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (e.message_id.as_str(), i))
+        .collect();
+    let parents: Vec<Option<usize>> = emails
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            e.in_reply_to
+                .as_deref()
+                .and_then(|parent| index.get(parent).copied())
+                .filter(|parent| *parent != i)
+        })
+        .collect();
+
+    let positions: Vec<ReplyPosition> = (0..emails.len())
+        .map(|i| {
+            // 沿父链向上计数，链长超过邮件数说明有环
+            let mut depth = 0;
+            let mut current = i;
+            while let Some(parent) = parents[current] {
+                depth += 1;
+                current = parent;
+                if depth > emails.len() {
+                    depth = 0;
+                    break;
+                }
+            }
+            ReplyPosition {
+                depth,
+                parent_email_id: parents[i].map(|parent| emails[parent].id),
+                parent_missing: parents[i].is_none() && emails[i].in_reply_to.is_some(),
+            }
+        })
+        .collect();
+
+    for (email, position) in emails.iter_mut().zip(positions) {
+        email.reply = position;
+    }
+}
+
+/// 最近活动的显示文本和时间段（没有邮件时按项目更新时间）
+fn fill_activity_display(project: &mut Project, now: chrono::DateTime<Utc>, zone: DisplayZone, locale: Locale) {
+    let date = project
+        .last_activity
+        .as_ref()
+        .map(|activity| activity.date.as_str())
+        .unwrap_or(&project.last_updated);
+    if let Some((display, bucket)) = activity_display(date, now, zone, locale) {
+        project.last_activity_display = Some(display);
+        project.activity_bucket = Some(bucket);
+    }
+}
+
+/// 一周内到期视为即将到期
+pub const DUE_SOON_DAYS: i64 = 7;
+
+fn deadline_title(project_name: &str, note: Option<&str>) -> String {
+    match note {
+        Some(note) => format!("Deadline: {} ({})", project_name, note),
+        None => format!("Deadline: {}", project_name),
+    }
+}
+
+async fn insert_deadline_milestone(
+    conn: &mut SqliteConnection,
+    project_id: i64,
+    date: &str,
+    title: &str,
+) -> Result<i64, AppError> {
+    let id = sqlx::query("INSERT INTO milestones (project_id, type, title, date) VALUES (?, 'deadline', ?, ?)")
+        .bind(project_id)
+        .bind(title)
+        .bind(date)
+        .execute(conn)
+        .await?
+        .last_insert_rowid();
+    Ok(id)
+}
+
+/// 线程 ID 的两种存储形式（不带 / 带尖括号），原始 Message-ID 可能以任一形式保存
+pub(crate) fn thread_id_variants(thread_id: &str) -> Result<(String, String), AppError> {
+    let bare = thread_id.trim().trim_start_matches('<').trim_end_matches('>').trim();
+    if bare.is_empty() {
+        return Err(AppError::Validation("Thread id must not be empty".to_string()));
+    }
+    Ok((bare.to_string(), format!("<{}>", bare)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::fixtures::{
+        insert_account, insert_email, insert_project, NewEmail,
+    };
+    use crate::storage::database::test_pool;
+
+    #[tokio::test]
+    async fn timeline_order_is_numeric_and_stable() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = Some(insert_account(&pool, "me@example.com").await);
+        let project_id = insert_project(&pool, "Timeline").await;
+
+        // 同一时间的独立邮件、线程和里程碑，ID 的字符串顺序和数值顺序不同
+        let date = "2024-03-01 09:00:00";
+        let emails = [
+            (10, None),
+            (9, None),
+            (100, None),
+            (2, None),
+            (20, Some("<t1@example.com>")),
+            (21, Some("<t1@example.com>")),
+            (30, Some("<t2@example.com>")),
+            (31, Some("<t2@example.com>")),
+        ];
+        for (id, thread_id) in emails {
+            let message_id = format!("<{}@example.com>", id);
+            let email = NewEmail {
+                id: Some(id),
+                account_id,
+                message_id: &message_id,
+                thread_id,
+                project_id: Some(project_id),
+                date: Some(date),
+                body_text: Some("Body"),
+                ..Default::default()
+            };
+            insert_email(&pool, email).await;
+        }
+        for id in [11, 3] {
+            sqlx::query("INSERT INTO milestones (id, project_id, type, title, date) VALUES (?, ?, 'note', 'Milestone', ?)")
+                .bind(id)
+                .bind(project_id)
+                .bind(date)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let repo = ProjectRepository::new(pool);
+        let first = repo.get_timeline(project_id).await.unwrap();
+        let second = repo.get_timeline(project_id).await.unwrap();
+        assert_eq!(
+            serde_json::to_string(&first).unwrap(),
+            serde_json::to_string(&second).unwrap(),
+        );
+
+        let ids: Vec<&str> = first.iter().map(|event| event.id()).collect();
+        let thread_ids: Vec<&str> = ids.iter().copied().filter(|id| id.starts_with("thread:")).collect();
+        let mut sorted_threads = thread_ids.clone();
+        sorted_threads.sort_unstable();
+        assert_eq!(thread_ids, sorted_threads);
+        assert_eq!(ids.len(), 8);
+        assert_eq!(&ids[..2], ["milestone:3", "milestone:11"]);
+        assert_eq!(&ids[4..], ["email:2", "email:9", "email:10", "email:100"]);
+    }
+
+    #[tokio::test]
+    async fn purge_deleted_keeps_the_classification_log_of_its_emails() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = Some(insert_account(&pool, "me@example.com").await);
+        let project_id = insert_project(&pool, "Trash").await;
+        let email = NewEmail {
+            account_id,
+            message_id: "<a@example.com>",
+            project_id: Some(project_id),
+            ..Default::default()
+        };
+        let email_id = insert_email(&pool, email).await;
+        sqlx::query("INSERT INTO classification_log (email_id, project_id, method) VALUES (?, ?, 'subject')")
+            .bind(email_id)
+            .bind(project_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE projects SET status = 'deleted', deleted_at = datetime('now', '-60 days') WHERE id = ?")
+            .bind(project_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let repo = ProjectRepository::new(pool.clone());
+        assert_eq!(repo.purge_deleted(30).await.unwrap(), 1);
+
+        let projects: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(projects, 0);
+        let logged: Vec<(i64, Option<i64>)> = sqlx::query_as("SELECT email_id, project_id FROM classification_log")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(logged, vec![(email_id, None)]);
+    }
+
+    #[tokio::test]
+    async fn mixed_date_formats_are_ordered_by_instant() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = Some(insert_account(&pool, "me@example.com").await);
+        let project_id = insert_project(&pool, "Dates").await;
+
+        // 按字符串比较时 "…T08:00:00+08:00"（UTC 00:00）排在 "… 05:00:00"（UTC 05:00）之后
+        let emails = [
+            (1, "2024-03-01T08:00:00+08:00", Some("<t@example.com>"), "Early"),
+            (2, "2024-03-01 05:00:00", Some("<t@example.com>"), "Late"),
+            (3, "2024-03-01T03:00:00+01:00", None, "Middle"),
+        ];
+        for (id, date, thread_id, sender) in emails {
+            let message_id = format!("<{}@example.com>", id);
+            let email = NewEmail {
+                id: Some(id),
+                account_id,
+                message_id: &message_id,
+                thread_id,
+                project_id: Some(project_id),
+                date: Some(date),
+                sender: Some(sender),
+                body_text: Some("Body"),
+                ..Default::default()
+            };
+            insert_email(&pool, email).await;
+        }
+
+        let repo = ProjectRepository::new(pool);
+        let activity = repo.get_last_activity(project_id, Locale::En).await.unwrap();
+        assert_eq!(activity.sender, "Late");
+
+        let timeline = repo.get_timeline(project_id).await.unwrap();
+        assert_eq!(timeline.len(), 2);
+        let TimelineEvent::Thread(thread) = &timeline[0] else {
+            panic!("expected the thread first, got {}", timeline[0].id());
+        };
+        assert_eq!(thread.date, "2024-03-01 05:00:00");
+        let children: Vec<&str> = thread.children.iter().map(|event| event.id()).collect();
+        assert_eq!(children, ["email:1", "email:2"]);
+        assert_eq!(timeline[1].id(), "email:3");
+    }
+}
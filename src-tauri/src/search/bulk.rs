@@ -0,0 +1,404 @@
+/// 对搜索结果批量操作
+///
+/// 使用与 `search_query` 相同的搜索条件，但不分页：先取出全部匹配的邮件 ID，再按批在事务中执行。
+/// 匹配数超过设置中的上限时需要 `confirm_over_cap`。已经处于目标状态的邮件会跳过，重复执行不会产生新的修改；
+/// 整个操作只发送一条汇总通知。
+use crate::error::AppError;
+use crate::events::notifications::SOURCE_BULK_ACTION;
+use crate::events::{EventEmitter, NotificationLevel};
+use crate::project::appearance::palette_color_for;
+use crate::project::classification_log::{ClassificationCandidate, ClassificationLog, ClassificationMethod};
+use crate::project::undo::{EmailAssignment, UndoJournal, UndoOperation};
+use crate::repository::ProjectRepository;
+use crate::search::query::search_plan;
+use crate::utils::validation;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{HashMap, HashSet};
+
+/// 每个事务处理的邮件数
+const BATCH_SIZE: usize = 100;
+
+/// 结果中最多列出的失败邮件数
+const MAX_REPORTED_FAILURES: usize = 100;
+
+/// 批量操作请求（搜索条件与 `search_query` 相同）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkSearchRequest {
+    pub query: String,
+    #[serde(default)]
+    pub lang: Option<String>,
+    /// 匹配数超过上限时仍然执行
+    #[serde(default)]
+    pub confirm_over_cap: bool,
+}
+
+/// 批量操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BulkSearchAction {
+    /// 移到已有项目
+    AssignProject { project_id: i64 },
+    /// 移到新项目（同名的未删除项目已存在时直接使用，重复执行不会再建）
+    CreateProject { name: String },
+    Star,
+    MarkRead,
+}
+
+impl BulkSearchAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BulkSearchAction::AssignProject { .. } => "assign_project",
+            BulkSearchAction::CreateProject { .. } => "create_project",
+            BulkSearchAction::Star => "star",
+            BulkSearchAction::MarkRead => "mark_read",
+        }
+    }
+}
+
+/// 未能处理的邮件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkFailure {
+    pub email_id: i64,
+    pub error: String,
+}
+
+/// 批量操作结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkSearchSummary {
+    pub action: String,
+    /// 匹配的邮件数
+    pub matched: usize,
+    /// 本次修改的邮件数
+    pub updated: usize,
+    /// 已经处于目标状态、没有修改的邮件数
+    pub unchanged: usize,
+    /// 失败的邮件数（`failures` 最多列出 `MAX_REPORTED_FAILURES` 条）
+    pub failed: usize,
+    pub failures: Vec<BulkFailure>,
+    /// 目标项目（移到项目时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<i64>,
+    /// 是否新建了项目
+    pub project_created: bool,
+}
+
+impl BulkSearchSummary {
+    fn fail(&mut self, email_id: i64, error: String) {
+        self.failed += 1;
+        if self.failures.len() < MAX_REPORTED_FAILURES {
+            self.failures.push(BulkFailure { email_id, error });
+        }
+    }
+}
+
+/// 解析后的操作（项目已确定）
+enum Resolved {
+    Move { project_id: i64, project_name: String },
+    Star,
+    MarkRead,
+}
+
+/// 搜索结果批量操作执行器
+pub struct SearchBulkApplier {
+    pool: SqlitePool,
+    event_emitter: Option<EventEmitter>,
+}
+
+impl SearchBulkApplier {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            event_emitter: None,
+        }
+    }
+
+    pub fn with_event_emitter(pool: SqlitePool, emitter: EventEmitter) -> Self {
+        Self {
+            pool,
+            event_emitter: Some(emitter),
+        }
+    }
+
+    /// 对搜索结果执行 `action`
+    pub async fn apply(&self, request: &BulkSearchRequest, action: &BulkSearchAction) -> Result<BulkSearchSummary, AppError> {
+        validation::max_length("query", &request.query, validation::MAX_QUERY_CHARS)?;
+        if let BulkSearchAction::CreateProject { name } = action {
+            if name.trim().is_empty() {
+                return Err(AppError::Validation("Project name is required".to_string()));
+            }
+            validation::max_length("name", name, validation::MAX_NAME_CHARS)?;
+        }
+
+        let email_ids = self.matching_ids(request).await?;
+        let cap: i64 = sqlx::query_scalar("SELECT bulk_action_cap FROM sync_settings WHERE id = 1")
+            .fetch_one(&self.pool)
+            .await?;
+        let cap = cap.max(1) as usize;
+        if email_ids.len() > cap && !request.confirm_over_cap {
+            return Err(AppError::BulkCapExceeded {
+                matched: email_ids.len(),
+                cap,
+            });
+        }
+
+        let mut summary = BulkSearchSummary {
+            action: action.as_str().to_string(),
+            matched: email_ids.len(),
+            ..Default::default()
+        };
+        if email_ids.is_empty() {
+            return Ok(summary);
+        }
+
+        let resolved = match action {
+            BulkSearchAction::AssignProject { project_id } => {
+                let name: Option<(String, Option<String>)> = sqlx::query_as("SELECT name, status FROM projects WHERE id = ?")
+                    .bind(project_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                match name {
+                    Some((project_name, status)) if status.as_deref() != Some("deleted") => Resolved::Move {
+                        project_id: *project_id,
+                        project_name,
+                    },
+                    _ => return Err(AppError::ProjectNotFound { id: *project_id }),
+                }
+            }
+            BulkSearchAction::CreateProject { name } => {
+                let (project_id, created) = self.find_or_create_project(name.trim()).await?;
+                summary.project_created = created;
+                Resolved::Move {
+                    project_id,
+                    project_name: name.trim().to_string(),
+                }
+            }
+            BulkSearchAction::Star => Resolved::Star,
+            BulkSearchAction::MarkRead => Resolved::MarkRead,
+        };
+        if let Resolved::Move { project_id, .. } = &resolved {
+            summary.project_id = Some(*project_id);
+        }
+
+        let mut affected_projects: HashSet<i64> = HashSet::new();
+        for batch in email_ids.chunks(BATCH_SIZE) {
+            match self.apply_batch(batch, &resolved).await {
+                Ok(outcome) => {
+                    summary.updated += outcome.updated.len();
+                    summary.unchanged += outcome.unchanged;
+                    for email_id in outcome.missing {
+                        summary.fail(email_id, format!("Email with id {} not found", email_id));
+                    }
+                    if matches!(resolved, Resolved::Move { .. }) {
+                        affected_projects.extend(outcome.updated.iter().filter_map(|(_, previous)| *previous));
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Bulk {} failed for a batch of {} emails: {}", summary.action, batch.len(), e);
+                    for email_id in batch {
+                        summary.fail(*email_id, e.to_string());
+                    }
+                }
+            }
+        }
+
+        if summary.updated > 0 {
+            if let Some(project_id) = summary.project_id {
+                affected_projects.insert(project_id);
+            }
+            let repo = ProjectRepository::new(self.pool.clone());
+            for project_id in affected_projects {
+                repo.recompute_stats(Some(project_id)).await?;
+            }
+        }
+
+        log::info!(
+            "Bulk {} on search {:?}: {} matched, {} updated, {} unchanged, {} failed",
+            summary.action, request.query, summary.matched, summary.updated, summary.unchanged, summary.failed
+        );
+        self.notify(&resolved, &summary);
+        Ok(summary)
+    }
+
+    /// 重新搜索（不分页），按搜索结果的顺序返回邮件 ID
+    async fn matching_ids(&self, request: &BulkSearchRequest) -> Result<Vec<i64>, AppError> {
+        let lang = request.lang.as_deref().map(str::trim).filter(|lang| !lang.is_empty());
+        let Some(plan) = search_plan(&self.pool, &request.query, lang).await? else {
+            return Ok(Vec::new());
+        };
+
+        let sql = format!("SELECT e.id {} {} {}", plan.from, plan.filter, plan.order);
+        let mut query = sqlx::query_scalar::<_, i64>(&sql);
+        for value in &plan.binds {
+            query = query.bind(value);
+        }
+        Ok(query.fetch_all(&self.pool).await?)
+    }
+
+    /// 查找同名的未删除项目，不存在时新建；返回项目 ID 和是否新建
+    async fn find_or_create_project(&self, name: &str) -> Result<(i64, bool), AppError> {
+        let mut tx = self.pool.begin().await?;
+        let existing: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM projects WHERE name = ? AND status != 'deleted' ORDER BY id LIMIT 1"
+        )
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(id) = existing {
+            return Ok((id, false));
+        }
+
+        let id = sqlx::query(
+            r#"
+            INSERT INTO projects (name, status, color, email_count, attachment_count, created_at, updated_at)
+            VALUES (?, 'active', ?, 0, 0, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            "#
+        )
+        .bind(name)
+        .bind(palette_color_for(name))
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+        tx.commit().await?;
+
+        log::info!("Created project {} ({:?}) for bulk action", id, name);
+        Ok((id, true))
+    }
+
+    /// 在一个事务中处理一批邮件（失败时整批回滚）
+    async fn apply_batch(&self, batch: &[i64], resolved: &Resolved) -> Result<BatchOutcome, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let rows = load_rows(&mut *tx, batch).await?;
+
+        let mut outcome = BatchOutcome::default();
+        for email_id in batch {
+            match rows.get(email_id) {
+                None => outcome.missing.push(*email_id),
+                Some(row) if row.already_applied(resolved) => outcome.unchanged += 1,
+                Some(row) => outcome.updated.push((*email_id, row.project_id)),
+            }
+        }
+        let pending = &outcome.updated;
+
+        match resolved {
+            Resolved::Move { project_id, project_name } => {
+                let chosen = ClassificationCandidate {
+                    method: ClassificationMethod::Manual,
+                    project_id: *project_id,
+                    matched_value: None,
+                    confidence: 1.0,
+                };
+                for (email_id, _) in pending {
+                    sqlx::query("UPDATE emails SET project_id = ? WHERE id = ?")
+                        .bind(project_id)
+                        .bind(email_id)
+                        .execute(&mut *tx)
+                        .await?;
+                    sqlx::query("UPDATE attachments SET project_id = ? WHERE email_id = ?")
+                        .bind(project_id)
+                        .bind(email_id)
+                        .execute(&mut *tx)
+                        .await?;
+                    ClassificationLog::record_correction_on(&mut *tx, *email_id, *project_id).await?;
+                    ClassificationLog::record_on(&mut *tx, *email_id, &chosen, &[]).await?;
+                }
+
+                if !pending.is_empty() {
+                    let inverse = UndoOperation::Reassign {
+                        assignments: pending
+                            .iter()
+                            .map(|(email_id, previous)| EmailAssignment {
+                                email_id: *email_id,
+                                previous_project_id: *previous,
+                                current_project_id: *project_id,
+                            })
+                            .collect(),
+                    };
+                    let description = format!("Move {} search results to \"{}\"", pending.len(), project_name);
+                    UndoJournal::record_on(&mut *tx, "bulk_apply_to_search", &description, &inverse).await?;
+                }
+            }
+            Resolved::Star | Resolved::MarkRead => {
+                let column = if matches!(resolved, Resolved::Star) { "is_starred" } else { "is_read" };
+                for (email_id, _) in pending {
+                    sqlx::query(&format!("UPDATE emails SET {column} = 1 WHERE id = ?"))
+                        .bind(email_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+            }
+        }
+
+        tx.commit().await?;
+        Ok(outcome)
+    }
+
+    /// 发送一条汇总通知
+    fn notify(&self, resolved: &Resolved, summary: &BulkSearchSummary) {
+        let Some(emitter) = &self.event_emitter else { return };
+
+        let done = match resolved {
+            Resolved::Move { project_name, .. } => format!("{} emails moved to \"{}\"", summary.updated, project_name),
+            Resolved::Star => format!("{} emails starred", summary.updated),
+            Resolved::MarkRead => format!("{} emails marked as read", summary.updated),
+        };
+        let mut message = done;
+        if summary.unchanged > 0 {
+            message.push_str(&format!(", {} already done", summary.unchanged));
+        }
+        if summary.failed > 0 {
+            message.push_str(&format!(", {} failed", summary.failed));
+        }
+        let level = if summary.failed > 0 { NotificationLevel::Warning } else { NotificationLevel::Success };
+        let related = summary.project_id.map(|id| format!("project:{}", id));
+        emitter.emit_notification_from(
+            "Bulk action finished",
+            &message,
+            level,
+            Some(SOURCE_BULK_ACTION),
+            related.as_deref(),
+        );
+    }
+}
+
+/// 一批邮件的处理结果
+#[derive(Default)]
+struct BatchOutcome {
+    /// 本次修改的邮件及其原项目
+    updated: Vec<(i64, Option<i64>)>,
+    unchanged: usize,
+    missing: Vec<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct BulkRow {
+    id: i64,
+    project_id: Option<i64>,
+    is_read: bool,
+    is_starred: bool,
+}
+
+impl BulkRow {
+    fn already_applied(&self, resolved: &Resolved) -> bool {
+        match resolved {
+            Resolved::Move { project_id, .. } => self.project_id == Some(*project_id),
+            Resolved::Star => self.is_starred,
+            Resolved::MarkRead => self.is_read,
+        }
+    }
+}
+
+/// 读取一批邮件的当前状态
+async fn load_rows(conn: &mut SqliteConnection, batch: &[i64]) -> Result<HashMap<i64, BulkRow>, AppError> {
+    let placeholders = vec!["?"; batch.len()].join(", ");
+    let sql = format!(
+        "SELECT id, project_id, is_read, is_starred FROM emails WHERE id IN ({})",
+        placeholders
+    );
+    let mut query = sqlx::query_as::<_, BulkRow>(&sql);
+    for email_id in batch {
+        query = query.bind(email_id);
+    }
+    let rows = query.fetch_all(&mut *conn).await?;
+    Ok(rows.into_iter().map(|row| (row.id, row)).collect())
+}
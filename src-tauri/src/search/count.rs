@@ -0,0 +1,124 @@
+/// 搜索结果数量预览（输入时显示"约 37 条结果"）
+///
+/// 使用与 `search_query` 相同的搜索条件，但只计数：不生成摘要、不排序。
+/// 结果按规范化后的请求缓存几秒，连续输入时重复的查询不再访问数据库。
+/// 计数超过时间预算时改用带上限的抽样计数，并标记为估计值。
+use crate::error::AppError;
+use crate::search::query::{search_plan, SearchPlan};
+use crate::storage::archive::DataSource;
+use crate::storage::cache::TtlCache;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+/// 查询短于该字符数时不计数
+pub const MIN_QUERY_CHARS: usize = 2;
+
+/// 完整计数的时间预算
+const COUNT_BUDGET: Duration = Duration::from_millis(100);
+
+/// 超出预算时抽样计数的上限
+const ESTIMATE_CAP: i64 = 1000;
+
+/// 缓存有效期
+const CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// 缓存的查询数量
+const CACHE_CAPACITY: usize = 64;
+
+/// 计数请求（搜索条件与 `search_query` 相同）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchCountRequest {
+    pub query: String,
+    #[serde(default)]
+    pub lang: Option<String>,
+    #[serde(default)]
+    pub source: Option<DataSource>,
+}
+
+/// 各类结果的数量
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SearchCount {
+    pub emails: i64,
+    /// 命中邮件所属的会话数
+    pub threads: i64,
+    /// 命中邮件所属的项目数
+    pub projects: i64,
+    /// 计数超出时间预算，数量为抽样得到的下限
+    #[sqlx(skip)]
+    pub estimated: bool,
+}
+
+/// 计数器（注册为全局状态，持有结果缓存）
+pub struct SearchCounter {
+    cache: TtlCache<(DataSource, Option<String>, String), SearchCount>,
+}
+
+impl Default for SearchCounter {
+    fn default() -> Self {
+        Self {
+            cache: TtlCache::new(CACHE_TTL, CACHE_CAPACITY),
+        }
+    }
+}
+
+impl SearchCounter {
+    /// 统计搜索结果数量；查询短于 `MIN_QUERY_CHARS` 时返回 None
+    pub async fn count(&self, pool: &SqlitePool, request: &SearchCountRequest) -> Result<Option<SearchCount>, AppError> {
+        let query = request.query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        if query.chars().count() < MIN_QUERY_CHARS {
+            return Ok(None);
+        }
+        let lang = request
+            .lang
+            .as_deref()
+            .map(str::trim)
+            .filter(|lang| !lang.is_empty())
+            .map(str::to_string);
+
+        let key = (request.source.unwrap_or_default(), lang.clone(), query.clone());
+        if let Some(count) = self.cache.get(&key) {
+            return Ok(Some(count));
+        }
+
+        let Some(plan) = search_plan(pool, &query, lang.as_deref()).await? else {
+            return Ok(None);
+        };
+        let count = match tokio::time::timeout(COUNT_BUDGET, count_matches(pool, &plan, None)).await {
+            Ok(count) => count?,
+            Err(_) => {
+                log::debug!("Search count for {:?} exceeded {} ms, estimating", query, COUNT_BUDGET.as_millis());
+                SearchCount {
+                    estimated: true,
+                    ..count_matches(pool, &plan, Some(ESTIMATE_CAP)).await?
+                }
+            }
+        };
+
+        self.cache.insert(key, count.clone());
+        Ok(Some(count))
+    }
+}
+
+/// 计数；`cap` 限制参与统计的邮件数
+async fn count_matches(pool: &SqlitePool, plan: &SearchPlan, cap: Option<i64>) -> Result<SearchCount, AppError> {
+    let limit = if cap.is_some() { "LIMIT ?" } else { "" };
+    let sql = format!(
+        r#"
+        SELECT COUNT(*) AS emails,
+               COUNT(DISTINCT COALESCE(thread_id, message_id)) AS threads,
+               COUNT(DISTINCT project_id) AS projects
+        FROM (SELECT e.thread_id, e.message_id, e.project_id {} {} {})
+        "#,
+        plan.from, plan.filter, limit
+    );
+    let mut query = sqlx::query_as::<_, SearchCount>(&sql);
+    for value in &plan.binds {
+        query = query.bind(value);
+    }
+    if let Some(cap) = cap {
+        query = query.bind(cap);
+    }
+
+    Ok(query.fetch_one(pool).await?)
+}
@@ -1 +1,341 @@
-pub fn hello() {}
+/// SQLite FTS5 全文索引
+///
+/// `emails_fts` / `attachments_fts` 通过触发器与源表保持同步，新同步的邮件立即可搜索。
+/// 中日韩邮件（按 `emails.lang`）另外写入 trigram 分词的 `emails_cjk_fts`，支持汉字子串匹配。
+/// 索引损坏或更换分词器时可以重建：先重新创建表和触发器（之后的写入由触发器捕获），
+/// 再分批回填已有数据。
+use crate::error::AppError;
+use crate::events::{EventEmitter, IndexProgressEvent, IndexStatus};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 分词器
+const FTS_TOKENIZER: &str = "unicode61 remove_diacritics 2";
+
+/// 中日韩邮件的分词器（unicode61 不切分汉字，整句只能作为一个词匹配）
+const CJK_FTS_TOKENIZER: &str = "trigram";
+
+/// 进入 trigram 索引的语言（与 `language::is_cjk` 一致）
+const CJK_LANGS_SQL: &str = "('zh', 'ja', 'ko')";
+
+/// 重建时每批回填的行数
+const REBUILD_BATCH_SIZE: i64 = 500;
+
+/// 索引状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchIndexStatus {
+    pub email_documents: i64,
+    pub email_rows: i64,
+    pub attachment_documents: i64,
+    pub attachment_rows: i64,
+    pub last_rebuild_at: Option<String>,
+    /// 文档数与源表行数一致
+    pub in_sync: bool,
+}
+
+/// 创建 FTS 表和同步触发器（已存在时跳过）
+///
+/// 首次创建时（旧数据库升级）用已有数据填充索引。
+pub async fn ensure_schema(pool: &SqlitePool) -> Result<(), AppError> {
+    let existed: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'emails_fts'"
+    )
+    .fetch_one(pool)
+    .await?;
+    let cjk_existed: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'emails_cjk_fts'"
+    )
+    .fetch_one(pool)
+    .await?;
+
+    create_schema(pool).await?;
+
+    if existed == 0 {
+        log::info!("Populating new search index");
+        sqlx::query(
+            r#"
+            INSERT INTO emails_fts(rowid, subject, sender, body_text)
+            SELECT id, subject, sender, body_text FROM emails;
+            INSERT INTO attachments_fts(rowid, filename)
+            SELECT id, filename FROM attachments;
+            "#
+        )
+        .execute(pool)
+        .await?;
+    }
+    if cjk_existed == 0 {
+        populate_cjk(pool).await?;
+    }
+
+    Ok(())
+}
+
+/// 用已识别为中日韩语言的邮件填充 trigram 索引
+async fn populate_cjk(pool: &SqlitePool) -> Result<(), AppError> {
+    sqlx::query(&format!(
+        r#"
+        INSERT INTO emails_cjk_fts(rowid, subject, sender, body_text)
+        SELECT id, subject, sender, body_text FROM emails
+        WHERE lang IN {} AND id NOT IN (SELECT rowid FROM emails_cjk_fts)
+        "#,
+        CJK_LANGS_SQL
+    ))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn create_schema(pool: &SqlitePool) -> Result<(), AppError> {
+    let sql = format!(
+        r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS emails_fts USING fts5(
+            subject, sender, body_text, tokenize = '{tokenizer}'
+        );
+        CREATE VIRTUAL TABLE IF NOT EXISTS attachments_fts USING fts5(
+            filename, tokenize = '{tokenizer}'
+        );
+        CREATE VIRTUAL TABLE IF NOT EXISTS emails_cjk_fts USING fts5(
+            subject, sender, body_text, tokenize = '{cjk_tokenizer}'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS emails_fts_insert AFTER INSERT ON emails BEGIN
+            INSERT INTO emails_fts(rowid, subject, sender, body_text)
+            VALUES (new.id, new.subject, new.sender, new.body_text);
+        END;
+        CREATE TRIGGER IF NOT EXISTS emails_fts_delete AFTER DELETE ON emails BEGIN
+            DELETE FROM emails_fts WHERE rowid = old.id;
+        END;
+        CREATE TRIGGER IF NOT EXISTS emails_fts_update
+        AFTER UPDATE OF subject, sender, body_text ON emails BEGIN
+            DELETE FROM emails_fts WHERE rowid = old.id;
+            INSERT INTO emails_fts(rowid, subject, sender, body_text)
+            VALUES (new.id, new.subject, new.sender, new.body_text);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS emails_cjk_fts_insert AFTER INSERT ON emails
+        WHEN new.lang IN {cjk_langs} BEGIN
+            INSERT INTO emails_cjk_fts(rowid, subject, sender, body_text)
+            VALUES (new.id, new.subject, new.sender, new.body_text);
+        END;
+        CREATE TRIGGER IF NOT EXISTS emails_cjk_fts_delete AFTER DELETE ON emails BEGIN
+            DELETE FROM emails_cjk_fts WHERE rowid = old.id;
+        END;
+        CREATE TRIGGER IF NOT EXISTS emails_cjk_fts_update
+        AFTER UPDATE OF subject, sender, body_text, lang ON emails BEGIN
+            DELETE FROM emails_cjk_fts WHERE rowid = old.id;
+            INSERT INTO emails_cjk_fts(rowid, subject, sender, body_text)
+            SELECT new.id, new.subject, new.sender, new.body_text WHERE new.lang IN {cjk_langs};
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS attachments_fts_insert AFTER INSERT ON attachments BEGIN
+            INSERT INTO attachments_fts(rowid, filename) VALUES (new.id, new.filename);
+        END;
+        CREATE TRIGGER IF NOT EXISTS attachments_fts_delete AFTER DELETE ON attachments BEGIN
+            DELETE FROM attachments_fts WHERE rowid = old.id;
+        END;
+        CREATE TRIGGER IF NOT EXISTS attachments_fts_update AFTER UPDATE OF filename ON attachments BEGIN
+            DELETE FROM attachments_fts WHERE rowid = old.id;
+            INSERT INTO attachments_fts(rowid, filename) VALUES (new.id, new.filename);
+        END;
+
+        CREATE TABLE IF NOT EXISTS search_index_meta (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            tokenizer TEXT,
+            last_rebuild_at DATETIME
+        );
+        INSERT OR IGNORE INTO search_index_meta (id, tokenizer) VALUES (1, '{tokenizer}');
+        "#,
+        tokenizer = FTS_TOKENIZER,
+        cjk_tokenizer = CJK_FTS_TOKENIZER,
+        cjk_langs = CJK_LANGS_SQL
+    );
+
+    sqlx::query(&sql).execute(pool).await?;
+    Ok(())
+}
+
+/// 全文索引维护
+pub struct SearchIndexer {
+    pool: SqlitePool,
+    event_emitter: Option<EventEmitter>,
+}
+
+impl SearchIndexer {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            event_emitter: None,
+        }
+    }
+
+    pub fn with_event_emitter(pool: SqlitePool, emitter: EventEmitter) -> Self {
+        Self {
+            pool,
+            event_emitter: Some(emitter),
+        }
+    }
+
+    fn emit_progress(&self, current: usize, total: usize, status: IndexStatus) {
+        if let Some(emitter) = &self.event_emitter {
+            emitter.emit_index_progress(IndexProgressEvent {
+                current,
+                total,
+                status,
+                index_type: "search".to_string(),
+            });
+        }
+    }
+
+    /// 重建全文索引
+    pub async fn rebuild(&self) -> Result<SearchIndexStatus, AppError> {
+        log::info!("Rebuilding search index");
+
+        // 1. 重新创建表和触发器，之后的写入由触发器捕获
+        sqlx::query(
+            r#"
+            DROP TRIGGER IF EXISTS emails_fts_insert;
+            DROP TRIGGER IF EXISTS emails_fts_delete;
+            DROP TRIGGER IF EXISTS emails_fts_update;
+            DROP TRIGGER IF EXISTS emails_cjk_fts_insert;
+            DROP TRIGGER IF EXISTS emails_cjk_fts_delete;
+            DROP TRIGGER IF EXISTS emails_cjk_fts_update;
+            DROP TRIGGER IF EXISTS attachments_fts_insert;
+            DROP TRIGGER IF EXISTS attachments_fts_delete;
+            DROP TRIGGER IF EXISTS attachments_fts_update;
+            DROP TABLE IF EXISTS emails_fts;
+            DROP TABLE IF EXISTS emails_cjk_fts;
+            DROP TABLE IF EXISTS attachments_fts;
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+        create_schema(&self.pool).await?;
+
+        let email_total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails")
+            .fetch_one(&self.pool)
+            .await?;
+        let attachment_total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments")
+            .fetch_one(&self.pool)
+            .await?;
+        let total = (email_total + attachment_total) as usize;
+        self.emit_progress(0, total, IndexStatus::Starting);
+
+        // 2. 分批回填（跳过已由触发器写入的行）
+        let mut current = 0;
+        let result = async {
+            current = self
+                .backfill(
+                    "emails",
+                    r#"
+                    INSERT INTO emails_fts(rowid, subject, sender, body_text)
+                    SELECT id, subject, sender, body_text FROM emails
+                    WHERE id > ? AND id <= ?
+                      AND id NOT IN (SELECT rowid FROM emails_fts WHERE rowid > ? AND rowid <= ?)
+                    "#,
+                    current,
+                    total,
+                )
+                .await?;
+            populate_cjk(&self.pool).await?;
+            current = self.backfill(
+                "attachments",
+                r#"
+                INSERT INTO attachments_fts(rowid, filename)
+                SELECT id, filename FROM attachments
+                WHERE id > ? AND id <= ?
+                  AND id NOT IN (SELECT rowid FROM attachments_fts WHERE rowid > ? AND rowid <= ?)
+                "#,
+                current,
+                total,
+            )
+            .await?;
+            Ok::<(), AppError>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            self.emit_progress(current, total, IndexStatus::Failed);
+            return Err(e);
+        }
+
+        sqlx::query(
+            "UPDATE search_index_meta SET tokenizer = ?, last_rebuild_at = CURRENT_TIMESTAMP WHERE id = 1"
+        )
+        .bind(FTS_TOKENIZER)
+        .execute(&self.pool)
+        .await?;
+
+        // 3. 校验文档数
+        let status = self.status().await?;
+        if !status.in_sync {
+            log::warn!("Search index row counts differ after rebuild: {:?}", status);
+        }
+
+        self.emit_progress(total, total, IndexStatus::Completed);
+        log::info!("Search index rebuilt: {:?}", status);
+        Ok(status)
+    }
+
+    /// 按 ID 范围分批回填，返回累计进度
+    async fn backfill(&self, table: &str, insert_sql: &str, start: usize, total: usize) -> Result<usize, AppError> {
+        let max_id: Option<i64> = sqlx::query_scalar(&format!("SELECT MAX(id) FROM {}", table))
+            .fetch_one(&self.pool)
+            .await?;
+        let max_id = max_id.unwrap_or(0);
+
+        let mut current = start;
+        let mut low = 0;
+        while low < max_id {
+            let high = low + REBUILD_BATCH_SIZE;
+            sqlx::query(insert_sql)
+                .bind(low)
+                .bind(high)
+                .bind(low)
+                .bind(high)
+                .execute(&self.pool)
+                .await?;
+
+            let batch_rows: i64 = sqlx::query_scalar(&format!(
+                "SELECT COUNT(*) FROM {} WHERE id > ? AND id <= ?",
+                table
+            ))
+            .bind(low)
+            .bind(high)
+            .fetch_one(&self.pool)
+            .await?;
+
+            current += batch_rows as usize;
+            self.emit_progress(current.min(total), total, IndexStatus::Building);
+            low = high;
+        }
+
+        Ok(current)
+    }
+
+    /// 索引状态
+    pub async fn status(&self) -> Result<SearchIndexStatus, AppError> {
+        let count = |sql: &'static str| async move {
+            sqlx::query_scalar::<_, i64>(sql).fetch_one(&self.pool).await
+        };
+
+        let email_documents = count("SELECT COUNT(*) FROM emails_fts").await?;
+        let email_rows = count("SELECT COUNT(*) FROM emails").await?;
+        let attachment_documents = count("SELECT COUNT(*) FROM attachments_fts").await?;
+        let attachment_rows = count("SELECT COUNT(*) FROM attachments").await?;
+        let last_rebuild_at: Option<String> = sqlx::query_scalar(
+            "SELECT last_rebuild_at FROM search_index_meta WHERE id = 1"
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .flatten();
+
+        Ok(SearchIndexStatus {
+            email_documents,
+            email_rows,
+            attachment_documents,
+            attachment_rows,
+            last_rebuild_at,
+            in_sync: email_documents == email_rows && attachment_documents == attachment_rows,
+        })
+    }
+}
@@ -1,3 +1,6 @@
+pub mod bulk;
+pub mod count;
 pub mod indexer;
 pub mod query;
+pub mod quick_switcher;
 pub mod ranker;
@@ -1 +1,158 @@
-pub fn hello() {}
+/// 本地全文搜索
+///
+/// 在 `emails_fts`（中日韩查询使用 `emails_cjk_fts`）中按 bm25 排序查询；数据库没有全文索引时
+/// （如旧版本导出的归档）退回到 LIKE 匹配。
+use crate::error::AppError;
+use crate::mail::language::contains_cjk;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 默认返回的结果数
+pub const DEFAULT_SEARCH_LIMIT: i64 = 50;
+
+/// 搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SearchHit {
+    pub email_id: i64,
+    pub project_id: Option<i64>,
+    pub subject: Option<String>,
+    pub sender: Option<String>,
+    pub date: Option<String>,
+    pub snippet: Option<String>,
+}
+
+/// trigram 分词要求每个词至少 3 个字符
+const TRIGRAM_MIN_CHARS: usize = 3;
+
+/// 搜索的 SQL 片段（结果列和 LIMIT 由调用方决定）
+///
+/// 邮件表的别名固定为 `e`，`from` 之后可以追加 JOIN。
+pub(crate) struct SearchPlan {
+    pub from: String,
+    pub filter: String,
+    pub order: &'static str,
+    /// 摘要表达式（LIKE 回退时为保存的预览，没有预览列时为 NULL）
+    pub snippet: String,
+    pub binds: Vec<String>,
+}
+
+/// 搜索邮件（主题、发件人、正文），`lang` 可按识别的语言过滤
+///
+/// 含汉字等中日韩字符的查询走 trigram 索引（unicode61 不切分汉字）；词太短或索引不存在时退回到 LIKE。
+pub async fn search_emails(
+    pool: &SqlitePool,
+    query: &str,
+    lang: Option<&str>,
+    limit: i64,
+) -> Result<Vec<SearchHit>, AppError> {
+    let Some(plan) = search_plan(pool, query, lang).await? else {
+        return Ok(Vec::new());
+    };
+
+    let sql = format!(
+        r#"
+        SELECT e.id AS email_id, e.project_id, e.subject, e.sender, e.date, {} AS snippet
+        {}
+        {}
+        {}
+        LIMIT ?
+        "#,
+        plan.snippet, plan.from, plan.filter, plan.order
+    );
+    let mut search = sqlx::query_as::<_, SearchHit>(&sql);
+    for value in &plan.binds {
+        search = search.bind(value);
+    }
+    let hits = search.bind(limit.max(1)).fetch_all(pool).await?;
+
+    Ok(hits)
+}
+
+/// 生成搜索的 SQL 片段；查询为空时返回 None
+pub(crate) async fn search_plan(
+    pool: &SqlitePool,
+    query: &str,
+    lang: Option<&str>,
+) -> Result<Option<SearchPlan>, AppError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(None);
+    }
+
+    let cjk = contains_cjk(query);
+    let index = if cjk { "emails_cjk_fts" } else { "emails_fts" };
+    let has_index: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?"
+    )
+    .bind(index)
+    .fetch_one(pool)
+    .await?;
+    let usable = has_index > 0
+        && (!cjk || query.split_whitespace().all(|term| term.chars().count() >= TRIGRAM_MIN_CHARS));
+
+    // 只在指定语言时引用 lang 列（旧版本导出的归档没有该列）
+    let lang_filter = if lang.is_some() { "AND e.lang = ?" } else { "" };
+
+    let mut plan = if usable {
+        let match_query = if cjk { trigram_query(query) } else { fts_query(query) };
+        SearchPlan {
+            from: format!("FROM {index} JOIN emails e ON e.id = {index}.rowid"),
+            filter: format!("WHERE {index} MATCH ? {lang_filter}"),
+            order: if cjk { "ORDER BY bm25(emails_cjk_fts)" } else { "ORDER BY bm25(emails_fts)" },
+            snippet: format!("snippet({index}, 2, '[', ']', '…', 12)"),
+            binds: vec![match_query],
+        }
+    } else {
+        // 发件人按结构化的名称和地址匹配；旧版本导出的归档没有这两列，退回到组合字符串
+        let has_sender_columns: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info('emails') WHERE name = 'sender_address'"
+        )
+        .fetch_one(pool)
+        .await?;
+        let pattern = format!("%{}%", query);
+        let (sender_filter, mut binds) = if has_sender_columns > 0 {
+            (
+                "e.sender_name LIKE ? OR e.sender_address LIKE ?",
+                vec![pattern.clone(), pattern.clone(), pattern.clone()],
+            )
+        } else {
+            ("e.sender LIKE ?", vec![pattern.clone(), pattern.clone()])
+        };
+        binds.push(pattern);
+        let has_preview: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info('emails') WHERE name = 'preview'"
+        )
+        .fetch_one(pool)
+        .await?;
+        SearchPlan {
+            from: "FROM emails e".to_string(),
+            filter: format!("WHERE (e.subject LIKE ? OR {sender_filter} OR e.body_text LIKE ?) {lang_filter}"),
+            order: "ORDER BY julianday(e.date) DESC",
+            snippet: if has_preview > 0 { "e.preview" } else { "NULL" }.to_string(),
+            binds,
+        }
+    };
+    if let Some(lang) = lang {
+        plan.binds.push(lang.to_string());
+    }
+
+    Ok(Some(plan))
+}
+
+/// trigram 索引查询：每个词作为子串匹配（不需要前缀通配）
+fn trigram_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 把用户输入转换为 FTS5 查询：每个词加引号（避免特殊字符被当作语法），按前缀匹配
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
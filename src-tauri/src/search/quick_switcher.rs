@@ -0,0 +1,254 @@
+/// 快速切换器（Cmd+K）数据源
+///
+/// 一次查询返回按分数排序的项目、联系人和最近邮件。项目按名称和标签匹配，
+/// 最近一周更新过的项目加权；联系人复用收件人建议的前缀索引；邮件按主题前缀在全文索引中查找，
+/// 取最新的几封。联系人表或全文索引尚未建立时只返回项目。
+/// 结果在进程内缓存几秒，连续输入时重复的前缀不再查询数据库。
+use crate::error::AppError;
+use crate::mail::contacts::ContactBook;
+use crate::storage::cache::TtlCache;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+/// 默认返回的条目数
+pub const DEFAULT_QUICK_SEARCH_LIMIT: i64 = 20;
+
+/// 缓存有效期
+const CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// 缓存的前缀数量
+const CACHE_CAPACITY: usize = 64;
+
+/// 项目最近更新的加权窗口（天）
+const RECENT_PROJECT_DAYS: f64 = 7.0;
+
+/// 切换器条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum QuickSwitchItem {
+    Project {
+        id: i64,
+        title: String,
+        color: Option<String>,
+        icon: Option<String>,
+        is_pinned: bool,
+        score: f64,
+    },
+    Contact {
+        name: Option<String>,
+        address: String,
+        score: f64,
+    },
+    Email {
+        id: i64,
+        project_id: Option<i64>,
+        subject: Option<String>,
+        sender: Option<String>,
+        date: Option<String>,
+        score: f64,
+    },
+}
+
+impl QuickSwitchItem {
+    fn score(&self) -> f64 {
+        match self {
+            Self::Project { score, .. } | Self::Contact { score, .. } | Self::Email { score, .. } => *score,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ProjectCandidate {
+    id: i64,
+    name: String,
+    color: Option<String>,
+    icon: Option<String>,
+    is_pinned: bool,
+    tags: Option<String>,
+    /// 距最近更新的天数
+    age_days: Option<f64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct EmailCandidate {
+    id: i64,
+    project_id: Option<i64>,
+    subject: Option<String>,
+    sender: Option<String>,
+    date: Option<String>,
+}
+
+/// 快速切换器（注册为全局状态，持有结果缓存）
+pub struct QuickSwitcher {
+    cache: TtlCache<(String, i64), Vec<QuickSwitchItem>>,
+}
+
+impl Default for QuickSwitcher {
+    fn default() -> Self {
+        Self {
+            cache: TtlCache::new(CACHE_TTL, CACHE_CAPACITY),
+        }
+    }
+}
+
+impl QuickSwitcher {
+    /// 按前缀查询
+    pub async fn search(&self, pool: &SqlitePool, prefix: &str, limit: i64) -> Result<Vec<QuickSwitchItem>, AppError> {
+        let prefix = prefix.trim().to_lowercase();
+        let limit = limit.clamp(1, 100);
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let key = (prefix.clone(), limit);
+        if let Some(items) = self.cache.get(&key) {
+            return Ok(items);
+        }
+
+        let mut items = search_projects(pool, &prefix, limit).await?;
+
+        let (has_contacts, has_fts) = available_sources(pool).await?;
+        if has_contacts {
+            match ContactBook::new(pool.clone()).suggest(&prefix, limit.min(10)).await {
+                Ok(contacts) => items.extend(contacts.into_iter().enumerate().map(|(rank, contact)| {
+                    QuickSwitchItem::Contact {
+                        name: contact.name,
+                        address: contact.address,
+                        score: 1.0 - rank as f64 * 0.01,
+                    }
+                })),
+                Err(e) => log::warn!("Quick switcher contact lookup failed: {}", e),
+            }
+        }
+        if has_fts {
+            match search_emails(pool, &prefix, limit.min(10)).await {
+                Ok(emails) => items.extend(emails),
+                Err(e) => log::warn!("Quick switcher email lookup failed: {}", e),
+            }
+        }
+
+        // 分数相同时保持类别顺序（项目 → 联系人 → 邮件）
+        items.sort_by(|a, b| b.score().total_cmp(&a.score()));
+        items.truncate(limit as usize);
+
+        self.cache.insert(key, items.clone());
+        Ok(items)
+    }
+
+    /// 项目或邮件发生变化后清空缓存
+    pub fn invalidate(&self) {
+        self.cache.clear();
+    }
+}
+
+/// 联系人表和全文索引是否已建立
+async fn available_sources(pool: &SqlitePool) -> Result<(bool, bool), AppError> {
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name IN ('contacts', 'emails_fts')"
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok((
+        tables.iter().any(|name| name == "contacts"),
+        tables.iter().any(|name| name == "emails_fts"),
+    ))
+}
+
+/// 项目：名称前缀 > 名称中某个词的前缀 > 标签匹配，置顶和最近一周更新过的项目加权
+async fn search_projects(pool: &SqlitePool, prefix: &str, limit: i64) -> Result<Vec<QuickSwitchItem>, AppError> {
+    let pattern = format!("%{}%", prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    let candidates = sqlx::query_as::<_, ProjectCandidate>(
+        r#"
+        SELECT id, name, color, icon, COALESCE(is_pinned, 0) AS is_pinned, tags,
+               julianday('now') - julianday(updated_at) AS age_days
+        FROM projects
+        WHERE status != 'deleted'
+          AND (name LIKE ?1 ESCAPE '\' OR tags LIKE ?1 ESCAPE '\')
+        "#
+    )
+    .bind(&pattern)
+    .fetch_all(pool)
+    .await?;
+
+    let mut items: Vec<QuickSwitchItem> = candidates
+        .into_iter()
+        .filter_map(|project| {
+            let name = project.name.to_lowercase();
+            let tags: Vec<String> = project
+                .tags
+                .as_deref()
+                .and_then(|tags| serde_json::from_str(tags).ok())
+                .unwrap_or_default();
+
+            let mut score = if name.starts_with(prefix) {
+                3.0
+            } else if name.split(|c: char| !c.is_alphanumeric()).any(|word| word.starts_with(prefix)) {
+                2.0
+            } else if tags.iter().any(|tag| tag.to_lowercase().starts_with(prefix)) {
+                1.5
+            } else if name.contains(prefix) {
+                1.2
+            } else {
+                return None;
+            };
+            if project.is_pinned {
+                score += 0.5;
+            }
+            if let Some(age) = project.age_days.filter(|age| *age < RECENT_PROJECT_DAYS) {
+                score += 1.0 - age.max(0.0) / RECENT_PROJECT_DAYS;
+            }
+
+            Some(QuickSwitchItem::Project {
+                id: project.id,
+                title: project.name,
+                color: project.color,
+                icon: project.icon,
+                is_pinned: project.is_pinned,
+                score,
+            })
+        })
+        .collect();
+
+    items.sort_by(|a, b| b.score().total_cmp(&a.score()));
+    items.truncate(limit as usize);
+    Ok(items)
+}
+
+/// 邮件：主题按前缀匹配，取最新的几封（FTS 按 rowid 倒序不需要排序全部结果）
+async fn search_emails(pool: &SqlitePool, prefix: &str, limit: i64) -> Result<Vec<QuickSwitchItem>, AppError> {
+    let match_query = prefix
+        .split_whitespace()
+        .map(|term| format!("subject : \"{}\"*", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+
+    let emails = sqlx::query_as::<_, EmailCandidate>(
+        r#"
+        SELECT e.id, e.project_id, e.subject, e.sender, e.date
+        FROM (
+            SELECT rowid FROM emails_fts WHERE emails_fts MATCH ? ORDER BY rowid DESC LIMIT ?
+        ) hits
+        JOIN emails e ON e.id = hits.rowid
+        WHERE e.duplicate_of IS NULL
+        ORDER BY e.id DESC
+        "#
+    )
+    .bind(match_query)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(emails
+        .into_iter()
+        .enumerate()
+        .map(|(rank, email)| QuickSwitchItem::Email {
+            id: email.id,
+            project_id: email.project_id,
+            subject: email.subject,
+            sender: email.sender,
+            date: email.date,
+            score: 0.8 - rank as f64 * 0.01,
+        })
+        .collect())
+}
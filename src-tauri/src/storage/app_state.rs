@@ -0,0 +1,117 @@
+/// 延迟初始化的数据库状态
+///
+/// 窗口先显示，连接池创建、迁移和后台任务在启动任务中完成（见 `lib.rs` 的 `initialize`）。
+/// 完成前需要数据库的命令返回 `NOT_READY`，前端收到 `app-ready` 事件后重试。
+/// 命令参数中的 `Db`、`ProjectRepository`、`ArtifactRepository` 从这里取连接池，
+/// 用法与原来的 `State<SqlitePool>` / `State<ProjectRepository>` 相同。
+use crate::error::{AppError, ErrorResponse};
+use crate::repository::{ArtifactRepository, ProjectRepository};
+use crate::storage::database::WriterPool;
+use sqlx::SqlitePool;
+use std::ops::Deref;
+use std::sync::OnceLock;
+use tauri::ipc::{CommandArg, CommandItem, InvokeError};
+use tauri::{Manager, Runtime};
+use tokio::sync::Notify;
+
+/// 启动完成后写入的连接池
+#[derive(Default)]
+pub struct DatabaseState {
+    pools: OnceLock<(SqlitePool, WriterPool)>,
+    ready: Notify,
+}
+
+impl DatabaseState {
+    /// 写入连接池并唤醒等待的任务（只生效一次）
+    pub fn set(&self, pool: SqlitePool, writer: WriterPool) {
+        if self.pools.set((pool, writer)).is_err() {
+            log::warn!("Database state initialized twice, keeping the first pools");
+        }
+        self.ready.notify_waiters();
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.pools.get().is_some()
+    }
+
+    /// 通用连接池，启动未完成时返回 `NotReady`
+    pub fn pool(&self) -> Result<SqlitePool, AppError> {
+        self.pools.get().map(|(pool, _)| pool.clone()).ok_or(AppError::NotReady)
+    }
+
+    /// 专用写连接池，启动未完成时返回 `NotReady`
+    pub fn writer(&self) -> Result<WriterPool, AppError> {
+        self.pools.get().map(|(_, writer)| writer.clone()).ok_or(AppError::NotReady)
+    }
+
+    /// 等待启动完成（deep link 等启动时就可能到达的请求使用）
+    pub async fn wait(&self) -> SqlitePool {
+        loop {
+            let notified = self.ready.notified();
+            if let Ok(pool) = self.pool() {
+                return pool;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// AppHandle / 窗口上读取数据库状态的便捷方法
+pub trait DatabaseExt<R: Runtime> {
+    fn db(&self) -> Result<SqlitePool, AppError>;
+    fn db_writer(&self) -> Result<WriterPool, AppError>;
+}
+
+impl<R: Runtime, M: Manager<R>> DatabaseExt<R> for M {
+    fn db(&self) -> Result<SqlitePool, AppError> {
+        self.try_state::<DatabaseState>().ok_or(AppError::NotReady)?.pool()
+    }
+
+    fn db_writer(&self) -> Result<WriterPool, AppError> {
+        self.try_state::<DatabaseState>().ok_or(AppError::NotReady)?.writer()
+    }
+}
+
+/// 命令参数：通用连接池（代替 `State<'_, SqlitePool>`）
+pub struct Db(SqlitePool);
+
+impl Db {
+    pub fn inner(&self) -> &SqlitePool {
+        &self.0
+    }
+}
+
+impl Deref for Db {
+    type Target = SqlitePool;
+
+    fn deref(&self) -> &SqlitePool {
+        &self.0
+    }
+}
+
+/// 从命令所在窗口取连接池，启动未完成时以 `NOT_READY` 拒绝命令
+fn command_pool<R: Runtime>(command: &CommandItem<'_, R>) -> Result<SqlitePool, InvokeError> {
+    command
+        .message
+        .webview_ref()
+        .db()
+        .map_err(|e| InvokeError::from(ErrorResponse::from(e)))
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for Db {
+    fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+        command_pool(&command).map(Db)
+    }
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for ProjectRepository {
+    fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+        command_pool(&command).map(ProjectRepository::new)
+    }
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for ArtifactRepository {
+    fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+        command_pool(&command).map(ArtifactRepository::new)
+    }
+}
@@ -0,0 +1,173 @@
+/// 只读归档数据库
+///
+/// 打开另一份 threadline.db（如去年导出的资料）作为只读数据源，读取命令通过
+/// `source: "archive"` 查询它。连接以 SQLite 只读模式打开，任何写入都会直接失败；
+/// 归档中的附件路径解析到单独的附件目录（默认为数据库文件旁的 `attachments`）。
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio::sync::RwLock;
+
+/// 归档连接池的最大连接数
+const MAX_ARCHIVE_CONNECTIONS: u32 = 2;
+
+/// 打开归档时要求存在的表
+const REQUIRED_TABLES: &[&str] = &["projects", "emails", "attachments"];
+
+/// 读取命令的数据源
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataSource {
+    #[default]
+    Live,
+    Archive,
+}
+
+/// 已打开的归档数据库
+#[derive(Debug, Clone)]
+pub struct ArchiveDatabase {
+    pub path: PathBuf,
+    pub pool: SqlitePool,
+    /// 归档附件的根目录
+    pub attachment_root: PathBuf,
+}
+
+impl ArchiveDatabase {
+    /// 将归档中的附件相对路径解析为绝对路径
+    pub fn resolve_attachment_path(&self, relative: &str) -> PathBuf {
+        self.attachment_root.join(relative)
+    }
+}
+
+/// 归档信息（返回给界面）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveInfo {
+    pub path: String,
+    pub attachment_root: String,
+    pub project_count: i64,
+    pub email_count: i64,
+}
+
+/// 归档数据库状态（注册为全局状态）
+#[derive(Default)]
+pub struct ArchiveState {
+    archive: RwLock<Option<ArchiveDatabase>>,
+}
+
+impl ArchiveState {
+    /// 打开归档数据库（已打开的归档会先关闭）
+    pub async fn open(&self, path: &str, attachment_root: Option<&str>) -> Result<ArchiveInfo, AppError> {
+        let path = PathBuf::from(path);
+        if !path.is_file() {
+            return Err(AppError::Validation(format!("Archive database not found: {}", path.display())));
+        }
+
+        let options = SqliteConnectOptions::from_str(&format!("sqlite:{}?mode=ro", path.display()))?
+            .read_only(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(MAX_ARCHIVE_CONNECTIONS)
+            .connect_with(options)
+            .await?;
+
+        if let Err(e) = check_schema(&pool).await {
+            pool.close().await;
+            return Err(e);
+        }
+
+        let attachment_root = match attachment_root.map(str::trim).filter(|root| !root.is_empty()) {
+            Some(root) => PathBuf::from(root),
+            None => default_attachment_root(&path),
+        };
+        let archive = ArchiveDatabase { path, pool, attachment_root };
+        let info = archive_info(&archive).await?;
+
+        if let Some(previous) = self.archive.write().await.replace(archive) {
+            previous.pool.close().await;
+        }
+        log::info!("Opened archive database {}", info.path);
+        Ok(info)
+    }
+
+    /// 关闭归档数据库，没有打开的归档时返回 false
+    pub async fn close(&self) -> bool {
+        match self.archive.write().await.take() {
+            Some(archive) => {
+                archive.pool.close().await;
+                log::info!("Closed archive database {}", archive.path.display());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 当前打开的归档
+    pub async fn current(&self) -> Result<ArchiveDatabase, AppError> {
+        self.archive
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| AppError::Validation("No archive database is open".to_string()))
+    }
+
+    /// 当前归档信息
+    pub async fn info(&self) -> Result<Option<ArchiveInfo>, AppError> {
+        let archive = self.archive.read().await.clone();
+        match archive {
+            Some(archive) => Ok(Some(archive_info(&archive).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 按数据源选择连接池
+    pub async fn pool(&self, source: DataSource, live: &SqlitePool) -> Result<SqlitePool, AppError> {
+        match source {
+            DataSource::Live => Ok(live.clone()),
+            DataSource::Archive => Ok(self.current().await?.pool),
+        }
+    }
+}
+
+async fn check_schema(pool: &SqlitePool) -> Result<(), AppError> {
+    for table in REQUIRED_TABLES {
+        let exists: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?"
+        )
+        .bind(table)
+        .fetch_one(pool)
+        .await?;
+        if exists == 0 {
+            return Err(AppError::Validation(format!(
+                "Not a ThreadLine database (missing table '{}')",
+                table
+            )));
+        }
+    }
+    Ok(())
+}
+
+async fn archive_info(archive: &ArchiveDatabase) -> Result<ArchiveInfo, AppError> {
+    let project_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects")
+        .fetch_one(&archive.pool)
+        .await?;
+    let email_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails")
+        .fetch_one(&archive.pool)
+        .await?;
+
+    Ok(ArchiveInfo {
+        path: archive.path.display().to_string(),
+        attachment_root: archive.attachment_root.display().to_string(),
+        project_count,
+        email_count,
+    })
+}
+
+/// 默认附件目录：与数据库文件同目录的 `attachments`
+fn default_attachment_root(db_path: &Path) -> PathBuf {
+    db_path
+        .parent()
+        .map(|dir| dir.join("attachments"))
+        .unwrap_or_else(|| PathBuf::from("attachments"))
+}
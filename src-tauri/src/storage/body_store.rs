@@ -0,0 +1,171 @@
+/// 超大邮件正文的外部存储
+///
+/// 正文（纯文本 + HTML）超过设置的大小上限时，数据库列中只保留截断后的内容并设置
+/// `body_truncated`，完整正文写入 `bodies/{account_id}/{message_id 哈希}.json`，
+/// 相对路径保存在 `body_path` 列。避免时间线等查询通过 IPC 传输几十 MB 的 HTML。
+use crate::error::AppError;
+use crate::mail::sync::calculate_sha256;
+use crate::storage::file_manager;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+
+/// 默认正文大小上限（字节）
+pub const DEFAULT_BODY_SIZE_CAP: i64 = 1024 * 1024;
+
+/// 准备写入数据库的正文
+#[derive(Debug, Clone, Default)]
+pub struct StoredBody {
+    pub text: Option<String>,
+    pub html: Option<String>,
+    pub truncated: bool,
+    pub path: Option<String>,
+}
+
+/// 完整正文文件内容
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FullBody {
+    pub text: Option<String>,
+    pub html: Option<String>,
+}
+
+/// 正文存储
+pub struct BodyStore {
+    cap: usize,
+}
+
+impl BodyStore {
+    pub fn new(cap: i64) -> Self {
+        Self {
+            cap: cap.max(1024) as usize,
+        }
+    }
+
+    /// 按设置中的 `body_size_cap` 创建
+    pub async fn load(pool: &SqlitePool) -> Self {
+        let cap = sqlx::query_scalar::<_, i64>("SELECT body_size_cap FROM sync_settings WHERE id = 1")
+            .fetch_one(pool)
+            .await
+            .unwrap_or(DEFAULT_BODY_SIZE_CAP);
+        Self::new(cap)
+    }
+
+    /// 未超过上限时原样返回；超过时写出完整正文文件并截断
+    pub async fn prepare(
+        &self,
+        account_id: i64,
+        message_id: &str,
+        text: Option<&str>,
+        html: Option<&str>,
+    ) -> Result<StoredBody, AppError> {
+        let size = text.map_or(0, str::len) + html.map_or(0, str::len);
+        if size <= self.cap {
+            return Ok(StoredBody {
+                text: text.map(str::to_string),
+                html: html.map(str::to_string),
+                ..Default::default()
+            });
+        }
+
+        let relative = format!("{}/{}.json", account_id, calculate_sha256(message_id.as_bytes()));
+        let path = resolve_body_path(&relative)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let full = FullBody {
+            text: text.map(str::to_string),
+            html: html.map(str::to_string),
+        };
+        tokio::fs::write(&path, serde_json::to_vec(&full)?).await?;
+
+        log::info!("Stored oversized body ({} bytes) for {} in {}", size, message_id, relative);
+
+        // 两列各保留一半，合计不超过上限
+        let keep = self.cap / 2;
+        Ok(StoredBody {
+            text: text.map(|t| truncate_at_char_boundary(t, keep)),
+            html: html.map(|h| truncate_at_char_boundary(h, keep)),
+            truncated: true,
+            path: Some(relative),
+        })
+    }
+}
+
+/// 正文文件根目录
+pub fn bodies_root() -> Result<PathBuf, AppError> {
+    Ok(file_manager::app_data_dir()?.join("bodies"))
+}
+
+/// 将 `body_path` 解析为绝对路径
+pub fn resolve_body_path(relative: &str) -> Result<PathBuf, AppError> {
+    Ok(bodies_root()?.join(relative))
+}
+
+/// 读取完整正文
+pub async fn read_full_body(relative: &str) -> Result<FullBody, AppError> {
+    let data = tokio::fs::read(resolve_body_path(relative)?).await?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// 正文迁移结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BodyCompactionSummary {
+    pub emails_moved: usize,
+    /// 数据库中减少的正文字节数
+    pub bytes_saved: i64,
+}
+
+/// 将已有的超大正文移出数据库
+pub async fn compact_oversized_bodies(pool: &SqlitePool) -> Result<BodyCompactionSummary, AppError> {
+    let store = BodyStore::load(pool).await;
+
+    let rows: Vec<(i64, i64, String, Option<String>, Option<String>)> = sqlx::query_as(
+        r#"
+        SELECT id, account_id, message_id, body_text, body_html
+        FROM emails
+        WHERE COALESCE(body_truncated, 0) = 0
+          AND COALESCE(LENGTH(CAST(body_text AS BLOB)), 0) + COALESCE(LENGTH(CAST(body_html AS BLOB)), 0) > ?
+        "#
+    )
+    .bind(store.cap as i64)
+    .fetch_all(pool)
+    .await?;
+
+    let mut summary = BodyCompactionSummary::default();
+    for (id, account_id, message_id, text, html) in rows {
+        let before = text.as_deref().map_or(0, str::len) + html.as_deref().map_or(0, str::len);
+        let stored = store.prepare(account_id, &message_id, text.as_deref(), html.as_deref()).await?;
+        let after = stored.text.as_deref().map_or(0, str::len) + stored.html.as_deref().map_or(0, str::len);
+
+        sqlx::query(
+            "UPDATE emails SET body_text = ?, body_html = ?, body_truncated = 1, body_path = ? WHERE id = ?"
+        )
+        .bind(&stored.text)
+        .bind(&stored.html)
+        .bind(&stored.path)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+        summary.emails_moved += 1;
+        summary.bytes_saved += (before - after) as i64;
+    }
+
+    log::info!(
+        "Moved {} oversized bodies out of the database, saved {} bytes",
+        summary.emails_moved, summary.bytes_saved
+    );
+    Ok(summary)
+}
+
+/// 截断到不超过 `max_bytes` 的字符边界
+fn truncate_at_char_boundary(s: &str, max_bytes: usize) -> String {
+    if s.len() <= max_bytes {
+        return s.to_string();
+    }
+    let mut end = max_bytes;
+    while end > 0 && !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s[..end].to_string()
+}
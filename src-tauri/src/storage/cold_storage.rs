@@ -0,0 +1,216 @@
+/// 附件冷存储
+///
+/// 把较旧的附件从本机磁盘移动到 `cold_storage_path`（通常是外置磁盘），
+/// 数据库中的 `file_path` 改为 `cold:` 前缀。星标附件和置顶项目中的附件保留在热存储。
+/// 复制后校验哈希，确认一致才删除原文件。
+use crate::error::AppError;
+use crate::mail::quota::{AccountQuota, QuotaStore};
+use crate::mail::sync::calculate_sha256;
+use crate::storage::disk_space::{self, DiskUsage};
+use crate::storage::file_manager::{self, COLD_PREFIX};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+
+/// 冷存储迁移结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ColdMigrationSummary {
+    pub moved: i64,
+    pub bytes_moved: i64,
+    /// 源文件缺失或哈希不一致而跳过的附件
+    pub skipped: i64,
+    pub failed: i64,
+}
+
+/// 单个存储层的用量
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TierUsage {
+    pub file_count: i64,
+    pub total_bytes: i64,
+}
+
+/// 按存储层统计的附件用量，以及磁盘和邮箱配额
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageStats {
+    pub hot: TierUsage,
+    pub cold: TierUsage,
+    /// 隔离区中等待永久删除的已删除附件（不计入热/冷存储）
+    pub quarantine: TierUsage,
+    pub cold_storage_path: Option<String>,
+    /// 冷存储目录当前是否可用
+    pub cold_mounted: bool,
+    /// 应用数据目录所在磁盘（无法读取时为 None）
+    pub disk: Option<DiskUsage>,
+    /// 各账户最近一次同步时查询到的邮箱配额
+    pub mail_quotas: Vec<AccountQuota>,
+}
+
+#[derive(sqlx::FromRow)]
+struct EligibleAttachment {
+    id: i64,
+    file_path: String,
+    content_hash: Option<String>,
+}
+
+/// 冷存储迁移
+pub struct ColdStorage {
+    pool: SqlitePool,
+}
+
+impl ColdStorage {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 把早于 `older_than_days` 天的附件移动到冷存储
+    pub async fn migrate(&self, older_than_days: i64) -> Result<ColdMigrationSummary, AppError> {
+        if older_than_days < 1 {
+            return Err(AppError::Validation("older_than_days must be at least 1".to_string()));
+        }
+        let cold_root = file_manager::mounted_cold_storage_root()?;
+        let hot_root = file_manager::attachments_root()?;
+
+        // 年龄按来源邮件日期计算，手动添加的文件按添加时间
+        let rows = sqlx::query_as::<_, EligibleAttachment>(
+            r#"
+            SELECT a.id, a.file_path, a.content_hash
+            FROM attachments a
+            LEFT JOIN emails e ON e.id = a.email_id
+            LEFT JOIN projects p ON p.id = a.project_id
+            WHERE a.file_path IS NOT NULL
+              AND a.file_path NOT LIKE 'cold:%'
+              AND COALESCE(a.is_starred, 0) = 0
+              AND COALESCE(p.is_pinned, 0) = 0
+              AND julianday(COALESCE(e.date, a.created_at)) < julianday('now', '-' || ? || ' days')
+            ORDER BY a.id
+            "#
+        )
+        .bind(older_than_days)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut summary = ColdMigrationSummary::default();
+        let mut moved_paths = HashSet::new();
+        for row in rows {
+            // 共用同一文件的附件记录已随第一条一起移动
+            if moved_paths.contains(&row.file_path) {
+                continue;
+            }
+            let source = hot_root.join(&row.file_path);
+            let target = cold_root.join(&row.file_path);
+
+            let data = match tokio::fs::read(&source).await {
+                Ok(data) => data,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    log::warn!("Skipping attachment {}: file missing at {:?}", row.id, source);
+                    summary.skipped += 1;
+                    continue;
+                }
+                Err(e) => {
+                    log::error!("Failed to read attachment {}: {}", row.id, e);
+                    summary.failed += 1;
+                    continue;
+                }
+            };
+            let hash = calculate_sha256(&data);
+            if row.content_hash.as_deref().is_some_and(|expected| expected != hash) {
+                log::warn!("Skipping attachment {}: content hash mismatch before move", row.id);
+                summary.skipped += 1;
+                continue;
+            }
+
+            match self.move_file(&row.file_path, &target, &data, &hash).await {
+                Ok(()) => {
+                    moved_paths.insert(row.file_path.clone());
+                    if let Err(e) = tokio::fs::remove_file(&source).await {
+                        log::warn!("Moved attachment {} but failed to remove {:?}: {}", row.id, source, e);
+                    }
+                    summary.moved += 1;
+                    summary.bytes_moved += data.len() as i64;
+                }
+                Err(e) => {
+                    log::error!("Failed to move attachment {} to cold storage: {}", row.id, e);
+                    let _ = tokio::fs::remove_file(&target).await;
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        log::info!(
+            "Cold storage migration: {} moved ({} bytes), {} skipped, {} failed",
+            summary.moved, summary.bytes_moved, summary.skipped, summary.failed
+        );
+        Ok(summary)
+    }
+
+    /// 写入冷存储、回读校验哈希，然后更新数据库路径
+    async fn move_file(
+        &self,
+        relative: &str,
+        target: &std::path::Path,
+        data: &[u8],
+        hash: &str,
+    ) -> Result<(), AppError> {
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(target, data).await?;
+
+        let copied = tokio::fs::read(target).await?;
+        if calculate_sha256(&copied) != hash {
+            return Err(AppError::FileSystem(format!("Hash mismatch after copying to {:?}", target)));
+        }
+
+        // 同一文件可能被多条附件记录引用（发出邮件复用已有文件），一起更新
+        sqlx::query("UPDATE attachments SET file_path = ? WHERE file_path = ?")
+            .bind(format!("{}{}", COLD_PREFIX, relative))
+            .bind(relative)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 按存储层统计附件用量（隔离区单独统计）
+    pub async fn stats(&self) -> Result<StorageStats, AppError> {
+        let (hot_count, hot_bytes, cold_count, cold_bytes): (i64, i64, i64, i64) = sqlx::query_as(
+            r#"
+            SELECT
+                COALESCE(SUM(CASE WHEN file_path NOT LIKE 'cold:%' THEN 1 ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN file_path NOT LIKE 'cold:%' THEN COALESCE(file_size, 0) ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN file_path LIKE 'cold:%' THEN 1 ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN file_path LIKE 'cold:%' THEN COALESCE(file_size, 0) ELSE 0 END), 0)
+            FROM attachments
+            WHERE file_path IS NOT NULL
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let (quarantine_count, quarantine_bytes): (i64, i64) = sqlx::query_as(
+            "SELECT COUNT(*), COALESCE(SUM(COALESCE(file_size, 0)), 0) FROM attachment_trash"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(StorageStats {
+            hot: TierUsage { file_count: hot_count, total_bytes: hot_bytes },
+            cold: TierUsage { file_count: cold_count, total_bytes: cold_bytes },
+            quarantine: TierUsage { file_count: quarantine_count, total_bytes: quarantine_bytes },
+            cold_storage_path: file_manager::cold_storage_root().map(|root| root.display().to_string()),
+            cold_mounted: file_manager::mounted_cold_storage_root().is_ok(),
+            disk: disk_space::app_data_usage()
+                .map_err(|e| log::warn!("Failed to read disk usage: {}", e))
+                .ok(),
+            mail_quotas: QuotaStore::new(self.pool.clone()).list().await?,
+        })
+    }
+}
+
+/// 从设置中加载冷存储目录
+pub async fn load_root(pool: &SqlitePool) -> Result<(), AppError> {
+    let path: Option<String> = sqlx::query_scalar("SELECT cold_storage_path FROM sync_settings WHERE id = 1")
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+    file_manager::set_cold_storage_root(path.as_deref().unwrap_or_default());
+    Ok(())
+}
@@ -1,13 +1,61 @@
-use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqlitePool, sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous}};
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
 use tauri::{AppHandle, Manager};
 use anyhow::Result;
 
 const DB_NAME: &str = "threadline.db";
 
-/// 初始化数据库连接池
-pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
+/// WAL 自动检查点阈值（页数）
+const WAL_AUTOCHECKPOINT_PAGES: u32 = 1000;
+
+/// 等待写锁的超时时间
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 通用连接池的连接数（读为主）
+const MAX_READER_CONNECTIONS: u32 = 4;
+
+/// 专用写连接池
+///
+/// SQLite 同一时间只允许一个写事务，同步等大批量写入走单连接的写池，
+/// 避免多个连接争抢写锁导致 SQLITE_BUSY。
+#[derive(Clone)]
+pub struct WriterPool(pub SqlitePool);
+
+/// 数据库 PRAGMA 状态（诊断用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabasePragmas {
+    pub journal_mode: String,
+    pub synchronous: i64,
+    pub busy_timeout: i64,
+    pub wal_autocheckpoint: i64,
+    pub page_size: i64,
+    pub page_count: i64,
+    pub freelist_count: i64,
+    /// WAL 文件大小（字节）
+    pub wal_size: Option<u64>,
+}
+
+/// 连接参数（所有连接一致）
+fn connect_options(db_path: &Path) -> Result<SqliteConnectOptions> {
+    // 添加 ?mode=rwc 允许创建数据库文件
+    let db_url = format!("sqlite:{}?mode=rwc", db_path.display());
+
+    // recursive_triggers：INSERT OR REPLACE 删除旧行时也触发 DELETE 触发器（全文索引依赖）
+    Ok(SqliteConnectOptions::from_str(&db_url)?
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(BUSY_TIMEOUT)
+        .pragma("wal_autocheckpoint", WAL_AUTOCHECKPOINT_PAGES.to_string())
+        .pragma("recursive_triggers", "ON"))
+}
+
+/// 数据库文件路径
+fn database_path(app: &AppHandle) -> Result<PathBuf> {
     let app_data_dir = app.path().app_data_dir()?;
 
     // 确保目录存在
@@ -16,29 +64,78 @@ pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
         fs::create_dir_all(&app_data_dir)?;
     }
 
-    let db_path = app_data_dir.join(DB_NAME);
-    log::info!("Database path: {:?}", db_path);
+    Ok(app_data_dir.join(DB_NAME))
+}
 
-    // 添加 ?mode=rwc 允许创建数据库文件
-    let db_url = format!("sqlite:{}?mode=rwc", db_path.display());
+/// 初始化专用写连接池（需在 `init_pool` 完成迁移之后调用）
+pub async fn init_writer_pool(app: &AppHandle) -> Result<WriterPool> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(connect_options(&database_path(app)?)?)
+        .await?;
+
+    Ok(WriterPool(pool))
+}
+
+/// 执行 WAL 检查点并截断 WAL 文件，返回 (是否被阻塞, WAL 页数, 已写回页数)
+pub async fn checkpoint_wal(pool: &SqlitePool) -> Result<(i64, i64, i64)> {
+    let result: (i64, i64, i64) = sqlx::query_as("PRAGMA wal_checkpoint(TRUNCATE)")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(result)
+}
+
+/// 读取当前数据库 PRAGMA
+pub async fn database_pragmas(app: &AppHandle, pool: &SqlitePool) -> Result<DatabasePragmas> {
+    let mut conn = pool.acquire().await?;
+    let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode").fetch_one(&mut *conn).await?;
+    let synchronous: i64 = sqlx::query_scalar("PRAGMA synchronous").fetch_one(&mut *conn).await?;
+    let busy_timeout: i64 = sqlx::query_scalar("PRAGMA busy_timeout").fetch_one(&mut *conn).await?;
+    let wal_autocheckpoint: i64 = sqlx::query_scalar("PRAGMA wal_autocheckpoint").fetch_one(&mut *conn).await?;
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size").fetch_one(&mut *conn).await?;
+    let page_count: i64 = sqlx::query_scalar("PRAGMA page_count").fetch_one(&mut *conn).await?;
+    let freelist_count: i64 = sqlx::query_scalar("PRAGMA freelist_count").fetch_one(&mut *conn).await?;
+
+    let mut wal_path = database_path(app)?.into_os_string();
+    wal_path.push("-wal");
+    let wal_size = fs::metadata(&wal_path).ok().map(|m| m.len());
+
+    Ok(DatabasePragmas {
+        journal_mode,
+        synchronous,
+        busy_timeout,
+        wal_autocheckpoint,
+        page_size,
+        page_count,
+        freelist_count,
+        wal_size,
+    })
+}
+
+/// 初始化数据库连接池
+pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
+    let db_path = database_path(app)?;
+    log::info!("Database path: {:?}", db_path);
 
     // 创建连接池
-    log::info!("Connecting to database: {}", db_url);
     let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(&db_url)
+        .max_connections(MAX_READER_CONNECTIONS)
+        .connect_with(connect_options(&db_path)?)
         .await
         .map_err(|e| {
             log::error!("Failed to connect to database: {}", e);
             e
         })?;
 
-    // Enable WAL mode for better concurrency
-    sqlx::query("PRAGMA journal_mode = WAL;")
-        .execute(&pool)
-        .await?;
+    create_schema(&pool).await?;
+
+    log::info!("Database initialized successfully.");
+    Ok(pool)
+}
 
-    // Create Tables
+/// 建表并迁移旧数据库（测试中也用于初始化临时数据库）
+pub(crate) async fn create_schema(pool: &SqlitePool) -> Result<()> {
     sqlx::query(
         r#"
         -- Accounts Table
@@ -52,6 +149,11 @@ pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
             oauth_access_token TEXT,  -- OAuth access token
             oauth_refresh_token TEXT,  -- OAuth refresh token
             oauth_token_expires_at INTEGER,  -- Token 过期时间 (Unix timestamp)
+            quota_used_kb INTEGER,  -- 邮箱存储配额（GETQUOTAROOT INBOX 的 STORAGE，KB）
+            quota_limit_kb INTEGER,
+            quota_checked_at DATETIME,
+            namespaces TEXT,  -- IMAP NAMESPACE 返回的个人 / 其他用户 / 共享前缀（JSON），服务器不支持时为 NULL
+            inbox_uid_validity INTEGER,  -- 上次同步时收件箱的 UIDVALIDITY，变化时本次不比对服务器删除
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP
         );
 
@@ -60,12 +162,25 @@ pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
             id INTEGER PRIMARY KEY,
             name TEXT NOT NULL,
             description TEXT,
-            status TEXT DEFAULT 'active',
+            status TEXT DEFAULT 'active',  -- project_statuses.key，或 deleted（回收站）
+            status_before_delete TEXT,  -- 移入回收站前的状态，恢复时使用
+            deleted_at DATETIME,
             color TEXT,
+            icon TEXT,
             is_pinned BOOLEAN DEFAULT 0,
+            pin_order INTEGER,  -- 置顶项目的手动排序
             email_count INTEGER DEFAULT 0,
             attachment_count INTEGER DEFAULT 0,
             tags TEXT,  -- JSON array of tags
+            due_date TEXT,  -- 截止日期 YYYY-MM-DD
+            due_note TEXT,
+            due_milestone_id INTEGER,  -- 由截止日期自动创建的 deadline 里程碑
+            needs_review BOOLEAN DEFAULT 0,  -- 超过规模上限，建议拆分；不再按主题归入新邮件
+            review_reason TEXT,
+            review_flagged_at DATETIME,
+            review_dismissed_at DATETIME,  -- 用户忽略提醒的时间，之后 7 天内不再标记
+            auto_key TEXT,  -- 分类器自动创建时的规范化名称，与 auto_account_id 唯一，避免并发同步重复创建
+            auto_account_id INTEGER,
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
             updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
         );
@@ -73,13 +188,24 @@ pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
         -- Emails Table
         CREATE TABLE IF NOT EXISTS emails (
             id INTEGER PRIMARY KEY,
-            message_id TEXT UNIQUE NOT NULL,
+            message_id TEXT NOT NULL,
             account_id INTEGER,
             thread_id TEXT,
+            in_reply_to TEXT,  -- In-Reply-To 头（不含尖括号），用于计算线程内的回复层级
             project_id INTEGER,
             subject TEXT,
-            sender TEXT,
+            sender TEXT,  -- "Name <addr>"，仅用于显示
+            sender_name TEXT,  -- 发件人显示名称
+            sender_address TEXT,  -- 发件人地址（小写）
             recipients TEXT,
+            cc TEXT,  -- 抄送（JSON 数组）
+            is_cc_only BOOLEAN DEFAULT 0,  -- 自己只在抄送中，分拣时降低优先级
+            lang TEXT,  -- 识别的语言（ISO 639-1，无法识别为 und，NULL 表示尚未识别）
+            preview TEXT,  -- 保存时计算的一行预览（去掉引用、链接和编码残留），NULL 表示尚未计算
+            server_deleted_at DATETIME,  -- 服务器上已删除的时间（墓碑，默认隐藏、不计数，可恢复），NULL 表示仍在服务器上
+            direction TEXT DEFAULT 'incoming',  -- incoming / outgoing（从已发送文件夹补充的自己的回复）
+            gmail_labels TEXT,  -- Gmail 标签（JSON 数组，X-GM-LABELS），非 Gmail 服务器为 NULL
+            gm_msgid TEXT,  -- Gmail 跨文件夹不变的邮件 ID（X-GM-MSGID），用于去重
             date DATETIME,
             body_text TEXT,
             body_html TEXT,
@@ -87,9 +213,17 @@ pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
             is_read BOOLEAN DEFAULT 0,
             is_starred BOOLEAN DEFAULT 0,
             raw_path TEXT,
+            content_fingerprint TEXT,  -- 归一化内容指纹，用于跨账户去重
+            duplicate_of INTEGER,  -- 重复邮件指向的规范邮件 ID
+            body_state TEXT DEFAULT 'full',  -- 'full' | 'remote'（仅同步了邮件头）| 'missing'（服务器已删除）
+            body_truncated BOOLEAN DEFAULT 0,  -- 正文超过大小上限，数据库中为截断内容
+            body_path TEXT,  -- 完整正文文件（相对 bodies 目录）
+            is_automated BOOLEAN DEFAULT 0,  -- 自动发送的通知邮件（CI、签名提醒等），时间线中折叠
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (account_id, message_id),
             FOREIGN KEY (account_id) REFERENCES accounts(id),
-            FOREIGN KEY (project_id) REFERENCES projects(id)
+            FOREIGN KEY (project_id) REFERENCES projects(id),
+            FOREIGN KEY (duplicate_of) REFERENCES emails(id)
         );
 
         -- Attachments Table
@@ -101,6 +235,7 @@ pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
             file_type TEXT,
             file_size INTEGER,
             mime_type TEXT,
+            detected_mime TEXT,  -- 按文件头识别的类型（无法识别时为空字符串，NULL 表示尚未识别）
             file_path TEXT,
             content_hash TEXT,
             parsed_content_path TEXT,
@@ -109,6 +244,15 @@ pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
             index_reason TEXT,
             indexed_at DATETIME,
             status TEXT,
+            is_starred BOOLEAN DEFAULT 0,
+            danger_level TEXT DEFAULT 'safe',  -- safe / suspicious / dangerous
+            integrity_status TEXT,  -- ok / broken / corrupt / missing_remote
+            integrity_reason TEXT,
+            origin TEXT DEFAULT 'email',  -- email（邮件附件）/ manual（手动添加到项目的文件，email_id 为空）
+            note TEXT,  -- 手动添加时的备注
+            part_path TEXT,  -- 在原始邮件中的 IMAP 部分路径（如 2.3），用于只重新下载这个部分
+            transfer_encoding TEXT,  -- 部分的传输编码：base64 / quoted-printable / 7bit / 8bit / binary
+            declared_size INTEGER,  -- 部分编码后的大小（与 BODYSTRUCTURE 中的大小一致）
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
             FOREIGN KEY (email_id) REFERENCES emails(id),
             FOREIGN KEY (project_id) REFERENCES projects(id)
@@ -126,6 +270,310 @@ pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
             FOREIGN KEY (email_id) REFERENCES emails(id)
         );
 
+        -- Contacts Table（从同步历史收集，用于收件人自动补全）
+        CREATE TABLE IF NOT EXISTS contacts (
+            id INTEGER PRIMARY KEY,
+            address TEXT NOT NULL UNIQUE,  -- 小写邮箱地址
+            name TEXT,
+            address_folded TEXT NOT NULL,  -- 小写、去除变音符号，用于前缀匹配
+            name_folded TEXT,
+            last_interaction TEXT,  -- YYYY-MM-DD HH:MM:SS (UTC)
+            received_count INTEGER DEFAULT 0,  -- 收到该联系人的邮件数
+            sent_count INTEGER DEFAULT 0,  -- 发给该联系人的邮件数
+            is_muted BOOLEAN DEFAULT 0,  -- 屏蔽后不出现在建议中
+            canonical_id INTEGER REFERENCES contacts(id)  -- 合并后指向主联系人（NULL 表示自身即主联系人）
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_contacts_address_folded ON contacts(address_folded);
+        CREATE INDEX IF NOT EXISTS idx_contacts_name_folded ON contacts(name_folded);
+
+        -- VIP Contacts Table（VIP 发件人，按小写地址匹配，合并联系人的别名同样算 VIP）
+        CREATE TABLE IF NOT EXISTS vip_contacts (
+            address TEXT PRIMARY KEY,  -- 小写邮箱地址（与 emails.sender_address 一致）
+            name TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Project Preferences Table
+        CREATE TABLE IF NOT EXISTS project_preferences (
+            project_id INTEGER PRIMARY KEY,
+            default_tab TEXT,  -- timeline / artifacts
+            sort_order TEXT,  -- newest_first / oldest_first
+            collapsed_threads BOOLEAN DEFAULT 0,
+            custom TEXT,  -- 前端自定义 JSON
+            extra TEXT,  -- 未识别字段（JSON object），向前兼容
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (project_id) REFERENCES projects(id)
+        );
+
+        -- Sender Rules Table（按发件人纠正自动邮件识别）
+        CREATE TABLE IF NOT EXISTS sender_rules (
+            pattern TEXT PRIMARY KEY,  -- 小写邮箱地址或 @域名
+            is_automated BOOLEAN NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Templates Table（常用回复模板）
+        CREATE TABLE IF NOT EXISTS templates (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            subject_template TEXT NOT NULL DEFAULT '',  -- 为空时使用 "Re: 原主题"
+            body_template TEXT NOT NULL DEFAULT '',  -- 支持 {{sender_name}}、{{project_name|默认值}} 等占位符
+            is_html BOOLEAN DEFAULT 0,  -- 正文为 HTML（替换的值会做 HTML 转义）
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Remote Content Cache Table（离线预取的远程图片，按内容哈希去重）
+        CREATE TABLE IF NOT EXISTS remote_content_cache (
+            file_name TEXT PRIMARY KEY,  -- {sha256}.{ext}，位于 remote_cache/ 目录
+            url TEXT NOT NULL,  -- 首次下载时的地址
+            mime_type TEXT,
+            size INTEGER NOT NULL,
+            last_accessed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE IF NOT EXISTS remote_content_refs (
+            email_id INTEGER NOT NULL,
+            url TEXT NOT NULL,  -- 正文中的原始地址，淘汰时恢复
+            file_name TEXT NOT NULL,
+            PRIMARY KEY (email_id, url),
+            FOREIGN KEY (email_id) REFERENCES emails(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_remote_content_refs_file ON remote_content_refs(file_name);
+
+        -- Email Receipts Table（已读回执 / 投递状态通知，不作为邮件保存）
+        CREATE TABLE IF NOT EXISTS email_receipts (
+            id INTEGER PRIMARY KEY,
+            account_id INTEGER,
+            message_id TEXT NOT NULL UNIQUE,  -- 回执邮件自身的 Message-ID
+            original_message_id TEXT NOT NULL,  -- 被回执的邮件（按 Message-ID 关联，原邮件可能稍后才同步）
+            kind TEXT NOT NULL,  -- read / delivery
+            status TEXT NOT NULL,  -- MDN 的 disposition（displayed / deleted ...）或 DSN 的 action（delivered / failed ...）
+            reporter TEXT,  -- 回执发送方显示名称或地址
+            recipient TEXT,  -- Final-Recipient 地址
+            date TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (account_id) REFERENCES accounts(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_email_receipts_original ON email_receipts(original_message_id);
+
+        -- Account Identities Table（别名 / send-as 地址，一个地址只属于一个账户）
+        CREATE TABLE IF NOT EXISTS account_identities (
+            id INTEGER PRIMARY KEY,
+            account_id INTEGER NOT NULL,
+            email TEXT NOT NULL UNIQUE,  -- 小写地址
+            display_name TEXT,
+            is_default BOOLEAN DEFAULT 0,
+            smtp_override TEXT,  -- JSON 格式的 SMTP 配置，为空时使用账户服务商的配置
+            source TEXT NOT NULL DEFAULT 'manual',  -- manual / gmail
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (account_id) REFERENCES accounts(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_account_identities_account ON account_identities(account_id);
+
+        -- Sync Checkpoints Table（未完成的首次同步，完成后删除）
+        CREATE TABLE IF NOT EXISTS sync_checkpoints (
+            account_id INTEGER PRIMARY KEY,
+            first_uid INTEGER NOT NULL,  -- 目标 UID 范围
+            last_uid INTEGER NOT NULL,
+            last_completed_uid INTEGER NOT NULL DEFAULT 0,
+            total INTEGER NOT NULL,  -- 首次同步开始时的邮件总数（进度按它计算）
+            completed INTEGER NOT NULL DEFAULT 0,
+            failed INTEGER NOT NULL DEFAULT 0,
+            started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (account_id) REFERENCES accounts(id)
+        );
+
+        -- Project Templates Table（新建项目时套用的标签、里程碑、说明和视图偏好）
+        CREATE TABLE IF NOT EXISTS project_templates (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            tags TEXT,  -- JSON array
+            milestones TEXT,  -- JSON array: [{title, type, offset_days}]
+            notes_template TEXT,  -- 项目说明，{name} 替换为项目名称
+            due_offset_days INTEGER,  -- 截止日期 = 创建日期 + 天数
+            color TEXT,
+            icon TEXT,
+            preferences TEXT,  -- ProjectPreferences JSON
+            match_senders TEXT,  -- 自动创建项目时按发件人匹配（逗号分隔，地址或 @域名）
+            match_subject TEXT,  -- 自动创建项目时按主题关键词匹配
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Email Folders Table（邮件所在的文件夹；Gmail 中同一封邮件可在多个文件夹中）
+        CREATE TABLE IF NOT EXISTS email_folders (
+            email_id INTEGER NOT NULL,
+            folder TEXT NOT NULL,
+            namespace TEXT DEFAULT 'personal',  -- 文件夹所属的 IMAP 命名空间：personal / other / shared
+            uid INTEGER,  -- 邮件在该文件夹中的 UID
+            added_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (email_id, folder),
+            FOREIGN KEY (email_id) REFERENCES emails(id)
+        );
+
+        -- Calendar Events Table（邮件中的会议邀请，同一 UID 只保留最新版本）
+        CREATE TABLE IF NOT EXISTS calendar_events (
+            id INTEGER PRIMARY KEY,
+            account_id INTEGER NOT NULL,
+            email_id INTEGER,  -- 最新一封邀请邮件
+            uid TEXT NOT NULL,
+            sequence INTEGER NOT NULL DEFAULT 0,
+            summary TEXT,
+            location TEXT,
+            starts_at TEXT,  -- UTC 时间为 RFC 3339，带时区的时间保留为本地时间
+            ends_at TEXT,
+            organizer_address TEXT,
+            organizer_name TEXT,
+            raw_ics TEXT NOT NULL,
+            my_response TEXT,  -- accepted / declined / tentative，NULL 表示尚未回复
+            responded_sequence INTEGER,  -- 回复时的 SEQUENCE，小于 sequence 表示邀请在回复后有更新
+            responded_at DATETIME,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (account_id, uid),
+            FOREIGN KEY (account_id) REFERENCES accounts(id),
+            FOREIGN KEY (email_id) REFERENCES emails(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_calendar_events_email ON calendar_events(email_id);
+
+        -- Pending Authorizations Table（OAuth 授权完成但尚未创建账户的记录，令牌保存在钥匙串中，15 分钟后清除）
+        CREATE TABLE IF NOT EXISTS pending_authorizations (
+            id INTEGER PRIMARY KEY,
+            provider TEXT NOT NULL,  -- gmail / outlook
+            email_hint TEXT,  -- 授权前用户输入的邮箱
+            expires_in INTEGER,  -- 访问令牌有效期（秒，从 created_at 起算）
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Email Translations Table（翻译结果缓存）
+        CREATE TABLE IF NOT EXISTS email_translations (
+            email_id INTEGER NOT NULL,
+            target_lang TEXT NOT NULL,  -- 大写语言代码，如 EN、ZH-HANS
+            provider TEXT NOT NULL,  -- deepl / local
+            subject TEXT,
+            body TEXT NOT NULL,  -- 去掉引用内容后的正文译文
+            source_lang TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (email_id, target_lang, provider)
+        );
+
+        -- Attachment Trash Table（已删除附件的隔离区，文件在 trash/<日期>/ 中保留 30 天）
+        CREATE TABLE IF NOT EXISTS attachment_trash (
+            id INTEGER PRIMARY KEY,
+            attachment_id INTEGER,  -- 原附件记录 ID（记录已删除）
+            original_path TEXT NOT NULL,  -- 原 file_path（热存储相对路径或 cold: 路径）
+            trash_path TEXT NOT NULL,  -- 隔离区中的路径，相对应用数据目录
+            content_hash TEXT,
+            filename TEXT NOT NULL,
+            file_size INTEGER,
+            mime_type TEXT,
+            email_id INTEGER,
+            project_id INTEGER,
+            origin TEXT,
+            deleted_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Project Statuses Table（项目状态词表，可自定义）
+        CREATE TABLE IF NOT EXISTS project_statuses (
+            key TEXT PRIMARY KEY,
+            label TEXT NOT NULL,
+            color TEXT,
+            is_terminal BOOLEAN DEFAULT 0,  -- 终结状态（已完成 / 已归档），自动归档只从终结状态进行
+            sort_order INTEGER DEFAULT 0
+        );
+        INSERT OR IGNORE INTO project_statuses (key, label, color, is_terminal, sort_order) VALUES
+            ('active', 'Active', '#3B82F6', 0, 0),
+            ('waiting_on_client', 'Waiting on client', '#F59E0B', 0, 1),
+            ('waiting_on_me', 'Waiting on me', '#EF4444', 0, 2),
+            ('blocked', 'Blocked', '#6B7280', 0, 3),
+            ('done', 'Done', '#10B981', 1, 4),
+            ('archived', 'Archived', '#9CA3AF', 1, 5);
+
+        -- Notifications Table
+        CREATE TABLE IF NOT EXISTS notifications (
+            id INTEGER PRIMARY KEY,
+            title TEXT NOT NULL,
+            message TEXT NOT NULL,
+            level TEXT NOT NULL,  -- info / success / warning / error
+            source TEXT,  -- sync / attachment_repair / deep_link / project_lifecycle
+            related_entity TEXT,  -- 如 project:42、account:3
+            read BOOLEAN DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE INDEX IF NOT EXISTS idx_notifications_read ON notifications(read);
+
+        -- Sync Runs Table（每次同步的记录）
+        CREATE TABLE IF NOT EXISTS sync_runs (
+            id INTEGER PRIMARY KEY,
+            account_id INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'running',  -- running / completed / failed
+            metered BOOLEAN DEFAULT 0,  -- 是否以按流量计费模式运行（只同步邮件头）
+            emails_synced INTEGER DEFAULT 0,
+            bytes_transferred INTEGER DEFAULT 0,
+            error TEXT,
+            trace_path TEXT,  -- IMAP 协议跟踪文件（开启跟踪时）
+            metrics TEXT,  -- IMAP 会话指标（JSON）
+            projects_capped INTEGER DEFAULT 0,  -- 达到新建项目上限后保持未分类的邮件数
+            started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            finished_at DATETIME,
+            FOREIGN KEY (account_id) REFERENCES accounts(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_sync_runs_account ON sync_runs(account_id, id);
+
+        -- Undo Log Table（归档、合并、移动、删除的逆操作）
+        CREATE TABLE IF NOT EXISTS undo_log (
+            id INTEGER PRIMARY KEY,
+            action TEXT NOT NULL,  -- archive_project / merge_projects / move_email_to_project / delete_project
+            description TEXT NOT NULL,
+            inverse TEXT NOT NULL,  -- JSON: UndoOperation
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            expires_at DATETIME NOT NULL,
+            undone_at DATETIME
+        );
+
+        -- Organization Snapshots Table
+        CREATE TABLE IF NOT EXISTS organization_snapshots (
+            id INTEGER PRIMARY KEY,
+            label TEXT NOT NULL,
+            project_count INTEGER DEFAULT 0,
+            email_count INTEGER DEFAULT 0,
+            data TEXT NOT NULL,  -- JSON: projects, (account_id, message_id, project_id) assignments, milestones
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Classification Log Table
+        CREATE TABLE IF NOT EXISTS classification_log (
+            id INTEGER PRIMARY KEY,
+            email_id INTEGER NOT NULL,
+            project_id INTEGER,
+            method TEXT NOT NULL,  -- 'thread' / 'subject' / 'label' / 'rule' / 'manual' / 'new' / 'duplicate'
+            matched_value TEXT,
+            confidence REAL,
+            alternatives TEXT,  -- JSON array of candidates considered but not chosen
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (email_id) REFERENCES emails(id),
+            FOREIGN KEY (project_id) REFERENCES projects(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_classification_log_email ON classification_log(email_id);
+
+        -- Classification Corrections Table（手动改正自动分类，用于计算分类准确率；不随分类日志清理）
+        CREATE TABLE IF NOT EXISTS classification_corrections (
+            id INTEGER PRIMARY KEY,
+            email_id INTEGER NOT NULL,
+            log_id INTEGER,  -- 被改正的分类日志记录
+            method TEXT NOT NULL,  -- 被改正的自动分类策略
+            from_project_id INTEGER,
+            to_project_id INTEGER,
+            classified_at DATETIME,  -- 自动分类的时间
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (email_id) REFERENCES emails(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_classification_corrections_log ON classification_corrections(log_id);
+        CREATE INDEX IF NOT EXISTS idx_classification_log_created ON classification_log(created_at);
+
         -- Sync Settings Table
         CREATE TABLE IF NOT EXISTS sync_settings (
             id INTEGER PRIMARY KEY CHECK (id = 1),  -- 单例模式，只允许一条记录
@@ -133,6 +581,54 @@ pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
             auto_sync_enabled BOOLEAN DEFAULT 1,  -- 是否自动同步
             sync_interval_minutes INTEGER DEFAULT 15,  -- 自动同步间隔（分钟）
             sync_attachments BOOLEAN DEFAULT 1,  -- 是否同步附件
+            show_duplicates BOOLEAN DEFAULT 0,  -- 是否在时间线/收件箱中显示重复邮件
+            backfill_batch_size INTEGER DEFAULT 50,  -- 正文补全每批邮件数
+            backfill_hour INTEGER DEFAULT 3,  -- 正文补全每晚运行的时间（本地小时）
+            blocked_extensions TEXT,  -- 追加的危险扩展名（逗号分隔）
+            blocked_mime_types TEXT,  -- 追加的危险 MIME 类型（逗号分隔）
+            locale TEXT DEFAULT 'en',  -- 后端返回文本的语言（en / zh）
+            body_size_cap INTEGER DEFAULT 1048576,  -- 正文（文本 + HTML）大小上限（字节）
+            trash_retention_days INTEGER DEFAULT 30,  -- 回收站中项目的保留天数
+            auto_archive_days INTEGER DEFAULT 0,  -- 终结状态的项目多少天无更新后自动归档，0 表示不自动归档
+            mirror_deletions BOOLEAN DEFAULT 0,  -- 增量同步时把服务器上已删除的收件箱邮件标记为墓碑
+            server_deleted_retention_days INTEGER DEFAULT 30,  -- 墓碑邮件的保留天数，之后永久删除
+            utc_offset_minutes INTEGER,  -- 显示"今天/昨天"使用的 UTC 偏移（分钟），NULL 表示使用系统时区
+            translation_provider TEXT DEFAULT 'none',  -- 邮件翻译服务：none / deepl / local（API 密钥在系统钥匙串中）
+            translation_endpoint TEXT DEFAULT '',  -- 本地翻译服务地址（LibreTranslate 兼容）
+            bulk_action_cap INTEGER DEFAULT 500,  -- 搜索结果批量操作的邮件数上限，超出时需要显式确认
+            max_new_projects_per_sync INTEGER DEFAULT 100,  -- 每次同步最多自动新建的项目数，超出后未匹配的邮件保持未分类，0 表示不限
+            deleted_project_match TEXT DEFAULT 'restore',  -- 新邮件匹配到已删除项目时：restore 恢复 / new 新建项目
+            quiet_hours_enabled BOOLEAN DEFAULT 0,  -- 是否启用静默时段
+            quiet_hours_start TEXT DEFAULT '22:00',  -- 静默时段开始（本地时间 HH:MM）
+            quiet_hours_end TEXT DEFAULT '07:00',  -- 静默时段结束（本地时间 HH:MM，早于开始时间表示跨夜）
+            quiet_hours_days TEXT DEFAULT '1,2,3,4,5,6,7',  -- 静默时段生效的星期（1=周一，逗号分隔，按开始时间所在日计）
+            quiet_hours_allow_manual BOOLEAN DEFAULT 1,  -- 静默时段内是否允许手动同步
+            max_bandwidth_kbps INTEGER DEFAULT 0,  -- 同步下载带宽上限（KB/s），0 表示不限速
+            metered_mode BOOLEAN DEFAULT 0,  -- 按流量计费模式：不下载附件，只同步邮件头
+            detect_metered BOOLEAN DEFAULT 0,  -- 根据系统报告的按流量计费网络自动启用计费模式
+            generic_subjects TEXT DEFAULT 'hi,hello,hey,question,quick question,invoice,update,follow up,meeting,call,request,info,fyi,help,urgent,thanks,no subject',  -- 过于笼统的主题（逗号分隔），自动建项目时会在名称前加上发件人组织
+            remote_images TEXT DEFAULT 'block',  -- 远程图片：block 不加载 / allow 加载
+            prefetch_remote_images BOOLEAN DEFAULT 0,  -- 同步时为置顶项目的邮件预取远程图片（需 remote_images = allow）
+            remote_cache_max_mb INTEGER DEFAULT 200,  -- 远程内容缓存上限（MB），超出时按最近访问淘汰
+            proxy_url TEXT DEFAULT '',  -- HTTP(S) 代理地址，为空时直连
+            classifier_window_days INTEGER DEFAULT 30,  -- 按主题归类时只匹配最近 N 天内的邮件
+            classifier_min_subject_len INTEGER DEFAULT 3,  -- 规范化后的主题短于该字符数时不按主题归类
+            classifier_strip_ticket_ids TEXT DEFAULT '',  -- 规范化主题时额外去除的正则（每行一个），如 \[JIRA-\d+\]
+            cold_storage_path TEXT DEFAULT '',  -- 冷存储目录（外置磁盘等），为空表示未配置；旧附件迁移到此处
+            classifier_use_labels BOOLEAN DEFAULT 0,  -- 优先归入名称与 Gmail 标签相同的已有项目
+            imap_trace_enabled BOOLEAN DEFAULT 0,  -- 记录 IMAP 协议跟踪（凭据脱敏，用于诊断服务器兼容问题）
+            run_in_background BOOLEAN DEFAULT 0,  -- 关闭最后一个窗口时隐藏到托盘，后台同步继续运行
+            display_name TEXT DEFAULT '',  -- 我的名字（回复模板中的 {{my_name}}）
+            project_limit_weekly_emails INTEGER DEFAULT 150,  -- 单个项目最近 7 天的邮件数上限，超过后标记待检查并停止按主题归入，0 表示不限
+            project_limit_senders INTEGER DEFAULT 40,  -- 单个项目在主题归类时间窗口内的不同发件人数上限，0 表示不限
+            project_limit_subjects INTEGER DEFAULT 30,  -- 单个项目在主题归类时间窗口内的不同规范化主题数上限，0 表示不限
+            watch_folder_enabled BOOLEAN DEFAULT 0,  -- 启动时开始监视文件夹（导入放入的 .eml 文件）
+            watch_folder_path TEXT DEFAULT '',  -- 监视的文件夹
+            watch_folder_account_id INTEGER,  -- 导入的邮件归属的账户
+            watch_folder_delete_processed BOOLEAN DEFAULT 0,  -- 导入后删除文件，而不是移动到 processed/
+            sync_shared_mailboxes BOOLEAN DEFAULT 0,  -- 同步其他用户和共享命名空间中的文件夹（共享邮箱、公共文件夹）
+            shared_mailbox_auto_create BOOLEAN DEFAULT 1,  -- 共享邮箱中的邮件没有匹配项目时是否自动创建项目
+            version INTEGER DEFAULT 1,  -- 乐观并发版本号，每次更新加一
             created_at TEXT DEFAULT CURRENT_TIMESTAMP,
             updated_at TEXT DEFAULT CURRENT_TIMESTAMP
         );
@@ -141,9 +637,367 @@ pub async fn init_pool(app: &AppHandle) -> Result<SqlitePool> {
         INSERT OR IGNORE INTO sync_settings (id) VALUES (1);
         "#
     )
-    .execute(&pool)
+    .execute(pool)
     .await?;
 
-    log::info!("Database initialized successfully.");
-    Ok(pool)
+    if migrate(pool).await? {
+        // 迁移可能改变了邮件与项目的关系，重新计算统计
+        crate::repository::ProjectRepository::new(pool.clone())
+            .recompute_stats(None)
+            .await?;
+    }
+
+    // 旧数据库首次升级时，从已同步的邮件收集联系人
+    crate::mail::contacts::ContactBook::new(pool.clone())
+        .rebuild_if_empty()
+        .await?;
+
+    Ok(())
+}
+
+/// 对已有数据库执行增量迁移
+///
+/// 新建的数据库已经包含最新的表结构，这里只处理旧版本数据库缺失的列和约束。
+/// 返回是否执行了任何迁移。
+async fn migrate(pool: &SqlitePool) -> Result<bool> {
+    let mut migrated = false;
+
+    migrated |= rebuild_emails_unique_constraint(pool).await?;
+
+    migrated |= add_column_if_missing(pool, "emails", "content_fingerprint", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "emails", "duplicate_of", "INTEGER REFERENCES emails(id)").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "show_duplicates", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "projects", "icon", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "attachments", "is_starred", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "emails", "body_state", "TEXT DEFAULT 'full'").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "backfill_batch_size", "INTEGER DEFAULT 50").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "backfill_hour", "INTEGER DEFAULT 3").await?;
+    migrated |= add_column_if_missing(pool, "projects", "pin_order", "INTEGER").await?;
+    migrated |= add_column_if_missing(pool, "attachments", "danger_level", "TEXT DEFAULT 'safe'").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "blocked_extensions", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "blocked_mime_types", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "locale", "TEXT DEFAULT 'en'").await?;
+    migrated |= add_column_if_missing(pool, "attachments", "integrity_status", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "attachments", "integrity_reason", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "version", "INTEGER DEFAULT 1").await?;
+    migrated |= add_column_if_missing(pool, "projects", "status_before_delete", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "projects", "deleted_at", "DATETIME").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "trash_retention_days", "INTEGER DEFAULT 30").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "deleted_project_match", "TEXT DEFAULT 'restore'").await?;
+    migrated |= add_column_if_missing(pool, "emails", "body_truncated", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "emails", "body_path", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "body_size_cap", "INTEGER DEFAULT 1048576").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "quiet_hours_enabled", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "quiet_hours_start", "TEXT DEFAULT '22:00'").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "quiet_hours_end", "TEXT DEFAULT '07:00'").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "quiet_hours_days", "TEXT DEFAULT '1,2,3,4,5,6,7'").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "quiet_hours_allow_manual", "BOOLEAN DEFAULT 1").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "max_bandwidth_kbps", "INTEGER DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "metered_mode", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "detect_metered", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "attachments", "origin", "TEXT DEFAULT 'email'").await?;
+    migrated |= add_column_if_missing(pool, "attachments", "note", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "generic_subjects", "TEXT DEFAULT 'hi,hello,hey,question,quick question,invoice,update,follow up,meeting,call,request,info,fyi,help,urgent,thanks,no subject'").await?;
+    migrated |= add_column_if_missing(pool, "emails", "is_automated", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "emails", "cc", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "emails", "is_cc_only", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "emails", "lang", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "emails", "direction", "TEXT DEFAULT 'incoming'").await?;
+    migrated |= add_column_if_missing(pool, "emails", "gmail_labels", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "remote_images", "TEXT DEFAULT 'block'").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "prefetch_remote_images", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "remote_cache_max_mb", "INTEGER DEFAULT 200").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "proxy_url", "TEXT DEFAULT ''").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "classifier_window_days", "INTEGER DEFAULT 30").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "classifier_min_subject_len", "INTEGER DEFAULT 3").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "classifier_strip_ticket_ids", "TEXT DEFAULT ''").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "cold_storage_path", "TEXT DEFAULT ''").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "classifier_use_labels", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "imap_trace_enabled", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "sync_runs", "trace_path", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "run_in_background", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "display_name", "TEXT DEFAULT ''").await?;
+    migrated |= add_column_if_missing(pool, "contacts", "canonical_id", "INTEGER REFERENCES contacts(id)").await?;
+    migrated |= add_column_if_missing(pool, "projects", "due_date", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "projects", "due_note", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "projects", "due_milestone_id", "INTEGER").await?;
+    migrated |= add_column_if_missing(pool, "projects", "needs_review", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "projects", "review_reason", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "projects", "review_flagged_at", "DATETIME").await?;
+    migrated |= add_column_if_missing(pool, "projects", "review_dismissed_at", "DATETIME").await?;
+    migrated |= add_column_if_missing(pool, "emails", "sender_name", "TEXT").await?;
+    if add_column_if_missing(pool, "emails", "sender_address", "TEXT").await? {
+        backfill_sender_columns(pool).await?;
+        migrated = true;
+    }
+    migrated |= add_column_if_missing(pool, "sync_settings", "project_limit_weekly_emails", "INTEGER DEFAULT 150").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "project_limit_senders", "INTEGER DEFAULT 40").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "project_limit_subjects", "INTEGER DEFAULT 30").await?;
+    migrated |= add_column_if_missing(pool, "emails", "in_reply_to", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "attachments", "detected_mime", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "sync_runs", "metrics", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "emails", "gm_msgid", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "accounts", "quota_used_kb", "INTEGER").await?;
+    migrated |= add_column_if_missing(pool, "accounts", "quota_limit_kb", "INTEGER").await?;
+    migrated |= add_column_if_missing(pool, "accounts", "quota_checked_at", "DATETIME").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "watch_folder_enabled", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "watch_folder_path", "TEXT DEFAULT ''").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "watch_folder_account_id", "INTEGER").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "watch_folder_delete_processed", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "accounts", "namespaces", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "email_folders", "namespace", "TEXT DEFAULT 'personal'").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "sync_shared_mailboxes", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "shared_mailbox_auto_create", "BOOLEAN DEFAULT 1").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "auto_archive_days", "INTEGER DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "emails", "preview", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "accounts", "inbox_uid_validity", "INTEGER").await?;
+    migrated |= add_column_if_missing(pool, "emails", "server_deleted_at", "DATETIME").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "mirror_deletions", "BOOLEAN DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "server_deleted_retention_days", "INTEGER DEFAULT 30").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "utc_offset_minutes", "INTEGER").await?;
+    migrated |= add_column_if_missing(pool, "projects", "auto_key", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "projects", "auto_account_id", "INTEGER").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "translation_provider", "TEXT DEFAULT 'none'").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "translation_endpoint", "TEXT DEFAULT ''").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "bulk_action_cap", "INTEGER DEFAULT 500").await?;
+    migrated |= add_column_if_missing(pool, "sync_settings", "max_new_projects_per_sync", "INTEGER DEFAULT 100").await?;
+    migrated |= add_column_if_missing(pool, "sync_runs", "projects_capped", "INTEGER DEFAULT 0").await?;
+    migrated |= add_column_if_missing(pool, "attachments", "part_path", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "attachments", "transfer_encoding", "TEXT").await?;
+    migrated |= add_column_if_missing(pool, "attachments", "declared_size", "INTEGER").await?;
+
+    sqlx::query(
+        r#"
+        CREATE INDEX IF NOT EXISTS idx_emails_message_id ON emails(message_id);
+        CREATE INDEX IF NOT EXISTS idx_emails_fingerprint ON emails(content_fingerprint);
+        CREATE INDEX IF NOT EXISTS idx_emails_duplicate_of ON emails(duplicate_of);
+        CREATE INDEX IF NOT EXISTS idx_emails_body_state ON emails(account_id, body_state);
+        CREATE INDEX IF NOT EXISTS idx_contacts_canonical ON contacts(canonical_id);
+        CREATE INDEX IF NOT EXISTS idx_emails_sender_address ON emails(sender_address);
+        CREATE INDEX IF NOT EXISTS idx_emails_gm_msgid ON emails(account_id, gm_msgid);
+        -- 按解析后的时间排序（RFC 3339 带时区与 SQLite 格式混合时按字符串排序会错乱）
+        CREATE INDEX IF NOT EXISTS idx_emails_date_order ON emails(julianday(date));
+        CREATE INDEX IF NOT EXISTS idx_emails_project_date_order ON emails(project_id, julianday(date));
+        -- 快速切换器：覆盖索引，扫描时不回表
+        CREATE INDEX IF NOT EXISTS idx_projects_quick_switch ON projects(status, name, tags, updated_at, is_pinned, color, icon);
+        -- 分类器自动创建的项目：同一账户同一规范化名称只有一个
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_projects_auto_key ON projects(auto_account_id, auto_key) WHERE auto_key IS NOT NULL;
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // 全文索引表和同步触发器（需在 emails 表重建之后创建）
+    crate::search::indexer::ensure_schema(pool).await?;
+
+    Ok(migrated)
+}
+
+/// 如果列不存在则添加（SQLite 不支持 ADD COLUMN IF NOT EXISTS），返回是否添加
+async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<bool> {
+    let columns: Vec<(String,)> = sqlx::query_as(
+        "SELECT name FROM pragma_table_info(?)"
+    )
+    .bind(table)
+    .fetch_all(pool)
+    .await?;
+
+    if columns.iter().any(|(name,)| name == column) {
+        return Ok(false);
+    }
+
+    log::info!("Migrating: adding column {}.{}", table, column);
+    sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+        .execute(pool)
+        .await?;
+
+    Ok(true)
+}
+
+/// 从旧的 "Name <addr>" 字符串填充 sender_name / sender_address
+pub(crate) async fn backfill_sender_columns(pool: &SqlitePool) -> Result<()> {
+    let rows: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT id, sender FROM emails WHERE sender IS NOT NULL AND sender_address IS NULL"
+    )
+    .fetch_all(pool)
+    .await?;
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    log::info!("Migrating: splitting sender into name and address for {} emails", rows.len());
+    let mut tx = pool.begin().await?;
+    for (id, sender) in &rows {
+        let (name, address) = crate::mail::recipients::split_sender(sender);
+        sqlx::query("UPDATE emails SET sender_name = ?, sender_address = ? WHERE id = ?")
+            .bind(name)
+            .bind(address)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// 旧版本的 emails 表对 message_id 做了全局唯一约束，
+/// 导致同一封邮件出现在多个账户时会互相覆盖。
+/// 这里将其重建为 (account_id, message_id) 联合唯一。
+async fn rebuild_emails_unique_constraint(pool: &SqlitePool) -> Result<bool> {
+    let table_sql: Option<(String,)> = sqlx::query_as(
+        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'emails'"
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let needs_rebuild = table_sql
+        .map(|(sql,)| sql.contains("message_id TEXT UNIQUE NOT NULL"))
+        .unwrap_or(false);
+
+    if !needs_rebuild {
+        return Ok(false);
+    }
+
+    log::info!("Migrating: rebuilding emails table with per-account message_id uniqueness");
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"
+        CREATE TABLE emails_new (
+            id INTEGER PRIMARY KEY,
+            message_id TEXT NOT NULL,
+            account_id INTEGER,
+            thread_id TEXT,
+            project_id INTEGER,
+            subject TEXT,
+            sender TEXT,
+            recipients TEXT,
+            date DATETIME,
+            body_text TEXT,
+            body_html TEXT,
+            has_attachments BOOLEAN,
+            is_read BOOLEAN DEFAULT 0,
+            is_starred BOOLEAN DEFAULT 0,
+            raw_path TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (account_id, message_id),
+            FOREIGN KEY (account_id) REFERENCES accounts(id),
+            FOREIGN KEY (project_id) REFERENCES projects(id)
+        );
+
+        INSERT INTO emails_new (
+            id, message_id, account_id, thread_id, project_id, subject, sender, recipients,
+            date, body_text, body_html, has_attachments, is_read, is_starred, raw_path, created_at
+        )
+        SELECT
+            id, message_id, account_id, thread_id, project_id, subject, sender, recipients,
+            date, body_text, body_html, has_attachments, is_read, is_starred, raw_path, created_at
+        FROM emails;
+
+        DROP TABLE emails;
+        ALTER TABLE emails_new RENAME TO emails;
+        "#
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// 测试用的临时数据库（与应用相同的连接参数和表结构）
+///
+/// 数据库文件（含 -wal / -shm）放在返回的临时目录中，目录在 drop 时连同文件一起删除，
+/// 调用方需要在使用连接池期间持有它。
+#[cfg(test)]
+pub(crate) async fn test_pool() -> (SqlitePool, tempfile::TempDir) {
+    let dir = tempfile::Builder::new()
+        .prefix("threadline-test-")
+        .tempdir()
+        .expect("create test database directory");
+    let db_path = dir.path().join("threadline.db");
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(MAX_READER_CONNECTIONS)
+        .connect_with(connect_options(&db_path).expect("test database options"))
+        .await
+        .expect("open test database");
+    create_schema(&pool).await.expect("create test schema");
+    (pool, dir)
+}
+
+/// 测试数据工厂：只填写测试关心的列，其余列取表的默认值，返回新行 ID
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use sqlx::SqlitePool;
+
+    pub(crate) async fn insert_account(pool: &SqlitePool, email: &str) -> i64 {
+        sqlx::query("INSERT INTO accounts (email) VALUES (?)")
+            .bind(email)
+            .execute(pool)
+            .await
+            .unwrap()
+            .last_insert_rowid()
+    }
+
+    pub(crate) async fn insert_project(pool: &SqlitePool, name: &str) -> i64 {
+        sqlx::query("INSERT INTO projects (name) VALUES (?)")
+            .bind(name)
+            .execute(pool)
+            .await
+            .unwrap()
+            .last_insert_rowid()
+    }
+
+    /// 待插入的邮件，未设置的列为 NULL（`created_at` 为当前时间）
+    #[derive(Default)]
+    pub(crate) struct NewEmail<'a> {
+        pub id: Option<i64>,
+        pub account_id: Option<i64>,
+        pub message_id: &'a str,
+        pub thread_id: Option<&'a str>,
+        pub project_id: Option<i64>,
+        pub duplicate_of: Option<i64>,
+        pub subject: Option<&'a str>,
+        pub sender: Option<&'a str>,
+        pub date: Option<&'a str>,
+        pub body_text: Option<&'a str>,
+        pub preview: Option<&'a str>,
+        pub has_attachments: Option<bool>,
+        pub created_at: Option<&'a str>,
+    }
+
+    pub(crate) async fn insert_email(pool: &SqlitePool, email: NewEmail<'_>) -> i64 {
+        sqlx::query(
+            r#"
+            INSERT INTO emails (
+                id, account_id, message_id, thread_id, project_id, duplicate_of, subject, sender,
+                date, body_text, preview, has_attachments, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP))
+            "#
+        )
+        .bind(email.id)
+        .bind(email.account_id)
+        .bind(email.message_id)
+        .bind(email.thread_id)
+        .bind(email.project_id)
+        .bind(email.duplicate_of)
+        .bind(email.subject)
+        .bind(email.sender)
+        .bind(email.date)
+        .bind(email.body_text)
+        .bind(email.preview)
+        .bind(email.has_attachments)
+        .bind(email.created_at)
+        .execute(pool)
+        .await
+        .unwrap()
+        .last_insert_rowid()
+    }
 }
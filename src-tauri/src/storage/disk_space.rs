@@ -0,0 +1,111 @@
+/// 磁盘空间检查
+///
+/// 附件和首次同步可能写满用户磁盘。大附件写入前、预计占用较大的首次同步开始前检查应用数据目录所在磁盘的
+/// 剩余空间，不足时返回 `AppError::DiskFull`（前端错误码 `DISK_FULL`），而不是写到一半失败成普通 IO 错误。
+/// 磁盘使用率达到 90% 时发出一次警告通知，回落后重新计数。空间不足时先提前清理已删除附件的隔离区。
+use crate::error::AppError;
+use crate::storage::{file_manager, quarantine};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 写入后至少保留的剩余空间
+pub const RESERVED_BYTES: u64 = 256 * 1024 * 1024;
+
+/// 超过该大小的写入先检查剩余空间
+pub const LARGE_WRITE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// 使用率达到该比例时发出警告
+pub const WARNING_RATIO: f64 = 0.9;
+
+/// 本次运行是否已发出磁盘空间警告
+static DISK_WARNING_SENT: AtomicBool = AtomicBool::new(false);
+
+/// 磁盘用量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskUsage {
+    /// 检查的目录
+    pub path: String,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+    pub used_ratio: f64,
+    /// 使用率达到警告阈值
+    pub warning: bool,
+}
+
+/// 目录所在磁盘的用量（目录尚未创建时检查最近的已存在上级目录）
+pub fn usage(path: &Path) -> Result<DiskUsage, AppError> {
+    let existing = existing_ancestor(path);
+    let total_bytes = fs2::total_space(&existing)?;
+    let available_bytes = fs2::available_space(&existing)?;
+    let used_ratio = if total_bytes == 0 {
+        0.0
+    } else {
+        1.0 - available_bytes as f64 / total_bytes as f64
+    };
+
+    Ok(DiskUsage {
+        path: path.display().to_string(),
+        total_bytes,
+        available_bytes,
+        used_ratio: (used_ratio * 1000.0).round() / 1000.0,
+        warning: used_ratio >= WARNING_RATIO,
+    })
+}
+
+/// 应用数据目录所在磁盘的用量
+pub fn app_data_usage() -> Result<DiskUsage, AppError> {
+    usage(&file_manager::app_data_dir()?)
+}
+
+/// 确认写入 `needed` 字节后仍保留 `RESERVED_BYTES`，否则返回 `DiskFull`
+///
+/// 空间不足时先提前清理已删除附件的隔离区，仍然不足才返回错误。
+pub fn ensure_available(path: &Path, needed: u64) -> Result<(), AppError> {
+    let required = needed.saturating_add(RESERVED_BYTES);
+    let mut available = fs2::available_space(existing_ancestor(path))?;
+    if available < required && quarantine::free_space(required - available) > 0 {
+        available = fs2::available_space(existing_ancestor(path))?;
+    }
+    if available < required {
+        log::warn!("Not enough disk space under {:?}: need {} bytes, {} available", path, needed, available);
+        return Err(AppError::DiskFull {
+            path: path.display().to_string(),
+            needed,
+            available,
+        });
+    }
+    Ok(())
+}
+
+/// 写文件失败时把"磁盘已满"转换为 `DiskFull`，其他错误保留为 IO 错误
+pub fn write_error(error: std::io::Error, path: &Path) -> AppError {
+    if error.kind() == std::io::ErrorKind::StorageFull {
+        AppError::DiskFull {
+            path: path.display().to_string(),
+            needed: 0,
+            available: fs2::available_space(existing_ancestor(path)).unwrap_or(0),
+        }
+    } else {
+        AppError::Io(error)
+    }
+}
+
+/// 磁盘使用率首次达到警告阈值时返回用量（同一次运行只返回一次，回落到阈值以下后重新计数）
+pub fn take_warning() -> Option<DiskUsage> {
+    let usage = app_data_usage()
+        .map_err(|e| log::debug!("Failed to check disk usage: {}", e))
+        .ok()?;
+    if !usage.warning {
+        DISK_WARNING_SENT.store(false, Ordering::Relaxed);
+        return None;
+    }
+    (!DISK_WARNING_SENT.swap(true, Ordering::Relaxed)).then_some(usage)
+}
+
+fn existing_ancestor(path: &Path) -> PathBuf {
+    path.ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(path)
+        .to_path_buf()
+}
@@ -1 +1,91 @@
-pub fn hello() {}
+/// 附件文件存储路径管理
+///
+/// 附件分为两层：默认的热存储（应用数据目录下的 attachments/）和可选的冷存储
+/// （设置中的 `cold_storage_path`，通常是外置磁盘）。冷存储中的附件在数据库中
+/// 以 `cold:` 前缀保存相对路径。
+use crate::error::AppError;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// 冷存储附件路径前缀
+pub const COLD_PREFIX: &str = "cold:";
+
+lazy_static::lazy_static! {
+    /// 当前配置的冷存储根目录（启动时和更新设置时写入）
+    static ref COLD_STORAGE_ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+}
+
+/// 获取应用数据目录（使用环境变量或默认路径）
+pub fn app_data_dir() -> Result<PathBuf, AppError> {
+    std::env::var("APPDATA")
+        .or_else(|_| std::env::var("HOME").map(|h| format!("{}/.config", h)))
+        .map(|p| PathBuf::from(p).join("com.threadline.app"))
+        .map_err(|e| AppError::Generic(format!("Failed to get app data directory: {}", e)))
+}
+
+/// 测试进程共用的数据目录（`APPDATA` 是进程级的，只设置一次，避免并行测试互相覆盖）
+#[cfg(test)]
+pub(crate) fn use_test_data_dir() -> PathBuf {
+    static DIR: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+    DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("threadline-test-appdata-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::env::set_var("APPDATA", &dir);
+        dir
+    })
+    .clone()
+}
+
+/// 附件存储根目录
+pub fn attachments_root() -> Result<PathBuf, AppError> {
+    Ok(app_data_dir()?.join("attachments"))
+}
+
+/// 设置冷存储根目录（空字符串表示未配置）
+pub fn set_cold_storage_root(path: &str) {
+    let path = path.trim();
+    let root = (!path.is_empty()).then(|| PathBuf::from(path));
+    *COLD_STORAGE_ROOT.write().unwrap_or_else(|e| e.into_inner()) = root;
+}
+
+/// 已配置的冷存储根目录（不检查是否挂载）
+pub fn cold_storage_root() -> Option<PathBuf> {
+    COLD_STORAGE_ROOT.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// 已挂载（目录存在）的冷存储根目录
+pub fn mounted_cold_storage_root() -> Result<PathBuf, AppError> {
+    match cold_storage_root() {
+        Some(root) if root.is_dir() => Ok(root),
+        Some(root) => Err(AppError::ColdStorageUnavailable(root.display().to_string())),
+        None => Err(AppError::ColdStorageUnavailable("no cold storage path configured".to_string())),
+    }
+}
+
+/// 是否为冷存储中的附件路径
+pub fn is_cold_path(stored: &str) -> bool {
+    stored.starts_with(COLD_PREFIX)
+}
+
+/// 将数据库中存储的附件相对路径解析为绝对路径
+///
+/// 冷存储路径在外置磁盘未挂载时返回 `ColdStorageUnavailable`。
+pub fn resolve_attachment_path(relative: &str) -> Result<PathBuf, AppError> {
+    match relative.strip_prefix(COLD_PREFIX) {
+        Some(cold) => Ok(mounted_cold_storage_root()?.join(cold)),
+        None => Ok(attachments_root()?.join(relative)),
+    }
+}
+
+/// 删除附件文件（文件不存在时忽略）
+pub async fn remove_attachment_file(relative: &str) -> Result<(), AppError> {
+    let path = resolve_attachment_path(relative)?;
+    match tokio::fs::remove_file(&path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(AppError::FileSystem(format!(
+            "Failed to remove attachment file {:?}: {}",
+            path, e
+        ))),
+    }
+}
@@ -70,6 +70,8 @@ pub async fn seed_mock_data(app: &AppHandle) -> anyhow::Result<()> {
         .execute(&pool)
         .await?;
 
+    super::database::backfill_sender_columns(&pool).await?;
+
     // 4. Insert Milestones for Project 1
     sqlx::query(&format!("INSERT INTO milestones (id, project_id, email_id, type, title, date) VALUES
         (1, 1, 4, 'signed', 'Contract Signed', '{} 14:30:00'),
@@ -1,7 +1,14 @@
+pub mod app_state;
+pub mod archive;
 pub mod database;
 pub mod file_manager;
 pub mod cache;
 pub mod mock_data;
+pub mod body_store;
+pub mod remote_content;
+pub mod cold_storage;
+pub mod disk_space;
+pub mod quarantine;
 
 pub struct StorageManager;
 
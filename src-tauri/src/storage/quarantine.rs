@@ -0,0 +1,384 @@
+/// 已删除附件的隔离区
+///
+/// 删除邮件、删除账户、清理回收站或删除手动添加的文件时，不再被任何附件记录引用的文件不直接删除，
+/// 而是移入应用数据目录下的 `trash/<日期>/`，并在 `attachment_trash` 中记录原路径、哈希和原附件记录。
+/// `QUARANTINE_DAYS` 天内可以恢复（重新建立附件记录），之后由每晚的后台任务永久删除。
+/// 写入前磁盘空间不足时，先从最早的日期开始提前清理隔离区，仍然不足才返回 `DiskFull`。
+use crate::error::AppError;
+use crate::mail::sync::calculate_sha256;
+use crate::repository::ProjectRepository;
+use crate::storage::{disk_space, file_manager};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+/// 隔离区文件的保留天数
+pub const QUARANTINE_DAYS: i64 = 30;
+
+/// 删除附件记录前读取的列（`RemovedAttachment`）
+pub const REMOVED_ATTACHMENT_COLUMNS: &str =
+    "id, email_id, project_id, filename, file_size, mime_type, file_path, content_hash, origin";
+
+/// 即将删除的附件记录（删除前读取，提交后交给 `quarantine_files`）
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RemovedAttachment {
+    pub id: i64,
+    pub email_id: Option<i64>,
+    pub project_id: Option<i64>,
+    pub filename: String,
+    pub file_size: Option<i64>,
+    pub mime_type: Option<String>,
+    pub file_path: Option<String>,
+    pub content_hash: Option<String>,
+    pub origin: Option<String>,
+}
+
+/// 隔离区中的文件
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct QuarantinedAttachment {
+    pub id: i64,
+    pub attachment_id: Option<i64>,
+    pub filename: String,
+    pub file_size: Option<i64>,
+    pub mime_type: Option<String>,
+    pub email_id: Option<i64>,
+    pub project_id: Option<i64>,
+    pub deleted_at: String,
+    /// 超过该时间后永久删除
+    pub expires_at: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct ManifestRow {
+    original_path: String,
+    trash_path: String,
+    content_hash: Option<String>,
+    filename: String,
+    file_size: Option<i64>,
+    mime_type: Option<String>,
+    email_id: Option<i64>,
+    project_id: Option<i64>,
+    origin: Option<String>,
+    expired: bool,
+}
+
+/// 隔离区根目录
+pub fn trash_root() -> Result<PathBuf, AppError> {
+    Ok(file_manager::app_data_dir()?.join("trash"))
+}
+
+/// 隔离区管理
+pub struct AttachmentQuarantine {
+    pool: SqlitePool,
+}
+
+impl AttachmentQuarantine {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 附件记录删除并提交后调用：不再被引用的文件移入隔离区（失败只记录日志）
+    pub async fn quarantine_files(&self, removed: &[RemovedAttachment]) {
+        for attachment in removed {
+            if let Err(e) = self.quarantine(attachment).await {
+                log::warn!("Failed to quarantine file of attachment {}: {}", attachment.id, e);
+            }
+        }
+    }
+
+    /// 把附件文件移入隔离区，返回隔离记录 ID（没有文件、仍被其他记录引用或文件已不存在时返回 None）
+    pub async fn quarantine(&self, attachment: &RemovedAttachment) -> Result<Option<i64>, AppError> {
+        let Some(relative) = attachment.file_path.as_deref() else {
+            return Ok(None);
+        };
+        // 发出的邮件按内容哈希复用已有文件，多条记录可能指向同一文件
+        let references: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE file_path = ?")
+            .bind(relative)
+            .fetch_one(&self.pool)
+            .await?;
+        if references > 0 {
+            log::debug!("Keeping attachment file {} still used by {} attachments", relative, references);
+            return Ok(None);
+        }
+
+        let source = file_manager::resolve_attachment_path(relative)?;
+        if !source.exists() {
+            return Ok(None);
+        }
+        let file_name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| attachment.filename.clone());
+        let trash_path = format!(
+            "trash/{}/{}_{}",
+            chrono::Local::now().format("%Y-%m-%d"),
+            attachment.id,
+            file_name
+        );
+        move_file(&source, &file_manager::app_data_dir()?.join(&trash_path)).await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO attachment_trash
+                (attachment_id, original_path, trash_path, content_hash, filename, file_size, mime_type,
+                 email_id, project_id, origin)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(attachment.id)
+        .bind(relative)
+        .bind(&trash_path)
+        .bind(&attachment.content_hash)
+        .bind(&attachment.filename)
+        .bind(attachment.file_size)
+        .bind(&attachment.mime_type)
+        .bind(attachment.email_id)
+        .bind(attachment.project_id)
+        .bind(&attachment.origin)
+        .execute(&self.pool)
+        .await?;
+
+        log::info!("Moved file of deleted attachment {} to {}", attachment.id, trash_path);
+        Ok(Some(result.last_insert_rowid()))
+    }
+
+    /// 隔离区中的文件（最近删除的在前）
+    pub async fn list(&self) -> Result<Vec<QuarantinedAttachment>, AppError> {
+        let entries = sqlx::query_as::<_, QuarantinedAttachment>(
+            r#"
+            SELECT id, attachment_id, filename, file_size, mime_type, email_id, project_id, deleted_at,
+                   datetime(deleted_at, '+' || ? || ' days') AS expires_at
+            FROM attachment_trash
+            ORDER BY deleted_at DESC, id DESC
+            "#
+        )
+        .bind(QUARANTINE_DAYS)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(entries)
+    }
+
+    /// 恢复隔离区中的文件，重新建立附件记录，返回新的附件 ID
+    ///
+    /// 原邮件仍存在时恢复为邮件附件；否则恢复为项目文件，项目为 `project_id`（未指定时使用原项目）。
+    pub async fn restore(&self, manifest_id: i64, project_id: Option<i64>) -> Result<i64, AppError> {
+        let row = sqlx::query_as::<_, ManifestRow>(
+            r#"
+            SELECT original_path, trash_path, content_hash, filename, file_size, mime_type, email_id, project_id, origin,
+                   julianday(deleted_at) < julianday('now', '-' || ? || ' days') AS expired
+            FROM attachment_trash
+            WHERE id = ?
+            "#
+        )
+        .bind(QUARANTINE_DAYS)
+        .bind(manifest_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::Validation(format!("Deleted attachment {} not found", manifest_id)))?;
+        if row.expired {
+            return Err(AppError::Validation(format!(
+                "Deleted attachment {} is older than {} days and can no longer be restored",
+                manifest_id, QUARANTINE_DAYS
+            )));
+        }
+
+        let source = file_manager::app_data_dir()?.join(&row.trash_path);
+        let data = match tokio::fs::read(&source).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(AppError::FileSystem(format!(
+                    "File of deleted attachment {} was purged to free disk space",
+                    manifest_id
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let hash = calculate_sha256(&data);
+        if row.content_hash.as_deref().is_some_and(|expected| expected != hash) {
+            return Err(AppError::FileSystem(format!(
+                "File of deleted attachment {} no longer matches its recorded hash",
+                manifest_id
+            )));
+        }
+
+        let email_id = match row.email_id {
+            Some(email_id) => sqlx::query_scalar::<_, i64>("SELECT id FROM emails WHERE id = ?")
+                .bind(email_id)
+                .fetch_optional(&self.pool)
+                .await?,
+            None => None,
+        };
+        let project_id = match project_id.or(row.project_id) {
+            Some(project_id) => sqlx::query_scalar::<_, i64>(
+                "SELECT id FROM projects WHERE id = ? AND status != 'deleted'"
+            )
+            .bind(project_id)
+            .fetch_optional(&self.pool)
+            .await?,
+            None => None,
+        };
+        if email_id.is_none() && project_id.is_none() {
+            return Err(AppError::Validation(
+                "The email of this attachment no longer exists; choose a project to restore it into".to_string(),
+            ));
+        }
+
+        // 恢复到热存储的原位置；原位置已有不同内容的文件时放到 restored/<隔离记录 ID>/
+        let mut relative = row.original_path.trim_start_matches(file_manager::COLD_PREFIX).to_string();
+        let mut target = file_manager::attachments_root()?.join(&relative);
+        let mut reuse_existing = false;
+        if target.exists() {
+            if tokio::fs::read(&target).await.is_ok_and(|existing| calculate_sha256(&existing) == hash) {
+                reuse_existing = true;
+            } else {
+                let file_name = Path::new(&relative)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| row.filename.clone());
+                relative = format!("restored/{}/{}", manifest_id, file_name);
+                target = file_manager::attachments_root()?.join(&relative);
+            }
+        }
+        if reuse_existing {
+            tokio::fs::remove_file(&source).await?;
+        } else {
+            disk_space::ensure_available(&target, data.len() as u64)?;
+            move_file(&source, &target).await?;
+        }
+
+        let origin = if email_id.is_some() {
+            row.origin.unwrap_or_else(|| "email".to_string())
+        } else {
+            "manual".to_string()
+        };
+        let mut tx = self.pool.begin().await?;
+        let attachment_id = sqlx::query(
+            r#"
+            INSERT INTO attachments (email_id, project_id, filename, file_size, mime_type, file_path, content_hash, origin)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(email_id)
+        .bind(project_id)
+        .bind(&row.filename)
+        .bind(row.file_size)
+        .bind(&row.mime_type)
+        .bind(&relative)
+        .bind(&hash)
+        .bind(&origin)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+        sqlx::query("DELETE FROM attachment_trash WHERE id = ?")
+            .bind(manifest_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        if let Some(project_id) = project_id {
+            ProjectRepository::new(self.pool.clone()).recompute_stats(Some(project_id)).await?;
+        }
+        log::info!("Restored deleted attachment {} as attachment {}", manifest_id, attachment_id);
+        Ok(attachment_id)
+    }
+
+    /// 永久删除超过保留期的文件，并清理文件已被提前清理的记录，返回删除的记录数
+    pub async fn purge(&self, retention_days: i64) -> Result<u64, AppError> {
+        let rows: Vec<(i64, String, bool)> = sqlx::query_as(
+            r#"
+            SELECT id, trash_path, julianday(deleted_at) < julianday('now', '-' || ? || ' days') AS expired
+            FROM attachment_trash
+            "#
+        )
+        .bind(retention_days.max(0))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let data_dir = file_manager::app_data_dir()?;
+        let mut purged = 0u64;
+        for (id, trash_path, expired) in rows {
+            let path = data_dir.join(&trash_path);
+            if expired {
+                match tokio::fs::remove_file(&path).await {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        log::warn!("Failed to remove quarantined file {:?}: {}", path, e);
+                        continue;
+                    }
+                }
+            } else if path.exists() {
+                continue;
+            }
+            sqlx::query("DELETE FROM attachment_trash WHERE id = ?")
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+            purged += 1;
+        }
+        remove_empty_dirs(&trash_root()?);
+
+        if purged > 0 {
+            log::info!("Purged {} quarantined attachment files", purged);
+        }
+        Ok(purged)
+    }
+}
+
+/// 磁盘空间不足时从最早的日期开始删除隔离区文件，直到释放 `needed` 字节，返回释放的字节数
+///
+/// 只删除文件，隔离记录由下一次 `purge` 清理。
+pub fn free_space(needed: u64) -> u64 {
+    let Ok(root) = trash_root() else { return 0 };
+    let Ok(entries) = std::fs::read_dir(&root) else { return 0 };
+    let mut days: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect();
+    days.sort();
+
+    let mut freed = 0u64;
+    for day in days {
+        let Ok(files) = std::fs::read_dir(&day) else { continue };
+        for file in files.flatten() {
+            let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            match std::fs::remove_file(file.path()) {
+                Ok(()) => freed += size,
+                Err(e) => log::warn!("Failed to remove quarantined file {:?}: {}", file.path(), e),
+            }
+            if freed >= needed {
+                break;
+            }
+        }
+        let _ = std::fs::remove_dir(&day);
+        if freed >= needed {
+            break;
+        }
+    }
+
+    if freed > 0 {
+        log::warn!("Low disk space: purged {} bytes of quarantined attachments early", freed);
+    }
+    freed
+}
+
+/// 移动文件（跨磁盘时复制后删除原文件）
+async fn move_file(source: &Path, target: &Path) -> Result<(), AppError> {
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    if tokio::fs::rename(source, target).await.is_ok() {
+        return Ok(());
+    }
+    tokio::fs::copy(source, target)
+        .await
+        .map_err(|e| disk_space::write_error(e, target))?;
+    tokio::fs::remove_file(source).await?;
+    Ok(())
+}
+
+/// 删除隔离区中已清空的日期目录
+fn remove_empty_dirs(root: &Path) {
+    let Ok(entries) = std::fs::read_dir(root) else { return };
+    for entry in entries.flatten() {
+        // 非空目录删除失败，忽略
+        let _ = std::fs::remove_dir(entry.path());
+    }
+}
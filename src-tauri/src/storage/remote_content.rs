@@ -0,0 +1,522 @@
+/// 远程图片离线缓存
+///
+/// 开启 `prefetch_remote_images`（且 `remote_images = allow`）后，同步时为置顶项目中的 HTML 邮件
+/// 下载引用的远程图片，按内容哈希保存到 `remote_cache/`，并把正文中的地址改写为 `tlcache://` 协议。
+/// 缓存超过上限时按最近访问时间淘汰，被淘汰图片的地址会在正文中恢复为原始 URL。
+use crate::error::AppError;
+use crate::storage::file_manager;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// 缓存文件的 URI 协议
+pub const CACHE_SCHEME: &str = "tlcache";
+
+/// 数据库中保存的缓存地址前缀（与平台无关，读取时转换）
+const STORED_PREFIX: &str = "tlcache://localhost/";
+
+/// 单张图片下载超时
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 单张图片大小上限
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// 每封邮件最多预取的图片数
+const MAX_IMAGES_PER_EMAIL: usize = 30;
+
+/// 不超过该尺寸的图片视为跟踪像素
+const TRACKING_PIXEL_MAX_SIZE: u32 = 1;
+
+/// 清理缓存的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheClearSummary {
+    pub files_removed: i64,
+    pub bytes_reclaimed: i64,
+    /// 恢复了原始图片地址的邮件数
+    pub emails_restored: i64,
+}
+
+/// 预取设置
+struct PrefetchSettings {
+    enabled: bool,
+    max_bytes: i64,
+    proxy_url: String,
+}
+
+/// 远程内容缓存
+pub struct RemoteContentCache {
+    pool: SqlitePool,
+}
+
+impl RemoteContentCache {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 预取单封邮件引用的远程图片，返回新缓存的图片数
+    ///
+    /// 只处理置顶项目中、正文未截断的 HTML 邮件。
+    pub async fn prefetch_email(&self, email_id: i64) -> Result<usize, AppError> {
+        let settings = self.settings().await?;
+        if !settings.enabled {
+            return Ok(0);
+        }
+
+        let html: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT e.body_html FROM emails e
+            JOIN projects p ON p.id = e.project_id
+            WHERE e.id = ? AND p.is_pinned = 1 AND COALESCE(e.body_truncated, 0) = 0
+            "#
+        )
+        .bind(email_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .flatten();
+        let Some(mut html) = html else {
+            return Ok(0);
+        };
+
+        let images = remote_images(&html);
+        if images.is_empty() {
+            return Ok(0);
+        }
+
+        let client = http_client(&settings.proxy_url)?;
+        let mut cached = 0;
+        for image in images.into_iter().take(MAX_IMAGES_PER_EMAIL) {
+            let file_name = match self.fetch(&client, &image.url).await {
+                Ok(Some(file_name)) => file_name,
+                Ok(None) => continue,
+                Err(e) => {
+                    log::debug!("Failed to prefetch {}: {}", image.url, e);
+                    continue;
+                }
+            };
+
+            sqlx::query("INSERT OR IGNORE INTO remote_content_refs (email_id, url, file_name) VALUES (?, ?, ?)")
+                .bind(email_id)
+                .bind(&image.raw)
+                .bind(&file_name)
+                .execute(&self.pool)
+                .await?;
+            let cached_url = format!("{}{}", STORED_PREFIX, file_name);
+            for quote in ['"', '\''] {
+                html = html.replace(
+                    &format!("{quote}{}{quote}", image.raw),
+                    &format!("{quote}{}{quote}", cached_url),
+                );
+            }
+            cached += 1;
+        }
+
+        if cached > 0 {
+            sqlx::query("UPDATE emails SET body_html = ? WHERE id = ?")
+                .bind(&html)
+                .bind(email_id)
+                .execute(&self.pool)
+                .await?;
+            log::info!("Prefetched {} remote images for email {}", cached, email_id);
+            self.evict(settings.max_bytes).await?;
+        }
+
+        Ok(cached)
+    }
+
+    /// 下载图片并写入缓存，返回缓存文件名；跟踪像素或非图片返回 None
+    async fn fetch(&self, client: &reqwest::Client, url: &str) -> Result<Option<String>, AppError> {
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| AppError::Network(e.to_string()))?;
+
+        let mime_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or(value).trim().to_lowercase())
+            .unwrap_or_default();
+        if !mime_type.starts_with("image/") {
+            return Ok(None);
+        }
+        if response.content_length().is_some_and(|length| length as usize > MAX_IMAGE_BYTES) {
+            return Ok(None);
+        }
+
+        let data = response.bytes().await.map_err(|e| AppError::Network(e.to_string()))?;
+        if data.len() > MAX_IMAGE_BYTES {
+            return Ok(None);
+        }
+        if let Some((width, height)) = image_dimensions(&data) {
+            if width <= TRACKING_PIXEL_MAX_SIZE && height <= TRACKING_PIXEL_MAX_SIZE {
+                return Ok(None);
+            }
+        }
+
+        let hash = format!("{:x}", Sha256::digest(&data));
+        let file_name = format!("{}.{}", hash, extension_for(&mime_type));
+        let path = cache_root()?.join(&file_name);
+        if !tokio::fs::try_exists(&path).await? {
+            tokio::fs::create_dir_all(cache_root()?).await?;
+            tokio::fs::write(&path, &data).await?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO remote_content_cache (file_name, url, mime_type, size)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(file_name) DO UPDATE SET last_accessed_at = CURRENT_TIMESTAMP
+            "#
+        )
+        .bind(&file_name)
+        .bind(url)
+        .bind(&mime_type)
+        .bind(data.len() as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(Some(file_name))
+    }
+
+    /// 按最近访问时间淘汰，直到缓存不超过上限
+    async fn evict(&self, max_bytes: i64) -> Result<(), AppError> {
+        let total: i64 = sqlx::query_scalar("SELECT COALESCE(SUM(size), 0) FROM remote_content_cache")
+            .fetch_one(&self.pool)
+            .await?;
+        if total <= max_bytes {
+            return Ok(());
+        }
+
+        let entries: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT file_name, size FROM remote_content_cache ORDER BY last_accessed_at ASC, created_at ASC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut remaining = total;
+        let mut evicted = 0;
+        for (file_name, size) in entries {
+            if remaining <= max_bytes {
+                break;
+            }
+            self.remove_entry(&file_name).await?;
+            remaining -= size;
+            evicted += 1;
+        }
+
+        log::info!("Evicted {} cached remote images ({} -> {} bytes)", evicted, total, remaining);
+        Ok(())
+    }
+
+    /// 删除缓存项：正文中的地址恢复为原始 URL，再删除文件，返回恢复的邮件数
+    async fn remove_entry(&self, file_name: &str) -> Result<i64, AppError> {
+        let refs: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT email_id, url FROM remote_content_refs WHERE file_name = ?"
+        )
+        .bind(file_name)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let cached_url = format!("{}{}", STORED_PREFIX, file_name);
+        let mut tx = self.pool.begin().await?;
+        for (email_id, url) in &refs {
+            sqlx::query("UPDATE emails SET body_html = replace(body_html, ?, ?) WHERE id = ?")
+                .bind(&cached_url)
+                .bind(url)
+                .bind(email_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("DELETE FROM remote_content_refs WHERE file_name = ?")
+            .bind(file_name)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM remote_content_cache WHERE file_name = ?")
+            .bind(file_name)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        match tokio::fs::remove_file(cache_root()?.join(file_name)).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("Failed to remove cached file {}: {}", file_name, e),
+        }
+
+        Ok(refs.len() as i64)
+    }
+
+    /// 删除不再被任何邮件引用的缓存项（邮件被永久删除后调用），返回删除的文件数
+    pub async fn remove_unreferenced(&self) -> Result<i64, AppError> {
+        let orphans: Vec<String> = sqlx::query_scalar(
+            "SELECT file_name FROM remote_content_cache WHERE file_name NOT IN (SELECT file_name FROM remote_content_refs)"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for file_name in &orphans {
+            self.remove_entry(file_name).await?;
+        }
+        if !orphans.is_empty() {
+            log::info!("Removed {} cached remote images no longer referenced by any email", orphans.len());
+        }
+        Ok(orphans.len() as i64)
+    }
+
+    /// 清空缓存（正文恢复为原始图片地址）
+    pub async fn clear(&self) -> Result<CacheClearSummary, AppError> {
+        let entries: Vec<(String, i64)> = sqlx::query_as("SELECT file_name, size FROM remote_content_cache")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut summary = CacheClearSummary::default();
+        let mut emails = HashSet::new();
+        for (file_name, size) in entries {
+            let email_ids: Vec<i64> = sqlx::query_scalar("SELECT email_id FROM remote_content_refs WHERE file_name = ?")
+                .bind(&file_name)
+                .fetch_all(&self.pool)
+                .await?;
+            emails.extend(email_ids);
+
+            self.remove_entry(&file_name).await?;
+            summary.files_removed += 1;
+            summary.bytes_reclaimed += size;
+        }
+        summary.emails_restored = emails.len() as i64;
+
+        log::info!(
+            "Cleared remote content cache: {} files, {} bytes",
+            summary.files_removed, summary.bytes_reclaimed
+        );
+        Ok(summary)
+    }
+
+    /// 记录正文中缓存图片的访问时间（供 LRU 淘汰）
+    pub async fn touch(&self, html: &str) -> Result<(), AppError> {
+        for file_name in cached_file_names(html) {
+            sqlx::query("UPDATE remote_content_cache SET last_accessed_at = CURRENT_TIMESTAMP WHERE file_name = ?")
+                .bind(file_name)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn settings(&self) -> Result<PrefetchSettings, AppError> {
+        let (remote_images, prefetch, max_mb, proxy_url): (String, bool, i64, String) = sqlx::query_as(
+            r#"
+            SELECT remote_images, prefetch_remote_images, remote_cache_max_mb, proxy_url
+            FROM sync_settings WHERE id = 1
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(PrefetchSettings {
+            enabled: prefetch && remote_images == "allow",
+            max_bytes: max_mb.max(0) * 1024 * 1024,
+            proxy_url,
+        })
+    }
+}
+
+/// 把正文中的缓存地址转换为当前平台 WebView 可加载的形式
+pub fn display_html(html: &str) -> String {
+    if cfg!(any(windows, target_os = "android")) {
+        html.replace(STORED_PREFIX, &format!("http://{}.localhost/", CACHE_SCHEME))
+    } else {
+        html.to_string()
+    }
+}
+
+/// `tlcache` 协议：返回缓存文件
+pub fn protocol_response(path: &str) -> tauri::http::Response<Vec<u8>> {
+    let file_name = path.trim_start_matches('/');
+    let valid = !file_name.is_empty()
+        && file_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.')
+        && !file_name.contains("..");
+
+    let file = valid
+        .then(|| cache_root().ok())
+        .flatten()
+        .and_then(|root| std::fs::read(root.join(file_name)).ok());
+
+    let builder = tauri::http::Response::builder();
+    let response = match file {
+        Some(data) => builder
+            .status(200)
+            .header("Content-Type", mime_for(file_name))
+            .header("Cache-Control", "max-age=86400")
+            .body(data),
+        None => builder.status(404).body(Vec::new()),
+    };
+    response.unwrap_or_else(|_| tauri::http::Response::new(Vec::new()))
+}
+
+/// 缓存目录
+pub fn cache_root() -> Result<PathBuf, AppError> {
+    Ok(file_manager::app_data_dir()?.join("remote_cache"))
+}
+
+/// 测试用：为邮件写入一张缓存图片及其引用，返回缓存文件路径
+#[cfg(test)]
+pub(crate) async fn cache_test_image(pool: &SqlitePool, email_id: i64, file_name: &str) -> PathBuf {
+    file_manager::use_test_data_dir();
+    let url = format!("https://example.com/{}", file_name);
+    let path = cache_root().unwrap().join(file_name);
+    tokio::fs::create_dir_all(cache_root().unwrap()).await.unwrap();
+    tokio::fs::write(&path, b"image").await.unwrap();
+
+    sqlx::query(
+        "INSERT OR IGNORE INTO remote_content_cache (file_name, url, mime_type, size) VALUES (?, ?, 'image/png', 5)"
+    )
+    .bind(file_name)
+    .bind(&url)
+    .execute(pool)
+    .await
+    .unwrap();
+    sqlx::query("INSERT INTO remote_content_refs (email_id, url, file_name) VALUES (?, ?, ?)")
+        .bind(email_id)
+        .bind(&url)
+        .bind(file_name)
+        .execute(pool)
+        .await
+        .unwrap();
+    path
+}
+
+fn http_client(proxy_url: &str) -> Result<reqwest::Client, AppError> {
+    let mut builder = reqwest::Client::builder().timeout(DOWNLOAD_TIMEOUT);
+    if !proxy_url.trim().is_empty() {
+        let proxy = reqwest::Proxy::all(proxy_url.trim())
+            .map_err(|e| AppError::Config(format!("Invalid proxy URL: {}", e)))?;
+        builder = builder.proxy(proxy);
+    }
+    builder
+        .build()
+        .map_err(|e| AppError::Network(format!("Failed to create HTTP client: {}", e)))
+}
+
+/// 正文中的远程图片
+struct RemoteImage {
+    /// 属性中的原始值（用于替换）
+    raw: String,
+    /// 解码 `&amp;` 后的下载地址
+    url: String,
+}
+
+/// 提取 `<img src="http(s)://...">`，跳过声明为 1x1 的图片
+fn remote_images(html: &str) -> Vec<RemoteImage> {
+    let lower = html.to_ascii_lowercase();
+    let mut seen = HashSet::new();
+    let mut images = Vec::new();
+
+    let mut offset = 0;
+    while let Some(start) = lower[offset..].find("<img") {
+        let tag_start = offset + start;
+        let tag_end = lower[tag_start..].find('>').map(|end| tag_start + end).unwrap_or(lower.len());
+        let tag = &html[tag_start..tag_end];
+        offset = tag_end;
+
+        let Some(src) = attribute(tag, "src") else {
+            continue;
+        };
+        if !(src.starts_with("http://") || src.starts_with("https://")) {
+            continue;
+        }
+        let is_pixel = |name: &str| {
+            attribute(tag, name)
+                .and_then(|value| value.trim_end_matches("px").parse::<u32>().ok())
+                .is_some_and(|value| value <= TRACKING_PIXEL_MAX_SIZE)
+        };
+        let declared_pixel = is_pixel("width") && is_pixel("height");
+        if declared_pixel || !seen.insert(src.to_string()) {
+            continue;
+        }
+
+        images.push(RemoteImage {
+            raw: src.to_string(),
+            url: src.replace("&amp;", "&"),
+        });
+    }
+
+    images
+}
+
+/// 读取标签属性值（支持双引号、单引号和无引号）
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let pattern = format!("{}=", name);
+    let mut search = 0;
+    while let Some(found) = lower[search..].find(&pattern) {
+        let index = search + found;
+        search = index + pattern.len();
+        // 属性名前必须是空白，避免 data-src 之类的误匹配
+        if !lower[..index].ends_with(|c: char| c.is_whitespace()) {
+            continue;
+        }
+
+        let rest = &tag[index + pattern.len()..];
+        return match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => rest[1..].split(quote).next(),
+            Some(_) => rest.split(|c: char| c.is_whitespace() || c == '>').next(),
+            None => None,
+        };
+    }
+    None
+}
+
+/// 正文中引用的缓存文件名
+fn cached_file_names(html: &str) -> Vec<&str> {
+    html.match_indices(STORED_PREFIX)
+        .filter_map(|(index, _)| {
+            let rest = &html[index + STORED_PREFIX.len()..];
+            rest.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.')).next()
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// 从 PNG / GIF 文件头读取尺寸
+fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if data.len() >= 24 && data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let width = u32::from_be_bytes(data[16..20].try_into().ok()?);
+        let height = u32::from_be_bytes(data[20..24].try_into().ok()?);
+        return Some((width, height));
+    }
+    if data.len() >= 10 && data.starts_with(b"GIF8") {
+        let width = u16::from_le_bytes([data[6], data[7]]) as u32;
+        let height = u16::from_le_bytes([data[8], data[9]]) as u32;
+        return Some((width, height));
+    }
+    None
+}
+
+fn extension_for(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/bmp" => "bmp",
+        _ => "img",
+    }
+}
+
+fn mime_for(file_name: &str) -> &'static str {
+    match file_name.rsplit('.').next().unwrap_or_default() {
+        "png" => "image/png",
+        "jpg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        _ => "application/octet-stream",
+    }
+}
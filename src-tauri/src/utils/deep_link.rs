@@ -0,0 +1,113 @@
+/// Deep link 解析与处理
+///
+/// 支持的链接格式：
+/// - `threadline://project/42` 打开指定项目
+use crate::error::AppError;
+use crate::events::notifications::SOURCE_DEEP_LINK;
+use crate::events::{EventEmitter, NavigateEvent, NotificationLevel};
+use crate::repository::ProjectRepository;
+use crate::storage::app_state::DatabaseState;
+use crate::utils::i18n::{tr, tr_with, Locale, Message};
+use tauri::{AppHandle, Manager};
+use url::Url;
+
+/// 应用注册的 URL scheme
+pub const SCHEME: &str = "threadline";
+
+/// Deep link 目标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLinkTarget {
+    Project(i64),
+}
+
+impl DeepLinkTarget {
+    /// 前端路由
+    pub fn route(&self) -> String {
+        match self {
+            DeepLinkTarget::Project(id) => format!("/projects/{}", id),
+        }
+    }
+}
+
+/// 解析 deep link
+pub fn parse_deep_link(link: &str) -> Result<DeepLinkTarget, AppError> {
+    let url = Url::parse(link)
+        .map_err(|e| AppError::Validation(format!("Invalid link {}: {}", link, e)))?;
+
+    if url.scheme() != SCHEME {
+        return Err(AppError::Validation(format!("Unsupported link scheme: {}", url.scheme())));
+    }
+
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|s| s.filter(|seg| !seg.is_empty()).collect())
+        .unwrap_or_default();
+
+    match (url.host_str(), segments.as_slice()) {
+        (Some("project"), [id]) => {
+            let id: i64 = id
+                .parse()
+                .map_err(|_| AppError::Validation(format!("Invalid project id in link: {}", id)))?;
+            if id <= 0 {
+                return Err(AppError::Validation(format!("Invalid project id in link: {}", id)));
+            }
+            Ok(DeepLinkTarget::Project(id))
+        }
+        _ => Err(AppError::Validation(format!("Unrecognized link: {}", link))),
+    }
+}
+
+/// 处理收到的 deep link：校验目标存在后向前端发送 `navigate` 事件，
+/// 无效链接发送通知而不是打开空白页面
+pub fn handle_deep_link(app: &AppHandle, link: &str) {
+    log::info!("Received deep link: {}", link);
+    let emitter = app.state::<EventEmitter>().inner().clone();
+
+    let app = app.clone();
+    let link = link.to_string();
+    tauri::async_runtime::spawn(async move {
+        // 冷启动时链接可能先于数据库初始化到达
+        let pool = app.state::<DatabaseState>().wait().await;
+        let locale = Locale::load(&pool).await;
+
+        let target = match parse_deep_link(&link) {
+            Ok(target) => target,
+            Err(e) => {
+                log::warn!("Rejected deep link {}: {}", link, e);
+                emitter.emit_notification_from(
+                    tr(locale, Message::InvalidLinkTitle),
+                    &e.to_string(),
+                    NotificationLevel::Warning,
+                    Some(SOURCE_DEEP_LINK),
+                    None,
+                );
+                return;
+            }
+        };
+
+        let DeepLinkTarget::Project(project_id) = target;
+        let repo = ProjectRepository::new(pool);
+        match repo.get_by_id(project_id).await {
+            Ok(_) => {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.unminimize();
+                    let _ = window.set_focus();
+                }
+                emitter.emit_navigate(NavigateEvent {
+                    route: target.route(),
+                    project_id: Some(project_id),
+                });
+            }
+            Err(e) => {
+                log::warn!("Deep link target not available: {}", e);
+                emitter.emit_notification_from(
+                    tr(locale, Message::ProjectNotFoundTitle),
+                    &tr_with(locale, Message::ProjectNotFoundBody, &[("id", &project_id.to_string())]),
+                    NotificationLevel::Warning,
+                    Some(SOURCE_DEEP_LINK),
+                    Some(&format!("project:{}", project_id)),
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,399 @@
+/// 后端返回文本的本地化
+///
+/// 语言从同步设置的 `locale` 列读取，未知语言回退到英文。
+/// 相对日期、通知标题和文件大小在后端格式化，原始 ISO 日期仍然一并返回，由前端选择使用。
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// 支持的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Zh,
+}
+
+impl Locale {
+    /// 解析语言标签（"zh-CN"、"zh_Hans"、"en-US" 等），未知语言回退到英文
+    pub fn from_tag(tag: &str) -> Self {
+        let primary = tag
+            .trim()
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match primary.as_str() {
+            "zh" => Locale::Zh,
+            _ => Locale::En,
+        }
+    }
+
+    /// 从设置读取当前语言
+    pub async fn load(pool: &SqlitePool) -> Self {
+        sqlx::query_scalar::<_, Option<String>>("SELECT locale FROM sync_settings WHERE id = 1")
+            .fetch_one(pool)
+            .await
+            .ok()
+            .flatten()
+            .map(|tag| Self::from_tag(&tag))
+            .unwrap_or_default()
+    }
+}
+
+/// 消息目录中的文本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    Unknown,
+    InvalidLinkTitle,
+    ProjectNotFoundTitle,
+    /// 参数：{id}
+    ProjectNotFoundBody,
+    TrayShowWindow,
+    TraySyncNow,
+    TrayPauseSync,
+    TrayRecentNotifications,
+    TrayNoNotifications,
+    TrayQuit,
+    /// 参数：{current}、{total}
+    TraySyncing,
+    /// 参数：{count}
+    TrayUnread,
+    /// 线程参与者中的自己
+    Me,
+}
+
+/// 获取本地化文本
+pub fn tr(locale: Locale, message: Message) -> &'static str {
+    match (locale, message) {
+        (Locale::En, Message::Unknown) => "Unknown",
+        (Locale::Zh, Message::Unknown) => "未知",
+        (Locale::En, Message::InvalidLinkTitle) => "Invalid link",
+        (Locale::Zh, Message::InvalidLinkTitle) => "链接无效",
+        (Locale::En, Message::ProjectNotFoundTitle) => "Project not found",
+        (Locale::Zh, Message::ProjectNotFoundTitle) => "项目不存在",
+        (Locale::En, Message::ProjectNotFoundBody) => "Project {id} does not exist or has been removed",
+        (Locale::Zh, Message::ProjectNotFoundBody) => "项目 {id} 不存在或已被删除",
+        (Locale::En, Message::TrayShowWindow) => "Show ThreadLine",
+        (Locale::Zh, Message::TrayShowWindow) => "显示 ThreadLine",
+        (Locale::En, Message::TraySyncNow) => "Sync now",
+        (Locale::Zh, Message::TraySyncNow) => "立即同步",
+        (Locale::En, Message::TrayPauseSync) => "Pause syncing",
+        (Locale::Zh, Message::TrayPauseSync) => "暂停同步",
+        (Locale::En, Message::TrayRecentNotifications) => "Recent notifications",
+        (Locale::Zh, Message::TrayRecentNotifications) => "最近通知",
+        (Locale::En, Message::TrayNoNotifications) => "No notifications",
+        (Locale::Zh, Message::TrayNoNotifications) => "没有通知",
+        (Locale::En, Message::TrayQuit) => "Quit",
+        (Locale::Zh, Message::TrayQuit) => "退出",
+        (Locale::En, Message::TraySyncing) => "Syncing {current}/{total}",
+        (Locale::Zh, Message::TraySyncing) => "正在同步 {current}/{total}",
+        (Locale::En, Message::TrayUnread) => "{count} unread",
+        (Locale::Zh, Message::TrayUnread) => "{count} 封未读",
+        (Locale::En, Message::Me) => "me",
+        (Locale::Zh, Message::Me) => "我",
+    }
+}
+
+/// 获取本地化文本并替换 `{name}` 占位符
+pub fn tr_with(locale: Locale, message: Message, args: &[(&str, &str)]) -> String {
+    args.iter().fold(tr(locale, message).to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+/// 解析数据库中的日期（RFC 3339、SQLite `CURRENT_TIMESTAMP` 格式或 RFC 2822）
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_rfc2822(value))
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|dt| dt.and_utc())
+        })
+}
+
+/// 相对日期（"2 天前" / "2 days ago"），无法解析时返回 None
+pub fn relative_time(value: &str, now: DateTime<Utc>, locale: Locale) -> Option<String> {
+    let then = parse_timestamp(value)?;
+    let seconds = (now - then).num_seconds();
+
+    // 未来的时间（时钟偏差）按"刚刚"处理
+    if seconds < 60 {
+        return Some(match locale {
+            Locale::En => "just now".to_string(),
+            Locale::Zh => "刚刚".to_string(),
+        });
+    }
+
+    let (count, unit_en, unit_zh) = match seconds {
+        s if s < 3600 => (s / 60, "minute", "分钟"),
+        s if s < 86_400 => (s / 3600, "hour", "小时"),
+        s if s < 86_400 * 30 => (s / 86_400, "day", "天"),
+        s if s < 86_400 * 365 => (s / (86_400 * 30), "month", "个月"),
+        s => (s / (86_400 * 365), "year", "年"),
+    };
+
+    Some(match locale {
+        Locale::En if count == 1 => format!("1 {} ago", unit_en),
+        Locale::En => format!("{} {}s ago", count, unit_en),
+        Locale::Zh => format!("{} {}前", count, unit_zh),
+    })
+}
+
+/// 接受的 UTC 偏移上限（分钟）
+pub const MAX_UTC_OFFSET_MINUTES: i32 = 18 * 60;
+
+/// 用户所在的时区（决定"今天""昨天"的边界）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayZone {
+    /// 系统时区（按每个时间点各自的夏令时规则换算）
+    Local,
+    /// 客户端给出的固定 UTC 偏移
+    Fixed(FixedOffset),
+}
+
+impl DisplayZone {
+    /// UTC 偏移（分钟，东区为正）；超出 ±18 小时时返回 None
+    pub fn from_offset_minutes(minutes: i32) -> Option<Self> {
+        if !(-MAX_UTC_OFFSET_MINUTES..=MAX_UTC_OFFSET_MINUTES).contains(&minutes) {
+            return None;
+        }
+        FixedOffset::east_opt(minutes * 60).map(Self::Fixed)
+    }
+
+    /// 请求中的偏移优先，其次为设置中保存的偏移，都没有时使用系统时区
+    pub async fn load(pool: &SqlitePool, requested_offset: Option<i32>) -> Self {
+        let offset = match requested_offset {
+            Some(offset) => Some(offset),
+            None => sqlx::query_scalar::<_, Option<i64>>("SELECT utc_offset_minutes FROM sync_settings WHERE id = 1")
+                .fetch_one(pool)
+                .await
+                .ok()
+                .flatten()
+                .and_then(|offset| i32::try_from(offset).ok()),
+        };
+        offset.and_then(Self::from_offset_minutes).unwrap_or(Self::Local)
+    }
+
+    /// 时间点在该时区的日期
+    pub fn date_of(&self, instant: DateTime<Utc>) -> NaiveDate {
+        match self {
+            Self::Local => instant.with_timezone(&Local).date_naive(),
+            Self::Fixed(offset) => instant.with_timezone(offset).date_naive(),
+        }
+    }
+}
+
+/// 最近活动所在的时间段（列表分组用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityBucket {
+    Today,
+    Yesterday,
+    /// 2–6 天前
+    ThisWeek,
+    Earlier,
+}
+
+/// 按用户时区的日历日期显示活动时间："Today" / "Yesterday" / "Mon" / "3 weeks ago"
+///
+/// 比较的是两个时间点在用户时区中的日期，而不是相差的小时数：晚上 23:30 收到的邮件在次日 00:30
+/// 显示为昨天，一小时前收到的邮件不会因为 UTC 日期不同而显示为昨天。未来的时间（时钟偏差）按今天处理。
+pub fn activity_display(
+    value: &str,
+    now: DateTime<Utc>,
+    zone: DisplayZone,
+    locale: Locale,
+) -> Option<(String, ActivityBucket)> {
+    let then = parse_timestamp(value)?;
+    let days = (zone.date_of(now) - zone.date_of(then)).num_days().max(0);
+
+    let bucket = match days {
+        0 => ActivityBucket::Today,
+        1 => ActivityBucket::Yesterday,
+        2..=6 => ActivityBucket::ThisWeek,
+        _ => ActivityBucket::Earlier,
+    };
+    let display = match (bucket, locale) {
+        (ActivityBucket::Today, Locale::En) => "Today".to_string(),
+        (ActivityBucket::Today, Locale::Zh) => "今天".to_string(),
+        (ActivityBucket::Yesterday, Locale::En) => "Yesterday".to_string(),
+        (ActivityBucket::Yesterday, Locale::Zh) => "昨天".to_string(),
+        (ActivityBucket::ThisWeek, _) => weekday_name(zone.date_of(then).weekday(), locale).to_string(),
+        (ActivityBucket::Earlier, _) => {
+            let (count, unit_en, unit_zh) = match days {
+                d if d < 30 => (d / 7, "week", "周"),
+                d if d < 365 => (d / 30, "month", "个月"),
+                d => (d / 365, "year", "年"),
+            };
+            match locale {
+                Locale::En if count == 1 => format!("1 {} ago", unit_en),
+                Locale::En => format!("{} {}s ago", count, unit_en),
+                Locale::Zh => format!("{} {}前", count, unit_zh),
+            }
+        }
+    };
+    Some((display, bucket))
+}
+
+fn weekday_name(weekday: Weekday, locale: Locale) -> &'static str {
+    match (locale, weekday) {
+        (Locale::En, Weekday::Mon) => "Mon",
+        (Locale::En, Weekday::Tue) => "Tue",
+        (Locale::En, Weekday::Wed) => "Wed",
+        (Locale::En, Weekday::Thu) => "Thu",
+        (Locale::En, Weekday::Fri) => "Fri",
+        (Locale::En, Weekday::Sat) => "Sat",
+        (Locale::En, Weekday::Sun) => "Sun",
+        (Locale::Zh, Weekday::Mon) => "周一",
+        (Locale::Zh, Weekday::Tue) => "周二",
+        (Locale::Zh, Weekday::Wed) => "周三",
+        (Locale::Zh, Weekday::Thu) => "周四",
+        (Locale::Zh, Weekday::Fri) => "周五",
+        (Locale::Zh, Weekday::Sat) => "周六",
+        (Locale::Zh, Weekday::Sun) => "周日",
+    }
+}
+
+/// 按语言格式化文件大小
+pub fn format_file_size(bytes: i64, locale: Locale) -> String {
+    match locale {
+        Locale::En => super::format_file_size(bytes),
+        Locale::Zh if bytes < 1024 => format!("{} 字节", bytes),
+        Locale::Zh => super::format_file_size(bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::test_pool;
+    use chrono::TimeZone;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        parse_timestamp(value).unwrap()
+    }
+
+    #[test]
+    fn locale_tags_fall_back_to_english() {
+        assert_eq!(Locale::from_tag("zh-CN"), Locale::Zh);
+        assert_eq!(Locale::from_tag(" zh_Hans "), Locale::Zh);
+        assert_eq!(Locale::from_tag("ZH"), Locale::Zh);
+        assert_eq!(Locale::from_tag("en-US"), Locale::En);
+        assert_eq!(Locale::from_tag("fr"), Locale::En);
+        assert_eq!(Locale::from_tag(""), Locale::En);
+    }
+
+    #[tokio::test]
+    async fn locale_is_loaded_from_settings() {
+        let (pool, _db_dir) = test_pool().await;
+        assert_eq!(Locale::load(&pool).await, Locale::En);
+
+        sqlx::query("UPDATE sync_settings SET locale = 'zh-CN' WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(Locale::load(&pool).await, Locale::Zh);
+    }
+
+    #[test]
+    fn parses_database_date_formats() {
+        let expected = Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+        assert_eq!(parse_timestamp("2024-03-01T17:30:00+08:00"), Some(expected));
+        assert_eq!(parse_timestamp("2024-03-01 09:30:00"), Some(expected));
+        assert_eq!(parse_timestamp("Fri, 1 Mar 2024 09:30:00 +0000"), Some(expected));
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn relative_time_is_localized() {
+        let now = at("2024-03-10 12:00:00");
+        let cases = [
+            ("2024-03-10 12:00:30", "just now", "刚刚"),
+            ("2024-03-10 11:59:30", "just now", "刚刚"),
+            ("2024-03-10 11:59:00", "1 minute ago", "1 分钟前"),
+            ("2024-03-10 09:00:00", "3 hours ago", "3 小时前"),
+            ("2024-03-08 12:00:00", "2 days ago", "2 天前"),
+            ("2023-12-10 12:00:00", "3 months ago", "3 个月前"),
+            ("2022-03-01 12:00:00", "2 years ago", "2 年前"),
+        ];
+        for (value, en, zh) in cases {
+            assert_eq!(relative_time(value, now, Locale::En).as_deref(), Some(en), "{}", value);
+            assert_eq!(relative_time(value, now, Locale::Zh).as_deref(), Some(zh), "{}", value);
+        }
+        assert_eq!(relative_time("not a date", now, Locale::En), None);
+    }
+
+    #[test]
+    fn messages_substitute_placeholders() {
+        assert_eq!(tr_with(Locale::En, Message::TraySyncing, &[("current", "3"), ("total", "10")]), "Syncing 3/10");
+        assert_eq!(tr_with(Locale::Zh, Message::ProjectNotFoundBody, &[("id", "7")]), "项目 7 不存在或已被删除");
+        assert_eq!(format_file_size(512, Locale::Zh), "512 字节");
+        assert_eq!(format_file_size(512, Locale::En), crate::utils::format_file_size(512));
+    }
+
+    fn zone(minutes: i32) -> DisplayZone {
+        DisplayZone::from_offset_minutes(minutes).unwrap()
+    }
+
+    #[test]
+    fn calendar_days_follow_the_user_time_zone() {
+        // UTC+8 的 00:30，邮件在当地前一天 23:30 收到
+        let now = at("2024-03-10T16:30:00Z");
+        let display = activity_display("2024-03-10T15:30:00Z", now, zone(8 * 60), Locale::En);
+        assert_eq!(display, Some(("Yesterday".to_string(), ActivityBucket::Yesterday)));
+
+        // 一小时前收到、UTC 日期不同但当地日期相同时仍是今天
+        let now = at("2024-03-10T01:30:00Z");
+        let display = activity_display("2024-03-10T00:30:00+00:00", now, zone(-5 * 60), Locale::En);
+        assert_eq!(display.map(|(_, bucket)| bucket), Some(ActivityBucket::Today));
+        let display = activity_display("2024-03-09T23:30:00Z", now, zone(-5 * 60), Locale::Zh);
+        assert_eq!(display, Some(("今天".to_string(), ActivityBucket::Today)));
+    }
+
+    #[test]
+    fn activity_buckets_and_labels() {
+        let now = at("2024-03-10 12:00:00"); // 周日
+        let utc = zone(0);
+        let cases = [
+            ("2024-03-10 13:00:00", "Today", "今天", ActivityBucket::Today),
+            ("2024-03-09 00:00:01", "Yesterday", "昨天", ActivityBucket::Yesterday),
+            ("2024-03-08 12:00:00", "Fri", "周五", ActivityBucket::ThisWeek),
+            ("2024-03-04 12:00:00", "Mon", "周一", ActivityBucket::ThisWeek),
+            ("2024-03-03 12:00:00", "1 week ago", "1 周前", ActivityBucket::Earlier),
+            ("2024-02-20 12:00:00", "2 weeks ago", "2 周前", ActivityBucket::Earlier),
+            ("2024-01-10 12:00:00", "2 months ago", "2 个月前", ActivityBucket::Earlier),
+            ("2022-03-01 12:00:00", "2 years ago", "2 年前", ActivityBucket::Earlier),
+        ];
+        for (value, en, zh, bucket) in cases {
+            assert_eq!(activity_display(value, now, utc, Locale::En), Some((en.to_string(), bucket)), "{}", value);
+            assert_eq!(activity_display(value, now, utc, Locale::Zh), Some((zh.to_string(), bucket)), "{}", value);
+        }
+        assert_eq!(activity_display("not a date", now, utc, Locale::En), None);
+    }
+
+    #[tokio::test]
+    async fn display_zone_prefers_request_then_settings() {
+        assert_eq!(DisplayZone::from_offset_minutes(18 * 60), Some(zone(18 * 60)));
+        assert_eq!(DisplayZone::from_offset_minutes(-19 * 60), None);
+        assert_eq!(DisplayZone::from_offset_minutes(i32::MIN), None);
+
+        let (pool, _db_dir) = test_pool().await;
+        assert_eq!(DisplayZone::load(&pool, None).await, DisplayZone::Local);
+        assert_eq!(DisplayZone::load(&pool, Some(330)).await, zone(330));
+
+        sqlx::query("UPDATE sync_settings SET utc_offset_minutes = -420 WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(DisplayZone::load(&pool, None).await, zone(-420));
+        assert_eq!(DisplayZone::load(&pool, Some(60)).await, zone(60));
+        // 请求中的偏移无效时回到系统时区
+        assert_eq!(DisplayZone::load(&pool, Some(24 * 60)).await, DisplayZone::Local);
+    }
+}
@@ -1,5 +1,25 @@
 pub mod crypto;
+pub mod deep_link;
+pub mod i18n;
+pub mod network;
+pub mod payload;
+pub mod preview;
+pub mod secrets;
+pub mod translate;
+pub mod tray;
+pub mod validation;
 
 pub fn init() {
     println!("Utils initialized");
 }
+
+/// 格式化文件大小（B / KB / MB）
+pub fn format_file_size(bytes: i64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
@@ -0,0 +1,65 @@
+/// 网络状态检测
+///
+/// 读取操作系统报告的"按流量计费"状态：Windows 通过 `NetworkInformation` 连接成本，
+/// Linux 通过 NetworkManager（`nmcli`）。无法判断时返回 None。
+use tokio::task;
+
+/// 当前网络连接是否按流量计费
+pub async fn detect_metered_connection() -> Option<bool> {
+    task::spawn_blocking(detect_metered_blocking)
+        .await
+        .ok()
+        .flatten()
+}
+
+#[cfg(target_os = "windows")]
+fn detect_metered_blocking() -> Option<bool> {
+    let output = std::process::Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            "[Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime] | Out-Null; \
+             [Windows.Networking.Connectivity.NetworkInformation]::GetInternetConnectionProfile().GetConnectionCost().NetworkCostType",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // Unrestricted / Fixed / Variable / Unknown
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "Unrestricted" => Some(false),
+        "Fixed" | "Variable" => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn detect_metered_blocking() -> Option<bool> {
+    let output = std::process::Command::new("nmcli")
+        .args(["-t", "-f", "GENERAL.METERED", "device", "show"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // 每个设备一行，如 "GENERAL.METERED:yes (guessed)"；任一已连接设备按流量计费即视为计费
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let values: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.split_once(':').map(|(_, value)| value.trim()))
+        .filter(|value| !value.is_empty() && *value != "unknown")
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+
+    Some(values.iter().any(|value| value.starts_with("yes")))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn detect_metered_blocking() -> Option<bool> {
+    None
+}
@@ -0,0 +1,70 @@
+/// 大响应压缩
+///
+/// 大项目的时间线、搜索结果序列化后有数 MB，WebView 反序列化时会卡顿。命令传入
+/// `compress: true` 时，序列化结果超过阈值就用 gzip 压缩并以 base64 返回，由前端解压；
+/// 未开启或体积较小时原样返回，与旧的返回格式一致。
+use crate::error::AppError;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use std::io::Write;
+use std::time::Instant;
+
+/// 超过该大小（字节）的响应才压缩
+pub const COMPRESSION_THRESHOLD: usize = 256 * 1024;
+
+/// 命令响应
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Payload<T> {
+    Plain(T),
+    Compressed(CompressedPayload),
+}
+
+/// 压缩后的响应：`{ encoding: "gzip", data: base64 }`
+#[derive(Debug, Serialize)]
+pub struct CompressedPayload {
+    pub encoding: &'static str,
+    pub data: String,
+    /// 压缩前的 JSON 字节数
+    pub original_size: usize,
+    pub compressed_size: usize,
+}
+
+/// 按需压缩响应（`command` 仅用于日志）
+pub fn envelope<T: Serialize>(command: &str, value: T, compress: bool) -> Result<Payload<T>, AppError> {
+    if !compress {
+        return Ok(Payload::Plain(value));
+    }
+
+    let started = Instant::now();
+    let json = serde_json::to_vec(&value)?;
+    if json.len() < COMPRESSION_THRESHOLD {
+        log::debug!("{} response: {} bytes, not compressed", command, json.len());
+        return Ok(Payload::Plain(value));
+    }
+
+    let mut encoder = GzEncoder::new(Vec::with_capacity(json.len() / 4), Compression::fast());
+    encoder.write_all(&json)?;
+    let compressed = encoder.finish()?;
+    let data = STANDARD.encode(&compressed);
+
+    log::info!(
+        "{} response: {} bytes -> {} bytes gzip ({} bytes base64, {:.0}%) in {:?}",
+        command,
+        json.len(),
+        compressed.len(),
+        data.len(),
+        data.len() as f64 * 100.0 / json.len() as f64,
+        started.elapsed()
+    );
+
+    Ok(Payload::Compressed(CompressedPayload {
+        encoding: "gzip",
+        data,
+        original_size: json.len(),
+        compressed_size: compressed.len(),
+    }))
+}
@@ -0,0 +1,293 @@
+/// 邮件预览文本
+///
+/// 收件箱、搜索和时间线显示的一行预览在保存邮件时计算一次，存入 `emails.preview`。
+/// 直接截取正文开头常常只得到引用的原文、长链接，或者格式错误的纯文本部分中残留的 base64 和 MIME 分隔行，
+/// 这里去掉这些内容、合并空白后按字符截断（不会截断在多字节字符或表情的组合序列中间），超出时加省略号。
+use crate::error::AppError;
+use sqlx::SqlitePool;
+
+/// 保存的预览长度（字符）
+pub const PREVIEW_CHARS: usize = 200;
+
+/// 超过该长度的链接从预览中去掉
+const MAX_URL_CHARS: usize = 40;
+
+/// 不含空白、只由 base64 字符组成且达到该长度的行视为编码残留
+const BASE64_LINE_CHARS: usize = 40;
+
+/// 每批补全的邮件数
+const BACKFILL_BATCH_SIZE: i64 = 500;
+
+/// 生成预览：去掉引用行、base64 / MIME 残留和长链接，合并空白，最多 `max_chars` 个字符
+pub fn make_preview(text: &str, max_chars: usize) -> String {
+    let mut words: Vec<String> = Vec::new();
+    for line in join_soft_breaks(text).lines() {
+        let line = line.trim();
+        if line.is_empty() || is_quoted(line) || is_encoded_residue(line) {
+            continue;
+        }
+        let line = decode_qp_escapes(line);
+        words.extend(
+            line.split_whitespace()
+                .filter(|word| !is_long_url(word))
+                .map(str::to_string),
+        );
+    }
+    truncate(&words.join(" "), max_chars)
+}
+
+/// 保存用的预览（没有正文时为空字符串）
+pub fn preview_for(body_text: Option<&str>) -> String {
+    body_text.map(|text| make_preview(text, PREVIEW_CHARS)).unwrap_or_default()
+}
+
+/// 去掉引用行和 base64 / MIME 残留后的正文（保留换行，连续空行合并为一行，用于翻译）
+pub fn strip_quoted(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in join_soft_breaks(text).lines() {
+        let trimmed = line.trim();
+        if is_quoted(trimmed) || is_encoded_residue(trimmed) {
+            continue;
+        }
+        if trimmed.is_empty() && lines.last().map(String::is_empty).unwrap_or(true) {
+            continue;
+        }
+        lines.push(decode_qp_escapes(line.trim_end()));
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// 合并 quoted-printable 软换行（行尾的 `=`）
+fn join_soft_breaks(text: &str) -> String {
+    text.replace("=\r\n", "").replace("=\n", "")
+}
+
+/// 引用行和回复头（"> ..."、"On ... wrote:"、"-----Original Message-----"）
+fn is_quoted(line: &str) -> bool {
+    line.starts_with('>')
+        || line.starts_with('|')
+        || (line.starts_with("On ") && line.ends_with("wrote:"))
+        || line.ends_with("写道：")
+        || line.ends_with("写道:")
+        || (line.starts_with("-----") && line.to_ascii_lowercase().contains("original message"))
+}
+
+/// base64 行、MIME 分隔行和泄露出来的 MIME 头
+fn is_encoded_residue(line: &str) -> bool {
+    let lower = line.to_ascii_lowercase();
+    if lower.starts_with("content-type:")
+        || lower.starts_with("content-transfer-encoding:")
+        || lower.starts_with("content-disposition:")
+        || lower.starts_with("mime-version:")
+    {
+        return true;
+    }
+    // 分隔行：--boundary 或 --boundary--，中间没有空白
+    if line.len() > 10 && line.starts_with("--") && !line.contains(char::is_whitespace) {
+        return true;
+    }
+    line.chars().count() >= BASE64_LINE_CHARS
+        && line
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '='))
+        && line.chars().any(|c| c.is_ascii_digit() || c == '+' || c == '/')
+}
+
+/// 解码残留的 `=XX` 转义（如 `=3D`、`=E2=80=99`），解码后不是合法 UTF-8 时保留原文
+fn decode_qp_escapes(line: &str) -> String {
+    let bytes = line.as_bytes();
+    let is_hex = |b: u8| matches!(b, b'0'..=b'9' | b'A'..=b'F');
+    let is_escape = |i: usize| bytes[i] == b'=' && i + 2 < bytes.len() && is_hex(bytes[i + 1]) && is_hex(bytes[i + 2]);
+    if !(0..bytes.len()).any(is_escape) {
+        return line.to_string();
+    }
+
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_escape(i) {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            decoded.push(u8::from_str_radix(hex, 16).unwrap_or(b'?'));
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| line.to_string())
+}
+
+/// 超过长度上限的链接（可能带尖括号或括号）
+fn is_long_url(word: &str) -> bool {
+    let url = word.trim_matches(|c: char| matches!(c, '<' | '>' | '(' | ')' | '[' | ']' | '"' | '\''));
+    let lower = url.to_ascii_lowercase();
+    (lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("www."))
+        && url.chars().count() > MAX_URL_CHARS
+}
+
+/// 按字符截断并加省略号；截断处不留下孤立的零宽连接符、变体选择符或肤色修饰符
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut preview: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    // 截断在组合序列中间（后面紧跟连接符、变体选择符或肤色修饰符，或停在 ZWJ 上）时去掉整个序列
+    if text[preview.len()..].starts_with(is_joining_mark) || preview.ends_with('\u{200D}') {
+        loop {
+            while preview.ends_with(is_joining_mark) {
+                preview.pop();
+            }
+            preview.pop();
+            if !preview.ends_with('\u{200D}') {
+                break;
+            }
+        }
+    }
+    while preview.ends_with(char::is_whitespace) {
+        preview.pop();
+    }
+    preview.push('…');
+    preview
+}
+
+fn is_joining_mark(c: char) -> bool {
+    matches!(c, '\u{200D}' | '\u{FE0E}' | '\u{FE0F}' | '\u{1F3FB}'..='\u{1F3FF}' | '\u{20E3}')
+}
+
+/// 为升级前保存的邮件计算预览，返回处理的邮件数
+pub async fn backfill_previews(pool: &SqlitePool) -> Result<u64, AppError> {
+    let mut processed = 0u64;
+    let mut last_id = 0i64;
+    loop {
+        let batch: Vec<(i64, Option<String>)> = sqlx::query_as(
+            "SELECT id, body_text FROM emails WHERE preview IS NULL AND body_text IS NOT NULL AND id > ? ORDER BY id LIMIT ?"
+        )
+        .bind(last_id)
+        .bind(BACKFILL_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+        let Some((id, _)) = batch.last() else { break };
+        last_id = *id;
+
+        let mut tx = pool.begin().await?;
+        for (id, body) in &batch {
+            sqlx::query("UPDATE emails SET preview = ? WHERE id = ?")
+                .bind(preview_for(body.as_deref()))
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        processed += batch.len() as u64;
+    }
+
+    if processed > 0 {
+        log::info!("Computed previews for {} existing emails", processed);
+    }
+    Ok(processed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::database::fixtures::{insert_account, insert_email, NewEmail};
+    use crate::storage::database::test_pool;
+
+    #[test]
+    fn preview_drops_quotes_encoded_residue_and_long_links() {
+        let body = "Hi team,\r\n\r\nThe   revised plan is attached.\r\n\
+            Details: https://example.com/a/very/long/tracking/link?id=0123456789abcdef and www.example.com\r\n\
+            --b1_4f8a2c9d0e\r\n\
+            Content-Type: text/plain; charset=utf-8\r\n\
+            SGVsbG8gd29ybGQhIFRoaXMgaXMgYSBiYXNlNjQgbGluZSAxMjM0NTY3ODkw\r\n\
+            On Mon, 12 Oct 2026 at 09:00, Alice <alice@example.com> wrote:\r\n\
+            > earlier message\r\n\
+            -----Original Message-----\r\n";
+        assert_eq!(
+            make_preview(body, PREVIEW_CHARS),
+            "Hi team, The revised plan is attached. Details: and www.example.com"
+        );
+    }
+
+    #[test]
+    fn preview_decodes_quoted_printable_leftovers() {
+        assert_eq!(make_preview("It=E2=80=99s ready, a=3Db and soft=\r\nbreak", 100), "It’s ready, a=b and softbreak");
+        // 不是合法 UTF-8 的转义原样保留
+        assert_eq!(make_preview("Price =FF off", 100), "Price =FF off");
+    }
+
+    #[test]
+    fn truncation_counts_characters_and_keeps_emoji_sequences_whole() {
+        assert_eq!(make_preview("short", 10), "short");
+        assert_eq!(make_preview("项目进度更新说明", 5), "项目进度…");
+        assert_eq!(make_preview("abc def ghi", 5), "abc…");
+
+        // 家庭表情由 ZWJ 连接，截断在序列中间时整个去掉
+        let family = "👩\u{200D}👩\u{200D}👧";
+        let preview = make_preview(&format!("ok {}", family), 5);
+        assert_eq!(preview, "ok…");
+        let thumbs = "👍\u{1F3FD}";
+        let text = format!("yes {} more", thumbs);
+        // 截断在表情和肤色修饰符之间时去掉整个表情，完整的序列保留
+        assert_eq!(make_preview(&text, 6), "yes…");
+        assert_eq!(make_preview(&text, 7), format!("yes {}…", thumbs));
+        assert_eq!(make_preview(&text, 8), format!("yes {}…", thumbs));
+        assert_eq!(make_preview(&format!("ok {} end", family), 7), "ok…");
+        assert_eq!(make_preview("key 1\u{FE0F}\u{20E3} next", 6), "key…");
+    }
+
+    #[test]
+    fn strip_quoted_keeps_line_structure() {
+        let body = "Line one\n\n\nLine two\n> quoted\nOn Mon, Alice wrote:\n\n";
+        assert_eq!(strip_quoted(body), "Line one\n\nLine two");
+        assert_eq!(preview_for(None), "");
+    }
+
+    #[tokio::test]
+    async fn backfill_fills_missing_previews_in_batches() {
+        let (pool, _db_dir) = test_pool().await;
+        let account_id = Some(insert_account(&pool, "me@example.com").await);
+        let total = BACKFILL_BATCH_SIZE + 3;
+        for i in 0..total {
+            let message_id = format!("m{}", i);
+            let body_text = format!("Body {}\n> quoted", i);
+            let email = NewEmail {
+                account_id,
+                message_id: &message_id,
+                body_text: Some(&body_text),
+                ..Default::default()
+            };
+            insert_email(&pool, email).await;
+        }
+        let kept = NewEmail {
+            account_id,
+            message_id: "kept",
+            body_text: Some("New body"),
+            preview: Some("Existing"),
+            ..Default::default()
+        };
+        insert_email(&pool, kept).await;
+        insert_email(&pool, NewEmail { account_id, message_id: "no-body", ..Default::default() }).await;
+
+        assert_eq!(backfill_previews(&pool).await.unwrap(), total as u64);
+        assert_eq!(backfill_previews(&pool).await.unwrap(), 0);
+
+        let preview = |message_id: &'static str| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_scalar::<_, Option<String>>("SELECT preview FROM emails WHERE message_id = ?")
+                    .bind(message_id)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(preview("m0").await.as_deref(), Some("Body 0"));
+        assert_eq!(preview("m502").await.as_deref(), Some("Body 502"));
+        assert_eq!(preview("kept").await.as_deref(), Some("Existing"));
+        assert_eq!(preview("no-body").await, None);
+    }
+}
@@ -0,0 +1,14 @@
+/// 系统钥匙串
+///
+/// 不写入数据库的密钥（翻译服务的 API 密钥、待完成 OAuth 授权的令牌）都保存在同一个服务名下，
+/// 用条目名区分用途。
+use crate::error::AppError;
+
+/// 钥匙串中的服务名
+const KEYRING_SERVICE: &str = "com.threadline.app";
+
+/// 钥匙串条目，`name` 如 `translation-deepl`、`oauth-pending-3`
+pub fn entry(name: &str) -> Result<keyring::Entry, AppError> {
+    keyring::Entry::new(KEYRING_SERVICE, name)
+        .map_err(|e| AppError::Config(format!("Keychain unavailable: {}", e)))
+}
@@ -0,0 +1,441 @@
+/// 邮件翻译
+///
+/// 翻译服务在设置中选择（`translation_provider`）：`none` 不翻译，`deepl` 使用 DeepL API，
+/// `local` 使用本机运行的 LibreTranslate 兼容服务（`translation_endpoint`，需要启用 `local-translation` 特性）。
+/// API 密钥保存在系统钥匙串中，不写入数据库。翻译结果按 (邮件, 目标语言, 服务) 缓存在 `email_translations` 中，
+/// 重复翻译直接返回缓存。对服务的请求排队串行发送，两次请求至少间隔 `MIN_REQUEST_INTERVAL`，
+/// 收到 429 时按 Retry-After 等待后重试。
+use crate::error::AppError;
+use crate::utils::{preview, secrets};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// 发送翻译的正文长度上限（字符）
+const MAX_BODY_CHARS: usize = 30_000;
+
+/// 两次请求的最小间隔
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// 请求超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 被限流时的最多重试次数
+const MAX_RETRIES: u32 = 3;
+
+/// 没有 Retry-After 时的首次等待时间（之后每次加倍）
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(2);
+
+lazy_static::lazy_static! {
+    /// 上一次请求的时间；持有锁期间发送请求，后来的翻译排队等待
+    static ref REQUEST_QUEUE: Mutex<Option<Instant>> = Mutex::new(None);
+}
+
+/// 翻译服务
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    None,
+    Deepl,
+    Local,
+}
+
+impl ProviderKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(ProviderKind::None),
+            "deepl" => Some(ProviderKind::Deepl),
+            "local" => Some(ProviderKind::Local),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderKind::None => "none",
+            ProviderKind::Deepl => "deepl",
+            ProviderKind::Local => "local",
+        }
+    }
+
+    /// 本次构建是否支持该服务
+    pub fn available(&self) -> bool {
+        match self {
+            ProviderKind::Local => cfg!(feature = "local-translation"),
+            _ => true,
+        }
+    }
+
+    /// 是否需要 API 密钥
+    fn needs_api_key(&self) -> bool {
+        matches!(self, ProviderKind::Deepl)
+    }
+}
+
+/// 邮件的翻译结果
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct EmailTranslation {
+    pub email_id: i64,
+    pub target_lang: String,
+    pub provider: String,
+    pub subject: Option<String>,
+    /// 去掉引用内容后的正文译文
+    pub body: String,
+    /// 服务识别的原文语言
+    pub source_lang: Option<String>,
+    pub created_at: String,
+    /// 是否来自缓存
+    #[sqlx(default)]
+    pub cached: bool,
+}
+
+/// 保存 API 密钥到钥匙串（为空时删除）
+pub fn set_api_key(provider: ProviderKind, api_key: Option<&str>) -> Result<(), AppError> {
+    if !provider.needs_api_key() {
+        return Err(AppError::Validation(format!(
+            "Translation provider {} does not use an API key",
+            provider.as_str()
+        )));
+    }
+    let entry = keyring_entry(provider)?;
+    match api_key.map(str::trim).filter(|key| !key.is_empty()) {
+        Some(key) => entry
+            .set_password(key)
+            .map_err(|e| AppError::Config(format!("Failed to save the API key to the keychain: {}", e))),
+        None => match entry.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(AppError::Config(format!("Failed to remove the API key from the keychain: {}", e))),
+        },
+    }
+}
+
+/// 钥匙串中是否保存了 API 密钥
+pub fn has_api_key(provider: ProviderKind) -> bool {
+    provider.needs_api_key() && api_key(provider).is_ok_and(|key| key.is_some())
+}
+
+fn api_key(provider: ProviderKind) -> Result<Option<String>, AppError> {
+    match keyring_entry(provider)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(AppError::Config(format!("Failed to read the API key from the keychain: {}", e))),
+    }
+}
+
+fn keyring_entry(provider: ProviderKind) -> Result<keyring::Entry, AppError> {
+    secrets::entry(&format!("translation-{}", provider.as_str()))
+}
+
+/// 邮件翻译
+pub struct Translator {
+    pool: SqlitePool,
+}
+
+impl Translator {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 翻译邮件主题和正文（去掉引用内容），已翻译过时直接返回缓存
+    pub async fn translate_email(&self, email_id: i64, target_lang: &str) -> Result<EmailTranslation, AppError> {
+        let target_lang = normalize_lang(target_lang)?;
+        let (kind, endpoint) = self.settings().await?;
+
+        let cached = sqlx::query_as::<_, EmailTranslation>(
+            r#"
+            SELECT email_id, target_lang, provider, subject, body, source_lang, created_at
+            FROM email_translations
+            WHERE email_id = ? AND target_lang = ? AND provider = ?
+            "#
+        )
+        .bind(email_id)
+        .bind(&target_lang)
+        .bind(kind.as_str())
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some(mut translation) = cached {
+            translation.cached = true;
+            return Ok(translation);
+        }
+
+        let backend = Backend::resolve(kind, endpoint)?;
+        let email: Option<(Option<String>, Option<String>)> =
+            sqlx::query_as("SELECT subject, body_text FROM emails WHERE id = ?")
+                .bind(email_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        let (subject, body) = email.ok_or(AppError::EmailNotFound { id: email_id })?;
+        let subject = subject.map(|subject| subject.trim().to_string()).filter(|subject| !subject.is_empty());
+        let body: String = preview::strip_quoted(body.as_deref().unwrap_or_default())
+            .chars()
+            .take(MAX_BODY_CHARS)
+            .collect();
+
+        let mut texts: Vec<&str> = Vec::new();
+        if let Some(subject) = &subject {
+            texts.push(subject);
+        }
+        if !body.is_empty() {
+            texts.push(&body);
+        }
+        let (mut translated, source_lang) = if texts.is_empty() {
+            (Vec::new(), None)
+        } else {
+            backend.translate(&texts, &target_lang).await?
+        };
+        let body = if body.is_empty() { String::new() } else { translated.pop().unwrap_or_default() };
+        let subject = subject.and_then(|_| translated.pop());
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO email_translations (email_id, target_lang, provider, subject, body, source_lang)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(email_id)
+        .bind(&target_lang)
+        .bind(kind.as_str())
+        .bind(&subject)
+        .bind(&body)
+        .bind(&source_lang)
+        .execute(&self.pool)
+        .await?;
+
+        log::info!("Translated email {} to {} with {}", email_id, target_lang, kind.as_str());
+        Ok(EmailTranslation {
+            email_id,
+            target_lang,
+            provider: kind.as_str().to_string(),
+            subject,
+            body,
+            source_lang,
+            created_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            cached: false,
+        })
+    }
+
+    /// 读取翻译设置（服务, 本地服务地址）
+    async fn settings(&self) -> Result<(ProviderKind, String), AppError> {
+        let row: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT translation_provider, translation_endpoint FROM sync_settings WHERE id = 1"
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let (provider, endpoint) = row.unwrap_or_default();
+        let kind = provider.as_deref().and_then(ProviderKind::parse).unwrap_or(ProviderKind::None);
+        Ok((kind, endpoint.unwrap_or_default()))
+    }
+}
+
+/// 目标语言代码：`JA`、`EN-US`、`ZH-HANS` 等（统一为大写）
+fn normalize_lang(lang: &str) -> Result<String, AppError> {
+    let lang = lang.trim().to_ascii_uppercase();
+    let valid = match lang.split_once('-') {
+        Some((base, variant)) => {
+            base.len() == 2 && (2..=4).contains(&variant.len()) && variant.chars().all(|c| c.is_ascii_alphabetic())
+        }
+        None => lang.len() == 2,
+    } && lang[..2].chars().all(|c| c.is_ascii_alphabetic());
+    if !valid {
+        return Err(AppError::InvalidField {
+            field: "target_lang".to_string(),
+            message: format!("Invalid language code: {}", lang),
+        });
+    }
+    Ok(lang)
+}
+
+/// 已配置好的翻译服务
+enum Backend {
+    Deepl { api_key: String },
+    #[cfg(feature = "local-translation")]
+    Local { endpoint: String },
+}
+
+#[derive(Deserialize)]
+struct DeeplResponse {
+    translations: Vec<DeeplTranslation>,
+}
+
+#[derive(Deserialize)]
+struct DeeplTranslation {
+    detected_source_language: Option<String>,
+    text: String,
+}
+
+#[cfg(feature = "local-translation")]
+#[derive(Deserialize)]
+struct LocalResponse {
+    #[serde(rename = "translatedText")]
+    translated_text: String,
+    #[serde(rename = "detectedLanguage")]
+    detected_language: Option<LocalDetectedLanguage>,
+}
+
+#[cfg(feature = "local-translation")]
+#[derive(Deserialize)]
+struct LocalDetectedLanguage {
+    language: String,
+}
+
+impl Backend {
+    /// 按设置创建，未选择服务或缺少密钥 / 地址时返回 `TranslationNotConfigured`
+    fn resolve(kind: ProviderKind, endpoint: String) -> Result<Self, AppError> {
+        match kind {
+            ProviderKind::None => Err(AppError::TranslationNotConfigured(
+                "No translation provider is selected in settings".to_string(),
+            )),
+            ProviderKind::Deepl => match api_key(kind)? {
+                Some(api_key) => Ok(Backend::Deepl { api_key }),
+                None => Err(AppError::TranslationNotConfigured("DeepL API key is not set".to_string())),
+            },
+            #[cfg(feature = "local-translation")]
+            ProviderKind::Local => {
+                let endpoint = endpoint.trim().trim_end_matches('/').to_string();
+                if endpoint.is_empty() {
+                    return Err(AppError::TranslationNotConfigured(
+                        "Local translation endpoint is not set".to_string(),
+                    ));
+                }
+                Ok(Backend::Local { endpoint })
+            }
+            #[cfg(not(feature = "local-translation"))]
+            ProviderKind::Local => {
+                let _ = endpoint;
+                Err(AppError::TranslationNotConfigured(
+                    "Local translation is not available in this build".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// 翻译多段文本，返回 (译文, 识别的原文语言)；请求排队发送
+    async fn translate(&self, texts: &[&str], target_lang: &str) -> Result<(Vec<String>, Option<String>), AppError> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| AppError::Network(format!("Failed to create HTTP client: {}", e)))?;
+
+        // DeepL 一次请求可以翻译多段文本，本地服务每次一段
+        let batches: Vec<&[&str]> = match self {
+            Backend::Deepl { .. } => vec![texts],
+            #[cfg(feature = "local-translation")]
+            Backend::Local { .. } => texts.chunks(1).collect(),
+        };
+        let mut translated = Vec::with_capacity(texts.len());
+        let mut source_lang = None;
+        for batch in batches {
+            let (texts, detected) = self.request(&client, batch, target_lang).await?;
+            translated.extend(texts);
+            source_lang = source_lang.or(detected);
+        }
+        Ok((translated, source_lang))
+    }
+
+    /// 排队发送一次请求，被限流时等待后重试
+    async fn request(
+        &self,
+        client: &reqwest::Client,
+        texts: &[&str],
+        target_lang: &str,
+    ) -> Result<(Vec<String>, Option<String>), AppError> {
+        let mut last_request = REQUEST_QUEUE.lock().await;
+        let mut retry_after = DEFAULT_RETRY_AFTER;
+        for attempt in 0..=MAX_RETRIES {
+            if let Some(last) = *last_request {
+                tokio::time::sleep_until(last + MIN_REQUEST_INTERVAL).await;
+            }
+            *last_request = Some(Instant::now());
+
+            let response = self.send(client, texts, target_lang).await?;
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS && attempt < MAX_RETRIES {
+                let wait = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or(retry_after);
+                log::info!("Translation provider is rate limiting, retrying in {:?}", wait);
+                tokio::time::sleep(wait).await;
+                retry_after *= 2;
+                continue;
+            }
+            return self.parse(response, texts.len()).await;
+        }
+        Err(AppError::Network("Translation provider is still rate limiting, try again later".to_string()))
+    }
+
+    async fn send(&self, client: &reqwest::Client, texts: &[&str], target_lang: &str) -> Result<reqwest::Response, AppError> {
+        let request = match self {
+            Backend::Deepl { api_key } => {
+                // 免费版密钥以 ":fx" 结尾，使用单独的地址
+                let url = if api_key.ends_with(":fx") {
+                    "https://api-free.deepl.com/v2/translate"
+                } else {
+                    "https://api.deepl.com/v2/translate"
+                };
+                let mut form: Vec<(&str, &str)> = texts.iter().map(|text| ("text", *text)).collect();
+                form.push(("target_lang", target_lang));
+                client
+                    .post(url)
+                    .header(reqwest::header::AUTHORIZATION, format!("DeepL-Auth-Key {}", api_key))
+                    .form(&form)
+            }
+            #[cfg(feature = "local-translation")]
+            Backend::Local { endpoint } => {
+                // LibreTranslate 使用小写的基础语言代码，一次请求一段文本
+                let target = target_lang.split('-').next().unwrap_or(target_lang).to_ascii_lowercase();
+                let text = texts.first().copied().unwrap_or_default();
+                client
+                    .post(format!("{}/translate", endpoint))
+                    .form(&[("q", text), ("source", "auto"), ("target", target.as_str()), ("format", "text")])
+            }
+        };
+        request
+            .send()
+            .await
+            .map_err(|e| AppError::Network(format!("Translation request failed: {}", e)))
+    }
+
+    async fn parse(&self, response: reqwest::Response, count: usize) -> Result<(Vec<String>, Option<String>), AppError> {
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| AppError::Network(format!("Failed to read translation response: {}", e)))?;
+        if status == reqwest::StatusCode::FORBIDDEN || status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(AppError::TranslationNotConfigured(
+                "The translation provider rejected the API key".to_string(),
+            ));
+        }
+        if !status.is_success() {
+            return Err(AppError::Network(format!("Translation provider returned {}: {}", status, body)));
+        }
+
+        match self {
+            Backend::Deepl { .. } => {
+                let response: DeeplResponse = serde_json::from_str(&body)?;
+                if response.translations.len() != count {
+                    return Err(AppError::Parse(format!(
+                        "Expected {} translations, got {}",
+                        count,
+                        response.translations.len()
+                    )));
+                }
+                let source_lang = response
+                    .translations
+                    .iter()
+                    .find_map(|translation| translation.detected_source_language.clone());
+                Ok((response.translations.into_iter().map(|translation| translation.text).collect(), source_lang))
+            }
+            #[cfg(feature = "local-translation")]
+            Backend::Local { .. } => {
+                let response: LocalResponse = serde_json::from_str(&body)?;
+                let source_lang = response.detected_language.map(|detected| detected.language.to_ascii_uppercase());
+                Ok((vec![response.translated_text], source_lang))
+            }
+        }
+    }
+}
@@ -0,0 +1,352 @@
+/// 系统托盘与后台模式
+///
+/// 托盘菜单：显示窗口、立即同步（所有账户）、暂停同步、最近通知、退出。
+/// 开启 `run_in_background` 时关闭最后一个窗口只隐藏到托盘，定时同步继续运行。
+/// 托盘提示和标题（macOS 菜单栏上的未读数）随前端使用的同一组事件（`sync-progress`、`notification`）更新。
+/// 退出走优雅关闭流程：不再开始新的同步，等待进行中的同步结束并截断 WAL 后再退出。
+use crate::commands::sync::{run_account_sync, SyncAccountRequest};
+use crate::error::AppError;
+use crate::events::notifications::{Notification, NotificationStore};
+use crate::events::{EventEmitter, NavigateEvent, SyncProgressEvent, SyncStatus};
+use crate::mail::sync::ActiveSyncs;
+use crate::storage::app_state::DatabaseExt;
+use crate::storage::database;
+use crate::utils::i18n::{tr, tr_with, Locale, Message};
+use sqlx::SqlitePool;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Listener, Manager, Window, WindowEvent};
+
+pub const TRAY_ID: &str = "main";
+
+const MENU_SHOW: &str = "tray:show";
+const MENU_SYNC_NOW: &str = "tray:sync_now";
+const MENU_PAUSE: &str = "tray:pause";
+const MENU_NOTIFICATIONS: &str = "tray:notifications";
+const MENU_QUIT: &str = "tray:quit";
+const NOTIFICATION_PREFIX: &str = "tray:notification:";
+
+/// 子菜单中显示的通知数
+const RECENT_NOTIFICATION_COUNT: i64 = 5;
+
+/// 菜单中通知标题的最大字符数
+const NOTIFICATION_LABEL_CHARS: usize = 48;
+
+/// 退出时等待进行中同步的最长时间
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// 关闭最后一个窗口时是否隐藏到托盘（窗口事件是同步回调，不便查询数据库）
+static RUN_IN_BACKGROUND: AtomicBool = AtomicBool::new(false);
+
+pub fn set_run_in_background(enabled: bool) {
+    RUN_IN_BACKGROUND.store(enabled, Ordering::Relaxed);
+}
+
+pub fn run_in_background() -> bool {
+    RUN_IN_BACKGROUND.load(Ordering::Relaxed)
+}
+
+/// 启动时读取后台模式设置
+pub async fn load_settings(pool: &SqlitePool) -> Result<(), AppError> {
+    let enabled: Option<bool> = sqlx::query_scalar("SELECT run_in_background FROM sync_settings WHERE id = 1")
+        .fetch_optional(pool)
+        .await?;
+    set_run_in_background(enabled.unwrap_or(false));
+    Ok(())
+}
+
+/// 托盘显示的状态
+#[derive(Debug, Clone, Default)]
+struct TraySnapshot {
+    locale: Locale,
+    syncing: bool,
+    paused: bool,
+    unread: i64,
+    notifications: Vec<Notification>,
+}
+
+/// 最近一次的托盘状态（同步进度事件只更新提示文本，不重新查询数据库）
+#[derive(Default)]
+struct TrayState(Mutex<TraySnapshot>);
+
+/// 创建托盘图标并订阅事件
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    app.manage(TrayState::default());
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("ThreadLine")
+        .menu(&build_menu(app, &TraySnapshot::default())?)
+        .on_menu_event(on_menu_event);
+    if let Some(icon) = app.default_window_icon().cloned() {
+        builder = builder.icon(icon);
+    }
+    builder.build(app)?;
+
+    let handle = app.clone();
+    app.listen_any("sync-progress", move |event| {
+        match serde_json::from_str::<SyncProgressEvent>(event.payload()) {
+            Ok(progress) if matches!(progress.status, SyncStatus::Starting | SyncStatus::Syncing) => {
+                show_progress(&handle, &progress);
+            }
+            _ => refresh(handle.clone()),
+        }
+    });
+    let handle = app.clone();
+    app.listen_any("notification", move |_| refresh(handle.clone()));
+    let handle = app.clone();
+    app.listen_any("app-ready", move |_| refresh(handle.clone()));
+
+    refresh(app.clone());
+    Ok(())
+}
+
+/// 开启后台模式时，关闭最后一个可见窗口改为隐藏
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    let WindowEvent::CloseRequested { api, .. } = event else { return };
+    if !run_in_background() {
+        return;
+    }
+
+    let others_visible = window
+        .app_handle()
+        .webview_windows()
+        .values()
+        .any(|other| other.label() != window.label() && other.is_visible().unwrap_or(false));
+    if !others_visible {
+        api.prevent_close();
+        if let Err(e) = window.hide() {
+            log::warn!("Failed to hide window to tray: {}", e);
+        }
+        log::info!("Last window hidden to tray; background sync continues");
+    }
+}
+
+/// 优雅退出：不再开始新的同步，等待进行中的同步结束并截断 WAL
+pub fn graceful_quit(app: AppHandle) {
+    let active_syncs = app.state::<ActiveSyncs>().inner().clone();
+    active_syncs.begin_shutdown();
+    for window in app.webview_windows().values() {
+        let _ = window.hide();
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        if active_syncs.any_active() {
+            log::info!("Waiting for in-flight syncs before quitting");
+        }
+        while active_syncs.any_active() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        if active_syncs.any_active() {
+            log::warn!("Syncs still running after {}s, quitting anyway", SHUTDOWN_TIMEOUT.as_secs());
+        }
+
+        // 启动未完成时还没有连接池，不需要截断 WAL
+        if let Ok(writer) = app.db_writer() {
+            if let Err(e) = database::checkpoint_wal(&writer.0).await {
+                log::warn!("WAL checkpoint before quit failed: {}", e);
+            }
+        }
+        log::info!("Quitting ThreadLine");
+        app.exit(0);
+    });
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        MENU_SHOW => show_main_window(app),
+        MENU_SYNC_NOW => sync_all(app.clone()),
+        MENU_PAUSE => {
+            let active_syncs = app.state::<ActiveSyncs>();
+            active_syncs.set_paused(!active_syncs.is_paused());
+            log::info!("Automatic sync {}", if active_syncs.is_paused() { "paused" } else { "resumed" });
+            refresh(app.clone());
+        }
+        MENU_QUIT => graceful_quit(app.clone()),
+        id => {
+            if let Some(id) = id.strip_prefix(NOTIFICATION_PREFIX).and_then(|id| id.parse().ok()) {
+                open_notification(app.clone(), id);
+            }
+        }
+    }
+}
+
+fn show_main_window(app: &AppHandle) {
+    let Some(window) = app.webview_windows().into_values().next() else { return };
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+}
+
+/// 依次同步所有账户
+fn sync_all(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let pool = match app.db() {
+            Ok(pool) => pool,
+            Err(e) => {
+                log::info!("Tray sync skipped: {}", e);
+                return;
+            }
+        };
+        let emails: Vec<String> = match sqlx::query_scalar("SELECT email FROM accounts ORDER BY id")
+            .fetch_all(&pool)
+            .await
+        {
+            Ok(emails) => emails,
+            Err(e) => {
+                log::warn!("Tray sync: failed to load accounts: {}", e);
+                return;
+            }
+        };
+
+        for email in emails {
+            let request = SyncAccountRequest {
+                email: email.clone(),
+                password: None,
+                automatic: false,
+            };
+            if let Err(e) = run_account_sync(&app, request).await {
+                log::warn!("Tray sync failed for {}: {}", email, e.message);
+            }
+        }
+    });
+}
+
+/// 标记通知已读，打开窗口并跳转到关联的项目
+fn open_notification(app: AppHandle, id: i64) {
+    show_main_window(&app);
+    tauri::async_runtime::spawn(async move {
+        let Ok(pool) = app.db() else { return };
+        if let Err(e) = NotificationStore::new(pool.clone()).mark_read(id).await {
+            log::warn!("Failed to mark notification {} read: {}", id, e);
+        }
+
+        let related: Option<String> = sqlx::query_scalar("SELECT related_entity FROM notifications WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&pool)
+            .await
+            .ok()
+            .flatten();
+        let project_id = related
+            .as_deref()
+            .and_then(|entity| entity.strip_prefix("project:"))
+            .and_then(|id| id.parse::<i64>().ok());
+        if let Some(project_id) = project_id {
+            app.state::<EventEmitter>().emit_navigate(NavigateEvent {
+                route: format!("/projects/{}", project_id),
+                project_id: Some(project_id),
+            });
+        }
+        refresh(app);
+    });
+}
+
+/// 重新读取状态并更新托盘菜单和提示
+fn refresh(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        // 启动完成前保留初始菜单，`app-ready` 后再刷新
+        let Some(snapshot) = load_snapshot(&app).await else { return };
+        apply(&app, &snapshot);
+        if let Some(state) = app.try_state::<TrayState>() {
+            *state.0.lock().unwrap() = snapshot;
+        }
+    });
+}
+
+async fn load_snapshot(app: &AppHandle) -> Option<TraySnapshot> {
+    let pool = app.db().ok()?;
+    let active_syncs = app.state::<ActiveSyncs>();
+
+    let unread = sqlx::query_scalar("SELECT COUNT(*) FROM emails WHERE is_read = 0 AND duplicate_of IS NULL")
+        .fetch_one(&pool)
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!("Tray: failed to count unread emails: {}", e);
+            0
+        });
+    let notifications = NotificationStore::new(pool.clone())
+        .list(false, RECENT_NOTIFICATION_COUNT)
+        .await
+        .unwrap_or_default();
+
+    Some(TraySnapshot {
+        locale: Locale::load(&pool).await,
+        syncing: active_syncs.any_active(),
+        paused: active_syncs.is_paused(),
+        unread,
+        notifications,
+    })
+}
+
+fn apply(app: &AppHandle, snapshot: &TraySnapshot) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else { return };
+    match build_menu(app, snapshot) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                log::warn!("Failed to update tray menu: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Failed to build tray menu: {}", e),
+    }
+
+    let count = snapshot.unread.to_string();
+    let tooltip = if snapshot.unread > 0 {
+        format!("ThreadLine — {}", tr_with(snapshot.locale, Message::TrayUnread, &[("count", &count)]))
+    } else {
+        "ThreadLine".to_string()
+    };
+    let _ = tray.set_tooltip(Some(tooltip));
+    // 菜单栏标题只在 macOS 上显示
+    let _ = tray.set_title((snapshot.unread > 0).then_some(count));
+}
+
+/// 同步进行中：更新提示和标题（旋转标记）
+fn show_progress(app: &AppHandle, progress: &SyncProgressEvent) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else { return };
+    let locale = app
+        .try_state::<TrayState>()
+        .map(|state| state.0.lock().unwrap().locale)
+        .unwrap_or_default();
+
+    let (current, total) = (progress.current.to_string(), progress.total.to_string());
+    let text = tr_with(locale, Message::TraySyncing, &[("current", &current), ("total", &total)]);
+    let _ = tray.set_tooltip(Some(format!("ThreadLine — {}", text)));
+    let _ = tray.set_title(Some("⟳"));
+}
+
+fn build_menu(app: &AppHandle, snapshot: &TraySnapshot) -> tauri::Result<Menu<tauri::Wry>> {
+    let locale = snapshot.locale;
+    let show = MenuItem::with_id(app, MENU_SHOW, tr(locale, Message::TrayShowWindow), true, None::<&str>)?;
+    let sync_now = MenuItem::with_id(app, MENU_SYNC_NOW, tr(locale, Message::TraySyncNow), !snapshot.syncing, None::<&str>)?;
+    let pause = CheckMenuItem::with_id(app, MENU_PAUSE, tr(locale, Message::TrayPauseSync), true, snapshot.paused, None::<&str>)?;
+
+    let notifications = Submenu::with_id(app, MENU_NOTIFICATIONS, tr(locale, Message::TrayRecentNotifications), true)?;
+    if snapshot.notifications.is_empty() {
+        notifications.append(&MenuItem::new(app, tr(locale, Message::TrayNoNotifications), false, None::<&str>)?)?;
+    }
+    for notification in &snapshot.notifications {
+        let mut label: String = notification.title.chars().take(NOTIFICATION_LABEL_CHARS).collect();
+        if !notification.read {
+            label.insert_str(0, "• ");
+        }
+        let id = format!("{}{}", NOTIFICATION_PREFIX, notification.id);
+        notifications.append(&MenuItem::with_id(app, id, label, true, None::<&str>)?)?;
+    }
+
+    let quit = MenuItem::with_id(app, MENU_QUIT, tr(locale, Message::TrayQuit), true, None::<&str>)?;
+    Menu::with_items(
+        app,
+        &[
+            &show,
+            &PredefinedMenuItem::separator(app)?,
+            &sync_now,
+            &pause,
+            &notifications,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )
+}
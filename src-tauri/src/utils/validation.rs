@@ -0,0 +1,231 @@
+/// 命令参数校验
+///
+/// 命令收到的字符串在进入 SQL 或文件路径之前在这里校验。失败时返回 `AppError::InvalidField`
+/// （前端错误码 `VAL_ERROR`，`details.field` 为出错的字段名），前端据此标注对应的输入框。
+use crate::error::AppError;
+use std::path::{Component, Path, PathBuf};
+
+/// 名称类字段（项目名、标签名等）的默认长度上限（字符）
+pub const MAX_NAME_CHARS: usize = 200;
+
+/// 搜索输入的长度上限（字符）
+pub const MAX_QUERY_CHARS: usize = 1000;
+
+/// 邮件地址的长度上限（RFC 5321）
+const MAX_EMAIL_CHARS: usize = 254;
+
+fn invalid(field: &str, message: impl Into<String>) -> AppError {
+    AppError::InvalidField {
+        field: field.to_string(),
+        message: message.into(),
+    }
+}
+
+/// 去除首尾空白后非空且不超过 `max_chars` 个字符，返回去除空白后的值
+pub fn required(field: &str, value: &str, max_chars: usize) -> Result<String, AppError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(invalid(field, format!("{} is required", field)));
+    }
+    max_length(field, value, max_chars)?;
+    Ok(value.to_string())
+}
+
+/// 不超过 `max_chars` 个字符（可以为空）
+pub fn max_length(field: &str, value: &str, max_chars: usize) -> Result<(), AppError> {
+    let length = value.chars().count();
+    if length > max_chars {
+        return Err(invalid(
+            field,
+            format!("{} is too long ({} characters, at most {})", field, length, max_chars),
+        ));
+    }
+    Ok(())
+}
+
+/// 至少 `min_chars` 个字符（不去除空白，用于密码）
+pub fn min_length(field: &str, value: &str, min_chars: usize) -> Result<(), AppError> {
+    if value.chars().count() < min_chars {
+        return Err(invalid(field, format!("{} must be at least {} characters", field, min_chars)));
+    }
+    Ok(())
+}
+
+/// 邮件地址语法（`local@domain`，域名至少包含一个点，不含空白和尖括号），返回去除空白后的地址
+pub fn email_address(field: &str, value: &str) -> Result<String, AppError> {
+    let address = required(field, value, MAX_EMAIL_CHARS)?;
+    let valid = match address.rsplit_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !local.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with(['.', '-'])
+                && !domain.ends_with(['.', '-'])
+                && !domain.contains("..")
+                && domain.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '-')
+                && !local.chars().any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | ',' | ';' | '"'))
+        }
+        None => false,
+    };
+    if !valid {
+        return Err(invalid(field, format!("Invalid email address: {}", address)));
+    }
+    Ok(address)
+}
+
+/// #RGB 或 #RRGGBB 颜色，返回大写形式
+pub fn hex_color(field: &str, value: &str) -> Result<String, AppError> {
+    let hex = value
+        .trim()
+        .strip_prefix('#')
+        .filter(|hex| (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| invalid(field, format!("Invalid color (expected #RRGGBB): {}", value)))?;
+    Ok(format!("#{}", hex.to_ascii_uppercase()))
+}
+
+/// 不小于 `min`
+pub fn at_least(field: &str, value: i64, min: i64) -> Result<(), AppError> {
+    if value < min {
+        return Err(invalid(field, format!("{} must be at least {}", field, min)));
+    }
+    Ok(())
+}
+
+/// 不大于 `max`
+pub fn at_most(field: &str, value: i64, max: i64) -> Result<(), AppError> {
+    if value > max {
+        return Err(invalid(field, format!("{} must be at most {}", field, max)));
+    }
+    Ok(())
+}
+
+/// 路径位于允许的根目录之一内，返回规范化后的绝对路径
+///
+/// 目标文件可以尚不存在（导出目标）：规范化最近的已存在上级目录后再拼接其余部分，
+/// 其余部分中的 `..` 一律拒绝，符号链接无法把路径带出根目录。
+pub fn path_within(field: &str, value: &str, roots: &[PathBuf]) -> Result<PathBuf, AppError> {
+    let path = Path::new(value.trim());
+    if value.trim().is_empty() || !path.is_absolute() {
+        return Err(invalid(field, format!("{} must be an absolute path", field)));
+    }
+
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or_else(|| invalid(field, format!("Invalid path: {}", value)))?;
+    let remainder = path.strip_prefix(existing).unwrap_or(Path::new(""));
+    if remainder.components().any(|component| !matches!(component, Component::Normal(_))) {
+        return Err(invalid(field, format!("Path must not contain '..': {}", value)));
+    }
+    let resolved = existing.canonicalize()?.join(remainder);
+
+    let allowed = roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| resolved.starts_with(root));
+    if !allowed {
+        return Err(invalid(field, format!("Path is outside the allowed folders: {}", value)));
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorResponse;
+
+    fn field_of(error: AppError) -> String {
+        let response = ErrorResponse::from(error);
+        assert_eq!(response.code, "VAL_ERROR");
+        response.details.unwrap()["field"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn required_trims_and_checks_length_in_characters() {
+        assert_eq!(required("name", "  Alpha  ", 10).unwrap(), "Alpha");
+        assert_eq!(field_of(required("name", " \t ", 10).unwrap_err()), "name");
+        // 按字符计数，不按字节
+        assert!(required("name", "项目名称", 4).is_ok());
+        assert_eq!(field_of(required("name", "项目名称五", 4).unwrap_err()), "name");
+        assert!(max_length("query", "", MAX_QUERY_CHARS).is_ok());
+    }
+
+    #[test]
+    fn min_length_does_not_trim() {
+        assert!(min_length("password", "    ", 4).is_ok());
+        assert_eq!(field_of(min_length("password", "abc", 4).unwrap_err()), "password");
+    }
+
+    #[test]
+    fn email_address_syntax() {
+        assert_eq!(email_address("email", " me@example.com ").unwrap(), "me@example.com");
+        assert!(email_address("email", "first.last+tag@mail.example.co.uk").is_ok());
+        assert!(email_address("email", "用户@例子.中国").is_ok());
+        for invalid in [
+            "",
+            "me",
+            "me@localhost",
+            "@example.com",
+            "me@@example.com",
+            "me@.example.com",
+            "me@example.com.",
+            "me@example..com",
+            "me@exa_mple.com",
+            "Me <me@example.com>",
+            "a b@example.com",
+            "a,b@example.com",
+        ] {
+            assert_eq!(field_of(email_address("email", invalid).unwrap_err()), "email", "{:?}", invalid);
+        }
+        let too_long = format!("{}@example.com", "a".repeat(MAX_EMAIL_CHARS));
+        assert!(email_address("email", &too_long).is_err());
+    }
+
+    #[test]
+    fn hex_color_normalizes_case() {
+        assert_eq!(hex_color("color", " #a1b2c3 ").unwrap(), "#A1B2C3");
+        assert_eq!(hex_color("color", "#fff").unwrap(), "#FFF");
+        for invalid in ["a1b2c3", "#abcd", "#ggg", "", "#12345G"] {
+            assert_eq!(field_of(hex_color("color", invalid).unwrap_err()), "color", "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn numeric_bounds_are_inclusive() {
+        assert!(at_least("count", 1, 1).is_ok());
+        assert_eq!(field_of(at_least("count", 0, 1).unwrap_err()), "count");
+        assert!(at_most("count", 10, 10).is_ok());
+        assert_eq!(field_of(at_most("count", 11, 10).unwrap_err()), "count");
+    }
+
+    #[test]
+    fn path_within_allows_new_files_under_a_root_only() {
+        let base = std::env::temp_dir().join(format!("threadline-test-validation-{}", std::process::id()));
+        let root = base.join("exports");
+        let outside = base.join("other");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        let roots = vec![root.clone()];
+
+        // 目标文件和中间目录都可以尚不存在
+        let target = root.join("2026").join("report.zip");
+        let resolved = path_within("path", target.to_str().unwrap(), &roots).unwrap();
+        assert_eq!(resolved, root.canonicalize().unwrap().join("2026").join("report.zip"));
+
+        assert_eq!(field_of(path_within("path", "exports/report.zip", &roots).unwrap_err()), "path");
+        assert!(path_within("path", outside.join("report.zip").to_str().unwrap(), &roots).is_err());
+        // 不存在的部分不能用 ".." 跳出根目录
+        let escape = format!("{}/missing/../../other/report.zip", root.display());
+        assert!(path_within("path", &escape, &roots).is_err());
+
+        #[cfg(unix)]
+        {
+            let link = root.join("link-out");
+            let _ = std::fs::remove_file(&link);
+            std::os::unix::fs::symlink(&outside, &link).unwrap();
+            assert!(path_within("path", link.join("report.zip").to_str().unwrap(), &roots).is_err());
+        }
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
@@ -49,6 +49,7 @@ export interface ProjectData {
   lastActivity?: {
     sender: string;
     date: string;
+    summary?: string; // 仅附件邮件的摘要
   };
   participants?: string[]; // 参与者名单
 }
@@ -221,6 +222,18 @@ export function ProjectCard({
           toast.success("已归档项目");
         }
         onUpdate?.();
+      } else if (action === "delete") {
+        await invoke("delete_project", { id: parseInt(project.id) });
+        toast.success("已移入回收站", {
+          action: {
+            label: "撤销",
+            onClick: async () => {
+              await invoke("restore_project", { id: parseInt(project.id) });
+              onUpdate?.();
+            },
+          },
+        });
+        onUpdate?.();
       } else if (action === "open") {
         onClick?.(project.id);
       } else {
@@ -535,6 +548,9 @@ export function ProjectCard({
                   <span className="font-medium text-foreground/70">
                     {project.lastActivity.sender}
                   </span>
+                  {project.lastActivity.summary && (
+                    <span> · {project.lastActivity.summary}</span>
+                  )}
                 </span>
               </>
             )}
@@ -1,4 +1,5 @@
 import React from "react";
+import { invoke } from "@tauri-apps/api/core";
 import {
   Mail,
   ChevronDown,
@@ -10,6 +11,7 @@ import {
   Image,
   Archive,
   Sheet,
+  Bot,
 } from "lucide-react";
 import { cn } from "@/lib/utils";
 import { Button } from "@/components/ui/button";
@@ -28,7 +30,7 @@ export type Attachment = {
 
 export type TimelineEvent = {
   id: string;
-  type: "milestone" | "email" | "thread";
+  type: "milestone" | "email" | "thread" | "automated";
   date: string;
   title?: string;
   subject?: string;
@@ -37,6 +39,20 @@ export type TimelineEvent = {
   attachments?: Attachment[];
   children?: TimelineEvent[];
   status?: string;
+  // 折叠的自动通知（type: "automated"）
+  thread_id?: string;
+  count?: number;
+  label?: string;
+  senders?: string[];
+  email_ids?: number[];
+};
+
+type ThreadEmail = {
+  id: number;
+  date: string;
+  sender: string;
+  subject: string;
+  is_automated: boolean;
 };
 
 // 获取文件类型图标和颜色
@@ -193,6 +209,60 @@ const EmailItem = ({
   );
 };
 
+// 折叠的自动通知，展开时按需加载
+const AutomatedItem = ({ event }: { event: TimelineEvent }) => {
+  const [isOpen, setIsOpen] = React.useState(false);
+  const [emails, setEmails] = React.useState<ThreadEmail[] | null>(null);
+
+  const handleOpenChange = async (open: boolean) => {
+    setIsOpen(open);
+    if (open && emails === null && event.thread_id) {
+      try {
+        const all = await invoke<ThreadEmail[]>("get_thread_emails", {
+          threadId: event.thread_id,
+          includeAutomated: true,
+        });
+        setEmails(all.filter((email) => event.email_ids?.includes(email.id)));
+      } catch (err) {
+        console.error("Failed to load automated emails:", err);
+        setEmails([]);
+      }
+    }
+  };
+
+  return (
+    <Collapsible open={isOpen} onOpenChange={handleOpenChange} className="space-y-1">
+      <CollapsibleTrigger asChild>
+        <Button
+          variant="ghost"
+          size="sm"
+          className="w-full justify-between h-auto py-1.5 px-3 text-xs text-muted-foreground hover:text-foreground"
+        >
+          <div className="flex items-center gap-2">
+            <Bot className="h-3.5 w-3.5" />
+            <span>{event.label}</span>
+            {event.senders && event.senders.length > 0 && (
+              <span className="truncate opacity-70">· {event.senders.join(", ")}</span>
+            )}
+          </div>
+          {isOpen ? <ChevronDown className="h-3.5 w-3.5" /> : <ChevronRight className="h-3.5 w-3.5" />}
+        </Button>
+      </CollapsibleTrigger>
+
+      <CollapsibleContent className="space-y-1 pl-8">
+        {emails?.map((email) => (
+          <div key={email.id} className="flex justify-between gap-2 text-xs text-muted-foreground">
+            <span className="truncate">
+              {email.sender} — {email.subject}
+            </span>
+            <span className="shrink-0">{new Date(email.date).toLocaleString()}</span>
+          </div>
+        ))}
+      </CollapsibleContent>
+    </Collapsible>
+  );
+};
+
 const ThreadItem = ({ event }: { event: TimelineEvent }) => {
   const [isOpen, setIsOpen] = React.useState(false);
   const emailCount = event.children?.length || 0;
@@ -220,9 +290,13 @@ const ThreadItem = ({ event }: { event: TimelineEvent }) => {
       </CollapsibleTrigger>
 
       <CollapsibleContent className="space-y-1.5 pt-1">
-        {event.children?.map((child) => (
-          <EmailItem key={child.id} event={child} isThreadChild />
-        ))}
+        {event.children?.map((child) =>
+          child.type === "automated" ? (
+            <AutomatedItem key={child.id} event={child} />
+          ) : (
+            <EmailItem key={child.id} event={child} isThreadChild />
+          )
+        )}
       </CollapsibleContent>
     </Collapsible>
   );
@@ -0,0 +1,36 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * 后端压缩后的大响应（命令传入 compress: true 且响应超过阈值时返回）
+ */
+interface CompressedPayload {
+  encoding: "gzip";
+  data: string;
+  original_size: number;
+  compressed_size: number;
+}
+
+function isCompressed(value: unknown): value is CompressedPayload {
+  return (
+    typeof value === "object" &&
+    value !== null &&
+    !Array.isArray(value) &&
+    (value as CompressedPayload).encoding === "gzip" &&
+    typeof (value as CompressedPayload).data === "string"
+  );
+}
+
+async function decompress<T>(payload: CompressedPayload): Promise<T> {
+  const bytes = Uint8Array.from(atob(payload.data), (c) => c.charCodeAt(0));
+  const stream = new Blob([bytes]).stream().pipeThrough(new DecompressionStream("gzip"));
+  const text = await new Response(stream).text();
+  return JSON.parse(text) as T;
+}
+
+/**
+ * 调用支持压缩的命令，自动解压响应
+ */
+export async function invokeCompressed<T>(cmd: string, args: Record<string, unknown> = {}): Promise<T> {
+  const result = await invoke<T | CompressedPayload>(cmd, { ...args, compress: true });
+  return isCompressed(result) ? decompress<T>(result) : (result as T);
+}
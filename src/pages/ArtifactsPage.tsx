@@ -48,6 +48,7 @@ interface BackendProject {
   last_activity?: {
     sender: string;
     date: string;
+    summary?: string; // 仅附件邮件的摘要
   };
   participants?: string[];
 }
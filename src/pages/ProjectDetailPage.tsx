@@ -8,6 +8,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { PageContainer } from "@/components/layout/PageContainer";
 import { ScrollArea } from "@/components/ui/scroll-area";
 import { cn } from "@/lib/utils";
+import { invokeCompressed } from "@/lib/payload";
 
 // CountBadge component matching ProjectsPage style
 function CountBadge({
@@ -34,10 +35,20 @@ function CountBadge({
   );
 }
 
+interface ProjectPreferences {
+  default_tab?: string | null;
+  sort_order?: string | null;
+  collapsed_threads?: boolean;
+  custom?: unknown;
+  // 未识别的字段原样回传给后端
+  [key: string]: unknown;
+}
+
 interface ProjectDetails {
   id: number;
   title: string;
   last_updated: string;
+  preferences?: ProjectPreferences | null;
 }
 
 interface Artifact {
@@ -46,6 +57,7 @@ interface Artifact {
   file_type: string;
   file_size: number;
   created_at: string;
+  is_starred?: boolean;
 }
 
 export function ProjectDetailPage() {
@@ -56,6 +68,7 @@ export function ProjectDetailPage() {
   const [events, setEvents] = useState<TimelineEvent[]>([]);
   const [artifacts, setArtifacts] = useState<Artifact[]>([]);
   const [error, setError] = useState<string | null>(null);
+  const [tab, setTab] = useState("timeline");
 
   useEffect(() => {
     if (!projectId) return;
@@ -70,9 +83,10 @@ export function ProjectDetailPage() {
         // 1. Fetch Project Details
         const proj = await invoke<ProjectDetails>("get_project", { id });
         setProject(proj);
+        setTab(proj.preferences?.default_tab ?? "timeline");
 
         // 2. Fetch Timeline
-        const timeline = await invoke<TimelineEvent[]>("get_project_timeline", {
+        const timeline = await invokeCompressed<TimelineEvent[]>("get_project_timeline", {
           id,
         });
         setEvents(timeline);
@@ -93,6 +107,17 @@ export function ProjectDetailPage() {
     fetchData();
   }, [projectId]);
 
+  // 切换标签页时记住该项目的默认视图
+  const handleTabChange = (value: string) => {
+    setTab(value);
+    if (!project) return;
+    const prefs = { ...(project.preferences ?? {}), default_tab: value };
+    setProject({ ...project, preferences: prefs });
+    invoke("set_project_preferences", { id: project.id, prefs }).catch((err) =>
+      console.error("Failed to save project preferences:", err),
+    );
+  };
+
   if (error) {
     return (
       <PageContainer className="p-6 bg-muted/30">
@@ -166,7 +191,11 @@ export function ProjectDetailPage() {
       </div>
 
       {/* Content */}
-      <Tabs defaultValue="timeline" className="flex-1 flex flex-col min-h-0">
+      <Tabs
+        value={tab}
+        onValueChange={handleTabChange}
+        className="flex-1 flex flex-col min-h-0"
+      >
         <TabsList className="mb-6 w-fit bg-white/70 dark:bg-surface-100/30 backdrop-blur-md border-border/50 shadow-[0_1px_3px_rgba(0,0,0,0.05),inset_0_1px_0_rgba(255,255,255,0.6)] dark:shadow-[0_1px_3px_rgba(0,0,0,0.3),inset_0_1px_0_rgba(255,255,255,0.05)]">
           <TabsTrigger value="timeline" className="flex items-center gap-2">
             <Calendar className="h-4 w-4" />
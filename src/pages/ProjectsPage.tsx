@@ -33,6 +33,7 @@ interface BackendProject {
   last_activity?: {
     sender: string;
     date: string;
+    summary?: string; // 仅附件邮件的摘要
   };
   participants?: string[];
 }
@@ -22,6 +22,7 @@ interface SyncSettings {
   auto_sync_enabled: boolean;
   sync_interval_minutes: number;
   sync_attachments: boolean;
+  version: number;
   created_at: string;
   updated_at: string;
 }
@@ -89,17 +90,27 @@ export const SettingsPage = () => {
 
     try {
       setSaving(true);
-      await invoke("update_sync_settings", {
+      const saved = await invoke<SyncSettings>("update_sync_settings", {
         request: {
           max_sync_count: settings.max_sync_count,
           auto_sync_enabled: settings.auto_sync_enabled,
           sync_interval_minutes: settings.sync_interval_minutes,
           sync_attachments: settings.sync_attachments,
+          expected_version: settings.version,
         },
       });
+      setSettings(saved);
       toast.success("设置已保存");
-      loadSettings(); // 重新加载以获取更新时间
     } catch (err) {
+      const error = err as { code?: string; details?: SyncSettings };
+      if (error?.code === "CONFLICT" && error.details) {
+        // 其他窗口已修改设置，显示最新值让用户重新确认
+        setSettings(error.details);
+        toast.warning("设置已在其他窗口中修改", {
+          description: "已加载最新设置，请确认后重新保存",
+        });
+        return;
+      }
       toast.error("保存设置失败", {
         description: err?.toString?.() ?? "未知错误",
       });
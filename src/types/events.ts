@@ -45,9 +45,25 @@ export interface IndexProgressEvent {
 export type NotificationLevel = "info" | "success" | "warning" | "error";
 
 export interface NotificationEvent {
+  /** 通知表中的 ID（保存失败时为 null） */
+  id: number | null;
   title: string;
   message: string;
   level: NotificationLevel;
+  source: string | null;
+  related_entity: string | null;
+}
+
+/** 通知中心中保存的通知 */
+export interface StoredNotification {
+  id: number;
+  title: string;
+  message: string;
+  level: NotificationLevel;
+  source: string | null;
+  related_entity: string | null;
+  read: boolean;
+  created_at: string;
 }
 
 // ============ 事件名称常量 ============